@@ -302,6 +302,7 @@ const EPSILON: __m256i = unsafe { transmute([GoldilocksField::ORDER.wrapping_neg
 /// Add 2^63 with overflow. Needed to emulate unsigned comparisons (see point 3. in
 /// packed_prime_field.rs).
 #[inline]
+#[target_feature(enable = "avx2")]
 pub unsafe fn shift(x: __m256i) -> __m256i {
     _mm256_xor_si256(x, SIGN_BIT)
 }
@@ -311,6 +312,7 @@ pub unsafe fn shift(x: __m256i) -> __m256i {
 ///   value). The returned value is similarly shifted by 1 << 63 (i.e. we return y_s = y + (1<<63),
 ///   where 0 <= y < FIELD_ORDER).
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn canonicalize_s(x_s: __m256i) -> __m256i {
     // If x >= FIELD_ORDER then corresponding mask bits are all 0; otherwise all 1.
     let mask = _mm256_cmpgt_epi64(SHIFTED_FIELD_ORDER, x_s);
@@ -322,6 +324,7 @@ unsafe fn canonicalize_s(x_s: __m256i) -> __m256i {
 /// Addition u64 + u64 -> u64. Assumes that x + y < 2^64 + FIELD_ORDER. The second argument is
 /// pre-shifted by 1 << 63. The result is similarly shifted.
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn add_no_double_overflow_64_64s_s(x: __m256i, y_s: __m256i) -> __m256i {
     let res_wrapped_s = _mm256_add_epi64(x, y_s);
     let mask = _mm256_cmpgt_epi64(y_s, res_wrapped_s); // -1 if overflowed else 0.
@@ -331,6 +334,7 @@ unsafe fn add_no_double_overflow_64_64s_s(x: __m256i, y_s: __m256i) -> __m256i {
 }
 
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn add(x: __m256i, y: __m256i) -> __m256i {
     let y_s = shift(y);
     let res_s = add_no_double_overflow_64_64s_s(x, canonicalize_s(y_s));
@@ -338,6 +342,7 @@ unsafe fn add(x: __m256i, y: __m256i) -> __m256i {
 }
 
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn sub(x: __m256i, y: __m256i) -> __m256i {
     let mut y_s = shift(y);
     y_s = canonicalize_s(y_s);
@@ -350,6 +355,7 @@ unsafe fn sub(x: __m256i, y: __m256i) -> __m256i {
 }
 
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn neg(y: __m256i) -> __m256i {
     let y_s = shift(y);
     _mm256_sub_epi64(SHIFTED_FIELD_ORDER, canonicalize_s(y_s))
@@ -358,6 +364,7 @@ unsafe fn neg(y: __m256i) -> __m256i {
 /// Full 64-bit by 64-bit multiplication. This emulated multiplication is 1.33x slower than the
 /// scalar instruction, but may be worth it if we want our data to live in vector registers.
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn mul64_64(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
     // We want to move the high 32 bits to the low position. The multiplication instruction ignores
     // the high 32 bits, so it's ok to just duplicate it into the low position. This duplication can
@@ -399,6 +406,7 @@ unsafe fn mul64_64(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
 
 /// Full 64-bit squaring. This routine is 1.2x faster than the scalar instruction.
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn square64(x: __m256i) -> (__m256i, __m256i) {
     // Get high 32 bits of x. See comment in mul64_64_s.
     let x_hi = _mm256_castps_si256(_mm256_movehdup_ps(_mm256_castsi256_ps(x)));
@@ -425,6 +433,7 @@ unsafe fn square64(x: __m256i) -> (__m256i, __m256i) {
 /// Goldilocks addition of a "small" number. `x_s` is pre-shifted by 2**63. `y` is assumed to be <=
 /// `0xffffffff00000000`. The result is shifted by 2**63.
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn add_small_64s_64_s(x_s: __m256i, y: __m256i) -> __m256i {
     let res_wrapped_s = _mm256_add_epi64(x_s, y);
     // 32-bit compare is faster than 64-bit. It's safe as long as x > res_wrapped iff x >> 32 >
@@ -442,6 +451,7 @@ unsafe fn add_small_64s_64_s(x_s: __m256i, y: __m256i) -> __m256i {
 /// Goldilocks subtraction of a "small" number. `x_s` is pre-shifted by 2**63. `y` is assumed to be
 /// <= `0xffffffff00000000`. The result is shifted by 2**63.
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn sub_small_64s_64_s(x_s: __m256i, y: __m256i) -> __m256i {
     let res_wrapped_s = _mm256_sub_epi64(x_s, y);
     // 32-bit compare is faster than 64-bit. It's safe as long as res_wrapped > x iff res_wrapped >>
@@ -457,6 +467,7 @@ unsafe fn sub_small_64s_64_s(x_s: __m256i, y: __m256i) -> __m256i {
 }
 
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn reduce128(x: (__m256i, __m256i)) -> __m256i {
     let (hi0, lo0) = x;
     let lo0_s = shift(lo0);
@@ -470,17 +481,20 @@ unsafe fn reduce128(x: (__m256i, __m256i)) -> __m256i {
 
 /// Multiply two integers modulo FIELD_ORDER.
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn mul(x: __m256i, y: __m256i) -> __m256i {
     reduce128(mul64_64(x, y))
 }
 
 /// Square an integer modulo FIELD_ORDER.
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn square(x: __m256i) -> __m256i {
     reduce128(square64(x))
 }
 
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn interleave1(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
     let a = _mm256_unpacklo_epi64(x, y);
     let b = _mm256_unpackhi_epi64(x, y);
@@ -488,6 +502,7 @@ unsafe fn interleave1(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
 }
 
 #[inline]
+#[target_feature(enable = "avx2")]
 unsafe fn interleave2(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
     let y_lo = _mm256_castsi256_si128(y); // This has 0 cost.
 
@@ -507,12 +522,14 @@ unsafe fn interleave2(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
     (a, b)
 }
 
+// The tests run these instructions directly, so they return early on CPUs which lack them.
 #[cfg(test)]
 mod tests {
     use crate::arch::x86_64::avx2_goldilocks_field::Avx2GoldilocksField;
     use crate::field_types::Field64;
     use crate::goldilocks_field::GoldilocksField;
     use crate::ops::Square;
+    use crate::packable::PackingBackend;
     use crate::packed_field::PackedField;
 
     fn test_vals_a() -> [GoldilocksField; 4] {
@@ -534,6 +551,9 @@ mod tests {
 
     #[test]
     fn test_add() {
+        if PackingBackend::detect() == PackingBackend::Scalar {
+            return;
+        }
         let a_arr = test_vals_a();
         let b_arr = test_vals_b();
 
@@ -550,6 +570,9 @@ mod tests {
 
     #[test]
     fn test_mul() {
+        if PackingBackend::detect() == PackingBackend::Scalar {
+            return;
+        }
         let a_arr = test_vals_a();
         let b_arr = test_vals_b();
 
@@ -566,6 +589,9 @@ mod tests {
 
     #[test]
     fn test_square() {
+        if PackingBackend::detect() == PackingBackend::Scalar {
+            return;
+        }
         let a_arr = test_vals_a();
 
         let packed_a = Avx2GoldilocksField::from_arr(a_arr);
@@ -580,6 +606,9 @@ mod tests {
 
     #[test]
     fn test_neg() {
+        if PackingBackend::detect() == PackingBackend::Scalar {
+            return;
+        }
         let a_arr = test_vals_a();
 
         let packed_a = Avx2GoldilocksField::from_arr(a_arr);
@@ -594,6 +623,9 @@ mod tests {
 
     #[test]
     fn test_sub() {
+        if PackingBackend::detect() == PackingBackend::Scalar {
+            return;
+        }
         let a_arr = test_vals_a();
         let b_arr = test_vals_b();
 
@@ -610,6 +642,9 @@ mod tests {
 
     #[test]
     fn test_interleave_is_involution() {
+        if PackingBackend::detect() == PackingBackend::Scalar {
+            return;
+        }
         let a_arr = test_vals_a();
         let b_arr = test_vals_b();
 
@@ -638,6 +673,9 @@ mod tests {
 
     #[test]
     fn test_interleave() {
+        if PackingBackend::detect() == PackingBackend::Scalar {
+            return;
+        }
         let in_a: [GoldilocksField; 4] = [
             GoldilocksField::from_noncanonical_u64(00),
             GoldilocksField::from_noncanonical_u64(01),
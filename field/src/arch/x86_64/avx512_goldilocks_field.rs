@@ -250,12 +250,14 @@ const FIELD_ORDER: __m512i = unsafe { transmute([GoldilocksField::ORDER; 8]) };
 const EPSILON: __m512i = unsafe { transmute([GoldilocksField::ORDER.wrapping_neg(); 8]) };
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn canonicalize(x: __m512i) -> __m512i {
     let mask = _mm512_cmpge_epu64_mask(x, FIELD_ORDER);
     _mm512_mask_sub_epi64(x, mask, x, FIELD_ORDER)
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn add_no_double_overflow_64_64(x: __m512i, y: __m512i) -> __m512i {
     let res_wrapped = _mm512_add_epi64(x, y);
     let mask = _mm512_cmplt_epu64_mask(res_wrapped, y); // mask set if add overflowed
@@ -264,6 +266,7 @@ unsafe fn add_no_double_overflow_64_64(x: __m512i, y: __m512i) -> __m512i {
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn sub_no_double_overflow_64_64(x: __m512i, y: __m512i) -> __m512i {
     let mask = _mm512_cmplt_epu64_mask(x, y); // mask set if sub will underflow (x < y)
     let res_wrapped = _mm512_sub_epi64(x, y);
@@ -272,16 +275,19 @@ unsafe fn sub_no_double_overflow_64_64(x: __m512i, y: __m512i) -> __m512i {
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn add(x: __m512i, y: __m512i) -> __m512i {
     add_no_double_overflow_64_64(x, canonicalize(y))
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn sub(x: __m512i, y: __m512i) -> __m512i {
     sub_no_double_overflow_64_64(x, canonicalize(y))
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn neg(y: __m512i) -> __m512i {
     _mm512_sub_epi64(FIELD_ORDER, canonicalize(y))
 }
@@ -289,6 +295,7 @@ unsafe fn neg(y: __m512i) -> __m512i {
 const LO_32_BITS_MASK: __mmask16 = unsafe { transmute(0b0101010101010101u16) };
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn mul64_64(x: __m512i, y: __m512i) -> (__m512i, __m512i) {
     // We want to move the high 32 bits to the low position. The multiplication instruction ignores
     // the high 32 bits, so it's ok to just duplicate it into the low position. This duplication can
@@ -329,6 +336,7 @@ unsafe fn mul64_64(x: __m512i, y: __m512i) -> (__m512i, __m512i) {
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn square64(x: __m512i) -> (__m512i, __m512i) {
     // Get high 32 bits of x. See comment in mul64_64_s.
     let x_hi = _mm512_castps_si512(_mm512_movehdup_ps(_mm512_castsi512_ps(x)));
@@ -353,6 +361,7 @@ unsafe fn square64(x: __m512i) -> (__m512i, __m512i) {
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn reduce128(x: (__m512i, __m512i)) -> __m512i {
     let (hi0, lo0) = x;
     let hi_hi0 = _mm512_srli_epi64::<32>(hi0);
@@ -363,16 +372,19 @@ unsafe fn reduce128(x: (__m512i, __m512i)) -> __m512i {
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn mul(x: __m512i, y: __m512i) -> __m512i {
     reduce128(mul64_64(x, y))
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn square(x: __m512i) -> __m512i {
     reduce128(square64(x))
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn interleave1(x: __m512i, y: __m512i) -> (__m512i, __m512i) {
     let a = _mm512_unpacklo_epi64(x, y);
     let b = _mm512_unpackhi_epi64(x, y);
@@ -391,6 +403,7 @@ const INTERLEAVE2_IDX_B: __m512i = unsafe {
 };
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn interleave2(x: __m512i, y: __m512i) -> (__m512i, __m512i) {
     let a = _mm512_permutex2var_epi64(x, INTERLEAVE2_IDX_A, y);
     let b = _mm512_permutex2var_epi64(x, INTERLEAVE2_IDX_B, y);
@@ -398,18 +411,21 @@ unsafe fn interleave2(x: __m512i, y: __m512i) -> (__m512i, __m512i) {
 }
 
 #[inline]
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
 unsafe fn interleave4(x: __m512i, y: __m512i) -> (__m512i, __m512i) {
     let a = _mm512_shuffle_i64x2::<0x44>(x, y);
     let b = _mm512_shuffle_i64x2::<0xee>(x, y);
     (a, b)
 }
 
+// The tests run these instructions directly, so they return early on CPUs which lack them.
 #[cfg(test)]
 mod tests {
     use crate::arch::x86_64::avx512_goldilocks_field::Avx512GoldilocksField;
     use crate::field_types::Field64;
     use crate::goldilocks_field::GoldilocksField;
     use crate::ops::Square;
+    use crate::packable::PackingBackend;
     use crate::packed_field::PackedField;
 
     fn test_vals_a() -> [GoldilocksField; 8] {
@@ -439,6 +455,9 @@ mod tests {
 
     #[test]
     fn test_add() {
+        if PackingBackend::detect() != PackingBackend::Avx512 {
+            return;
+        }
         let a_arr = test_vals_a();
         let b_arr = test_vals_b();
 
@@ -455,6 +474,9 @@ mod tests {
 
     #[test]
     fn test_mul() {
+        if PackingBackend::detect() != PackingBackend::Avx512 {
+            return;
+        }
         let a_arr = test_vals_a();
         let b_arr = test_vals_b();
 
@@ -471,6 +493,9 @@ mod tests {
 
     #[test]
     fn test_square() {
+        if PackingBackend::detect() != PackingBackend::Avx512 {
+            return;
+        }
         let a_arr = test_vals_a();
 
         let packed_a = Avx512GoldilocksField::from_arr(a_arr);
@@ -485,6 +510,9 @@ mod tests {
 
     #[test]
     fn test_neg() {
+        if PackingBackend::detect() != PackingBackend::Avx512 {
+            return;
+        }
         let a_arr = test_vals_a();
 
         let packed_a = Avx512GoldilocksField::from_arr(a_arr);
@@ -499,6 +527,9 @@ mod tests {
 
     #[test]
     fn test_sub() {
+        if PackingBackend::detect() != PackingBackend::Avx512 {
+            return;
+        }
         let a_arr = test_vals_a();
         let b_arr = test_vals_b();
 
@@ -515,6 +546,9 @@ mod tests {
 
     #[test]
     fn test_interleave_is_involution() {
+        if PackingBackend::detect() != PackingBackend::Avx512 {
+            return;
+        }
         let a_arr = test_vals_a();
         let b_arr = test_vals_b();
 
@@ -549,6 +583,9 @@ mod tests {
 
     #[test]
     fn test_interleave() {
+        if PackingBackend::detect() != PackingBackend::Avx512 {
+            return;
+        }
         let in_a: [GoldilocksField; 8] = [
             GoldilocksField::from_noncanonical_u64(00),
             GoldilocksField::from_noncanonical_u64(01),
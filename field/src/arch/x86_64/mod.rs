@@ -1,3 +1,27 @@
+//! Packed Goldilocks fields using x86-64 vector extensions. Both are always compiled, and the one
+//! to use is chosen at runtime by `with_best_goldilocks_packing`, so that a binary built for a
+//! baseline x86-64 target still uses the vector extensions of the CPU it runs on.
+
+use crate::goldilocks_field::GoldilocksField;
+use crate::packable::{PackedKernel, PackingBackend};
+
+pub mod avx2_goldilocks_field;
+pub mod avx512_goldilocks_field;
+
+use avx2_goldilocks_field::Avx2GoldilocksField;
+use avx512_goldilocks_field::Avx512GoldilocksField;
+
+/// The packing of `GoldilocksField` selected at compile time from the enabled target features.
+#[cfg(all(
+    target_feature = "avx512bw",
+    target_feature = "avx512cd",
+    target_feature = "avx512dq",
+    target_feature = "avx512f",
+    target_feature = "avx512vl"
+))]
+pub(crate) type StaticGoldilocksPacking = Avx512GoldilocksField;
+
+/// The packing of `GoldilocksField` selected at compile time from the enabled target features.
 #[cfg(all(
     target_feature = "avx2",
     not(all(
@@ -8,13 +32,38 @@
         target_feature = "avx512vl"
     ))
 ))]
-pub mod avx2_goldilocks_field;
+pub(crate) type StaticGoldilocksPacking = Avx2GoldilocksField;
 
-#[cfg(all(
-    target_feature = "avx512bw",
-    target_feature = "avx512cd",
-    target_feature = "avx512dq",
-    target_feature = "avx512f",
-    target_feature = "avx512vl"
-))]
-pub mod avx512_goldilocks_field;
+/// The packing of `GoldilocksField` selected at compile time from the enabled target features.
+#[cfg(not(target_feature = "avx2"))]
+pub(crate) type StaticGoldilocksPacking = GoldilocksField;
+
+pub(crate) fn with_best_goldilocks_packing<K: PackedKernel<GoldilocksField>>(
+    kernel: K,
+) -> K::Output {
+    match PackingBackend::detect() {
+        // Safety: `detect` has checked that the CPU supports the required extensions.
+        PackingBackend::Avx512 => unsafe { run_avx512(kernel) },
+        PackingBackend::Avx2 => unsafe { run_avx2(kernel) },
+        PackingBackend::Scalar => kernel.run::<GoldilocksField>(),
+    }
+}
+
+/// Runs `kernel`, compiled with AVX2 enabled so that the packed operations it inlines can use it.
+///
+/// # Safety
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+unsafe fn run_avx2<K: PackedKernel<GoldilocksField>>(kernel: K) -> K::Output {
+    kernel.run::<Avx2GoldilocksField>()
+}
+
+/// Runs `kernel`, compiled with AVX-512 enabled so that the packed operations it inlines can use
+/// it.
+///
+/// # Safety
+/// The CPU must support the AVX-512 subsets listed below.
+#[target_feature(enable = "avx512bw,avx512cd,avx512dq,avx512f,avx512vl")]
+unsafe fn run_avx512<K: PackedKernel<GoldilocksField>>(kernel: K) -> K::Output {
+    kernel.run::<Avx512GoldilocksField>()
+}
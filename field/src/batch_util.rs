@@ -1,5 +1,7 @@
+use std::ops::{AddAssign, MulAssign};
+
 use crate::field_types::Field;
-use crate::packable::Packable;
+use crate::packable::{Packable, PackedKernel};
 use crate::packed_field::PackedField;
 
 fn pack_with_leftovers_split_point<P: PackedField>(slice: &[P::Scalar]) -> usize {
@@ -27,39 +29,65 @@ fn pack_slice_with_leftovers_mut<P: PackedField>(
 /// Elementwise inplace multiplication of two slices of field elements.
 /// Implementation be faster than the trivial for loop.
 pub fn batch_multiply_inplace<F: Field>(out: &mut [F], a: &[F]) {
-    let n = out.len();
-    assert_eq!(n, a.len(), "both arrays must have the same length");
-
-    // Split out slice of vectors, leaving leftovers as scalars
-    let (out_packed, out_leftovers) =
-        pack_slice_with_leftovers_mut::<<F as Packable>::Packing>(out);
-    let (a_packed, a_leftovers) = pack_slice_with_leftovers::<<F as Packable>::Packing>(a);
-
-    // Multiply packed and the leftovers
-    for (x_out, x_a) in out_packed.iter_mut().zip(a_packed) {
-        *x_out *= *x_a;
-    }
-    for (x_out, x_a) in out_leftovers.iter_mut().zip(a_leftovers) {
-        *x_out *= *x_a;
-    }
+    F::with_best_packing(BatchKernel {
+        op: BatchOp::Multiply,
+        out,
+        a,
+    });
 }
 
 /// Elementwise inplace addition of two slices of field elements.
 /// Implementation be faster than the trivial for loop.
 pub fn batch_add_inplace<F: Field>(out: &mut [F], a: &[F]) {
-    let n = out.len();
-    assert_eq!(n, a.len(), "both arrays must have the same length");
+    F::with_best_packing(BatchKernel {
+        op: BatchOp::Add,
+        out,
+        a,
+    });
+}
+
+#[derive(Copy, Clone)]
+enum BatchOp {
+    Add,
+    Multiply,
+}
+
+struct BatchKernel<'a, F: Field> {
+    op: BatchOp,
+    out: &'a mut [F],
+    a: &'a [F],
+}
+
+impl<'a, F: Field> PackedKernel<F> for BatchKernel<'a, F> {
+    type Output = ();
 
-    // Split out slice of vectors, leaving leftovers as scalars
-    let (out_packed, out_leftovers) =
-        pack_slice_with_leftovers_mut::<<F as Packable>::Packing>(out);
-    let (a_packed, a_leftovers) = pack_slice_with_leftovers::<<F as Packable>::Packing>(a);
+    #[inline(always)]
+    fn run<P: PackedField<Scalar = F>>(self) {
+        let n = self.out.len();
+        assert_eq!(n, self.a.len(), "both arrays must have the same length");
 
-    // Add packed and the leftovers
-    for (x_out, x_a) in out_packed.iter_mut().zip(a_packed) {
-        *x_out += *x_a;
+        // Split out slice of vectors, leaving leftovers as scalars
+        let (out_packed, out_leftovers) = pack_slice_with_leftovers_mut::<P>(self.out);
+        let (a_packed, a_leftovers) = pack_slice_with_leftovers::<P>(self.a);
+
+        // Combine packed and the leftovers
+        apply_op(self.op, out_packed, a_packed);
+        apply_op(self.op, out_leftovers, a_leftovers);
     }
-    for (x_out, x_a) in out_leftovers.iter_mut().zip(a_leftovers) {
-        *x_out += *x_a;
+}
+
+#[inline(always)]
+fn apply_op<T: Copy + AddAssign + MulAssign>(op: BatchOp, out: &mut [T], a: &[T]) {
+    match op {
+        BatchOp::Add => {
+            for (x_out, &x_a) in out.iter_mut().zip(a) {
+                *x_out += x_a;
+            }
+        }
+        BatchOp::Multiply => {
+            for (x_out, &x_a) in out.iter_mut().zip(a) {
+                *x_out *= x_a;
+            }
+        }
     }
 }
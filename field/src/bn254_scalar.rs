@@ -0,0 +1,45 @@
+use crate::montgomery::{MontgomeryField, MontgomeryParams};
+
+/// The scalar field of the BN254 (alt_bn128) elliptic curve, in Montgomery form.
+///
+/// Its order is
+/// ```ignore
+/// P = 0x30644E72E131A029B85045B68181585D2833E84879B9709143E1F593F0000001
+/// ```
+///
+/// Elements used to be built from their limbs with `Bn254Scalar(limbs)`; use
+/// `Bn254Scalar::from_noncanonical_limbs(limbs)` instead. They serialize as before.
+pub type Bn254Scalar = MontgomeryField<Bn254ScalarParams, 4>;
+
+pub struct Bn254ScalarParams;
+
+impl MontgomeryParams<4> for Bn254ScalarParams {
+    const MODULUS: [u64; 4] = [
+        0x43E1F593F0000001,
+        0x2833E84879B97091,
+        0xB85045B68181585D,
+        0x30644E72E131A029,
+    ];
+
+    const BITS: usize = 254;
+
+    const TWO_ADICITY: usize = 28;
+
+    // Sage: `g = GF(p).multiplicative_generator()`
+    const MULTIPLICATIVE_GROUP_GENERATOR: [u64; 4] = [5, 0, 0, 0];
+
+    // Sage: `g_2 = power_mod(g, (p - 1) // 2^28, p)`
+    const POWER_OF_TWO_GENERATOR: [u64; 4] = [
+        0x9BD61B6E725B19F0,
+        0x402D111E41112ED4,
+        0x00E0A7EB8EF62ABC,
+        0x2A3C09F0A58A7E85,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_field_arithmetic;
+
+    test_field_arithmetic!(crate::bn254_scalar::Bn254Scalar);
+}
@@ -0,0 +1,217 @@
+//! Constant-time field operations, for generating witnesses which contain secrets.
+//!
+//! The ordinary field arithmetic branches on rare carries and borrows, inversion uses a
+//! variable-time extended GCD, and exponentiation skips the multiplications for zero bits of the
+//! exponent, all of which can leak operands through timing. The operations here perform the same
+//! sequence of instructions whatever their operands. They are several times slower, so plonky2's
+//! witness generators only use them when its `constant-time` feature is enabled. That feature also
+//! enables this crate's `constant-time-inversion` feature, which makes `GoldilocksField::try_inverse`
+//! use `ct_inverse_or_zero`, so code outside the witness generators inverts in constant time too.
+
+use std::ops::{BitAnd, BitOr, Not};
+
+use num::BigUint;
+
+use crate::extension_field::{Extendable, FieldExtension};
+use crate::field_types::Field;
+
+/// A secret condition, held as a mask which is all ones if it holds and all zeros otherwise, so that
+/// acting on it needs no branch. Masks go through an optimization barrier when they are created, so
+/// the compiler can't tell which of the two values a mask holds, and can't reintroduce a branch.
+#[derive(Copy, Clone, Debug)]
+pub struct CtMask(u64);
+
+impl CtMask {
+    pub fn from_bool(condition: bool) -> Self {
+        Self::from_bit(condition as u64)
+    }
+
+    /// The mask of the lowest bit of `bit`.
+    pub fn from_bit(bit: u64) -> Self {
+        Self(barrier((bit & 1).wrapping_neg()))
+    }
+
+    /// Whether `a == b`.
+    pub fn eq_u64(a: u64, b: u64) -> Self {
+        let d = a ^ b;
+        // The top bit of `d | -d` is set if and only if `d` is nonzero.
+        Self::from_bit(((d | d.wrapping_neg()) >> 63) ^ 1)
+    }
+
+    /// Whether `a < b`, as unsigned integers.
+    pub fn lt_u64(a: u64, b: u64) -> Self {
+        // The top bit of this is the borrow of `a - b`; see Hacker's Delight, section 2-12.
+        Self::from_bit(((!a & b) | ((!a | b) & a.wrapping_sub(b))) >> 63)
+    }
+
+    pub fn mask(self) -> u64 {
+        self.0
+    }
+
+    /// Reveals the condition, for when it isn't secret, or its value is about to be leaked anyway.
+    pub fn declassify(self) -> bool {
+        barrier(self.0) != 0
+    }
+}
+
+impl Not for CtMask {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl BitAnd for CtMask {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for CtMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Returns `x`, through a volatile read, which the compiler can't see through.
+#[inline(always)]
+fn barrier(x: u64) -> u64 {
+    // Safety: `x` is a valid and aligned local.
+    unsafe { std::ptr::read_volatile(&x) }
+}
+
+/// A field with constant-time implementations of the operations needed to generate witnesses.
+pub trait ConstantTimeField: Field {
+    /// Returns `a` if `condition` holds, and `b` otherwise.
+    fn ct_select(condition: CtMask, a: Self, b: Self) -> Self;
+
+    fn ct_is_zero(self) -> CtMask;
+
+    fn ct_add(self, rhs: Self) -> Self;
+
+    fn ct_mul(self, rhs: Self) -> Self;
+
+    /// Returns the inverse of `self`, or zero if `self` is zero.
+    fn ct_inverse_or_zero(self) -> Self;
+
+    /// Computes `self^power` with a squaring and a multiplication for each of the 64 bits of
+    /// `power`, so that neither the base nor the exponent is leaked.
+    fn ct_exp_u64(self, power: u64) -> Self {
+        ct_exp_bits(self, (0..64).rev().map(|i| CtMask::from_bit(power >> i)))
+    }
+}
+
+/// Computes `x^e`, where `bits` are the bits of `e`, most significant first.
+fn ct_exp_bits<F: ConstantTimeField>(x: F, bits: impl Iterator<Item = CtMask>) -> F {
+    let mut acc = F::ONE;
+    for bit in bits {
+        acc = acc.ct_mul(acc);
+        acc = F::ct_select(bit, acc.ct_mul(x), acc);
+    }
+    acc
+}
+
+/// Computes `num / den` in the extension field, or zero if `den` is zero. The inverse of `den` is
+/// computed as `den^(|F|^D - 2)`.
+pub fn ct_div_extension<F: ConstantTimeField + Extendable<D>, const D: usize>(
+    num: F::Extension,
+    den: F::Extension,
+) -> F::Extension {
+    let den = den.to_basefield_array();
+    let exponent: BigUint = F::order().pow(D as u32) - 2u32;
+    let mut den_inv = [F::ZERO; D];
+    den_inv[0] = F::ONE;
+    for i in (0..exponent.bits()).rev() {
+        den_inv = ct_mul_extension::<F, D>(den_inv, den_inv);
+        let product = ct_mul_extension::<F, D>(den_inv, den);
+        let bit = CtMask::from_bool(exponent.bit(i));
+        for (inv, p) in den_inv.iter_mut().zip(product) {
+            *inv = F::ct_select(bit, p, *inv);
+        }
+    }
+    F::Extension::from_basefield_array(ct_mul_extension::<F, D>(num.to_basefield_array(), den_inv))
+}
+
+/// Multiplies elements of `F[X]/(X^D - W)`, given by their coefficients.
+fn ct_mul_extension<F: ConstantTimeField + Extendable<D>, const D: usize>(
+    a: [F; D],
+    b: [F; D],
+) -> [F; D] {
+    let mut c = [F::ZERO; D];
+    for i in 0..D {
+        for j in 0..D {
+            let product = a[i].ct_mul(b[j]);
+            if i + j < D {
+                c[i + j] = c[i + j].ct_add(product);
+            } else {
+                c[i + j - D] = c[i + j - D].ct_add(F::W.ct_mul(product));
+            }
+        }
+    }
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constant_time::{ct_div_extension, ConstantTimeField, CtMask};
+    use crate::extension_field::quadratic::QuadraticExtension;
+    use crate::field_types::{Field, Field64};
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::inversion::try_inverse_u64;
+
+    type F = GoldilocksField;
+    type FF = QuadraticExtension<F>;
+
+    #[test]
+    fn test_matches_variable_time() {
+        for _ in 0..100 {
+            let (x, y) = (F::rand(), F::rand());
+            let power = rand::random::<u64>();
+            assert_eq!(x.ct_add(y), x + y);
+            assert_eq!(x.ct_mul(y), x * y);
+            assert_eq!(x.ct_exp_u64(power), x.exp_u64(power));
+            // `inverse` itself goes through `ct_inverse_or_zero` under `constant-time-inversion`, so
+            // compare against the binary GCD directly.
+            assert_eq!(Some(x.ct_inverse_or_zero()), try_inverse_u64(&x));
+            assert_eq!(x * x.ct_inverse_or_zero(), F::ONE);
+            assert_eq!(F::ct_select(CtMask::from_bool(true), x, y), x);
+            assert_eq!(F::ct_select(CtMask::from_bool(false), x, y), y);
+
+            let (a, b) = (FF::rand(), FF::rand());
+            assert_eq!(ct_div_extension::<F, 2>(a, b) * b, a);
+        }
+
+        assert!(F::ZERO.ct_is_zero().declassify());
+        assert!(F::from_noncanonical_u64(F::ORDER).ct_is_zero().declassify());
+        assert!(!F::ONE.ct_is_zero().declassify());
+        assert_eq!(F::ZERO.ct_inverse_or_zero(), F::ZERO);
+        assert_eq!(ct_div_extension::<F, 2>(FF::ONE, FF::ZERO), FF::ZERO);
+        // Operands near the order exercise the carries and borrows.
+        let x = F::from_noncanonical_u64(u64::MAX);
+        assert_eq!(x.ct_add(x), x + x);
+        assert_eq!(x.ct_mul(x), x * x);
+    }
+
+    #[test]
+    fn test_masks() {
+        let values = [0, 1, 2, 1 << 63, u64::MAX - 1, u64::MAX, rand::random()];
+        for a in values {
+            for b in values {
+                assert_eq!(CtMask::eq_u64(a, b).declassify(), a == b);
+                assert_eq!(CtMask::lt_u64(a, b).declassify(), a < b);
+            }
+        }
+        for condition in [false, true] {
+            let mask = CtMask::from_bool(condition);
+            assert_eq!(mask.mask(), if condition { u64::MAX } else { 0 });
+            assert_eq!((!mask).declassify(), !condition);
+            assert_eq!((mask & CtMask::from_bool(true)).declassify(), condition);
+            assert_eq!((mask | CtMask::from_bool(false)).declassify(), condition);
+        }
+    }
+}
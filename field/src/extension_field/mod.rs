@@ -19,11 +19,16 @@ pub trait OEF<const D: usize>: FieldExtension<D> {
     // should set this to W^((p - 1)/D), where W is as above and p is
     // the order of the BaseField.
     const DTH_ROOT: Self::BaseField;
+
+    /// The powers `DTH_ROOT^i` for `i < D`, which are the coefficients of the Frobenius
+    /// automorphisms, since `x^(p^k) = sum_i a_i DTH_ROOT^(k i) X^i` for `x = sum_i a_i X^i`.
+    const DTH_ROOT_POWERS: [Self::BaseField; D];
 }
 
 impl<F: Field> OEF<1> for F {
     const W: Self::BaseField = F::ZERO;
     const DTH_ROOT: Self::BaseField = F::ZERO;
+    const DTH_ROOT_POWERS: [Self::BaseField; 1] = [F::ONE];
 }
 
 pub trait Frobenius<const D: usize>: OEF<D> {
@@ -44,20 +49,15 @@ pub trait Frobenius<const D: usize>: OEF<D> {
             // x^(p^(count % D))
             return self.repeated_frobenius(count % D);
         }
-        let arr = self.to_basefield_array();
-
-        // z0 = DTH_ROOT^count = W^(k * count) where k = floor((p^D-1)/D)
-        let mut z0 = Self::DTH_ROOT;
-        for _ in 1..count {
-            z0 *= Self::DTH_ROOT;
-        }
+        let mut arr = self.to_basefield_array();
 
-        let mut res = [Self::BaseField::ZERO; D];
-        for (i, z) in z0.powers().take(D).enumerate() {
-            res[i] = arr[i] * z;
+        // The coefficient of `X^i` is multiplied by `DTH_ROOT^(count * i)`, where
+        // `DTH_ROOT^D = 1`.
+        for (i, a) in arr.iter_mut().enumerate().skip(1) {
+            *a *= Self::DTH_ROOT_POWERS[count * i % D];
         }
 
-        Self::from_basefield_array(res)
+        Self::from_basefield_array(arr)
     }
 }
 
@@ -68,6 +68,9 @@ pub trait Extendable<const D: usize>: Field + Sized {
 
     const DTH_ROOT: Self;
 
+    /// The powers `DTH_ROOT^i` for `i < D`.
+    const DTH_ROOT_POWERS: [Self; D];
+
     /// Chosen so that when raised to the power `(p^D - 1) >> F::Extension::TWO_ADICITY)`
     /// we obtain F::EXT_POWER_OF_TWO_GENERATOR.
     const EXT_MULTIPLICATIVE_GROUP_GENERATOR: [Self; D];
@@ -82,6 +85,7 @@ impl<F: Field + Frobenius<1> + FieldExtension<1, BaseField = F>> Extendable<1> f
     type Extension = F;
     const W: Self = F::ZERO;
     const DTH_ROOT: Self = F::ZERO;
+    const DTH_ROOT_POWERS: [Self; 1] = [F::ONE];
     const EXT_MULTIPLICATIVE_GROUP_GENERATOR: [Self; 1] = [F::MULTIPLICATIVE_GROUP_GENERATOR];
     const EXT_POWER_OF_TWO_GENERATOR: [Self; 1] = [F::POWER_OF_TWO_GENERATOR];
 }
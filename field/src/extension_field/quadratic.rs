@@ -24,6 +24,7 @@ impl<F: Extendable<2>> Default for QuadraticExtension<F> {
 impl<F: Extendable<2>> OEF<2> for QuadraticExtension<F> {
     const W: F = F::W;
     const DTH_ROOT: F = F::DTH_ROOT;
+    const DTH_ROOT_POWERS: [F; 2] = F::DTH_ROOT_POWERS;
 }
 
 impl<F: Extendable<2>> Frobenius<2> for QuadraticExtension<F> {}
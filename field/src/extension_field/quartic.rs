@@ -25,6 +25,7 @@ impl<F: Extendable<4>> Default for QuarticExtension<F> {
 impl<F: Extendable<4>> OEF<4> for QuarticExtension<F> {
     const W: F = F::W;
     const DTH_ROOT: F = F::DTH_ROOT;
+    const DTH_ROOT_POWERS: [F; 4] = F::DTH_ROOT_POWERS;
 }
 
 impl<F: Extendable<4>> Frobenius<4> for QuarticExtension<F> {}
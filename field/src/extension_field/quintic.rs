@@ -24,6 +24,7 @@ impl<F: Extendable<5>> Default for QuinticExtension<F> {
 impl<F: Extendable<5>> OEF<5> for QuinticExtension<F> {
     const W: F = F::W;
     const DTH_ROOT: F = F::DTH_ROOT;
+    const DTH_ROOT_POWERS: [F; 5] = F::DTH_ROOT_POWERS;
 }
 
 impl<F: Extendable<5>> Frobenius<5> for QuinticExtension<F> {}
@@ -5,7 +5,7 @@ use plonky2_util::{log2_strict, reverse_index_bits_in_place};
 use unroll::unroll_for_loops;
 
 use crate::field_types::Field;
-use crate::packable::Packable;
+use crate::packable::{Packable, PackedKernel};
 use crate::packed_field::PackedField;
 use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
 
@@ -32,6 +32,114 @@ pub fn fft_root_table<F: Field>(n: usize) -> FftRootTable<F> {
     root_table
 }
 
+/// Tables for repeated FFTs of one size onto one coset `shift * H`: the roots of unity used by
+/// each layer, and the powers of the shift. Building them costs about as much as a transform, so
+/// callers performing many transforms of the same size, such as the LDEs of a batch of polynomials,
+/// should build them once and reuse them.
+#[derive(Clone, Debug)]
+pub struct FftTables<F: Field> {
+    lg_n: usize,
+    root_table: FftRootTable<F>,
+    shift: F,
+    /// `shift^i` for `i < n`, or empty if the shift is one.
+    shift_powers: Vec<F>,
+}
+
+impl<F: Field> FftTables<F> {
+    /// Tables for FFTs of size `2^lg_n` on the subgroup itself.
+    pub fn new(lg_n: usize) -> Self {
+        Self::coset(lg_n, F::ONE)
+    }
+
+    /// Tables for FFTs of size `2^lg_n` onto the coset `shift * H`.
+    pub fn coset(lg_n: usize, shift: F) -> Self {
+        Self::coset_with_root_table(lg_n, shift, None)
+    }
+
+    /// Like `coset`, but takes the rows of `root_table` if it is given and large enough, rather
+    /// than recomputing them.
+    pub fn coset_with_root_table(
+        lg_n: usize,
+        shift: F,
+        root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        let root_table = match root_table {
+            Some(table) if table.len() >= lg_n => table[..lg_n].to_vec(),
+            _ => fft_root_table(1 << lg_n),
+        };
+        let shift_powers = if shift == F::ONE {
+            Vec::new()
+        } else {
+            shift.powers().take(1 << lg_n).collect()
+        };
+        Self {
+            lg_n,
+            root_table,
+            shift,
+            shift_powers,
+        }
+    }
+
+    pub fn lg_n(&self) -> usize {
+        self.lg_n
+    }
+
+    pub fn shift(&self) -> F {
+        self.shift
+    }
+
+    pub fn root_table(&self) -> &FftRootTable<F> {
+        &self.root_table
+    }
+
+    /// Whether these tables are for FFTs of size `2^lg_n` onto the coset `shift * H`.
+    pub fn is_for(&self, lg_n: usize, shift: F) -> bool {
+        self.lg_n == lg_n && self.shift == shift
+    }
+
+    /// Evaluates `poly` on the coset, reusing its buffer for the result. `zero_factor` has the same
+    /// meaning as in `fft_with_options`.
+    pub fn coset_fft(
+        &self,
+        poly: PolynomialCoeffs<F>,
+        zero_factor: Option<usize>,
+    ) -> PolynomialValues<F> {
+        let PolynomialCoeffs { coeffs: mut buffer } = poly;
+        assert_eq!(buffer.len(), 1 << self.lg_n, "Wrong FFT size");
+        let r = zero_factor.unwrap_or(0);
+        // Only the first `n / 2^r` coefficients may be nonzero.
+        let num_nonzero = buffer.len() >> r;
+        for (c, &p) in buffer[..num_nonzero].iter_mut().zip(&self.shift_powers) {
+            *c *= p;
+        }
+        fft_classic(&mut buffer, r, &self.root_table);
+        PolynomialValues { values: buffer }
+    }
+
+    /// Interpolates `values` on the subgroup `H`, ignoring the shift.
+    pub fn ifft(&self, values: PolynomialValues<F>) -> PolynomialCoeffs<F> {
+        assert_eq!(values.len(), 1 << self.lg_n, "Wrong FFT size");
+        ifft_with_options(values, None, Some(&self.root_table))
+    }
+}
+
+/// Evaluates `poly` on the coset `shift * H` using `tables` if they match the transform, and
+/// otherwise building new ones. The tables are returned so that the next transform of the same
+/// size can reuse them.
+pub fn coset_fft_with_tables<F: Field>(
+    poly: PolynomialCoeffs<F>,
+    shift: F,
+    zero_factor: Option<usize>,
+    tables: Option<FftTables<F>>,
+) -> (PolynomialValues<F>, FftTables<F>) {
+    let lg_n = log2_strict(poly.len());
+    let tables = match tables {
+        Some(tables) if tables.is_for(lg_n, shift) => tables,
+        _ => FftTables::coset(lg_n, shift),
+    };
+    (tables.coset_fft(poly, zero_factor), tables)
+}
+
 #[inline]
 fn fft_dispatch<F: Field>(
     input: &mut [F],
@@ -96,11 +204,12 @@ pub fn ifft_with_options<F: Field>(
 
 /// Generic FFT implementation that works with both scalar and packed inputs.
 #[unroll_for_loops]
+#[inline(always)]
 fn fft_classic_simd<P: PackedField>(
     values: &mut [P::Scalar],
     r: usize,
     lg_n: usize,
-    root_table: &FftRootTable<P::Scalar>,
+    root_table: &[Vec<P::Scalar>],
 ) {
     let lg_packed_width = log2_strict(P::WIDTH); // 0 when P is a scalar.
     let packed_values = P::pack_slice_mut(values);
@@ -172,13 +281,16 @@ pub(crate) fn fft_classic<F: Field>(values: &mut [F], r: usize, root_table: &Fft
     let n = values.len();
     let lg_n = log2_strict(n);
 
-    if root_table.len() != lg_n {
+    // Row `i` of a root table holds powers of a root of unity of order `2^(i + 1)`, independently
+    // of the table's size, so a table for a larger FFT also works for this one.
+    if root_table.len() < lg_n {
         panic!(
-            "Expected root table of length {}, but it was {}.",
+            "Expected root table of length at least {}, but it was {}.",
             lg_n,
             root_table.len()
         );
     }
+    let root_table = &root_table[..lg_n];
 
     // After reverse_index_bits, the only non-zero elements of values
     // are at indices i*2^r for i = 0..n/2^r.  The loop below copies
@@ -195,13 +307,40 @@ pub(crate) fn fft_classic<F: Field>(values: &mut [F], r: usize, root_table: &Fft
         }
     }
 
-    let lg_packed_width = log2_strict(<F as Packable>::Packing::WIDTH);
-    if lg_n <= lg_packed_width {
-        // Need the slice to be at least the width of two packed vectors for the vectorized version
-        // to work. Do this tiny problem in scalar.
-        fft_classic_simd::<F>(values, r, lg_n, root_table);
-    } else {
-        fft_classic_simd::<<F as Packable>::Packing>(values, r, lg_n, root_table);
+    F::with_best_packing(FftKernel {
+        values,
+        r,
+        lg_n,
+        root_table,
+    });
+}
+
+/// The layers of `fft_classic` after the zero-factor shortcut, run with the best packing.
+struct FftKernel<'a, F: Field> {
+    values: &'a mut [F],
+    r: usize,
+    lg_n: usize,
+    root_table: &'a [Vec<F>],
+}
+
+impl<'a, F: Field> PackedKernel<F> for FftKernel<'a, F> {
+    type Output = ();
+
+    #[inline(always)]
+    fn run<P: PackedField<Scalar = F>>(self) {
+        let Self {
+            values,
+            r,
+            lg_n,
+            root_table,
+        } = self;
+        if lg_n <= log2_strict(P::WIDTH) {
+            // Need the slice to be at least the width of two packed vectors for the vectorized
+            // version to work. Do this tiny problem in scalar.
+            fft_classic_simd::<F>(values, r, lg_n, root_table);
+        } else {
+            fft_classic_simd::<P>(values, r, lg_n, root_table);
+        }
     }
 }
 
@@ -209,7 +348,7 @@ pub(crate) fn fft_classic<F: Field>(values: &mut [F], r: usize, root_table: &Fft
 mod tests {
     use plonky2_util::{log2_ceil, log2_strict};
 
-    use crate::fft::{fft, fft_with_options, ifft};
+    use crate::fft::{coset_fft_with_tables, fft, fft_with_options, ifft, FftTables};
     use crate::field_types::Field;
     use crate::goldilocks_field::GoldilocksField;
     use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
@@ -250,6 +389,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn coset_fft_with_precomputed_tables() {
+        type F = GoldilocksField;
+        let shift = F::rand();
+        let tables = FftTables::coset(5, shift);
+
+        for r in 0..3 {
+            let padded = PolynomialCoeffs::new(F::rand_vec(32 >> r)).lde(r);
+            assert_eq!(
+                tables.coset_fft(padded.clone(), Some(r)),
+                padded.coset_fft(shift)
+            );
+        }
+        let values = PolynomialValues::new(F::rand_vec(32));
+        assert_eq!(tables.ifft(values.clone()), ifft(values));
+
+        // Tables of the right size and shift are reused, and others are replaced.
+        let poly = PolynomialCoeffs::new(F::rand_vec(8));
+        let (_, tables) = coset_fft_with_tables(poly.lde(2), shift, Some(2), Some(tables));
+        assert!(tables.is_for(5, shift));
+        let (values, tables) = coset_fft_with_tables(poly.clone(), F::ONE, None, Some(tables));
+        assert!(tables.is_for(3, F::ONE));
+        assert_eq!(values, fft(poly));
+    }
+
     fn evaluate_naive<F: Field>(coefficients: &PolynomialCoeffs<F>) -> PolynomialValues<F> {
         let degree = coefficients.len();
         let degree_padded = 1 << log2_ceil(degree);
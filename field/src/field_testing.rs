@@ -86,6 +86,21 @@ macro_rules! test_field_arithmetic {
                 assert_ne!(base.exp_biguint(&pow), base.exp_biguint(&big_pow_wrong));
             }
 
+            #[test]
+            fn exponentiation_windowed() {
+                type F = $field;
+
+                let x = F::rand();
+                for _ in 0..20 {
+                    let power = rand::random::<u64>() >> rand::random::<u8>() % 48;
+                    let expected = (0..64)
+                        .filter(|&j| power >> j & 1 == 1)
+                        .map(|j| x.exp_power_of_2(j))
+                        .product::<F>();
+                    assert_eq!(x.exp_u64(power), expected);
+                }
+            }
+
             #[test]
             fn inverses() {
                 type F = $field;
@@ -13,6 +13,13 @@ use serde::Serialize;
 use crate::extension_field::Frobenius;
 use crate::ops::Square;
 
+/// `Field::exp_u64` uses square-and-multiply for exponents of at most this many bits, and a
+/// sliding window for longer ones, for which its precomputation pays off.
+const EXP_WINDOW_MIN_BITS: usize = 16;
+
+/// The maximum width of a window in `Field::exp_u64`.
+const EXP_WINDOW_BITS: usize = 4;
+
 /// A finite field.
 pub trait Field:
     'static
@@ -317,14 +324,49 @@ pub trait Field:
     }
 
     fn exp_u64(&self, power: u64) -> Self {
-        let mut current = *self;
-        let mut product = Self::ONE;
+        let num_bits = bits_u64(power);
+        if num_bits <= EXP_WINDOW_MIN_BITS {
+            let mut current = *self;
+            let mut product = Self::ONE;
+
+            for j in 0..num_bits {
+                if (power >> j & 1) != 0 {
+                    product *= current;
+                }
+                current = current.square();
+            }
+            return product;
+        }
 
-        for j in 0..bits_u64(power) {
-            if (power >> j & 1) != 0 {
-                product *= current;
+        // Sliding-window exponentiation, which multiplies once per window of up to
+        // `EXP_WINDOW_BITS` bits rather than once per set bit, using precomputed odd powers.
+        let square = self.square();
+        let mut odd_powers = [*self; 1 << (EXP_WINDOW_BITS - 1)];
+        for i in 1..odd_powers.len() {
+            odd_powers[i] = odd_powers[i - 1] * square;
+        }
+
+        let mut product = Self::ONE;
+        let mut i = num_bits;
+        while i > 0 {
+            let top = i - 1;
+            if power >> top & 1 == 0 {
+                product = product.square();
+                i -= 1;
+                continue;
+            }
+            // The window ends at the lowest set bit among the `EXP_WINDOW_BITS` bits from `top`.
+            let mut bottom = top.saturating_sub(EXP_WINDOW_BITS - 1);
+            while power >> bottom & 1 == 0 {
+                bottom += 1;
+            }
+            let width = top - bottom + 1;
+            for _ in 0..width {
+                product = product.square();
             }
-            current = current.square();
+            let window = (power >> bottom) & ((1 << width) - 1);
+            product *= odd_powers[(window >> 1) as usize];
+            i = bottom;
         }
         product
     }
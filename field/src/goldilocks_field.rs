@@ -5,10 +5,12 @@ use std::iter::{Product, Sum};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use num::{BigUint, Integer};
+use plonky2_util::wide_arith::{split_u128, widening_mul};
 use plonky2_util::{assume, branch_hint};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+use crate::constant_time::{ConstantTimeField, CtMask};
 use crate::extension_field::quadratic::QuadraticExtension;
 use crate::extension_field::quartic::QuarticExtension;
 use crate::extension_field::quintic::QuinticExtension;
@@ -92,7 +94,16 @@ impl Field for GoldilocksField {
 
     #[inline(always)]
     fn try_inverse(&self) -> Option<Self> {
-        try_inverse_u64(self)
+        if cfg!(feature = "constant-time-inversion") {
+            // Only whether `self` is zero is leaked.
+            if self.ct_is_zero().declassify() {
+                None
+            } else {
+                Some(self.ct_inverse_or_zero())
+            }
+        } else {
+            try_inverse_u64(self)
+        }
     }
 
     fn from_biguint(n: BigUint) -> Self {
@@ -293,6 +304,8 @@ impl Extendable<2> for GoldilocksField {
     // DTH_ROOT = W^((ORDER - 1)/2)
     const DTH_ROOT: Self = Self(18446744069414584320);
 
+    const DTH_ROOT_POWERS: [Self; 2] = [Self(1), Self(18446744069414584320)];
+
     const EXT_MULTIPLICATIVE_GROUP_GENERATOR: [Self; 2] =
         [Self(18081566051660590251), Self(16121475356294670766)];
 
@@ -307,6 +320,13 @@ impl Extendable<4> for GoldilocksField {
     // DTH_ROOT = W^((ORDER - 1)/4)
     const DTH_ROOT: Self = Self(281474976710656);
 
+    const DTH_ROOT_POWERS: [Self; 4] = [
+        Self(1),
+        Self(281474976710656),
+        Self(18446744069414584320),
+        Self(18446462594437873665),
+    ];
+
     const EXT_MULTIPLICATIVE_GROUP_GENERATOR: [Self; 4] = [
         Self(5024755240244648895),
         Self(13227474371289740625),
@@ -326,6 +346,14 @@ impl Extendable<5> for GoldilocksField {
     // DTH_ROOT = W^((ORDER - 1)/5)
     const DTH_ROOT: Self = Self(1041288259238279555);
 
+    const DTH_ROOT_POWERS: [Self; 5] = [
+        Self(1),
+        Self(1041288259238279555),
+        Self(15820824984080659046),
+        Self(211587555138949697),
+        Self(1373043270956696022),
+    ];
+
     const EXT_MULTIPLICATIVE_GROUP_GENERATOR: [Self; 5] = [
         Self(2899034827742553394),
         Self(13012057356839176729),
@@ -384,11 +412,15 @@ unsafe fn add_no_canonicalize_trashing_input(x: u64, y: u64) -> u64 {
     res_wrapped + EPSILON * (carry as u64)
 }
 
-/// Reduces to a 64-bit value. The result might not be in canonical form; it could be in between the
-/// field order and `2^64`.
+/// Reduces a 128-bit value, such as the product of two field elements, modulo the field order. The
+/// result might not be in canonical form; it could be in between the field order and `2^64`.
+///
+/// This is the reduction used by multiplication, and is part of the crate's stable API so that
+/// other code working with 128-bit intermediates, such as custom generators or packed fields, can
+/// reuse it.
 #[inline]
-fn reduce128(x: u128) -> GoldilocksField {
-    let (x_lo, x_hi) = split(x); // This is a no-op
+pub fn reduce128(x: u128) -> GoldilocksField {
+    let (x_lo, x_hi) = split_u128(x); // This is a no-op
     let x_hi_hi = x_hi >> 32;
     let x_hi_lo = x_hi & EPSILON;
 
@@ -402,17 +434,86 @@ fn reduce128(x: u128) -> GoldilocksField {
     GoldilocksField(t2)
 }
 
-#[inline]
-fn split(x: u128) -> (u64, u64) {
-    (x as u64, (x >> 64) as u64)
+impl Frobenius<1> for GoldilocksField {}
+
+impl ConstantTimeField for GoldilocksField {
+    fn ct_select(condition: CtMask, a: Self, b: Self) -> Self {
+        let mask = condition.mask();
+        Self((a.0 & mask) | (b.0 & !mask))
+    }
+
+    fn ct_is_zero(self) -> CtMask {
+        // Zero has one non-canonical form, `ORDER`.
+        CtMask::eq_u64(self.0, 0) | CtMask::eq_u64(self.0, Self::ORDER)
+    }
+
+    fn ct_add(self, rhs: Self) -> Self {
+        // As in `add`, but always applying the adjustment for a double overflow.
+        let (sum, over) = self.0.overflowing_add(rhs.0);
+        let (sum, over) = sum.overflowing_add(ct_mask(over) & EPSILON);
+        Self(sum.wrapping_add(ct_mask(over) & EPSILON))
+    }
+
+    fn ct_mul(self, rhs: Self) -> Self {
+        // As in `reduce128`, but always applying the adjustment for a borrow.
+        let (x_lo, x_hi) = widening_mul(self.0, rhs.0);
+        let x_hi_hi = x_hi >> 32;
+        let x_hi_lo = x_hi & EPSILON;
+
+        let (t0, borrow) = x_lo.overflowing_sub(x_hi_hi);
+        let t0 = t0.wrapping_sub(ct_mask(borrow) & EPSILON); // Cannot underflow.
+        let t1 = x_hi_lo * EPSILON;
+        let (t2, carry) = t0.overflowing_add(t1);
+        Self(t2.wrapping_add(ct_mask(carry) & EPSILON))
+    }
+
+    fn ct_inverse_or_zero(self) -> Self {
+        // Computes `self^(p - 2)` with an addition chain of 64 squarings and 9 multiplications,
+        // using `p - 2 = (2^31 - 1) 2^33 + (2^32 - 1)`. Writing `t_k = self^(2^k - 1)`, we have
+        // `t_{j + k} = t_j^(2^k) t_k`.
+        let exp_acc = |x: Self, squarings: usize, y: Self| {
+            let mut x = x;
+            for _ in 0..squarings {
+                x = x.ct_mul(x);
+            }
+            x.ct_mul(y)
+        };
+        let t1 = self;
+        let t2 = exp_acc(t1, 1, t1);
+        let t3 = exp_acc(t2, 1, t1);
+        let t6 = exp_acc(t3, 3, t3);
+        let t12 = exp_acc(t6, 6, t6);
+        let t24 = exp_acc(t12, 12, t12);
+        let t30 = exp_acc(t24, 6, t6);
+        let t31 = exp_acc(t30, 1, t1);
+        let t32 = exp_acc(t31, 1, t1);
+        exp_acc(t31, 33, t32)
+    }
 }
 
-impl Frobenius<1> for GoldilocksField {}
+/// All ones if `condition` holds, and all zeros otherwise.
+#[inline]
+fn ct_mask(condition: bool) -> u64 {
+    CtMask::from_bool(condition).mask()
+}
 
 #[cfg(test)]
 mod tests {
+    use crate::field_types::{Field, Field64, PrimeField64};
+    use crate::goldilocks_field::{reduce128, GoldilocksField};
     use crate::{test_field_arithmetic, test_prime_field_arithmetic};
 
     test_prime_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
     test_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
+
+    #[test]
+    fn test_reduce128() {
+        type F = GoldilocksField;
+        for x in [0, u128::MAX, (F::ORDER as u128) << 64, rand::random()] {
+            let expected = (x % F::ORDER as u128) as u64;
+            assert_eq!(reduce128(x).to_canonical_u64(), expected);
+        }
+        let (a, b) = (F::rand(), F::rand());
+        assert_eq!(reduce128(a.0 as u128 * b.0 as u128), a * b);
+    }
 }
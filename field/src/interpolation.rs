@@ -9,19 +9,8 @@ use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
 /// Note that the implementation assumes that `F` is two-adic, in particular that
 /// `2^{F::TWO_ADICITY} >= points.len()`. This leads to a simple FFT-based implementation.
 pub fn interpolant<F: Field>(points: &[(F, F)]) -> PolynomialCoeffs<F> {
-    let n = points.len();
-    let n_log = log2_ceil(n);
-
-    let subgroup = F::two_adic_subgroup(n_log);
-    let barycentric_weights = barycentric_weights(points);
-    let subgroup_evals = subgroup
-        .into_iter()
-        .map(|x| interpolate(points, x, &barycentric_weights))
-        .collect();
-
-    let mut coeffs = ifft(PolynomialValues {
-        values: subgroup_evals,
-    });
+    let (xs, ys): (Vec<F>, Vec<F>) = points.iter().copied().unzip();
+    let mut coeffs = BarycentricDomain::new(xs).interpolant(&ys);
     coeffs.trim();
     coeffs
 }
@@ -64,6 +53,152 @@ pub fn barycentric_weights<F: Field>(points: &[(F, F)]) -> Vec<F> {
     )
 }
 
+/// The barycentric weights of the coset `shift * H`, where `H` is the subgroup of order `2^log_n`
+/// generated by `g`, listed in the order of the points `shift * g^i`.
+///
+/// The vanishing polynomial of the coset is `Z(X) = X^n - shift^n`, so the weight of `x_i` is
+/// `1 / Z'(x_i) = x_i / (n * shift^n)`, which only takes a single inversion.
+pub fn coset_barycentric_weights<F: Field>(log_n: usize, shift: F) -> Vec<F> {
+    let n = 1 << log_n;
+    let g = F::primitive_root_of_unity(log_n);
+    let scale = (F::from_canonical_usize(n) * shift.exp_power_of_2(log_n)).inverse();
+    g.powers().take(n).map(|x| x * shift * scale).collect()
+}
+
+/// A set of interpolation points along with their barycentric weights, so that polynomials can be
+/// evaluated from their values on the points without recomputing Lagrange denominators.
+#[derive(Clone, Debug)]
+pub struct BarycentricDomain<F: Field> {
+    points: Vec<F>,
+    weights: Vec<F>,
+    /// For a coset `shift * H` of a subgroup of order `2^log_n`, `(log_n, shift)`.
+    coset: Option<(usize, F)>,
+}
+
+impl<F: Field> BarycentricDomain<F> {
+    /// A domain of arbitrary distinct points. Computing its weights takes a quadratic number of
+    /// multiplications, so prefer `subgroup` or `coset` where possible.
+    pub fn new(points: Vec<F>) -> Self {
+        let pairs = points.iter().map(|&x| (x, F::ZERO)).collect::<Vec<_>>();
+        let weights = barycentric_weights(&pairs);
+        Self {
+            points,
+            weights,
+            coset: None,
+        }
+    }
+
+    /// The subgroup of order `2^log_n`, in the order of the powers of its generator.
+    pub fn subgroup(log_n: usize) -> Self {
+        Self::coset(log_n, F::ONE)
+    }
+
+    /// The coset `shift * H` of the subgroup of order `2^log_n`, in the order of the points
+    /// `shift * g^i`.
+    pub fn coset(log_n: usize, shift: F) -> Self {
+        let g = F::primitive_root_of_unity(log_n);
+        Self {
+            points: g.powers().take(1 << log_n).map(|x| x * shift).collect(),
+            weights: coset_barycentric_weights(log_n, shift),
+            coset: Some((log_n, shift)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn points(&self) -> &[F] {
+        &self.points
+    }
+
+    pub fn weights(&self) -> &[F] {
+        &self.weights
+    }
+
+    /// The vanishing polynomial of this domain, evaluated at `x`.
+    fn eval_vanishing(&self, x: F) -> F {
+        match self.coset {
+            Some((log_n, shift)) => x.exp_power_of_2(log_n) - shift.exp_power_of_2(log_n),
+            None => self.points.iter().map(|&x_i| x - x_i).product(),
+        }
+    }
+
+    /// The Lagrange basis polynomials `L_i` of this domain evaluated at `x`, where `L_i` is one on
+    /// the `i`th point and zero on the others.
+    pub fn lagrange_basis(&self, x: F) -> Vec<F> {
+        let indices = (0..self.len()).collect::<Vec<_>>();
+        self.lagrange_basis_at(&indices, x)
+    }
+
+    /// Like `lagrange_basis`, but only the basis polynomials with the given indices. For a subgroup
+    /// or coset, this takes time linear in the number of indices rather than in the domain size.
+    pub fn lagrange_basis_at(&self, indices: &[usize], x: F) -> Vec<F> {
+        let differences = indices
+            .iter()
+            .map(|&i| x - self.points[i])
+            .collect::<Vec<_>>();
+        // If x is one of these points, the barycentric formula would divide by zero.
+        if let Some(k) = differences.iter().position(|d| d.is_zero()) {
+            let mut basis = vec![F::ZERO; indices.len()];
+            basis[k] = F::ONE;
+            return basis;
+        }
+
+        let vanishing = self.eval_vanishing(x);
+        F::batch_multiplicative_inverse(&differences)
+            .into_iter()
+            .zip(indices)
+            .map(|(d_inv, &i)| vanishing * self.weights[i] * d_inv)
+            .collect()
+    }
+
+    /// Evaluates, at `x`, the polynomial of degree less than `self.len()` with the given values on
+    /// the points of this domain.
+    pub fn evaluate(&self, values: &[F], x: F) -> F {
+        assert_eq!(values.len(), self.len(), "Wrong number of values");
+        self.lagrange_basis(x)
+            .into_iter()
+            .zip(values)
+            .map(|(l, &y)| l * y)
+            .sum()
+    }
+
+    /// Like `evaluate`, but for a polynomial which is zero on every point of the domain except
+    /// those listed as `(index, value)` pairs, without building its full list of values.
+    pub fn evaluate_sparse(&self, entries: &[(usize, F)], x: F) -> F {
+        let indices = entries.iter().map(|&(i, _)| i).collect::<Vec<_>>();
+        self.lagrange_basis_at(&indices, x)
+            .into_iter()
+            .zip(entries)
+            .map(|(l, &(_, y))| l * y)
+            .sum()
+    }
+
+    /// The coefficients of the polynomial of degree less than `self.len()` with the given values on
+    /// the points of this domain. Unlike `interpolant`, these aren't trimmed.
+    pub fn interpolant(&self, values: &[F]) -> PolynomialCoeffs<F> {
+        assert_eq!(values.len(), self.len(), "Wrong number of values");
+        match self.coset {
+            Some((_, shift)) => PolynomialValues::new(values.to_vec()).coset_ifft(shift),
+            None => {
+                let n_log = log2_ceil(self.len());
+                let subgroup_evals = F::two_adic_subgroup(n_log)
+                    .into_iter()
+                    .map(|x| self.evaluate(values, x))
+                    .collect();
+                let mut coeffs = ifft(PolynomialValues::new(subgroup_evals));
+                coeffs.coeffs.truncate(self.len());
+                coeffs
+            }
+        }
+    }
+}
+
 /// Interpolate the linear polynomial passing through `points` on `x`.
 pub fn interpolate2<F: Field>(points: [(F, F); 2], x: F) -> F {
     // a0 -> a1
@@ -131,6 +266,86 @@ mod tests {
         domain.iter().map(|&x| (x, coeffs.eval(x))).collect()
     }
 
+    #[test]
+    fn barycentric_coset() {
+        type F = GoldilocksField;
+
+        for log_n in 0..5 {
+            let shift = F::rand();
+            let domain = BarycentricDomain::coset(log_n, shift);
+            let pairs = domain
+                .points()
+                .iter()
+                .map(|&x| (x, F::ZERO))
+                .collect::<Vec<_>>();
+            assert_eq!(domain.weights(), barycentric_weights(&pairs));
+
+            let coeffs = PolynomialCoeffs::new(F::rand_vec(1 << log_n));
+            let values = coeffs.coset_fft(shift).values;
+            assert_eq!(domain.interpolant(&values), coeffs);
+            let x = F::rand();
+            assert_eq!(domain.evaluate(&values, x), coeffs.eval(x));
+            assert_eq!(domain.evaluate(&values, domain.points()[0]), values[0]);
+        }
+    }
+
+    #[test]
+    fn barycentric_arbitrary_points() {
+        type F = QuarticExtension<GoldilocksField>;
+
+        let domain = BarycentricDomain::new(F::rand_vec(7));
+        let coeffs = PolynomialCoeffs::new(F::rand_vec(7));
+        let values = domain
+            .points()
+            .iter()
+            .map(|&x| coeffs.eval(x))
+            .collect::<Vec<_>>();
+        assert_eq!(domain.interpolant(&values), coeffs);
+        let x = F::rand();
+        assert_eq!(domain.evaluate(&values, x), coeffs.eval(x));
+    }
+
+    #[test]
+    fn lagrange_basis() {
+        type F = GoldilocksField;
+
+        let domain = BarycentricDomain::coset(3, F::rand());
+        for (i, &x_i) in domain.points().iter().enumerate() {
+            let basis = domain.lagrange_basis(x_i);
+            for (j, &l_j) in basis.iter().enumerate() {
+                assert_eq!(l_j, if i == j { F::ONE } else { F::ZERO });
+            }
+        }
+        let x = F::rand();
+        assert_eq!(domain.lagrange_basis(x).into_iter().sum::<F>(), F::ONE);
+        assert_eq!(
+            domain.lagrange_basis_at(&[5, 2], x),
+            vec![domain.lagrange_basis(x)[5], domain.lagrange_basis(x)[2]]
+        );
+    }
+
+    #[test]
+    fn barycentric_sparse() {
+        type F = GoldilocksField;
+
+        let log_n = 10;
+        let domain = BarycentricDomain::coset(log_n, F::rand());
+        let entries = [(3, F::rand()), (500, F::rand()), (1000, F::rand())];
+        let mut values = vec![F::ZERO; 1 << log_n];
+        for &(i, y) in &entries {
+            values[i] = y;
+        }
+        let x = F::rand();
+        assert_eq!(
+            domain.evaluate_sparse(&entries, x),
+            domain.evaluate(&values, x)
+        );
+        let x = domain.points()[500];
+        assert_eq!(domain.evaluate_sparse(&entries, x), entries[1].1);
+        let x = domain.points()[7];
+        assert_eq!(domain.evaluate_sparse(&entries, x), F::ZERO);
+    }
+
     #[test]
     fn test_interpolate2() {
         type F = QuarticExtension<GoldilocksField>;
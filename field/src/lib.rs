@@ -5,12 +5,18 @@
 #![allow(clippy::len_without_is_empty)]
 #![allow(clippy::needless_range_loop)]
 #![allow(clippy::return_self_not_must_use)]
+#![feature(avx512_target_feature)]
 #![feature(generic_const_exprs)]
 #![feature(specialization)]
 #![feature(stdsimd)]
 
 pub(crate) mod arch;
 pub mod batch_util;
+pub mod bls12_381_base;
+pub mod bls12_381_scalar;
+pub mod bn254_base;
+pub mod bn254_scalar;
+pub mod constant_time;
 pub mod cosets;
 pub mod extension_field;
 pub mod fft;
@@ -18,6 +24,7 @@ pub mod field_types;
 pub mod goldilocks_field;
 pub mod interpolation;
 mod inversion;
+pub mod montgomery;
 pub mod ops;
 pub mod packable;
 pub mod packed_field;
@@ -0,0 +1,491 @@
+//! Prime fields of `N` 64-bit limbs whose elements are stored in Montgomery form, i.e. `x` is
+//! stored as `x R mod p` with `R = 2^(64 N)`. Multiplication then reduces with a few limb
+//! multiplications rather than a big integer division, which makes it practical to add fields
+//! without Goldilocks' special structure: a field only has to provide its `MontgomeryParams`.
+
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter::{Product, Sum};
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use num::bigint::{BigUint, RandBigInt};
+use num::Integer;
+use plonky2_util::wide_arith::{add_with_carry, mul_add_carry, sub_with_borrow};
+use rand::Rng;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::field_types::{Field, PrimeField};
+
+/// The parameters of a prime field of `N` limbs in Montgomery form. Values are little-endian limbs
+/// in canonical form; the Montgomery constants are derived from them at compile time.
+pub trait MontgomeryParams<const N: usize>: 'static {
+    /// The field order `p`, which must be odd and less than `2^(64 N - 1)`.
+    const MODULUS: [u64; N];
+
+    /// The bit length of `p`.
+    const BITS: usize;
+
+    /// The 2-adicity of `p - 1`.
+    const TWO_ADICITY: usize;
+
+    /// A generator of the multiplicative group.
+    const MULTIPLICATIVE_GROUP_GENERATOR: [u64; N];
+
+    /// A generator of the multiplicative subgroup of order `2^TWO_ADICITY`.
+    const POWER_OF_TWO_GENERATOR: [u64; N];
+}
+
+/// An element of the prime field described by `P`, in Montgomery form.
+pub struct MontgomeryField<P: MontgomeryParams<N>, const N: usize> {
+    /// `x R mod p`, in canonical form.
+    limbs: [u64; N],
+    _phantom: PhantomData<fn() -> P>,
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> MontgomeryField<P, N> {
+    /// `-p^{-1} mod 2^64`.
+    const INV: u64 = neg_inverse_mod_2_64(P::MODULUS[0]);
+
+    /// `R^2 mod p`, which converts to Montgomery form.
+    const R2: [u64; N] = r_squared(&P::MODULUS);
+
+    const fn from_montgomery_limbs(limbs: [u64; N]) -> Self {
+        Self {
+            limbs,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The element with the given canonical limbs, which must be less than `p`.
+    pub const fn from_canonical_limbs(limbs: [u64; N]) -> Self {
+        Self::from_noncanonical_limbs(limbs)
+    }
+
+    /// The element congruent to the given limbs, which may be any value up to `R`.
+    pub const fn from_noncanonical_limbs(limbs: [u64; N]) -> Self {
+        // Since `R^2 mod p < p`, the product is less than `p R`, so the result is reduced.
+        Self::from_montgomery_limbs(mont_mul(&limbs, &Self::R2, &P::MODULUS, Self::INV))
+    }
+
+    /// The canonical limbs of this element.
+    pub const fn to_canonical_limbs(&self) -> [u64; N] {
+        mont_mul(&self.limbs, &small_limbs(1), &P::MODULUS, Self::INV)
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Clone for MontgomeryField<P, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Copy for MontgomeryField<P, N> {}
+
+impl<P: MontgomeryParams<N>, const N: usize> Default for MontgomeryField<P, N> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+// The Montgomery form is kept canonical, so limbs can be compared directly.
+impl<P: MontgomeryParams<N>, const N: usize> PartialEq for MontgomeryField<P, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.limbs == other.limbs
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Eq for MontgomeryField<P, N> {}
+
+impl<P: MontgomeryParams<N>, const N: usize> Hash for MontgomeryField<P, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.limbs.hash(state)
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Display for MontgomeryField<P, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.to_canonical_biguint(), f)
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Debug for MontgomeryField<P, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.to_canonical_biguint(), f)
+    }
+}
+
+/// Elements are serialized as a tuple of their canonical limbs, independently of the
+/// representation, which is how fields stored as plain limbs serialize them.
+impl<P: MontgomeryParams<N>, const N: usize> Serialize for MontgomeryField<P, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for limb in self.to_canonical_limbs() {
+            tuple.serialize_element(&limb)?;
+        }
+        tuple.end()
+    }
+}
+
+/// Like fields stored as plain limbs, this accepts limbs which aren't reduced.
+impl<'de, P: MontgomeryParams<N>, const N: usize> Deserialize<'de> for MontgomeryField<P, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LimbsVisitor<const N: usize>;
+
+        impl<'de, const N: usize> Visitor<'de> for LimbsVisitor<N> {
+            type Value = [u64; N];
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "a tuple of {} limbs", N)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut limbs = [0; N];
+                for (i, limb) in limbs.iter_mut().enumerate() {
+                    *limb = seq
+                        .next_element()?
+                        .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+                }
+                Ok(limbs)
+            }
+        }
+
+        let limbs = deserializer.deserialize_tuple(N, LimbsVisitor)?;
+        Ok(Self::from_noncanonical_limbs(limbs))
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Field for MontgomeryField<P, N> {
+    const ZERO: Self = Self::from_montgomery_limbs([0; N]);
+    const ONE: Self = Self::from_canonical_limbs(small_limbs(1));
+    const TWO: Self = Self::from_canonical_limbs(small_limbs(2));
+    const NEG_ONE: Self =
+        Self::from_montgomery_limbs(sub_mod(&P::MODULUS, &Self::ONE.limbs, &P::MODULUS));
+
+    const TWO_ADICITY: usize = P::TWO_ADICITY;
+    const CHARACTERISTIC_TWO_ADICITY: usize = Self::TWO_ADICITY;
+
+    const MULTIPLICATIVE_GROUP_GENERATOR: Self =
+        Self::from_canonical_limbs(P::MULTIPLICATIVE_GROUP_GENERATOR);
+    const POWER_OF_TWO_GENERATOR: Self = Self::from_canonical_limbs(P::POWER_OF_TWO_GENERATOR);
+
+    const BITS: usize = P::BITS;
+
+    fn order() -> BigUint {
+        biguint_from_limbs(&P::MODULUS)
+    }
+    fn characteristic() -> BigUint {
+        Self::order()
+    }
+
+    fn try_inverse(&self) -> Option<Self> {
+        if self.is_zero() {
+            return None;
+        }
+
+        // Fermat's Little Theorem
+        Some(self.exp_biguint(&(Self::order() - 2u32)))
+    }
+
+    fn from_biguint(val: BigUint) -> Self {
+        let mut limbs = [0; N];
+        for (limb, digit) in limbs
+            .iter_mut()
+            .zip(val.mod_floor(&Self::order()).iter_u64_digits())
+        {
+            *limb = digit;
+        }
+        Self::from_canonical_limbs(limbs)
+    }
+
+    #[inline]
+    fn from_canonical_u64(n: u64) -> Self {
+        Self::from_canonical_limbs(small_limbs(n))
+    }
+
+    fn from_noncanonical_u128(n: u128) -> Self {
+        Self::from_biguint(BigUint::from(n))
+    }
+
+    fn rand_from_rng<R: Rng>(rng: &mut R) -> Self {
+        Self::from_biguint(rng.gen_biguint_below(&Self::order()))
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> PrimeField for MontgomeryField<P, N> {
+    fn to_canonical_biguint(&self) -> BigUint {
+        biguint_from_limbs(&self.to_canonical_limbs())
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Neg for MontgomeryField<P, N> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self::ZERO - self
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Add for MontgomeryField<P, N> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self::from_montgomery_limbs(add_mod(&self.limbs, &rhs.limbs, &P::MODULUS))
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> AddAssign for MontgomeryField<P, N> {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Sum for MontgomeryField<P, N> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Sub for MontgomeryField<P, N> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self::from_montgomery_limbs(sub_mod(&self.limbs, &rhs.limbs, &P::MODULUS))
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> SubAssign for MontgomeryField<P, N> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Mul for MontgomeryField<P, N> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_montgomery_limbs(mont_mul(&self.limbs, &rhs.limbs, &P::MODULUS, Self::INV))
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> MulAssign for MontgomeryField<P, N> {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Product for MontgomeryField<P, N> {
+    #[inline]
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, x| acc * x).unwrap_or(Self::ONE)
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> Div for MontgomeryField<P, N> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self::Output {
+        self * rhs.inverse()
+    }
+}
+
+impl<P: MontgomeryParams<N>, const N: usize> DivAssign for MontgomeryField<P, N> {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+fn biguint_from_limbs(limbs: &[u64]) -> BigUint {
+    BigUint::new(
+        limbs
+            .iter()
+            .flat_map(|&limb| [limb as u32, (limb >> 32) as u32])
+            .collect(),
+    )
+}
+
+const fn small_limbs<const N: usize>(n: u64) -> [u64; N] {
+    let mut limbs = [0; N];
+    limbs[0] = n;
+    limbs
+}
+
+/// Whether `a >= b`.
+const fn geq<const N: usize>(a: &[u64; N], b: &[u64; N]) -> bool {
+    let mut i = N;
+    while i > 0 {
+        i -= 1;
+        if a[i] != b[i] {
+            return a[i] > b[i];
+        }
+    }
+    true
+}
+
+const fn add_limbs<const N: usize>(a: &[u64; N], b: &[u64; N]) -> ([u64; N], bool) {
+    let mut sum = [0; N];
+    let mut carry = false;
+    let mut i = 0;
+    while i < N {
+        (sum[i], carry) = add_with_carry(a[i], b[i], carry);
+        i += 1;
+    }
+    (sum, carry)
+}
+
+const fn sub_limbs<const N: usize>(a: &[u64; N], b: &[u64; N]) -> ([u64; N], bool) {
+    let mut diff = [0; N];
+    let mut borrow = false;
+    let mut i = 0;
+    while i < N {
+        (diff[i], borrow) = sub_with_borrow(a[i], b[i], borrow);
+        i += 1;
+    }
+    (diff, borrow)
+}
+
+/// `a + b mod p`, for `a, b < p`.
+const fn add_mod<const N: usize>(a: &[u64; N], b: &[u64; N], p: &[u64; N]) -> [u64; N] {
+    let (sum, carry) = add_limbs(a, b);
+    if carry || geq(&sum, p) {
+        sub_limbs(&sum, p).0
+    } else {
+        sum
+    }
+}
+
+/// `a - b mod p`, for `a, b < p`.
+const fn sub_mod<const N: usize>(a: &[u64; N], b: &[u64; N], p: &[u64; N]) -> [u64; N] {
+    let (diff, borrow) = sub_limbs(a, b);
+    if borrow {
+        add_limbs(&diff, p).0
+    } else {
+        diff
+    }
+}
+
+/// The Montgomery product `a b R^{-1} mod p` of `a, b < R` with `a b < p R`, computed with the
+/// coarsely integrated operand scanning method.
+const fn mont_mul<const N: usize>(a: &[u64; N], b: &[u64; N], p: &[u64; N], inv: u64) -> [u64; N] {
+    // The accumulator has N + 1 limbs, the last being `t_hi`.
+    let mut t = [0; N];
+    let mut t_hi = 0;
+    let mut i = 0;
+    while i < N {
+        // t += a * b[i]
+        let mut carry = 0;
+        let mut j = 0;
+        while j < N {
+            (t[j], carry) = mul_add_carry(t[j], a[j], b[i], carry);
+            j += 1;
+        }
+        let (sum, overflow) = add_with_carry(t_hi, carry, false);
+        t_hi = sum;
+
+        // t = (t + m p) / 2^64, where m is chosen to make the division exact.
+        let m = t[0].wrapping_mul(inv);
+        let (_, mut carry) = mul_add_carry(t[0], m, p[0], 0);
+        let mut j = 1;
+        while j < N {
+            (t[j - 1], carry) = mul_add_carry(t[j], m, p[j], carry);
+            j += 1;
+        }
+        let (sum, overflow2) = add_with_carry(t_hi, carry, false);
+        t[N - 1] = sum;
+        t_hi = overflow as u64 + overflow2 as u64;
+        i += 1;
+    }
+
+    // Now t < 2p.
+    if t_hi != 0 || geq(&t, p) {
+        sub_limbs(&t, p).0
+    } else {
+        t
+    }
+}
+
+/// `-x^{-1} mod 2^64` for odd `x`, by Newton iteration; each step doubles the number of correct
+/// low bits, starting from the three given by `x` itself.
+const fn neg_inverse_mod_2_64(x: u64) -> u64 {
+    let mut inv = x;
+    let mut i = 0;
+    while i < 5 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(x.wrapping_mul(inv)));
+        i += 1;
+    }
+    inv.wrapping_neg()
+}
+
+/// `R^2 mod p`, by doubling one `128 N` times.
+const fn r_squared<const N: usize>(p: &[u64; N]) -> [u64; N] {
+    let mut r = small_limbs(1);
+    let mut i = 0;
+    while i < 128 * N {
+        r = add_mod(&r, &r, p);
+        i += 1;
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigUint;
+    use serde::de::value::{Error, SeqDeserializer};
+    use serde::Deserialize;
+
+    use crate::bn254_scalar::{Bn254Scalar, Bn254ScalarParams};
+    use crate::field_types::{Field, PrimeField};
+    use crate::montgomery::{neg_inverse_mod_2_64, MontgomeryParams};
+
+    #[test]
+    fn test_neg_inverse_mod_2_64() {
+        for x in [1, 3, 0xF0000001, u64::MAX, rand::random::<u64>() | 1] {
+            assert_eq!(x.wrapping_mul(neg_inverse_mod_2_64(x)), u64::MAX);
+        }
+    }
+
+    #[test]
+    fn test_montgomery_against_biguint() {
+        type F = Bn254Scalar;
+        let order = F::order();
+        for _ in 0..10 {
+            let (x, y) = (F::rand(), F::rand());
+            let (x_big, y_big) = (x.to_canonical_biguint(), y.to_canonical_biguint());
+            assert_eq!((x * y).to_canonical_biguint(), &x_big * &y_big % &order);
+            assert_eq!((x + y).to_canonical_biguint(), (&x_big + &y_big) % &order);
+            assert_eq!(
+                (x - y).to_canonical_biguint(),
+                (&x_big + &order - &y_big) % &order
+            );
+            assert_eq!(F::from_biguint(x_big), x);
+        }
+        assert_eq!(F::NEG_ONE.to_canonical_biguint(), &order - 1u32);
+    }
+
+    #[test]
+    fn test_noncanonical_limbs() -> Result<(), Error> {
+        type F = Bn254Scalar;
+        let mut limbs = Bn254ScalarParams::MODULUS;
+        limbs[0] += 1;
+        assert_eq!(F::from_noncanonical_limbs(limbs), F::ONE);
+        assert_eq!(
+            F::from_noncanonical_limbs([u64::MAX; 4]).to_canonical_biguint(),
+            ((BigUint::from(1u32) << 256) - 1u32) % F::order()
+        );
+
+        let x = F::deserialize(SeqDeserializer::<_, Error>::new(limbs.into_iter()))?;
+        assert_eq!(x, F::ONE);
+        Ok(())
+    }
+}
@@ -3,38 +3,106 @@ use crate::packed_field::PackedField;
 
 /// Points us to the default packing for a particular field. There may me multiple choices of
 /// PackedField for a particular Field (e.g. every Field is also a PackedField), but this is the
-/// recommended one. The recommended packing varies by target_arch and target_feature.
+/// recommended one.
 pub trait Packable: Field {
+    /// The packing selected at compile time from the enabled target features. Code which can be
+    /// written as a `PackedKernel` should use `with_best_packing` instead, which also uses vector
+    /// extensions that weren't enabled at compile time but are supported by the running CPU.
     type Packing: PackedField<Scalar = Self>;
+
+    /// Runs `kernel` with the best packing of this field supported by the running CPU.
+    fn with_best_packing<K: PackedKernel<Self>>(kernel: K) -> K::Output;
 }
 
 impl<F: Field> Packable for F {
     default type Packing = Self;
+
+    default fn with_best_packing<K: PackedKernel<Self>>(kernel: K) -> K::Output {
+        kernel.run::<Self>()
+    }
 }
 
-#[cfg(all(
-    target_arch = "x86_64",
-    target_feature = "avx2",
-    not(all(
-        target_feature = "avx512bw",
-        target_feature = "avx512cd",
-        target_feature = "avx512dq",
-        target_feature = "avx512f",
-        target_feature = "avx512vl"
-    ))
-))]
+#[cfg(target_arch = "x86_64")]
 impl Packable for crate::goldilocks_field::GoldilocksField {
-    type Packing = crate::arch::x86_64::avx2_goldilocks_field::Avx2GoldilocksField;
+    type Packing = crate::arch::x86_64::StaticGoldilocksPacking;
+
+    fn with_best_packing<K: PackedKernel<Self>>(kernel: K) -> K::Output {
+        crate::arch::x86_64::with_best_goldilocks_packing(kernel)
+    }
 }
 
-#[cfg(all(
-    target_arch = "x86_64",
-    target_feature = "avx512bw",
-    target_feature = "avx512cd",
-    target_feature = "avx512dq",
-    target_feature = "avx512f",
-    target_feature = "avx512vl"
-))]
-impl Packable for crate::goldilocks_field::GoldilocksField {
-    type Packing = crate::arch::x86_64::avx512_goldilocks_field::Avx512GoldilocksField;
+/// A computation which is generic over the packing of `F` it uses, so that the packing can be
+/// chosen at runtime by `Packable::with_best_packing`.
+///
+/// Implementations should mark `run` as `#[inline(always)]`. It is then compiled along with the
+/// target features of the packing it is run with, which lets the packed operations it calls be
+/// inlined.
+pub trait PackedKernel<F: Field> {
+    type Output;
+
+    fn run<P: PackedField<Scalar = F>>(self) -> Self::Output;
+}
+
+/// The instruction sets which packed fields are implemented with.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PackingBackend {
+    Scalar,
+    Avx2,
+    Avx512,
+}
+
+impl PackingBackend {
+    /// The widest backend supported by the running CPU. There are no packed fields for other
+    /// architectures yet, so they always use `Scalar`.
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx512bw")
+                && is_x86_feature_detected!("avx512cd")
+                && is_x86_feature_detected!("avx512dq")
+                && is_x86_feature_detected!("avx512f")
+                && is_x86_feature_detected!("avx512vl")
+            {
+                return Self::Avx512;
+            }
+            if is_x86_feature_detected!("avx2") {
+                return Self::Avx2;
+            }
+        }
+        Self::Scalar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field_types::Field;
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::packable::{Packable, PackedKernel};
+    use crate::packed_field::PackedField;
+
+    /// Sums the elementwise products of two slices whose length is a multiple of 16.
+    struct DotProduct<'a, F: Field>(&'a [F], &'a [F]);
+
+    impl<'a, F: Field> PackedKernel<F> for DotProduct<'a, F> {
+        type Output = F;
+
+        #[inline(always)]
+        fn run<P: PackedField<Scalar = F>>(self) -> F {
+            let sum = P::pack_slice(self.0)
+                .iter()
+                .zip(P::pack_slice(self.1))
+                .map(|(&a, &b)| a * b)
+                .fold(P::ZEROS, |acc, x| acc + x);
+            sum.as_slice().iter().copied().sum()
+        }
+    }
+
+    #[test]
+    fn test_best_packing() {
+        type F = GoldilocksField;
+        let a = F::rand_vec(64);
+        let b = F::rand_vec(64);
+        let expected = a.iter().zip(&b).map(|(&x, &y)| x * y).sum::<F>();
+        assert_eq!(F::with_best_packing(DotProduct(&a, &b)), expected);
+    }
 }
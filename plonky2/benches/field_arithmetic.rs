@@ -1,4 +1,5 @@
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use plonky2::field::constant_time::ConstantTimeField;
 use plonky2::field::extension_field::quartic::QuarticExtension;
 use plonky2::field::extension_field::quintic::QuinticExtension;
 use plonky2::field::field_types::Field;
@@ -173,8 +174,33 @@ pub(crate) fn bench_field<F: Field>(c: &mut Criterion) {
     );
 }
 
+/// Benchmarks constant-time inversion against generic constant-time exponentiation by a full 64-bit
+/// exponent. The `try_inverse` benchmark in `bench_field` gives the variable-time cost, but only
+/// without the `constant-time` feature, since that feature routes `try_inverse` through
+/// `ct_inverse_or_zero` as well.
+pub(crate) fn bench_constant_time_inversion<F: ConstantTimeField>(c: &mut Criterion) {
+    c.bench_function(&format!("ct_inverse_or_zero<{}>", type_name::<F>()), |b| {
+        b.iter_batched(
+            || F::rand(),
+            |x| x.ct_inverse_or_zero(),
+            BatchSize::SmallInput,
+        )
+    });
+
+    // The generic constant-time exponentiation, which inversion previously used. Its cost doesn't
+    // depend on the exponent.
+    c.bench_function(&format!("ct_exp_u64<{}>", type_name::<F>()), |b| {
+        b.iter_batched(
+            || F::rand(),
+            |x| x.ct_exp_u64(u64::MAX),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     bench_field::<GoldilocksField>(c);
+    bench_constant_time_inversion::<GoldilocksField>(c);
     bench_field::<QuarticExtension<GoldilocksField>>(c);
     bench_field::<QuinticExtension<GoldilocksField>>(c);
 }
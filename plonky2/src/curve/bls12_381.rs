@@ -0,0 +1,314 @@
+use plonky2_field::bls12_381_base::Bls12_381Base;
+use plonky2_field::bls12_381_scalar::Bls12_381Scalar;
+use plonky2_field::field_types::Field;
+use serde::{Deserialize, Serialize};
+
+use crate::curve::curve_types::{AffinePoint, Curve, ProjectivePoint};
+use crate::curve::hash_to_curve::hash_to_g2;
+use crate::curve::pairing::{Fp12, Fp2, G2AffinePoint, PairingCurve, TwistType};
+
+/// The BLS12-381 curve. This describes G1; G2 lives on the twist `y^2 = x^3 + 4 (1 + u)` over `Fp2`.
+#[derive(Debug, Copy, Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Bls12_381;
+
+impl Curve for Bls12_381 {
+    type BaseField = Bls12_381Base;
+    type ScalarField = Bls12_381Scalar;
+
+    const A: Bls12_381Base = Bls12_381Base::ZERO;
+    const B: Bls12_381Base = Bls12_381Base([4, 0, 0, 0, 0, 0]);
+    const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint {
+        x: BLS12_381_GENERATOR_X,
+        y: BLS12_381_GENERATOR_Y,
+        zero: false,
+    };
+}
+
+impl PairingCurve for Bls12_381 {
+    const XI: Fp2<Bls12_381Base> = Fp2([Bls12_381Base::ONE, Bls12_381Base::ONE]);
+    const TWIST: TwistType = TwistType::M;
+}
+
+/// The BLS parameter `x = -0xd201000000010000`, which determines the Miller loop and the hard part
+/// of the final exponentiation.
+pub const BLS12_381_X: u64 = 0xd201000000010000;
+pub const BLS12_381_X_IS_NEGATIVE: bool = true;
+
+const BLS12_381_GENERATOR_X: Bls12_381Base = Bls12_381Base([
+    0xFB3AF00ADB22C6BB,
+    0x6C55E83FF97A1AEF,
+    0xA14E3A3F171BAC58,
+    0xC3688C4F9774B905,
+    0x2695638C4FA9AC0F,
+    0x17F1D3A73197D794,
+]);
+
+const BLS12_381_GENERATOR_Y: Bls12_381Base = Bls12_381Base([
+    0x0CAA232946C5E7E1,
+    0xD03CC744A2888AE4,
+    0x00DB18CB2C04B3ED,
+    0xFCF5E095D5D00AF6,
+    0xA09E30ED741D8AE4,
+    0x08B3F481E3AAA0F1,
+]);
+
+/// The x coordinate of the standard G2 generator.
+pub const BLS12_381_G2_GENERATOR_X: Fp2<Bls12_381Base> = Fp2([
+    Bls12_381Base([
+        0xD48056C8C121BDB8,
+        0x0BAC0326A805BBEF,
+        0xB4510B647AE3D177,
+        0xC6E47AD4FA403B02,
+        0x260805272DC51051,
+        0x024AA2B2F08F0A91,
+    ]),
+    Bls12_381Base([
+        0xE5AC7D055D042B7E,
+        0x334CF11213945D57,
+        0xB5DA61BBDC7F5049,
+        0x596BD0D09920B61A,
+        0x7DACD3A088274F65,
+        0x13E02B6052719F60,
+    ]),
+]);
+
+/// The y coordinate of the standard G2 generator.
+pub const BLS12_381_G2_GENERATOR_Y: Fp2<Bls12_381Base> = Fp2([
+    Bls12_381Base([
+        0xE193548608B82801,
+        0x923AC9CC3BACA289,
+        0x6D429A695160D12C,
+        0xADFD9BAA8CBDD3A7,
+        0x8CC9CDC6DA2E351A,
+        0x0CE5D527727D6E11,
+    ]),
+    Bls12_381Base([
+        0xAAA9075FF05F79BE,
+        0x3F370D275CEC1DA1,
+        0x267492AB572E99AB,
+        0xCB3E287E85A763AF,
+        0x32ACD2B02BC28B99,
+        0x0606C4A02EA734CC,
+    ]),
+]);
+
+/// A cube root of unity `beta` in the base field, such that `(x, y) -> (beta x, y)` acts on G1 as
+/// multiplication by `-x^2`.
+pub const BLS12_381_BETA: Bls12_381Base = Bls12_381Base([
+    0x2E01FFFFFFFEFFFE,
+    0xDE17D813620A0002,
+    0xDDB3A93BE6F89688,
+    0xBA69C6076A0F77EA,
+    0x5F19672FDF76CE51,
+    0x0000000000000000,
+]);
+
+pub fn g2_generator() -> G2AffinePoint<Bls12_381> {
+    G2AffinePoint::nonzero(BLS12_381_G2_GENERATOR_X, BLS12_381_G2_GENERATOR_Y)
+}
+
+/// Multiplies a point by the BLS parameter `x`, which is negative.
+fn mul_by_x(p: ProjectivePoint<Bls12_381>) -> ProjectivePoint<Bls12_381> {
+    let mut result = ProjectivePoint::ZERO;
+    for i in (0..64).rev() {
+        result = result.double();
+        if (BLS12_381_X >> i) & 1 == 1 {
+            result = result + p;
+        }
+    }
+    if BLS12_381_X_IS_NEGATIVE {
+        -result
+    } else {
+        result
+    }
+}
+
+/// Checks that a point of the curve lies in G1, using the endomorphism test of Scott, "A note on
+/// group membership tests for G1, G2 and GT on BLS pairing-friendly curves": a point is in G1 if
+/// and only if `(beta x, y) = [-x^2] p`.
+pub fn g1_in_subgroup(p: &AffinePoint<Bls12_381>) -> bool {
+    if p.zero {
+        return true;
+    }
+    let endo = AffinePoint::<Bls12_381>::nonzero(BLS12_381_BETA * p.x, p.y);
+    let x_squared_p = mul_by_x(mul_by_x(p.to_projective()));
+    endo.to_projective() == -x_squared_p
+}
+
+/// Checks that a point of the twist lies in G2, using the endomorphism test of Scott: a point is
+/// in G2 if and only if `psi(q) = [x] q`, where `psi` is the Frobenius endomorphism of the twist.
+pub fn g2_in_subgroup(q: &G2AffinePoint<Bls12_381>) -> bool {
+    let x_q = q.mul_u64(BLS12_381_X);
+    let x_q = if BLS12_381_X_IS_NEGATIVE { -x_q } else { x_q };
+    q.frobenius(1) == x_q
+}
+
+/// Computes the product of the Miller loops `f_{|x|, Q}(P)` for each pair `(P, Q)`. This mirrors
+/// `CircuitBuilder::miller_loop_bls12_381`, and shares its assumption that points are nonzero.
+pub fn miller_loop(
+    pairs: &[(AffinePoint<Bls12_381>, G2AffinePoint<Bls12_381>)],
+) -> Fp12<Bls12_381> {
+    let mut f = Fp12::ONE;
+    let mut ts = pairs.iter().map(|(_, q)| *q).collect::<Vec<_>>();
+
+    let num_bits = 64 - BLS12_381_X.leading_zeros() as usize;
+    for i in (0..num_bits - 1).rev() {
+        f = f * f;
+        for ((p, _), t) in pairs.iter().zip(ts.iter_mut()) {
+            f = f * Fp12::line_evaluation(t.tangent_slope(), t, p);
+            *t = t.double();
+        }
+
+        if (BLS12_381_X >> i) & 1 == 1 {
+            for ((p, q), t) in pairs.iter().zip(ts.iter_mut()) {
+                f = f * Fp12::line_evaluation(t.chord_slope(q), t, p);
+                *t = *t + *q;
+            }
+        }
+    }
+
+    if BLS12_381_X_IS_NEGATIVE {
+        f = f.conj();
+    }
+    f
+}
+
+/// Computes `f^x` for an element `f` of the cyclotomic subgroup.
+fn exp_by_x(f: Fp12<Bls12_381>) -> Fp12<Bls12_381> {
+    let result = f.exp_u64(BLS12_381_X);
+    if BLS12_381_X_IS_NEGATIVE {
+        result.conj()
+    } else {
+        result
+    }
+}
+
+/// Raises the output of a Miller loop to the power `3 (p^12 - 1) / r`, following the same addition
+/// chain as `CircuitBuilder::final_exponentiation_bls12_381`.
+pub fn final_exponentiation(f: Fp12<Bls12_381>) -> Fp12<Bls12_381> {
+    // Easy part: f^((p^6 - 1) (p^2 + 1)).
+    let f = f.conj() * f.inverse();
+    let y = f.frobenius(2) * f;
+
+    // Hard part: y^(3 (p^4 - p^2 + 1) / r).
+    let y1 = exp_by_x(y) * y.conj();
+    let a = exp_by_x(y1) * y1.conj();
+    let b = exp_by_x(a);
+    let c = exp_by_x(b) * a.conj();
+    let d = exp_by_x(c) * y * y * y;
+    d * c.frobenius(1) * b.frobenius(2) * a.frobenius(3)
+}
+
+/// Computes the optimal ate pairing `e(p, q)`.
+pub fn pairing(p: &AffinePoint<Bls12_381>, q: &G2AffinePoint<Bls12_381>) -> Fp12<Bls12_381> {
+    final_exponentiation(miller_loop(&[(*p, *q)]))
+}
+
+/// Verifies a BLS signature with public keys in G1 and signatures in G2, hashing the message to
+/// G2 with the domain separation tag `dst`. This is the native counterpart of
+/// `CircuitBuilder::verify_bls_signature`.
+pub fn verify_bls_signature(
+    pk: &AffinePoint<Bls12_381>,
+    msg: &[u8],
+    dst: &[u8],
+    sig: &G2AffinePoint<Bls12_381>,
+) -> bool {
+    if pk.zero || sig.zero || !pk.is_valid() || !sig.is_valid() {
+        return false;
+    }
+    if !g1_in_subgroup(pk) || !g2_in_subgroup(sig) {
+        return false;
+    }
+    let msg_hash = hash_to_g2(msg, dst);
+    let g = Bls12_381::GENERATOR_AFFINE;
+    let neg_g = AffinePoint::nonzero(g.x, -g.y);
+    final_exponentiation(miller_loop(&[(*pk, msg_hash), (neg_g, *sig)])) == Fp12::ONE
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigUint;
+    use plonky2_field::bls12_381_base::Bls12_381Base;
+    use plonky2_field::bls12_381_scalar::Bls12_381Scalar;
+    use plonky2_field::field_types::{Field, PrimeField};
+
+    use crate::curve::bls12_381::{
+        g1_in_subgroup, g2_generator, g2_in_subgroup, pairing, verify_bls_signature, Bls12_381,
+        BLS12_381_G2_GENERATOR_X, BLS12_381_G2_GENERATOR_Y,
+    };
+    use crate::curve::curve_types::{AffinePoint, Curve, CurveScalar};
+    use crate::curve::hash_to_curve::{hash_to_g2, map_to_twist, ETH_BLS_SIG_DST};
+    use crate::curve::pairing::{Fp12, Fp2, PairingCurve};
+
+    #[test]
+    fn test_generator() {
+        let g = Bls12_381::GENERATOR_AFFINE;
+        assert!(g.is_valid());
+
+        let neg_g = AffinePoint::<Bls12_381> {
+            x: g.x,
+            y: -g.y,
+            zero: g.zero,
+        };
+        assert!(neg_g.is_valid());
+    }
+
+    #[test]
+    fn test_g2_generator() {
+        let x = BLS12_381_G2_GENERATOR_X;
+        let y = BLS12_381_G2_GENERATOR_Y;
+        let b = Fp2::from_base(Bls12_381::B) * Bls12_381::XI;
+        assert_eq!(y.square(), x.square() * x + b);
+    }
+
+    #[test]
+    fn test_subgroup_checks() {
+        assert!(g1_in_subgroup(&Bls12_381::GENERATOR_AFFINE));
+        // (0, 2) has order 3.
+        let p = AffinePoint::<Bls12_381>::nonzero(Bls12_381Base::ZERO, Bls12_381Base::TWO);
+        assert!(!g1_in_subgroup(&p));
+
+        assert!(g2_in_subgroup(&g2_generator()));
+        let u = Fp2([Bls12_381Base::rand(), Bls12_381Base::rand()]);
+        assert!(!g2_in_subgroup(&map_to_twist(u)));
+    }
+
+    #[test]
+    fn test_pairing_bilinearity() {
+        let p = Bls12_381::GENERATOR_AFFINE;
+        let q = g2_generator();
+        let e = pairing(&p, &q);
+        assert_ne!(e, Fp12::ONE);
+
+        let a = 5;
+        let a_p = (CurveScalar(Bls12_381Scalar::from_canonical_u64(a))
+            * Bls12_381::GENERATOR_PROJECTIVE)
+            .to_affine();
+        let a_q = q.mul_u64(a);
+        assert_eq!(pairing(&a_p, &q), e.exp_u64(a));
+        assert_eq!(pairing(&p, &a_q), e.exp_u64(a));
+    }
+
+    fn secret_key() -> Bls12_381Scalar {
+        Bls12_381Scalar::from_biguint(BigUint::parse_bytes(b"2a2a2a2a2a2a2a2a2a2a", 16).unwrap())
+    }
+
+    #[test]
+    fn test_verify_bls_signature() {
+        let sk = secret_key();
+        let pk = (CurveScalar(sk) * Bls12_381::GENERATOR_PROJECTIVE).to_affine();
+        let msg = b"sync committee";
+        let sig = hash_to_g2(msg, ETH_BLS_SIG_DST).mul_biguint(&sk.to_canonical_biguint());
+        assert!(verify_bls_signature(&pk, msg, ETH_BLS_SIG_DST, &sig));
+
+        let wrong_pk =
+            (CurveScalar(sk + Bls12_381Scalar::ONE) * Bls12_381::GENERATOR_PROJECTIVE).to_affine();
+        assert!(!verify_bls_signature(&wrong_pk, msg, ETH_BLS_SIG_DST, &sig));
+        assert!(!verify_bls_signature(
+            &pk,
+            b"another message",
+            ETH_BLS_SIG_DST,
+            &sig
+        ));
+    }
+}
@@ -0,0 +1,86 @@
+use plonky2_field::bn254_base::Bn254Base;
+use plonky2_field::bn254_scalar::Bn254Scalar;
+use plonky2_field::field_types::Field;
+use serde::{Deserialize, Serialize};
+
+use crate::curve::curve_types::{AffinePoint, Curve};
+use crate::curve::pairing::{Fp2, PairingCurve, TwistType};
+
+/// The BN254 curve, also known as alt_bn128. This describes G1; G2 lives on the twist
+/// `y^2 = x^3 + 3 / (9 + u)` over `Fp2`.
+#[derive(Debug, Copy, Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Bn254;
+
+impl Curve for Bn254 {
+    type BaseField = Bn254Base;
+    type ScalarField = Bn254Scalar;
+
+    const A: Bn254Base = Bn254Base::ZERO;
+    const B: Bn254Base = Bn254Base([3, 0, 0, 0]);
+    const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint {
+        x: Bn254Base::ONE,
+        y: Bn254Base::TWO,
+        zero: false,
+    };
+}
+
+impl PairingCurve for Bn254 {
+    const XI: Fp2<Bn254Base> = Fp2([Bn254Base([9, 0, 0, 0]), Bn254Base::ONE]);
+    const TWIST: TwistType = TwistType::D;
+}
+
+/// The BN parameter `u`, which determines the Miller loop length `6u + 2` and the hard part of the
+/// final exponentiation.
+pub const BN254_U: u64 = 4965661367192848881;
+
+/// The x coordinate of the standard G2 generator.
+pub const BN254_G2_GENERATOR_X: Fp2<Bn254Base> = Fp2([
+    Bn254Base([
+        0x46DEBD5CD992F6ED,
+        0x674322D4F75EDADD,
+        0x426A00665E5C4479,
+        0x1800DEEF121F1E76,
+    ]),
+    Bn254Base([
+        0x97E485B7AEF312C2,
+        0xF1AA493335A9E712,
+        0x7260BFB731FB5D25,
+        0x198E9393920D483A,
+    ]),
+]);
+
+/// The y coordinate of the standard G2 generator.
+pub const BN254_G2_GENERATOR_Y: Fp2<Bn254Base> = Fp2([
+    Bn254Base([
+        0x4CE6CC0166FA7DAA,
+        0xE3D1E7690C43D37B,
+        0x4AAB71808DCB408F,
+        0x12C85EA5DB8C6DEB,
+    ]),
+    Bn254Base([
+        0x55ACDADCD122975B,
+        0xBC4B313370B38EF3,
+        0xEC9E99AD690C3395,
+        0x090689D0585FF075,
+    ]),
+]);
+
+#[cfg(test)]
+mod tests {
+    use crate::curve::bn254::{Bn254, BN254_G2_GENERATOR_X, BN254_G2_GENERATOR_Y};
+    use crate::curve::curve_types::Curve;
+    use crate::curve::pairing::{Fp2, PairingCurve};
+
+    #[test]
+    fn test_generator() {
+        assert!(Bn254::GENERATOR_AFFINE.is_valid());
+    }
+
+    #[test]
+    fn test_g2_generator() {
+        let x = BN254_G2_GENERATOR_X;
+        let y = BN254_G2_GENERATOR_Y;
+        let b = Fp2::from_base(Bn254::B) * Bn254::XI.inverse();
+        assert_eq!(y.square(), x.square() * x + b);
+    }
+}
@@ -1,10 +1,10 @@
 use itertools::Itertools;
 use plonky2_field::field_types::Field;
 use plonky2_field::field_types::PrimeField;
-use rayon::prelude::*;
 
 use crate::curve::curve_summation::affine_multisummation_best;
 use crate::curve::curve_types::{AffinePoint, Curve, ProjectivePoint};
+use crate::util::maybe_rayon::*;
 
 /// In Yao's method, we compute an affine summation for each digit. In a parallel setting, it would
 /// be easiest to assign individual summations to threads, but this would be sub-optimal because
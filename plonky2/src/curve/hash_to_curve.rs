@@ -0,0 +1,600 @@
+//! Hashing to G2 of BLS12-381 following RFC 9380, with the suite `BLS12381G2_XMD:SHA-256_SSWU_RO_`.
+//! The message is expanded with `expand_message_xmd` and hashed to two elements of `Fp2`. Each is
+//! mapped to a curve `E'` which is 3-isogenous to the twist by the simplified SWU map, then to the
+//! twist by the isogeny, and the sum of the two points is multiplied by the effective cofactor.
+
+use num::BigUint;
+use plonky2_field::bls12_381_base::Bls12_381Base;
+use plonky2_field::field_types::{Field, PrimeField};
+use sha2::{Digest, Sha256};
+
+use crate::curve::bls12_381::{Bls12_381, BLS12_381_X, BLS12_381_X_IS_NEGATIVE};
+use crate::curve::pairing::{Fp2, G2AffinePoint};
+
+/// The domain separation tag of the proof-of-possession BLS signature scheme used by Ethereum
+/// consensus.
+pub const ETH_BLS_SIG_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// The number of uniform bytes reduced to each base field element, which leaves a bias of about
+/// `2^-128`.
+pub const HASH_TO_FIELD_BYTES: usize = 64;
+
+/// Expands a message to `len` uniform bytes with SHA-256, as in section 5.3.1 of RFC 9380.
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    assert!(
+        dst.len() <= 255,
+        "DSTs longer than 255 bytes must be hashed first"
+    );
+    let ell = (len + 31) / 32;
+    assert!(ell <= 255 && len <= u16::MAX as usize);
+    let mut dst_prime = dst.to_vec();
+    dst_prime.push(dst.len() as u8);
+
+    let b_0 = Sha256::new()
+        .chain_update([0u8; 64])
+        .chain_update(msg)
+        .chain_update((len as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut b_i = Sha256::new()
+        .chain_update(b_0)
+        .chain_update([1u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = b_i.to_vec();
+    for i in 2..=ell {
+        let b_0_xor_b_i = b_0
+            .iter()
+            .zip(b_i.iter())
+            .map(|(x, y)| x ^ y)
+            .collect::<Vec<_>>();
+        b_i = Sha256::new()
+            .chain_update(b_0_xor_b_i)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    uniform_bytes.truncate(len);
+    uniform_bytes
+}
+
+/// Hashes a message to two elements of `Fp2`, as in section 5.2 of RFC 9380.
+pub fn hash_to_field(msg: &[u8], dst: &[u8]) -> [Fp2<Bls12_381Base>; 2] {
+    let uniform_bytes = expand_message_xmd(msg, dst, 4 * HASH_TO_FIELD_BYTES);
+    let mut elements = uniform_bytes.chunks(HASH_TO_FIELD_BYTES).map(|chunk| {
+        Bls12_381Base::from_biguint(BigUint::from_bytes_be(chunk) % Bls12_381Base::order())
+    });
+    [(); 2].map(|_| Fp2([elements.next().unwrap(), elements.next().unwrap()]))
+}
+
+/// The sign of an element of `Fp2`, as defined in section 4.1 of RFC 9380.
+pub fn sgn0(x: Fp2<Bls12_381Base>) -> bool {
+    let sign_0 = x.0[0].to_canonical_biguint().bit(0);
+    let zero_0 = x.0[0].is_zero();
+    let sign_1 = x.0[1].to_canonical_biguint().bit(0);
+    sign_0 || (zero_0 && sign_1)
+}
+
+/// The simplified SWU map to `E'`, as in section 6.6.2 of RFC 9380. Returns the coordinates of
+/// the resulting point.
+pub fn map_to_curve_sswu(u: Fp2<Bls12_381Base>) -> (Fp2<Bls12_381Base>, Fp2<Bls12_381Base>) {
+    let z_u2 = SSWU_Z * u.square();
+    let tv1 = z_u2.square() + z_u2;
+    let x1 = if tv1.is_zero() {
+        SSWU_B * (SSWU_Z * SSWU_A).inverse()
+    } else {
+        -SSWU_B * SSWU_A.inverse() * (Fp2::ONE + tv1.inverse())
+    };
+
+    // If `g(x1)` is not a square, then `g(x2) = Z^3 u^6 g(x1)` is, since `Z` is not a square.
+    let gx1 = sswu_rhs(x1);
+    let (x, y) = if gx1.is_square() {
+        (x1, gx1.sqrt().unwrap())
+    } else {
+        let x2 = z_u2 * x1;
+        (x2, sswu_rhs(x2).sqrt().unwrap())
+    };
+
+    let y = if sgn0(u) == sgn0(y) { y } else { -y };
+    (x, y)
+}
+
+/// Evaluates the right hand side of the equation of `E'`, `x^3 + A' x + B'`.
+fn sswu_rhs(x: Fp2<Bls12_381Base>) -> Fp2<Bls12_381Base> {
+    x.square() * x + SSWU_A * x + SSWU_B
+}
+
+/// Applies the 3-isogeny from `E'` to the twist, given in appendix E.3 of RFC 9380.
+pub fn iso_map(x: Fp2<Bls12_381Base>, y: Fp2<Bls12_381Base>) -> G2AffinePoint<Bls12_381> {
+    let x_den = eval_poly(&ISO_X_DEN, x);
+    let y_den = eval_poly(&ISO_Y_DEN, x);
+    if x_den.is_zero() || y_den.is_zero() {
+        return G2AffinePoint::ZERO;
+    }
+    G2AffinePoint::nonzero(
+        eval_poly(&ISO_X_NUM, x) * x_den.inverse(),
+        y * eval_poly(&ISO_Y_NUM, x) * y_den.inverse(),
+    )
+}
+
+fn eval_poly(coeffs: &[Fp2<Bls12_381Base>], x: Fp2<Bls12_381Base>) -> Fp2<Bls12_381Base> {
+    coeffs
+        .iter()
+        .rev()
+        .fold(Fp2::ZERO, |acc, &coeff| acc * x + coeff)
+}
+
+/// Maps an element of `Fp2` to the twist, by the simplified SWU map followed by the isogeny. The
+/// result is not in G2 in general.
+pub fn map_to_twist(u: Fp2<Bls12_381Base>) -> G2AffinePoint<Bls12_381> {
+    let (x, y) = map_to_curve_sswu(u);
+    iso_map(x, y)
+}
+
+fn mul_by_x(p: G2AffinePoint<Bls12_381>) -> G2AffinePoint<Bls12_381> {
+    let result = p.mul_u64(BLS12_381_X);
+    if BLS12_381_X_IS_NEGATIVE {
+        -result
+    } else {
+        result
+    }
+}
+
+/// Multiplies a point of the twist by the effective cofactor `h_eff`, using the method of Budroni
+/// and Pintore given in appendix G.3 of RFC 9380:
+/// `h_eff P = [x^2 - x - 1] P + [x - 1] psi(P) + psi^2(2 P)`.
+pub fn clear_cofactor(p: G2AffinePoint<Bls12_381>) -> G2AffinePoint<Bls12_381> {
+    let t1 = mul_by_x(p);
+    let t2 = p.frobenius(1);
+    let t3 = p.double().frobenius(2) - t2;
+    let t2 = mul_by_x(t1 + t2);
+    t3 + t2 - t1 - p
+}
+
+/// Hashes a message to G2, as in section 3 of RFC 9380.
+pub fn hash_to_g2(msg: &[u8], dst: &[u8]) -> G2AffinePoint<Bls12_381> {
+    let [u0, u1] = hash_to_field(msg, dst);
+    clear_cofactor(map_to_twist(u0) + map_to_twist(u1))
+}
+
+/// The coefficient `A'` of the isogenous curve `E'`, `240 u`.
+pub(crate) const SSWU_A: Fp2<Bls12_381Base> = Fp2([
+    Bls12_381Base([
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+    ]),
+    Bls12_381Base([
+        0x00000000000000F0,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+    ]),
+]);
+/// The coefficient `B'` of the isogenous curve `E'`, `1012 (1 + u)`.
+pub(crate) const SSWU_B: Fp2<Bls12_381Base> = Fp2([
+    Bls12_381Base([
+        0x00000000000003F4,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+    ]),
+    Bls12_381Base([
+        0x00000000000003F4,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+        0x0000000000000000,
+    ]),
+]);
+/// The non-square `Z = -(2 + u)` used by the simplified SWU map.
+pub(crate) const SSWU_Z: Fp2<Bls12_381Base> = Fp2([
+    Bls12_381Base([
+        0xB9FEFFFFFFFFAAA9,
+        0x1EABFFFEB153FFFF,
+        0x6730D2A0F6B0F624,
+        0x64774B84F38512BF,
+        0x4B1BA7B6434BACD7,
+        0x1A0111EA397FE69A,
+    ]),
+    Bls12_381Base([
+        0xB9FEFFFFFFFFAAAA,
+        0x1EABFFFEB153FFFF,
+        0x6730D2A0F6B0F624,
+        0x64774B84F38512BF,
+        0x4B1BA7B6434BACD7,
+        0x1A0111EA397FE69A,
+    ]),
+]);
+/// The coefficients of the numerator of the x coordinate of the isogeny, from the constant term up.
+pub(crate) const ISO_X_NUM: [Fp2<Bls12_381Base>; 4] = [
+    Fp2([
+        Bls12_381Base([
+            0x6238AAAAAAAA97D6,
+            0x5C2638E343D9C71C,
+            0x88B58423C50AE15D,
+            0x32C52D39FD3A042A,
+            0xBB5B7A9A47D7ED85,
+            0x05C759507E8E333E,
+        ]),
+        Bls12_381Base([
+            0x6238AAAAAAAA97D6,
+            0x5C2638E343D9C71C,
+            0x88B58423C50AE15D,
+            0x32C52D39FD3A042A,
+            0xBB5B7A9A47D7ED85,
+            0x05C759507E8E333E,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        Bls12_381Base([
+            0x26A9FFFFFFFFC71A,
+            0x1472AAA9CB8D5555,
+            0x9A208C6B4F20A418,
+            0x984F87ADF7AE0C7F,
+            0x32126FCED787C88F,
+            0x11560BF17BAA99BC,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x26A9FFFFFFFFC71E,
+            0x1472AAA9CB8D5555,
+            0x9A208C6B4F20A418,
+            0x984F87ADF7AE0C7F,
+            0x32126FCED787C88F,
+            0x11560BF17BAA99BC,
+        ]),
+        Bls12_381Base([
+            0x9354FFFFFFFFE38D,
+            0x0A395554E5C6AAAA,
+            0xCD104635A790520C,
+            0xCC27C3D6FBD7063F,
+            0x190937E76BC3E447,
+            0x08AB05F8BDD54CDE,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x88E2AAAAAAAA5ED1,
+            0x7098E38D0F671C71,
+            0x22D6108F142B8575,
+            0xCB14B4E7F4E810AA,
+            0xED6DEA691F5FB614,
+            0x171D6541FA38CCFA,
+        ]),
+        Bls12_381Base([
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+    ]),
+];
+/// The coefficients of the (monic) denominator of the x coordinate of the isogeny.
+pub(crate) const ISO_X_DEN: [Fp2<Bls12_381Base>; 3] = [
+    Fp2([
+        Bls12_381Base([
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        Bls12_381Base([
+            0xB9FEFFFFFFFFAA63,
+            0x1EABFFFEB153FFFF,
+            0x6730D2A0F6B0F624,
+            0x64774B84F38512BF,
+            0x4B1BA7B6434BACD7,
+            0x1A0111EA397FE69A,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x000000000000000C,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        Bls12_381Base([
+            0xB9FEFFFFFFFFAA9F,
+            0x1EABFFFEB153FFFF,
+            0x6730D2A0F6B0F624,
+            0x64774B84F38512BF,
+            0x4B1BA7B6434BACD7,
+            0x1A0111EA397FE69A,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x0000000000000001,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        Bls12_381Base([
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+    ]),
+];
+/// The coefficients of the numerator of the y coordinate of the isogeny, which is multiplied by `y`.
+pub(crate) const ISO_Y_NUM: [Fp2<Bls12_381Base>; 4] = [
+    Fp2([
+        Bls12_381Base([
+            0x12CFC71C71C6D706,
+            0xFC8C25EBF8C92F68,
+            0xF54439D87D27E500,
+            0x0F7DA5D4A07F649B,
+            0x59A4C18B076D1193,
+            0x1530477C7AB4113B,
+        ]),
+        Bls12_381Base([
+            0x12CFC71C71C6D706,
+            0xFC8C25EBF8C92F68,
+            0xF54439D87D27E500,
+            0x0F7DA5D4A07F649B,
+            0x59A4C18B076D1193,
+            0x1530477C7AB4113B,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        Bls12_381Base([
+            0x6238AAAAAAAA97BE,
+            0x5C2638E343D9C71C,
+            0x88B58423C50AE15D,
+            0x32C52D39FD3A042A,
+            0xBB5B7A9A47D7ED85,
+            0x05C759507E8E333E,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x26A9FFFFFFFFC71C,
+            0x1472AAA9CB8D5555,
+            0x9A208C6B4F20A418,
+            0x984F87ADF7AE0C7F,
+            0x32126FCED787C88F,
+            0x11560BF17BAA99BC,
+        ]),
+        Bls12_381Base([
+            0x9354FFFFFFFFE38F,
+            0x0A395554E5C6AAAA,
+            0xCD104635A790520C,
+            0xCC27C3D6FBD7063F,
+            0x190937E76BC3E447,
+            0x08AB05F8BDD54CDE,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0xE1B371C71C718B10,
+            0x4E79097A56DC4BD9,
+            0xB0E977C69AA27452,
+            0x761B0F37A1E26286,
+            0xFBF7043DE3811AD0,
+            0x124C9AD43B6CF79B,
+        ]),
+        Bls12_381Base([
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+    ]),
+];
+/// The coefficients of the (monic) denominator of the y coordinate of the isogeny.
+pub(crate) const ISO_Y_DEN: [Fp2<Bls12_381Base>; 4] = [
+    Fp2([
+        Bls12_381Base([
+            0xB9FEFFFFFFFFA8FB,
+            0x1EABFFFEB153FFFF,
+            0x6730D2A0F6B0F624,
+            0x64774B84F38512BF,
+            0x4B1BA7B6434BACD7,
+            0x1A0111EA397FE69A,
+        ]),
+        Bls12_381Base([
+            0xB9FEFFFFFFFFA8FB,
+            0x1EABFFFEB153FFFF,
+            0x6730D2A0F6B0F624,
+            0x64774B84F38512BF,
+            0x4B1BA7B6434BACD7,
+            0x1A0111EA397FE69A,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        Bls12_381Base([
+            0xB9FEFFFFFFFFA9D3,
+            0x1EABFFFEB153FFFF,
+            0x6730D2A0F6B0F624,
+            0x64774B84F38512BF,
+            0x4B1BA7B6434BACD7,
+            0x1A0111EA397FE69A,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x0000000000000012,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        Bls12_381Base([
+            0xB9FEFFFFFFFFAA99,
+            0x1EABFFFEB153FFFF,
+            0x6730D2A0F6B0F624,
+            0x64774B84F38512BF,
+            0x4B1BA7B6434BACD7,
+            0x1A0111EA397FE69A,
+        ]),
+    ]),
+    Fp2([
+        Bls12_381Base([
+            0x0000000000000001,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+        Bls12_381Base([
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+            0x0000000000000000,
+        ]),
+    ]),
+];
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use num::BigUint;
+    use plonky2_field::bls12_381_base::Bls12_381Base;
+    use plonky2_field::field_types::Field;
+
+    use crate::curve::bls12_381::g2_in_subgroup;
+    use crate::curve::hash_to_curve::{
+        expand_message_xmd, hash_to_field, hash_to_g2, iso_map, map_to_curve_sswu, sgn0,
+    };
+    use crate::curve::pairing::Fp2;
+
+    const DST: &[u8] = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+
+    fn fp2_from_hex(c0: &str, c1: &str) -> Fp2<Bls12_381Base> {
+        let parse =
+            |s: &str| Bls12_381Base::from_biguint(BigUint::parse_bytes(s.as_bytes(), 16).unwrap());
+        Fp2([parse(c0), parse(c1)])
+    }
+
+    #[test]
+    fn test_expand_message_xmd() {
+        // From appendix K.1 of RFC 9380.
+        let uniform_bytes =
+            expand_message_xmd(b"", b"QUUX-V01-CS02-with-expander-SHA256-128", 0x20);
+        let expected = "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235";
+        assert_eq!(hex(&uniform_bytes), expected);
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().fold(String::new(), |mut s, b| {
+            write!(s, "{:02x}", b).unwrap();
+            s
+        })
+    }
+
+    #[test]
+    fn test_hash_to_field() {
+        // From appendix J.10.1 of RFC 9380.
+        let [u0, u1] = hash_to_field(b"", DST);
+        assert_eq!(
+            u0,
+            fp2_from_hex(
+                "03dbc2cce174e91ba93cbb08f26b917f98194a2ea08d1cce75b2b9cc9f21689d80bd79b594a613d0a68eb807dfdc1cf8",
+                "05a2acec64114845711a54199ea339abd125ba38253b70a92c876df10598bd1986b739cad67961eb94f7076511b3b39a",
+            )
+        );
+        assert_eq!(
+            u1,
+            fp2_from_hex(
+                "02f99798e8a5acdeed60d7e18e9120521ba1f47ec090984662846bc825de191b5b7641148c0dbc237726a334473eee94",
+                "145a81e418d4010cc027a68f14391b30074e89e60ee7a22f87217b2f6eb0c4b94c9115b436e6fa4607e95a98de30a435",
+            )
+        );
+    }
+
+    #[test]
+    fn test_hash_to_g2() {
+        // From appendix J.10.1 of RFC 9380.
+        let p = hash_to_g2(b"", DST);
+        assert_eq!(
+            p.x,
+            fp2_from_hex(
+                "0141ebfbdca40eb85b87142e130ab689c673cf60f1a3e98d69335266f30d9b8d4ac44c1038e9dcdd5393faf5c41fb78a",
+                "05cb8437535e20ecffaef7752baddf98034139c38452458baeefab379ba13dff5bf5dd71b72418717047f5b0f37da03d",
+            )
+        );
+        assert_eq!(
+            p.y,
+            fp2_from_hex(
+                "0503921d7f6a12805e72940b963c0cf3471c7b2a524950ca195d11062ee75ec076daf2d4bc358c4b190c0c98064fdd92",
+                "12424ac32561493f3fe3c260708a12b7c620e7be00099a974e259ddc7d1f6395c3c811cdd19f1e8dbf3e9ecfdcbab8d6",
+            )
+        );
+        assert!(g2_in_subgroup(&p));
+
+        let p = hash_to_g2(b"abc", DST);
+        assert_eq!(
+            p.x,
+            fp2_from_hex(
+                "02c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbec7780ccc7954725f4168aff2787776e6",
+                "139cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4ca3a230ed250fbe3a2acf73a41177fd8",
+            )
+        );
+        assert!(g2_in_subgroup(&p));
+    }
+
+    #[test]
+    fn test_map_to_twist() {
+        let u = Fp2([Bls12_381Base::rand(), Bls12_381Base::rand()]);
+        let (x, y) = map_to_curve_sswu(u);
+        assert_eq!(sgn0(u), sgn0(y));
+        let p = iso_map(x, y);
+        assert!(p.is_valid());
+        assert!(!g2_in_subgroup(&p));
+    }
+}
@@ -1,7 +1,11 @@
+pub mod bls12_381;
+pub mod bn254;
 pub mod curve_adds;
 pub mod curve_msm;
 pub mod curve_multiplication;
 pub mod curve_summation;
 pub mod curve_types;
 pub mod ecdsa;
+pub mod hash_to_curve;
+pub mod pairing;
 pub mod secp256k1;
@@ -0,0 +1,459 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+use num::BigUint;
+use plonky2_field::field_types::Field;
+
+use crate::curve::curve_types::{AffinePoint, Curve};
+
+/// The type of the sextic twist `E'` of a pairing-friendly curve, which determines where the
+/// evaluations of line functions land in `Fp12`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TwistType {
+    /// A multiplicative twist, `E': y^2 = x^3 + b * xi`.
+    M,
+    /// A divisive twist, `E': y^2 = x^3 + b / xi`.
+    D,
+}
+
+/// A curve with an embedding degree of 12, whose G2 is represented on a sextic twist over `Fp2`.
+pub trait PairingCurve: Curve {
+    /// The non-residue `xi` used to build `Fp12 = Fp2[w] / (w^6 - xi)`.
+    const XI: Fp2<Self::BaseField>;
+
+    const TWIST: TwistType;
+
+    /// The coefficient `b'` of the twist `E': y^2 = x^3 + b'`.
+    fn twist_b() -> Fp2<Self::BaseField> {
+        match Self::TWIST {
+            TwistType::M => Fp2::from_base(Self::B) * Self::XI,
+            TwistType::D => Fp2::from_base(Self::B) * Self::XI.inverse(),
+        }
+    }
+}
+
+/// An element of `Fp2 = Fp[u] / (u^2 + 1)`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Fp2<FF: Field>(pub [FF; 2]);
+
+impl<FF: Field> Fp2<FF> {
+    pub const ZERO: Self = Self([FF::ZERO, FF::ZERO]);
+    pub const ONE: Self = Self([FF::ONE, FF::ZERO]);
+
+    pub fn from_base(x: FF) -> Self {
+        Self([x, FF::ZERO])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    pub fn conj(&self) -> Self {
+        Self([self.0[0], -self.0[1]])
+    }
+
+    pub fn square(&self) -> Self {
+        *self * *self
+    }
+
+    pub fn inverse(&self) -> Self {
+        let norm = self.0[0] * self.0[0] + self.0[1] * self.0[1];
+        let norm_inv = norm.inverse();
+        Self([self.0[0] * norm_inv, -self.0[1] * norm_inv])
+    }
+
+    /// Returns whether this is a square, using the fact that `a` is a square in `Fp2` if and only if
+    /// its norm is a square in `Fp`.
+    pub fn is_square(&self) -> bool {
+        let norm = self.0[0] * self.0[0] + self.0[1] * self.0[1];
+        norm.is_zero() || norm.exp_biguint(&((FF::order() - 1u32) >> 1)) == FF::ONE
+    }
+
+    /// Computes a square root, or returns `None` if there is none. This assumes `p = 3 (mod 4)`,
+    /// and follows Algorithm 9 of Adj and Rodríguez-Henríquez, "Square root computation over even
+    /// extension fields".
+    pub fn sqrt(&self) -> Option<Self> {
+        let p = FF::order();
+        let a1 = self.exp_biguint(&((&p - 3u32) >> 2));
+        let alpha = a1.square() * *self;
+        let x0 = a1 * *self;
+        let root = if alpha == -Self::ONE {
+            Self([FF::ZERO, FF::ONE]) * x0
+        } else {
+            (Self::ONE + alpha).exp_biguint(&((p - 1u32) >> 1)) * x0
+        };
+        if root.square() == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    pub fn exp_biguint(&self, power: &BigUint) -> Self {
+        let mut result = Self::ONE;
+        for &digit in power.to_u64_digits().iter().rev() {
+            for i in (0..64).rev() {
+                result = result.square();
+                if (digit >> i) & 1 == 1 {
+                    result = result * *self;
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<FF: Field> Add for Fp2<FF> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self([self.0[0] + rhs.0[0], self.0[1] + rhs.0[1]])
+    }
+}
+
+impl<FF: Field> Sub for Fp2<FF> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self([self.0[0] - rhs.0[0], self.0[1] - rhs.0[1]])
+    }
+}
+
+impl<FF: Field> Neg for Fp2<FF> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self([-self.0[0], -self.0[1]])
+    }
+}
+
+impl<FF: Field> Mul for Fp2<FF> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let [a0, a1] = self.0;
+        let [b0, b1] = rhs.0;
+        Self([a0 * b0 - a1 * b1, a0 * b1 + a1 * b0])
+    }
+}
+
+/// An element of `Fp12 = Fp2[w] / (w^6 - xi)`, represented by its six `Fp2` coefficients.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Fp12<C: PairingCurve>(pub [Fp2<C::BaseField>; 6]);
+
+impl<C: PairingCurve> Fp12<C> {
+    pub const ONE: Self = Self([
+        Fp2::ONE,
+        Fp2::ZERO,
+        Fp2::ZERO,
+        Fp2::ZERO,
+        Fp2::ZERO,
+        Fp2::ZERO,
+    ]);
+
+    /// The conjugate `f^(p^6)`, which negates the odd coefficients since `w^(p^6) = -w`.
+    pub fn conj(&self) -> Self {
+        let mut result = *self;
+        for i in (1..6).step_by(2) {
+            result.0[i] = -result.0[i];
+        }
+        result
+    }
+
+    /// Computes `f^(p^k)`.
+    pub fn frobenius(&self, k: usize) -> Self {
+        let coeffs = Self::frobenius_coeffs(k);
+        let mut result = *self;
+        for i in 0..6 {
+            let a = if k % 2 == 1 {
+                self.0[i].conj()
+            } else {
+                self.0[i]
+            };
+            result.0[i] = a * coeffs[i];
+        }
+        result
+    }
+
+    /// The constants `xi^(i (p^k - 1) / 6)`, such that `w^(i p^k) = xi^(i (p^k - 1) / 6) w^i`.
+    pub fn frobenius_coeffs(k: usize) -> [Fp2<C::BaseField>; 6] {
+        let p = C::BaseField::order();
+        let base = (p.pow(k as u32) - 1u32) / 6u32;
+        let mut coeffs = [Fp2::ONE; 6];
+        for i in 1..6 {
+            coeffs[i] = C::XI.exp_biguint(&(&base * i as u32));
+        }
+        coeffs
+    }
+
+    pub fn inverse(&self) -> Self {
+        // Write `f = a + b w`, where `a` and `b` are in `Fp6 = Fp2[v] / (v^3 - xi)` with `v = w^2`.
+        // Then `f^-1 = (a - b w) / (a^2 - b^2 v)`.
+        let a = [self.0[0], self.0[2], self.0[4]];
+        let b = [self.0[1], self.0[3], self.0[5]];
+        let b_squared = fp6_mul::<C>(b, b);
+        let b_squared_v = [C::XI * b_squared[2], b_squared[0], b_squared[1]];
+        let a_squared = fp6_mul::<C>(a, a);
+        let denom = [
+            a_squared[0] - b_squared_v[0],
+            a_squared[1] - b_squared_v[1],
+            a_squared[2] - b_squared_v[2],
+        ];
+        let denom_inv = fp6_inverse::<C>(denom);
+        let a = fp6_mul::<C>(a, denom_inv);
+        let b = fp6_mul::<C>(b, denom_inv);
+        Self([a[0], -b[0], a[1], -b[1], a[2], -b[2]])
+    }
+
+    pub fn exp_u64(&self, power: u64) -> Self {
+        let mut result = Self::ONE;
+        for i in (0..64).rev() {
+            result = result * result;
+            if (power >> i) & 1 == 1 {
+                result = result * *self;
+            }
+        }
+        result
+    }
+
+    /// The sparse element of `Fp12` obtained by evaluating the line with slope `lambda` through the
+    /// G2 point `t` at the G1 point `p`, after untwisting. This mirrors
+    /// `CircuitBuilder::line_evaluation`.
+    pub fn line_evaluation(
+        lambda: Fp2<C::BaseField>,
+        t: &G2AffinePoint<C>,
+        p: &AffinePoint<C>,
+    ) -> Self {
+        let constant_term = lambda * t.x - t.y;
+        let neg_lambda_x_p = -(lambda * Fp2::from_base(p.x));
+        let y_p = Fp2::from_base(p.y);
+
+        let mut line = [Fp2::ZERO; 6];
+        match C::TWIST {
+            TwistType::M => {
+                line[0] = constant_term;
+                line[2] = neg_lambda_x_p;
+                line[3] = y_p;
+            }
+            TwistType::D => {
+                line[0] = y_p;
+                line[1] = neg_lambda_x_p;
+                line[3] = constant_term;
+            }
+        }
+        Self(line)
+    }
+}
+
+impl<C: PairingCurve> Mul for Fp12<C> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut product = [Fp2::ZERO; 11];
+        for i in 0..6 {
+            for j in 0..6 {
+                product[i + j] = product[i + j] + self.0[i] * rhs.0[j];
+            }
+        }
+        let mut result = [Fp2::ZERO; 6];
+        for i in 0..6 {
+            result[i] = product[i];
+            if i + 6 < 11 {
+                result[i] = result[i] + C::XI * product[i + 6];
+            }
+        }
+        Self(result)
+    }
+}
+
+/// An affine point on the sextic twist of `C`, i.e. a point of G2.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct G2AffinePoint<C: PairingCurve> {
+    pub x: Fp2<C::BaseField>,
+    pub y: Fp2<C::BaseField>,
+    pub zero: bool,
+}
+
+impl<C: PairingCurve> G2AffinePoint<C> {
+    pub const ZERO: Self = Self {
+        x: Fp2::ZERO,
+        y: Fp2::ZERO,
+        zero: true,
+    };
+
+    pub fn nonzero(x: Fp2<C::BaseField>, y: Fp2<C::BaseField>) -> Self {
+        Self { x, y, zero: false }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.zero || self.y.square() == self.x.square() * self.x + C::twist_b()
+    }
+
+    /// The slope of the tangent at this point, which must be nonzero and not of order 2.
+    pub fn tangent_slope(&self) -> Fp2<C::BaseField> {
+        let x_squared = self.x.square();
+        (x_squared + x_squared + x_squared) * (self.y + self.y).inverse()
+    }
+
+    /// The slope of the chord through this point and `rhs`, which must have distinct x coordinates.
+    pub fn chord_slope(&self, rhs: &Self) -> Fp2<C::BaseField> {
+        (rhs.y - self.y) * (rhs.x - self.x).inverse()
+    }
+
+    pub fn double(&self) -> Self {
+        if self.zero || self.y.is_zero() {
+            return Self::ZERO;
+        }
+        let lambda = self.tangent_slope();
+        self.chord_result(lambda, self.x)
+    }
+
+    fn chord_result(&self, lambda: Fp2<C::BaseField>, x2: Fp2<C::BaseField>) -> Self {
+        let x3 = lambda.square() - self.x - x2;
+        let y3 = lambda * (self.x - x3) - self.y;
+        Self::nonzero(x3, y3)
+    }
+
+    pub fn mul_u64(&self, k: u64) -> Self {
+        let mut result = Self::ZERO;
+        for i in (0..64).rev() {
+            result = result.double();
+            if (k >> i) & 1 == 1 {
+                result = result + *self;
+            }
+        }
+        result
+    }
+
+    pub fn mul_biguint(&self, k: &BigUint) -> Self {
+        let mut result = Self::ZERO;
+        for &digit in k.to_u64_digits().iter().rev() {
+            for i in (0..64).rev() {
+                result = result.double();
+                if (digit >> i) & 1 == 1 {
+                    result = result + *self;
+                }
+            }
+        }
+        result
+    }
+
+    /// Applies the `p^k`-power Frobenius endomorphism through the twist, i.e. computes
+    /// `untwist^-1(frobenius^k(untwist(q)))`. This mirrors `CircuitBuilder::g2_frobenius`.
+    pub fn frobenius(&self, k: usize) -> Self {
+        if self.zero {
+            return *self;
+        }
+        let (x_coeff, y_coeff) = g2_frobenius_coeffs::<C>(k);
+        let (x, y) = if k % 2 == 1 {
+            (self.x.conj(), self.y.conj())
+        } else {
+            (self.x, self.y)
+        };
+        Self::nonzero(x * x_coeff, y * y_coeff)
+    }
+}
+
+/// The constants by which the Frobenius endomorphism of the twist scales the conjugated
+/// coordinates. Since `untwist(x, y) = (x w^2, y w^3)` for D-type twists and `(x / w^2, y / w^3)`
+/// for M-type twists, these are `w^(2 (p^k - 1))` and `w^(3 (p^k - 1))` or their inverses.
+pub fn g2_frobenius_coeffs<C: PairingCurve>(k: usize) -> (Fp2<C::BaseField>, Fp2<C::BaseField>) {
+    let coeffs = Fp12::<C>::frobenius_coeffs(k);
+    match C::TWIST {
+        TwistType::D => (coeffs[2], coeffs[3]),
+        TwistType::M => (coeffs[2].inverse(), coeffs[3].inverse()),
+    }
+}
+
+impl<C: PairingCurve> Add for G2AffinePoint<C> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        if self.zero {
+            return rhs;
+        }
+        if rhs.zero {
+            return self;
+        }
+        if self.x == rhs.x {
+            return if self.y == rhs.y {
+                self.double()
+            } else {
+                Self::ZERO
+            };
+        }
+        let lambda = self.chord_slope(&rhs);
+        self.chord_result(lambda, rhs.x)
+    }
+}
+
+impl<C: PairingCurve> Sub for G2AffinePoint<C> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<C: PairingCurve> Neg for G2AffinePoint<C> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            x: self.x,
+            y: -self.y,
+            zero: self.zero,
+        }
+    }
+}
+
+type Fp6<FF> = [Fp2<FF>; 3];
+
+fn fp6_mul<C: PairingCurve>(a: Fp6<C::BaseField>, b: Fp6<C::BaseField>) -> Fp6<C::BaseField> {
+    let mut product = [Fp2::ZERO; 5];
+    for i in 0..3 {
+        for j in 0..3 {
+            product[i + j] = product[i + j] + a[i] * b[j];
+        }
+    }
+    [
+        product[0] + C::XI * product[3],
+        product[1] + C::XI * product[4],
+        product[2],
+    ]
+}
+
+fn fp6_inverse<C: PairingCurve>(c: Fp6<C::BaseField>) -> Fp6<C::BaseField> {
+    let [c0, c1, c2] = c;
+    let t0 = c0.square() - C::XI * c1 * c2;
+    let t1 = C::XI * c2.square() - c0 * c1;
+    let t2 = c1.square() - c0 * c2;
+    let denom = c0 * t0 + C::XI * (c2 * t1 + c1 * t2);
+    let denom_inv = denom.inverse();
+    [t0 * denom_inv, t1 * denom_inv, t2 * denom_inv]
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2_field::field_types::Field;
+
+    use crate::curve::bls12_381::Bls12_381;
+    use crate::curve::pairing::{Fp12, Fp2};
+
+    fn rand_fp12() -> Fp12<Bls12_381> {
+        Fp12([(); 6].map(|_| Fp2([Field::rand(), Field::rand()])))
+    }
+
+    #[test]
+    fn test_fp12_inverse() {
+        let f = rand_fp12();
+        assert_eq!(f * f.inverse(), Fp12::ONE);
+    }
+
+    #[test]
+    fn test_fp12_frobenius() {
+        let f = rand_fp12();
+        assert_eq!(f.frobenius(6), f.conj());
+        assert_eq!(f.frobenius(1).frobenius(1), f.frobenius(2));
+        assert_eq!(f.frobenius(12), f);
+    }
+}
@@ -24,7 +24,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
 
     pub fn fri_challenges<C: GenericConfig<D, F = F>, const D: usize>(
         &mut self,
-        commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
+        commit_phase_merkle_caps: &[MerkleCap<F, C::CommitPhaseHasher>],
         final_poly: &PolynomialCoeffs<F::Extension>,
         pow_witness: F,
         degree_bits: usize,
@@ -36,9 +36,11 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         let num_fri_queries = config.num_query_rounds;
         let lde_size = 1 << (degree_bits + config.rate_bits);
         // Scaling factor to combine polynomials.
+        self.set_label("fri_alpha");
         let fri_alpha = self.get_extension_challenge::<D>();
 
         // Recover the random betas used in the FRI reductions.
+        self.set_label("fri_commit_phase");
         let fri_betas = commit_phase_merkle_caps
             .iter()
             .map(|cap| {
@@ -47,8 +49,10 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             })
             .collect();
 
+        self.set_label("fri_final_poly");
         self.observe_extension_elements(&final_poly.coeffs);
 
+        self.set_label("fri_pow");
         let fri_pow_response = C::InnerHasher::hash_no_pad(
             &self
                 .get_hash()
@@ -60,6 +64,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         )
         .elements[0];
 
+        self.set_label("fri_query_indices");
         let fri_query_indices = (0..num_fri_queries)
             .map(|_| self.get_challenge().to_canonical_u64() as usize % lde_size)
             .collect();
@@ -92,9 +97,11 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
     ) -> FriChallengesTarget<D> {
         let num_fri_queries = inner_fri_config.num_query_rounds;
         // Scaling factor to combine polynomials.
+        self.set_label("fri_alpha");
         let fri_alpha = self.get_extension_challenge(builder);
 
         // Recover the random betas used in the FRI reductions.
+        self.set_label("fri_commit_phase");
         let fri_betas = commit_phase_merkle_caps
             .iter()
             .map(|cap| {
@@ -103,8 +110,10 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
             })
             .collect();
 
+        self.set_label("fri_final_poly");
         self.observe_extension_elements(&final_poly.0);
 
+        self.set_label("fri_pow");
         let pow_inputs = self
             .get_hash(builder)
             .elements
@@ -116,6 +125,7 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
             .hash_n_to_hash_no_pad::<C::InnerHasher>(pow_inputs)
             .elements[0];
 
+        self.set_label("fri_query_indices");
         let fri_query_indices = (0..num_fri_queries)
             .map(|_| self.get_challenge(builder))
             .collect();
@@ -0,0 +1,244 @@
+//! The prover's FFTs, which can be offloaded to an accelerator such as a GPU by implementing
+//! `FftBackend`.
+//!
+//! A backend performs every FFT of the Plonk prover: the interpolations and low-degree extensions
+//! of committed polynomials, the interpolation of the quotient polynomials from their values on a
+//! coset, and FRI's transforms of its folded polynomials, in the commit phase and for the final
+//! polynomial. FRI's polynomials have coefficients in an extension of `F`, but the roots of unity
+//! and shifts lie in `F`, so each of their transforms is given to the backend as a batch of `D`
+//! transforms, one for each component of the coefficients.
+
+use std::fmt::Debug;
+
+use plonky2_field::extension_field::{Extendable, FieldExtension};
+use plonky2_field::fft::{ifft_with_options, FftRootTable, FftTables};
+use plonky2_field::field_types::Field;
+use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use plonky2_util::log2_strict;
+
+use crate::util::maybe_rayon::*;
+
+/// A backend performing the batches of interpolations and low-degree extensions needed to commit
+/// to polynomials.
+pub trait FftBackend<F: Field>: 'static + Send + Sync + Debug {
+    /// Interpolates each of `values` on the subgroup of order its length, which is a power of two.
+    fn batch_ifft(&self, values: Vec<PolynomialValues<F>>) -> Vec<PolynomialCoeffs<F>>;
+
+    /// Evaluates each of `polys`, which all have the same power-of-two length `n`, on the coset
+    /// `shift * H` of the subgroup `H` of order `n << rate_bits`, in the order of the powers of the
+    /// generator of `H`.
+    fn batch_coset_lde(
+        &self,
+        polys: &[PolynomialCoeffs<F>],
+        rate_bits: usize,
+        shift: F,
+    ) -> Vec<Vec<F>>;
+
+    /// Interpolates each of `values` on the coset `shift * H` of the subgroup `H` of order its
+    /// length.
+    fn batch_coset_ifft(
+        &self,
+        values: Vec<PolynomialValues<F>>,
+        shift: F,
+    ) -> Vec<PolynomialCoeffs<F>> {
+        let shift_inv = shift.inverse();
+        let mut polys = self.batch_ifft(values);
+        for poly in &mut polys {
+            for (c, r) in poly.coeffs.iter_mut().zip(shift_inv.powers()) {
+                *c *= r;
+            }
+        }
+        polys
+    }
+}
+
+/// Like `FftBackend::batch_coset_lde` for a single polynomial with coefficients in an extension of
+/// `F`, whose components are transformed by `backend` as one batch.
+pub(crate) fn coset_lde_extension<F: Extendable<D>, const D: usize>(
+    backend: &dyn FftBackend<F>,
+    poly: &PolynomialCoeffs<F::Extension>,
+    rate_bits: usize,
+    shift: F,
+) -> PolynomialValues<F::Extension> {
+    let components = (0..D)
+        .map(|j| {
+            PolynomialCoeffs::new(
+                poly.coeffs
+                    .iter()
+                    .map(|c| c.to_basefield_array()[j])
+                    .collect(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let component_values = backend.batch_coset_lde(&components, rate_bits, shift);
+    let values = (0..poly.len() << rate_bits)
+        .map(|i| {
+            let mut arr = [F::ZERO; D];
+            for (x, values) in arr.iter_mut().zip(&component_values) {
+                *x = values[i];
+            }
+            F::Extension::from_basefield_array(arr)
+        })
+        .collect();
+    PolynomialValues::new(values)
+}
+
+/// Performs FFTs on the CPU, in parallel, optionally reusing a precomputed root table.
+#[derive(Clone, Debug, Default)]
+pub struct CpuFftBackend<F: Field> {
+    root_table: Option<FftRootTable<F>>,
+}
+
+impl<F: Field> CpuFftBackend<F> {
+    pub fn new() -> Self {
+        Self { root_table: None }
+    }
+
+    /// A backend using `root_table` for transforms small enough for it.
+    pub fn with_root_table(root_table: FftRootTable<F>) -> Self {
+        Self {
+            root_table: Some(root_table),
+        }
+    }
+
+    /// The root table, if it covers transforms of size `2^lg_n`.
+    fn root_table(&self, lg_n: usize) -> Option<&FftRootTable<F>> {
+        self.root_table.as_ref().filter(|table| table.len() >= lg_n)
+    }
+}
+
+impl<F: Field> FftBackend<F> for CpuFftBackend<F> {
+    fn batch_ifft(&self, values: Vec<PolynomialValues<F>>) -> Vec<PolynomialCoeffs<F>> {
+        values
+            .into_par_iter()
+            .map(|v| {
+                let root_table = self.root_table(log2_strict(v.len()));
+                ifft_with_options(v, None, root_table)
+            })
+            .collect()
+    }
+
+    fn batch_coset_lde(
+        &self,
+        polys: &[PolynomialCoeffs<F>],
+        rate_bits: usize,
+        shift: F,
+    ) -> Vec<Vec<F>> {
+        let first = match polys.first() {
+            Some(first) => first,
+            None => return Vec::new(),
+        };
+        let lg_n = log2_strict(first.len()) + rate_bits;
+        let tables = FftTables::coset_with_root_table(lg_n, shift, self.root_table(lg_n));
+        polys
+            .par_iter()
+            .map(|p| {
+                assert_eq!(p.len(), first.len(), "Polynomial degrees inconsistent");
+                tables.coset_fft(p.lde(rate_bits), Some(rate_bits)).values
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use plonky2_field::extension_field::Extendable;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
+
+    use crate::fri::fft_backend::{coset_lde_extension, CpuFftBackend, FftBackend};
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Counts the batches it is given, and transforms them naively, like a simple accelerator
+    /// driver would.
+    #[derive(Debug, Default)]
+    struct CountingBackend {
+        batches: Arc<AtomicUsize>,
+    }
+
+    impl FftBackend<F> for CountingBackend {
+        fn batch_ifft(&self, values: Vec<PolynomialValues<F>>) -> Vec<PolynomialCoeffs<F>> {
+            self.batches.fetch_add(1, Ordering::Relaxed);
+            values.into_iter().map(|v| v.ifft()).collect()
+        }
+
+        fn batch_coset_lde(
+            &self,
+            polys: &[PolynomialCoeffs<F>],
+            rate_bits: usize,
+            shift: F,
+        ) -> Vec<Vec<F>> {
+            self.batches.fetch_add(1, Ordering::Relaxed);
+            polys
+                .iter()
+                .map(|p| p.lde(rate_bits).coset_fft(shift).values)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_cpu_fft_backend() {
+        let shift = F::coset_shift();
+        let polys = (0..3)
+            .map(|_| PolynomialCoeffs::new(F::rand_vec(16)))
+            .collect::<Vec<_>>();
+        let expected = CountingBackend::default().batch_coset_lde(&polys, 2, shift);
+        assert_eq!(
+            CpuFftBackend::new().batch_coset_lde(&polys, 2, shift),
+            expected
+        );
+
+        let values = polys.iter().map(|p| p.clone().fft()).collect::<Vec<_>>();
+        assert_eq!(CpuFftBackend::new().batch_ifft(values), polys);
+
+        let coset_values = polys.iter().map(|p| p.coset_fft(shift)).collect::<Vec<_>>();
+        assert_eq!(
+            CpuFftBackend::new().batch_coset_ifft(coset_values, shift),
+            polys
+        );
+    }
+
+    #[test]
+    fn test_coset_lde_extension() {
+        let shift = F::coset_shift();
+        let poly = PolynomialCoeffs::new(<F as Extendable<D>>::Extension::rand_vec(16));
+        assert_eq!(
+            coset_lde_extension::<F, D>(&CpuFftBackend::new(), &poly, 2, shift),
+            poly.lde(2).coset_fft(shift.into())
+        );
+    }
+
+    #[test]
+    fn test_custom_fft_backend() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let mut data = builder.build::<C>();
+
+        let batches = Arc::new(AtomicUsize::new(0));
+        data.set_fft_backend(Box::new(CountingBackend {
+            batches: batches.clone(),
+        }));
+        let proof = data.prove(PartialWitness::new())?;
+        // An IFFT and an LDE for the wires and for the partial products, a coset IFFT and an LDE
+        // for the quotient, and in FRI, an FFT for each folding round and one for the final
+        // polynomial.
+        let num_fri_rounds = data.common.fri_params.reduction_arity_bits.len();
+        assert_eq!(batches.load(Ordering::Relaxed), 7 + num_fri_rounds);
+        data.verify(proof)
+    }
+}
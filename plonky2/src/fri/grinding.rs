@@ -0,0 +1,250 @@
+//! The search for FRI proof-of-work witnesses, which can be offloaded to an accelerator such as a
+//! GPU by implementing `PowGrinder`.
+
+use std::fmt::Debug;
+
+use itertools::Itertools;
+
+use crate::hash::hash_types::{BytesHash, RichField};
+use crate::hash::hashing::{PlonkyPermutation, SPONGE_RATE, SPONGE_WIDTH};
+use crate::hash::keccak::KeccakHash;
+use crate::hash::keccak_batch::{keccak256_batch, KECCAK_LANES};
+use crate::hash::poseidon::{PoseidonHash, PoseidonPermutation};
+use crate::plonk::config::{GenericHashOut, Hasher};
+use crate::util::maybe_rayon::*;
+
+/// A backend searching for proof-of-work witnesses for the hash function `H`.
+pub trait PowGrinder<F: RichField, H: Hasher<F>>: Send + Sync + Debug {
+    /// Returns a witness `w` such that the first element of `H::hash_no_pad(prefix || [w])`, as a
+    /// canonical `u64`, has at least `leading_zeros` leading zeros.
+    fn grind(&self, prefix: &[F], leading_zeros: u32) -> F;
+}
+
+/// Searches for witnesses on the CPU, in parallel.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CpuGrinder;
+
+impl<F: RichField, H: Hasher<F>> PowGrinder<F, H> for CpuGrinder {
+    fn grind(&self, prefix: &[F], leading_zeros: u32) -> F {
+        (0..=F::NEG_ONE.to_canonical_u64())
+            .into_par_iter()
+            .find_any(|&i| {
+                pow_response::<F, H>(prefix, F::from_canonical_u64(i)).leading_zeros()
+                    >= leading_zeros
+            })
+            .map(F::from_canonical_u64)
+            .expect("Proof of work failed. This is highly unlikely!")
+    }
+}
+
+/// Searches for witnesses on the CPU, in parallel, but always returns the smallest one, so that
+/// proofs are reproducible. This can be slower than `CpuGrinder`, which returns whichever witness
+/// it finds first.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DeterministicCpuGrinder;
+
+impl<F: RichField, H: Hasher<F>> PowGrinder<F, H> for DeterministicCpuGrinder {
+    fn grind(&self, prefix: &[F], leading_zeros: u32) -> F {
+        (0..=F::NEG_ONE.to_canonical_u64())
+            .into_par_iter()
+            .find_first(|&i| {
+                pow_response::<F, H>(prefix, F::from_canonical_u64(i)).leading_zeros()
+                    >= leading_zeros
+            })
+            .map(F::from_canonical_u64)
+            .expect("Proof of work failed. This is highly unlikely!")
+    }
+}
+
+/// A kernel for Poseidon, which absorbs the prefix once, so that each candidate costs a single
+/// permutation.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PoseidonGrinder;
+
+impl<F: RichField> PowGrinder<F, PoseidonHash> for PoseidonGrinder {
+    fn grind(&self, prefix: &[F], leading_zeros: u32) -> F {
+        // The witness is absorbed with the last, partial chunk of the prefix, so the sponge state
+        // before that chunk is the same for every candidate.
+        let (full_chunks, tail) = prefix.split_at(prefix.len() / SPONGE_RATE * SPONGE_RATE);
+        let mut state = [F::ZERO; SPONGE_WIDTH];
+        for chunk in full_chunks.chunks(SPONGE_RATE) {
+            state[..SPONGE_RATE].copy_from_slice(chunk);
+            state = PoseidonPermutation::permute(state);
+        }
+        state[..tail.len()].copy_from_slice(tail);
+
+        (0..=F::NEG_ONE.to_canonical_u64())
+            .into_par_iter()
+            .find_any(|&i| {
+                let mut state = state;
+                state[tail.len()] = F::from_canonical_u64(i);
+                PoseidonPermutation::permute(state)[0]
+                    .to_canonical_u64()
+                    .leading_zeros()
+                    >= leading_zeros
+            })
+            .map(F::from_canonical_u64)
+            .expect("Proof of work failed. This is highly unlikely!")
+    }
+}
+
+/// A kernel for Keccak, which hashes `KECCAK_LANES` candidates at once with `keccak256_batch`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct KeccakGrinder;
+
+impl<F: RichField, const N: usize> PowGrinder<F, KeccakHash<N>> for KeccakGrinder {
+    fn grind(&self, prefix: &[F], leading_zeros: u32) -> F {
+        let lanes = KECCAK_LANES as u64;
+        let candidates =
+            |batch: u64| (batch * lanes..(batch + 1) * lanes).map(F::from_canonical_u64);
+        let batch = (0..F::NEG_ONE.to_canonical_u64() / lanes)
+            .into_par_iter()
+            .find_any(|&batch| {
+                let inputs = candidates(batch)
+                    .map(|w| prefix.iter().copied().chain(Some(w)).collect_vec())
+                    .collect_vec();
+                keccak256_batch::<F, KECCAK_LANES>(&inputs)
+                    .iter()
+                    .any(|hash| {
+                        let mut arr = [0; N];
+                        arr.copy_from_slice(&hash[..N]);
+                        let response = GenericHashOut::<F>::to_vec(&BytesHash(arr))[0];
+                        response.to_canonical_u64().leading_zeros() >= leading_zeros
+                    })
+            })
+            .expect("Proof of work failed. This is highly unlikely!");
+        candidates(batch)
+            .find(|&w| pow_response::<F, KeccakHash<N>>(prefix, w).leading_zeros() >= leading_zeros)
+            .unwrap()
+    }
+}
+
+/// The first element of `H::hash_no_pad(prefix || [witness])`, as a canonical `u64`.
+pub(crate) fn pow_response<F: RichField, H: Hasher<F>>(prefix: &[F], witness: F) -> u64 {
+    let hash = H::hash_no_pad(&prefix.iter().copied().chain(Some(witness)).collect_vec());
+    GenericHashOut::<F>::to_vec(&hash)[0].to_canonical_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use plonky2_field::field_types::{Field, PrimeField64};
+
+    use crate::fri::grinding::{
+        pow_response, CpuGrinder, DeterministicCpuGrinder, KeccakGrinder, PoseidonGrinder,
+        PowGrinder,
+    };
+    use crate::gates::noop::NoopGate;
+    use crate::hash::hash_types::RichField;
+    use crate::hash::keccak::KeccakHash;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::InnerHasher;
+
+    /// Searches sequentially from a given start, like a single accelerator kernel would.
+    #[derive(Debug, Default)]
+    struct SequentialGrinder {
+        start: u64,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl<F: RichField, H: Hasher<F>> PowGrinder<F, H> for SequentialGrinder {
+        fn grind(&self, prefix: &[F], leading_zeros: u32) -> F {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            (self.start..)
+                .map(F::from_canonical_u64)
+                .find(|&w| pow_response::<F, H>(prefix, w).leading_zeros() >= leading_zeros)
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn test_cpu_grinder() {
+        let prefix = F::rand_vec(4);
+        let w = PowGrinder::<F, H>::grind(&CpuGrinder, &prefix, 8);
+        assert!(pow_response::<F, H>(&prefix, w).leading_zeros() >= 8);
+    }
+
+    #[test]
+    fn test_deterministic_cpu_grinder() {
+        let prefix = F::rand_vec(4);
+        let w = PowGrinder::<F, H>::grind(&DeterministicCpuGrinder, &prefix, 8);
+        let smallest = (0..)
+            .map(F::from_canonical_u64)
+            .find(|&w| pow_response::<F, H>(&prefix, w).leading_zeros() >= 8)
+            .unwrap();
+        assert_eq!(w, smallest);
+    }
+
+    #[test]
+    fn test_poseidon_grinder() {
+        // Include prefixes on either side of a chunk boundary.
+        for len in [0, 3, 7, 8, 12] {
+            let prefix = F::rand_vec(len);
+            let w = PowGrinder::<F, PoseidonHash>::grind(&PoseidonGrinder, &prefix, 8);
+            assert!(pow_response::<F, PoseidonHash>(&prefix, w).leading_zeros() >= 8);
+        }
+    }
+
+    #[test]
+    fn test_keccak_grinder() {
+        // Responses have 56 bits, so 8 leading zeros come for free.
+        for len in [0, 5, 16, 17] {
+            let prefix = F::rand_vec(len);
+            let w = PowGrinder::<F, KeccakHash<25>>::grind(&KeccakGrinder, &prefix, 16);
+            assert!(pow_response::<F, KeccakHash<25>>(&prefix, w).leading_zeros() >= 16);
+        }
+    }
+
+    #[test]
+    fn test_custom_grinder() -> Result<()> {
+        // Grind fewer bits to keep the test fast, making up the security with two more queries.
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.proof_of_work_bits = 12;
+        config.fri_config.num_query_rounds += 2;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let mut data = builder.build::<C>();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        data.set_pow_grinder(Box::new(SequentialGrinder {
+            start: 1 << 40,
+            calls: calls.clone(),
+        }));
+        let proof = data.prove(PartialWitness::new())?;
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(proof.proof.opening_proof.pow_witness.to_canonical_u64() >= 1 << 40);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_invalid_grinder() {
+        #[derive(Debug)]
+        struct ZeroGrinder;
+        impl<F: RichField, H: Hasher<F>> PowGrinder<F, H> for ZeroGrinder {
+            fn grind(&self, _prefix: &[F], _leading_zeros: u32) -> F {
+                F::ZERO
+            }
+        }
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.proof_of_work_bits = 30;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        builder.add_gate(NoopGate, vec![]);
+        let mut data = builder.build::<C>();
+        data.set_pow_grinder(Box::new(ZeroGrinder));
+        assert!(data.prove(PartialWitness::new()).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Caching of low-degree extensions across commitments, for proving the same circuit repeatedly
+//! with mostly unchanged witnesses.
+
+use itertools::izip;
+use plonky2_field::field_types::Field;
+use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
+
+use crate::fri::fft_backend::FftBackend;
+
+/// The values, coefficients and LDE of each polynomial of the last batch committed to with this
+/// cache. A commitment only recomputes the polynomials whose values changed, which are found by
+/// comparing them with the cached values.
+///
+/// A cache should only be used for one oracle of one circuit; its entries are matched by the
+/// position of each polynomial in the batch.
+#[derive(Debug, Default)]
+pub struct LdeCache<F: Field> {
+    rate_bits: usize,
+    columns: Vec<CachedColumn<F>>,
+    num_reused: usize,
+}
+
+#[derive(Debug)]
+struct CachedColumn<F: Field> {
+    values: PolynomialValues<F>,
+    coeffs: PolynomialCoeffs<F>,
+    lde: Vec<F>,
+}
+
+impl<F: Field> LdeCache<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of polynomials whose LDEs were reused by the last commitment.
+    pub fn num_reused(&self) -> usize {
+        self.num_reused
+    }
+
+    pub fn clear(&mut self) {
+        self.columns.clear();
+        self.num_reused = 0;
+    }
+
+    /// The coefficients and LDEs of the polynomials interpolating `values`, computing with
+    /// `fft_backend` only those which aren't cached. The LDEs are lent from the cache; the
+    /// coefficients are copied, since the commitment keeps them.
+    pub(crate) fn ldes(
+        &mut self,
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        fft_backend: &dyn FftBackend<F>,
+    ) -> (Vec<PolynomialCoeffs<F>>, Vec<&[F]>) {
+        if rate_bits != self.rate_bits {
+            self.columns.clear();
+            self.rate_bits = rate_bits;
+        }
+        let mut old_columns = std::mem::take(&mut self.columns)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        old_columns.resize_with(values.len(), || None);
+
+        let mut columns = Vec::with_capacity(values.len());
+        let (mut changed_indices, mut changed_values) = (Vec::new(), Vec::new());
+        for (i, (values, old)) in values.into_iter().zip(old_columns).enumerate() {
+            match old {
+                Some(column) if column.values == values => columns.push(Some(column)),
+                _ => {
+                    columns.push(None);
+                    changed_indices.push(i);
+                    changed_values.push(values);
+                }
+            }
+        }
+        self.num_reused = columns.len() - changed_indices.len();
+
+        // Recompute the changed polynomials in one batch.
+        if !changed_values.is_empty() {
+            let coeffs = fft_backend.batch_ifft(changed_values.clone());
+            let ldes = fft_backend.batch_coset_lde(&coeffs, rate_bits, F::coset_shift());
+            for (i, values, coeffs, lde) in izip!(changed_indices, changed_values, coeffs, ldes) {
+                columns[i] = Some(CachedColumn {
+                    values,
+                    coeffs,
+                    lde,
+                });
+            }
+        }
+
+        self.columns = columns.into_iter().map(Option::unwrap).collect();
+        self.columns
+            .iter()
+            .map(|column| (column.coeffs.clone(), column.lde.as_slice()))
+            .unzip()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
+
+    use crate::fri::fft_backend::CpuFftBackend;
+    use crate::fri::lde_cache::LdeCache;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_lde_cache() {
+        let rate_bits = 2;
+        let backend = CpuFftBackend::new();
+        let lde = |p: &PolynomialCoeffs<F>| p.lde(rate_bits).coset_fft(F::coset_shift()).values;
+        let mut values = (0..4)
+            .map(|_| PolynomialValues::new(F::rand_vec(16)))
+            .collect::<Vec<_>>();
+
+        let mut cache = LdeCache::new();
+        cache.ldes(values.clone(), rate_bits, &backend);
+        assert_eq!(cache.num_reused(), 0);
+
+        values[2].values[5] = F::ONE;
+        let (coeffs, ldes) = cache.ldes(values.clone(), rate_bits, &backend);
+        for (i, v) in values.into_iter().enumerate() {
+            let expected_coeffs = v.ifft();
+            assert_eq!(ldes[i], lde(&expected_coeffs));
+            assert_eq!(coeffs[i], expected_coeffs);
+        }
+        assert_eq!(cache.num_reused(), 3);
+    }
+
+    #[test]
+    fn test_prove_incremental() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let num_wires = config.num_wires;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let mut acc = x;
+        for _ in 0..100 {
+            acc = builder.mul_add(acc, acc, x);
+        }
+        builder.mul(y, y);
+        let data = builder.build::<C>();
+
+        let mut cache = LdeCache::new();
+        for (i, y_value) in [3, 4, 5].into_iter().enumerate() {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::TWO);
+            pw.set_target(y, F::from_canonical_u64(y_value));
+            let proof = data.prove_incremental(pw, &mut cache)?;
+            if i == 0 {
+                assert_eq!(cache.num_reused(), 0);
+            } else {
+                // Only the wires of the gate computing `y * y` changed.
+                assert!(cache.num_reused() > 0 && cache.num_reused() < num_wires);
+            }
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+}
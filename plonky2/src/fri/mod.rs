@@ -1,7 +1,15 @@
+use anyhow::{ensure, Result};
+
 use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::fri::structure::FriOracleInfo;
 
 mod challenges;
+pub mod fft_backend;
+pub mod grinding;
+pub mod lde_cache;
 pub mod oracle;
+pub mod pcs;
+pub mod presets;
 pub mod proof;
 pub mod prover;
 pub mod recursive_verifier;
@@ -15,9 +23,13 @@ pub struct FriConfig {
     /// `rate = 2^{-rate_bits}`.
     pub rate_bits: usize,
 
-    /// Height of Merkle tree caps.
+    /// Height of the Merkle tree caps of the initial (committed polynomial) trees.
     pub cap_height: usize,
 
+    /// Height of the Merkle tree caps of the trees built during the FRI commit phase. These trees
+    /// are smaller than the initial trees, so a smaller cap is often a better tradeoff.
+    pub commit_phase_cap_height: usize,
+
     pub proof_of_work_bits: u32,
 
     pub reduction_strategy: FriReductionStrategy,
@@ -31,21 +43,47 @@ impl FriConfig {
         1.0 / ((1 << self.rate_bits) as f64)
     }
 
-    pub fn fri_params(&self, degree_bits: usize, hiding: bool) -> FriParams {
+    /// The conjectured security of the FRI queries, in bits; see the ethSTARK paper.
+    pub fn conjectured_security_bits(&self) -> usize {
+        self.num_query_rounds * self.rate_bits + self.proof_of_work_bits as usize
+    }
+
+    pub fn fri_params(
+        &self,
+        degree_bits: usize,
+        salt_size: usize,
+        salt_mode: SaltMode,
+    ) -> FriParams {
         let reduction_arity_bits = self.reduction_strategy.reduction_arity_bits(
             degree_bits,
             self.rate_bits,
+            self.commit_phase_cap_height,
             self.num_query_rounds,
         );
         FriParams {
             config: self.clone(),
-            hiding,
+            salt_size,
+            salt_mode,
             degree_bits,
             reduction_arity_bits,
         }
     }
 }
 
+/// How the leaves of blinding oracles are salted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SaltMode {
+    /// Each leaf is salted with `salt_size` independent random elements.
+    PerLeaf,
+    /// Each blinding oracle commits to one extra polynomial over `F::Extension`, chosen at random
+    /// with the same degree as the oracle's polynomials, whose `D` base field coordinates salt the
+    /// leaves, so that `salt_size` must be `D`. These masking polynomials are also added to the
+    /// reduced polynomial of FRI, which makes every FRI layer uniformly random rather than only
+    /// the initial Merkle leaves. This requires the degree to exceed the number of points at which
+    /// a masking polynomial is revealed, i.e. the number of FRI queries.
+    Masked,
+}
+
 /// FRI parameters, including generated parameters which are specific to an instance size, in
 /// contrast to `FriConfig` which is user-specified and independent of instance size.
 #[derive(Debug)]
@@ -53,8 +91,12 @@ pub struct FriParams {
     /// User-specified FRI configuration.
     pub config: FriConfig,
 
-    /// Whether to use a hiding variant of Merkle trees (where random salts are added to leaves).
-    pub hiding: bool,
+    /// The number of random salt elements added to the leaves of blinding oracles, to make their
+    /// Merkle trees hiding. Zero if no oracle is hiding.
+    pub salt_size: usize,
+
+    /// How the salt of blinding oracles is generated.
+    pub salt_mode: SaltMode,
 
     /// The degree of the purported codeword, measured in bits.
     pub degree_bits: usize,
@@ -75,14 +117,53 @@ impl FriParams {
         self.reduction_arity_bits.iter().copied().max()
     }
 
+    /// The number of salt elements at the end of each leaf of the given oracle.
+    pub(crate) fn oracle_salt_size(&self, oracle: FriOracleInfo) -> usize {
+        if oracle.blinding {
+            self.salt_size
+        } else {
+            0
+        }
+    }
+
+    /// The indices of the oracles whose masking polynomials are added to the reduced polynomial,
+    /// in order.
+    pub(crate) fn masked_oracles(&self, oracles: &[FriOracleInfo]) -> Vec<usize> {
+        if self.salt_mode != SaltMode::Masked || self.salt_size == 0 {
+            return Vec::new();
+        }
+        (0..oracles.len())
+            .filter(|&i| oracles[i].blinding)
+            .collect()
+    }
+
     pub fn lde_bits(&self) -> usize {
         self.degree_bits + self.config.rate_bits
     }
 
+    /// Checks that the salt size suits the salt mode, for the extension of degree `d` used by FRI.
+    /// A masking polynomial has `d` coordinates, so `SaltMode::Masked` needs a salt size of `d`, or
+    /// zero if nothing is salted.
+    pub fn check_salt_size(&self, d: usize) -> Result<()> {
+        ensure!(
+            self.salt_mode != SaltMode::Masked || self.salt_size == 0 || self.salt_size == d,
+            "masked salting needs a salt size of {}, the extension degree, but it is {}",
+            d,
+            self.salt_size
+        );
+        Ok(())
+    }
+
     pub fn lde_size(&self) -> usize {
         1 << self.lde_bits()
     }
 
+    /// The log of the size of the LDE which the given oracle is committed to, i.e. the height of
+    /// its Merkle tree.
+    pub fn oracle_lde_bits(&self, oracle: FriOracleInfo) -> usize {
+        self.lde_bits() + oracle.extra_rate_bits
+    }
+
     pub fn final_poly_bits(&self) -> usize {
         self.degree_bits - self.total_arities()
     }
@@ -1,25 +1,34 @@
-use plonky2_field::extension_field::Extendable;
-use plonky2_field::fft::FftRootTable;
+use anyhow::Result;
+use plonky2_field::extension_field::{Extendable, FieldExtension};
 use plonky2_field::field_types::Field;
 use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use plonky2_util::{log2_strict, reverse_index_bits_in_place};
-use rayon::prelude::*;
+use rand::{thread_rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
+use crate::fri::fft_backend::{coset_lde_extension, FftBackend};
+use crate::fri::grinding::PowGrinder;
+use crate::fri::lde_cache::LdeCache;
 use crate::fri::proof::FriProof;
 use crate::fri::prover::fri_proof;
 use crate::fri::structure::{FriBatchInfo, FriInstanceInfo};
-use crate::fri::FriParams;
+use crate::fri::{FriParams, SaltMode};
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_tree::MerkleTree;
 use crate::iop::challenger::Challenger;
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::timed;
+use crate::util::maybe_rayon::*;
+use crate::util::progress::ProvingMonitor;
 use crate::util::reducing::ReducingFactor;
 use crate::util::reverse_bits;
+use crate::util::serialization::ProofStream;
 use crate::util::timing::TimingTree;
 use crate::util::transpose;
+use crate::util::zeroize::zeroize_vecs;
 
-/// Four (~64 bit) field elements gives ~128 bit security.
+/// The default number of salt elements for blinding oracles. Four (~64 bit) field elements gives
+/// ~128 bit security.
 pub const SALT_SIZE: usize = 4;
 
 /// Represents a FRI oracle, i.e. a batch of polynomials which have been Merklized.
@@ -29,7 +38,11 @@ pub struct PolynomialBatch<F: RichField + Extendable<D>, C: GenericConfig<D, F =
     pub merkle_tree: MerkleTree<F, C::Hasher>,
     pub degree_log: usize,
     pub rate_bits: usize,
-    pub blinding: bool,
+    /// The number of random salt elements appended to each leaf, which is zero unless the oracle
+    /// is blinding.
+    pub salt_size: usize,
+    /// The polynomial whose coordinates form the salt, in `SaltMode::Masked`.
+    pub masking_polynomial: Option<PolynomialCoeffs<F::Extension>>,
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
@@ -39,27 +52,93 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     pub fn from_values(
         values: Vec<PolynomialValues<F>>,
         rate_bits: usize,
-        blinding: bool,
+        salt_size: usize,
+        salt_mode: SaltMode,
         cap_height: usize,
         timing: &mut TimingTree,
-        fft_root_table: Option<&FftRootTable<F>>,
+        monitor: &ProvingMonitor,
+        fft_backend: &dyn FftBackend<F>,
     ) -> Self
     where
         [(); C::Hasher::HASH_SIZE]:,
     {
-        let coeffs = timed!(
+        Self::from_values_with_rng(
+            values,
+            rate_bits,
+            salt_size,
+            salt_mode,
+            cap_height,
             timing,
-            "IFFT",
-            values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
-        );
+            monitor,
+            fft_backend,
+            &mut thread_rng(),
+        )
+    }
+
+    /// Like `from_values`, but samples any salt from `rng`.
+    pub fn from_values_with_rng(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        salt_size: usize,
+        salt_mode: SaltMode,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        monitor: &ProvingMonitor,
+        fft_backend: &dyn FftBackend<F>,
+        rng: &mut dyn RngCore,
+    ) -> Self
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        let coeffs = timed!(timing, "IFFT", fft_backend.batch_ifft(values));
 
-        Self::from_coeffs(
+        Self::from_coeffs_with_rng(
             coeffs,
             rate_bits,
-            blinding,
+            salt_size,
+            salt_mode,
+            cap_height,
+            timing,
+            monitor,
+            fft_backend,
+            rng,
+        )
+    }
+
+    /// Like `from_values_with_rng`, but reuses the LDEs of the polynomials whose values are
+    /// unchanged since the last commitment using `cache`. Salts are sampled afresh, and the Merkle
+    /// tree is rebuilt, since each of its leaves holds one value of every polynomial.
+    pub fn from_values_cached(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        salt_size: usize,
+        salt_mode: SaltMode,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        monitor: &ProvingMonitor,
+        fft_backend: &dyn FftBackend<F>,
+        rng: &mut dyn RngCore,
+        cache: &mut LdeCache<F>,
+    ) -> Self
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        let (polynomials, ldes) = timed!(
+            timing,
+            "IFFT + FFT of changed polynomials",
+            cache.ldes(values, rate_bits, fft_backend)
+        );
+        Self::from_ldes(
+            polynomials,
+            &ldes,
+            rate_bits,
+            salt_size,
+            salt_mode,
             cap_height,
             timing,
-            fft_root_table,
+            monitor,
+            fft_backend,
+            rng,
         )
     }
 
@@ -67,27 +146,125 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     pub fn from_coeffs(
         polynomials: Vec<PolynomialCoeffs<F>>,
         rate_bits: usize,
-        blinding: bool,
+        salt_size: usize,
+        salt_mode: SaltMode,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        monitor: &ProvingMonitor,
+        fft_backend: &dyn FftBackend<F>,
+    ) -> Self
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        Self::from_coeffs_with_rng(
+            polynomials,
+            rate_bits,
+            salt_size,
+            salt_mode,
+            cap_height,
+            timing,
+            monitor,
+            fft_backend,
+            &mut thread_rng(),
+        )
+    }
+
+    /// Like `from_coeffs`, but samples any salt from `rng`.
+    pub fn from_coeffs_with_rng(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        salt_size: usize,
+        salt_mode: SaltMode,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        monitor: &ProvingMonitor,
+        fft_backend: &dyn FftBackend<F>,
+        rng: &mut dyn RngCore,
+    ) -> Self
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        let mut ldes = timed!(
+            timing,
+            "FFT",
+            fft_backend.batch_coset_lde(&polynomials, rate_bits, F::coset_shift())
+        );
+        let batch = Self::from_ldes(
+            polynomials,
+            &ldes.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+            rate_bits,
+            salt_size,
+            salt_mode,
+            cap_height,
+            timing,
+            monitor,
+            fft_backend,
+            rng,
+        );
+        if cfg!(feature = "hardened") {
+            zeroize_vecs(&mut ldes);
+        }
+        batch
+    }
+
+    /// Commits to `polynomials` given their LDEs, appending the salt to each leaf.
+    fn from_ldes(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        lde_values: &[&[F]],
+        rate_bits: usize,
+        salt_size: usize,
+        salt_mode: SaltMode,
         cap_height: usize,
         timing: &mut TimingTree,
-        fft_root_table: Option<&FftRootTable<F>>,
+        monitor: &ProvingMonitor,
+        fft_backend: &dyn FftBackend<F>,
+        mut rng: &mut dyn RngCore,
     ) -> Self
     where
         [(); C::Hasher::HASH_SIZE]:,
     {
         let degree = polynomials[0].len();
-        let lde_values = timed!(
+        let masking_polynomial = match salt_mode {
+            SaltMode::Masked if salt_size > 0 => {
+                assert_eq!(
+                    salt_size, D,
+                    "The masking polynomial has D coordinates; see FriParams::check_salt_size"
+                );
+                Some(PolynomialCoeffs::new(
+                    (0..degree)
+                        .map(|_| F::Extension::rand_from_rng(&mut rng))
+                        .collect(),
+                ))
+            }
+            _ => None,
+        };
+        let mut salt_values = timed!(
             timing,
-            "FFT + blinding",
-            Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table)
+            "blinding",
+            Self::salt_values(
+                degree,
+                rate_bits,
+                salt_size,
+                masking_polynomial.as_ref(),
+                fft_backend,
+                rng
+            )
         );
+        let columns = lde_values
+            .iter()
+            .copied()
+            .chain(salt_values.iter().map(Vec::as_slice))
+            .collect::<Vec<_>>();
 
-        let mut leaves = timed!(timing, "transpose LDEs", transpose(&lde_values));
+        let mut leaves = timed!(timing, "transpose LDEs", transpose(&columns));
+        if cfg!(feature = "hardened") {
+            zeroize_vecs(&mut salt_values);
+        }
         reverse_index_bits_in_place(&mut leaves);
         let merkle_tree = timed!(
             timing,
             "build Merkle tree",
-            MerkleTree::new(leaves, cap_height)
+            MerkleTree::new_with_monitor(leaves, cap_height, monitor)
         );
 
         Self {
@@ -95,55 +272,83 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             merkle_tree,
             degree_log: log2_strict(degree),
             rate_bits,
-            blinding,
+            salt_size,
+            masking_polynomial,
         }
     }
 
-    fn lde_values(
-        polynomials: &[PolynomialCoeffs<F>],
+    /// The values of the salt columns appended to each leaf.
+    fn salt_values(
+        degree: usize,
         rate_bits: usize,
-        blinding: bool,
-        fft_root_table: Option<&FftRootTable<F>>,
+        salt_size: usize,
+        masking_polynomial: Option<&PolynomialCoeffs<F::Extension>>,
+        fft_backend: &dyn FftBackend<F>,
+        rng: &mut dyn RngCore,
     ) -> Vec<Vec<F>> {
-        let degree = polynomials[0].len();
-
-        // If blinding, salt with two random elements to each leaf vector.
-        let salt_size = if blinding { SALT_SIZE } else { 0 };
-
-        polynomials
-            .par_iter()
-            .map(|p| {
-                assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
-                p.lde(rate_bits)
-                    .coset_fft_with_options(F::coset_shift(), Some(rate_bits), fft_root_table)
-                    .values
-            })
-            .chain(
-                (0..salt_size)
+        match masking_polynomial {
+            // Salt each leaf vector with the coordinates of the masking polynomial.
+            Some(masking_polynomial) => {
+                let coordinates = (0..D)
+                    .map(|j| {
+                        PolynomialCoeffs::new(
+                            masking_polynomial
+                                .coeffs
+                                .iter()
+                                .map(|c| c.to_basefield_array()[j])
+                                .collect(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                fft_backend.batch_coset_lde(&coordinates, rate_bits, F::coset_shift())
+            }
+            // Salt each leaf vector with `salt_size` random elements. Each column is sampled from
+            // its own generator, seeded from `rng`, so that columns can be sampled in parallel.
+            None => {
+                let seeds = (0..salt_size)
+                    .map(|_| {
+                        let mut seed = [0; 32];
+                        rng.fill_bytes(&mut seed);
+                        seed
+                    })
+                    .collect::<Vec<_>>();
+                seeds
                     .into_par_iter()
-                    .map(|_| F::rand_vec(degree << rate_bits)),
-            )
-            .collect()
+                    .map(|seed| {
+                        let mut column_rng = ChaCha20Rng::from_seed(seed);
+                        (0..degree << rate_bits)
+                            .map(|_| F::rand_from_rng(&mut column_rng))
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
     }
 
     pub fn get_lde_values(&self, index: usize) -> &[F] {
         let index = reverse_bits(index, self.degree_log + self.rate_bits);
         let slice = &self.merkle_tree.leaves[index];
-        &slice[..slice.len() - if self.blinding { SALT_SIZE } else { 0 }]
+        &slice[..slice.len() - self.salt_size]
     }
 
-    /// Produces a batch opening proof.
+    /// Produces a batch opening proof. If `stream` is given, the proof is also written to it, as
+    /// in `fri_proof`. Fails if the proof is cancelled through `monitor`.
     pub fn prove_openings(
         instance: &FriInstanceInfo<F, D>,
         oracles: &[&Self],
         challenger: &mut Challenger<F, C::Hasher>,
         fri_params: &FriParams,
+        fft_backend: &dyn FftBackend<F>,
+        pow_grinder: &dyn PowGrinder<F, C::InnerHasher>,
         timing: &mut TimingTree,
-    ) -> FriProof<F, C::Hasher, D>
+        monitor: &ProvingMonitor,
+        stream: Option<&mut ProofStream>,
+    ) -> Result<FriProof<F, C::Hasher, C::CommitPhaseHasher, D>>
     where
-        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         assert!(D > 1, "Not implemented for D=1.");
+        challenger.set_label("fri_alpha");
         let alpha = challenger.get_extension_challenge::<D>();
         let mut alpha = ReducingFactor::new(alpha);
 
@@ -167,14 +372,32 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         // which the LDT will pass. See github.com/mir-protocol/plonky2/pull/436 for details.
         final_poly.coeffs.insert(0, F::Extension::ZERO);
 
-        let lde_final_poly = final_poly.lde(fri_params.config.rate_bits);
+        // Mask the final polynomial with the masking polynomials, which have the same degree.
+        let masking_polys = fri_params
+            .masked_oracles(&instance.oracles)
+            .into_iter()
+            .map(|i| {
+                oracles[i]
+                    .masking_polynomial
+                    .as_ref()
+                    .expect("Blinding oracle has no masking polynomial")
+            })
+            .collect::<Vec<_>>();
+        if !masking_polys.is_empty() {
+            let masking_poly = alpha.reduce_polys(masking_polys.into_iter());
+            alpha.shift_poly(&mut final_poly);
+            final_poly += masking_poly;
+        }
+
+        let rate_bits = fri_params.config.rate_bits;
         let lde_final_values = timed!(
             timing,
-            &format!("perform final FFT {}", lde_final_poly.len()),
-            lde_final_poly.coset_fft(F::coset_shift().into())
+            &format!("perform final FFT {}", final_poly.len() << rate_bits),
+            coset_lde_extension::<F, D>(fft_backend, &final_poly, rate_bits, F::coset_shift())
         );
+        let lde_final_poly = final_poly.lde(rate_bits);
 
-        let fri_proof = fri_proof::<F, C, D>(
+        fri_proof::<F, C, D>(
             &oracles
                 .par_iter()
                 .map(|c| &c.merkle_tree)
@@ -183,9 +406,11 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             lde_final_values,
             challenger,
             fri_params,
+            fft_backend,
+            pow_grinder,
             timing,
-        );
-
-        fri_proof
+            monitor,
+            stream,
+        )
     }
 }
@@ -0,0 +1,690 @@
+//! A standalone polynomial commitment scheme based on FRI, independent of Plonk's opening sets.
+//!
+//! Polynomials are committed to in batches, each batch being a Merkle tree of LDEs. A proof opens
+//! every polynomial of every batch at each of a list of points, which must not lie in the LDE
+//! coset. The committed polynomials of all batches must have the same degree `2^degree_bits`,
+//! where `degree_bits` is that of the `FriParams` used. Commitments are not hiding.
+//!
+//! The caps, points and opened values are observed by the challenger, so that `open` and `verify`
+//! must be given challengers in the same state.
+//!
+//! `FriPcs` exposes the underlying scheme, for arbitrary instances, through the
+//! `PolynomialCommitmentScheme` trait used by Plonk.
+
+use anyhow::{ensure, Result};
+use itertools::Itertools;
+use plonky2_field::extension_field::{Extendable, FieldExtension};
+use plonky2_field::field_types::Field;
+use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+
+use crate::fri::fft_backend::{CpuFftBackend, FftBackend};
+use crate::fri::grinding::{CpuGrinder, PowGrinder};
+use crate::fri::lde_cache::LdeCache;
+use crate::fri::oracle::PolynomialBatch;
+use crate::fri::proof::{
+    CompressedFriProof, FriChallenges, FriChallengesTarget, FriProof, FriProofTarget,
+};
+use crate::fri::structure::{
+    FriBatchInfo, FriInstanceInfo, FriInstanceInfoTarget, FriOpeningBatch, FriOpenings,
+    FriOpeningsTarget, FriOracleInfo, FriPolynomialInfo,
+};
+use crate::fri::verifier::{verify_compressed_fri_proof, verify_fri_proof};
+use crate::fri::FriParams;
+use crate::hash::hash_types::{MerkleCapTarget, RichField};
+use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::challenger::Challenger;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::plonk::pcs::{PcsProverContext, PolynomialCommitmentScheme};
+use crate::util::maybe_rayon::*;
+use crate::util::progress::ProvingMonitor;
+use crate::util::timing::TimingTree;
+
+/// Proof that the committed polynomials take the given values at the opening points.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(bound = "")]
+pub struct FriOpeningProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    /// The opened values, where `values[i][j][k]` is the value of the `k`th polynomial of the
+    /// `j`th commitment at the `i`th point.
+    pub values: Vec<Vec<Vec<F::Extension>>>,
+    /// A batch FRI argument for all openings.
+    pub opening_proof: FriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
+}
+
+/// The backends which the FRI prover runs on.
+pub struct FriProverParams<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    /// The backend performing the prover's FFTs. By default, this runs on the CPU.
+    pub fft_backend: Box<dyn FftBackend<F>>,
+    /// The backend searching for proof-of-work witnesses.
+    pub pow_grinder: Box<dyn PowGrinder<F, C::InnerHasher>>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Default
+    for FriProverParams<F, C, D>
+{
+    fn default() -> Self {
+        Self {
+            fft_backend: Box::new(CpuFftBackend::new()),
+            pow_grinder: Box::new(CpuGrinder),
+        }
+    }
+}
+
+/// FRI with Merkle commitments to the LDEs of the polynomials, as a polynomial commitment scheme.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FriPcs;
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    PolynomialCommitmentScheme<F, C, D> for FriPcs
+{
+    type Params = FriParams;
+    type ProverParams = FriProverParams<F, C, D>;
+    type ProverData = PolynomialBatch<F, C, D>;
+    type Cache = LdeCache<F>;
+    type Commitment = MerkleCap<F, C::Hasher>;
+    type CommitmentTarget = MerkleCapTarget;
+    type OpeningProof = FriProof<F, C::Hasher, C::CommitPhaseHasher, D>;
+    type OpeningProofTarget = FriProofTarget<D>;
+    type CompressedOpeningProof = CompressedFriProof<F, C::Hasher, C::CommitPhaseHasher, D>;
+    type Challenges = FriChallenges<F, D>;
+    type ChallengesTarget = FriChallengesTarget<D>;
+
+    fn commit(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        oracle: FriOracleInfo,
+        params: &FriParams,
+        prover_params: &FriProverParams<F, C, D>,
+        ctx: &mut PcsProverContext,
+    ) -> PolynomialBatch<F, C, D>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        PolynomialBatch::from_coeffs_with_rng(
+            polynomials,
+            params.config.rate_bits + oracle.extra_rate_bits,
+            params.oracle_salt_size(oracle),
+            params.salt_mode,
+            params.config.cap_height,
+            ctx.timing,
+            ctx.monitor,
+            prover_params.fft_backend.as_ref(),
+            ctx.rng,
+        )
+    }
+
+    fn commit_values(
+        values: Vec<PolynomialValues<F>>,
+        oracle: FriOracleInfo,
+        params: &FriParams,
+        prover_params: &FriProverParams<F, C, D>,
+        cache: Option<&mut LdeCache<F>>,
+        ctx: &mut PcsProverContext,
+    ) -> PolynomialBatch<F, C, D>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        let rate_bits = params.config.rate_bits + oracle.extra_rate_bits;
+        let salt_size = params.oracle_salt_size(oracle);
+        match cache {
+            Some(cache) => PolynomialBatch::from_values_cached(
+                values,
+                rate_bits,
+                salt_size,
+                params.salt_mode,
+                params.config.cap_height,
+                ctx.timing,
+                ctx.monitor,
+                prover_params.fft_backend.as_ref(),
+                ctx.rng,
+                cache,
+            ),
+            None => PolynomialBatch::from_values_with_rng(
+                values,
+                rate_bits,
+                salt_size,
+                params.salt_mode,
+                params.config.cap_height,
+                ctx.timing,
+                ctx.monitor,
+                prover_params.fft_backend.as_ref(),
+                ctx.rng,
+            ),
+        }
+    }
+
+    fn commitment(prover_data: &PolynomialBatch<F, C, D>) -> MerkleCap<F, C::Hasher> {
+        prover_data.merkle_tree.cap.clone()
+    }
+
+    fn open(
+        instance: &FriInstanceInfo<F, D>,
+        prover_data: &[&PolynomialBatch<F, C, D>],
+        challenger: &mut Challenger<F, C::Hasher>,
+        params: &FriParams,
+        prover_params: &FriProverParams<F, C, D>,
+        ctx: &mut PcsProverContext,
+    ) -> Result<FriProof<F, C::Hasher, C::CommitPhaseHasher, D>>
+    where
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        PolynomialBatch::prove_openings(
+            instance,
+            prover_data,
+            challenger,
+            params,
+            prover_params.fft_backend.as_ref(),
+            prover_params.pow_grinder.as_ref(),
+            ctx.timing,
+            ctx.monitor,
+            ctx.stream.as_deref_mut(),
+        )
+    }
+
+    fn verify(
+        instance: &FriInstanceInfo<F, D>,
+        openings: &FriOpenings<F, D>,
+        challenges: &FriChallenges<F, D>,
+        commitments: &[MerkleCap<F, C::Hasher>],
+        proof: &FriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
+        params: &FriParams,
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        verify_fri_proof::<F, C, D>(instance, openings, challenges, commitments, proof, params)
+    }
+
+    fn compress(
+        proof: FriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
+        challenges: &FriChallenges<F, D>,
+        params: &FriParams,
+    ) -> CompressedFriProof<F, C::Hasher, C::CommitPhaseHasher, D> {
+        proof.compress::<C>(&challenges.fri_query_indices, params)
+    }
+
+    fn verify_compressed(
+        instance: &FriInstanceInfo<F, D>,
+        openings: &FriOpenings<F, D>,
+        challenges: &FriChallenges<F, D>,
+        commitments: &[MerkleCap<F, C::Hasher>],
+        proof: &CompressedFriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
+        params: &FriParams,
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        verify_compressed_fri_proof::<F, C, D>(
+            instance,
+            openings,
+            challenges,
+            commitments,
+            proof,
+            params,
+        )
+    }
+
+    fn verify_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        instance: &FriInstanceInfoTarget<D>,
+        openings: &FriOpeningsTarget<D>,
+        challenges: &FriChallengesTarget<D>,
+        commitments: &[MerkleCapTarget],
+        proof: &FriProofTarget<D>,
+        params: &FriParams,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
+    {
+        builder.verify_fri_proof::<C>(instance, openings, challenges, commitments, proof, params);
+    }
+}
+
+/// Commits to a batch of polynomials with `2^params.degree_bits` coefficients each.
+pub fn commit<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    polynomials: Vec<PolynomialCoeffs<F>>,
+    params: &FriParams,
+    timing: &mut TimingTree,
+) -> PolynomialBatch<F, C, D>
+where
+    [(); C::Hasher::HASH_SIZE]:,
+{
+    assert!(!polynomials.is_empty(), "No polynomials to commit to");
+    assert!(
+        polynomials
+            .iter()
+            .all(|p| p.len() == 1 << params.degree_bits),
+        "Polynomial sizes don't match the FRI parameters"
+    );
+    let oracle = FriOracleInfo {
+        blinding: false,
+        extra_rate_bits: 0,
+    };
+    <FriPcs as PolynomialCommitmentScheme<F, C, D>>::commit(
+        polynomials,
+        oracle,
+        params,
+        &FriProverParams::default(),
+        &mut PcsProverContext {
+            timing,
+            monitor: &ProvingMonitor::default(),
+            rng: &mut thread_rng(),
+            stream: None,
+        },
+    )
+}
+
+/// Opens all the polynomials of `commitments` at each of `points`.
+pub fn open<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    commitments: &[&PolynomialBatch<F, C, D>],
+    points: &[F::Extension],
+    challenger: &mut Challenger<F, C::Hasher>,
+    params: &FriParams,
+    timing: &mut TimingTree,
+) -> FriOpeningProof<F, C, D>
+where
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
+{
+    assert!(
+        points.iter().all(|&x| !in_lde_coset::<F, D>(x, params)),
+        "Opening point is in the LDE coset."
+    );
+    let values = points
+        .iter()
+        .map(|&x| {
+            commitments
+                .iter()
+                .map(|c| {
+                    c.polynomials
+                        .par_iter()
+                        .map(|p| p.to_extension().eval(x))
+                        .collect()
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<Vec<_>>>>();
+
+    let caps = commitments
+        .iter()
+        .map(|c| c.merkle_tree.cap.clone())
+        .collect::<Vec<_>>();
+    let openings = fri_openings::<F, D>(&values);
+    observe_statement::<F, C, D>(challenger, &caps, points, &openings);
+
+    let num_polys = commitments
+        .iter()
+        .map(|c| c.polynomials.len())
+        .collect::<Vec<_>>();
+    let opening_proof = <FriPcs as PolynomialCommitmentScheme<F, C, D>>::open(
+        &fri_instance::<F, D>(&num_polys, points),
+        commitments,
+        challenger,
+        params,
+        &FriProverParams::default(),
+        &mut PcsProverContext {
+            timing,
+            monitor: &ProvingMonitor::default(),
+            rng: &mut thread_rng(),
+            stream: None,
+        },
+    )
+    .expect("The default monitor never cancels a proof");
+
+    FriOpeningProof {
+        values,
+        opening_proof,
+    }
+}
+
+/// Verifies that the polynomials committed to in `caps` take the values in `proof` at each of
+/// `points`.
+pub fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    caps: &[MerkleCap<F, C::Hasher>],
+    points: &[F::Extension],
+    proof: &FriOpeningProof<F, C, D>,
+    challenger: &mut Challenger<F, C::Hasher>,
+    params: &FriParams,
+) -> Result<()>
+where
+    [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
+{
+    let FriOpeningProof {
+        values,
+        opening_proof,
+    } = proof;
+    ensure!(
+        points.iter().all(|&x| !in_lde_coset::<F, D>(x, params)),
+        "Opening point is in the LDE coset."
+    );
+    ensure!(
+        values.len() == points.len(),
+        "Wrong number of opening points."
+    );
+    let num_polys = values
+        .first()
+        .map(|v| v.iter().map(|c| c.len()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    ensure!(
+        num_polys.len() == caps.len()
+            && values
+                .iter()
+                .all(|v| v.iter().map(|c| c.len()).eq(num_polys.iter().copied())),
+        "Opened values have inconsistent shapes."
+    );
+    // The FRI verifier indexes into the proof, so we check its shape first. In particular, the
+    // leaves must hold exactly the opened polynomials.
+    let num_reductions = params.reduction_arity_bits.len();
+    ensure!(
+        opening_proof.commit_phase_merkle_caps.len() == num_reductions,
+        "Wrong number of commit phase Merkle caps."
+    );
+    for round in &opening_proof.query_round_proofs {
+        ensure!(
+            round.steps.len() == num_reductions,
+            "Wrong number of query steps."
+        );
+        let evals_proofs = &round.initial_trees_proof.evals_proofs;
+        ensure!(
+            evals_proofs
+                .iter()
+                .map(|(evals, _)| evals.len())
+                .eq(num_polys.iter().copied()),
+            "Merkle leaves don't match the opened values."
+        );
+    }
+
+    let openings = fri_openings::<F, D>(values);
+    observe_statement::<F, C, D>(challenger, caps, points, &openings);
+    let challenges = challenger.fri_challenges::<C, D>(
+        &opening_proof.commit_phase_merkle_caps,
+        &opening_proof.final_poly,
+        opening_proof.pow_witness,
+        params.degree_bits,
+        &params.config,
+    );
+
+    <FriPcs as PolynomialCommitmentScheme<F, C, D>>::verify(
+        &fri_instance::<F, D>(&num_polys, points),
+        &openings,
+        &challenges,
+        caps,
+        opening_proof,
+        params,
+    )
+}
+
+fn in_lde_coset<F: RichField + Extendable<D>, const D: usize>(
+    x: F::Extension,
+    params: &FriParams,
+) -> bool {
+    let lde_bits = params.lde_bits();
+    x.exp_power_of_2(lde_bits)
+        == F::Extension::from_basefield(F::coset_shift().exp_power_of_2(lde_bits))
+}
+
+fn observe_statement<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    challenger: &mut Challenger<F, C::Hasher>,
+    caps: &[MerkleCap<F, C::Hasher>],
+    points: &[F::Extension],
+    openings: &FriOpenings<F, D>,
+) {
+    for cap in caps {
+        challenger.observe_cap(cap);
+    }
+    challenger.observe_extension_elements(points);
+    challenger.observe_openings(openings);
+}
+
+/// The FRI instance opening every polynomial of each commitment at each point.
+fn fri_instance<F: RichField + Extendable<D>, const D: usize>(
+    num_polys: &[usize],
+    points: &[F::Extension],
+) -> FriInstanceInfo<F, D> {
+    let polynomials = num_polys
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &n)| FriPolynomialInfo::from_range(i, 0..n))
+        .collect::<Vec<_>>();
+    FriInstanceInfo {
+        oracles: vec![
+            FriOracleInfo {
+                blinding: false,
+                extra_rate_bits: 0,
+            };
+            num_polys.len()
+        ],
+        batches: points
+            .iter()
+            .map(|&point| FriBatchInfo {
+                point,
+                polynomials: polynomials.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn fri_openings<F: RichField + Extendable<D>, const D: usize>(
+    values: &[Vec<Vec<F::Extension>>],
+) -> FriOpenings<F, D> {
+    FriOpenings {
+        batches: values
+            .iter()
+            .map(|v| FriOpeningBatch {
+                values: v.iter().flatten().copied().collect_vec(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::polynomial::PolynomialCoeffs;
+    use rand::thread_rng;
+
+    use crate::fri::oracle::SALT_SIZE;
+    use crate::fri::pcs::{commit, open, verify, FriPcs, FriProverParams};
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::fri::structure::{
+        FriBatchInfo, FriInstanceInfo, FriOpeningBatch, FriOpenings, FriOracleInfo,
+        FriPolynomialInfo,
+    };
+    use crate::fri::{FriConfig, SaltMode};
+    use crate::iop::challenger::Challenger;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::pcs::{PcsProverContext, PolynomialCommitmentScheme};
+    use crate::util::progress::ProvingMonitor;
+    use crate::util::timing::TimingTree;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type FF = <C as GenericConfig<D>>::FE;
+
+    #[test]
+    fn test_commit_open_verify() -> Result<()> {
+        let config = FriConfig {
+            rate_bits: 2,
+            cap_height: 2,
+            commit_phase_cap_height: 1,
+            proof_of_work_bits: 4,
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(2, 3),
+            num_query_rounds: 20,
+        };
+        let degree_bits = 8;
+        let params = config.fri_params(degree_bits, 0, SaltMode::PerLeaf);
+        let mut timing = TimingTree::default();
+
+        let random_polys = |n| {
+            (0..n)
+                .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << degree_bits)))
+                .collect::<Vec<_>>()
+        };
+        let batch_0 = commit::<F, C, D>(random_polys(3), &params, &mut timing);
+        let batch_1 = commit::<F, C, D>(random_polys(5), &params, &mut timing);
+        let caps = [
+            batch_0.merkle_tree.cap.clone(),
+            batch_1.merkle_tree.cap.clone(),
+        ];
+        let points = [FF::rand(), FF::rand()];
+
+        let mut challenger = Challenger::new();
+        let proof = open(
+            &[&batch_0, &batch_1],
+            &points,
+            &mut challenger,
+            &params,
+            &mut timing,
+        );
+        assert_eq!(
+            proof.values[1][1][4],
+            batch_1.polynomials[4].to_extension::<D>().eval(points[1])
+        );
+        verify(&caps, &points, &proof, &mut Challenger::new(), &params)?;
+
+        // Wrong values, points or commitments are rejected.
+        let mut wrong_proof = proof.clone();
+        wrong_proof.values[0][1][2] += FF::ONE;
+        assert!(verify(
+            &caps,
+            &points,
+            &wrong_proof,
+            &mut Challenger::new(),
+            &params
+        )
+        .is_err());
+        let wrong_points = [points[0], FF::rand()];
+        assert!(verify(
+            &caps,
+            &wrong_points,
+            &proof,
+            &mut Challenger::new(),
+            &params
+        )
+        .is_err());
+        let wrong_caps = [caps[1].clone(), caps[0].clone()];
+        assert!(verify(
+            &wrong_caps,
+            &points,
+            &proof,
+            &mut Challenger::new(),
+            &params
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blinding_commitment() -> Result<()> {
+        type Pcs = FriPcs;
+        let config = FriConfig {
+            rate_bits: 2,
+            cap_height: 2,
+            commit_phase_cap_height: 1,
+            proof_of_work_bits: 4,
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(2, 3),
+            num_query_rounds: 20,
+        };
+        let degree_bits = 8;
+        let params = config.fri_params(degree_bits, SALT_SIZE, SaltMode::PerLeaf);
+        let mut timing = TimingTree::default();
+
+        let prover_params = FriProverParams::default();
+        let monitor = ProvingMonitor::default();
+        let mut rng = thread_rng();
+
+        let polys = (0..3)
+            .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << degree_bits)))
+            .collect::<Vec<_>>();
+        let oracle = FriOracleInfo {
+            blinding: true,
+            extra_rate_bits: 0,
+        };
+        let batch = <Pcs as PolynomialCommitmentScheme<F, C, D>>::commit(
+            polys.clone(),
+            oracle,
+            &params,
+            &prover_params,
+            &mut PcsProverContext {
+                timing: &mut timing,
+                monitor: &monitor,
+                rng: &mut rng,
+                stream: None,
+            },
+        );
+        let commitment = <Pcs as PolynomialCommitmentScheme<F, C, D>>::commitment(&batch);
+        let point = FF::rand();
+        let instance = FriInstanceInfo {
+            oracles: vec![oracle],
+            batches: vec![FriBatchInfo {
+                point,
+                polynomials: FriPolynomialInfo::from_range(0, 0..3),
+            }],
+        };
+        let openings = FriOpenings {
+            batches: vec![FriOpeningBatch {
+                values: polys
+                    .iter()
+                    .map(|p| p.to_extension::<D>().eval(point))
+                    .collect(),
+            }],
+        };
+        let observe = |challenger: &mut Challenger<F, <C as GenericConfig<D>>::Hasher>| {
+            challenger.observe_cap(&commitment);
+            challenger.observe_openings(&openings);
+        };
+
+        let mut challenger = Challenger::new();
+        observe(&mut challenger);
+        let proof = <Pcs as PolynomialCommitmentScheme<F, C, D>>::open(
+            &instance,
+            &[&batch],
+            &mut challenger,
+            &params,
+            &prover_params,
+            &mut PcsProverContext {
+                timing: &mut timing,
+                monitor: &monitor,
+                rng: &mut rng,
+                stream: None,
+            },
+        )?;
+
+        let mut challenger = Challenger::new();
+        observe(&mut challenger);
+        let challenges = challenger.fri_challenges::<C, D>(
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            proof.pow_witness,
+            params.degree_bits,
+            &params.config,
+        );
+        <Pcs as PolynomialCommitmentScheme<F, C, D>>::verify(
+            &instance,
+            &openings,
+            &challenges,
+            &[commitment.clone()],
+            &proof,
+            &params,
+        )?;
+
+        let compressed_proof =
+            <Pcs as PolynomialCommitmentScheme<F, C, D>>::compress(proof, &challenges, &params);
+        <Pcs as PolynomialCommitmentScheme<F, C, D>>::verify_compressed(
+            &instance,
+            &openings,
+            &challenges,
+            &[commitment],
+            &compressed_proof,
+            &params,
+        )
+    }
+}
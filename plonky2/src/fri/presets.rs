@@ -0,0 +1,215 @@
+//! Named FRI configurations covering the common tradeoffs between prover time, proof size and the
+//! cost of verifying proofs recursively. Each preset targets 100 bits of conjectured security.
+
+use crate::fri::reduction_strategies::{fri_proof_size, FriReductionStrategy};
+use crate::fri::{FriConfig, SaltMode};
+
+/// A named FRI configuration. Presets are ordered from the fastest prover with the largest proofs
+/// to the slowest prover with the smallest proofs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FriPreset {
+    /// A rate of 1/2, which keeps FRI's trees and the quotient's LDE small at the cost of many
+    /// queries, and hence large proofs. Suited to proofs which are aggregated before leaving the
+    /// prover. The quotient of a circuit with gates of degree above 3, such as `PoseidonGate`,
+    /// can't be computed at this rate, so `CircuitConfig::fri_preset_config` commits to its inputs
+    /// at a higher rate.
+    FastProver,
+    /// A rate of 1/8, as used by `CircuitConfig::standard_recursion_config`. A good default for
+    /// proofs which are verified recursively.
+    Balanced,
+    /// A rate of 1/128 and arities of at most 8 chosen for proof size. Proofs are a fraction of the
+    /// size of `Balanced` proofs and remain cheap to verify recursively, but the prover's LDEs are
+    /// 16 times larger.
+    SmallProof,
+    /// A rate of 1/256, a high proof-of-work setting and caps of a single hash, as used by
+    /// `CircuitConfig::size_optimized_wrapper_config`. Suited to a final proof which is verified by
+    /// a smart contract, where every byte of calldata counts, and not recursed upon further.
+    OnChain,
+}
+
+impl FriPreset {
+    pub fn fri_config(self) -> FriConfig {
+        match self {
+            FriPreset::FastProver => FriConfig {
+                rate_bits: 1,
+                cap_height: 4,
+                commit_phase_cap_height: 4,
+                proof_of_work_bits: 16,
+                reduction_strategy: FriReductionStrategy::ConstantArityBits(3, 5),
+                num_query_rounds: 84,
+            },
+            FriPreset::Balanced => FriConfig {
+                rate_bits: 3,
+                cap_height: 4,
+                commit_phase_cap_height: 4,
+                proof_of_work_bits: 16,
+                reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
+                num_query_rounds: 28,
+            },
+            FriPreset::SmallProof => FriConfig {
+                rate_bits: 7,
+                cap_height: 4,
+                commit_phase_cap_height: 2,
+                proof_of_work_bits: 16,
+                reduction_strategy: FriReductionStrategy::MinProofSize(Some(3)),
+                num_query_rounds: 12,
+            },
+            FriPreset::OnChain => FriConfig {
+                rate_bits: 8,
+                cap_height: 0,
+                commit_phase_cap_height: 0,
+                proof_of_work_bits: 20,
+                reduction_strategy: FriReductionStrategy::MinSize(None),
+                num_query_rounds: 10,
+            },
+        }
+    }
+
+    /// The conjectured security of this preset, in bits.
+    pub fn security_bits(self) -> usize {
+        self.fri_config().conjectured_security_bits()
+    }
+
+    /// The estimated size, in field elements, of the parts of a FRI proof for a polynomial of
+    /// degree `2^degree_bits` which depend on the preset, as given by `fri_proof_size`. Initial tree
+    /// openings, which also depend on the circuit's width, are not included.
+    pub fn estimated_fri_proof_size(self, degree_bits: usize) -> usize {
+        let config = self.fri_config();
+        let params = config.fri_params(degree_bits, 0, SaltMode::PerLeaf);
+        fri_proof_size(
+            degree_bits,
+            config.rate_bits,
+            config.commit_phase_cap_height,
+            config.num_query_rounds,
+            &params.reduction_arity_bits,
+        )
+    }
+}
+
+/// What `CircuitConfig::with_security_bits` optimizes for, given a security target.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Preference {
+    /// The fastest prover, with the largest proofs; based on `FriPreset::FastProver`.
+    ProverTime,
+    /// The smallest proofs, with a slower prover; based on `FriPreset::SmallProof`.
+    ProofSize,
+    /// Proofs which are cheap to verify recursively; based on `FriPreset::Balanced`.
+    RecursionCost,
+}
+
+impl Preference {
+    /// The preset whose rate, arities and caps the derived configuration uses.
+    pub fn fri_preset(self) -> FriPreset {
+        match self {
+            Preference::ProverTime => FriPreset::FastProver,
+            Preference::ProofSize => FriPreset::SmallProof,
+            Preference::RecursionCost => FriPreset::Balanced,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::fri::presets::{FriPreset, Preference};
+    use crate::gates::noop::NoopGate;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const PRESETS: [FriPreset; 4] = [
+        FriPreset::FastProver,
+        FriPreset::Balanced,
+        FriPreset::SmallProof,
+        FriPreset::OnChain,
+    ];
+
+    #[test]
+    fn test_presets() {
+        for preset in PRESETS {
+            assert!(preset.security_bits() >= 100);
+        }
+        let sizes = PRESETS.map(|p| p.estimated_fri_proof_size(16));
+        assert!(sizes[0] > sizes[1]);
+        assert!(sizes[1] > sizes[2]);
+        assert!(sizes[1] > sizes[3]);
+    }
+
+    #[test]
+    fn test_config_with_security_bits() {
+        let standard = CircuitConfig::standard_recursion_config();
+        let config = CircuitConfig::with_security_bits::<D>(100, Preference::RecursionCost);
+        assert_eq!(config.fri_config, standard.fri_config);
+        assert_eq!(config.num_challenges, None);
+
+        for preference in [
+            Preference::ProverTime,
+            Preference::ProofSize,
+            Preference::RecursionCost,
+        ] {
+            for bits in [20, 80, 100, 110] {
+                let config = CircuitConfig::with_security_bits::<D>(bits, preference);
+                assert!(config.fri_config.conjectured_security_bits() >= bits);
+                assert_eq!(config.security_bits, bits);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "too small")]
+    fn test_config_security_beyond_extension() {
+        CircuitConfig::with_security_bits::<1>(100, Preference::RecursionCost);
+    }
+
+    #[test]
+    fn test_prove_with_security_bits() -> Result<()> {
+        let config = CircuitConfig::with_security_bits::<D>(80, Preference::ProverTime);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let zero = builder.zero();
+        builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![zero; 4]);
+        for _ in 0..1000 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        // Each set of challenges gives 63 - 10 - 7 bits at degree 2^10 with 80 routed wires.
+        assert_eq!(data.common.degree_bits, 10);
+        assert_eq!(data.common.config.num_challenges, Some(2));
+        let proof = data.prove(PartialWitness::new())?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_min_num_challenges() {
+        let mut config = CircuitConfig::standard_recursion_config();
+        assert_eq!(config.challenge_soundness_bits::<F>(12), 63 - 12 - 7);
+        assert_eq!(config.min_num_challenges::<F>(12), Some(3));
+        config.security_bits = 80;
+        assert_eq!(config.min_num_challenges::<F>(12), Some(2));
+        assert_eq!(config.min_num_challenges::<F>(60), None);
+    }
+
+    #[test]
+    fn test_prove_with_presets() -> Result<()> {
+        for preset in PRESETS {
+            let config = CircuitConfig::fri_preset_config(preset);
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            // `PoseidonGate` has constraints of degree 7, beyond what a rate of 1/2 supports.
+            let zero = builder.zero();
+            builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![zero; 4]);
+            for _ in 0..1000 {
+                builder.add_gate(NoopGate, vec![]);
+            }
+            let data = builder.build::<C>();
+            let proof = data.prove(PartialWitness::new())?;
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+}
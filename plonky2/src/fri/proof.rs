@@ -5,6 +5,7 @@ use plonky2_field::extension_field::{flatten, unflatten, Extendable};
 use plonky2_field::polynomial::PolynomialCoeffs;
 use serde::{Deserialize, Serialize};
 
+use crate::fri::structure::FriOracleInfo;
 use crate::fri::FriParams;
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::hash::hash_types::MerkleCapTarget;
@@ -15,7 +16,6 @@ use crate::hash::path_compression::{compress_merkle_proofs, decompress_merkle_pr
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::plonk_common::salt_size;
 use crate::plonk::proof::{FriInferredElements, ProofChallenges};
 
 /// Evaluations and Merkle proof produced by the prover in a FRI query step.
@@ -41,13 +41,24 @@ pub struct FriInitialTreeProof<F: RichField, H: Hasher<F>> {
 }
 
 impl<F: RichField, H: Hasher<F>> FriInitialTreeProof<F, H> {
-    pub(crate) fn unsalted_eval(&self, oracle_index: usize, poly_index: usize, salted: bool) -> F {
-        self.unsalted_evals(oracle_index, salted)[poly_index]
+    pub(crate) fn unsalted_eval(
+        &self,
+        oracle_index: usize,
+        poly_index: usize,
+        salt_size: usize,
+    ) -> F {
+        self.unsalted_evals(oracle_index, salt_size)[poly_index]
+    }
+
+    fn unsalted_evals(&self, oracle_index: usize, salt_size: usize) -> &[F] {
+        let evals = &self.evals_proofs[oracle_index].0;
+        &evals[..evals.len() - salt_size]
     }
 
-    fn unsalted_evals(&self, oracle_index: usize, salted: bool) -> &[F] {
+    /// The salt at the end of the leaf of the given oracle.
+    pub(crate) fn salt(&self, oracle_index: usize, salt_size: usize) -> &[F] {
         let evals = &self.evals_proofs[oracle_index].0;
-        &evals[..evals.len() - salt_size(salted)]
+        &evals[evals.len() - salt_size..]
     }
 }
 
@@ -61,23 +72,31 @@ impl FriInitialTreeProofTarget {
         &self,
         oracle_index: usize,
         poly_index: usize,
-        salted: bool,
+        salt_size: usize,
     ) -> Target {
-        self.unsalted_evals(oracle_index, salted)[poly_index]
+        self.unsalted_evals(oracle_index, salt_size)[poly_index]
     }
 
-    fn unsalted_evals(&self, oracle_index: usize, salted: bool) -> &[Target] {
+    fn unsalted_evals(&self, oracle_index: usize, salt_size: usize) -> &[Target] {
         let evals = &self.evals_proofs[oracle_index].0;
-        &evals[..evals.len() - salt_size(salted)]
+        &evals[..evals.len() - salt_size]
+    }
+
+    /// The salt at the end of the leaf of the given oracle.
+    pub(crate) fn salt(&self, oracle_index: usize, salt_size: usize) -> &[Target] {
+        let evals = &self.evals_proofs[oracle_index].0;
+        &evals[evals.len() - salt_size..]
     }
 }
 
-/// Proof for a FRI query round.
+/// Proof for a FRI query round, where `H` is the hasher of the initial trees and `CH` that of the
+/// commit phase trees.
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
-pub struct FriQueryRound<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> {
+pub struct FriQueryRound<F: RichField + Extendable<D>, H: Hasher<F>, CH: Hasher<F>, const D: usize>
+{
     pub initial_trees_proof: FriInitialTreeProof<F, H>,
-    pub steps: Vec<FriQueryStep<F, H, D>>,
+    pub steps: Vec<FriQueryStep<F, CH, D>>,
 }
 
 #[derive(Clone, Debug)]
@@ -89,29 +108,34 @@ pub struct FriQueryRoundTarget<const D: usize> {
 /// Compressed proof of the FRI query rounds.
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
-pub struct CompressedFriQueryRounds<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> {
+pub struct CompressedFriQueryRounds<
+    F: RichField + Extendable<D>,
+    H: Hasher<F>,
+    CH: Hasher<F>,
+    const D: usize,
+> {
     /// Query indices.
     pub indices: Vec<usize>,
     /// Map from initial indices `i` to the `FriInitialProof` for the `i`th leaf.
     pub initial_trees_proofs: HashMap<usize, FriInitialTreeProof<F, H>>,
     /// For each FRI query step, a map from indices `i` to the `FriQueryStep` for the `i`th leaf.
-    pub steps: Vec<HashMap<usize, FriQueryStep<F, H, D>>>,
+    pub steps: Vec<HashMap<usize, FriQueryStep<F, CH, D>>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
-pub struct FriProof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> {
+pub struct FriProof<F: RichField + Extendable<D>, H: Hasher<F>, CH: Hasher<F>, const D: usize> {
     /// A Merkle cap for each reduced polynomial in the commit phase.
-    pub commit_phase_merkle_caps: Vec<MerkleCap<F, H>>,
+    pub commit_phase_merkle_caps: Vec<MerkleCap<F, CH>>,
     /// Query rounds proofs
-    pub query_round_proofs: Vec<FriQueryRound<F, H, D>>,
+    pub query_round_proofs: Vec<FriQueryRound<F, H, CH, D>>,
     /// The final polynomial in coefficient form.
     pub final_poly: PolynomialCoeffs<F::Extension>,
     /// Witness showing that the prover did PoW.
     pub pow_witness: F,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct FriProofTarget<const D: usize> {
     pub commit_phase_merkle_caps: Vec<MerkleCapTarget>,
     pub query_round_proofs: Vec<FriQueryRoundTarget<D>>,
@@ -121,24 +145,31 @@ pub struct FriProofTarget<const D: usize> {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
-pub struct CompressedFriProof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> {
+pub struct CompressedFriProof<
+    F: RichField + Extendable<D>,
+    H: Hasher<F>,
+    CH: Hasher<F>,
+    const D: usize,
+> {
     /// A Merkle cap for each reduced polynomial in the commit phase.
-    pub commit_phase_merkle_caps: Vec<MerkleCap<F, H>>,
+    pub commit_phase_merkle_caps: Vec<MerkleCap<F, CH>>,
     /// Compressed query rounds proof.
-    pub query_round_proofs: CompressedFriQueryRounds<F, H, D>,
+    pub query_round_proofs: CompressedFriQueryRounds<F, H, CH, D>,
     /// The final polynomial in coefficient form.
     pub final_poly: PolynomialCoeffs<F::Extension>,
     /// Witness showing that the prover did PoW.
     pub pow_witness: F,
 }
 
-impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H, D> {
+impl<F: RichField + Extendable<D>, H: Hasher<F>, CH: Hasher<F>, const D: usize>
+    FriProof<F, H, CH, D>
+{
     /// Compress all the Merkle paths in the FRI proof and remove duplicate indices.
-    pub fn compress<C: GenericConfig<D, F = F, Hasher = H>>(
+    pub fn compress<C: GenericConfig<D, F = F, Hasher = H, CommitPhaseHasher = CH>>(
         self,
         indices: &[usize],
         params: &FriParams,
-    ) -> CompressedFriProof<F, H, D> {
+    ) -> CompressedFriProof<F, H, CH, D> {
         let FriProof {
             commit_phase_merkle_caps,
             query_round_proofs,
@@ -147,6 +178,7 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
             ..
         } = self;
         let cap_height = params.config.cap_height;
+        let commit_phase_cap_height = params.config.commit_phase_cap_height;
         let reduction_arity_bits = &params.reduction_arity_bits;
         let num_reductions = reduction_arity_bits.len();
         let num_initial_trees = query_round_proofs[0].initial_trees_proof.evals_proofs.len();
@@ -192,7 +224,7 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
         let steps_proofs = steps_indices
             .iter()
             .zip(steps_proofs)
-            .map(|(is, ps)| compress_merkle_proofs(cap_height, is, &ps))
+            .map(|(is, ps)| compress_merkle_proofs(commit_phase_cap_height, is, &ps))
             .collect::<Vec<_>>();
 
         let mut compressed_query_proofs = CompressedFriQueryRounds {
@@ -238,16 +270,20 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
     }
 }
 
-impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriProof<F, H, D> {
+impl<F: RichField + Extendable<D>, H: Hasher<F>, CH: Hasher<F>, const D: usize>
+    CompressedFriProof<F, H, CH, D>
+{
     /// Decompress all the Merkle paths in the FRI proof and reinsert duplicate indices.
-    pub(crate) fn decompress<C: GenericConfig<D, F = F, Hasher = H>>(
+    pub(crate) fn decompress<C: GenericConfig<D, F = F, Hasher = H, CommitPhaseHasher = CH>>(
         self,
         challenges: &ProofChallenges<F, D>,
         fri_inferred_elements: FriInferredElements<F, D>,
+        oracles: &[FriOracleInfo],
         params: &FriParams,
-    ) -> FriProof<F, H, D>
+    ) -> FriProof<F, H, CH, D>
     where
         [(); H::HASH_SIZE]:,
+        [(); CH::HASH_SIZE]:,
     {
         let CompressedFriProof {
             commit_phase_merkle_caps,
@@ -262,6 +298,7 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriPr
         } = &challenges.fri_challenges;
         let mut fri_inferred_elements = fri_inferred_elements.0.into_iter();
         let cap_height = params.config.cap_height;
+        let commit_phase_cap_height = params.config.commit_phase_cap_height;
         let reduction_arity_bits = &params.reduction_arity_bits;
         let num_reductions = reduction_arity_bits.len();
         let num_initial_trees = query_round_proofs
@@ -325,12 +362,17 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriPr
         let initial_trees_proofs = izip!(
             &initial_trees_leaves,
             &initial_trees_indices,
-            initial_trees_proofs
+            initial_trees_proofs,
+            oracles
         )
-        .map(|(ls, is, ps)| decompress_merkle_proofs(ls, is, &ps, height, cap_height))
+        .map(|(ls, is, ps, &oracle)| {
+            decompress_merkle_proofs(ls, is, &ps, params.oracle_lde_bits(oracle), cap_height)
+        })
         .collect::<Vec<_>>();
         let steps_proofs = izip!(&steps_evals, &steps_indices, steps_proofs, heights)
-            .map(|(ls, is, ps, h)| decompress_merkle_proofs(ls, is, &ps, h, cap_height))
+            .map(|(ls, is, ps, h)| {
+                decompress_merkle_proofs(ls, is, &ps, h, commit_phase_cap_height)
+            })
             .collect::<Vec<_>>();
 
         let mut decompressed_query_proofs = Vec::with_capacity(num_reductions);
@@ -1,9 +1,10 @@
-use itertools::Itertools;
+use anyhow::{ensure, Result};
 use plonky2_field::extension_field::{flatten, unflatten, Extendable};
 use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use plonky2_util::reverse_index_bits_in_place;
-use rayon::prelude::*;
 
+use crate::fri::fft_backend::{coset_lde_extension, FftBackend};
+use crate::fri::grinding::{pow_response, PowGrinder};
 use crate::fri::proof::{FriInitialTreeProof, FriProof, FriQueryRound, FriQueryStep};
 use crate::fri::{FriConfig, FriParams};
 use crate::hash::hash_types::{HashOut, RichField};
@@ -12,9 +13,14 @@ use crate::iop::challenger::Challenger;
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::reduce_with_powers;
 use crate::timed;
+use crate::util::maybe_rayon::*;
+use crate::util::progress::{ProvingEvent, ProvingMonitor, ProvingPhase};
+use crate::util::serialization::ProofStream;
 use crate::util::timing::TimingTree;
 
-/// Builds a FRI proof.
+/// Builds a FRI proof. If `stream` is given, the proof is also written to it, each query round as
+/// soon as it's computed, and the query rounds are left out of the returned proof. Fails if the
+/// proof is cancelled through `monitor`.
 pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
     // Coefficients of the polynomial on which the LDT is performed. Only the first `1/rate` coefficients are non-zero.
@@ -23,15 +29,20 @@ pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     lde_polynomial_values: PolynomialValues<F::Extension>,
     challenger: &mut Challenger<F, C::Hasher>,
     fri_params: &FriParams,
+    fft_backend: &dyn FftBackend<F>,
+    pow_grinder: &dyn PowGrinder<F, C::InnerHasher>,
     timing: &mut TimingTree,
-) -> FriProof<F, C::Hasher, D>
+    monitor: &ProvingMonitor,
+    mut stream: Option<&mut ProofStream>,
+) -> Result<FriProof<F, C::Hasher, C::CommitPhaseHasher, D>>
 where
-    [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     let n = lde_polynomial_values.len();
     assert_eq!(lde_polynomial_coeffs.len(), n);
 
     // Commit phase
+    monitor.start_phase(ProvingPhase::FriCommit)?;
     let (trees, final_coeffs) = timed!(
         timing,
         "fold codewords in the commitment phase",
@@ -40,27 +51,48 @@ where
             lde_polynomial_values,
             challenger,
             fri_params,
+            fft_backend,
+            monitor,
         )
     );
 
+    if let Some(stream) = stream.as_deref_mut() {
+        for tree in &trees {
+            stream.write_merkle_cap(&tree.cap);
+        }
+    }
+
     // PoW phase
+    monitor.start_phase(ProvingPhase::FriProofOfWork)?;
+    challenger.set_label("fri_pow");
     let current_hash = challenger.get_hash();
     let pow_witness = timed!(
         timing,
         "find proof-of-work witness",
-        fri_proof_of_work::<F, C, D>(current_hash, &fri_params.config)
-    );
+        fri_proof_of_work::<F, C, D>(current_hash, &fri_params.config, pow_grinder)
+    )?;
 
     // Query phase
-    let query_round_proofs =
-        fri_prover_query_rounds::<F, C, D>(initial_merkle_trees, &trees, challenger, n, fri_params);
+    monitor.start_phase(ProvingPhase::FriQueries)?;
+    challenger.set_label("fri_query_indices");
+    let query_round_proofs = fri_prover_query_rounds::<F, C, D>(
+        initial_merkle_trees,
+        &trees,
+        challenger,
+        n,
+        fri_params,
+        stream.as_deref_mut(),
+    );
+    if let Some(stream) = stream {
+        stream.write_fri_final_poly_and_pow::<F, D>(&final_coeffs, pow_witness);
+    }
 
-    FriProof {
+    Ok(FriProof {
         commit_phase_merkle_caps: trees.iter().map(|t| t.cap.clone()).collect(),
         query_round_proofs,
         final_poly: final_coeffs,
         pow_witness,
-    }
+    })
 }
 
 fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
@@ -68,17 +100,21 @@ fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     mut values: PolynomialValues<F::Extension>,
     challenger: &mut Challenger<F, C::Hasher>,
     fri_params: &FriParams,
+    fft_backend: &dyn FftBackend<F>,
+    monitor: &ProvingMonitor,
 ) -> (
-    Vec<MerkleTree<F, C::Hasher>>,
+    Vec<MerkleTree<F, C::CommitPhaseHasher>>,
     PolynomialCoeffs<F::Extension>,
 )
 where
-    [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     let mut trees = Vec::new();
 
     let mut shift = F::MULTIPLICATIVE_GROUP_GENERATOR;
-    for arity_bits in &fri_params.reduction_arity_bits {
+    let num_rounds = fri_params.reduction_arity_bits.len();
+    for (round, arity_bits) in fri_params.reduction_arity_bits.iter().enumerate() {
+        monitor.report(ProvingEvent::FriRound { round, num_rounds });
         let arity = 1 << arity_bits;
 
         reverse_index_bits_in_place(&mut values.values);
@@ -87,8 +123,13 @@ where
             .par_chunks(arity)
             .map(|chunk: &[F::Extension]| flatten(chunk))
             .collect();
-        let tree = MerkleTree::<F, C::Hasher>::new(chunked_values, fri_params.config.cap_height);
+        let tree = MerkleTree::<F, C::CommitPhaseHasher>::new_with_monitor(
+            chunked_values,
+            fri_params.config.commit_phase_cap_height,
+            monitor,
+        );
 
+        challenger.set_label("fri_commit_phase");
         challenger.observe_cap(&tree.cap);
         trees.push(tree);
 
@@ -102,7 +143,7 @@ where
                 .collect::<Vec<_>>(),
         );
         shift = shift.exp_u64(arity as u64);
-        values = coeffs.coset_fft(shift.into())
+        values = coset_lde_extension::<F, D>(fft_backend, &coeffs, 0, shift);
     }
 
     // The coefficients being removed here should always be zero.
@@ -110,6 +151,7 @@ where
         .coeffs
         .truncate(coeffs.len() >> fri_params.config.rate_bits);
 
+    challenger.set_label("fri_final_poly");
     challenger.observe_extension_elements(&coeffs.coeffs);
     (trees, coeffs)
 }
@@ -117,25 +159,17 @@ where
 fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     current_hash: HashOut<F>,
     config: &FriConfig,
-) -> F {
-    (0..=F::NEG_ONE.to_canonical_u64())
-        .into_par_iter()
-        .find_any(|&i| {
-            C::InnerHasher::hash_no_pad(
-                &current_hash
-                    .elements
-                    .iter()
-                    .copied()
-                    .chain(Some(F::from_canonical_u64(i)))
-                    .collect_vec(),
-            )
-            .elements[0]
-                .to_canonical_u64()
-                .leading_zeros()
-                >= config.proof_of_work_bits + (64 - F::order().bits()) as u32
-        })
-        .map(F::from_canonical_u64)
-        .expect("Proof of work failed. This is highly unlikely!")
+    pow_grinder: &dyn PowGrinder<F, C::InnerHasher>,
+) -> Result<F> {
+    let leading_zeros = config.proof_of_work_bits + (64 - F::order().bits()) as u32;
+    let pow_witness = pow_grinder.grind(&current_hash.elements, leading_zeros);
+    // The grinder may run on another device, so we check its result.
+    ensure!(
+        pow_response::<F, C::InnerHasher>(&current_hash.elements, pow_witness).leading_zeros()
+            >= leading_zeros,
+        "Invalid proof-of-work witness"
+    );
+    Ok(pow_witness)
 }
 
 fn fri_prover_query_rounds<
@@ -144,20 +178,28 @@ fn fri_prover_query_rounds<
     const D: usize,
 >(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
-    trees: &[MerkleTree<F, C::Hasher>],
+    trees: &[MerkleTree<F, C::CommitPhaseHasher>],
     challenger: &mut Challenger<F, C::Hasher>,
     n: usize,
     fri_params: &FriParams,
-) -> Vec<FriQueryRound<F, C::Hasher, D>> {
+    mut stream: Option<&mut ProofStream>,
+) -> Vec<FriQueryRound<F, C::Hasher, C::CommitPhaseHasher, D>> {
     (0..fri_params.config.num_query_rounds)
-        .map(|_| {
-            fri_prover_query_round::<F, C, D>(
+        .filter_map(|_| {
+            let round = fri_prover_query_round::<F, C, D>(
                 initial_merkle_trees,
                 trees,
                 challenger,
                 n,
                 fri_params,
-            )
+            );
+            match stream.as_deref_mut() {
+                Some(stream) => {
+                    stream.write_fri_query_round::<F, C, D>(&round);
+                    None
+                }
+                None => Some(round),
+            }
         })
         .collect()
 }
@@ -168,11 +210,11 @@ fn fri_prover_query_round<
     const D: usize,
 >(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
-    trees: &[MerkleTree<F, C::Hasher>],
+    trees: &[MerkleTree<F, C::CommitPhaseHasher>],
     challenger: &mut Challenger<F, C::Hasher>,
     n: usize,
     fri_params: &FriParams,
-) -> FriQueryRound<F, C::Hasher, D> {
+) -> FriQueryRound<F, C::Hasher, C::CommitPhaseHasher, D> {
     let mut query_steps = Vec::new();
     let x = challenger.get_challenge();
     let mut x_index = x.to_canonical_u64() as usize % n;
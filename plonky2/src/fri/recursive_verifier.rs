@@ -1,3 +1,6 @@
+use std::panic::Location;
+
+use anyhow::{ensure, Result};
 use itertools::Itertools;
 use plonky2_field::extension_field::Extendable;
 use plonky2_util::{log2_strict, reverse_index_bits_in_place};
@@ -6,7 +9,9 @@ use crate::fri::proof::{
     FriChallengesTarget, FriInitialTreeProofTarget, FriProofTarget, FriQueryRoundTarget,
     FriQueryStepTarget,
 };
-use crate::fri::structure::{FriBatchInfoTarget, FriInstanceInfoTarget, FriOpeningsTarget};
+use crate::fri::structure::{
+    FriBatchInfoTarget, FriInstanceInfoTarget, FriOpeningsTarget, FriOracleInfo,
+};
 use crate::fri::{FriConfig, FriParams};
 use crate::gadgets::interpolation::InterpolationGate;
 use crate::gates::gate::Gate;
@@ -16,6 +21,7 @@ use crate::gates::random_access::RandomAccessGate;
 use crate::hash::hash_types::MerkleCapTarget;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::{flatten_target, ExtensionTarget};
+use crate::iop::source_locations::with_location;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
@@ -70,10 +76,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Make sure we have enough wires and routed wires to do the FRI checks efficiently. This check
     /// isn't required -- without it we'd get errors elsewhere in the stack -- but just gives more
     /// helpful errors.
-    fn check_recursion_config<C: GenericConfig<D, F = F>>(&self, max_fri_arity_bits: usize) {
+    pub(crate) fn check_recursion_config<C: GenericConfig<D, F = F>>(
+        &self,
+        max_fri_arity_bits: usize,
+    ) -> Result<()> {
         let random_access = RandomAccessGate::<F, D>::new_from_config(
             &self.config,
-            max_fri_arity_bits.max(self.config.fri_config.cap_height),
+            max_fri_arity_bits
+                .max(self.config.fri_config.cap_height)
+                .max(self.config.fri_config.commit_phase_cap_height),
         );
         let (interpolation_wires, interpolation_routed_wires) =
             if 1 << max_fri_arity_bits > self.config.max_quotient_degree_factor {
@@ -89,19 +100,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             .num_routed_wires()
             .max(interpolation_routed_wires);
 
-        assert!(
-            self.config.num_wires >= min_wires,
-            "To efficiently perform FRI checks with an arity of 2^{}, at least {} wires are needed. Consider reducing arity.",
-            max_fri_arity_bits,
-            min_wires
-        );
-
-        assert!(
-            self.config.num_routed_wires >= min_routed_wires,
-            "To efficiently perform FRI checks with an arity of 2^{}, at least {} routed wires are needed. Consider reducing arity.",
+        let mut errors = Vec::new();
+        if self.config.num_wires < min_wires {
+            errors.push(format!("at least {} wires are needed", min_wires));
+        }
+        if self.config.num_routed_wires < min_routed_wires {
+            errors.push(format!(
+                "at least {} routed wires are needed",
+                min_routed_wires
+            ));
+        }
+        ensure!(
+            errors.is_empty(),
+            "To efficiently perform FRI checks with an arity of 2^{}, {}. Consider reducing arity.",
             max_fri_arity_bits,
-            min_routed_wires
+            errors.join(" and ")
         );
+        Ok(())
     }
 
     fn fri_verify_proof_of_work<H: AlgebraicHasher<F>>(
@@ -115,6 +130,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    #[track_caller]
     pub fn verify_fri_proof<C: GenericConfig<D, F = F>>(
         &mut self,
         instance: &FriInstanceInfoTarget<D>,
@@ -125,9 +141,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         params: &FriParams,
     ) where
         C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
     {
         if let Some(max_arity_bits) = params.max_arity_bits() {
-            self.check_recursion_config::<C>(max_arity_bits);
+            if let Err(e) = self.check_recursion_config::<C>(max_arity_bits) {
+                // `try_build` reports the error. The checks themselves would only fail for lack of
+                // wires, so they're left out of a circuit which can't be built anyway.
+                let location = if cfg!(debug_assertions) {
+                    Some(Location::caller())
+                } else {
+                    None
+                };
+                self.param_errors
+                    .push(with_location(e.to_string(), "verifying", location));
+                return;
+            }
         }
 
         debug_assert_eq!(
@@ -198,19 +226,31 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         proof: &FriInitialTreeProofTarget,
         initial_merkle_caps: &[MerkleCapTarget],
         cap_index: Target,
+        oracles: &[FriOracleInfo],
     ) {
-        for (i, ((evals, merkle_proof), cap)) in proof
+        for (i, (((evals, merkle_proof), cap), &oracle)) in proof
             .evals_proofs
             .iter()
             .zip(initial_merkle_caps)
+            .zip(oracles)
             .enumerate()
         {
+            // An oracle committed at a higher rate than FRI's has a deeper tree, in which the
+            // queried leaf's index has `extra_rate_bits` more leading zeros.
+            let (leaf_index_bits, cap_index) = if oracle.extra_rate_bits == 0 {
+                (x_index_bits.to_vec(), cap_index)
+            } else {
+                let mut bits = x_index_bits.to_vec();
+                bits.resize(x_index_bits.len() + oracle.extra_rate_bits, self._false());
+                let cap_index = self.le_sum(bits[merkle_proof.siblings.len()..].iter());
+                (bits, cap_index)
+            };
             with_context!(
                 self,
                 &format!("verify {}'th initial Merkle proof", i),
                 self.verify_merkle_proof_with_cap_index::<H>(
                     evals.clone(),
-                    x_index_bits,
+                    &leaf_index_bits,
                     cap_index,
                     cap,
                     merkle_proof
@@ -229,11 +269,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         params: &FriParams,
     ) -> ExtensionTarget<D> {
         assert!(D > 1, "Not implemented for D=1.");
-        let degree_log = params.degree_bits;
         debug_assert_eq!(
-            degree_log,
+            params.oracle_lde_bits(instance.oracles[0]),
             params.config.cap_height + proof.evals_proofs[0].1.siblings.len()
-                - params.config.rate_bits
         );
         let subgroup_x = self.convert_to_ext(subgroup_x);
         let mut alpha = ReducingFactorTarget::new(alpha);
@@ -248,9 +286,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             let evals = polynomials
                 .iter()
                 .map(|p| {
-                    let poly_blinding = instance.oracles[p.oracle_index].blinding;
-                    let salted = params.hiding && poly_blinding;
-                    proof.unsalted_eval(p.oracle_index, p.polynomial_index, salted)
+                    let salt_size = params.oracle_salt_size(instance.oracles[p.oracle_index]);
+                    proof.unsalted_eval(p.oracle_index, p.polynomial_index, salt_size)
                 })
                 .collect_vec();
             let reduced_evals = alpha.reduce_base(&evals, self);
@@ -262,7 +299,20 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         // Multiply the final polynomial by `X`, so that `final_poly` has the maximum degree for
         // which the LDT will pass. See github.com/mir-protocol/plonky2/pull/436 for details.
-        self.mul_extension(sum, subgroup_x)
+        let sum = self.mul_extension(sum, subgroup_x);
+
+        // Add the masking polynomials, whose coordinates are the salts of the masked oracles.
+        let masking_evals = params
+            .masked_oracles(&instance.oracles)
+            .into_iter()
+            .map(|i| ExtensionTarget(proof.salt(i, params.salt_size).try_into().unwrap()))
+            .collect::<Vec<_>>();
+        if masking_evals.is_empty() {
+            return sum;
+        }
+        let reduced_masking_evals = alpha.reduce(&masking_evals, self);
+        let sum = alpha.shift(sum, self);
+        self.add_extension(sum, reduced_masking_evals)
     }
 
     fn fri_verifier_query_round<C: GenericConfig<D, F = F>>(
@@ -278,6 +328,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         params: &FriParams,
     ) where
         C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
     {
         let n_log = log2_strict(n);
 
@@ -288,6 +339,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         let cap_index =
             self.le_sum(x_index_bits[x_index_bits.len() - params.config.cap_height..].iter());
+        // The cap index of the commit phase trees is also given by the top bits of `x_index`, as
+        // each reduction only removes low bits.
+        let commit_phase_cap_index = self.le_sum(
+            x_index_bits[x_index_bits.len() - params.config.commit_phase_cap_height..].iter(),
+        );
         with_context!(
             self,
             "check FRI initial proof",
@@ -295,7 +351,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 &x_index_bits,
                 &round_proof.initial_trees_proof,
                 initial_merkle_caps,
-                cap_index
+                cap_index,
+                &instance.oracles
             )
         );
 
@@ -350,10 +407,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             with_context!(
                 self,
                 "verify FRI round Merkle proof.",
-                self.verify_merkle_proof_with_cap_index::<C::Hasher>(
+                self.verify_merkle_proof_with_cap_index::<C::CommitPhaseHasher>(
                     flatten_target(evals),
                     &coset_index_bits,
-                    cap_index,
+                    commit_phase_cap_index,
                     &proof.commit_phase_merkle_caps[i],
                     &round_proof.steps[i].merkle_proof,
                 )
@@ -401,15 +458,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     pub fn add_virtual_fri_proof(
         &mut self,
         num_leaves_per_oracle: &[usize],
+        oracles: &[FriOracleInfo],
         params: &FriParams,
     ) -> FriProofTarget<D> {
-        let cap_height = params.config.cap_height;
+        let cap_height = params.config.commit_phase_cap_height;
         let num_queries = params.config.num_query_rounds;
         let commit_phase_merkle_caps = (0..params.reduction_arity_bits.len())
             .map(|_| self.add_virtual_cap(cap_height))
             .collect();
         let query_round_proofs = (0..num_queries)
-            .map(|_| self.add_virtual_fri_query(num_leaves_per_oracle, params))
+            .map(|_| self.add_virtual_fri_query(num_leaves_per_oracle, oracles, params))
             .collect();
         let final_poly = self.add_virtual_poly_coeff_ext(params.final_poly_len());
         let pow_witness = self.add_virtual_target();
@@ -424,20 +482,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     fn add_virtual_fri_query(
         &mut self,
         num_leaves_per_oracle: &[usize],
+        oracles: &[FriOracleInfo],
         params: &FriParams,
     ) -> FriQueryRoundTarget<D> {
         let cap_height = params.config.cap_height;
-        assert!(params.lde_bits() >= cap_height);
-        let mut merkle_proof_len = params.lde_bits() - cap_height;
+        let commit_phase_cap_height = params.config.commit_phase_cap_height;
+        let mut layer_bits = params.lde_bits();
+        assert!(layer_bits >= cap_height);
 
         let initial_trees_proof =
-            self.add_virtual_fri_initial_trees_proof(num_leaves_per_oracle, merkle_proof_len);
+            self.add_virtual_fri_initial_trees_proof(num_leaves_per_oracle, oracles, params);
 
         let mut steps = vec![];
         for &arity_bits in &params.reduction_arity_bits {
-            assert!(merkle_proof_len >= arity_bits);
-            merkle_proof_len -= arity_bits;
-            steps.push(self.add_virtual_fri_query_step(arity_bits, merkle_proof_len));
+            assert!(layer_bits >= arity_bits + commit_phase_cap_height);
+            layer_bits -= arity_bits;
+            steps.push(
+                self.add_virtual_fri_query_step(arity_bits, layer_bits - commit_phase_cap_height),
+            );
         }
 
         FriQueryRoundTarget {
@@ -449,13 +511,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     fn add_virtual_fri_initial_trees_proof(
         &mut self,
         num_leaves_per_oracle: &[usize],
-        initial_merkle_proof_len: usize,
+        oracles: &[FriOracleInfo],
+        params: &FriParams,
     ) -> FriInitialTreeProofTarget {
         let evals_proofs = num_leaves_per_oracle
             .iter()
-            .map(|&num_oracle_leaves| {
+            .zip(oracles)
+            .map(|(&num_oracle_leaves, &oracle)| {
                 let leaves = self.add_virtual_targets(num_oracle_leaves);
-                let merkle_proof = self.add_virtual_merkle_proof(initial_merkle_proof_len);
+                let merkle_proof = self.add_virtual_merkle_proof(
+                    params.oracle_lde_bits(oracle) - params.config.cap_height,
+                );
                 (leaves, merkle_proof)
             })
             .collect();
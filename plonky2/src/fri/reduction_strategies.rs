@@ -18,6 +18,13 @@ pub enum FriReductionStrategy {
     /// optional max `arity_bits`. If this proof will have recursive proofs on top of it, a max
     /// `arity_bits` of 3 is recommended.
     MinSize(Option<usize>),
+
+    /// `MinProofSize(opt_max_arity_bits)` searches for the sequence of reduction arities, and hence
+    /// the final polynomial length, minimizing the FRI proof size estimated by `fri_proof_size`,
+    /// with an optional max `arity_bits`. Unlike `MinSize`, the estimate accounts for Merkle caps,
+    /// and every commit phase tree is kept at least as large as its cap. This suits proofs which
+    /// should be as small as possible, at some cost in prover time.
+    MinProofSize(Option<usize>),
 }
 
 impl FriReductionStrategy {
@@ -26,6 +33,7 @@ impl FriReductionStrategy {
         &self,
         mut degree_bits: usize,
         rate_bits: usize,
+        cap_height: usize,
         num_queries: usize,
     ) -> Vec<usize> {
         match self {
@@ -45,6 +53,14 @@ impl FriReductionStrategy {
             FriReductionStrategy::MinSize(opt_max_arity_bits) => {
                 min_size_arity_bits(degree_bits, rate_bits, num_queries, *opt_max_arity_bits)
             }
+
+            FriReductionStrategy::MinProofSize(opt_max_arity_bits) => min_proof_size_arity_bits(
+                degree_bits,
+                rate_bits,
+                cap_height,
+                num_queries,
+                *opt_max_arity_bits,
+            ),
         }
     }
 }
@@ -60,8 +76,14 @@ fn min_size_arity_bits(
     let max_arity_bits = opt_max_arity_bits.unwrap_or(4);
 
     let start = Instant::now();
-    let (mut arity_bits, fri_proof_size) =
-        min_size_arity_bits_helper(degree_bits, rate_bits, num_queries, max_arity_bits, vec![]);
+    let (mut arity_bits, fri_proof_size) = min_size_arity_bits_helper(
+        degree_bits,
+        rate_bits,
+        rate_bits,
+        max_arity_bits,
+        &|arity_bits| relative_proof_size(degree_bits, rate_bits, num_queries, arity_bits),
+        vec![],
+    );
     arity_bits.shrink_to_fit();
 
     debug!(
@@ -76,20 +98,56 @@ fn min_size_arity_bits(
     arity_bits
 }
 
-/// Return `(arity_bits, fri_proof_size)`.
-fn min_size_arity_bits_helper(
+fn min_proof_size_arity_bits(
     degree_bits: usize,
     rate_bits: usize,
+    cap_height: usize,
     num_queries: usize,
+    opt_max_arity_bits: Option<usize>,
+) -> Vec<usize> {
+    let max_arity_bits = opt_max_arity_bits.unwrap_or(4);
+
+    let start = Instant::now();
+    // Each reduction commits to a tree whose leaves are cosets of the reduced layer, so the reduced
+    // layer must have at least `2^cap_height` points.
+    let (mut arity_bits, size) = min_size_arity_bits_helper(
+        degree_bits,
+        rate_bits,
+        rate_bits.max(cap_height),
+        max_arity_bits,
+        &|arity_bits| fri_proof_size(degree_bits, rate_bits, cap_height, num_queries, arity_bits),
+        vec![],
+    );
+    arity_bits.shrink_to_fit();
+
+    debug!(
+        "min_proof_size_arity_bits took {:.3}s",
+        start.elapsed().as_secs_f32()
+    );
+    debug!(
+        "Smallest arity_bits {:?} results in estimated FRI proof size of {} elements",
+        arity_bits, size
+    );
+
+    arity_bits
+}
+
+/// Return `(arity_bits, fri_proof_size)` for the best sequence extending `prefix`, such that every
+/// layer has at least `2^min_layer_bits` points.
+fn min_size_arity_bits_helper(
+    degree_bits: usize,
+    rate_bits: usize,
+    min_layer_bits: usize,
     global_max_arity_bits: usize,
+    proof_size: &dyn Fn(&[usize]) -> usize,
     prefix: Vec<usize>,
 ) -> (Vec<usize>, usize) {
     let sum_of_arities: usize = prefix.iter().sum();
     let current_layer_bits = degree_bits + rate_bits - sum_of_arities;
-    assert!(current_layer_bits >= rate_bits);
+    assert!(current_layer_bits >= min_layer_bits);
 
     let mut best_arity_bits = prefix.clone();
-    let mut best_size = relative_proof_size(degree_bits, rate_bits, num_queries, &prefix);
+    let mut best_size = proof_size(&prefix);
 
     // The largest next_arity_bits to search. Note that any optimal arity sequence will be
     // monotonically non-increasing, as a larger arity will shrink more Merkle proofs if it occurs
@@ -98,7 +156,7 @@ fn min_size_arity_bits_helper(
         .last()
         .copied()
         .unwrap_or(global_max_arity_bits)
-        .min(current_layer_bits - rate_bits);
+        .min(current_layer_bits - min_layer_bits);
 
     for next_arity_bits in 1..=max_arity_bits {
         let mut extended_prefix = prefix.clone();
@@ -107,8 +165,9 @@ fn min_size_arity_bits_helper(
         let (arity_bits, size) = min_size_arity_bits_helper(
             degree_bits,
             rate_bits,
-            num_queries,
+            min_layer_bits,
             max_arity_bits,
+            proof_size,
             extended_prefix,
         );
         if size < best_size {
@@ -152,3 +211,125 @@ fn relative_proof_size(
 
     total_elems
 }
+
+/// Compute the number of field elements in the parts of an uncompressed FRI proof which depend on
+/// the reduction arities: the commit phase Merkle caps, the evaluations and Merkle proofs of each
+/// query step, the final polynomial's coefficients and the proof-of-work witness. Initial tree
+/// proofs are ignored. This assumes a quadratic extension field and hashes of 4 field elements, as
+/// in `PoseidonGoldilocksConfig`.
+pub fn fri_proof_size(
+    degree_bits: usize,
+    rate_bits: usize,
+    cap_height: usize,
+    num_queries: usize,
+    arity_bits: &[usize],
+) -> usize {
+    const D: usize = 2;
+    const HASH_ELEMS: usize = 4;
+
+    let mut current_layer_bits = degree_bits + rate_bits;
+
+    let mut total_elems = 0;
+    for &arity_bits in arity_bits {
+        let arity = 1 << arity_bits;
+        assert!(
+            current_layer_bits >= arity_bits + cap_height,
+            "Commit phase tree smaller than its cap"
+        );
+        current_layer_bits -= arity_bits;
+
+        // Add the commit phase cap.
+        total_elems += (1 << cap_height) * HASH_ELEMS;
+        // Add the coset evaluations, which are extension field elements.
+        total_elems += arity * D * num_queries;
+        // Add siblings in the Merkle path, which stops at the cap.
+        total_elems += (current_layer_bits - cap_height) * HASH_ELEMS * num_queries;
+    }
+
+    // Add the final polynomial's coefficients and the proof-of-work witness.
+    assert!(current_layer_bits >= rate_bits);
+    let final_poly_len = 1 << (current_layer_bits - rate_bits);
+    total_elems += D * final_poly_len + 1;
+
+    total_elems
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::fri::reduction_strategies::{fri_proof_size, FriReductionStrategy};
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_min_proof_size() {
+        let (degree_bits, rate_bits, cap_height, num_queries) = (20, 3, 4, 28);
+        let size = |strategy: FriReductionStrategy| {
+            let arity_bits =
+                strategy.reduction_arity_bits(degree_bits, rate_bits, cap_height, num_queries);
+            fri_proof_size(degree_bits, rate_bits, cap_height, num_queries, &arity_bits)
+        };
+
+        let min = size(FriReductionStrategy::MinProofSize(None));
+        assert!(min <= size(FriReductionStrategy::ConstantArityBits(4, 5)));
+        assert!(min <= size(FriReductionStrategy::ConstantArityBits(3, 5)));
+        assert!(min <= size(FriReductionStrategy::MinSize(None)));
+
+        // The search never builds a tree smaller than its cap.
+        let arity_bits = FriReductionStrategy::MinProofSize(Some(4)).reduction_arity_bits(
+            6,
+            rate_bits,
+            8,
+            num_queries,
+        );
+        assert!(arity_bits.iter().sum::<usize>() <= 6 + rate_bits - 8);
+    }
+
+    #[test]
+    fn test_fri_proof_size_matches_proof() -> Result<()> {
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::MinProofSize(None);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..1000 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+
+        let fri_proof = &proof.proof.opening_proof;
+        let caps_size = fri_proof
+            .commit_phase_merkle_caps
+            .iter()
+            .map(|cap| cap.0.len() * 4)
+            .sum::<usize>();
+        let steps_size = fri_proof
+            .query_round_proofs
+            .iter()
+            .flat_map(|round| &round.steps)
+            .map(|step| step.evals.len() * D + step.merkle_proof.siblings.len() * 4)
+            .sum::<usize>();
+        let final_poly_size = fri_proof.final_poly.len() * D;
+
+        let fri_config = &data.common.config.fri_config;
+        assert_eq!(
+            caps_size + steps_size + final_poly_size + 1,
+            fri_proof_size(
+                data.common.degree_bits,
+                fri_config.rate_bits,
+                fri_config.commit_phase_cap_height,
+                fri_config.num_query_rounds,
+                &data.common.fri_params.reduction_arity_bits,
+            )
+        );
+
+        data.verify(proof)
+    }
+}
@@ -26,6 +26,11 @@ pub struct FriInstanceInfoTarget<const D: usize> {
 #[derive(Copy, Clone)]
 pub struct FriOracleInfo {
     pub blinding: bool,
+    /// The number of bits by which the oracle's rate exceeds FRI's, i.e. its LDE is committed on a
+    /// domain `2^extra_rate_bits` times larger than the one FRI queries. FRI's domain is a subgroup
+    /// coset of the oracle's, whose points are the first leaves of the oracle's bit-reversed Merkle
+    /// tree, so a query opens the same leaf index in every oracle, with a longer Merkle proof.
+    pub extra_rate_bits: usize,
 }
 
 /// A batch of openings at a particular point.
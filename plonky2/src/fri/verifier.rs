@@ -1,16 +1,22 @@
-use anyhow::{ensure, Result};
+use std::collections::HashMap;
+
+use anyhow::{anyhow, ensure, Result};
 use plonky2_field::extension_field::{flatten, Extendable, FieldExtension};
 use plonky2_field::field_types::Field;
-use plonky2_field::interpolation::{barycentric_weights, interpolate};
+use plonky2_field::interpolation::BarycentricDomain;
 use plonky2_util::{log2_strict, reverse_index_bits_in_place};
 
-use crate::fri::proof::{FriChallenges, FriInitialTreeProof, FriProof, FriQueryRound};
-use crate::fri::structure::{FriBatchInfo, FriInstanceInfo, FriOpenings};
+use crate::fri::proof::{
+    CompressedFriProof, FriChallenges, FriInitialTreeProof, FriProof, FriQueryRound,
+};
+use crate::fri::structure::{FriBatchInfo, FriInstanceInfo, FriOpenings, FriOracleInfo};
 use crate::fri::{FriConfig, FriParams};
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::verify_merkle_proof;
 use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::path_compression::verify_compressed_merkle_proofs;
 use crate::plonk::config::{GenericConfig, Hasher};
+use crate::util::maybe_rayon::*;
 use crate::util::reducing::ReducingFactor;
 use crate::util::reverse_bits;
 
@@ -34,13 +40,7 @@ pub(crate) fn compute_evaluation<F: Field + Extendable<D>, const D: usize>(
     let rev_x_index_within_coset = reverse_bits(x_index_within_coset, arity_bits);
     let coset_start = x * g.exp_u64((arity - rev_x_index_within_coset) as u64);
     // The answer is gotten by interpolating {(x*g^i, P(x*g^i))} and evaluating at beta.
-    let points = g
-        .powers()
-        .map(|y| (coset_start * y).into())
-        .zip(evals)
-        .collect::<Vec<_>>();
-    let barycentric_weights = barycentric_weights(&points);
-    interpolate(&points, beta, &barycentric_weights)
+    BarycentricDomain::<F::Extension>::coset(arity_bits, coset_start.into()).evaluate(&evals, beta)
 }
 
 pub(crate) fn fri_verify_proof_of_work<F: RichField + Extendable<D>, const D: usize>(
@@ -61,11 +61,12 @@ pub fn verify_fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>
     openings: &FriOpenings<F, D>,
     challenges: &FriChallenges<F, D>,
     initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
-    proof: &FriProof<F, C::Hasher, D>,
+    proof: &FriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
     params: &FriParams,
 ) -> Result<()>
 where
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     ensure!(
         params.final_poly_len() == proof.final_poly.len(),
@@ -86,21 +87,192 @@ where
 
     let precomputed_reduced_evals =
         PrecomputedReducedOpenings::from_os_and_alpha(openings, challenges.fri_alpha);
-    for (&x_index, round_proof) in challenges
+    let log_n = log2_strict(n);
+    let subgroup_xs = challenges
         .fri_query_indices
         .iter()
+        .map(|&x_index| fri_query_subgroup_x(x_index, log_n))
+        .collect::<Vec<_>>();
+    let denominator_invs = fri_combine_denominator_invs(instance, &subgroup_xs);
+    // Query rounds are independent, so we check them in parallel.
+    challenges
+        .fri_query_indices
+        .par_iter()
         .zip(&proof.query_round_proofs)
+        .zip(&denominator_invs)
+        .try_for_each(|((&x_index, round_proof), denominator_invs)| {
+            fri_verifier_query_round::<F, C, D>(
+                instance,
+                challenges,
+                &precomputed_reduced_evals,
+                denominator_invs,
+                initial_merkle_caps,
+                proof,
+                x_index,
+                n,
+                round_proof,
+                params,
+            )
+        })
+}
+
+/// Verifies a compressed FRI proof without decompressing it. Each query round infers the
+/// evaluation omitted from each step, and the Merkle proofs of each tree are checked together in
+/// their compressed form, so that shared paths are neither copied nor hashed more than once.
+pub(crate) fn verify_compressed_fri_proof<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    instance: &FriInstanceInfo<F, D>,
+    openings: &FriOpenings<F, D>,
+    challenges: &FriChallenges<F, D>,
+    initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    proof: &CompressedFriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
+    params: &FriParams,
+) -> Result<()>
+where
+    [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
+{
+    ensure!(
+        params.final_poly_len() == proof.final_poly.len(),
+        "Final polynomial has wrong degree."
+    );
+
+    // Check PoW.
+    fri_verify_proof_of_work(challenges.fri_pow_response, &params.config)?;
+
+    // Check that parameters are coherent.
+    let rounds = &proof.query_round_proofs;
+    let reduction_arity_bits = &params.reduction_arity_bits;
+    let num_reductions = reduction_arity_bits.len();
+    ensure!(
+        proof.commit_phase_merkle_caps.len() == num_reductions
+            && rounds.steps.len() == num_reductions,
+        "Number of reductions does not match config."
+    );
+
+    let precomputed_reduced_evals =
+        PrecomputedReducedOpenings::from_os_and_alpha(openings, challenges.fri_alpha);
+    let log_n = log2_strict(params.lde_size());
+    let subgroup_xs = challenges
+        .fri_query_indices
+        .iter()
+        .map(|&x_index| fri_query_subgroup_x(x_index, log_n))
+        .collect::<Vec<_>>();
+    let denominator_invs = fri_combine_denominator_invs(instance, &subgroup_xs);
+
+    // The distinct initial indices, and at each reduction the distinct coset indices, in the order
+    // in which they were first queried, which is the order the proofs were compressed in.
+    let mut initial_indices = Vec::new();
+    let mut coset_indices = vec![Vec::new(); num_reductions];
+    // The evaluations on each coset reached so far at each reduction, with the inferred evaluation
+    // reinserted.
+    let mut cosets_evals = vec![HashMap::<usize, Vec<F::Extension>>::new(); num_reductions];
+    'rounds: for ((&(mut x_index), &(mut subgroup_x)), denominator_invs) in challenges
+        .fri_query_indices
+        .iter()
+        .zip(&subgroup_xs)
+        .zip(&denominator_invs)
     {
-        fri_verifier_query_round::<F, C, D>(
+        let initial_trees_proof = rounds
+            .initial_trees_proofs
+            .get(&x_index)
+            .ok_or_else(|| anyhow!("Missing initial trees proof."))?;
+        if !initial_indices.contains(&x_index) {
+            initial_indices.push(x_index);
+        }
+        let mut old_eval = fri_combine_initial::<F, C, D>(
             instance,
-            challenges,
+            initial_trees_proof,
+            challenges.fri_alpha,
+            subgroup_x,
             &precomputed_reduced_evals,
-            initial_merkle_caps,
-            proof,
-            x_index,
-            n,
-            round_proof,
+            denominator_invs,
             params,
+        );
+
+        for (i, &arity_bits) in reduction_arity_bits.iter().enumerate() {
+            let arity = 1 << arity_bits;
+            let coset_index = x_index >> arity_bits;
+            let x_index_within_coset = x_index & (arity - 1);
+
+            if let Some(evals) = cosets_evals[i].get(&coset_index) {
+                // An earlier round has already checked the rest of the path from this coset, so
+                // only consistency with our old evaluation remains to be checked.
+                ensure!(evals[x_index_within_coset] == old_eval);
+                continue 'rounds;
+            }
+            let mut evals = rounds.steps[i]
+                .get(&coset_index)
+                .ok_or_else(|| anyhow!("Missing query step."))?
+                .evals
+                .clone();
+            ensure!(evals.len() == arity - 1, "Query step has wrong arity.");
+            // The omitted evaluation is our old evaluation, so consistency holds by construction.
+            evals.insert(x_index_within_coset, old_eval);
+
+            // Infer P(y) from {P(x)}_{x^arity=y}.
+            old_eval = compute_evaluation(
+                subgroup_x,
+                x_index_within_coset,
+                arity_bits,
+                &evals,
+                challenges.fri_betas[i],
+            );
+            cosets_evals[i].insert(coset_index, evals);
+            coset_indices[i].push(coset_index);
+
+            // Update the point x to x^arity.
+            subgroup_x = subgroup_x.exp_power_of_2(arity_bits);
+
+            x_index = coset_index;
+        }
+
+        // Final check of FRI. After all the reductions, we check that the final polynomial is equal
+        // to the one sent by the prover.
+        ensure!(
+            proof.final_poly.eval(subgroup_x.into()) == old_eval,
+            "Final polynomial evaluation is invalid."
+        );
+    }
+
+    for (j, (cap, &oracle)) in initial_merkle_caps
+        .iter()
+        .zip(&instance.oracles)
+        .enumerate()
+    {
+        let openings = initial_indices
+            .iter()
+            .map(|&i| {
+                let (evals, merkle_proof) = &rounds.initial_trees_proofs[&i].evals_proofs[j];
+                (evals.as_slice(), i, merkle_proof)
+            })
+            .collect::<Vec<_>>();
+        verify_compressed_merkle_proofs::<F, C::Hasher>(
+            &openings,
+            params.oracle_lde_bits(oracle),
+            cap,
+        )?;
+    }
+
+    let mut height = log_n;
+    for (i, &arity_bits) in reduction_arity_bits.iter().enumerate() {
+        height -= arity_bits;
+        let leaves = coset_indices[i]
+            .iter()
+            .map(|c| flatten(&cosets_evals[i][c]))
+            .collect::<Vec<_>>();
+        let openings = coset_indices[i]
+            .iter()
+            .zip(&leaves)
+            .map(|(&c, leaf)| (leaf.as_slice(), c, &rounds.steps[i][&c].merkle_proof))
+            .collect::<Vec<_>>();
+        verify_compressed_merkle_proofs::<F, C::CommitPhaseHasher>(
+            &openings,
+            height,
+            &proof.commit_phase_merkle_caps[i],
         )?;
     }
 
@@ -111,17 +283,59 @@ fn fri_verify_initial_proof<F: RichField, H: Hasher<F>>(
     x_index: usize,
     proof: &FriInitialTreeProof<F, H>,
     initial_merkle_caps: &[MerkleCap<F, H>],
+    oracles: &[FriOracleInfo],
+    params: &FriParams,
 ) -> Result<()>
 where
     [(); H::HASH_SIZE]:,
 {
-    for ((evals, merkle_proof), cap) in proof.evals_proofs.iter().zip(initial_merkle_caps) {
+    for (((evals, merkle_proof), cap), &oracle) in proof
+        .evals_proofs
+        .iter()
+        .zip(initial_merkle_caps)
+        .zip(oracles)
+    {
+        ensure!(
+            params.config.cap_height + merkle_proof.siblings.len()
+                == params.oracle_lde_bits(oracle),
+            "Initial Merkle proof has wrong length."
+        );
         verify_merkle_proof::<F, H>(evals.clone(), x_index, cap, merkle_proof)?;
     }
 
     Ok(())
 }
 
+/// The element of the LDE coset at index `x_index`, in bit-reversed order.
+pub(crate) fn fri_query_subgroup_x<F: RichField>(x_index: usize, log_n: usize) -> F {
+    F::MULTIPLICATIVE_GROUP_GENERATOR
+        * F::primitive_root_of_unity(log_n).exp_u64(reverse_bits(x_index, log_n) as u64)
+}
+
+/// Computes the inverses of the denominators `x - point` of `fri_combine_initial`, for each query
+/// point `x` and each batch of `instance`, with a single batch inversion.
+pub(crate) fn fri_combine_denominator_invs<F: RichField + Extendable<D>, const D: usize>(
+    instance: &FriInstanceInfo<F, D>,
+    subgroup_xs: &[F],
+) -> Vec<Vec<F::Extension>> {
+    let num_batches = instance.batches.len();
+    let denominators = subgroup_xs
+        .iter()
+        .flat_map(|&x| {
+            instance
+                .batches
+                .iter()
+                .map(move |batch| F::Extension::from_basefield(x) - batch.point)
+        })
+        .collect::<Vec<_>>();
+    let invs = F::Extension::batch_multiplicative_inverse(&denominators);
+    (0..subgroup_xs.len())
+        .map(|i| invs[i * num_batches..(i + 1) * num_batches].to_vec())
+        .collect()
+}
+
+/// Combines the initial openings at `subgroup_x`, given the inverses of its denominators, as
+/// computed by `fri_combine_denominator_invs`.
 pub(crate) fn fri_combine_initial<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -132,6 +346,7 @@ pub(crate) fn fri_combine_initial<
     alpha: F::Extension,
     subgroup_x: F,
     precomputed_reduced_evals: &PrecomputedReducedOpenings<F, D>,
+    denominator_invs: &[F::Extension],
     params: &FriParams,
 ) -> F::Extension {
     assert!(D > 1, "Not implemented for D=1.");
@@ -139,30 +354,43 @@ pub(crate) fn fri_combine_initial<
     let mut alpha = ReducingFactor::new(alpha);
     let mut sum = F::Extension::ZERO;
 
-    for (batch, reduced_openings) in instance
+    for ((batch, reduced_openings), denominator_inv) in instance
         .batches
         .iter()
         .zip(&precomputed_reduced_evals.reduced_openings_at_point)
+        .zip(denominator_invs)
     {
-        let FriBatchInfo { point, polynomials } = batch;
+        let FriBatchInfo { polynomials, .. } = batch;
         let evals = polynomials
             .iter()
             .map(|p| {
-                let poly_blinding = instance.oracles[p.oracle_index].blinding;
-                let salted = params.hiding && poly_blinding;
-                proof.unsalted_eval(p.oracle_index, p.polynomial_index, salted)
+                let salt_size = params.oracle_salt_size(instance.oracles[p.oracle_index]);
+                proof.unsalted_eval(p.oracle_index, p.polynomial_index, salt_size)
             })
             .map(F::Extension::from_basefield);
         let reduced_evals = alpha.reduce(evals);
         let numerator = reduced_evals - *reduced_openings;
-        let denominator = subgroup_x - *point;
         sum = alpha.shift(sum);
-        sum += numerator / denominator;
+        sum += numerator * *denominator_inv;
     }
 
     // Multiply the final polynomial by `X`, so that `final_poly` has the maximum degree for
     // which the LDT will pass. See github.com/mir-protocol/plonky2/pull/436 for details.
-    sum * subgroup_x
+    let sum = sum * subgroup_x;
+
+    // Add the masking polynomials, whose coordinates are the salts of the masked oracles.
+    let masking_evals = params
+        .masked_oracles(&instance.oracles)
+        .into_iter()
+        .map(|i| {
+            F::Extension::from_basefield_array(proof.salt(i, params.salt_size).try_into().unwrap())
+        })
+        .collect::<Vec<_>>();
+    if masking_evals.is_empty() {
+        return sum;
+    }
+    let reduced_masking_evals = alpha.reduce(masking_evals.iter());
+    alpha.shift(sum) + reduced_masking_evals
 }
 
 fn fri_verifier_query_round<
@@ -173,25 +401,27 @@ fn fri_verifier_query_round<
     instance: &FriInstanceInfo<F, D>,
     challenges: &FriChallenges<F, D>,
     precomputed_reduced_evals: &PrecomputedReducedOpenings<F, D>,
+    denominator_invs: &[F::Extension],
     initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
-    proof: &FriProof<F, C::Hasher, D>,
+    proof: &FriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
     mut x_index: usize,
     n: usize,
-    round_proof: &FriQueryRound<F, C::Hasher, D>,
+    round_proof: &FriQueryRound<F, C::Hasher, C::CommitPhaseHasher, D>,
     params: &FriParams,
 ) -> Result<()>
 where
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     fri_verify_initial_proof::<F, C::Hasher>(
         x_index,
         &round_proof.initial_trees_proof,
         initial_merkle_caps,
+        &instance.oracles,
+        params,
     )?;
     // `subgroup_x` is `subgroup[x_index]`, i.e., the actual field element in the domain.
-    let log_n = log2_strict(n);
-    let mut subgroup_x = F::MULTIPLICATIVE_GROUP_GENERATOR
-        * F::primitive_root_of_unity(log_n).exp_u64(reverse_bits(x_index, log_n) as u64);
+    let mut subgroup_x = fri_query_subgroup_x::<F>(x_index, log2_strict(n));
 
     // old_eval is the last derived evaluation; it will be checked for consistency with its
     // committed "parent" value in the next iteration.
@@ -201,6 +431,7 @@ where
         challenges.fri_alpha,
         subgroup_x,
         precomputed_reduced_evals,
+        denominator_invs,
         params,
     );
 
@@ -224,7 +455,7 @@ where
             challenges.fri_betas[i],
         );
 
-        verify_merkle_proof::<F, C::Hasher>(
+        verify_merkle_proof::<F, C::CommitPhaseHasher>(
             flatten(evals),
             coset_index,
             &proof.commit_phase_merkle_caps[i],
@@ -7,14 +7,15 @@ use crate::iop::witness::Witness;
 use crate::plonk::config::AlgebraicHasher;
 
 /// Set the targets in a `FriProofTarget` to their corresponding values in a `FriProof`.
-pub fn set_fri_proof_target<F, W, H, const D: usize>(
+pub fn set_fri_proof_target<F, W, H, CH, const D: usize>(
     witness: &mut W,
     fri_proof_target: &FriProofTarget<D>,
-    fri_proof: &FriProof<F, H, D>,
+    fri_proof: &FriProof<F, H, CH, D>,
 ) where
     F: RichField + Extendable<D>,
     W: Witness<F> + ?Sized,
     H: AlgebraicHasher<F>,
+    CH: AlgebraicHasher<F>,
 {
     witness.set_target(fri_proof_target.pow_witness, fri_proof.pow_witness);
 
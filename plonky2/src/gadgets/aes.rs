@@ -0,0 +1,296 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+#[rustfmt::skip]
+const AES_SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+/// Round constants used by the AES-128 key schedule.
+const AES_RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+const AES128_ROUNDS: usize = 10;
+
+/// A byte, represented by its little-endian bits.
+type ByteBits = Vec<BoolTarget>;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Encrypts a single 16-byte block with AES-128. Key and block bytes are range-checked.
+    pub fn aes128_encrypt_block(
+        &mut self,
+        key: &[Target; 16],
+        block: &[Target; 16],
+    ) -> [Target; 16] {
+        let round_keys = self.aes128_expand_key(key);
+        let block = block.iter().map(|&b| self.split_byte_le(b)).collect();
+        let output = self.aes128_encrypt_block_bits(&round_keys, block);
+        let mut res = [self.zero(); 16];
+        for (r, o) in res.iter_mut().zip(output) {
+            *r = self.le_bits_to_target(&o);
+        }
+        res
+    }
+
+    /// Encrypts (or, equivalently, decrypts) a message of bytes with AES-128 in counter mode. The
+    /// counter block for the `i`th message block is `nonce || BE32(initial_counter + i)`. Key,
+    /// nonce and message bytes are range-checked.
+    ///
+    /// To prove encryption under a committed key, the caller can additionally constrain a hash of
+    /// `key` to a public value.
+    pub fn aes128_ctr(
+        &mut self,
+        key: &[Target; 16],
+        nonce: &[Target; 12],
+        initial_counter: u32,
+        message: &[Target],
+    ) -> Vec<Target> {
+        let round_keys = self.aes128_expand_key(key);
+        let nonce = nonce
+            .iter()
+            .map(|&b| self.split_byte_le(b))
+            .collect::<Vec<_>>();
+
+        let mut res = Vec::with_capacity(message.len());
+        for (i, chunk) in message.chunks(16).enumerate() {
+            let counter = initial_counter.wrapping_add(i as u32);
+            let mut counter_block = nonce.clone();
+            for b in counter.to_be_bytes() {
+                counter_block.push(self.constant_le_bits(b as u64, 8));
+            }
+            let keystream = self.aes128_encrypt_block_bits(&round_keys, counter_block);
+            for (&m, k) in chunk.iter().zip(keystream) {
+                let m = self.split_byte_le(m);
+                let c = self.xor_bits(&m, &k);
+                res.push(self.le_bits_to_target(&c));
+            }
+        }
+        res
+    }
+
+    /// Applies the AES S-box to a byte. The lookup is done in two levels: the low nibble selects
+    /// an entry from each of 16 rows of the S-box table, then the high nibble selects a row.
+    fn aes_sub_byte(&mut self, x: &[BoolTarget]) -> ByteBits {
+        let low = self.le_bits_to_target(&x[..4]);
+        let high = self.le_bits_to_target(&x[4..]);
+        let candidates = (0..16)
+            .map(|row| {
+                let table = AES_SBOX[16 * row..16 * (row + 1)]
+                    .iter()
+                    .map(|&s| self.constant(F::from_canonical_u16(s as u16)))
+                    .collect();
+                self.aes_table_lookup(low, table)
+            })
+            .collect();
+        let res = self.aes_table_lookup(high, candidates);
+        self.split_byte_le(res)
+    }
+
+    fn aes_table_lookup(&mut self, index: Target, table: Vec<Target>) -> Target {
+        let claimed_element = self.add_virtual_target();
+        self.random_access(index, claimed_element, table);
+        claimed_element
+    }
+
+    /// Multiplication by `x` in `GF(2^8)`, modulo the AES polynomial `x^8 + x^4 + x^3 + x + 1`.
+    fn aes_xtime(&mut self, x: &[BoolTarget]) -> ByteBits {
+        let high = x[7];
+        let mut res = vec![high, x[0], x[1], x[2], x[3], x[4], x[5], x[6]];
+        for i in [1, 3, 4] {
+            res[i] = self.xor(res[i], high);
+        }
+        res
+    }
+
+    /// Computes the 11 AES-128 round keys, each as 16 bytes.
+    fn aes128_expand_key(&mut self, key: &[Target; 16]) -> Vec<Vec<ByteBits>> {
+        let mut words = key
+            .chunks(4)
+            .map(|w| w.iter().map(|&b| self.split_byte_le(b)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        for i in 4..4 * (AES128_ROUNDS + 1) {
+            let mut temp = words[i - 1].clone();
+            if i % 4 == 0 {
+                temp.rotate_left(1);
+                temp = temp.iter().map(|b| self.aes_sub_byte(b)).collect();
+                let rcon = self.constant_le_bits(AES_RCON[i / 4 - 1] as u64, 8);
+                temp[0] = self.xor_bits(&temp[0], &rcon);
+            }
+            let word = words[i - 4]
+                .iter()
+                .zip(&temp)
+                .map(|(a, b)| self.xor_bits(a, b))
+                .collect();
+            words.push(word);
+        }
+        words
+            .chunks(4)
+            .map(|round_key| round_key.concat())
+            .collect()
+    }
+
+    fn aes128_encrypt_block_bits(
+        &mut self,
+        round_keys: &[Vec<ByteBits>],
+        block: Vec<ByteBits>,
+    ) -> Vec<ByteBits> {
+        let mut state = self.aes_add_round_key(&block, &round_keys[0]);
+        for round in 1..=AES128_ROUNDS {
+            state = state.iter().map(|b| self.aes_sub_byte(b)).collect();
+            state = aes_shift_rows(&state);
+            if round != AES128_ROUNDS {
+                state = self.aes_mix_columns(&state);
+            }
+            state = self.aes_add_round_key(&state, &round_keys[round]);
+        }
+        state
+    }
+
+    fn aes_add_round_key(&mut self, state: &[ByteBits], round_key: &[ByteBits]) -> Vec<ByteBits> {
+        state
+            .iter()
+            .zip(round_key)
+            .map(|(s, k)| self.xor_bits(s, k))
+            .collect()
+    }
+
+    fn aes_mix_columns(&mut self, state: &[ByteBits]) -> Vec<ByteBits> {
+        let mut res = Vec::with_capacity(16);
+        for column in state.chunks(4) {
+            for i in 0..4 {
+                let (a0, a1, a2, a3) = (
+                    &column[i],
+                    &column[(i + 1) % 4],
+                    &column[(i + 2) % 4],
+                    &column[(i + 3) % 4],
+                );
+                // b_i = 2 a_i + 3 a_{i+1} + a_{i+2} + a_{i+3}, where 2 a = xtime(a).
+                let mut b = self.xor_bits(a0, a1);
+                b = self.aes_xtime(&b);
+                b = self.xor_bits(&b, a1);
+                b = self.xor_bits(&b, a2);
+                b = self.xor_bits(&b, a3);
+                res.push(b);
+            }
+        }
+        res
+    }
+}
+
+/// The state is stored in column-major order, so row `r` of column `c` is at index `r + 4c`.
+/// Row `r` is rotated left by `r` positions.
+fn aes_shift_rows(state: &[ByteBits]) -> Vec<ByteBits> {
+    (0..16)
+        .map(|i| {
+            let (row, column) = (i % 4, i / 4);
+            state[row + 4 * ((column + row) % 4)].clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::iop::target::Target;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn constant_bytes(builder: &mut CircuitBuilder<F, D>, bytes: &[u8]) -> Vec<Target> {
+        bytes
+            .iter()
+            .map(|&b| builder.constant(F::from_canonical_u16(b as u16)))
+            .collect()
+    }
+
+    #[test]
+    fn test_aes128_encrypt_block() -> Result<()> {
+        // Test vector from FIPS-197, appendix C.1.
+        let key = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+            0x0e, 0x0f,
+        ];
+        let plaintext = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+            0xee, 0xff,
+        ];
+        let ciphertext = [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4,
+            0xc5, 0x5a,
+        ];
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let key = constant_bytes(&mut builder, &key);
+        let plaintext = constant_bytes(&mut builder, &plaintext);
+        let output =
+            builder.aes128_encrypt_block(&key.try_into().unwrap(), &plaintext.try_into().unwrap());
+        let expected = constant_bytes(&mut builder, &ciphertext);
+        for (o, e) in output.into_iter().zip(expected) {
+            builder.connect(o, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_aes128_ctr() -> Result<()> {
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let nonce = [
+            0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb,
+        ];
+        let plaintext = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93,
+            0x17, 0x2a, 0xae, 0x2d, 0x8a, 0x57,
+        ];
+        let ciphertext = [
+            0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d,
+            0xb6, 0xce, 0x98, 0x06, 0xf6, 0x6b,
+        ];
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let key = constant_bytes(&mut builder, &key).try_into().unwrap();
+        let nonce = constant_bytes(&mut builder, &nonce).try_into().unwrap();
+        let plaintext = constant_bytes(&mut builder, &plaintext);
+        let output = builder.aes128_ctr(&key, &nonce, 0xfcfdfeff, &plaintext);
+        let expected = constant_bytes(&mut builder, &ciphertext);
+        for (o, e) in output.into_iter().zip(expected) {
+            builder.connect(o, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -2,7 +2,9 @@ use std::borrow::Borrow;
 
 use plonky2_field::extension_field::Extendable;
 use plonky2_field::field_types::Field64;
+use plonky2_util::ceil_div_usize;
 
+use crate::gadgets::biguint::BigUintTarget;
 use crate::gates::arithmetic_base::ArithmeticGate;
 use crate::gates::exponentiation::ExponentiationGate;
 use crate::hash::hash_types::RichField;
@@ -59,6 +61,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             return result;
         }
 
+        // A constant multiple of a constant multiple of `y` is a single constant multiple of `y`.
+        let scaling =
+            self.arithmetic_as_scaling(const_0, const_1, multiplicand_0, multiplicand_1, addend);
+        if let Some((scale, x)) = scaling {
+            if let Some(&(inner_scale, y)) = self.base_scalings.get(&x) {
+                return self.mul_const(scale * inner_scale, y);
+            }
+        }
+
         // See if we've already computed the same operation.
         let operation = BaseArithmeticOperation {
             const_0,
@@ -74,9 +85,41 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         // Otherwise, we must actually perform the operation using an ArithmeticExtensionGate slot.
         let result = self.add_base_arithmetic_operation(operation);
         self.base_arithmetic_results.insert(operation, result);
+        if let Some(scaling) = scaling {
+            self.base_scalings.insert(result, scaling);
+        }
         result
     }
 
+    /// If `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend` is a constant multiple of
+    /// a single non-constant target, returns the constant and the target.
+    fn arithmetic_as_scaling(
+        &self,
+        const_0: F,
+        const_1: F,
+        multiplicand_0: Target,
+        multiplicand_1: Target,
+        addend: Target,
+    ) -> Option<(F, Target)> {
+        let mul_0_const = self.target_as_constant(multiplicand_0);
+        let mul_1_const = self.target_as_constant(multiplicand_1);
+        let addend_const = self.target_as_constant(addend);
+
+        let first_term_zero =
+            const_0 == F::ZERO || mul_0_const == Some(F::ZERO) || mul_1_const == Some(F::ZERO);
+        let second_term_zero = const_1 == F::ZERO || addend_const == Some(F::ZERO);
+
+        match (first_term_zero, second_term_zero) {
+            (false, true) => match (mul_0_const, mul_1_const) {
+                (Some(c), None) => Some((const_0 * c, multiplicand_1)),
+                (None, Some(c)) => Some((const_0 * c, multiplicand_0)),
+                _ => None,
+            },
+            (true, false) if addend_const.is_none() => Some((const_1, addend)),
+            _ => None,
+        }
+    }
+
     fn add_base_arithmetic_operation(&mut self, operation: BaseArithmeticOperation<F>) -> Target {
         let (gate, i) = self.find_base_arithmetic_gate(operation.const_0, operation.const_1);
         let wires_multiplicand_0 = Target::wire(gate, ArithmeticGate::wire_ith_multiplicand_0(i));
@@ -214,7 +257,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     pub fn exp_power_of_2(&mut self, base: Target, power_log: usize) -> Target {
         if power_log > self.num_base_arithmetic_ops_per_gate() {
             // Cheaper to just use `ExponentiateGate`.
-            return self.exp_u64(base, 1 << power_log);
+            let _false = self._false();
+            let _true = self._true();
+            let mut exponent_bits = vec![_false; power_log];
+            exponent_bits.push(_true);
+            return self.exp_from_bits(base, exponent_bits);
         }
 
         let mut product = base;
@@ -224,18 +271,54 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         product
     }
 
-    // TODO: Test
-    /// Exponentiate `base` to the power of `exponent`, given by its little-endian bits.
+    /// Exponentiate `base` to the power of `exponent`, given by its little-endian bits. The exponent
+    /// may be wider than an `ExponentiationGate`, in which case it is split into chunks `e_j` of `m`
+    /// bits, each handled by its own gate, using `base^e = prod_j (base^(2^(m j)))^(e_j)`.
     pub fn exp_from_bits(
         &mut self,
         base: Target,
         exponent_bits: impl IntoIterator<Item = impl Borrow<BoolTarget>>,
+    ) -> Target {
+        let num_power_bits =
+            ExponentiationGate::<F, D>::new_from_config(&self.config).num_power_bits;
+        let exponent_bits: Vec<BoolTarget> =
+            exponent_bits.into_iter().map(|b| *b.borrow()).collect();
+        if exponent_bits.len() <= num_power_bits {
+            return self.exp_from_bits_single_gate(base, exponent_bits);
+        }
+
+        // Leave room for one more bit, so that a single gate can raise each chunk's base to the
+        // power of `2^m` to get the next one.
+        let chunk_size = num_power_bits - 1;
+        let _false = self._false();
+        let _true = self._true();
+        let mut next_base_bits = vec![_false; chunk_size];
+        next_base_bits.push(_true);
+
+        let num_chunks = ceil_div_usize(exponent_bits.len(), chunk_size);
+        let mut chunk_base = base;
+        let mut product = self.one();
+        for (i, chunk) in exponent_bits.chunks(chunk_size).enumerate() {
+            let chunk_power = self.exp_from_bits_single_gate(chunk_base, chunk.to_vec());
+            product = self.mul(product, chunk_power);
+            if i + 1 < num_chunks {
+                chunk_base = self.exp_from_bits_single_gate(chunk_base, next_base_bits.clone());
+            }
+        }
+        product
+    }
+
+    /// Exponentiate `base` using a single `ExponentiationGate`, which must be wide enough for the
+    /// exponent.
+    fn exp_from_bits_single_gate(
+        &mut self,
+        base: Target,
+        exponent_bits: Vec<BoolTarget>,
     ) -> Target {
         let _false = self._false();
         let gate = ExponentiationGate::new_from_config(&self.config);
         let num_power_bits = gate.num_power_bits;
-        let mut exp_bits_vec: Vec<BoolTarget> =
-            exponent_bits.into_iter().map(|b| *b.borrow()).collect();
+        let mut exp_bits_vec = exponent_bits;
         while exp_bits_vec.len() < num_power_bits {
             exp_bits_vec.push(_false);
         }
@@ -257,6 +340,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.exp_from_bits(base, exponent_bits.iter())
     }
 
+    /// Exponentiate `base` to the power of an arbitrarily large `exponent`.
+    pub fn exp_biguint(&mut self, base: Target, exponent: &BigUintTarget) -> Target {
+        let exponent_bits = exponent
+            .limbs
+            .iter()
+            .flat_map(|limb| self.split_le(limb.0, 32))
+            .collect::<Vec<_>>();
+
+        self.exp_from_bits(base, exponent_bits)
+    }
+
     /// Like `exp_from_bits` but with a constant base.
     pub fn exp_from_bits_const_base(
         &mut self,
@@ -321,6 +415,25 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let res = self.sub(one, b.target);
         BoolTarget::new_unsafe(res)
     }
+
+    /// Computes `x AND y`.
+    pub fn and(&mut self, x: BoolTarget, y: BoolTarget) -> BoolTarget {
+        BoolTarget::new_unsafe(self.mul(x.target, y.target))
+    }
+
+    /// Computes `x OR y`, as `x + y - xy`.
+    pub fn or(&mut self, x: BoolTarget, y: BoolTarget) -> BoolTarget {
+        let sum = self.add(x.target, y.target);
+        let res = self.arithmetic(F::NEG_ONE, F::ONE, x.target, y.target, sum);
+        BoolTarget::new_unsafe(res)
+    }
+
+    /// Computes `x XOR y`, as `x + y - 2xy`.
+    pub fn xor(&mut self, x: BoolTarget, y: BoolTarget) -> BoolTarget {
+        let sum = self.add(x.target, y.target);
+        let res = self.arithmetic(-F::TWO, F::ONE, x.target, y.target, sum);
+        BoolTarget::new_unsafe(res)
+    }
 }
 
 /// Represents a base arithmetic operation in the circuit. Used to memoize results.
@@ -332,3 +445,62 @@ pub(crate) struct BaseArithmeticOperation<F: Field64> {
     multiplicand_1: Target,
     addend: Target,
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2_field::field_types::Field;
+    use rand::Rng;
+
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_exp_biguint() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = rand::thread_rng();
+        let base = F::rand();
+        let exponent = BigUint::from_slice(&[rng.gen(), rng.gen(), rng.gen(), rng.gen()]);
+        let expected = base.exp_biguint(&exponent);
+
+        let base_t = builder.add_virtual_target();
+        pw.set_target(base_t, base);
+        let exponent_t = builder.add_virtual_biguint_target(4);
+        pw.set_biguint_target(&exponent_t, &exponent);
+        let result = builder.exp_biguint(base_t, &exponent_t);
+        let expected_t = builder.constant(expected);
+        builder.connect(result, expected_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_exp_power_of_2_large() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let base = F::rand();
+        let base_t = builder.add_virtual_target();
+        pw.set_target(base_t, base);
+        let result = builder.exp_power_of_2(base_t, 100);
+        let expected_t = builder.constant(base.exp_power_of_2(100));
+        builder.connect(result, expected_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
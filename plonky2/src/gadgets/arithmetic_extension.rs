@@ -1,9 +1,11 @@
+use plonky2_field::constant_time::ct_div_extension;
 use plonky2_field::extension_field::FieldExtension;
 use plonky2_field::extension_field::{Extendable, OEF};
 use plonky2_field::field_types::{Field, Field64};
 use plonky2_util::bits_u64;
 
 use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
+use crate::gates::inner_product_extension::InnerProductExtensionGate;
 use crate::gates::multiplication_extension::MulExtensionGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::{ExtensionAlgebraTarget, ExtensionTarget};
@@ -32,6 +34,20 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             return result;
         }
 
+        // A constant multiple of a constant multiple of `y` is a single constant multiple of `y`.
+        let scaling = self.arithmetic_extension_as_scaling(
+            const_0,
+            const_1,
+            multiplicand_0,
+            multiplicand_1,
+            addend,
+        );
+        if let Some((scale, x)) = scaling {
+            if let Some(&(inner_scale, y)) = self.extension_scalings.get(&x) {
+                return self.mul_const_extension(scale * inner_scale, y);
+            }
+        }
+
         // See if we've already computed the same operation.
         let operation = ExtensionArithmeticOperation {
             const_0,
@@ -53,9 +69,49 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         };
         // Otherwise, we must actually perform the operation using an ArithmeticExtensionGate slot.
         self.arithmetic_results.insert(operation, result);
+        if let Some(scaling) = scaling {
+            self.extension_scalings.insert(result, scaling);
+        }
         result
     }
 
+    /// If `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend` is a base field constant
+    /// multiple of a single non-constant target, returns the constant and the target.
+    fn arithmetic_extension_as_scaling(
+        &self,
+        const_0: F,
+        const_1: F,
+        multiplicand_0: ExtensionTarget<D>,
+        multiplicand_1: ExtensionTarget<D>,
+        addend: ExtensionTarget<D>,
+    ) -> Option<(F, ExtensionTarget<D>)> {
+        let mul_0_const = self.target_as_constant_ext(multiplicand_0);
+        let mul_1_const = self.target_as_constant_ext(multiplicand_1);
+        let addend_const = self.target_as_constant_ext(addend);
+        let zero = Some(F::Extension::ZERO);
+
+        let first_term_zero = const_0 == F::ZERO || mul_0_const == zero || mul_1_const == zero;
+        let second_term_zero = const_1 == F::ZERO || addend_const == zero;
+
+        let base_field_const = |c: F::Extension| {
+            let coeffs = c.to_basefield_array();
+            if coeffs[1..].iter().all(|x| x.is_zero()) {
+                Some(coeffs[0])
+            } else {
+                None
+            }
+        };
+        match (first_term_zero, second_term_zero) {
+            (false, true) => match (mul_0_const, mul_1_const) {
+                (Some(c), None) => base_field_const(c).map(|c| (const_0 * c, multiplicand_1)),
+                (None, Some(c)) => base_field_const(c).map(|c| (const_0 * c, multiplicand_0)),
+                _ => None,
+            },
+            (true, false) if addend_const.is_none() => Some((const_1, addend)),
+            _ => None,
+        }
+    }
+
     fn compute_arithmetic_extension_operation(
         &mut self,
         operation: ExtensionArithmeticOperation<F, D>,
@@ -165,20 +221,53 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.inner_product_extension(F::ONE, e, vec![(a, b), (c, d)])
     }
 
-    /// Returns `sum_{(a,b) in vecs} constant * a * b`.
+    /// Returns `starting_acc + sum_{(a,b) in vecs} constant * a * b`. Long inner products are
+    /// computed with `InnerProductExtensionGate`s, each handling as many pairs as fit in a row.
     pub fn inner_product_extension(
         &mut self,
         constant: F,
         starting_acc: ExtensionTarget<D>,
         pairs: Vec<(ExtensionTarget<D>, ExtensionTarget<D>)>,
     ) -> ExtensionTarget<D> {
+        let gate = InnerProductExtensionGate::<D>::new_from_config(&self.config);
+        // A chunk only gets its own gate if it fills at least half of it; shorter chunks are
+        // cheaper as arithmetic operations, which share gates with the rest of the circuit.
+        let min_gate_pairs = (gate.num_pairs / 2).max(2);
+
         let mut acc = starting_acc;
-        for (a, b) in pairs {
+        let mut remaining = &pairs[..];
+        while remaining.len() >= min_gate_pairs {
+            let (chunk, rest) = remaining.split_at(remaining.len().min(gate.num_pairs));
+            acc = self.inner_product_extension_gate(&gate, constant, acc, chunk);
+            remaining = rest;
+        }
+        for &(a, b) in remaining {
             acc = self.arithmetic_extension(constant, F::ONE, a, b, acc);
         }
         acc
     }
 
+    fn inner_product_extension_gate(
+        &mut self,
+        gate: &InnerProductExtensionGate<D>,
+        constant: F,
+        acc: ExtensionTarget<D>,
+        pairs: &[(ExtensionTarget<D>, ExtensionTarget<D>)],
+    ) -> ExtensionTarget<D> {
+        let gate_index = self.add_gate(gate.clone(), vec![constant]);
+        let wire = |range| ExtensionTarget::from_range(gate_index, range);
+
+        self.connect_extension(acc, wire(InnerProductExtensionGate::<D>::wires_old_acc()));
+        let zero = self.zero_extension();
+        for i in 0..gate.num_pairs {
+            // Unused pairs are set to zero, which doesn't change the sum.
+            let (a, b) = pairs.get(i).copied().unwrap_or((zero, zero));
+            self.connect_extension(a, wire(InnerProductExtensionGate::<D>::wires_ith_first(i)));
+            self.connect_extension(b, wire(InnerProductExtensionGate::<D>::wires_ith_second(i)));
+        }
+        wire(InnerProductExtensionGate::<D>::wires_output())
+    }
+
     pub fn add_extension(
         &mut self,
         a: ExtensionTarget<D>,
@@ -498,7 +587,11 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
     fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
         let num = witness.get_extension_target(self.numerator);
         let dem = witness.get_extension_target(self.denominator);
-        let quotient = num / dem;
+        let quotient = if cfg!(feature = "constant-time") {
+            ct_div_extension::<F, D>(num, dem)
+        } else {
+            num / dem
+        };
         out_buffer.set_extension_target(self.quotient, quotient)
     }
 }
@@ -556,7 +649,9 @@ pub(crate) struct ExtensionArithmeticOperation<F: Field64 + Extendable<D>, const
 mod tests {
     use anyhow::Result;
     use plonky2_field::extension_field::algebra::ExtensionAlgebra;
+    use plonky2_field::extension_field::FieldExtension;
     use plonky2_field::field_types::Field;
+    use plonky2_field::ops::Square;
 
     use crate::iop::ext_target::ExtensionAlgebraTarget;
     use crate::iop::witness::{PartialWitness, Witness};
@@ -665,4 +760,80 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_fold_consecutive_scalings() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let three = F::from_canonical_u64(3);
+        let x = builder.add_virtual_target();
+        pw.set_target(x, F::rand());
+        let x_3 = builder.mul_const(three, x);
+        // Undoing a scaling gives back the original target.
+        assert_eq!(builder.mul_const(three.inverse(), x_3), x);
+        // Scalings of scalings are scalings of the original target, so they are memoized.
+        let x_9 = builder.mul_const(three, x_3);
+        assert_eq!(builder.mul_const(three.square(), x), x_9);
+
+        let y = builder.add_virtual_extension_target();
+        pw.set_extension_target(y, FF::rand());
+        let y_3 = builder.mul_const_extension(three, y);
+        assert_eq!(builder.mul_const_extension(three.inverse(), y_3), y);
+        let y_9 = builder.mul_const_extension(three, y_3);
+        assert_eq!(builder.mul_const_extension(three.square(), y), y_9);
+        let y_9_expected = builder.mul_const_extension(F::from_canonical_u64(9), y);
+        builder.connect_extension(y_9, y_9_expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_inner_product_extension() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let constant = F::rand();
+        // Long enough to use two full gates, followed by some arithmetic operations.
+        let n = 45;
+        let xs = FF::rand_vec(n);
+        let ys = FF::rand_vec(n);
+        let acc = FF::rand();
+        let expected = acc
+            + FieldExtension::<D>::scalar_mul(
+                &xs.iter().zip(&ys).map(|(&x, &y)| x * y).sum::<FF>(),
+                constant,
+            );
+
+        let xts = builder.add_virtual_extension_targets(n);
+        let yts = builder.add_virtual_extension_targets(n);
+        let acct = builder.add_virtual_extension_target();
+        for i in 0..n {
+            pw.set_extension_target(xts[i], xs[i]);
+            pw.set_extension_target(yts[i], ys[i]);
+        }
+        pw.set_extension_target(acct, acc);
+        let pairs = xts.into_iter().zip(yts).collect();
+        let result = builder.inner_product_extension(constant, acct, pairs);
+        let expected_t = builder.constant_extension(expected);
+        builder.connect_extension(result, expected_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 
 use plonky2_field::extension_field::Extendable;
 
-use crate::gates::add_many_u32::U32AddManyGate;
+use crate::gates::add_many_u32::{U32AddManyGate, MAX_NUM_ADDENDS};
 use crate::gates::arithmetic_u32::U32ArithmeticGate;
 use crate::gates::subtraction_u32::U32SubtractionGate;
 use crate::hash::hash_types::RichField;
@@ -150,6 +150,18 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             return self.add_u32(to_add[0], carry);
         }
 
+        // Longer sums, such as the columns of products of large `BigUint`s, are split so that each
+        // gate's carry, including the one it is given, fits in its carry limbs.
+        if to_add.len() >= MAX_NUM_ADDENDS {
+            let (head, tail) = to_add.split_at(MAX_NUM_ADDENDS - 1);
+            let (head_sum, head_carry) = self.add_u32s_with_carry(head, carry);
+            let rest = [&[head_sum], tail].concat();
+            let zero = self.zero_u32();
+            let (sum, tail_carry) = self.add_u32s_with_carry(&rest, zero);
+            let (total_carry, _) = self.add_u32(head_carry, tail_carry);
+            return (sum, total_carry);
+        }
+
         let num_addends = to_add.len();
 
         let gate = U32AddManyGate::<F, D>::new_from_config(&self.config, num_addends);
@@ -268,4 +280,34 @@ mod tests {
         let proof = data.prove(pw).unwrap();
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    pub fn test_add_many_u32s_long() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // More addends than one gate supports, all maximal, so every partial carry is as large as
+        // it can be.
+        const NUM_ADDENDS: usize = 40;
+
+        let config = CircuitConfig::standard_recursion_config();
+
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let to_add = vec![builder.constant_u32(u32::MAX); NUM_ADDENDS];
+        let carry = builder.constant_u32(NUM_ADDENDS as u32);
+        let sum = (NUM_ADDENDS as u64) * (u32::MAX as u64) + NUM_ADDENDS as u64;
+        let (result_low, result_high) = builder.add_u32s_with_carry(&to_add, carry);
+        let expected_low = builder.constant_u32((sum % (1 << 32)) as u32);
+        let expected_high = builder.constant_u32((sum >> 32) as u32);
+
+        builder.connect_u32(result_low, expected_low);
+        builder.connect_u32(result_high, expected_high);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
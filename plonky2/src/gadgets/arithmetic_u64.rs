@@ -0,0 +1,447 @@
+use std::marker::PhantomData;
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::biguint::BigUintTarget;
+use crate::gates::addition_u64::U64AdditionGate;
+use crate::gates::multiplication_u64::U64MultiplicationGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A 64-bit unsigned integer, represented by its 32-bit limbs in little-endian order.
+#[derive(Clone, Copy, Debug)]
+pub struct U64Target(pub [U32Target; 2]);
+
+impl U64Target {
+    pub fn lo(&self) -> U32Target {
+        self.0[0]
+    }
+
+    pub fn hi(&self) -> U32Target {
+        self.0[1]
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn add_virtual_u64_target(&mut self) -> U64Target {
+        U64Target([self.add_virtual_u32_target(), self.add_virtual_u32_target()])
+    }
+
+    pub fn add_virtual_u64_targets(&mut self, n: usize) -> Vec<U64Target> {
+        (0..n).map(|_| self.add_virtual_u64_target()).collect()
+    }
+
+    pub fn zero_u64(&mut self) -> U64Target {
+        let zero = self.zero_u32();
+        U64Target([zero, zero])
+    }
+
+    pub fn constant_u64(&mut self, c: u64) -> U64Target {
+        U64Target([
+            self.constant_u32(c as u32),
+            self.constant_u32((c >> 32) as u32),
+        ])
+    }
+
+    pub fn connect_u64(&mut self, x: U64Target, y: U64Target) {
+        self.connect_u32(x.lo(), y.lo());
+        self.connect_u32(x.hi(), y.hi());
+    }
+
+    /// Returns the sum of the given values modulo `2^64`, along with the carry out of the high
+    /// limb.
+    pub fn add_many_u64(&mut self, to_add: &[U64Target]) -> (U64Target, U32Target) {
+        let lows = to_add.iter().map(|x| x.lo()).collect::<Vec<_>>();
+        let highs = to_add.iter().map(|x| x.hi()).collect::<Vec<_>>();
+        let (lo, carry) = self.add_many_u32(&lows);
+        let (hi, carry) = self.add_u32s_with_carry(&highs, carry);
+        (U64Target([lo, hi]), carry)
+    }
+
+    /// Returns `(x + y) mod 2^64`, along with the carry.
+    pub fn add_u64(&mut self, x: U64Target, y: U64Target) -> (U64Target, U32Target) {
+        let _false = self._false();
+        let (sum, carry) = self.add_u64_with_carry(x, y, _false);
+        (sum, U32Target(carry.target))
+    }
+
+    /// Returns `(x + y + carry) mod 2^64`, along with the carry out, which is set iff the sum
+    /// overflows.
+    pub fn add_u64_with_carry(
+        &mut self,
+        x: U64Target,
+        y: U64Target,
+        carry: BoolTarget,
+    ) -> (U64Target, BoolTarget) {
+        let gate = U64AdditionGate::<F, D>::new_from_config(&self.config);
+        let (gate_index, copy) = self.find_u64_addition_gate();
+
+        let inputs = [
+            (gate.wire_ith_input_x_low(copy), x.lo().0),
+            (gate.wire_ith_input_x_high(copy), x.hi().0),
+            (gate.wire_ith_input_y_low(copy), y.lo().0),
+            (gate.wire_ith_input_y_high(copy), y.hi().0),
+            (gate.wire_ith_input_carry(copy), carry.target),
+        ];
+        for (wire, target) in inputs {
+            self.connect(Target::wire(gate_index, wire), target);
+        }
+
+        let output = U64Target([
+            U32Target(Target::wire(gate_index, gate.wire_ith_output_low(copy))),
+            U32Target(Target::wire(gate_index, gate.wire_ith_output_high(copy))),
+        ]);
+        let output_carry =
+            BoolTarget::new_unsafe(Target::wire(gate_index, gate.wire_ith_output_carry(copy)));
+
+        (output, output_carry)
+    }
+
+    /// Returns the full 128-bit product `x * y`, as its low and high 64 bits.
+    ///
+    /// This uses a `U64MultiplicationGate` if the config has enough wires for one, and
+    /// `U32ArithmeticGate` operations otherwise.
+    pub fn mul_u64(&mut self, x: U64Target, y: U64Target) -> (U64Target, U64Target) {
+        if U64MultiplicationGate::<F, D>::num_ops(&self.config) > 0 {
+            let (low, high, _overflow) = self.mul_u64_with_gate(x, y);
+            return (low, high);
+        }
+
+        let zero = self.zero_u32();
+        // Schoolbook multiplication on the 32-bit limbs. Each `mul_add_u32` computes a value below
+        // `2^64`, so none of them can wrap around the field.
+        let (r0, k0) = self.mul_add_u32(x.lo(), y.lo(), zero);
+        let (t0, k1) = self.mul_add_u32(x.lo(), y.hi(), k0);
+        let (r1, k2) = self.mul_add_u32(x.hi(), y.lo(), t0);
+        let (t1, k3) = self.mul_add_u32(x.hi(), y.hi(), k1);
+        let (r2, k4) = self.add_u32(t1, k2);
+        // The product is below `2^128`, so this doesn't carry.
+        let (r3, _) = self.add_u32(k3, k4);
+        (U64Target([r0, r1]), U64Target([r2, r3]))
+    }
+
+    /// Returns `(x * y) mod 2^64`, along with a flag which is set iff the product overflows.
+    pub fn mul_u64_with_overflow(&mut self, x: U64Target, y: U64Target) -> (U64Target, BoolTarget) {
+        if U64MultiplicationGate::<F, D>::num_ops(&self.config) > 0 {
+            let (low, _high, overflow) = self.mul_u64_with_gate(x, y);
+            return (low, overflow);
+        }
+
+        let (low, high) = self.mul_u64(x, y);
+        let overflow = self.is_nonzero_u64(high);
+        (low, overflow)
+    }
+
+    /// Returns the low and high 64 bits of `x * y`, along with the overflow flag, computed by a
+    /// `U64MultiplicationGate` operation.
+    fn mul_u64_with_gate(
+        &mut self,
+        x: U64Target,
+        y: U64Target,
+    ) -> (U64Target, U64Target, BoolTarget) {
+        let gate = U64MultiplicationGate::<F, D>::new_from_config(&self.config);
+        let (gate_index, copy) = self.find_u64_multiplication_gate();
+
+        let inputs = [
+            (gate.wire_ith_input_x_low(copy), x.lo().0),
+            (gate.wire_ith_input_x_high(copy), x.hi().0),
+            (gate.wire_ith_input_y_low(copy), y.lo().0),
+            (gate.wire_ith_input_y_high(copy), y.hi().0),
+        ];
+        for (wire, target) in inputs {
+            self.connect(Target::wire(gate_index, wire), target);
+        }
+
+        let [r0, r1, r2, r3] = [0, 1, 2, 3].map(|j| {
+            U32Target(Target::wire(
+                gate_index,
+                gate.wire_ith_output_jth_limb(copy, j),
+            ))
+        });
+        let overflow = BoolTarget::new_unsafe(Target::wire(
+            gate_index,
+            gate.wire_ith_output_overflow(copy),
+        ));
+
+        (U64Target([r0, r1]), U64Target([r2, r3]), overflow)
+    }
+
+    /// Returns whether `x` is nonzero. `x` must be range-checked already.
+    fn is_nonzero_u64(&mut self, x: U64Target) -> BoolTarget {
+        // Both limbs are below `2^32`, so their sum is zero iff they both are.
+        let sum = self.add(x.lo().0, x.hi().0);
+        let inverse = self.add_virtual_target();
+        self.add_simple_generator(NonzeroInverseGenerator { x: sum, inverse });
+
+        // `flag = sum * inverse` and `sum * (1 - flag) = 0` force `flag` to be 1 iff `sum != 0`.
+        let flag = self.mul(sum, inverse);
+        let one = self.one();
+        let not_flag = self.sub(one, flag);
+        let check = self.mul(sum, not_flag);
+        self.assert_zero(check);
+
+        BoolTarget::new_unsafe(flag)
+    }
+
+    /// Returns the quotient and remainder of `dividend / divisor`. The inputs are assumed to be
+    /// range-checked already, and `divisor` must be nonzero, or no valid witness exists.
+    pub fn div_rem_u64(
+        &mut self,
+        dividend: U64Target,
+        divisor: U64Target,
+    ) -> (U64Target, U64Target) {
+        let quotient = self.add_virtual_u64_target();
+        let remainder = self.add_virtual_u64_target();
+        self.add_simple_generator(DivRemU64Generator::<F, D> {
+            dividend,
+            divisor,
+            quotient,
+            remainder,
+            _phantom: PhantomData,
+        });
+        self.range_check_u32(vec![
+            quotient.lo(),
+            quotient.hi(),
+            remainder.lo(),
+            remainder.hi(),
+        ]);
+
+        // Check that `quotient * divisor + remainder = dividend` over the integers. The product is
+        // computed in full, and `connect_biguint` asserts that its limbs above 64 bits are zero.
+        let to_biguint = |x: U64Target| BigUintTarget {
+            limbs: x.0.to_vec(),
+        };
+        let product = self.mul_biguint(&to_biguint(quotient), &to_biguint(divisor));
+        let sum = self.add_biguint(&product, &to_biguint(remainder));
+        self.connect_biguint(&sum, &to_biguint(dividend));
+
+        // Check that `remainder < divisor`, i.e. that `remainder - divisor` borrows.
+        let zero = self.zero_u32();
+        let (_, borrow) = self.sub_u32(remainder.lo(), divisor.lo(), zero);
+        let (_, borrow) = self.sub_u32(remainder.hi(), divisor.hi(), borrow);
+        self.assert_one(borrow.0);
+
+        (quotient, remainder)
+    }
+}
+
+#[derive(Debug)]
+struct DivRemU64Generator<F: RichField + Extendable<D>, const D: usize> {
+    dividend: U64Target,
+    divisor: U64Target,
+    quotient: U64Target,
+    remainder: U64Target,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F> for DivRemU64Generator<F, D> {
+    fn dependencies(&self) -> Vec<Target> {
+        self.dividend
+            .0
+            .iter()
+            .chain(&self.divisor.0)
+            .map(|&l| l.0)
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let dividend = witness.get_u64_target(self.dividend);
+        let divisor = witness.get_u64_target(self.divisor);
+        assert_ne!(divisor, 0, "Division by zero");
+
+        out_buffer.set_u64_target(self.quotient, dividend / divisor);
+        out_buffer.set_u64_target(self.remainder, dividend % divisor);
+    }
+}
+
+/// Sets `inverse` to the inverse of `x`, or to zero if `x` is zero.
+#[derive(Debug)]
+struct NonzeroInverseGenerator {
+    x: Target,
+    inverse: Target,
+}
+
+impl<F: RichField> SimpleGenerator<F> for NonzeroInverseGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.x]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let x = witness.get_target(self.x);
+        out_buffer.set_target(self.inverse, x.try_inverse().unwrap_or(F::ZERO));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::{thread_rng, Rng};
+
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    #[test]
+    fn test_add_many_u64() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        let values = (0..5).map(|_| rng.gen::<u64>()).collect::<Vec<_>>();
+        let sum = values.iter().map(|&x| x as u128).sum::<u128>();
+        let to_add = values
+            .iter()
+            .map(|&x| builder.constant_u64(x))
+            .collect::<Vec<_>>();
+        let (result, carry) = builder.add_many_u64(&to_add);
+        let expected_result = builder.constant_u64(sum as u64);
+        let expected_carry = builder.constant_u32((sum >> 64) as u32);
+        builder.connect_u64(result, expected_result);
+        builder.connect_u32(carry, expected_carry);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_div_rem_u64() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        let cases = [
+            (rng.gen::<u64>(), rng.gen::<u64>()),
+            (rng.gen::<u64>(), rng.gen::<u32>() as u64),
+            (u64::MAX, 1),
+            (u64::MAX, u64::MAX),
+            (7, u64::MAX),
+        ];
+        for (dividend, divisor) in cases {
+            let dividend_target = builder.add_virtual_u64_target();
+            let divisor_target = builder.add_virtual_u64_target();
+            pw.set_u64_target(dividend_target, dividend);
+            pw.set_u64_target(divisor_target, divisor);
+
+            let (quotient, remainder) = builder.div_rem_u64(dividend_target, divisor_target);
+            let expected_quotient = builder.constant_u64(dividend / divisor);
+            let expected_remainder = builder.constant_u64(dividend % divisor);
+            builder.connect_u64(quotient, expected_quotient);
+            builder.connect_u64(remainder, expected_remainder);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_add_u64_with_carry() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        let cases = [
+            (rng.gen::<u64>(), rng.gen::<u64>(), rng.gen::<bool>()),
+            (u64::MAX, 0, true),
+            (u64::MAX, u64::MAX, true),
+            (u32::MAX as u64, 1, false),
+        ];
+        for (x, y, carry) in cases {
+            let x_target = builder.add_virtual_u64_target();
+            let y_target = builder.add_virtual_u64_target();
+            let carry_target = builder.add_virtual_bool_target();
+            pw.set_u64_target(x_target, x);
+            pw.set_u64_target(y_target, y);
+            pw.set_bool_target(carry_target, carry);
+
+            let (sum, overflow_0) = x.overflowing_add(y);
+            let (sum, overflow_1) = sum.overflowing_add(carry as u64);
+            let (result, carry_out) = builder.add_u64_with_carry(x_target, y_target, carry_target);
+            let expected_result = builder.constant_u64(sum);
+            let expected_carry = builder.constant_bool(overflow_0 || overflow_1);
+            builder.connect_u64(result, expected_result);
+            builder.connect(carry_out.target, expected_carry.target);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_mul_u64_with_overflow() -> Result<()> {
+        // The standard config is too narrow for a `U64MultiplicationGate`.
+        check_mul_u64_with_overflow(CircuitConfig::standard_recursion_config())
+    }
+
+    #[test]
+    fn test_mul_u64_with_overflow_gate() -> Result<()> {
+        let config = CircuitConfig {
+            num_wires: 161,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        check_mul_u64_with_overflow(config)
+    }
+
+    fn check_mul_u64_with_overflow(config: CircuitConfig) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        let cases = [
+            (rng.gen::<u64>(), rng.gen::<u64>()),
+            (rng.gen::<u32>() as u64, rng.gen::<u32>() as u64),
+            (u64::MAX, u64::MAX),
+            (u64::MAX, 1),
+            (1 << 32, 1 << 32),
+        ];
+        for (x, y) in cases {
+            let x_target = builder.add_virtual_u64_target();
+            let y_target = builder.add_virtual_u64_target();
+            pw.set_u64_target(x_target, x);
+            pw.set_u64_target(y_target, y);
+
+            let product = x as u128 * y as u128;
+            let (low, high) = builder.mul_u64(x_target, y_target);
+            let expected_low = builder.constant_u64(product as u64);
+            let expected_high = builder.constant_u64((product >> 64) as u64);
+            builder.connect_u64(low, expected_low);
+            builder.connect_u64(high, expected_high);
+
+            let (wrapped, overflow) = builder.mul_u64_with_overflow(x_target, y_target);
+            let expected_overflow = builder.constant_bool(product >> 64 != 0);
+            builder.connect_u64(wrapped, expected_low);
+            builder.connect(overflow.target, expected_overflow.target);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
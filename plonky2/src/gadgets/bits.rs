@@ -0,0 +1,99 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::arithmetic_u64::U64Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// Helpers for working with fixed-width words represented as little-endian bit vectors, as used by
+/// bit-oriented primitives such as symmetric ciphers and SHA-style hash functions.
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Splits a byte into its 8 bits, in little-endian order. This also range-checks `x`.
+    pub fn split_byte_le(&mut self, x: Target) -> Vec<BoolTarget> {
+        self.split_le(x, 8)
+    }
+
+    /// Splits a `U32Target` into its 32 bits, in little-endian order.
+    pub fn split_u32_le(&mut self, x: U32Target) -> Vec<BoolTarget> {
+        self.split_le(x.0, 32)
+    }
+
+    /// Splits a `U64Target` into its 64 bits, in little-endian order.
+    pub fn split_u64_le(&mut self, x: U64Target) -> Vec<BoolTarget> {
+        let mut bits = self.split_u32_le(x.lo());
+        bits.extend(self.split_u32_le(x.hi()));
+        bits
+    }
+
+    /// Returns the number with the given little-endian bit representation.
+    pub fn le_bits_to_target(&mut self, bits: &[BoolTarget]) -> Target {
+        self.le_sum(bits.iter())
+    }
+
+    /// Returns the `U32Target` with the given 32-bit little-endian bit representation.
+    pub fn le_bits_to_u32(&mut self, bits: &[BoolTarget]) -> U32Target {
+        debug_assert_eq!(bits.len(), 32);
+        U32Target(self.le_sum(bits.iter()))
+    }
+
+    /// Returns the `U64Target` with the given 64-bit little-endian bit representation.
+    pub fn le_bits_to_u64(&mut self, bits: &[BoolTarget]) -> U64Target {
+        debug_assert_eq!(bits.len(), 64);
+        U64Target([
+            self.le_bits_to_u32(&bits[..32]),
+            self.le_bits_to_u32(&bits[32..]),
+        ])
+    }
+
+    /// Returns the little-endian bits of a constant, as constant `BoolTarget`s.
+    pub fn constant_le_bits(&mut self, value: u64, num_bits: usize) -> Vec<BoolTarget> {
+        (0..num_bits)
+            .map(|i| self.constant_bool((value >> i) & 1 == 1))
+            .collect()
+    }
+
+    /// Computes the bitwise XOR of two equal-length bit vectors.
+    pub fn xor_bits(&mut self, x: &[BoolTarget], y: &[BoolTarget]) -> Vec<BoolTarget> {
+        debug_assert_eq!(x.len(), y.len());
+        x.iter().zip(y).map(|(&a, &b)| self.xor(a, b)).collect()
+    }
+
+    /// Packs bytes into a `U32Target`, in little-endian order. The bytes are assumed to be range
+    /// checked already.
+    pub fn le_bytes_to_u32(&mut self, bytes: &[Target]) -> U32Target {
+        debug_assert_eq!(bytes.len(), 4);
+        let base = F::from_canonical_u32(1 << 8);
+        let packed = bytes
+            .iter()
+            .rev()
+            .fold(self.zero(), |acc, &b| self.mul_const_add(base, acc, b));
+        U32Target(packed)
+    }
+
+    /// Packs bytes into a `U32Target`, in big-endian order. The bytes are assumed to be range
+    /// checked already.
+    pub fn be_bytes_to_u32(&mut self, bytes: &[Target]) -> U32Target {
+        let le_bytes = bytes.iter().rev().copied().collect::<Vec<_>>();
+        self.le_bytes_to_u32(&le_bytes)
+    }
+
+    /// Returns the little-endian bits of the word whose big-endian byte encoding is `bytes`. Each
+    /// byte is range-checked.
+    pub fn be_bytes_to_le_bits(&mut self, bytes: &[Target]) -> Vec<BoolTarget> {
+        bytes
+            .iter()
+            .rev()
+            .flat_map(|&b| self.split_byte_le(b))
+            .collect()
+    }
+
+    /// Returns the big-endian byte encoding of the word with the given little-endian bits.
+    pub fn le_bits_to_be_bytes(&mut self, bits: &[BoolTarget]) -> Vec<Target> {
+        debug_assert_eq!(bits.len() % 8, 0);
+        bits.chunks(8)
+            .rev()
+            .map(|byte| self.le_bits_to_target(byte))
+            .collect()
+    }
+}
@@ -0,0 +1,284 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::curve::bls12_381::{Bls12_381, BLS12_381_BETA, BLS12_381_X, BLS12_381_X_IS_NEGATIVE};
+use crate::curve::curve_types::{AffinePoint, Curve};
+use crate::gadgets::curve::AffinePointTarget;
+use crate::gadgets::pairing::{Fp12Target, G2AffinePointTarget};
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A pair of a G1 point and a G2 point, as consumed by the Miller loop.
+pub type PairingInput = (AffinePointTarget<Bls12_381>, G2AffinePointTarget<Bls12_381>);
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the product of the Miller loops `f_{|x|, Q}(P)` for each pair `(P, Q)`, sharing the
+    /// squarings between pairs. Points are assumed to be nonzero and in the prime-order subgroups.
+    pub fn miller_loop_bls12_381(&mut self, pairs: &[PairingInput]) -> Fp12Target<Bls12_381> {
+        let mut f = self.one_fp12();
+        let mut ts = pairs.iter().map(|(_, q)| q.clone()).collect::<Vec<_>>();
+
+        let num_bits = 64 - BLS12_381_X.leading_zeros() as usize;
+        for i in (0..num_bits - 1).rev() {
+            f = self.square_fp12(&f);
+            for ((p, _), t) in pairs.iter().zip(ts.iter_mut()) {
+                let (doubled, line) = self.g2_double_with_line(t, p);
+                f = self.mul_fp12_by_line(&f, &line);
+                *t = doubled;
+            }
+
+            if (BLS12_381_X >> i) & 1 == 1 {
+                for ((p, q), t) in pairs.iter().zip(ts.iter_mut()) {
+                    let (sum, line) = self.g2_add_with_line(t, q, p);
+                    f = self.mul_fp12_by_line(&f, &line);
+                    *t = sum;
+                }
+            }
+        }
+
+        if BLS12_381_X_IS_NEGATIVE {
+            f = self.conj_fp12(&f);
+        }
+        f
+    }
+
+    /// Raises the output of a Miller loop to the power `(p^12 - 1) / r`, or rather to a multiple of
+    /// it which is coprime to `r`, following the addition chain of Hayashida, Hayasaka and Teruya.
+    pub fn final_exponentiation_bls12_381(
+        &mut self,
+        f: &Fp12Target<Bls12_381>,
+    ) -> Fp12Target<Bls12_381> {
+        // Easy part: f^((p^6 - 1) (p^2 + 1)).
+        let f_conj = self.conj_fp12(f);
+        let f_inv = self.inv_fp12(f);
+        let f = self.mul_fp12(&f_conj, &f_inv);
+        let f_frob = self.frobenius_fp12(&f, 2);
+        let y = self.mul_fp12(&f_frob, &f);
+
+        // Hard part: y^(3 (p^4 - p^2 + 1) / r). Since y is now in the cyclotomic subgroup, its
+        // inverse is just its conjugate.
+        let y_conj = self.conj_fp12(&y);
+        let y_x = self.exp_by_x_bls12_381(&y);
+        let y1 = self.mul_fp12(&y_x, &y_conj);
+
+        let y1_conj = self.conj_fp12(&y1);
+        let y1_x = self.exp_by_x_bls12_381(&y1);
+        let a = self.mul_fp12(&y1_x, &y1_conj);
+
+        let b = self.exp_by_x_bls12_381(&a);
+
+        let a_conj = self.conj_fp12(&a);
+        let b_x = self.exp_by_x_bls12_381(&b);
+        let c = self.mul_fp12(&b_x, &a_conj);
+
+        let y_squared = self.square_fp12(&y);
+        let y_cubed = self.mul_fp12(&y_squared, &y);
+        let c_x = self.exp_by_x_bls12_381(&c);
+        let d = self.mul_fp12(&c_x, &y_cubed);
+
+        let c_frob = self.frobenius_fp12(&c, 1);
+        let b_frob = self.frobenius_fp12(&b, 2);
+        let a_frob = self.frobenius_fp12(&a, 3);
+        let result = self.mul_fp12(&d, &c_frob);
+        let result = self.mul_fp12(&result, &b_frob);
+        self.mul_fp12(&result, &a_frob)
+    }
+
+    /// Computes `f^x` for an element `f` of the cyclotomic subgroup.
+    fn exp_by_x_bls12_381(&mut self, f: &Fp12Target<Bls12_381>) -> Fp12Target<Bls12_381> {
+        let result = self.exp_fp12_u64(f, BLS12_381_X);
+        if BLS12_381_X_IS_NEGATIVE {
+            self.conj_fp12(&result)
+        } else {
+            result
+        }
+    }
+
+    /// Computes the optimal ate pairing `e(p, q)`.
+    pub fn pairing_bls12_381(
+        &mut self,
+        p: &AffinePointTarget<Bls12_381>,
+        q: &G2AffinePointTarget<Bls12_381>,
+    ) -> Fp12Target<Bls12_381> {
+        let f = self.miller_loop_bls12_381(&[(p.clone(), q.clone())]);
+        self.final_exponentiation_bls12_381(&f)
+    }
+
+    /// Multiplies a G1 point by `|x|`, with the same caveats as `g2_mul_u64`.
+    fn curve_mul_by_abs_x_bls12_381(
+        &mut self,
+        p: &AffinePointTarget<Bls12_381>,
+    ) -> AffinePointTarget<Bls12_381> {
+        let num_bits = 64 - BLS12_381_X.leading_zeros() as usize;
+        let mut result = p.clone();
+        for i in (0..num_bits - 1).rev() {
+            result = self.curve_double(&result);
+            if (BLS12_381_X >> i) & 1 == 1 {
+                result = self.curve_add(&result, p);
+            }
+        }
+        result
+    }
+
+    /// Multiplies a G2 point by the BLS parameter `x`, with the same caveats as `g2_mul_u64`.
+    pub(crate) fn g2_mul_by_x_bls12_381(
+        &mut self,
+        q: &G2AffinePointTarget<Bls12_381>,
+    ) -> G2AffinePointTarget<Bls12_381> {
+        let result = self.g2_mul_u64(q, BLS12_381_X);
+        if BLS12_381_X_IS_NEGATIVE {
+            self.g2_neg(&result)
+        } else {
+            result
+        }
+    }
+
+    /// Asserts that a nonzero point of the curve lies in G1, with the endomorphism test of
+    /// `g1_in_subgroup`. Points outside of G1 make the circuit unsatisfiable, either through the
+    /// final comparison or through an exceptional case of the incomplete addition formulas.
+    pub fn g1_assert_in_subgroup_bls12_381(&mut self, p: &AffinePointTarget<Bls12_381>) {
+        let beta = self.constant_nonnative(BLS12_381_BETA);
+        let endo = AffinePointTarget {
+            x: self.mul_nonnative(&beta, &p.x),
+            y: p.y.clone(),
+        };
+        // Since `x^2 = |x|^2`, `[-x^2] p = -[|x|] [|x|] p`.
+        let abs_x_p = self.curve_mul_by_abs_x_bls12_381(p);
+        let x_squared_p = self.curve_mul_by_abs_x_bls12_381(&abs_x_p);
+        let neg_x_squared_p = self.curve_neg(&x_squared_p);
+        self.connect_affine_point(&endo, &neg_x_squared_p);
+    }
+
+    /// Asserts that a nonzero point of the twist lies in G2, with the endomorphism test of
+    /// `g2_in_subgroup`. As with `g1_assert_in_subgroup_bls12_381`, other points make the circuit
+    /// unsatisfiable.
+    pub fn g2_assert_in_subgroup_bls12_381(&mut self, q: &G2AffinePointTarget<Bls12_381>) {
+        let psi_q = self.g2_frobenius(q, 1);
+        let x_q = self.g2_mul_by_x_bls12_381(q);
+        self.connect_g2_point(&psi_q, &x_q);
+    }
+
+    /// Verifies a BLS signature with public keys in G1 and signatures in G2, by checking that
+    /// `e(pk, H(m)) = e(g1, sig)`. The message is given as bytes, and is hashed to G2 in the
+    /// circuit with the domain separation tag `dst`, as in `hash_to_g2_bls12_381`. The public key
+    /// and the signature are checked to lie in G1 and G2 respectively; this is the in-circuit
+    /// counterpart of `curve::bls12_381::verify_bls_signature`.
+    pub fn verify_bls_signature(
+        &mut self,
+        pk: &AffinePointTarget<Bls12_381>,
+        msg: &[Target],
+        dst: &[u8],
+        sig: &G2AffinePointTarget<Bls12_381>,
+    ) {
+        self.curve_assert_valid(pk);
+        self.g2_assert_valid(sig);
+        self.g1_assert_in_subgroup_bls12_381(pk);
+        self.g2_assert_in_subgroup_bls12_381(sig);
+        let msg_hash = self.hash_to_g2_bls12_381(msg, dst);
+
+        // Check that e(pk, H(m)) e(-g1, sig) = 1, sharing a single final exponentiation.
+        let g = Bls12_381::GENERATOR_AFFINE;
+        let neg_g = self.constant_affine_point(AffinePoint::<Bls12_381>::nonzero(g.x, -g.y));
+        let f = self.miller_loop_bls12_381(&[(pk.clone(), msg_hash), (neg_g, sig.clone())]);
+        let result = self.final_exponentiation_bls12_381(&f);
+        let one = self.one_fp12();
+        self.connect_fp12(&result, &one);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2_field::bls12_381_base::Bls12_381Base;
+    use plonky2_field::bls12_381_scalar::Bls12_381Scalar;
+    use plonky2_field::field_types::{Field, PrimeField};
+
+    use crate::curve::bls12_381::{g2_generator, Bls12_381};
+    use crate::curve::curve_types::{Curve, CurveScalar};
+    use crate::curve::hash_to_curve::{hash_to_g2, map_to_twist, ETH_BLS_SIG_DST};
+    use crate::curve::pairing::{Fp2, G2AffinePoint};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const MSG: &[u8] = b"sync committee";
+
+    fn test_secret_key() -> Bls12_381Scalar {
+        Bls12_381Scalar::from_biguint(BigUint::parse_bytes(b"2a2a2a2a2a2a2a2a2a2a", 16).unwrap())
+    }
+
+    /// Verifies a signature on `signed_msg` by the test key against the public key of `pk_sk`,
+    /// claiming that the signed message is `MSG`.
+    fn test_verify(pk_sk: Bls12_381Scalar, signed_msg: &[u8]) -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let pk = (CurveScalar(pk_sk) * Bls12_381::GENERATOR_PROJECTIVE).to_affine();
+        let pk = builder.constant_affine_point(pk);
+        let sig = hash_to_g2(signed_msg, ETH_BLS_SIG_DST)
+            .mul_biguint(&test_secret_key().to_canonical_biguint());
+        let sig = builder.constant_g2_point(sig.x, sig.y);
+        let msg = MSG
+            .iter()
+            .map(|&b| builder.constant(F::from_canonical_u32(b as u32)))
+            .collect::<Vec<_>>();
+        builder.verify_bls_signature(&pk, &msg, ETH_BLS_SIG_DST, &sig);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[ignore]
+    fn test_verify_bls_signature() -> Result<()> {
+        test_verify(test_secret_key(), MSG)
+    }
+
+    #[test]
+    #[ignore]
+    #[should_panic]
+    fn test_verify_bls_signature_wrong_key() {
+        test_verify(test_secret_key() + Bls12_381Scalar::ONE, MSG).unwrap()
+    }
+
+    #[test]
+    #[ignore]
+    #[should_panic]
+    fn test_verify_bls_signature_wrong_message() {
+        test_verify(test_secret_key(), b"another message").unwrap()
+    }
+
+    fn test_g2_subgroup_check(q: G2AffinePoint<Bls12_381>) -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let q = builder.constant_g2_point(q.x, q.y);
+        builder.g2_assert_in_subgroup_bls12_381(&q);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[ignore]
+    fn test_g2_subgroup_check_accepts() -> Result<()> {
+        test_g2_subgroup_check(g2_generator())
+    }
+
+    #[test]
+    #[ignore]
+    #[should_panic]
+    fn test_g2_subgroup_check_rejects() {
+        // A point of the twist which is not in G2, since its cofactor has not been cleared.
+        test_g2_subgroup_check(map_to_twist(Fp2([Bls12_381Base::ONE, Bls12_381Base::TWO]))).unwrap()
+    }
+}
@@ -0,0 +1,229 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::curve::bn254::{Bn254, BN254_U};
+use crate::gadgets::curve::AffinePointTarget;
+use crate::gadgets::pairing::{Fp12Target, G2AffinePointTarget};
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The number of iterations of the optimal ate Miller loop, `6u + 2`.
+const BN254_ATE_LOOP_COUNT: u128 = 6 * BN254_U as u128 + 2;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the product of the optimal ate Miller loops for each pair `(P, Q)`, sharing the
+    /// squarings between pairs. Points are assumed to be nonzero and in the prime-order subgroups.
+    pub fn miller_loop_bn254(
+        &mut self,
+        pairs: &[(AffinePointTarget<Bn254>, G2AffinePointTarget<Bn254>)],
+    ) -> Fp12Target<Bn254> {
+        let mut f = self.one_fp12();
+        let mut ts = pairs.iter().map(|(_, q)| q.clone()).collect::<Vec<_>>();
+
+        let num_bits = 128 - BN254_ATE_LOOP_COUNT.leading_zeros() as usize;
+        for i in (0..num_bits - 1).rev() {
+            f = self.square_fp12(&f);
+            for ((p, _), t) in pairs.iter().zip(ts.iter_mut()) {
+                let (doubled, line) = self.g2_double_with_line(t, p);
+                f = self.mul_fp12_by_line(&f, &line);
+                *t = doubled;
+            }
+
+            if (BN254_ATE_LOOP_COUNT >> i) & 1 == 1 {
+                for ((p, q), t) in pairs.iter().zip(ts.iter_mut()) {
+                    let (sum, line) = self.g2_add_with_line(t, q, p);
+                    f = self.mul_fp12_by_line(&f, &line);
+                    *t = sum;
+                }
+            }
+        }
+
+        // The two final steps of the optimal ate pairing, with the lines through pi(Q) and
+        // -pi^2(Q).
+        for ((p, q), t) in pairs.iter().zip(ts.iter_mut()) {
+            let q1 = self.g2_frobenius(q, 1);
+            let q2 = self.g2_frobenius(q, 2);
+            let neg_q2 = self.g2_neg(&q2);
+
+            let (sum, line) = self.g2_add_with_line(t, &q1, p);
+            f = self.mul_fp12_by_line(&f, &line);
+            let (_, line) = self.g2_add_with_line(&sum, &neg_q2, p);
+            f = self.mul_fp12_by_line(&f, &line);
+        }
+        f
+    }
+
+    /// Raises the output of a Miller loop to the power `(p^12 - 1) / r`.
+    pub fn final_exponentiation_bn254(&mut self, f: &Fp12Target<Bn254>) -> Fp12Target<Bn254> {
+        // Easy part: f^((p^6 - 1) (p^2 + 1)).
+        let f_conj = self.conj_fp12(f);
+        let f_inv = self.inv_fp12(f);
+        let f = self.mul_fp12(&f_conj, &f_inv);
+        let f_frob = self.frobenius_fp12(&f, 2);
+        let y = self.mul_fp12(&f_frob, &f);
+
+        // Hard part: y^((p^4 - p^2 + 1) / r) = y^(l0 + l1 p + l2 p^2 + l3 p^3), where
+        //   l3 = 1,
+        //   l2 = 6u^2 + 1,
+        //   l1 = -36u^3 - 18u^2 - 12u + 1,
+        //   l0 = -36u^3 - 30u^2 - 18u - 2.
+        // Since y is now in the cyclotomic subgroup, its inverse is just its conjugate.
+        let a = self.exp_fp12_u64(&y, BN254_U);
+        let b = self.exp_fp12_u64(&a, BN254_U);
+        let c = self.exp_fp12_u64(&b, BN254_U);
+
+        let a6 = self.exp_fp12_u64(&a, 6);
+        let a12 = self.square_fp12(&a6);
+        let a18 = self.mul_fp12(&a12, &a6);
+        let b6 = self.exp_fp12_u64(&b, 6);
+        let b12 = self.square_fp12(&b6);
+        let b18 = self.mul_fp12(&b12, &b6);
+        let b30 = self.mul_fp12(&b18, &b12);
+        let c36 = self.exp_fp12_u64(&c, 36);
+        let y2 = self.square_fp12(&y);
+
+        let y_l2 = self.mul_fp12(&b6, &y);
+
+        let c36_b18 = self.mul_fp12(&c36, &b18);
+        let c36_b18_a12 = self.mul_fp12(&c36_b18, &a12);
+        let y_l1 = self.conj_fp12(&c36_b18_a12);
+        let y_l1 = self.mul_fp12(&y_l1, &y);
+
+        let c36_b30 = self.mul_fp12(&c36, &b30);
+        let c36_b30_a18 = self.mul_fp12(&c36_b30, &a18);
+        let c36_b30_a18_y2 = self.mul_fp12(&c36_b30_a18, &y2);
+        let y_l0 = self.conj_fp12(&c36_b30_a18_y2);
+
+        let y_l1_frob = self.frobenius_fp12(&y_l1, 1);
+        let y_l2_frob = self.frobenius_fp12(&y_l2, 2);
+        let y_l3_frob = self.frobenius_fp12(&y, 3);
+        let result = self.mul_fp12(&y_l0, &y_l1_frob);
+        let result = self.mul_fp12(&result, &y_l2_frob);
+        self.mul_fp12(&result, &y_l3_frob)
+    }
+
+    /// Computes the optimal ate pairing `e(p, q)`.
+    pub fn pairing_bn254(
+        &mut self,
+        p: &AffinePointTarget<Bn254>,
+        q: &G2AffinePointTarget<Bn254>,
+    ) -> Fp12Target<Bn254> {
+        let f = self.miller_loop_bn254(&[(p.clone(), q.clone())]);
+        self.final_exponentiation_bn254(&f)
+    }
+
+    /// Asserts that `prod_i e(P_i, Q_i) = 1`, which is the form of the checks made by Groth16 and
+    /// KZG verifiers. A single final exponentiation is shared by all pairs.
+    pub fn pairing_check_bn254(
+        &mut self,
+        pairs: &[(AffinePointTarget<Bn254>, G2AffinePointTarget<Bn254>)],
+    ) {
+        let f = self.miller_loop_bn254(pairs);
+        let result = self.final_exponentiation_bn254(&f);
+        let one = self.one_fp12();
+        self.connect_fp12(&result, &one);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2_field::bn254_base::Bn254Base;
+    use plonky2_field::bn254_scalar::Bn254Scalar;
+    use plonky2_field::field_types::Field;
+
+    use crate::curve::bn254::{Bn254, BN254_G2_GENERATOR_X, BN254_G2_GENERATOR_Y};
+    use crate::curve::curve_types::{Curve, CurveScalar};
+    use crate::curve::pairing::{Fp12, Fp2, G2AffinePoint};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn fp2_from_hex(c0: &str, c1: &str) -> Fp2<Bn254Base> {
+        let parse =
+            |s: &str| Bn254Base::from_biguint(BigUint::parse_bytes(s.as_bytes(), 16).unwrap());
+        Fp2([parse(c0), parse(c1)])
+    }
+
+    /// A cheap check of the building blocks of the BN254 Miller loop, against their native
+    /// counterparts, since the full pairing check is too slow to run routinely.
+    #[test]
+    fn test_miller_loop_steps_bn254() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let p = Bn254::GENERATOR_AFFINE;
+        let q = G2AffinePoint::<Bn254>::nonzero(BN254_G2_GENERATOR_X, BN254_G2_GENERATOR_Y);
+        let p_target = builder.constant_affine_point(p);
+        let q_target = builder.constant_g2_point(q.x, q.y);
+
+        let (doubled, line) = builder.g2_double_with_line(&q_target, &p_target);
+        let expected_doubled = q.double();
+        let expected_line = Fp12::line_evaluation(q.tangent_slope(), &q, &p);
+        let expected_doubled_target =
+            builder.constant_g2_point(expected_doubled.x, expected_doubled.y);
+        builder.connect_g2_point(&doubled, &expected_doubled_target);
+        for (c, &e) in line.iter().zip(&expected_line.0) {
+            match c {
+                Some(c) => {
+                    let e = builder.constant_fp2(e);
+                    builder.connect_fp2(c, &e);
+                }
+                None => assert_eq!(e, Fp2::ZERO),
+            }
+        }
+
+        let (sum, _) = builder.g2_add_with_line(&doubled, &q_target, &p_target);
+        let expected_sum = expected_doubled + q;
+        let expected_sum_target = builder.constant_g2_point(expected_sum.x, expected_sum.y);
+        builder.connect_g2_point(&sum, &expected_sum_target);
+
+        let frob = builder.g2_frobenius(&q_target, 1);
+        builder.g2_assert_valid(&frob);
+        let expected_frob = q.frobenius(1);
+        let expected_frob_target = builder.constant_g2_point(expected_frob.x, expected_frob.y);
+        builder.connect_g2_point(&frob, &expected_frob_target);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[ignore]
+    fn test_pairing_check_bn254() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // Check that e(7 g1, g2) e(-g1, 7 g2) = 1.
+        let g1 = Bn254::GENERATOR_AFFINE;
+        let g1_7 =
+            (CurveScalar(Bn254Scalar::from_canonical_u32(7)) * g1.to_projective()).to_affine();
+        let g1_7 = builder.constant_affine_point(g1_7);
+        let neg_g1 = builder.constant_affine_point(g1);
+        let neg_g1 = builder.curve_neg(&neg_g1);
+        let g2 = builder.constant_g2_point(BN254_G2_GENERATOR_X, BN254_G2_GENERATOR_Y);
+        let g2_7 = builder.constant_g2_point(
+            fp2_from_hex(
+                "224bdc5d4327fcf8ed702e01de1c2f1657a253ba75e32a89c390142aaa28b308",
+                "2903ba015a9abde26a5d081e84551e63be0fd4516e46ee6d593edeba46362455",
+            ),
+            fp2_from_hex(
+                "1d92fff52a265017eeccb372e37d7a7bd431800eca28dfd82e21e8054114233f",
+                "03c8b7cda6b2dedb7aeeaf5fda464ad17036bea1c4e6f7adbaed1ebe0335e0d8",
+            ),
+        );
+        builder.g2_assert_valid(&g2_7);
+        builder.pairing_check_bn254(&[(g1_7, g2), (neg_g1, g2_7)]);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
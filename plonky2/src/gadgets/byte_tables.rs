@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The number of entries of each committed table.
+const TABLE_LEN: usize = 1 << 8;
+
+/// A predefined table of byte operations, which gadgets can look values up in by its ID.
+///
+/// The first time a circuit looks a value up in a table, the entries of the table are added to the
+/// circuit as constants, so they are committed to in the constants oracle along with the rest of
+/// the circuit, and the prover can't choose them. Since there is no lookup argument, a lookup is a
+/// random access into these entries, indexed by its inputs. Tables of two bytes would have `2^16`
+/// entries, so those are committed as tables of two nibbles instead, and looked up once per nibble.
+/// Each table is committed at most once per circuit, and repeated lookups return the same target,
+/// so hash and VM gadgets can all use the same tables without duplicating any work.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ByteTable {
+    /// Maps each byte to itself, so looking a value up checks that it is a byte.
+    Range,
+    /// The bitwise XOR of two bytes.
+    Xor,
+    /// The bitwise AND of two bytes.
+    And,
+    /// Shifts a byte left by the given number of bits, dropping the bits shifted out.
+    ShiftLeft(u8),
+    /// Shifts a byte right by the given number of bits.
+    ShiftRight(u8),
+}
+
+impl ByteTable {
+    /// A stable identifier for this table.
+    pub fn id(self) -> u16 {
+        match self {
+            ByteTable::Range => 0,
+            ByteTable::Xor => 1,
+            ByteTable::And => 2,
+            ByteTable::ShiftLeft(n) => 0x100 | n as u16,
+            ByteTable::ShiftRight(n) => 0x200 | n as u16,
+        }
+    }
+
+    /// The table with the given ID, if there is one.
+    pub fn from_id(id: u16) -> Option<Self> {
+        let table = match id {
+            0 => ByteTable::Range,
+            1 => ByteTable::Xor,
+            2 => ByteTable::And,
+            _ if id >> 8 == 1 => ByteTable::ShiftLeft(id as u8),
+            _ if id >> 8 == 2 => ByteTable::ShiftRight(id as u8),
+            _ => return None,
+        };
+        if table.is_valid() {
+            Some(table)
+        } else {
+            None
+        }
+    }
+
+    fn is_valid(self) -> bool {
+        match self {
+            ByteTable::ShiftLeft(n) | ByteTable::ShiftRight(n) => n < 8,
+            _ => true,
+        }
+    }
+
+    /// The number of bytes a row of this table is indexed by.
+    pub fn num_inputs(self) -> usize {
+        match self {
+            ByteTable::Xor | ByteTable::And => 2,
+            _ => 1,
+        }
+    }
+
+    /// The entry of this table for the given inputs.
+    pub fn eval(self, inputs: &[u8]) -> u8 {
+        assert_eq!(inputs.len(), self.num_inputs(), "Wrong number of inputs");
+        match self {
+            ByteTable::Range => inputs[0],
+            ByteTable::Xor => inputs[0] ^ inputs[1],
+            ByteTable::And => inputs[0] & inputs[1],
+            ByteTable::ShiftLeft(n) => inputs[0] << n,
+            ByteTable::ShiftRight(n) => inputs[0] >> n,
+        }
+    }
+
+    /// The committed entries of this table. A table of one byte is indexed by it, and a table of
+    /// two bytes is instead the table of the same operation on two nibbles `a` and `b`, indexed by
+    /// `a + 16 b`.
+    fn entries(self) -> Vec<u8> {
+        (0..TABLE_LEN)
+            .map(|i| match self.num_inputs() {
+                1 => self.eval(&[i as u8]),
+                _ => self.eval(&[i as u8 & 0xf, (i >> 4) as u8]),
+            })
+            .collect()
+    }
+}
+
+/// The tables committed to and the lookups made so far in a circuit, which are shared by all
+/// gadgets using byte tables.
+#[derive(Default)]
+pub(crate) struct ByteTableCache {
+    entries: HashMap<ByteTable, Vec<Target>>,
+    nibbles: HashMap<Target, Vec<Target>>,
+    lookups: HashMap<(ByteTable, Vec<Target>), Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Looks up the entry of `table` for `inputs`, which are checked to be bytes.
+    pub fn byte_lookup(&mut self, table: ByteTable, inputs: &[Target]) -> Target {
+        assert!(table.is_valid(), "Invalid table {:?}", table);
+        assert_eq!(inputs.len(), table.num_inputs(), "Wrong number of inputs");
+
+        let key = (table, inputs.to_vec());
+        if let Some(&result) = self.byte_tables.lookups.get(&key) {
+            return result;
+        }
+
+        let result = match table.num_inputs() {
+            // The random access checks that the index is below `TABLE_LEN`, so this also checks
+            // that the input is a byte.
+            1 => self.committed_table_access(table, inputs[0]),
+            _ => {
+                let x = self.byte_nibbles(inputs[0]);
+                let y = self.byte_nibbles(inputs[1]);
+                let sixteen = F::from_canonical_u64(16);
+                let low_index = self.mul_const_add(sixteen, y[0], x[0]);
+                let high_index = self.mul_const_add(sixteen, y[1], x[1]);
+                let low = self.committed_table_access(table, low_index);
+                let high = self.committed_table_access(table, high_index);
+                self.mul_const_add(sixteen, high, low)
+            }
+        };
+
+        self.byte_tables.lookups.insert(key, result);
+        result
+    }
+
+    /// The entry of the committed `table` at `index`, committing to the table if this is the first
+    /// time it is used.
+    fn committed_table_access(&mut self, table: ByteTable, index: Target) -> Target {
+        let entries = match self.byte_tables.entries.get(&table) {
+            Some(entries) => entries.clone(),
+            None => {
+                let entries = table
+                    .entries()
+                    .into_iter()
+                    .map(|e| self.constant(F::from_canonical_u16(e as u16)))
+                    .collect::<Vec<_>>();
+                self.byte_tables.entries.insert(table, entries.clone());
+                entries
+            }
+        };
+        // The gates of the random access generate the entry.
+        let entry = self.add_virtual_target();
+        self.random_access(index, entry, entries);
+        entry
+    }
+
+    /// The little-endian nibbles of the byte `x`, decomposing it if this is the first time.
+    fn byte_nibbles(&mut self, x: Target) -> Vec<Target> {
+        if let Some(nibbles) = self.byte_tables.nibbles.get(&x) {
+            return nibbles.clone();
+        }
+        // A base-16 decomposition would need constraints of degree 16, so split into base-4 digits
+        // and combine them in pairs.
+        let digits = self.split_le_base::<4>(x, 4);
+        let four = F::from_canonical_u64(4);
+        let nibbles = digits
+            .chunks(2)
+            .map(|d| self.mul_const_add(four, d[1], d[0]))
+            .collect::<Vec<_>>();
+        self.byte_tables.nibbles.insert(x, nibbles.clone());
+        nibbles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use rand::{thread_rng, Rng};
+
+    use crate::gadgets::byte_tables::ByteTable;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const TABLES: [ByteTable; 7] = [
+        ByteTable::Range,
+        ByteTable::Xor,
+        ByteTable::And,
+        ByteTable::ShiftLeft(1),
+        ByteTable::ShiftLeft(7),
+        ByteTable::ShiftRight(3),
+        ByteTable::ShiftRight(0),
+    ];
+
+    #[test]
+    fn test_table_ids() {
+        for table in TABLES {
+            assert_eq!(ByteTable::from_id(table.id()), Some(table));
+        }
+        assert_eq!(ByteTable::from_id(0x108), None);
+        assert_eq!(ByteTable::from_id(3), None);
+    }
+
+    #[test]
+    fn test_byte_lookups() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        let values = [rng.gen::<u8>(), rng.gen::<u8>()];
+        let targets = builder.add_virtual_targets(2);
+        for (&t, &v) in targets.iter().zip(&values) {
+            pw.set_target(t, F::from_canonical_u16(v as u16));
+        }
+
+        for table in TABLES {
+            let n = table.num_inputs();
+            let result = builder.byte_lookup(table, &targets[..n]);
+            let expected = builder.constant(F::from_canonical_u16(table.eval(&values[..n]) as u16));
+            builder.connect(result, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_byte_lookups_are_shared() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = builder.add_virtual_targets(2);
+
+        let xor = builder.byte_lookup(ByteTable::Xor, &targets);
+        let num_gates = builder.num_gates();
+        // Repeating a lookup is free.
+        assert_eq!(builder.byte_lookup(ByteTable::Xor, &targets), xor);
+        assert_eq!(builder.num_gates(), num_gates);
+
+        // Another lookup of the same bytes reuses their nibbles, and each table is committed once.
+        builder.byte_lookup(ByteTable::And, &targets);
+        builder.byte_lookup(ByteTable::And, &[targets[1], targets[0]]);
+        assert_eq!(builder.byte_tables.nibbles.len(), 2);
+        assert_eq!(builder.byte_tables.entries.len(), 2);
+    }
+}
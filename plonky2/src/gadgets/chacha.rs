@@ -0,0 +1,245 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::BoolTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The constant words "expand 32-byte k" which begin the ChaCha20 state.
+const CHACHA_CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+const CHACHA_DOUBLE_ROUNDS: usize = 10;
+
+/// A 32-bit word, represented by its little-endian bits.
+type WordBits = Vec<BoolTarget>;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the ChaCha20 block function (RFC 8439) for the given key, block counter and nonce,
+    /// returning the 16 words of serialized keystream.
+    pub fn chacha20_block(
+        &mut self,
+        key: &[U32Target; 8],
+        counter: U32Target,
+        nonce: &[U32Target; 3],
+    ) -> [U32Target; 16] {
+        let mut initial_state = Vec::with_capacity(16);
+        for c in CHACHA_CONSTANTS {
+            initial_state.push(self.constant_le_bits(c as u64, 32));
+        }
+        for &k in key {
+            initial_state.push(self.split_u32_le(k));
+        }
+        initial_state.push(self.split_u32_le(counter));
+        for &n in nonce {
+            initial_state.push(self.split_u32_le(n));
+        }
+
+        let mut state = initial_state.clone();
+        for _ in 0..CHACHA_DOUBLE_ROUNDS {
+            // Column rounds.
+            self.chacha_quarter_round(&mut state, 0, 4, 8, 12);
+            self.chacha_quarter_round(&mut state, 1, 5, 9, 13);
+            self.chacha_quarter_round(&mut state, 2, 6, 10, 14);
+            self.chacha_quarter_round(&mut state, 3, 7, 11, 15);
+            // Diagonal rounds.
+            self.chacha_quarter_round(&mut state, 0, 5, 10, 15);
+            self.chacha_quarter_round(&mut state, 1, 6, 11, 12);
+            self.chacha_quarter_round(&mut state, 2, 7, 8, 13);
+            self.chacha_quarter_round(&mut state, 3, 4, 9, 14);
+        }
+
+        let mut output = [self.zero_u32(); 16];
+        for i in 0..16 {
+            let x = self.le_bits_to_u32(&state[i]);
+            let y = self.le_bits_to_u32(&initial_state[i]);
+            output[i] = self.add_u32(x, y).0;
+        }
+        output
+    }
+
+    /// Encrypts (or, equivalently, decrypts) a message with ChaCha20, by XORing it with the
+    /// keystream starting at block `initial_counter`. Words are interpreted in little-endian order,
+    /// as in RFC 8439.
+    ///
+    /// # Panics
+    /// If the message needs more blocks than the 32-bit counter has left after `initial_counter`,
+    /// since a wrapped counter would reuse the keystream.
+    pub fn chacha20_xor(
+        &mut self,
+        key: &[U32Target; 8],
+        nonce: &[U32Target; 3],
+        initial_counter: u32,
+        message: &[U32Target],
+    ) -> Vec<U32Target> {
+        let num_blocks = (message.len() + 15) / 16;
+        assert!(
+            initial_counter as u64 + num_blocks as u64 <= 1 << 32,
+            "A message of {} blocks starting at block {} overflows the ChaCha20 block counter",
+            num_blocks,
+            initial_counter
+        );
+        message
+            .chunks(16)
+            .enumerate()
+            .flat_map(|(i, chunk)| {
+                let counter = self.constant_u32(initial_counter + i as u32);
+                let keystream = self.chacha20_block(key, counter, nonce);
+                chunk
+                    .iter()
+                    .zip(keystream)
+                    .map(|(&m, k)| {
+                        let m_bits = self.split_u32_le(m);
+                        let k_bits = self.split_u32_le(k);
+                        let c_bits = self.xor_bits(&m_bits, &k_bits);
+                        self.le_bits_to_u32(&c_bits)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn chacha_quarter_round(
+        &mut self,
+        state: &mut [WordBits],
+        a: usize,
+        b: usize,
+        c: usize,
+        d: usize,
+    ) {
+        state[a] = self.add_u32_bits(&state[a], &state[b]);
+        state[d] = self.xor_rotate_left(&state[d], &state[a], 16);
+        state[c] = self.add_u32_bits(&state[c], &state[d]);
+        state[b] = self.xor_rotate_left(&state[b], &state[c], 12);
+        state[a] = self.add_u32_bits(&state[a], &state[b]);
+        state[d] = self.xor_rotate_left(&state[d], &state[a], 8);
+        state[c] = self.add_u32_bits(&state[c], &state[d]);
+        state[b] = self.xor_rotate_left(&state[b], &state[c], 7);
+    }
+
+    /// Computes `x + y mod 2^32`.
+    fn add_u32_bits(&mut self, x: &[BoolTarget], y: &[BoolTarget]) -> WordBits {
+        let x = self.le_bits_to_u32(x);
+        let y = self.le_bits_to_u32(y);
+        let (sum, _carry) = self.add_u32(x, y);
+        self.split_u32_le(sum)
+    }
+
+    /// Computes `(x ^ y) <<< shift`.
+    fn xor_rotate_left(&mut self, x: &[BoolTarget], y: &[BoolTarget], shift: usize) -> WordBits {
+        let mut res = self.xor_bits(x, y);
+        // Bits are little-endian, so a left rotation moves each bit to a higher index.
+        res.rotate_right(shift);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::gadgets::arithmetic_u32::U32Target;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// The key `00 01 02 ... 1f` from the RFC 8439 test vectors, as little-endian words.
+    fn test_key(builder: &mut CircuitBuilder<F, D>) -> [U32Target; 8] {
+        let mut key = [builder.zero_u32(); 8];
+        for i in 0..8 {
+            let bytes = [
+                4 * i as u8,
+                4 * i as u8 + 1,
+                4 * i as u8 + 2,
+                4 * i as u8 + 3,
+            ];
+            key[i] = builder.constant_u32(u32::from_le_bytes(bytes));
+        }
+        key
+    }
+
+    #[test]
+    fn test_chacha20_block() -> Result<()> {
+        // Test vector from RFC 8439, section 2.3.2.
+        let expected = [
+            0xe4e7f110, 0x15593bd1, 0x1fdd0f50, 0xc47120a3, 0xc7f4d1c7, 0x0368c033, 0x9aaa2204,
+            0x4e6cd4c3, 0x466482d2, 0x09aa9f07, 0x05d7c214, 0xa2028bd9, 0xd19c12b5, 0xb94e16de,
+            0xe883d0cb, 0x4e3c50a2,
+        ];
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let key = test_key(&mut builder);
+        let counter = builder.constant_u32(1);
+        let nonce = [
+            builder.constant_u32(0x09000000),
+            builder.constant_u32(0x4a000000),
+            builder.constant_u32(0),
+        ];
+        let output = builder.chacha20_block(&key, counter, &nonce);
+        for (o, e) in output.into_iter().zip(expected) {
+            let e = builder.constant_u32(e);
+            builder.connect_u32(o, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows the ChaCha20 block counter")]
+    fn test_chacha20_xor_counter_overflow() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let key = test_key(&mut builder);
+        let nonce = [builder.zero_u32(); 3];
+        // The second block would need counter 2^32.
+        let message = [builder.zero_u32(); 17];
+        builder.chacha20_xor(&key, &nonce, u32::MAX, &message);
+    }
+
+    #[test]
+    fn test_chacha20_xor() -> Result<()> {
+        let plaintext = [
+            0x6964614c, 0x61207365, 0x4720646e, 0x6c746e65, 0x6e656d65, 0x20666f20, 0x20656874,
+            0x73616c63, 0x666f2073,
+        ];
+        let ciphertext = [
+            0x9a352e6e, 0x80f96825, 0x2807ba41, 0x81690ddd, 0xec7a7ee9, 0xc260431d, 0xccaf270a,
+            0x0bae9ffd, 0xc5651bf9,
+        ];
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let key = test_key(&mut builder);
+        let nonce = [
+            builder.constant_u32(0),
+            builder.constant_u32(0x4a000000),
+            builder.constant_u32(0),
+        ];
+        let message = plaintext.map(|m| builder.constant_u32(m));
+        let output = builder.chacha20_xor(&key, &nonce, 1, &message);
+        for (o, c) in output.into_iter().zip(ciphertext) {
+            let c = builder.constant_u32(c);
+            builder.connect_u32(o, c);
+        }
+
+        // Decryption should recover the plaintext.
+        let ciphertext = ciphertext.map(|c| builder.constant_u32(c));
+        let output = builder.chacha20_xor(&key, &nonce, 1, &ciphertext);
+        for (o, m) in output.into_iter().zip(plaintext) {
+            let m = builder.constant_u32(m);
+            builder.connect_u32(o, m);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,307 @@
+//! Lookup tables which are committed to once, as standalone artifacts, and shared by any number of
+//! circuits.
+//!
+//! A `CommittedTable` is a Merkle tree of the rows of a table, e.g. a 16-bit range table or the
+//! byte XOR table. A circuit referencing it holds only its cap, as constants, so every proof of the
+//! circuit is verified against the same commitment, and large tables aren't copied into each
+//! circuit. A lookup opens a row of the table with a Merkle proof, so it costs one hash per layer
+//! of the tree below the cap. Unlike the tables of `byte_lookup`, which are committed as constants
+//! of each circuit using them, this suits tables too large to be random-accessed in a circuit.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_util::{log2_ceil, log2_strict};
+
+use crate::gadgets::byte_tables::ByteTable;
+use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::merkle_tree::{MerkleCap, MerkleTree};
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// A table whose rows consist of some inputs followed by the corresponding outputs, committed to as
+/// a Merkle tree with one leaf per row.
+#[derive(Debug)]
+pub struct CommittedTable<F: RichField, H: AlgebraicHasher<F>> {
+    num_inputs: usize,
+    tree: MerkleTree<F, H>,
+    /// The index of the row holding each tuple of inputs.
+    row_indices: HashMap<Vec<u64>, usize>,
+}
+
+impl<F: RichField, H: AlgebraicHasher<F>> CommittedTable<F, H> {
+    /// Commits to the given rows, each of which starts with `num_inputs` inputs. The rows are
+    /// padded to a power of two by repeating the last one.
+    pub fn new(mut rows: Vec<Vec<F>>, num_inputs: usize, cap_height: usize) -> Self
+    where
+        [(); H::HASH_SIZE]:,
+    {
+        assert!(!rows.is_empty(), "The table must be nonempty");
+        let width = rows[0].len();
+        assert!(width >= num_inputs, "Rows must hold all the inputs");
+
+        let mut row_indices = HashMap::new();
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.len(), width, "Rows must have the same width");
+            let key = row[..num_inputs]
+                .iter()
+                .map(|x| x.to_canonical_u64())
+                .collect();
+            let j = *row_indices.entry(key).or_insert(i);
+            assert_eq!(row, &rows[j], "Rows with the same inputs must be equal");
+        }
+
+        let num_rows = 1 << log2_ceil(rows.len()).max(cap_height);
+        let last = rows[rows.len() - 1].clone();
+        rows.resize(num_rows, last);
+
+        Self {
+            num_inputs,
+            tree: MerkleTree::new(rows, cap_height),
+            row_indices,
+        }
+    }
+
+    /// Commits to one of the predefined byte tables, whose rows are its input bytes followed by its
+    /// entry for them.
+    pub fn from_byte_table(table: ByteTable, cap_height: usize) -> Self
+    where
+        [(); H::HASH_SIZE]:,
+    {
+        let num_inputs = table.num_inputs();
+        let rows = (0..1usize << (8 * num_inputs))
+            .map(|i| {
+                let inputs = (0..num_inputs)
+                    .map(|j| (i >> (8 * j)) as u8)
+                    .collect::<Vec<_>>();
+                let mut row = inputs
+                    .iter()
+                    .map(|&x| F::from_canonical_u16(x as u16))
+                    .collect::<Vec<_>>();
+                row.push(F::from_canonical_u16(table.eval(&inputs) as u16));
+                row
+            })
+            .collect();
+        Self::new(rows, num_inputs, cap_height)
+    }
+
+    /// Commits to the table of integers below `2^bits`, looking a value up in which range-checks
+    /// it.
+    pub fn range(bits: usize, cap_height: usize) -> Self
+    where
+        [(); H::HASH_SIZE]:,
+    {
+        let rows = (0..1u64 << bits)
+            .map(|i| vec![F::from_canonical_u64(i)])
+            .collect();
+        Self::new(rows, 1, cap_height)
+    }
+
+    pub fn cap(&self) -> &MerkleCap<F, H> {
+        &self.tree.cap
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.tree.leaves[0].len() - self.num_inputs
+    }
+
+    /// The index of the row with the given inputs, if there is one.
+    pub fn row_index(&self, inputs: &[F]) -> Option<usize> {
+        let key = inputs
+            .iter()
+            .map(|x| x.to_canonical_u64())
+            .collect::<Vec<_>>();
+        self.row_indices.get(&key).copied()
+    }
+
+    /// The outputs of the row with the given inputs, if there is one.
+    pub fn lookup(&self, inputs: &[F]) -> Option<&[F]> {
+        self.row_index(inputs)
+            .map(|i| &self.tree.leaves[i][self.num_inputs..])
+    }
+
+    fn cap_height(&self) -> usize {
+        log2_strict(self.tree.cap.len())
+    }
+
+    fn num_layers(&self) -> usize {
+        log2_strict(self.tree.leaves.len()) - self.cap_height()
+    }
+}
+
+/// A reference to a `CommittedTable` in a circuit, whose cap is fixed in the circuit.
+#[derive(Clone, Debug)]
+pub struct CommittedTableTarget<F: RichField, H: AlgebraicHasher<F>> {
+    pub cap: MerkleCapTarget,
+    table: Arc<CommittedTable<F, H>>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// References a committed table, which may be shared with other circuits.
+    pub fn committed_table<H: AlgebraicHasher<F> + 'static>(
+        &mut self,
+        table: Arc<CommittedTable<F, H>>,
+    ) -> CommittedTableTarget<F, H> {
+        let cap = MerkleCapTarget(
+            table
+                .cap()
+                .0
+                .iter()
+                .map(|&h| self.constant_hash(h))
+                .collect(),
+        );
+        CommittedTableTarget { cap, table }
+    }
+
+    /// Looks up the outputs of the row of `table` with the given inputs, failing to prove if there is
+    /// no such row.
+    pub fn committed_table_lookup<H: AlgebraicHasher<F> + 'static>(
+        &mut self,
+        table: &CommittedTableTarget<F, H>,
+        inputs: &[Target],
+    ) -> Vec<Target> {
+        assert_eq!(
+            inputs.len(),
+            table.table.num_inputs(),
+            "Wrong number of inputs"
+        );
+        let num_layers = table.table.num_layers();
+        let cap_height = table.table.cap_height();
+
+        let row_index = self.add_virtual_target();
+        let outputs = self.add_virtual_targets(table.table.num_outputs());
+        let proof = self.add_virtual_merkle_proof(num_layers);
+        self.add_simple_generator(CommittedTableLookupGenerator {
+            table: table.table.clone(),
+            inputs: inputs.to_vec(),
+            row_index,
+            outputs: outputs.clone(),
+            siblings: proof.siblings.clone(),
+        });
+
+        let row_index_bits = self.split_le(row_index, num_layers + cap_height);
+        let cap_index = self.le_sum(row_index_bits[num_layers..].iter());
+        let row = [inputs, &outputs].concat();
+        self.verify_merkle_proof_with_cap_index::<H>(
+            row,
+            &row_index_bits[..num_layers],
+            cap_index,
+            &table.cap,
+            &proof,
+        );
+        outputs
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CommittedTableLookupGenerator<F: RichField, H: AlgebraicHasher<F>> {
+    table: Arc<CommittedTable<F, H>>,
+    inputs: Vec<Target>,
+    row_index: Target,
+    outputs: Vec<Target>,
+    siblings: Vec<HashOutTarget>,
+}
+
+impl<F: RichField, H: AlgebraicHasher<F> + 'static> SimpleGenerator<F>
+    for CommittedTableLookupGenerator<F, H>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        self.inputs.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let inputs = witness.get_targets(&self.inputs);
+        let row_index = self
+            .table
+            .row_index(&inputs)
+            .expect("No row of the table has the given inputs");
+        let outputs = &self.table.tree.leaves[row_index][self.table.num_inputs..];
+        let proof = self.table.tree.prove(row_index);
+
+        out_buffer.set_target(self.row_index, F::from_canonical_usize(row_index));
+        for (&t, &x) in self.outputs.iter().zip(outputs) {
+            out_buffer.set_target(t, x);
+        }
+        for (&t, &sibling) in self.siblings.iter().zip(&proof.siblings) {
+            out_buffer.set_hash_target(t, sibling);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::gadgets::byte_tables::ByteTable;
+    use crate::gadgets::committed_table::CommittedTable;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = PoseidonHash;
+
+    #[test]
+    fn test_committed_table_lookup() {
+        let table = CommittedTable::<F, H>::new(
+            vec![
+                vec![F::ONE, F::TWO],
+                vec![F::TWO, F::ONE],
+                vec![F::ZERO, F::ZERO],
+            ],
+            1,
+            1,
+        );
+        assert_eq!(table.num_outputs(), 1);
+        assert_eq!(table.lookup(&[F::TWO]), Some(&[F::ONE][..]));
+        assert_eq!(table.lookup(&[F::NEG_ONE]), None);
+
+        let xor = CommittedTable::<F, H>::from_byte_table(ByteTable::Xor, 4);
+        let inputs = [F::from_canonical_u16(0x5a), F::from_canonical_u16(0x0f)];
+        assert_eq!(
+            xor.lookup(&inputs),
+            Some(&[F::from_canonical_u16(0x55)][..])
+        );
+    }
+
+    /// Two different circuits look values up in the same committed table.
+    #[test]
+    fn test_shared_committed_table() -> Result<()> {
+        let table = Arc::new(CommittedTable::<F, H>::from_byte_table(ByteTable::Xor, 4));
+
+        for values in [[0x12u16, 0xff], [0x80, 0x80]] {
+            let config = CircuitConfig::standard_recursion_config();
+            let mut pw = PartialWitness::new();
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+
+            let table_t = builder.committed_table(table.clone());
+            let inputs = builder.add_virtual_targets(2);
+            for (&t, &v) in inputs.iter().zip(&values) {
+                pw.set_target(t, F::from_canonical_u16(v));
+            }
+            let outputs = builder.committed_table_lookup(&table_t, &inputs);
+            let expected = builder.constant(F::from_canonical_u16(values[0] ^ values[1]));
+            builder.connect(outputs[0], expected);
+
+            let data = builder.build::<C>();
+            let proof = data.prove(pw)?;
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+}
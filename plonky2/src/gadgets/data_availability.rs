@@ -0,0 +1,445 @@
+//! Data availability sampling. A blob of field elements is arranged as the evaluations of a batch of
+//! polynomials, which are extended with a low-degree extension and Merklized, exactly as a FRI
+//! oracle. A Merkle root alone doesn't show that the committed leaves form a codeword, so the
+//! committer also sends a FRI proof that they are close to the LDE of polynomials of the blob's
+//! degree. Given that proof, any `1 / 2^rate_bits` fraction of the extended data, up to the
+//! distance FRI tolerates, suffices to recover the blob, so a verifier who sees valid openings at
+//! enough random positions is convinced, with high probability, that the whole blob is available.
+//! Openings alone, without checking the low-degree proof, give no such guarantee.
+//!
+//! Positions are indices of Merkle leaves, which store the LDE in bit-reversed order, as in FRI.
+
+use anyhow::Result;
+use log::Level;
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::polynomial::PolynomialValues;
+use plonky2_util::{ceil_div_usize, log2_ceil};
+
+use crate::fri::fft_backend::CpuFftBackend;
+use crate::fri::oracle::PolynomialBatch;
+use crate::fri::pcs::{self, FriOpeningProof};
+use crate::fri::proof::FriProofTarget;
+use crate::fri::structure::{
+    FriBatchInfoTarget, FriInstanceInfoTarget, FriOpeningBatchTarget, FriOpeningsTarget,
+    FriOracleInfo, FriPolynomialInfo,
+};
+use crate::fri::witness_util::set_fri_proof_target;
+use crate::fri::{FriConfig, FriParams, SaltMode};
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hashing::hash_n_to_m_no_pad;
+use crate::hash::merkle_proofs::{verify_merkle_proof, MerkleProof, MerkleProofTarget};
+use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::challenger::{Challenger, RecursiveChallenger};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::util::progress::ProvingMonitor;
+use crate::util::timing::TimingTree;
+
+/// A commitment to a blob, arranged as `width` polynomials whose evaluations are the blob's
+/// elements in row-major order.
+pub struct BlobCommitment<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    pub batch: PolynomialBatch<F, C, D>,
+    pub width: usize,
+    /// The parameters of the low-degree proof, which also fix the rate and cap height.
+    pub params: FriParams,
+}
+
+/// A proof that a blob commitment is close to the LDE of `width` polynomials of the blob's degree.
+/// It opens them at a point derived from the commitment, which FRI then checks.
+pub type BlobLowDegreeProof<F, C, const D: usize> = FriOpeningProof<F, C, D>;
+
+/// An opening of a blob commitment at one position: the row of LDE values stored in that leaf,
+/// along with its Merkle proof.
+#[derive(Clone, Debug)]
+pub struct BlobOpening<F: RichField, H: Hasher<F>> {
+    pub values: Vec<F>,
+    pub proof: MerkleProof<F, H>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    BlobCommitment<F, C, D>
+{
+    /// Commits to `blob`, which is padded with zeros to fill a `width` by `2^k` matrix, with the
+    /// rate and cap height of `fri_config`.
+    pub fn new(blob: &[F], width: usize, fri_config: &FriConfig) -> Self
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        let degree_log = log2_ceil(ceil_div_usize(blob.len(), width));
+        let params = fri_config.fri_params(degree_log, 0, SaltMode::PerLeaf);
+        let degree = 1 << degree_log;
+        let values = (0..width)
+            .map(|j| {
+                PolynomialValues::new(
+                    (0..degree)
+                        .map(|i| blob.get(i * width + j).copied().unwrap_or(F::ZERO))
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let mut timing = TimingTree::new("commit to blob", Level::Debug);
+        let batch = PolynomialBatch::from_values(
+            values,
+            params.config.rate_bits,
+            0,
+            SaltMode::PerLeaf,
+            params.config.cap_height,
+            &mut timing,
+            &ProvingMonitor::default(),
+            &CpuFftBackend::new(),
+        );
+        Self {
+            batch,
+            width,
+            params,
+        }
+    }
+
+    pub fn cap(&self) -> &MerkleCap<F, C::Hasher> {
+        &self.batch.merkle_tree.cap
+    }
+
+    /// The log of the number of positions, i.e. of the size of the LDE.
+    pub fn lde_bits(&self) -> usize {
+        self.batch.degree_log + self.batch.rate_bits
+    }
+
+    pub fn open(&self, position: usize) -> BlobOpening<F, C::Hasher> {
+        BlobOpening {
+            values: self.batch.merkle_tree.get(position).to_vec(),
+            proof: self.batch.merkle_tree.prove(position),
+        }
+    }
+
+    pub fn verify_opening(
+        cap: &MerkleCap<F, C::Hasher>,
+        position: usize,
+        opening: &BlobOpening<F, C::Hasher>,
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        verify_merkle_proof(opening.values.clone(), position, cap, &opening.proof)
+    }
+
+    /// Proves that the commitment is close to a codeword, which the guarantee of sampling relies
+    /// on.
+    pub fn prove_low_degree(&self) -> BlobLowDegreeProof<F, C, D>
+    where
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        let mut challenger = Challenger::new();
+        challenger.observe_cap(self.cap());
+        let zeta = challenger.get_extension_challenge::<D>();
+        let mut timing = TimingTree::new("prove blob low degree", Level::Debug);
+        pcs::open(
+            &[&self.batch],
+            &[zeta],
+            &mut challenger,
+            &self.params,
+            &mut timing,
+        )
+    }
+
+    /// Verifies a proof from `prove_low_degree` against the commitment's cap.
+    pub fn verify_low_degree(
+        cap: &MerkleCap<F, C::Hasher>,
+        proof: &BlobLowDegreeProof<F, C, D>,
+        params: &FriParams,
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        let mut challenger = Challenger::new();
+        challenger.observe_cap(cap);
+        let zeta = challenger.get_extension_challenge::<D>();
+        pcs::verify(&[cap.clone()], &[zeta], proof, &mut challenger, params)
+    }
+}
+
+/// Derives `num_samples` pseudorandom positions in an LDE of size `2^lde_bits` from a seed, which
+/// would typically be a hash of the commitment or a public randomness beacon.
+pub fn sample_positions<F: RichField, H: AlgebraicHasher<F>>(
+    seed: HashOut<F>,
+    num_samples: usize,
+    lde_bits: usize,
+) -> Vec<usize> {
+    hash_n_to_m_no_pad::<F, H::Permutation>(&seed.elements, num_samples)
+        .into_iter()
+        .map(|x| (x.to_canonical_u64() as usize) & ((1 << lde_bits) - 1))
+        .collect()
+}
+
+/// A target representing a `BlobOpening`.
+#[derive(Clone, Debug)]
+pub struct BlobOpeningTarget {
+    pub values: Vec<Target>,
+    pub proof: MerkleProofTarget,
+}
+
+pub fn set_blob_opening_target<F: RichField, H: Hasher<F, Hash = HashOut<F>>, W: Witness<F>>(
+    witness: &mut W,
+    target: &BlobOpeningTarget,
+    opening: &BlobOpening<F, H>,
+) {
+    for (&t, &v) in target.values.iter().zip(&opening.values) {
+        witness.set_target(t, v);
+    }
+    for (&t, &sibling) in target.proof.siblings.iter().zip(&opening.proof.siblings) {
+        witness.set_hash_target(t, sibling);
+    }
+}
+
+/// A target representing a `BlobLowDegreeProof`.
+#[derive(Clone, Debug)]
+pub struct BlobLowDegreeProofTarget<const D: usize> {
+    /// The values of the blob's polynomials at the point derived from the commitment.
+    pub values: Vec<ExtensionTarget<D>>,
+    pub opening_proof: FriProofTarget<D>,
+}
+
+pub fn set_blob_low_degree_proof_target<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    W: Witness<F>,
+    const D: usize,
+>(
+    witness: &mut W,
+    target: &BlobLowDegreeProofTarget<D>,
+    proof: &BlobLowDegreeProof<F, C, D>,
+) where
+    C::Hasher: AlgebraicHasher<F>,
+    C::CommitPhaseHasher: AlgebraicHasher<F>,
+{
+    witness.set_extension_targets(&target.values, &proof.values[0][0]);
+    set_fri_proof_target(witness, &target.opening_proof, &proof.opening_proof);
+}
+
+const BLOB_ORACLE: FriOracleInfo = FriOracleInfo {
+    blinding: false,
+    extra_rate_bits: 0,
+};
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a virtual target for the low-degree proof of a blob with the given width, with the
+    /// parameters of its commitment.
+    pub fn add_virtual_blob_low_degree_proof(
+        &mut self,
+        width: usize,
+        params: &FriParams,
+    ) -> BlobLowDegreeProofTarget<D> {
+        BlobLowDegreeProofTarget {
+            values: self.add_virtual_extension_targets(width),
+            opening_proof: self.add_virtual_fri_proof(&[width], &[BLOB_ORACLE], params),
+        }
+    }
+
+    /// Verifies the low-degree proof of the blob committed to in `cap`, as in
+    /// `BlobCommitment::verify_low_degree`.
+    pub fn verify_blob_low_degree<C: GenericConfig<D, F = F>>(
+        &mut self,
+        cap: &MerkleCapTarget,
+        proof: &BlobLowDegreeProofTarget<D>,
+        params: &FriParams,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
+    {
+        let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(self);
+        challenger.observe_cap(cap);
+        let zeta = challenger.get_extension_challenge(self);
+        // The statement observed by `pcs::open`.
+        challenger.observe_cap(cap);
+        challenger.observe_extension_element(zeta);
+        challenger.observe_extension_elements(&proof.values);
+        let challenges = challenger.fri_challenges::<C>(
+            self,
+            &proof.opening_proof.commit_phase_merkle_caps,
+            &proof.opening_proof.final_poly,
+            proof.opening_proof.pow_witness,
+            &params.config,
+        );
+
+        let instance = FriInstanceInfoTarget {
+            oracles: vec![BLOB_ORACLE],
+            batches: vec![FriBatchInfoTarget {
+                point: zeta,
+                polynomials: FriPolynomialInfo::from_range(0, 0..proof.values.len()),
+            }],
+        };
+        let openings = FriOpeningsTarget {
+            batches: vec![FriOpeningBatchTarget {
+                values: proof.values.clone(),
+            }],
+        };
+        self.verify_fri_proof::<C>(
+            &instance,
+            &openings,
+            &challenges,
+            &[cap.clone()],
+            &proof.opening_proof,
+            params,
+        );
+    }
+
+    /// Adds a virtual opening target for a blob with the given width, whose commitment has an LDE
+    /// of size `2^lde_bits` and the given cap height.
+    pub fn add_virtual_blob_opening(
+        &mut self,
+        width: usize,
+        lde_bits: usize,
+        cap_height: usize,
+    ) -> BlobOpeningTarget {
+        BlobOpeningTarget {
+            values: self.add_virtual_targets(width),
+            proof: self.add_virtual_merkle_proof(lde_bits - cap_height),
+        }
+    }
+
+    /// Verifies an opening of a blob commitment at `position`, which is range-checked to
+    /// `lde_bits` bits.
+    pub fn verify_blob_opening<H: AlgebraicHasher<F>>(
+        &mut self,
+        cap: &MerkleCapTarget,
+        position: Target,
+        lde_bits: usize,
+        opening: &BlobOpeningTarget,
+    ) {
+        let position_bits = self.split_le(position, lde_bits);
+        self.verify_blob_opening_bits::<H>(cap, &position_bits, opening);
+    }
+
+    /// Derives `openings.len()` positions from `seed` as in `sample_positions`, and verifies the
+    /// openings at those positions.
+    pub fn verify_blob_samples<H: AlgebraicHasher<F>>(
+        &mut self,
+        cap: &MerkleCapTarget,
+        seed: HashOutTarget,
+        lde_bits: usize,
+        openings: &[BlobOpeningTarget],
+    ) {
+        let positions = self.hash_n_to_m_no_pad::<H>(seed.elements.to_vec(), openings.len());
+        for (position, opening) in positions.into_iter().zip(openings) {
+            // As in FRI, this decomposition permits non-canonical encodings, which only
+            // negligibly bias the sampled positions.
+            let position_bits = self.low_bits(position, lde_bits, F::BITS);
+            self.verify_blob_opening_bits::<H>(cap, &position_bits, opening);
+        }
+    }
+
+    fn verify_blob_opening_bits<H: AlgebraicHasher<F>>(
+        &mut self,
+        cap: &MerkleCapTarget,
+        position_bits: &[BoolTarget],
+        opening: &BlobOpeningTarget,
+    ) {
+        let num_layers = opening.proof.siblings.len();
+        let cap_index = self.le_sum(position_bits[num_layers..].iter());
+        self.verify_merkle_proof_with_cap_index::<H>(
+            opening.values.clone(),
+            &position_bits[..num_layers],
+            cap_index,
+            cap,
+            &opening.proof,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use super::*;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    #[test]
+    fn test_blob_samples() -> Result<()> {
+        let width = 4;
+        let num_samples = 8;
+        let fri_config = CircuitConfig::standard_recursion_config().fri_config;
+        let cap_height = fri_config.cap_height;
+
+        let blob = F::rand_vec(1000);
+        let commitment = BlobCommitment::<F, C, D>::new(&blob, width, &fri_config);
+        let lde_bits = commitment.lde_bits();
+        let low_degree_proof = commitment.prove_low_degree();
+        BlobCommitment::<F, C, D>::verify_low_degree(
+            commitment.cap(),
+            &low_degree_proof,
+            &commitment.params,
+        )?;
+        let seed = HashOut::rand();
+        let positions = sample_positions::<F, H>(seed, num_samples, lde_bits);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+
+        let cap = builder.add_virtual_cap(cap_height);
+        pw.set_cap_target(&cap, commitment.cap());
+        let seed_target = builder.add_virtual_hash();
+        pw.set_hash_target(seed_target, seed);
+
+        let openings = positions
+            .iter()
+            .map(|&position| {
+                let opening = commitment.open(position);
+                BlobCommitment::<F, C, D>::verify_opening(commitment.cap(), position, &opening)?;
+                let target = builder.add_virtual_blob_opening(width, lde_bits, cap_height);
+                set_blob_opening_target(&mut pw, &target, &opening);
+                Ok(target)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        builder.verify_blob_samples::<H>(&cap, seed_target, lde_bits, &openings);
+        let low_degree_target =
+            builder.add_virtual_blob_low_degree_proof(width, &commitment.params);
+        set_blob_low_degree_proof_target::<F, C, _, D>(
+            &mut pw,
+            &low_degree_target,
+            &low_degree_proof,
+        );
+        builder.verify_blob_low_degree::<C>(&cap, &low_degree_target, &commitment.params);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_non_codeword_rejected() {
+        let width = 4;
+        let fri_config = CircuitConfig::standard_recursion_config().fri_config;
+        let mut commitment = BlobCommitment::<F, C, D>::new(&F::rand_vec(1000), width, &fri_config);
+        // Replace part of the LDE by arbitrary values, so that the committed leaves are far from
+        // any codeword, and recommit.
+        let lde_size = 1 << commitment.lde_bits();
+        let mut leaves = commitment.batch.merkle_tree.leaves.clone();
+        for leaf in leaves.iter_mut().take(lde_size / 2) {
+            *leaf = F::rand_vec(width);
+        }
+        commitment.batch.merkle_tree = MerkleTree::new(leaves, fri_config.cap_height);
+        let proof = commitment.prove_low_degree();
+        assert!(BlobCommitment::<F, C, D>::verify_low_degree(
+            commitment.cap(),
+            &proof,
+            &commitment.params
+        )
+        .is_err());
+    }
+}
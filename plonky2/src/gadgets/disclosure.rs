@@ -0,0 +1,305 @@
+//! Selective disclosure of public inputs. Rather than exposing some values as public inputs
+//! directly, a circuit can expose a hiding commitment to them, and the prover can later open any
+//! of the values against it, natively or in a follow-up circuit, without revealing the others.
+//! This suits credentials, whose holder proves statements about its attributes but only discloses
+//! the ones a verifier asks for.
+//!
+//! The values are committed to as the root of a Merkle tree whose `i`th leaf is
+//! `value_i || blinding_i`, where each blinding is a random digest, so opening a leaf reveals
+//! nothing about the other values. The tree is padded to a power of two with zero values, which
+//! are blinded too.
+
+use anyhow::Result;
+use plonky2_field::extension_field::Extendable;
+use plonky2_util::log2_strict;
+
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::merkle_proofs::{verify_merkle_proof, MerkleProof, MerkleProofTarget};
+use crate::hash::merkle_tree::{MerkleCap, MerkleTree};
+use crate::iop::target::Target;
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain};
+
+/// Values committed to for selective disclosure, along with their blindings.
+#[derive(Clone, Debug)]
+pub struct CommittedValues<F: RichField, H: AlgebraicHasher<F>> {
+    num_values: usize,
+    tree: MerkleTree<F, H>,
+}
+
+impl<F: RichField, H: AlgebraicHasher<F>> CommittedValues<F, H>
+where
+    [(); H::HASH_SIZE]:,
+{
+    /// Commits to `values` with random blindings.
+    pub fn new(values: &[F]) -> Self {
+        let blindings = (0..values.len().next_power_of_two())
+            .map(|_| HashOut::rand())
+            .collect();
+        Self::with_blindings(values, blindings)
+    }
+
+    /// Commits to `values` with the given blindings, one for each leaf of the padded tree.
+    pub fn with_blindings(values: &[F], blindings: Vec<HashOut<F>>) -> Self {
+        assert!(!values.is_empty(), "No values to commit to");
+        assert_eq!(
+            blindings.len(),
+            values.len().next_power_of_two(),
+            "Wrong number of blindings"
+        );
+        let leaves = blindings
+            .iter()
+            .enumerate()
+            .map(|(i, blinding)| {
+                let value = values.get(i).copied().unwrap_or(F::ZERO);
+                [vec![value], blinding.elements.to_vec()].concat()
+            })
+            .collect();
+        Self {
+            num_values: values.len(),
+            tree: MerkleTree::new(leaves, 0),
+        }
+    }
+
+    pub fn num_values(&self) -> usize {
+        self.num_values
+    }
+
+    pub fn commitment(&self) -> HashOut<F> {
+        self.tree.cap.0[0]
+    }
+
+    /// Opens the value at `index`, revealing it and its blinding but no other value.
+    pub fn open(&self, index: usize) -> Disclosure<F, H> {
+        assert!(index < self.num_values, "Index out of bounds");
+        let leaf = self.tree.get(index);
+        Disclosure {
+            index,
+            value: leaf[0],
+            blinding: HashOut::from_partial(&leaf[1..]),
+            proof: self.tree.prove(index),
+        }
+    }
+
+    fn blinding(&self, index: usize) -> HashOut<F> {
+        HashOut::from_partial(&self.tree.get(index)[1..])
+    }
+}
+
+/// A value opened against a commitment made by `CommittedValues`.
+#[derive(Clone, Debug)]
+pub struct Disclosure<F: RichField, H: AlgebraicHasher<F>> {
+    pub index: usize,
+    pub value: F,
+    pub blinding: HashOut<F>,
+    pub proof: MerkleProof<F, H>,
+}
+
+impl<F: RichField, H: AlgebraicHasher<F>> Disclosure<F, H>
+where
+    [(); H::HASH_SIZE]:,
+{
+    /// Checks that `value` is the value at `index` of the values committed to by `commitment`.
+    pub fn verify(&self, commitment: HashOut<F>) -> Result<()> {
+        let leaf = [vec![self.value], self.blinding.elements.to_vec()].concat();
+        verify_merkle_proof(leaf, self.index, &MerkleCap(vec![commitment]), &self.proof)
+    }
+}
+
+/// Values registered by `register_committed_public_inputs`.
+#[derive(Clone, Debug)]
+pub struct CommittedPublicInputsTarget {
+    /// The blinding of each leaf of the padded tree.
+    pub blindings: Vec<HashOutTarget>,
+    pub commitment: HashOutTarget,
+    /// The index among the circuit's public inputs of the first element of the commitment.
+    pub public_input_index: usize,
+}
+
+/// Sets the blindings of committed public inputs, whose values are set as usual.
+pub fn set_committed_public_inputs_target<F: RichField, H: AlgebraicHasher<F>, W: Witness<F>>(
+    witness: &mut W,
+    target: &CommittedPublicInputsTarget,
+    committed: &CommittedValues<F, H>,
+) where
+    [(); H::HASH_SIZE]:,
+{
+    for (i, &blinding) in target.blindings.iter().enumerate() {
+        witness.set_hash_target(blinding, committed.blinding(i));
+    }
+}
+
+/// A target representing a `Disclosure`.
+#[derive(Clone, Debug)]
+pub struct DisclosureTarget {
+    pub index: Target,
+    pub value: Target,
+    pub blinding: HashOutTarget,
+    pub proof: MerkleProofTarget,
+}
+
+/// Sets the witness for a disclosure target.
+pub fn set_disclosure_target<F: RichField, H: AlgebraicHasher<F>, W: Witness<F>>(
+    witness: &mut W,
+    target: &DisclosureTarget,
+    disclosure: &Disclosure<F, H>,
+) {
+    witness.set_target(target.index, F::from_canonical_usize(disclosure.index));
+    witness.set_target(target.value, disclosure.value);
+    witness.set_hash_target(target.blinding, disclosure.blinding);
+    for (&t, &sibling) in target.proof.siblings.iter().zip(&disclosure.proof.siblings) {
+        witness.set_hash_target(t, sibling);
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Commits to `values` as in `CommittedValues`, and registers the commitment as four public
+    /// inputs in place of the values themselves.
+    pub fn register_committed_public_inputs<H: AlgebraicHasher<F>>(
+        &mut self,
+        values: &[Target],
+    ) -> CommittedPublicInputsTarget {
+        assert!(!values.is_empty(), "No values to commit to");
+        let num_leaves = values.len().next_power_of_two();
+        let zero = self.zero();
+        let _false = self._false();
+
+        let blindings = (0..num_leaves)
+            .map(|_| self.add_virtual_hash())
+            .collect::<Vec<_>>();
+        let mut digests = blindings
+            .iter()
+            .enumerate()
+            .map(|(i, blinding)| {
+                let value = values.get(i).copied().unwrap_or(zero);
+                let leaf = [vec![value], blinding.elements.to_vec()].concat();
+                self.hash_or_noop::<H>(leaf)
+            })
+            .collect::<Vec<_>>();
+        let num_layers = log2_strict(num_leaves);
+        for i in 0..num_layers {
+            let domain = CompressionDomain::for_merkle_node(i + 1, num_layers);
+            digests = digests
+                .chunks_exact(2)
+                .map(|pair| {
+                    H::two_to_one_swapped_with_domain(pair[0], pair[1], _false, domain, self)
+                })
+                .collect();
+        }
+
+        let commitment = digests[0];
+        let public_input_index = self.public_inputs.len();
+        self.register_public_inputs(&commitment.elements);
+        CommittedPublicInputsTarget {
+            blindings,
+            commitment,
+            public_input_index,
+        }
+    }
+
+    /// Adds a disclosure of one of `num_values` committed values.
+    pub fn add_virtual_disclosure_target(&mut self, num_values: usize) -> DisclosureTarget {
+        let depth = log2_strict(num_values.next_power_of_two());
+        DisclosureTarget {
+            index: self.add_virtual_target(),
+            value: self.add_virtual_target(),
+            blinding: self.add_virtual_hash(),
+            proof: self.add_virtual_merkle_proof(depth),
+        }
+    }
+
+    /// Verifies that the disclosed value is the value at the disclosed index of the values
+    /// committed to by `commitment`, which is typically taken from the public inputs of an inner
+    /// proof. The index is range-checked to the depth of the tree.
+    pub fn verify_disclosure<H: AlgebraicHasher<F>>(
+        &mut self,
+        commitment: HashOutTarget,
+        disclosure: &DisclosureTarget,
+    ) {
+        let index_bits = self.split_le(disclosure.index, disclosure.proof.siblings.len());
+        let leaf = [
+            vec![disclosure.value],
+            disclosure.blinding.elements.to_vec(),
+        ]
+        .concat();
+        let zero = self.zero();
+        self.verify_merkle_proof_with_cap_index::<H>(
+            leaf,
+            &index_bits,
+            zero,
+            &MerkleCapTarget(vec![commitment]),
+            &disclosure.proof,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use super::*;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    #[test]
+    fn test_disclosure() -> Result<()> {
+        let values = F::rand_vec(5);
+        let committed = CommittedValues::<F, H>::new(&values);
+        let commitment = committed.commitment();
+        for (i, &value) in values.iter().enumerate() {
+            let disclosure = committed.open(i);
+            assert_eq!(disclosure.value, value);
+            disclosure.verify(commitment)?;
+        }
+
+        let mut forged = committed.open(2);
+        forged.value += F::ONE;
+        assert!(forged.verify(commitment).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_committed_public_inputs() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let values = F::rand_vec(3);
+        let committed = CommittedValues::<F, H>::new(&values);
+
+        // A circuit exposing only a commitment to its values.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let mut pw = PartialWitness::new();
+        let value_targets = builder.add_virtual_targets(values.len());
+        for (&t, &v) in value_targets.iter().zip(&values) {
+            pw.set_target(t, v);
+        }
+        let target = builder.register_committed_public_inputs::<H>(&value_targets);
+        set_committed_public_inputs_target(&mut pw, &target, &committed);
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        let index = target.public_input_index;
+        let commitment = HashOut::from_partial(&proof.public_inputs[index..index + 4]);
+        assert_eq!(commitment, committed.commitment());
+        data.verify(proof)?;
+
+        // A follow-up circuit disclosing one of them.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        let commitment_target = builder.add_virtual_hash();
+        pw.set_hash_target(commitment_target, commitment);
+        let disclosure_target = builder.add_virtual_disclosure_target(values.len());
+        set_disclosure_target(&mut pw, &disclosure_target, &committed.open(1));
+        builder.verify_disclosure::<H>(commitment_target, &disclosure_target);
+        builder.register_public_input(disclosure_target.value);
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, vec![values[1]]);
+        data.verify(proof)
+    }
+}
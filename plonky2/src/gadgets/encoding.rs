@@ -0,0 +1,226 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// Marks characters outside of an alphabet in a decoding table. It is larger than any digit, so a
+/// range check on a decoded digit rejects invalid characters.
+const INVALID: u8 = 0xff;
+
+const HEX_DECODE_TABLE: [u8; 256] = {
+    let mut table = [INVALID; 256];
+    let mut i = 0;
+    while i < 10 {
+        table[b'0' as usize + i] = i as u8;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 6 {
+        table[b'a' as usize + i] = 10 + i as u8;
+        table[b'A' as usize + i] = 10 + i as u8;
+        i += 1;
+    }
+    table
+};
+
+const BASE64_STANDARD_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL_SAFE_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const fn base64_decode_table(alphabet: &[u8; 64]) -> [u8; 256] {
+    let mut table = [INVALID; 256];
+    let mut i = 0;
+    while i < 64 {
+        table[alphabet[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+const BASE64_STANDARD_DECODE_TABLE: [u8; 256] = base64_decode_table(BASE64_STANDARD_ALPHABET);
+const BASE64_URL_SAFE_DECODE_TABLE: [u8; 256] = base64_decode_table(BASE64_URL_SAFE_ALPHABET);
+
+/// The alphabet of a Base64 encoding, as defined in RFC 4648.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Base64Alphabet {
+    /// The standard alphabet, with `+` and `/`.
+    Standard,
+    /// The URL and filename safe alphabet, with `-` and `_`, as used by JWTs.
+    UrlSafe,
+}
+
+impl Base64Alphabet {
+    fn decode_table(&self) -> &'static [u8; 256] {
+        match self {
+            Base64Alphabet::Standard => &BASE64_STANDARD_DECODE_TABLE,
+            Base64Alphabet::UrlSafe => &BASE64_URL_SAFE_DECODE_TABLE,
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Decodes a string of ASCII hex digits, in either case, into bytes. Each pair of digits is one
+    /// byte, most significant digit first. Fails to prove unless every character is a hex digit.
+    pub fn decode_hex(&mut self, chars: &[Target]) -> Vec<Target> {
+        assert_eq!(chars.len() % 2, 0, "Hex strings must have an even length");
+        let base = F::from_canonical_u32(16);
+        chars
+            .chunks(2)
+            .map(|pair| {
+                let high = self.decode_digit(pair[0], &HEX_DECODE_TABLE, 4);
+                let low = self.decode_digit(pair[1], &HEX_DECODE_TABLE, 4);
+                let high = self.le_bits_to_target(&high);
+                let low = self.le_bits_to_target(&low);
+                self.mul_const_add(base, high, low)
+            })
+            .collect()
+    }
+
+    /// Decodes an unpadded Base64 string into bytes. Since lengths are fixed when building a
+    /// circuit, any `=` padding should be stripped by the caller. Fails to prove unless every
+    /// character is in the alphabet and, for a trailing partial group, the unused low bits of the
+    /// last character are zero, so that each byte string has exactly one accepted encoding.
+    pub fn decode_base64(&mut self, chars: &[Target], alphabet: Base64Alphabet) -> Vec<Target> {
+        assert_ne!(chars.len() % 4, 1, "Invalid length for unpadded Base64");
+        let table = alphabet.decode_table();
+
+        // The big-endian bits of the concatenated 6-bit digits.
+        let bits = chars
+            .iter()
+            .flat_map(|&c| {
+                let mut digit_bits = self.decode_digit(c, table, 6);
+                digit_bits.reverse();
+                digit_bits
+            })
+            .collect::<Vec<_>>();
+
+        let num_bytes = bits.len() / 8;
+        for &b in &bits[8 * num_bytes..] {
+            self.assert_zero(b.target);
+        }
+        bits[..8 * num_bytes]
+            .chunks(8)
+            .map(|byte| {
+                let le_bits = byte.iter().rev().copied().collect::<Vec<_>>();
+                self.le_bits_to_target(&le_bits)
+            })
+            .collect()
+    }
+
+    /// Looks up the digit value of an ASCII character in a decoding table, and returns its
+    /// `num_bits` little-endian bits. The character is range-checked to a byte, and the bit
+    /// decomposition rejects characters marked as `INVALID`.
+    fn decode_digit(&mut self, c: Target, table: &[u8; 256], num_bits: usize) -> Vec<BoolTarget> {
+        // Two-level lookup: the low nibble selects an entry from each of 16 rows of the table, then
+        // the high nibble selects a row.
+        let c_bits = self.split_byte_le(c);
+        let low = self.le_bits_to_target(&c_bits[..4]);
+        let high = self.le_bits_to_target(&c_bits[4..]);
+        let candidates = table
+            .chunks(16)
+            .map(|row| {
+                let row = row
+                    .iter()
+                    .map(|&d| self.constant(F::from_canonical_u16(d as u16)))
+                    .collect();
+                let claimed_element = self.add_virtual_target();
+                self.random_access(low, claimed_element, row);
+                claimed_element
+            })
+            .collect();
+        let digit = self.add_virtual_target();
+        self.random_access(high, digit, candidates);
+        self.split_le(digit, num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::gadgets::encoding::Base64Alphabet;
+    use crate::iop::target::Target;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn constant_bytes(builder: &mut CircuitBuilder<F, D>, bytes: &[u8]) -> Vec<Target> {
+        bytes
+            .iter()
+            .map(|&b| builder.constant(F::from_canonical_u16(b as u16)))
+            .collect()
+    }
+
+    fn test_decode(
+        encoded: &str,
+        expected: &[u8],
+        decode: fn(&mut CircuitBuilder<F, D>, &[Target]) -> Vec<Target>,
+    ) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let chars = constant_bytes(&mut builder, encoded.as_bytes());
+        let decoded = decode(&mut builder, &chars);
+        let expected = constant_bytes(&mut builder, expected);
+        assert_eq!(decoded.len(), expected.len());
+        for (d, e) in decoded.into_iter().zip(expected) {
+            builder.connect(d, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_decode_hex() -> Result<()> {
+        test_decode(
+            "00ff7Fa9DEadBEEF",
+            &[0x00, 0xff, 0x7f, 0xa9, 0xde, 0xad, 0xbe, 0xef],
+            |b, c| b.decode_hex(c),
+        )
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_decode_hex_invalid() {
+        test_decode("0g", &[0x00], |b, c| b.decode_hex(c)).unwrap()
+    }
+
+    #[test]
+    fn test_decode_base64() -> Result<()> {
+        // Test vectors from RFC 4648, section 10, with padding removed.
+        for (encoded, expected) in [
+            ("Zg", "f"),
+            ("Zm8", "fo"),
+            ("Zm9v", "foo"),
+            ("Zm9vYg", "foob"),
+            ("Zm9vYmE", "fooba"),
+            ("Zm9vYmFy", "foobar"),
+        ] {
+            test_decode(encoded, expected.as_bytes(), |b, c| {
+                b.decode_base64(c, Base64Alphabet::Standard)
+            })?;
+        }
+        test_decode("-_-_", &[0xfb, 0xff, 0xbf], |b, c| {
+            b.decode_base64(c, Base64Alphabet::UrlSafe)
+        })
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_decode_base64_wrong_alphabet() {
+        test_decode("+/+/", &[0xfb, 0xff, 0xbf], |b, c| {
+            b.decode_base64(c, Base64Alphabet::UrlSafe)
+        })
+        .unwrap()
+    }
+}
@@ -0,0 +1,224 @@
+//! Verification of Ethereum block headers. A header is the RLP encoding of a list of fields. Every
+//! header is longer than 255 bytes but shorter than `2^16` bytes, so the list prefix is always
+//! `0xf9` followed by a two-byte length, and the fields up to the logs bloom, which all have fixed
+//! sizes, are at fixed offsets. The scalar fields which follow them have variable lengths, so they
+//! are decoded at offsets only known at proving time.
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u64::U64Target;
+use crate::gadgets::rlp::RLP_WINDOW_BYTES;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The prefixes of the header and of its fixed-size fields, along with their offsets.
+const HEADER_PREFIXES: [(usize, u8); 10] = [
+    // The list prefix, for a list whose length takes two bytes.
+    (0, 0xf9),
+    // The parent hash, ommers hash, beneficiary, state root, transactions root and receipts root.
+    (3, 0xa0),
+    (36, 0xa0),
+    (69, 0x94),
+    (90, 0xa0),
+    (123, 0xa0),
+    (156, 0xa0),
+    // The logs bloom, a 256-byte string.
+    (189, 0xb9),
+    (190, 0x01),
+    (191, 0x00),
+];
+
+const HEADER_PARENT_HASH_OFFSET: usize = 4;
+const HEADER_STATE_ROOT_OFFSET: usize = 91;
+const HEADER_TRANSACTIONS_ROOT_OFFSET: usize = 124;
+const HEADER_RECEIPTS_ROOT_OFFSET: usize = 157;
+
+/// The offset of the difficulty, which is the first field after the logs bloom.
+const HEADER_SCALARS_OFFSET: usize = 448;
+
+/// Fields of a verified block header. Hashes are represented by their 32 bytes.
+#[derive(Clone, Debug)]
+pub struct BlockHeaderTarget {
+    pub parent_hash: [Target; 32],
+    pub state_root: [Target; 32],
+    pub transactions_root: [Target; 32],
+    pub receipts_root: [Target; 32],
+    pub number: U64Target,
+    pub gas_limit: U64Target,
+    pub gas_used: U64Target,
+    pub timestamp: U64Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Verifies that the first `len` bytes of `header` are an RLP-encoded block header whose
+    /// Keccak-256 hash is `block_hash`, and returns its fields. The length of `header` is the
+    /// maximum supported header length, and `len` must be at most that.
+    pub fn verify_block_header(
+        &mut self,
+        header: &[Target],
+        len: Target,
+        block_hash: &[Target; 32],
+    ) -> BlockHeaderTarget {
+        let window_len = RLP_WINDOW_BYTES - 8;
+        assert!(header.len() >= HEADER_SCALARS_OFFSET + window_len);
+
+        let hash = self.keccak256_variable(header, len);
+        for (&h, &b) in hash.iter().zip(block_hash) {
+            self.connect(h, b);
+        }
+
+        for (offset, prefix) in HEADER_PREFIXES {
+            let prefix = self.constant(F::from_canonical_u16(prefix as u16));
+            self.connect(header[offset], prefix);
+        }
+        // The list's payload is the rest of the header.
+        let base = F::from_canonical_u32(1 << 8);
+        let payload_len = self.mul_const_add(base, header[1], header[2]);
+        let list_len = self.add_const(payload_len, F::from_canonical_usize(3));
+        self.connect(list_len, len);
+
+        let window = &header[HEADER_SCALARS_OFFSET..HEADER_SCALARS_OFFSET + window_len];
+        let offset = self.zero();
+        let (_difficulty, offset) = self.rlp_decode_u64(window, offset);
+        let (number, offset) = self.rlp_decode_u64(window, offset);
+        let (gas_limit, offset) = self.rlp_decode_u64(window, offset);
+        let (gas_used, offset) = self.rlp_decode_u64(window, offset);
+        let (timestamp, _) = self.rlp_decode_u64(window, offset);
+
+        let hash_at =
+            |offset: usize| -> [Target; 32] { header[offset..offset + 32].try_into().unwrap() };
+        BlockHeaderTarget {
+            parent_hash: hash_at(HEADER_PARENT_HASH_OFFSET),
+            state_root: hash_at(HEADER_STATE_ROOT_OFFSET),
+            transactions_root: hash_at(HEADER_TRANSACTIONS_ROOT_OFFSET),
+            receipts_root: hash_at(HEADER_RECEIPTS_ROOT_OFFSET),
+            number,
+            gas_limit,
+            gas_used,
+            timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use keccak_hash::keccak;
+    use plonky2_field::field_types::Field;
+    use rand::{thread_rng, Rng};
+
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const MAX_HEADER_BYTES: usize = 640;
+
+    fn rlp_string(bytes: &[u8]) -> Vec<u8> {
+        let mut res = match bytes {
+            [b] if *b < 0x80 => vec![],
+            _ if bytes.len() < 56 => vec![0x80 + bytes.len() as u8],
+            _ if bytes.len() < 256 => vec![0xb8, bytes.len() as u8],
+            _ => {
+                let len = (bytes.len() as u16).to_be_bytes();
+                vec![0xb9, len[0], len[1]]
+            }
+        };
+        res.extend_from_slice(bytes);
+        res
+    }
+
+    fn rlp_u64(value: u64) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        rlp_string(&bytes[value.leading_zeros() as usize / 8..])
+    }
+
+    fn constant_bytes(builder: &mut CircuitBuilder<F, D>, bytes: &[u8]) -> Vec<Target> {
+        bytes
+            .iter()
+            .map(|&b| builder.constant(F::from_canonical_u16(b as u16)))
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_block_header() -> Result<()> {
+        let mut rng = thread_rng();
+        let mut random_bytes = |n: usize| (0..n).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+
+        let parent_hash = random_bytes(32);
+        let state_root = random_bytes(32);
+        let transactions_root = random_bytes(32);
+        let receipts_root = random_bytes(32);
+        let (number, gas_limit, gas_used, timestamp) = (17034870, 30000000, 12345678, 1681338455);
+
+        let fields = [
+            rlp_string(&parent_hash),
+            rlp_string(&random_bytes(32)),
+            rlp_string(&random_bytes(20)),
+            rlp_string(&state_root),
+            rlp_string(&transactions_root),
+            rlp_string(&receipts_root),
+            rlp_string(&random_bytes(256)),
+            rlp_u64(0),
+            rlp_u64(number),
+            rlp_u64(gas_limit),
+            rlp_u64(gas_used),
+            rlp_u64(timestamp),
+            rlp_string(b"plonky2"),
+            rlp_string(&random_bytes(32)),
+            rlp_string(&[0; 8]),
+            rlp_u64(31337),
+        ]
+        .concat();
+        let list_len = (fields.len() as u16).to_be_bytes();
+        let header = [vec![0xf9, list_len[0], list_len[1]], fields].concat();
+        let block_hash = keccak(&header);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let header_targets = builder.add_virtual_targets(MAX_HEADER_BYTES);
+        for (i, &t) in header_targets.iter().enumerate() {
+            let byte = header.get(i).copied().unwrap_or(0);
+            pw.set_target(t, F::from_canonical_u16(byte as u16));
+        }
+        let len = builder.add_virtual_target();
+        pw.set_target(len, F::from_canonical_usize(header.len()));
+        let block_hash = constant_bytes(&mut builder, block_hash.as_bytes());
+
+        let fields =
+            builder.verify_block_header(&header_targets, len, &block_hash.try_into().unwrap());
+        for (targets, expected) in [
+            (fields.parent_hash, parent_hash),
+            (fields.state_root, state_root),
+            (fields.transactions_root, transactions_root),
+            (fields.receipts_root, receipts_root),
+        ] {
+            let expected = constant_bytes(&mut builder, &expected);
+            for (t, e) in targets.into_iter().zip(expected) {
+                builder.connect(t, e);
+            }
+        }
+        for (target, expected) in [
+            (fields.number, number),
+            (fields.gas_limit, gas_limit),
+            (fields.gas_used, gas_used),
+            (fields.timestamp, timestamp),
+        ] {
+            let expected = builder.constant_u64(expected);
+            builder.connect_u64(target, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
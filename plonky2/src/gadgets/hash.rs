@@ -1,7 +1,8 @@
 use plonky2_field::extension_field::Extendable;
 
 use crate::hash::hash_types::RichField;
-use crate::hash::hashing::SPONGE_WIDTH;
+use crate::hash::hashing::{SPONGE_RATE, SPONGE_WIDTH};
+use crate::hash::poseidon::PoseidonHash;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::AlgebraicHasher;
@@ -25,4 +26,118 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) -> [Target; SPONGE_WIDTH] {
         H::permute_swapped(inputs, swap, self)
     }
+
+    /// Hashes `inputs` with the Poseidon sponge, absorbing and squeezing `rate` elements per
+    /// permutation, like `hash_n_to_m_no_pad_with_rate`. Each permutation is a `PoseidonGate`, whose
+    /// state is copied from the outputs of the previous one. The rate can be at most `SPONGE_RATE`,
+    /// which keeps a capacity of at least `SPONGE_WIDTH - SPONGE_RATE` elements. With a rate of
+    /// `SPONGE_RATE`, this is the same hash as `hash_n_to_m_no_pad::<PoseidonHash>`.
+    ///
+    /// There is no dedicated absorption gate. Gates can only constrain their own row, and a
+    /// `PoseidonGate` already takes a whole row of the standard config, so a permutation can't share
+    /// its row with the next one; the copy constraints carrying the state between rows add no gates.
+    pub fn poseidon_sponge_hash(
+        &mut self,
+        inputs: Vec<Target>,
+        num_outputs: usize,
+        rate: usize,
+    ) -> Vec<Target> {
+        assert!(
+            0 < rate && rate <= SPONGE_RATE,
+            "The rate must be between 1 and {}",
+            SPONGE_RATE
+        );
+        let zero = self.zero();
+
+        let mut state = [zero; SPONGE_WIDTH];
+
+        // Absorb all input chunks, in overwrite mode as in `hash_n_to_m_no_pad`.
+        for input_chunk in inputs.chunks(rate) {
+            state[..input_chunk.len()].copy_from_slice(input_chunk);
+            state = self.permute::<PoseidonHash>(state);
+        }
+
+        // Squeeze until we have the desired number of outputs.
+        let mut outputs = Vec::with_capacity(num_outputs);
+        loop {
+            for &item in state.iter().take(rate) {
+                outputs.push(item);
+                if outputs.len() == num_outputs {
+                    return outputs;
+                }
+            }
+            state = self.permute::<PoseidonHash>(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::hash::hashing::{hash_n_to_m_no_pad_with_rate, SPONGE_RATE};
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_poseidon_sponge_hash() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let values = F::rand_vec(30);
+        let inputs = builder.add_virtual_targets(values.len());
+        for (&t, &v) in inputs.iter().zip(&values) {
+            pw.set_target(t, v);
+        }
+
+        // Add the zero constant up front, so that its `ConstantGate` isn't counted below.
+        builder.zero();
+        for rate in [SPONGE_RATE, 5] {
+            let start = builder.num_gates();
+            let outputs = builder.poseidon_sponge_hash(inputs.clone(), 4, rate);
+            // One row per absorbed chunk, since four outputs fit in a single squeeze.
+            assert_eq!(
+                builder.num_gates() - start,
+                (values.len() + rate - 1) / rate
+            );
+
+            let expected = hash_n_to_m_no_pad_with_rate::<
+                F,
+                <PoseidonHash as Hasher<F>>::Permutation,
+            >(&values, 4, rate);
+            for (&output, &e) in outputs.iter().zip(&expected) {
+                let e = builder.constant(e);
+                builder.connect(output, e);
+            }
+        }
+
+        let expected = PoseidonHash::hash_no_pad(&values);
+        let outputs = builder.poseidon_sponge_hash(inputs, 4, SPONGE_RATE);
+        for (&output, &e) in outputs.iter().zip(&expected.elements) {
+            let e = builder.constant(e);
+            builder.connect(output, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "The rate must be between 1 and 8")]
+    fn test_poseidon_sponge_hash_rate_too_large() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let inputs = builder.add_virtual_targets(30);
+        builder.poseidon_sponge_hash(inputs, 4, SPONGE_RATE + 1);
+    }
 }
@@ -0,0 +1,415 @@
+//! In-circuit hashing to G2 of BLS12-381, following RFC 9380 as `curve::hash_to_curve` does
+//! natively.
+
+use std::marker::PhantomData;
+
+use plonky2_field::bls12_381_base::Bls12_381Base;
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+
+use crate::curve::bls12_381::Bls12_381;
+use crate::curve::hash_to_curve::{
+    sgn0, HASH_TO_FIELD_BYTES, ISO_X_DEN, ISO_X_NUM, ISO_Y_DEN, ISO_Y_NUM, SSWU_A, SSWU_B, SSWU_Z,
+};
+use crate::curve::pairing::Fp2;
+use crate::gadgets::biguint::BigUintTarget;
+use crate::gadgets::nonnative::NonNativeTarget;
+use crate::gadgets::pairing::{Fp2Target, G2AffinePointTarget};
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Expands a message of bytes, whose length is fixed at circuit build time, to `len` uniform
+    /// bytes with SHA-256, as in section 5.3.1 of RFC 9380.
+    pub fn expand_message_xmd(&mut self, msg: &[Target], dst: &[u8], len: usize) -> Vec<Target> {
+        assert!(
+            dst.len() <= 255,
+            "DSTs longer than 255 bytes must be hashed first"
+        );
+        let ell = (len + 31) / 32;
+        assert!(ell <= 255 && len <= u16::MAX as usize);
+        let mut dst_prime = dst.to_vec();
+        dst_prime.push(dst.len() as u8);
+        let dst_prime = self.constant_bytes(&dst_prime);
+
+        let mut msg_prime = self.constant_bytes(&[0; 64]);
+        msg_prime.extend_from_slice(msg);
+        let suffix = self.constant_bytes(&[(len >> 8) as u8, len as u8, 0]);
+        msg_prime.extend(suffix);
+        msg_prime.extend_from_slice(&dst_prime);
+        let b_0 = self.sha256(&msg_prime);
+
+        let mut b_i = [self.zero(); 32];
+        let mut uniform_bytes = Vec::with_capacity(ell * 32);
+        for i in 1..=ell {
+            let mut input = if i == 1 {
+                b_0.to_vec()
+            } else {
+                self.xor_bytes(&b_0, &b_i)
+            };
+            input.push(self.constant(F::from_canonical_usize(i)));
+            input.extend_from_slice(&dst_prime);
+            b_i = self.sha256(&input);
+            uniform_bytes.extend_from_slice(&b_i);
+        }
+        uniform_bytes.truncate(len);
+        uniform_bytes
+    }
+
+    fn constant_bytes(&mut self, bytes: &[u8]) -> Vec<Target> {
+        bytes
+            .iter()
+            .map(|&b| self.constant(F::from_canonical_u32(b as u32)))
+            .collect()
+    }
+
+    fn xor_bytes(&mut self, x: &[Target], y: &[Target]) -> Vec<Target> {
+        x.iter()
+            .zip(y)
+            .map(|(&a, &b)| {
+                let a_bits = self.split_byte_le(a);
+                let b_bits = self.split_byte_le(b);
+                let xor_bits = self.xor_bits(&a_bits, &b_bits);
+                self.le_bits_to_target(&xor_bits)
+            })
+            .collect()
+    }
+
+    /// Hashes a message to two elements of `Fp2`, as in section 5.2 of RFC 9380. The results are
+    /// in canonical form.
+    pub fn hash_to_field_bls12_381_g2(
+        &mut self,
+        msg: &[Target],
+        dst: &[u8],
+    ) -> [Fp2Target<Bls12_381Base>; 2] {
+        let uniform_bytes = self.expand_message_xmd(msg, dst, 4 * HASH_TO_FIELD_BYTES);
+        let elements = uniform_bytes
+            .chunks(HASH_TO_FIELD_BYTES)
+            .map(|chunk| {
+                // The chunk is a big-endian integer, so its last four bytes form the lowest limb.
+                let limbs = chunk
+                    .rchunks(4)
+                    .map(|word| self.be_bytes_to_u32(word))
+                    .collect();
+                let x = self.biguint_to_nonnative(&BigUintTarget { limbs });
+                let x = self.reduce_nonnative(&x);
+                self.assert_canonical(&x);
+                x
+            })
+            .collect::<Vec<_>>();
+        [0, 1].map(|i| Fp2Target {
+            c0: elements[2 * i].clone(),
+            c1: elements[2 * i + 1].clone(),
+        })
+    }
+
+    /// Asserts that `x` is less than the field order, so that its parity is well defined.
+    fn assert_canonical(&mut self, x: &NonNativeTarget<Bls12_381Base>) {
+        let max = self.constant_biguint(&(Bls12_381Base::order() - 1u32));
+        let is_canonical = self.cmp_biguint(&x.value, &max);
+        self.assert_one(is_canonical.target);
+    }
+
+    /// The sign of a canonical element of `Fp2`, as defined in section 4.1 of RFC 9380.
+    fn sgn0_fp2(&mut self, x: &Fp2Target<Bls12_381Base>) -> BoolTarget {
+        let sign_0 = self.split_le(x.c0.value.get_limb(0).0, 32)[0];
+        let zero = self.zero_biguint();
+        let zero_0 = self.cmp_biguint(&x.c0.value, &zero);
+        let sign_1 = self.split_le(x.c1.value.get_limb(0).0, 32)[0];
+        let zero_0_and_sign_1 = self.and(zero_0, sign_1);
+        self.or(sign_0, zero_0_and_sign_1)
+    }
+
+    fn select_fp2(
+        &mut self,
+        b: BoolTarget,
+        x: &Fp2Target<Bls12_381Base>,
+        y: &Fp2Target<Bls12_381Base>,
+    ) -> Fp2Target<Bls12_381Base> {
+        let not_b = self.not(b);
+        let mut select = |x: &NonNativeTarget<Bls12_381Base>,
+                          y: &NonNativeTarget<Bls12_381Base>| {
+            let b_x = self.mul_nonnative_by_bool(x, b);
+            let not_b_y = self.mul_nonnative_by_bool(y, not_b);
+            self.add_nonnative(&b_x, &not_b_y)
+        };
+        Fp2Target {
+            c0: select(&x.c0, &y.c0),
+            c1: select(&x.c1, &y.c1),
+        }
+    }
+
+    /// The simplified SWU map to `E'`, as in section 6.6.2 of RFC 9380. `u` must be canonical.
+    /// The exceptional case `Z^2 u^4 + Z u^2 = 0`, which is only reached by a negligible fraction
+    /// of inputs, makes the circuit unsatisfiable.
+    pub fn map_to_curve_sswu_bls12_381(
+        &mut self,
+        u: &Fp2Target<Bls12_381Base>,
+    ) -> (Fp2Target<Bls12_381Base>, Fp2Target<Bls12_381Base>) {
+        let u_squared = self.mul_fp2(u, u);
+        let z_u2 = self.mul_fp2_by_const(&u_squared, SSWU_Z);
+        let z_u2_squared = self.mul_fp2(&z_u2, &z_u2);
+        let tv1 = self.add_fp2(&z_u2_squared, &z_u2);
+        let tv1_inv = self.inv_fp2(&tv1);
+        let one = self.constant_fp2(Fp2::ONE);
+        let one_plus_tv1_inv = self.add_fp2(&one, &tv1_inv);
+        let x1 = self.mul_fp2_by_const(&one_plus_tv1_inv, -SSWU_B * SSWU_A.inverse());
+        let gx1 = self.sswu_rhs(&x1);
+        let x2 = self.mul_fp2(&z_u2, &x1);
+        let gx2 = self.sswu_rhs(&x2);
+
+        // Since `g(x2) = Z^3 u^6 g(x1)` and `Z` is not a square, exactly one of `g(x1)` and `g(x2)`
+        // is a square, so exhibiting a square root of the selected one proves the choice correct.
+        let gx1_is_square = self.add_virtual_bool_target_safe();
+        let y = self.add_virtual_fp2_target();
+        self.add_simple_generator(SswuSqrtGenerator::<F, D> {
+            u: u.clone(),
+            gx1: gx1.clone(),
+            gx2: gx2.clone(),
+            gx1_is_square,
+            y: y.clone(),
+            _phantom: PhantomData,
+        });
+        self.range_check_u32(y.c0.value.limbs.clone());
+        self.range_check_u32(y.c1.value.limbs.clone());
+        self.assert_canonical(&y.c0);
+        self.assert_canonical(&y.c1);
+
+        let x = self.select_fp2(gx1_is_square, &x1, &x2);
+        let gx = self.select_fp2(gx1_is_square, &gx1, &gx2);
+        let y_squared = self.mul_fp2(&y, &y);
+        self.connect_fp2(&y_squared, &gx);
+
+        let sgn0_u = self.sgn0_fp2(u);
+        let sgn0_y = self.sgn0_fp2(&y);
+        self.connect(sgn0_u.target, sgn0_y.target);
+        (x, y)
+    }
+
+    /// Evaluates the right hand side of the equation of `E'`, `x^3 + A' x + B'`.
+    fn sswu_rhs(&mut self, x: &Fp2Target<Bls12_381Base>) -> Fp2Target<Bls12_381Base> {
+        let x_squared = self.mul_fp2(x, x);
+        let x_squared_plus_a = self.add_fp2_const(&x_squared, SSWU_A);
+        let x_cubed_plus_a_x = self.mul_fp2(&x_squared_plus_a, x);
+        self.add_fp2_const(&x_cubed_plus_a_x, SSWU_B)
+    }
+
+    fn add_fp2_const(
+        &mut self,
+        x: &Fp2Target<Bls12_381Base>,
+        c: Fp2<Bls12_381Base>,
+    ) -> Fp2Target<Bls12_381Base> {
+        let c = self.constant_fp2(c);
+        self.add_fp2(x, &c)
+    }
+
+    /// Evaluates a polynomial with constant coefficients by Horner's method.
+    fn eval_fp2_poly(
+        &mut self,
+        coeffs: &[Fp2<Bls12_381Base>],
+        x: &Fp2Target<Bls12_381Base>,
+    ) -> Fp2Target<Bls12_381Base> {
+        let (&leading, rest) = coeffs.split_last().unwrap();
+        let mut acc = self.constant_fp2(leading);
+        for &coeff in rest.iter().rev() {
+            let acc_x = self.mul_fp2(&acc, x);
+            acc = self.add_fp2_const(&acc_x, coeff);
+        }
+        acc
+    }
+
+    /// Applies the 3-isogeny from `E'` to the twist. The kernel of the isogeny, which would map to
+    /// zero, makes the circuit unsatisfiable.
+    pub fn iso_map_bls12_381(
+        &mut self,
+        x: &Fp2Target<Bls12_381Base>,
+        y: &Fp2Target<Bls12_381Base>,
+    ) -> G2AffinePointTarget<Bls12_381> {
+        let x_num = self.eval_fp2_poly(&ISO_X_NUM, x);
+        let x_den = self.eval_fp2_poly(&ISO_X_DEN, x);
+        let y_num = self.eval_fp2_poly(&ISO_Y_NUM, x);
+        let y_den = self.eval_fp2_poly(&ISO_Y_DEN, x);
+
+        let x_den_inv = self.inv_fp2(&x_den);
+        let y_den_inv = self.inv_fp2(&y_den);
+        let y_y_num = self.mul_fp2(y, &y_num);
+        G2AffinePointTarget {
+            x: self.mul_fp2(&x_num, &x_den_inv),
+            y: self.mul_fp2(&y_y_num, &y_den_inv),
+        }
+    }
+
+    /// Maps a canonical element of `Fp2` to the twist, by the simplified SWU map followed by the
+    /// isogeny.
+    pub fn map_to_twist_bls12_381(
+        &mut self,
+        u: &Fp2Target<Bls12_381Base>,
+    ) -> G2AffinePointTarget<Bls12_381> {
+        let (x, y) = self.map_to_curve_sswu_bls12_381(u);
+        self.iso_map_bls12_381(&x, &y)
+    }
+
+    /// Multiplies a point of the twist by the effective cofactor, as in `clear_cofactor`.
+    pub fn clear_cofactor_bls12_381(
+        &mut self,
+        p: &G2AffinePointTarget<Bls12_381>,
+    ) -> G2AffinePointTarget<Bls12_381> {
+        let t1 = self.g2_mul_by_x_bls12_381(p);
+        let t2 = self.g2_frobenius(p, 1);
+        let double_p = self.g2_double(p);
+        let t3 = self.g2_frobenius(&double_p, 2);
+        let t3 = self.g2_sub(&t3, &t2);
+        let t1_plus_t2 = self.g2_add(&t1, &t2);
+        let t2 = self.g2_mul_by_x_bls12_381(&t1_plus_t2);
+        let t3 = self.g2_add(&t3, &t2);
+        let t3 = self.g2_sub(&t3, &t1);
+        self.g2_sub(&t3, p)
+    }
+
+    fn g2_sub(
+        &mut self,
+        p: &G2AffinePointTarget<Bls12_381>,
+        q: &G2AffinePointTarget<Bls12_381>,
+    ) -> G2AffinePointTarget<Bls12_381> {
+        let neg_q = self.g2_neg(q);
+        self.g2_add(p, &neg_q)
+    }
+
+    /// Hashes a message of bytes, whose length is fixed at circuit build time, to G2 as in section 3
+    /// of RFC 9380. We use incomplete addition formulas, so messages for which an intermediate
+    /// point is zero or two summands coincide make the circuit unsatisfiable; finding one is as
+    /// hard as finding a preimage of the hash to the curve.
+    pub fn hash_to_g2_bls12_381(
+        &mut self,
+        msg: &[Target],
+        dst: &[u8],
+    ) -> G2AffinePointTarget<Bls12_381> {
+        let [u0, u1] = self.hash_to_field_bls12_381_g2(msg, dst);
+        let q0 = self.map_to_twist_bls12_381(&u0);
+        let q1 = self.map_to_twist_bls12_381(&u1);
+        let q = self.g2_add(&q0, &q1);
+        self.clear_cofactor_bls12_381(&q)
+    }
+}
+
+#[derive(Debug)]
+struct SswuSqrtGenerator<F: RichField + Extendable<D>, const D: usize> {
+    u: Fp2Target<Bls12_381Base>,
+    gx1: Fp2Target<Bls12_381Base>,
+    gx2: Fp2Target<Bls12_381Base>,
+    gx1_is_square: BoolTarget,
+    y: Fp2Target<Bls12_381Base>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F> for SswuSqrtGenerator<F, D> {
+    fn dependencies(&self) -> Vec<Target> {
+        [&self.u, &self.gx1, &self.gx2]
+            .iter()
+            .flat_map(|x| x.c0.value.limbs.iter().chain(&x.c1.value.limbs))
+            .map(|&l| l.0)
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let get_fp2 = |x: &Fp2Target<Bls12_381Base>| {
+            Fp2([
+                witness.get_nonnative_target(x.c0.clone()),
+                witness.get_nonnative_target(x.c1.clone()),
+            ])
+        };
+        let u = get_fp2(&self.u);
+        let gx1 = get_fp2(&self.gx1);
+        let gx1_is_square = gx1.is_square();
+        let gx = if gx1_is_square {
+            gx1
+        } else {
+            get_fp2(&self.gx2)
+        };
+        let y = gx
+            .sqrt()
+            .expect("Exactly one of g(x1) and g(x2) is a square");
+        let y = if sgn0(u) == sgn0(y) { y } else { -y };
+
+        out_buffer.set_bool_target(self.gx1_is_square, gx1_is_square);
+        out_buffer.set_nonnative_target(self.y.c0.clone(), y.0[0]);
+        out_buffer.set_nonnative_target(self.y.c1.clone(), y.0[1]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use num::BigUint;
+    use plonky2_field::bls12_381_base::Bls12_381Base;
+    use plonky2_field::field_types::{Field, PrimeField};
+
+    use crate::curve::hash_to_curve::{hash_to_g2, map_to_twist};
+    use crate::curve::pairing::Fp2;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const DST: &[u8] = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+
+    fn fp2_from_hex(c0: &str, c1: &str) -> Fp2<Bls12_381Base> {
+        let parse =
+            |s: &str| Bls12_381Base::from_biguint(BigUint::parse_bytes(s.as_bytes(), 16).unwrap());
+        Fp2([parse(c0), parse(c1)])
+    }
+
+    #[test]
+    fn test_map_to_twist() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // The first field element which the message "" hashes to, from appendix J.10.1 of RFC 9380.
+        let u = fp2_from_hex(
+            "03dbc2cce174e91ba93cbb08f26b917f98194a2ea08d1cce75b2b9cc9f21689d80bd79b594a613d0a68eb807dfdc1cf8",
+            "05a2acec64114845711a54199ea339abd125ba38253b70a92c876df10598bd1986b739cad67961eb94f7076511b3b39a",
+        );
+        let u_target = builder.add_virtual_fp2_target();
+        pw.set_biguint_target(&u_target.c0.value, &u.0[0].to_canonical_biguint());
+        pw.set_biguint_target(&u_target.c1.value, &u.0[1].to_canonical_biguint());
+
+        let p = builder.map_to_twist_bls12_381(&u_target);
+        let expected = map_to_twist(u);
+        let expected = builder.constant_g2_point(expected.x, expected.y);
+        builder.connect_g2_point(&p, &expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[ignore]
+    fn test_hash_to_g2() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let msg = b"abc";
+        let msg_targets = msg
+            .iter()
+            .map(|&b| builder.constant(F::from_canonical_u32(b as u32)))
+            .collect::<Vec<_>>();
+        let p = builder.hash_to_g2_bls12_381(&msg_targets, DST);
+        let expected = hash_to_g2(msg, DST);
+        let expected = builder.constant_g2_point(expected.x, expected.y);
+        builder.connect_g2_point(&p, &expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,306 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u64::U64Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The round constants of Keccak-f[1600].
+const KECCAK_ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// The rotation offsets of the rho step, indexed by `[x][y]`.
+const KECCAK_ROTATIONS: [[usize; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// The number of bytes absorbed per permutation by Keccak-256.
+pub const KECCAK256_RATE_BYTES: usize = 136;
+
+/// A 64-bit lane of the Keccak state, represented by its little-endian bits.
+type LaneBits = Vec<BoolTarget>;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the Keccak-256 digest of a message of bytes, whose length is fixed at circuit
+    /// build time. This is the original Keccak padding used by Ethereum, not SHA3-256. Message
+    /// bytes are range-checked.
+    pub fn keccak256(&mut self, message: &[Target]) -> [Target; 32] {
+        let mut padded = message.to_vec();
+        padded.push(self.one());
+        while padded.len() % KECCAK256_RATE_BYTES != 0 {
+            padded.push(self.zero());
+        }
+        let last = padded.len() - 1;
+        padded[last] = self.add_const(padded[last], F::from_canonical_u32(0x80));
+
+        let states = self.keccak256_absorb(&padded);
+        self.keccak256_squeeze(states.last().unwrap())
+    }
+
+    /// Computes the Keccak-256 digest of the first `len` bytes of `message`, where `len` is only
+    /// known at proving time. Bytes past `len` are ignored, and `len` must be at most
+    /// `message.len()`. The cost is that of hashing `message.len() + 1` bytes.
+    pub fn keccak256_variable(&mut self, message: &[Target], len: Target) -> [Target; 32] {
+        let num_blocks = message.len() / KECCAK256_RATE_BYTES + 1;
+        let padded_len = num_blocks * KECCAK256_RATE_BYTES;
+        let is_end = self.one_hot(len, padded_len);
+
+        // `is_final_block[k]` is set if the padding starts in block `k`, which is then the last
+        // block to be absorbed.
+        let is_final_block = is_end
+            .chunks(KECCAK256_RATE_BYTES)
+            .map(|block| {
+                let bits = block.iter().map(|b| b.target).collect::<Vec<_>>();
+                BoolTarget::new_unsafe(self.add_many(&bits))
+            })
+            .collect::<Vec<_>>();
+
+        let zero = self.zero();
+        let high_bit = F::from_canonical_u32(0x80);
+        let mut before_end = self.one();
+        let padded = (0..padded_len)
+            .map(|i| {
+                before_end = self.sub(before_end, is_end[i].target);
+                let byte = message.get(i).copied().unwrap_or(zero);
+                // The message byte if `i < len`, plus the first padding byte `0x01` if `i == len`.
+                let padded_byte = self.mul_add(byte, before_end, is_end[i].target);
+                if i % KECCAK256_RATE_BYTES == KECCAK256_RATE_BYTES - 1 {
+                    let k = i / KECCAK256_RATE_BYTES;
+                    self.mul_const_add(high_bit, is_final_block[k].target, padded_byte)
+                } else {
+                    padded_byte
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let states = self.keccak256_absorb(&padded);
+        let mut final_state = vec![vec![self._false(); 64]; 4];
+        for (state, &is_final) in states.iter().zip(&is_final_block) {
+            for (final_lane, lane) in final_state.iter_mut().zip(state) {
+                for (final_bit, &bit) in final_lane.iter_mut().zip(lane) {
+                    let selected = self.mul_add(is_final.target, bit.target, final_bit.target);
+                    *final_bit = BoolTarget::new_unsafe(selected);
+                }
+            }
+        }
+        self.keccak256_squeeze(&final_state)
+    }
+
+    /// Applies the Keccak-f[1600] permutation to a state of 25 lanes, where lane `x + 5y` is the
+    /// lane at position `(x, y)`.
+    pub fn keccak_f(&mut self, state: &[U64Target; 25]) -> [U64Target; 25] {
+        let state = state
+            .iter()
+            .map(|&x| self.split_u64_le(x))
+            .collect::<Vec<_>>();
+        let new_state = self.keccak_f_bits(state);
+
+        let mut res = [self.zero_u64(); 25];
+        for (r, lane) in res.iter_mut().zip(new_state) {
+            *r = self.le_bits_to_u64(&lane);
+        }
+        res
+    }
+
+    /// Absorbs padded blocks of bytes into the sponge, returning the state after each block.
+    fn keccak256_absorb(&mut self, padded: &[Target]) -> Vec<Vec<LaneBits>> {
+        debug_assert_eq!(padded.len() % KECCAK256_RATE_BYTES, 0);
+        let mut state = vec![vec![self._false(); 64]; 25];
+        padded
+            .chunks(KECCAK256_RATE_BYTES)
+            .map(|block| {
+                for (lane, word) in state.iter_mut().zip(block.chunks(8)) {
+                    let word = word
+                        .iter()
+                        .flat_map(|&b| self.split_byte_le(b))
+                        .collect::<Vec<_>>();
+                    *lane = self.xor_bits(lane, &word);
+                }
+                state = self.keccak_f_bits(state.clone());
+                state.clone()
+            })
+            .collect()
+    }
+
+    /// Returns the 32-byte digest, which is the first four lanes of the state in little-endian
+    /// byte order.
+    fn keccak256_squeeze(&mut self, state: &[LaneBits]) -> [Target; 32] {
+        let digest = state[..4]
+            .iter()
+            .flat_map(|lane| lane.chunks(8))
+            .map(|byte| self.le_bits_to_target(byte))
+            .collect::<Vec<_>>();
+        digest.try_into().unwrap()
+    }
+
+    fn keccak_f_bits(&mut self, mut state: Vec<LaneBits>) -> Vec<LaneBits> {
+        debug_assert_eq!(state.len(), 25);
+
+        for round_constant in KECCAK_ROUND_CONSTANTS {
+            // Theta.
+            let parities = (0..5)
+                .map(|x| {
+                    let mut parity = state[x].clone();
+                    for y in 1..5 {
+                        parity = self.xor_bits(&parity, &state[x + 5 * y]);
+                    }
+                    parity
+                })
+                .collect::<Vec<_>>();
+            for x in 0..5 {
+                let d = self.xor_bits(&parities[(x + 4) % 5], &rotl(&parities[(x + 1) % 5], 1));
+                for y in 0..5 {
+                    state[x + 5 * y] = self.xor_bits(&state[x + 5 * y], &d);
+                }
+            }
+
+            // Rho and pi.
+            let mut permuted = vec![vec![]; 25];
+            for x in 0..5 {
+                for y in 0..5 {
+                    permuted[y + 5 * ((2 * x + 3 * y) % 5)] =
+                        rotl(&state[x + 5 * y], KECCAK_ROTATIONS[x][y]);
+                }
+            }
+
+            // Chi.
+            for x in 0..5 {
+                for y in 0..5 {
+                    state[x + 5 * y] = self.keccak_chi(
+                        &permuted[x + 5 * y],
+                        &permuted[(x + 1) % 5 + 5 * y],
+                        &permuted[(x + 2) % 5 + 5 * y],
+                    );
+                }
+            }
+
+            // Iota.
+            for i in 0..64 {
+                if (round_constant >> i) & 1 == 1 {
+                    state[0][i] = self.not(state[0][i]);
+                }
+            }
+        }
+
+        state
+    }
+
+    /// Computes `a XOR (NOT b AND c)`.
+    fn keccak_chi(&mut self, a: &[BoolTarget], b: &[BoolTarget], c: &[BoolTarget]) -> LaneBits {
+        (0..64)
+            .map(|i| {
+                // `NOT b AND c = c - bc`.
+                let not_b_and_c =
+                    self.arithmetic(F::NEG_ONE, F::ONE, b[i].target, c[i].target, c[i].target);
+                self.xor(a[i], BoolTarget::new_unsafe(not_b_and_c))
+            })
+            .collect()
+    }
+}
+
+/// Rotates a little-endian lane left by `n` bits.
+fn rotl(x: &[BoolTarget], n: usize) -> LaneBits {
+    let mut res = x.to_vec();
+    res.rotate_right(n);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use keccak_hash::keccak;
+    use plonky2_field::field_types::Field;
+    use rand::{thread_rng, Rng};
+
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn connect_digest(builder: &mut CircuitBuilder<F, D>, digest: &[Target], expected: &[u8]) {
+        for (&d, &e) in digest.iter().zip(expected) {
+            let e = builder.constant(F::from_canonical_u16(e as u16));
+            builder.connect(d, e);
+        }
+    }
+
+    #[test]
+    fn test_keccak256() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        for message in [&b""[..], &b"abc"[..], &[0x42; 136][..]] {
+            let message_targets = builder.add_virtual_targets(message.len());
+            for (&t, &b) in message_targets.iter().zip(message) {
+                pw.set_target(t, F::from_canonical_u16(b as u16));
+            }
+            let digest = builder.keccak256(&message_targets);
+            connect_digest(&mut builder, &digest, keccak(message).as_bytes());
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_keccak256_variable() -> Result<()> {
+        const MAX_LEN: usize = 140;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        let message = (0..MAX_LEN).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+        let message_targets = builder.add_virtual_targets(MAX_LEN);
+        for (&t, &b) in message_targets.iter().zip(&message) {
+            pw.set_target(t, F::from_canonical_u16(b as u16));
+        }
+
+        for len in [3, 135, 136] {
+            let len_target = builder.constant(F::from_canonical_usize(len));
+            let digest = builder.keccak256_variable(&message_targets, len_target);
+            connect_digest(&mut builder, &digest, keccak(&message[..len]).as_bytes());
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
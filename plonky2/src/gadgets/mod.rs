@@ -1,16 +1,41 @@
+pub mod aes;
 pub mod arithmetic;
 pub mod arithmetic_extension;
 pub mod arithmetic_u32;
+pub mod arithmetic_u64;
 pub mod biguint;
+pub mod bits;
+pub mod bls12_381;
+pub mod bn254;
+pub mod byte_tables;
+pub mod chacha;
+pub mod committed_table;
 pub mod curve;
+pub mod data_availability;
+pub mod disclosure;
 pub mod ecdsa;
+pub mod encoding;
+pub mod eth_header;
 pub mod hash;
+pub mod hash_to_curve;
 pub mod interpolation;
+pub mod keccak;
 pub mod multiple_comparison;
 pub mod nonnative;
+pub mod pairing;
 pub mod polynomial;
+pub mod privacy;
 pub mod random_access;
+pub mod regex;
 pub mod range_check;
+pub mod rlp;
+pub mod rollup;
 pub mod select;
+pub mod sha256;
+pub(crate) mod sha2_util;
+pub mod sha512;
+pub mod shift;
 pub mod split_base;
 pub(crate) mod split_join;
+pub mod tip5;
+pub mod vm;
@@ -0,0 +1,649 @@
+use std::marker::PhantomData;
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::{Field, PrimeField};
+
+use crate::curve::pairing::{g2_frobenius_coeffs, Fp12, Fp2, PairingCurve, TwistType};
+use crate::gadgets::curve::AffinePointTarget;
+use crate::gadgets::nonnative::NonNativeTarget;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A target representing an element `c0 + c1 u` of `Fp2 = Fp[u] / (u^2 + 1)`.
+#[derive(Clone, Debug)]
+pub struct Fp2Target<FF: Field> {
+    pub c0: NonNativeTarget<FF>,
+    pub c1: NonNativeTarget<FF>,
+}
+
+/// A target representing an element of `Fp12 = Fp2[w] / (w^6 - xi)`, by its six `Fp2`
+/// coefficients.
+#[derive(Clone, Debug)]
+pub struct Fp12Target<C: PairingCurve>(pub [Fp2Target<C::BaseField>; 6]);
+
+/// A target representing an affine point on the sextic twist of `C`, i.e. a point of G2. As with
+/// `AffinePointTarget`, we use incomplete arithmetic, so we assume these points are not zero.
+#[derive(Clone, Debug)]
+pub struct G2AffinePointTarget<C: PairingCurve> {
+    pub x: Fp2Target<C::BaseField>,
+    pub y: Fp2Target<C::BaseField>,
+}
+
+/// The evaluation of a line function at a G1 point, as a sparse element of `Fp12`. Only the
+/// coefficients which are `Some` may be nonzero.
+pub type LineTarget<FF> = [Option<Fp2Target<FF>>; 6];
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn constant_fp2<FF: PrimeField>(&mut self, x: Fp2<FF>) -> Fp2Target<FF> {
+        Fp2Target {
+            c0: self.constant_nonnative(x.0[0]),
+            c1: self.constant_nonnative(x.0[1]),
+        }
+    }
+
+    pub fn zero_fp2<FF: PrimeField>(&mut self) -> Fp2Target<FF> {
+        self.constant_fp2(Fp2::ZERO)
+    }
+
+    pub fn add_virtual_fp2_target<FF: Field>(&mut self) -> Fp2Target<FF> {
+        Fp2Target {
+            c0: self.add_virtual_nonnative_target(),
+            c1: self.add_virtual_nonnative_target(),
+        }
+    }
+
+    pub fn connect_fp2<FF: Field>(&mut self, lhs: &Fp2Target<FF>, rhs: &Fp2Target<FF>) {
+        self.connect_nonnative(&lhs.c0, &rhs.c0);
+        self.connect_nonnative(&lhs.c1, &rhs.c1);
+    }
+
+    pub fn add_fp2<FF: PrimeField>(
+        &mut self,
+        a: &Fp2Target<FF>,
+        b: &Fp2Target<FF>,
+    ) -> Fp2Target<FF> {
+        Fp2Target {
+            c0: self.add_nonnative(&a.c0, &b.c0),
+            c1: self.add_nonnative(&a.c1, &b.c1),
+        }
+    }
+
+    pub fn add_many_fp2<FF: PrimeField>(&mut self, to_add: &[Fp2Target<FF>]) -> Fp2Target<FF> {
+        let c0s = to_add.iter().map(|x| x.c0.clone()).collect::<Vec<_>>();
+        let c1s = to_add.iter().map(|x| x.c1.clone()).collect::<Vec<_>>();
+        Fp2Target {
+            c0: self.add_many_nonnative(&c0s),
+            c1: self.add_many_nonnative(&c1s),
+        }
+    }
+
+    pub fn sub_fp2<FF: PrimeField>(
+        &mut self,
+        a: &Fp2Target<FF>,
+        b: &Fp2Target<FF>,
+    ) -> Fp2Target<FF> {
+        Fp2Target {
+            c0: self.sub_nonnative(&a.c0, &b.c0),
+            c1: self.sub_nonnative(&a.c1, &b.c1),
+        }
+    }
+
+    pub fn neg_fp2<FF: PrimeField>(&mut self, x: &Fp2Target<FF>) -> Fp2Target<FF> {
+        Fp2Target {
+            c0: self.neg_nonnative(&x.c0),
+            c1: self.neg_nonnative(&x.c1),
+        }
+    }
+
+    pub fn conj_fp2<FF: PrimeField>(&mut self, x: &Fp2Target<FF>) -> Fp2Target<FF> {
+        Fp2Target {
+            c0: x.c0.clone(),
+            c1: self.neg_nonnative(&x.c1),
+        }
+    }
+
+    /// Multiplies two `Fp2` elements, using Karatsuba's trick to save one base field multiplication.
+    pub fn mul_fp2<FF: PrimeField>(
+        &mut self,
+        a: &Fp2Target<FF>,
+        b: &Fp2Target<FF>,
+    ) -> Fp2Target<FF> {
+        let a0_b0 = self.mul_nonnative(&a.c0, &b.c0);
+        let a1_b1 = self.mul_nonnative(&a.c1, &b.c1);
+        let a_sum = self.add_nonnative(&a.c0, &a.c1);
+        let b_sum = self.add_nonnative(&b.c0, &b.c1);
+        let sums_product = self.mul_nonnative(&a_sum, &b_sum);
+        let cross_terms = self.add_nonnative(&a0_b0, &a1_b1);
+
+        Fp2Target {
+            c0: self.sub_nonnative(&a0_b0, &a1_b1),
+            c1: self.sub_nonnative(&sums_product, &cross_terms),
+        }
+    }
+
+    /// Multiplies an `Fp2` element by an element of the base field.
+    pub fn mul_fp2_by_base<FF: PrimeField>(
+        &mut self,
+        a: &Fp2Target<FF>,
+        b: &NonNativeTarget<FF>,
+    ) -> Fp2Target<FF> {
+        Fp2Target {
+            c0: self.mul_nonnative(&a.c0, b),
+            c1: self.mul_nonnative(&a.c1, b),
+        }
+    }
+
+    /// Multiplies an `Fp2` element by a constant. Multiplications by zero and one are skipped,
+    /// which makes multiplying by small non-residues such as `1 + u` cheap.
+    pub fn mul_fp2_by_const<FF: PrimeField>(
+        &mut self,
+        a: &Fp2Target<FF>,
+        c: Fp2<FF>,
+    ) -> Fp2Target<FF> {
+        let [k0, k1] = c.0;
+        let k0_a0 = self.mul_nonnative_by_const(&a.c0, k0);
+        let k0_a1 = self.mul_nonnative_by_const(&a.c1, k0);
+        let k1_a0 = self.mul_nonnative_by_const(&a.c0, k1);
+        let k1_a1 = self.mul_nonnative_by_const(&a.c1, k1);
+
+        let c0 = match (k0_a0, k1_a1) {
+            (Some(x), Some(y)) => self.sub_nonnative(&x, &y),
+            (Some(x), None) => x,
+            (None, Some(y)) => self.neg_nonnative(&y),
+            (None, None) => self.zero_nonnative(),
+        };
+        let c1 = match (k0_a1, k1_a0) {
+            (Some(x), Some(y)) => self.add_nonnative(&x, &y),
+            (Some(x), None) | (None, Some(x)) => x,
+            (None, None) => self.zero_nonnative(),
+        };
+        Fp2Target { c0, c1 }
+    }
+
+    /// Returns `k x`, or `None` if `k` is zero.
+    fn mul_nonnative_by_const<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+        k: FF,
+    ) -> Option<NonNativeTarget<FF>> {
+        if k == FF::ZERO {
+            None
+        } else if k == FF::ONE {
+            Some(x.clone())
+        } else {
+            let k = self.constant_nonnative(k);
+            Some(self.mul_nonnative(x, &k))
+        }
+    }
+
+    /// Inverts an `Fp2` element, using `(c0 + c1 u)^-1 = (c0 - c1 u) / (c0^2 + c1^2)`.
+    pub fn inv_fp2<FF: PrimeField>(&mut self, x: &Fp2Target<FF>) -> Fp2Target<FF> {
+        let c0_squared = self.mul_nonnative(&x.c0, &x.c0);
+        let c1_squared = self.mul_nonnative(&x.c1, &x.c1);
+        let norm = self.add_nonnative(&c0_squared, &c1_squared);
+        let norm_inv = self.inv_nonnative(&norm);
+        let conj = self.conj_fp2(x);
+        self.mul_fp2_by_base(&conj, &norm_inv)
+    }
+
+    pub fn constant_fp12<C: PairingCurve>(&mut self, x: Fp12<C>) -> Fp12Target<C> {
+        Fp12Target(x.0.map(|c| self.constant_fp2(c)))
+    }
+
+    pub fn one_fp12<C: PairingCurve>(&mut self) -> Fp12Target<C> {
+        self.constant_fp12(Fp12::ONE)
+    }
+
+    pub fn add_virtual_fp12_target<C: PairingCurve>(&mut self) -> Fp12Target<C> {
+        Fp12Target([(); 6].map(|_| self.add_virtual_fp2_target()))
+    }
+
+    pub fn connect_fp12<C: PairingCurve>(&mut self, lhs: &Fp12Target<C>, rhs: &Fp12Target<C>) {
+        for (l, r) in lhs.0.iter().zip(&rhs.0) {
+            self.connect_fp2(l, r);
+        }
+    }
+
+    pub fn mul_fp12<C: PairingCurve>(
+        &mut self,
+        a: &Fp12Target<C>,
+        b: &Fp12Target<C>,
+    ) -> Fp12Target<C> {
+        let b = b.0.clone().map(Some);
+        self.mul_fp12_sparse(a, &b)
+    }
+
+    /// Squares an `Fp12` element. This computes each cross term `a_i a_j` once, so it uses 21
+    /// `Fp2` multiplications rather than 36.
+    pub fn square_fp12<C: PairingCurve>(&mut self, a: &Fp12Target<C>) -> Fp12Target<C> {
+        let mut products = vec![Vec::new(); 11];
+        for i in 0..6 {
+            products[2 * i].push(self.mul_fp2(&a.0[i], &a.0[i]));
+            for j in i + 1..6 {
+                let a_i_a_j = self.mul_fp2(&a.0[i], &a.0[j]);
+                products[i + j].push(a_i_a_j.clone());
+                products[i + j].push(a_i_a_j);
+            }
+        }
+        self.reduce_fp12_products(products)
+    }
+
+    /// Multiplies an `Fp12` element by the evaluation of a line function.
+    pub fn mul_fp12_by_line<C: PairingCurve>(
+        &mut self,
+        a: &Fp12Target<C>,
+        line: &LineTarget<C::BaseField>,
+    ) -> Fp12Target<C> {
+        self.mul_fp12_sparse(a, line)
+    }
+
+    fn mul_fp12_sparse<C: PairingCurve>(
+        &mut self,
+        a: &Fp12Target<C>,
+        b: &[Option<Fp2Target<C::BaseField>>; 6],
+    ) -> Fp12Target<C> {
+        let mut products = vec![Vec::new(); 11];
+        for i in 0..6 {
+            for j in 0..6 {
+                if let Some(b_j) = &b[j] {
+                    products[i + j].push(self.mul_fp2(&a.0[i], b_j));
+                }
+            }
+        }
+        self.reduce_fp12_products(products)
+    }
+
+    /// Given the products contributing to each power `w^k` for `k < 11`, sums them and reduces
+    /// using `w^6 = xi`.
+    fn reduce_fp12_products<C: PairingCurve>(
+        &mut self,
+        products: Vec<Vec<Fp2Target<C::BaseField>>>,
+    ) -> Fp12Target<C> {
+        debug_assert_eq!(products.len(), 11);
+        let zero = self.zero_fp2();
+        let sums = products
+            .iter()
+            .map(|p| {
+                if p.is_empty() {
+                    zero.clone()
+                } else {
+                    self.add_many_fp2(p)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let coeffs = (0..6)
+            .map(|i| {
+                if i + 6 < 11 && !products[i + 6].is_empty() {
+                    let high = self.mul_fp2_by_const(&sums[i + 6], C::XI);
+                    self.add_fp2(&sums[i], &high)
+                } else {
+                    sums[i].clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        Fp12Target(coeffs.try_into().unwrap())
+    }
+
+    /// Computes the conjugate `f^(p^6)`.
+    pub fn conj_fp12<C: PairingCurve>(&mut self, f: &Fp12Target<C>) -> Fp12Target<C> {
+        let mut result = f.clone();
+        for i in (1..6).step_by(2) {
+            result.0[i] = self.neg_fp2(&f.0[i]);
+        }
+        result
+    }
+
+    /// Computes `f^(p^k)`.
+    pub fn frobenius_fp12<C: PairingCurve>(
+        &mut self,
+        f: &Fp12Target<C>,
+        k: usize,
+    ) -> Fp12Target<C> {
+        let coeffs = Fp12::<C>::frobenius_coeffs(k);
+        let mut result = f.clone();
+        for i in 0..6 {
+            let a = if k % 2 == 1 {
+                self.conj_fp2(&f.0[i])
+            } else {
+                f.0[i].clone()
+            };
+            result.0[i] = self.mul_fp2_by_const(&a, coeffs[i]);
+        }
+        result
+    }
+
+    pub fn inv_fp12<C: PairingCurve>(&mut self, f: &Fp12Target<C>) -> Fp12Target<C> {
+        let inv = self.add_virtual_fp12_target::<C>();
+        self.add_simple_generator(Fp12InverseGenerator::<F, D, C> {
+            f: f.clone(),
+            inv: inv.clone(),
+            _phantom: PhantomData,
+        });
+
+        let product = self.mul_fp12(f, &inv);
+        let one = self.one_fp12();
+        self.connect_fp12(&product, &one);
+
+        inv
+    }
+
+    /// Computes `f^e` for a constant exponent `e`, by square-and-multiply.
+    pub fn exp_fp12_u64<C: PairingCurve>(&mut self, f: &Fp12Target<C>, e: u64) -> Fp12Target<C> {
+        if e == 0 {
+            return self.one_fp12();
+        }
+
+        let num_bits = 64 - e.leading_zeros() as usize;
+        let mut result = f.clone();
+        for i in (0..num_bits - 1).rev() {
+            result = self.square_fp12(&result);
+            if (e >> i) & 1 == 1 {
+                result = self.mul_fp12(&result, f);
+            }
+        }
+        result
+    }
+
+    pub fn constant_g2_point<C: PairingCurve>(
+        &mut self,
+        x: Fp2<C::BaseField>,
+        y: Fp2<C::BaseField>,
+    ) -> G2AffinePointTarget<C> {
+        G2AffinePointTarget {
+            x: self.constant_fp2(x),
+            y: self.constant_fp2(y),
+        }
+    }
+
+    pub fn add_virtual_g2_point_target<C: PairingCurve>(&mut self) -> G2AffinePointTarget<C> {
+        G2AffinePointTarget {
+            x: self.add_virtual_fp2_target(),
+            y: self.add_virtual_fp2_target(),
+        }
+    }
+
+    /// Asserts that a point lies on the twist of `C`. This does not check that the point is in
+    /// the prime-order subgroup.
+    pub fn g2_assert_valid<C: PairingCurve>(&mut self, q: &G2AffinePointTarget<C>) {
+        let twist_b = self.constant_fp2(C::twist_b());
+
+        let y_squared = self.mul_fp2(&q.y, &q.y);
+        let x_squared = self.mul_fp2(&q.x, &q.x);
+        let x_cubed = self.mul_fp2(&x_squared, &q.x);
+        let rhs = self.add_fp2(&x_cubed, &twist_b);
+        self.connect_fp2(&y_squared, &rhs);
+    }
+
+    pub fn connect_g2_point<C: PairingCurve>(
+        &mut self,
+        lhs: &G2AffinePointTarget<C>,
+        rhs: &G2AffinePointTarget<C>,
+    ) {
+        self.connect_fp2(&lhs.x, &rhs.x);
+        self.connect_fp2(&lhs.y, &rhs.y);
+    }
+
+    /// Applies the `p^k`-power Frobenius endomorphism to a G2 point, i.e. computes
+    /// `untwist^-1(frobenius^k(untwist(q)))`.
+    pub fn g2_frobenius<C: PairingCurve>(
+        &mut self,
+        q: &G2AffinePointTarget<C>,
+        k: usize,
+    ) -> G2AffinePointTarget<C> {
+        let (x_coeff, y_coeff) = g2_frobenius_coeffs::<C>(k);
+        let (x, y) = if k % 2 == 1 {
+            (self.conj_fp2(&q.x), self.conj_fp2(&q.y))
+        } else {
+            (q.x.clone(), q.y.clone())
+        };
+        G2AffinePointTarget {
+            x: self.mul_fp2_by_const(&x, x_coeff),
+            y: self.mul_fp2_by_const(&y, y_coeff),
+        }
+    }
+
+    pub fn g2_neg<C: PairingCurve>(
+        &mut self,
+        q: &G2AffinePointTarget<C>,
+    ) -> G2AffinePointTarget<C> {
+        G2AffinePointTarget {
+            x: q.x.clone(),
+            y: self.neg_fp2(&q.y),
+        }
+    }
+
+    /// Doubles a G2 point. As with `curve_double`, the point must not be zero.
+    pub fn g2_double<C: PairingCurve>(
+        &mut self,
+        t: &G2AffinePointTarget<C>,
+    ) -> G2AffinePointTarget<C> {
+        let lambda = self.g2_tangent_slope(t);
+        self.g2_chord_result(&lambda, t, &t.x)
+    }
+
+    /// Adds two distinct, non-opposite G2 points.
+    pub fn g2_add<C: PairingCurve>(
+        &mut self,
+        t: &G2AffinePointTarget<C>,
+        q: &G2AffinePointTarget<C>,
+    ) -> G2AffinePointTarget<C> {
+        let lambda = self.g2_chord_slope(t, q);
+        self.g2_chord_result(&lambda, t, &q.x)
+    }
+
+    /// Multiplies a G2 point by a nonzero constant, by double-and-add. With our incomplete formulas,
+    /// this requires that no intermediate multiple `[k] q` with `1 < k < scalar` is zero, which
+    /// holds when `q` has prime order larger than the scalar; otherwise the circuit is
+    /// unsatisfiable.
+    pub fn g2_mul_u64<C: PairingCurve>(
+        &mut self,
+        q: &G2AffinePointTarget<C>,
+        scalar: u64,
+    ) -> G2AffinePointTarget<C> {
+        assert_ne!(scalar, 0);
+        let num_bits = 64 - scalar.leading_zeros() as usize;
+        let mut result = q.clone();
+        for i in (0..num_bits - 1).rev() {
+            result = self.g2_double(&result);
+            if (scalar >> i) & 1 == 1 {
+                result = self.g2_add(&result, q);
+            }
+        }
+        result
+    }
+
+    fn g2_tangent_slope<C: PairingCurve>(
+        &mut self,
+        t: &G2AffinePointTarget<C>,
+    ) -> Fp2Target<C::BaseField> {
+        let x_squared = self.mul_fp2(&t.x, &t.x);
+        let double_x_squared = self.add_fp2(&x_squared, &x_squared);
+        let triple_x_squared = self.add_fp2(&double_x_squared, &x_squared);
+        let double_y = self.add_fp2(&t.y, &t.y);
+        let inv_double_y = self.inv_fp2(&double_y);
+        self.mul_fp2(&triple_x_squared, &inv_double_y)
+    }
+
+    fn g2_chord_slope<C: PairingCurve>(
+        &mut self,
+        t: &G2AffinePointTarget<C>,
+        q: &G2AffinePointTarget<C>,
+    ) -> Fp2Target<C::BaseField> {
+        let dy = self.sub_fp2(&q.y, &t.y);
+        let dx = self.sub_fp2(&q.x, &t.x);
+        let inv_dx = self.inv_fp2(&dx);
+        self.mul_fp2(&dy, &inv_dx)
+    }
+
+    /// Doubles a G2 point `t`, returning `2t` along with the tangent line at `t` evaluated at the
+    /// G1 point `p`.
+    pub fn g2_double_with_line<C: PairingCurve>(
+        &mut self,
+        t: &G2AffinePointTarget<C>,
+        p: &AffinePointTarget<C>,
+    ) -> (G2AffinePointTarget<C>, LineTarget<C::BaseField>) {
+        let lambda = self.g2_tangent_slope(t);
+
+        let line = self.line_evaluation(&lambda, t, p);
+        let doubled = self.g2_chord_result(&lambda, t, &t.x);
+        (doubled, line)
+    }
+
+    /// Adds two distinct, non-opposite G2 points `t` and `q`, returning `t + q` along with the line
+    /// through them evaluated at the G1 point `p`.
+    pub fn g2_add_with_line<C: PairingCurve>(
+        &mut self,
+        t: &G2AffinePointTarget<C>,
+        q: &G2AffinePointTarget<C>,
+        p: &AffinePointTarget<C>,
+    ) -> (G2AffinePointTarget<C>, LineTarget<C::BaseField>) {
+        let lambda = self.g2_chord_slope(t, q);
+
+        let line = self.line_evaluation(&lambda, t, p);
+        let sum = self.g2_chord_result(&lambda, t, &q.x);
+        (sum, line)
+    }
+
+    /// Given the slope `lambda` of a line through `t` and another point with x coordinate `x2`,
+    /// returns the third point of intersection, negated.
+    fn g2_chord_result<C: PairingCurve>(
+        &mut self,
+        lambda: &Fp2Target<C::BaseField>,
+        t: &G2AffinePointTarget<C>,
+        x2: &Fp2Target<C::BaseField>,
+    ) -> G2AffinePointTarget<C> {
+        let lambda_squared = self.mul_fp2(lambda, lambda);
+        let x_sum = self.add_fp2(&t.x, x2);
+        let x3 = self.sub_fp2(&lambda_squared, &x_sum);
+        let x_diff = self.sub_fp2(&t.x, &x3);
+        let lambda_x_diff = self.mul_fp2(lambda, &x_diff);
+        let y3 = self.sub_fp2(&lambda_x_diff, &t.y);
+        G2AffinePointTarget { x: x3, y: y3 }
+    }
+
+    /// Evaluates the line with slope `lambda` through the G2 point `t` at the G1 point `p`, after
+    /// untwisting. The coefficients it occupies depend on the twist type.
+    fn line_evaluation<C: PairingCurve>(
+        &mut self,
+        lambda: &Fp2Target<C::BaseField>,
+        t: &G2AffinePointTarget<C>,
+        p: &AffinePointTarget<C>,
+    ) -> LineTarget<C::BaseField> {
+        let lambda_x_t = self.mul_fp2(lambda, &t.x);
+        let constant_term = self.sub_fp2(&lambda_x_t, &t.y);
+        let lambda_x_p = self.mul_fp2_by_base(lambda, &p.x);
+        let neg_lambda_x_p = self.neg_fp2(&lambda_x_p);
+        let y_p = Fp2Target {
+            c0: p.y.clone(),
+            c1: self.zero_nonnative(),
+        };
+
+        let mut line = [(); 6].map(|_| None);
+        match C::TWIST {
+            TwistType::M => {
+                line[0] = Some(constant_term);
+                line[2] = Some(neg_lambda_x_p);
+                line[3] = Some(y_p);
+            }
+            TwistType::D => {
+                line[0] = Some(y_p);
+                line[1] = Some(neg_lambda_x_p);
+                line[3] = Some(constant_term);
+            }
+        }
+        line
+    }
+}
+
+#[derive(Debug)]
+struct Fp12InverseGenerator<F: RichField + Extendable<D>, const D: usize, C: PairingCurve> {
+    f: Fp12Target<C>,
+    inv: Fp12Target<C>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize, C: PairingCurve> SimpleGenerator<F>
+    for Fp12InverseGenerator<F, D, C>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        self.f
+            .0
+            .iter()
+            .flat_map(|c| c.c0.value.limbs.iter().chain(&c.c1.value.limbs))
+            .map(|&l| l.0)
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let f = Fp12::<C>(self.f.0.clone().map(|c| {
+            Fp2([
+                witness.get_nonnative_target(c.c0),
+                witness.get_nonnative_target(c.c1),
+            ])
+        }));
+        let inv = f.inverse();
+
+        for (t, v) in self.inv.0.iter().zip(inv.0) {
+            out_buffer.set_nonnative_target(t.c0.clone(), v.0[0]);
+            out_buffer.set_nonnative_target(t.c1.clone(), v.0[1]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::curve::bls12_381::Bls12_381;
+    use crate::curve::pairing::{Fp12, Fp2};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn rand_fp12() -> Fp12<Bls12_381> {
+        Fp12([(); 6].map(|_| Fp2([Field::rand(), Field::rand()])))
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fp12_arithmetic() -> Result<()> {
+        let a = rand_fp12();
+        let b = rand_fp12();
+
+        let config = CircuitConfig::standard_ecc_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let a_target = builder.constant_fp12(a);
+        let b_target = builder.constant_fp12(b);
+
+        let product = builder.mul_fp12(&a_target, &b_target);
+        let expected_product = builder.constant_fp12(a * b);
+        builder.connect_fp12(&product, &expected_product);
+
+        let square = builder.square_fp12(&a_target);
+        let expected_square = builder.constant_fp12(a * a);
+        builder.connect_fp12(&square, &expected_square);
+
+        let inv = builder.inv_fp12(&a_target);
+        let expected_inv = builder.constant_fp12(a.inverse());
+        builder.connect_fp12(&inv, &expected_inv);
+
+        let frob = builder.frobenius_fp12(&a_target, 1);
+        let expected_frob = builder.constant_fp12(a.frobenius(1));
+        builder.connect_fp12(&frob, &expected_frob);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,405 @@
+//! Building blocks for shielded-transfer ("privacy pool") circuits: note commitments, nullifiers,
+//! membership proofs against an append-only commitment tree, and value conservation checks.
+//!
+//! A note is committed to as `H(value || owner || blinding)`, where `owner = H(spending_key)`.
+//! Spending the note at position `i` of the commitment tree reveals the nullifier
+//! `H(spending_key || commitment || i)`, which can only be computed by the owner, and which is the
+//! same every time the note is spent, so double spends can be detected.
+
+use std::marker::PhantomData;
+
+use anyhow::{ensure, Result};
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain};
+
+/// The number of bits in a note value. Sums of up to `2^15` values cannot wrap around the
+/// Goldilocks field, so value conservation can be checked with plain field arithmetic.
+pub const NOTE_VALUE_BITS: usize = 48;
+
+/// A shielded note.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Note<F: Field> {
+    pub value: u64,
+    /// The hash of the owner's spending key.
+    pub owner: HashOut<F>,
+    /// Randomness which hides the note's contents.
+    pub blinding: HashOut<F>,
+}
+
+impl<F: RichField> Note<F> {
+    pub fn new<H: AlgebraicHasher<F>>(
+        value: u64,
+        spending_key: HashOut<F>,
+        blinding: HashOut<F>,
+    ) -> Self {
+        Self {
+            value,
+            owner: note_owner::<F, H>(spending_key),
+            blinding,
+        }
+    }
+
+    fn to_elements(self) -> Vec<F> {
+        let mut elements = vec![F::from_canonical_u64(self.value)];
+        elements.extend(self.owner.elements);
+        elements.extend(self.blinding.elements);
+        elements
+    }
+
+    pub fn commitment<H: AlgebraicHasher<F>>(&self) -> HashOut<F> {
+        debug_assert!(self.value < 1 << NOTE_VALUE_BITS);
+        H::hash_no_pad(&self.to_elements())
+    }
+
+    /// The nullifier revealed when spending this note from position `leaf_index` of the commitment
+    /// tree.
+    pub fn nullifier<H: AlgebraicHasher<F>>(
+        &self,
+        spending_key: HashOut<F>,
+        leaf_index: usize,
+    ) -> HashOut<F> {
+        let mut inputs = spending_key.elements.to_vec();
+        inputs.extend(self.commitment::<H>().elements);
+        inputs.push(F::from_canonical_usize(leaf_index));
+        H::hash_no_pad(&inputs)
+    }
+}
+
+/// The owner field of notes spendable with the given key.
+pub fn note_owner<F: RichField, H: AlgebraicHasher<F>>(spending_key: HashOut<F>) -> HashOut<F> {
+    H::hash_no_pad(&spending_key.elements)
+}
+
+/// An append-only Merkle tree of note commitments with a fixed depth. Empty leaves are zero
+/// digests, and only the nonempty part of the tree is stored, so deep trees are cheap.
+#[derive(Clone, Debug)]
+pub struct CommitmentTree<F: RichField, H: AlgebraicHasher<F>> {
+    depth: usize,
+    /// `layers[i]` holds the nonempty digests at height `i`, with `layers[0]` being the leaves.
+    layers: Vec<Vec<HashOut<F>>>,
+    /// `empty_digests[i]` is the root of an empty subtree of height `i`.
+    empty_digests: Vec<HashOut<F>>,
+    _phantom: PhantomData<H>,
+}
+
+impl<F: RichField, H: AlgebraicHasher<F>> CommitmentTree<F, H> {
+    pub fn new(depth: usize) -> Self {
+        let mut empty_digests = vec![HashOut::ZERO];
+        for i in 0..depth {
+            let domain = CompressionDomain::for_merkle_node(i + 1, depth);
+            let empty_digest =
+                H::two_to_one_with_domain(empty_digests[i], empty_digests[i], domain);
+            empty_digests.push(empty_digest);
+        }
+        Self {
+            depth,
+            layers: vec![Vec::new(); depth + 1],
+            empty_digests,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends a commitment, returning its leaf index.
+    pub fn append(&mut self, commitment: HashOut<F>) -> Result<usize> {
+        let leaf_index = self.len();
+        ensure!(leaf_index < 1 << self.depth, "Commitment tree is full");
+
+        self.layers[0].push(commitment);
+        let mut index = leaf_index;
+        for i in 0..self.depth {
+            let left = self.node(i, index & !1);
+            let right = self.node(i, index | 1);
+            let domain = CompressionDomain::for_merkle_node(i + 1, self.depth);
+            let parent = H::two_to_one_with_domain(left, right, domain);
+            index >>= 1;
+            if index < self.layers[i + 1].len() {
+                self.layers[i + 1][index] = parent;
+            } else {
+                self.layers[i + 1].push(parent);
+            }
+        }
+        Ok(leaf_index)
+    }
+
+    pub fn root(&self) -> HashOut<F> {
+        self.node(self.depth, 0)
+    }
+
+    /// The root as a Merkle cap of height 0, as expected by `verify_merkle_proof`.
+    pub fn cap(&self) -> MerkleCap<F, H> {
+        MerkleCap(vec![self.root()])
+    }
+
+    /// Creates a membership proof for the commitment at `leaf_index`.
+    pub fn prove(&self, leaf_index: usize) -> MerkleProof<F, H> {
+        assert!(leaf_index < self.len());
+        let siblings = (0..self.depth)
+            .map(|i| self.node(i, (leaf_index >> i) ^ 1))
+            .collect();
+        MerkleProof { siblings }
+    }
+
+    fn node(&self, height: usize, index: usize) -> HashOut<F> {
+        self.layers[height]
+            .get(index)
+            .copied()
+            .unwrap_or(self.empty_digests[height])
+    }
+}
+
+/// A target representing a shielded note.
+#[derive(Copy, Clone, Debug)]
+pub struct NoteTarget {
+    pub value: Target,
+    pub owner: HashOutTarget,
+    pub blinding: HashOutTarget,
+}
+
+impl NoteTarget {
+    fn to_targets(self) -> Vec<Target> {
+        let mut targets = vec![self.value];
+        targets.extend(self.owner.elements);
+        targets.extend(self.blinding.elements);
+        targets
+    }
+}
+
+/// Sets the witness for a note target.
+pub fn set_note_target<F: Field, W: Witness<F>>(
+    witness: &mut W,
+    target: &NoteTarget,
+    note: &Note<F>,
+) {
+    witness.set_target(target.value, F::from_canonical_u64(note.value));
+    witness.set_hash_target(target.owner, note.owner);
+    witness.set_hash_target(target.blinding, note.blinding);
+}
+
+/// Sets the witness for a membership proof target.
+pub fn set_membership_proof_target<F: RichField, H: AlgebraicHasher<F>, W: Witness<F>>(
+    witness: &mut W,
+    target: &MerkleProofTarget,
+    proof: &MerkleProof<F, H>,
+) {
+    for (&t, &sibling) in target.siblings.iter().zip(&proof.siblings) {
+        witness.set_hash_target(t, sibling);
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn add_virtual_note_target(&mut self) -> NoteTarget {
+        NoteTarget {
+            value: self.add_virtual_target(),
+            owner: self.add_virtual_hash(),
+            blinding: self.add_virtual_hash(),
+        }
+    }
+
+    pub fn add_virtual_membership_proof(&mut self, depth: usize) -> MerkleProofTarget {
+        self.add_virtual_merkle_proof(depth)
+    }
+
+    pub fn note_owner<H: AlgebraicHasher<F>>(
+        &mut self,
+        spending_key: HashOutTarget,
+    ) -> HashOutTarget {
+        self.hash_n_to_hash_no_pad::<H>(spending_key.elements.to_vec())
+    }
+
+    /// Computes a note's commitment. This also range-checks the note's value.
+    pub fn note_commitment<H: AlgebraicHasher<F>>(&mut self, note: &NoteTarget) -> HashOutTarget {
+        self.range_check(note.value, NOTE_VALUE_BITS);
+        self.hash_n_to_hash_no_pad::<H>(note.to_targets())
+    }
+
+    pub fn note_nullifier<H: AlgebraicHasher<F>>(
+        &mut self,
+        spending_key: HashOutTarget,
+        commitment: HashOutTarget,
+        leaf_index: Target,
+    ) -> HashOutTarget {
+        let mut inputs = spending_key.elements.to_vec();
+        inputs.extend(commitment.elements);
+        inputs.push(leaf_index);
+        self.hash_n_to_hash_no_pad::<H>(inputs)
+    }
+
+    /// Verifies that `commitment` is the leaf at `leaf_index` of the commitment tree with the given
+    /// root. Returns the little-endian bits of `leaf_index`, which is range-checked to the depth of
+    /// the tree.
+    pub fn verify_note_membership<H: AlgebraicHasher<F>>(
+        &mut self,
+        commitment: HashOutTarget,
+        leaf_index: Target,
+        root: HashOutTarget,
+        proof: &MerkleProofTarget,
+    ) -> Vec<BoolTarget> {
+        let leaf_index_bits = self.split_le(leaf_index, proof.siblings.len());
+        let zero = self.zero();
+        self.verify_merkle_proof_with_cap_index::<H>(
+            commitment.elements.to_vec(),
+            &leaf_index_bits,
+            zero,
+            &MerkleCapTarget(vec![root]),
+            proof,
+        );
+        leaf_index_bits
+    }
+
+    /// Spends a note: checks that the spending key owns the note and that the note's commitment is
+    /// in the tree, and returns the note's nullifier.
+    pub fn spend_note<H: AlgebraicHasher<F>>(
+        &mut self,
+        note: &NoteTarget,
+        spending_key: HashOutTarget,
+        leaf_index: Target,
+        root: HashOutTarget,
+        proof: &MerkleProofTarget,
+    ) -> HashOutTarget {
+        let owner = self.note_owner::<H>(spending_key);
+        self.connect_hashes(owner, note.owner);
+        let commitment = self.note_commitment::<H>(note);
+        self.verify_note_membership::<H>(commitment, leaf_index, root, proof);
+        self.note_nullifier::<H>(spending_key, commitment, leaf_index)
+    }
+
+    /// Asserts that the input values equal the output values plus `fee`. Each value is range-checked
+    /// to `NOTE_VALUE_BITS` bits, so the sums cannot wrap around as long as there are fewer than
+    /// `2^15` values on either side.
+    pub fn assert_value_conservation(
+        &mut self,
+        inputs: &[Target],
+        outputs: &[Target],
+        fee: Target,
+    ) {
+        debug_assert!(inputs.len() < 1 << 15 && outputs.len() < 1 << 15);
+        for &v in inputs.iter().chain(outputs).chain([&fee]) {
+            self.range_check(v, NOTE_VALUE_BITS);
+        }
+        let input_sum = self.add_many(inputs);
+        let output_sum = self.add_many(outputs);
+        let output_sum_plus_fee = self.add(output_sum, fee);
+        self.connect(input_sum, output_sum_plus_fee);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use super::*;
+    use crate::hash::merkle_proofs::verify_merkle_proof;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    const DEPTH: usize = 20;
+
+    #[test]
+    fn test_commitment_tree() -> Result<()> {
+        let mut tree = CommitmentTree::<F, H>::new(DEPTH);
+        let empty_root = tree.root();
+        let commitments = (0..5).map(|_| HashOut::rand()).collect::<Vec<_>>();
+        for (i, &c) in commitments.iter().enumerate() {
+            assert_eq!(tree.append(c)?, i);
+        }
+        assert_ne!(tree.root(), empty_root);
+
+        for (i, &c) in commitments.iter().enumerate() {
+            let proof = tree.prove(i);
+            verify_merkle_proof(c.elements.to_vec(), i, &tree.cap(), &proof)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_spend_note() -> Result<()> {
+        let spending_key = HashOut::<F>::rand();
+        let notes = [
+            Note::new::<H>(1000, spending_key, HashOut::rand()),
+            Note::new::<H>(234, spending_key, HashOut::rand()),
+        ];
+        let outputs = [Note::<F>::new::<H>(1200, HashOut::rand(), HashOut::rand())];
+        let fee = 34;
+
+        let mut tree = CommitmentTree::<F, H>::new(DEPTH);
+        tree.append(HashOut::rand())?;
+        let indices = notes
+            .iter()
+            .map(|n| tree.append(n.commitment::<H>()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+
+        let root = builder.add_virtual_hash();
+        pw.set_hash_target(root, tree.root());
+        let key_target = builder.add_virtual_hash();
+        pw.set_hash_target(key_target, spending_key);
+
+        let mut input_values = Vec::new();
+        for (note, &index) in notes.iter().zip(&indices) {
+            let note_target = builder.add_virtual_note_target();
+            set_note_target(&mut pw, &note_target, note);
+            let index_target = builder.constant(F::from_canonical_usize(index));
+            let proof_target = builder.add_virtual_membership_proof(DEPTH);
+            set_membership_proof_target(&mut pw, &proof_target, &tree.prove(index));
+
+            let nullifier = builder.spend_note::<H>(
+                &note_target,
+                key_target,
+                index_target,
+                root,
+                &proof_target,
+            );
+            let expected = note.nullifier::<H>(spending_key, index);
+            let expected = builder.constant_hash(expected);
+            builder.connect_hashes(nullifier, expected);
+            input_values.push(note_target.value);
+        }
+
+        let mut output_values = Vec::new();
+        for note in &outputs {
+            let note_target = builder.add_virtual_note_target();
+            set_note_target(&mut pw, &note_target, note);
+            let commitment = builder.note_commitment::<H>(&note_target);
+            builder.register_public_inputs(&commitment.elements);
+            output_values.push(note_target.value);
+        }
+
+        let fee = builder.constant(F::from_canonical_u64(fee));
+        builder.assert_value_conservation(&input_values, &output_values, fee);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
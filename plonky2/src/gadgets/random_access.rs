@@ -1,5 +1,5 @@
 use plonky2_field::extension_field::Extendable;
-use plonky2_util::log2_strict;
+use plonky2_util::{ceil_div_usize, log2_ceil};
 
 use crate::gates::random_access::RandomAccessGate;
 use crate::hash::hash_types::RichField;
@@ -9,14 +9,41 @@ use crate::plonk::circuit_builder::CircuitBuilder;
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Checks that a `Target` matches a vector at a non-deterministic index.
-    /// Note: `access_index` is not range-checked.
+    ///
+    /// The vector may have any length. Vectors which fit in a single `RandomAccessGate` are padded
+    /// with zeros to the next power of two, and longer ones are split into chunks, with a first
+    /// access selecting an element of each chunk and a second selecting the right chunk.
+    /// Note: `access_index` is not range-checked against `v.len()`, so an index in the padding
+    /// reads zero.
     pub fn random_access(&mut self, access_index: Target, claimed_element: Target, v: Vec<Target>) {
-        let vec_size = v.len();
-        let bits = log2_strict(vec_size);
-        debug_assert!(vec_size > 0);
-        if vec_size == 1 {
+        debug_assert!(!v.is_empty());
+        if v.len() == 1 {
             return self.connect(claimed_element, v[0]);
         }
+        let element = if v.len() <= 1 << self.max_random_access_bits() {
+            self.random_access_padded(access_index, v)
+        } else {
+            self.random_access_chunked(access_index, v)
+        };
+        self.connect(claimed_element, element);
+    }
+
+    /// The largest number of bits of index which a single `RandomAccessGate` can support.
+    fn max_random_access_bits(&self) -> usize {
+        let mut bits = 1;
+        while RandomAccessGate::<F, D>::new_from_config(&self.config, bits + 1).num_copies > 0 {
+            bits += 1;
+        }
+        bits
+    }
+
+    /// Returns the element of `v` at `access_index` using one copy of a `RandomAccessGate`, after
+    /// padding `v` with zeros to a power of two.
+    fn random_access_padded(&mut self, access_index: Target, mut v: Vec<Target>) -> Target {
+        let bits = log2_ceil(v.len());
+        let zero = self.zero();
+        v.resize(1 << bits, zero);
+
         let (gate_index, copy) = self.find_random_access_gate(bits);
         let dummy_gate = RandomAccessGate::<F, D>::new_from_config(&self.config, bits);
 
@@ -30,10 +57,38 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             access_index,
             Target::wire(gate_index, dummy_gate.wire_access_index(copy)),
         );
-        self.connect(
-            claimed_element,
-            Target::wire(gate_index, dummy_gate.wire_claimed_element(copy)),
-        );
+        Target::wire(gate_index, dummy_gate.wire_claimed_element(copy))
+    }
+
+    /// Returns the element of `v` at `access_index`, for vectors too long for a single gate. The
+    /// index is split into its low bits, which select an element of each chunk of `v`, and its high
+    /// bits, which select one of these elements.
+    fn random_access_chunked(&mut self, access_index: Target, v: Vec<Target>) -> Target {
+        let chunk_bits = self.max_random_access_bits();
+        let num_chunks = ceil_div_usize(v.len(), 1 << chunk_bits);
+        let high_bits = log2_ceil(num_chunks);
+
+        let index_bits = self.split_le(access_index, chunk_bits + high_bits);
+        let low_index = self.le_sum(index_bits[..chunk_bits].iter());
+        let high_index = self.le_sum(index_bits[chunk_bits..].iter());
+
+        let chunk_elements = v
+            .chunks(1 << chunk_bits)
+            .map(|chunk| match chunk {
+                // A lone last element is too short for a gate. It's read at low index zero, and the
+                // rest of its chunk is padding, which reads zero.
+                [element] => {
+                    let zero = self.zero();
+                    index_bits[..chunk_bits]
+                        .iter()
+                        .fold(*element, |acc, &bit| self.select(bit, zero, acc))
+                }
+                _ => self.random_access_padded(low_index, chunk.to_vec()),
+            })
+            .collect::<Vec<_>>();
+        let element = self.add_virtual_target();
+        self.random_access(high_index, element, chunk_elements);
+        element
     }
 
     /// Checks that an `ExtensionTarget` matches a vector at a non-deterministic index.
@@ -60,7 +115,7 @@ mod tests {
     use plonky2_field::field_types::Field;
 
     use super::*;
-    use crate::iop::witness::PartialWitness;
+    use crate::iop::witness::{PartialWitness, Witness};
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
     use crate::plonk::verifier::verify;
@@ -96,4 +151,71 @@ mod tests {
         }
         Ok(())
     }
+
+    fn test_random_access_given_arbitrary_len(len: usize) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let vec = F::rand_vec(len);
+        let v = builder.add_virtual_targets(len);
+        for (&t, &x) in v.iter().zip(&vec) {
+            pw.set_target(t, x);
+        }
+
+        for i in [0, len / 3, len - 1] {
+            let it = builder.constant(F::from_canonical_usize(i));
+            let elem = builder.constant(vec[i]);
+            builder.random_access(it, elem, v.clone());
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_random_access_non_power_of_two() -> Result<()> {
+        test_random_access_given_arbitrary_len(5)
+    }
+
+    #[test]
+    fn test_random_access_long_list() -> Result<()> {
+        test_random_access_given_arbitrary_len(300)
+    }
+
+    #[test]
+    fn test_random_access_single_element_last_chunk() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let len = (1 << builder.max_random_access_bits()) + 1;
+        let vec = F::rand_vec(len);
+        let v = builder.add_virtual_targets(len);
+        for (&t, &x) in v.iter().zip(&vec) {
+            pw.set_target(t, x);
+        }
+
+        // The last element is alone in its chunk, and the index after it is in the padding.
+        for (i, x) in [
+            (len - 2, vec[len - 2]),
+            (len - 1, vec[len - 1]),
+            (len, F::ZERO),
+        ] {
+            let it = builder.constant(F::from_canonical_usize(i));
+            let elem = builder.constant(x);
+            builder.random_access(it, elem, v.clone());
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
@@ -0,0 +1,763 @@
+//! Matching byte arrays against bounded regular expressions, with capture groups.
+//!
+//! A pattern is compiled into its Glushkov automaton, which has one state per byte class in the
+//! pattern plus an initial state, and no epsilon transitions: entering a state consumes one byte of
+//! its class. The transitions are expanded into a table with one row `(from, byte, to, groups)` per
+//! byte they accept, where `groups` is a mask of the capture groups containing `to`, and the table
+//! is committed to as a `CommittedTable`, so it can be shared by every circuit using the pattern.
+//!
+//! The prover supplies the run of the automaton on the input, and the circuit checks each step
+//! against the table, which also checks that each input is a byte, and that the run ends in an
+//! accepting state. Since the automaton may be nondeterministic, the run is chosen by the prover:
+//! the generator prefers transitions to states earlier in the pattern, but a dishonest prover may
+//! choose any parse. Captures are unique when the pattern is unambiguous, as patterns anchored by
+//! literals usually are; e.g. `.*from:([a-z]+)\r\n.*` has a single parse for any input with one
+//! `from:` line.
+//!
+//! Capture groups may not be repeated by `*`, `+` or `{m,n}`, so each group captures a single
+//! contiguous span. The input is matched in full; a pattern can search for a match anywhere in the
+//! input by starting and ending with `.*`.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::committed_table::{CommittedTable, CommittedTableTarget};
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// The maximum number of capture groups in a pattern, so that group masks fit in a `u32`.
+pub const MAX_GROUPS: usize = 32;
+
+/// The maximum number of copies `{m,n}` may expand a subexpression into.
+const MAX_REPETITIONS: usize = 256;
+
+type ByteSet = [bool; 256];
+
+/// A compiled regular expression.
+#[derive(Clone, Debug)]
+pub struct Regex {
+    num_groups: usize,
+    /// The bytes consumed by entering each state. The initial state, 0, can't be entered.
+    classes: Vec<ByteSet>,
+    /// The mask of the capture groups containing each state.
+    groups: Vec<u32>,
+    /// The states reachable from each state, in increasing order.
+    successors: Vec<Vec<usize>>,
+    accepting: Vec<bool>,
+}
+
+impl Regex {
+    /// Compiles a pattern. The supported syntax is literal bytes, `.`, classes such as `[a-z_]`
+    /// and `[^\r\n]`, the escapes `\d`, `\w`, `\s` and their negations, `\n`, `\r`, `\t`, `\xHH`
+    /// and escaped punctuation, alternation `|`, capture groups `(...)`, non-capturing groups
+    /// `(?:...)`, and the repetitions `*`, `+`, `?`, `{n}`, `{m,}` and `{m,n}`.
+    pub fn new(pattern: &str) -> Result<Self> {
+        let mut parser = Parser {
+            pattern: pattern.as_bytes(),
+            pos: 0,
+            num_groups: 0,
+        };
+        let node = parser.parse_alternation()?;
+        ensure!(
+            parser.pos == parser.pattern.len(),
+            "Unmatched ')' at position {}",
+            parser.pos
+        );
+
+        let mut automaton = Automaton {
+            classes: vec![[false; 256]],
+            groups: vec![0],
+            follow: vec![BTreeSet::new()],
+        };
+        let fragment = automaton.add(&node, 0, false)?;
+
+        let num_states = automaton.classes.len();
+        let mut successors = automaton
+            .follow
+            .into_iter()
+            .map(|s| s.into_iter().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        successors[0] = fragment.first.into_iter().collect();
+        let mut accepting = vec![false; num_states];
+        for &s in &fragment.last {
+            accepting[s] = true;
+        }
+        accepting[0] = fragment.nullable;
+
+        Ok(Self {
+            num_groups: parser.num_groups,
+            classes: automaton.classes,
+            groups: automaton.groups,
+            successors,
+            accepting,
+        })
+    }
+
+    pub fn num_groups(&self) -> usize {
+        self.num_groups
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_match(&self, input: &[u8]) -> bool {
+        self.run(input).is_some()
+    }
+
+    /// The `(start, end)` offsets of each capture group if `input` matches, for the same parse the
+    /// circuit's generator chooses. Groups which capture nothing have `start == end == 0`.
+    pub fn captures(&self, input: &[u8]) -> Option<Vec<(usize, usize)>> {
+        self.run(input).map(|states| self.captures_of_run(&states))
+    }
+
+    /// The rows `(from, byte, to, groups)` of the transition table.
+    pub fn transitions(&self) -> impl Iterator<Item = [u64; 4]> + '_ {
+        self.successors
+            .iter()
+            .enumerate()
+            .flat_map(move |(from, succ)| {
+                succ.iter().flat_map(move |&to| {
+                    (0..256)
+                        .filter(move |&b| self.classes[to][b])
+                        .map(move |b| [from as u64, b as u64, to as u64, self.groups[to] as u64])
+                })
+            })
+    }
+
+    /// Commits to the transition table, keyed by all of its columns.
+    pub fn committed_table<F: RichField, H: AlgebraicHasher<F>>(
+        &self,
+        cap_height: usize,
+    ) -> CommittedTable<F, H>
+    where
+        [(); H::HASH_SIZE]:,
+    {
+        let rows = self
+            .transitions()
+            .map(|row| row.iter().map(|&x| F::from_canonical_u64(x)).collect())
+            .collect();
+        CommittedTable::new(rows, 4, cap_height)
+    }
+
+    /// The states of an accepting run on `input`, starting with the initial state, if there is
+    /// one. Each step moves to the first state from which the rest of the input is accepted.
+    fn run(&self, input: &[u8]) -> Option<Vec<usize>> {
+        let num_states = self.num_states();
+        let n = input.len();
+
+        // `alive[i][s]` holds if the automaton accepts `input[i..]` from state `s`.
+        let mut alive = vec![vec![false; num_states]; n + 1];
+        alive[n] = self.accepting.clone();
+        for i in (0..n).rev() {
+            for s in 0..num_states {
+                alive[i][s] = self.successors[s]
+                    .iter()
+                    .any(|&t| self.classes[t][input[i] as usize] && alive[i + 1][t]);
+            }
+        }
+        if !alive[0][0] {
+            return None;
+        }
+
+        let mut states = vec![0];
+        for i in 0..n {
+            let next = self.successors[states[i]]
+                .iter()
+                .copied()
+                .find(|&t| self.classes[t][input[i] as usize] && alive[i + 1][t])
+                .unwrap();
+            states.push(next);
+        }
+        Some(states)
+    }
+
+    fn captures_of_run(&self, states: &[usize]) -> Vec<(usize, usize)> {
+        (0..self.num_groups)
+            .map(|g| {
+                let mut captured =
+                    (0..states.len() - 1).filter(|&i| self.groups[states[i + 1]] >> g & 1 == 1);
+                match captured.next() {
+                    Some(start) => (start, captured.last().unwrap_or(start) + 1),
+                    None => (0, 0),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Empty,
+    Class(Box<ByteSet>),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+    Group(usize, Box<Node>),
+}
+
+struct Parser<'a> {
+    pattern: &'a [u8],
+    pos: usize,
+    num_groups: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        let c = self
+            .peek()
+            .ok_or_else(|| anyhow!("Unexpected end of pattern"))?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let matches = self.peek() == Some(c);
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat(b'|') {
+            branches.push(self.parse_concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alternation(branches)
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node> {
+        let mut nodes = Vec::new();
+        while !matches!(self.peek(), None | Some(b'|') | Some(b')')) {
+            nodes.push(self.parse_repeat()?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn parse_repeat(&mut self) -> Result<Node> {
+        let mut node = self.parse_atom()?;
+        loop {
+            let (min, max) = match self.peek() {
+                Some(b'*') => (0, None),
+                Some(b'+') => (1, None),
+                Some(b'?') => (0, Some(1)),
+                Some(b'{') => {
+                    self.pos += 1;
+                    let min = self.parse_number()?;
+                    let max = if self.eat(b',') {
+                        if self.peek() == Some(b'}') {
+                            None
+                        } else {
+                            Some(self.parse_number()?)
+                        }
+                    } else {
+                        Some(min)
+                    };
+                    ensure!(self.peek() == Some(b'}'), "Expected '}}' at {}", self.pos);
+                    ensure!(max.map_or(true, |max| min <= max), "Invalid repetition");
+                    ensure!(
+                        max.unwrap_or(min) <= MAX_REPETITIONS,
+                        "Repetitions are limited to {}",
+                        MAX_REPETITIONS
+                    );
+                    (min, max)
+                }
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+            };
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<usize> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        ensure!(self.pos > start, "Expected a number at {}", start);
+        Ok(std::str::from_utf8(&self.pattern[start..self.pos])?.parse()?)
+    }
+
+    fn parse_atom(&mut self) -> Result<Node> {
+        let c = self.next_byte()?;
+        Ok(match c {
+            b'(' => {
+                let group = if self.eat(b'?') {
+                    ensure!(self.eat(b':'), "Unsupported group syntax at {}", self.pos);
+                    None
+                } else {
+                    ensure!(self.num_groups < MAX_GROUPS, "Too many capture groups");
+                    self.num_groups += 1;
+                    Some(self.num_groups - 1)
+                };
+                let node = self.parse_alternation()?;
+                ensure!(self.eat(b')'), "Unclosed group");
+                match group {
+                    Some(g) => Node::Group(g, Box::new(node)),
+                    None => node,
+                }
+            }
+            b'[' => Node::Class(Box::new(self.parse_class()?)),
+            b'.' => Node::Class(Box::new([true; 256])),
+            b'\\' => Node::Class(Box::new(self.parse_escape()?)),
+            b'*' | b'+' | b'?' | b'{' => bail!("Nothing to repeat at {}", self.pos - 1),
+            _ => Node::Class(Box::new(singleton(c))),
+        })
+    }
+
+    /// Parses a class after its opening `[`.
+    fn parse_class(&mut self) -> Result<ByteSet> {
+        let negated = self.eat(b'^');
+        let mut set = [false; 256];
+        let mut first = true;
+        while first || self.peek() != Some(b']') {
+            first = false;
+            let c = self.next_byte()?;
+            let lo = match c {
+                b'\\' => {
+                    let escaped = self.parse_escape()?;
+                    match single_byte(&escaped) {
+                        Some(b) => b,
+                        None => {
+                            union(&mut set, &escaped);
+                            continue;
+                        }
+                    }
+                }
+                _ => c,
+            };
+            if self.peek() == Some(b'-') && self.pattern.get(self.pos + 1) != Some(&b']') {
+                self.pos += 1;
+                let hi = match self.next_byte()? {
+                    b'\\' => single_byte(&self.parse_escape()?)
+                        .ok_or_else(|| anyhow!("Invalid range end at {}", self.pos))?,
+                    hi => hi,
+                };
+                ensure!(lo <= hi, "Invalid range at {}", self.pos);
+                for b in lo..=hi {
+                    set[b as usize] = true;
+                }
+            } else {
+                set[lo as usize] = true;
+            }
+        }
+        self.pos += 1;
+        if negated {
+            for b in set.iter_mut() {
+                *b = !*b;
+            }
+        }
+        Ok(set)
+    }
+
+    /// Parses an escape after its `\`.
+    fn parse_escape(&mut self) -> Result<ByteSet> {
+        let c = self.next_byte()?;
+        let (set, negated) = match c {
+            b'd' | b'D' => (byte_range_set(&[(b'0', b'9')]), c == b'D'),
+            b'w' | b'W' => (
+                byte_range_set(&[(b'a', b'z'), (b'A', b'Z'), (b'0', b'9'), (b'_', b'_')]),
+                c == b'W',
+            ),
+            b's' | b'S' => (byte_range_set(&[(b' ', b' '), (b'\t', b'\r')]), c == b'S'),
+            b'n' => (singleton(b'\n'), false),
+            b'r' => (singleton(b'\r'), false),
+            b't' => (singleton(b'\t'), false),
+            b'x' => {
+                let hex = [self.next_byte()?, self.next_byte()?];
+                let b = u8::from_str_radix(std::str::from_utf8(&hex)?, 16)?;
+                (singleton(b), false)
+            }
+            _ if c.is_ascii_alphanumeric() => bail!("Unknown escape \\{}", c as char),
+            _ => (singleton(c), false),
+        };
+        Ok(if negated { set.map(|b| !b) } else { set })
+    }
+}
+
+fn singleton(c: u8) -> ByteSet {
+    let mut set = [false; 256];
+    set[c as usize] = true;
+    set
+}
+
+fn byte_range_set(ranges: &[(u8, u8)]) -> ByteSet {
+    let mut set = [false; 256];
+    for &(lo, hi) in ranges {
+        for b in lo..=hi {
+            set[b as usize] = true;
+        }
+    }
+    set
+}
+
+fn single_byte(set: &ByteSet) -> Option<u8> {
+    let mut bytes = (0..256).filter(|&b| set[b]);
+    match (bytes.next(), bytes.next()) {
+        (Some(b), None) => Some(b as u8),
+        _ => None,
+    }
+}
+
+fn union(set: &mut ByteSet, other: &ByteSet) {
+    for (b, &o) in set.iter_mut().zip(other) {
+        *b |= o;
+    }
+}
+
+/// A Glushkov automaton under construction.
+struct Automaton {
+    classes: Vec<ByteSet>,
+    groups: Vec<u32>,
+    /// The states which may follow each state.
+    follow: Vec<BTreeSet<usize>>,
+}
+
+/// The states an expression's automaton may start and end in, and whether it accepts the empty
+/// string.
+struct Fragment {
+    nullable: bool,
+    first: BTreeSet<usize>,
+    last: BTreeSet<usize>,
+}
+
+impl Automaton {
+    /// Adds states for `node`, inside the capture groups in `groups`. `repeated` is set inside
+    /// subexpressions which may match more than once.
+    fn add(&mut self, node: &Node, groups: u32, repeated: bool) -> Result<Fragment> {
+        Ok(match node {
+            Node::Empty => Fragment {
+                nullable: true,
+                first: BTreeSet::new(),
+                last: BTreeSet::new(),
+            },
+            Node::Class(set) => {
+                let state = self.classes.len();
+                self.classes.push(**set);
+                self.groups.push(groups);
+                self.follow.push(BTreeSet::new());
+                Fragment {
+                    nullable: false,
+                    first: BTreeSet::from([state]),
+                    last: BTreeSet::from([state]),
+                }
+            }
+            Node::Concat(nodes) => {
+                let mut fragment = self.add(&Node::Empty, groups, repeated)?;
+                for node in nodes {
+                    let next = self.add(node, groups, repeated)?;
+                    fragment = self.concat(fragment, next);
+                }
+                fragment
+            }
+            Node::Alternation(nodes) => {
+                let mut fragment = Fragment {
+                    nullable: false,
+                    first: BTreeSet::new(),
+                    last: BTreeSet::new(),
+                };
+                for node in nodes {
+                    let branch = self.add(node, groups, repeated)?;
+                    fragment.nullable |= branch.nullable;
+                    fragment.first.extend(branch.first);
+                    fragment.last.extend(branch.last);
+                }
+                fragment
+            }
+            Node::Repeat { node, min, max } => {
+                let repeated = repeated || max.map_or(true, |max| max > 1);
+                let mut fragment = self.add(&Node::Empty, groups, repeated)?;
+                for _ in 0..*min {
+                    let copy = self.add(node, groups, repeated)?;
+                    fragment = self.concat(fragment, copy);
+                }
+                match max {
+                    Some(max) => {
+                        for _ in *min..*max {
+                            let mut copy = self.add(node, groups, repeated)?;
+                            copy.nullable = true;
+                            fragment = self.concat(fragment, copy);
+                        }
+                    }
+                    None => {
+                        let mut copy = self.add(node, groups, repeated)?;
+                        for &s in &copy.last {
+                            self.follow[s].extend(copy.first.iter().copied());
+                        }
+                        copy.nullable = true;
+                        fragment = self.concat(fragment, copy);
+                    }
+                }
+                fragment
+            }
+            Node::Group(g, node) => {
+                ensure!(!repeated, "Capture group {} may match more than once", g);
+                self.add(node, groups | 1 << g, repeated)?
+            }
+        })
+    }
+
+    fn concat(&mut self, a: Fragment, b: Fragment) -> Fragment {
+        for &s in &a.last {
+            self.follow[s].extend(b.first.iter().copied());
+        }
+        let mut first = a.first;
+        if a.nullable {
+            first.extend(b.first.iter().copied());
+        }
+        let mut last = b.last;
+        if b.nullable {
+            last.extend(a.last);
+        }
+        Fragment {
+            nullable: a.nullable && b.nullable,
+            first,
+            last,
+        }
+    }
+}
+
+/// The start and end offsets of a capture group in the input. Groups which capture nothing have
+/// `start == end == 0`.
+#[derive(Copy, Clone, Debug)]
+pub struct CaptureTarget {
+    pub start: Target,
+    pub end: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Checks that `bytes` matches `regex` in full, and returns the offsets of its capture groups.
+    /// `table` must reference `regex.committed_table(cap_height)`, with the config's `cap_height`.
+    pub fn regex_match<H: AlgebraicHasher<F> + 'static>(
+        &mut self,
+        regex: &Arc<Regex>,
+        table: &CommittedTableTarget<F, H>,
+        bytes: &[Target],
+    ) -> Vec<CaptureTarget> {
+        let cap_height = self.config.fri_config.cap_height;
+        assert_eq!(
+            table.cap.0.len(),
+            1 << cap_height,
+            "The regex table must be committed with a cap height of {}",
+            cap_height
+        );
+        let n = bytes.len();
+        let mut states = vec![self.zero()];
+        states.extend(self.add_virtual_targets(n));
+        let groups = self.add_virtual_targets(n);
+        self.add_simple_generator(RegexRunGenerator {
+            regex: regex.clone(),
+            bytes: bytes.to_vec(),
+            states: states[1..].to_vec(),
+            groups: groups.clone(),
+        });
+
+        for i in 0..n {
+            self.committed_table_lookup(table, &[states[i], bytes[i], states[i + 1], groups[i]]);
+        }
+
+        // The final state must be accepting.
+        let differences = (0..regex.num_states())
+            .filter(|&s| regex.accepting[s])
+            .map(|s| {
+                let s = self.constant(F::from_canonical_usize(s));
+                self.sub(states[n], s)
+            })
+            .collect::<Vec<_>>();
+        let product = self.mul_many(&differences);
+        self.assert_zero(product);
+
+        if regex.num_groups == 0 {
+            return Vec::new();
+        }
+        let group_bits = groups
+            .iter()
+            .map(|&g| self.split_le(g, regex.num_groups))
+            .collect::<Vec<_>>();
+        (0..regex.num_groups)
+            .map(|g| {
+                // Each group captures a contiguous span, so it starts at the one byte it captures
+                // after a byte it doesn't, and ends after the one byte it captures before a byte it
+                // doesn't.
+                let mut start = self.zero();
+                let mut end = self.zero();
+                for i in 0..n {
+                    let captured = group_bits[i][g];
+                    let before = if i == 0 {
+                        self._false()
+                    } else {
+                        group_bits[i - 1][g]
+                    };
+                    let after = if i + 1 == n {
+                        self._false()
+                    } else {
+                        group_bits[i + 1][g]
+                    };
+                    let not_before = self.not(before);
+                    let is_start = self.and(captured, not_before);
+                    start = self.mul_const_add(F::from_canonical_usize(i), is_start.target, start);
+                    let not_after = self.not(after);
+                    let is_end = self.and(captured, not_after);
+                    end = self.mul_const_add(F::from_canonical_usize(i + 1), is_end.target, end);
+                }
+                CaptureTarget { start, end }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct RegexRunGenerator {
+    regex: Arc<Regex>,
+    bytes: Vec<Target>,
+    states: Vec<Target>,
+    groups: Vec<Target>,
+}
+
+impl<F: RichField> SimpleGenerator<F> for RegexRunGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        self.bytes.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let bytes = witness
+            .get_targets(&self.bytes)
+            .into_iter()
+            .map(|b| {
+                let b = b.to_canonical_u64();
+                assert!(b < 256, "Regex input is not a byte");
+                b as u8
+            })
+            .collect::<Vec<_>>();
+        let states = self
+            .regex
+            .run(&bytes)
+            .expect("Input doesn't match the regex");
+        for i in 0..bytes.len() {
+            let state = states[i + 1];
+            out_buffer.set_target(self.states[i], F::from_canonical_usize(state));
+            out_buffer.set_target(
+                self.groups[i],
+                F::from_canonical_u32(self.regex.groups[state]),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::gadgets::regex::Regex;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = PoseidonHash;
+
+    #[test]
+    fn test_regex_native() -> Result<()> {
+        let regex = Regex::new(r"[a-c]+(\d{2,3})?x|y*")?;
+        assert!(regex.is_match(b"abc12x"));
+        assert!(regex.is_match(b"cx"));
+        assert!(regex.is_match(b""));
+        assert!(regex.is_match(b"yyy"));
+        assert!(!regex.is_match(b"abc1x"));
+        assert!(!regex.is_match(b"abc1234x"));
+        assert!(!regex.is_match(b"dx"));
+        assert_eq!(regex.captures(b"a123x"), Some(vec![(1, 4)]));
+        assert_eq!(regex.captures(b"ax"), Some(vec![(0, 0)]));
+
+        let regex = Regex::new(r"[^:]*:\s*(?:(\w+)|\[(.*)\])")?;
+        assert_eq!(regex.captures(b"to: alice"), Some(vec![(4, 9), (0, 0)]));
+        assert_eq!(regex.captures(b"to:[a b]"), Some(vec![(0, 0), (4, 7)]));
+
+        assert!(Regex::new("(a)*").is_err());
+        assert!(Regex::new("(a){2}").is_err());
+        assert!(Regex::new("(a)?").is_ok());
+        assert!(Regex::new("a)").is_err());
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("[a").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_match() -> Result<()> {
+        let regex = Arc::new(Regex::new(r".*from:([a-z]+)@([a-z]+)\.com\r\n.*")?);
+        let input = b"to:bob@b.org\r\nfrom:alice@example.com\r\nhi";
+        let captures = regex.captures(input).unwrap();
+        assert_eq!(captures, vec![(19, 24), (25, 32)]);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+
+        let cap_height = config.fri_config.cap_height;
+        let table = builder.committed_table(Arc::new(regex.committed_table::<F, H>(cap_height)));
+        let bytes = builder.add_virtual_targets(input.len());
+        for (&t, &b) in bytes.iter().zip(input.iter()) {
+            pw.set_target(t, F::from_canonical_u16(b as u16));
+        }
+        let capture_targets = builder.regex_match(&regex, &table, &bytes);
+        for (capture, &(start, end)) in capture_targets.iter().zip(&captures) {
+            let start_expected = builder.constant(F::from_canonical_usize(start));
+            let end_expected = builder.constant(F::from_canonical_usize(end));
+            builder.connect(capture.start, start_expected);
+            builder.connect(capture.end, end_expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "must be committed with a cap height of 4")]
+    fn test_regex_match_cap_height() {
+        let regex = Arc::new(Regex::new("a+").unwrap());
+        let config = CircuitConfig::standard_recursion_config();
+        assert_eq!(config.fri_config.cap_height, 4);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let table = builder.committed_table(Arc::new(regex.committed_table::<F, H>(2)));
+        let bytes = builder.add_virtual_targets(2);
+        builder.regex_match(&regex, &table, &bytes);
+    }
+}
@@ -0,0 +1,130 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::arithmetic_u64::U64Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The number of bytes that `rlp_decode_u64` can index into, which is the largest vector supported
+/// by a `RandomAccessGate` in the standard configurations.
+pub const RLP_WINDOW_BYTES: usize = 64;
+
+/// The maximum length in bytes of an RLP-encoded `u64`, i.e. a prefix byte and 8 bytes of payload.
+const RLP_U64_MAX_BYTES: usize = 9;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Decodes an RLP-encoded scalar of at most 8 bytes which starts at position `offset` in
+    /// `bytes`, and returns its value along with the position of the next item. Since the offset is
+    /// only known at proving time, `bytes` is limited to a window of
+    /// `RLP_WINDOW_BYTES - RLP_U64_MAX_BYTES + 1` bytes, and bytes past the window read as zero.
+    /// The bytes are assumed to be range-checked already.
+    ///
+    /// This follows the RLP decoding rules but does not reject non-canonical encodings, such as
+    /// leading zeros, which is sound when the encoding is bound by a hash of canonical data.
+    pub fn rlp_decode_u64(&mut self, bytes: &[Target], offset: Target) -> (U64Target, Target) {
+        assert!(bytes.len() <= RLP_WINDOW_BYTES - RLP_U64_MAX_BYTES + 1);
+        let zero = self.zero();
+        let mut window = bytes.to_vec();
+        window.resize(RLP_WINDOW_BYTES, zero);
+
+        let prefix = self.rlp_lookup(window.clone(), offset);
+        let prefix_bits = self.split_byte_le(prefix);
+        // A prefix below `0x80` is a single byte encoding itself. Otherwise, `prefix - 0x80` is
+        // the length of the payload which follows, which we require to be at most 8.
+        let is_long = prefix_bits[7];
+        let low_bits = self.le_bits_to_target(&prefix_bits[..7]);
+        let payload_len = self.mul(is_long.target, low_bits);
+        let eight = self.constant(F::from_canonical_usize(8));
+        let slack = self.sub(eight, payload_len);
+        self.range_check(slack, 4);
+
+        // Read the payload big-endian, accumulating the bytes which belong to the low and high
+        // 32-bit limbs separately. Payload byte `j` is in the low limb iff `len - 4 <= j < len`,
+        // and in the high limb iff `j < len - 4`.
+        let mut lo = zero;
+        let mut hi = zero;
+        let base_minus_one = F::from_canonical_u32(255);
+        for j in 0..8 {
+            let position = self.add_const(offset, F::from_canonical_usize(j + 1));
+            let byte = self.rlp_lookup(window.clone(), position);
+            let in_lo = self.rlp_length_table_lookup(payload_len, |len| j < len && j + 4 >= len);
+            let in_hi = self.rlp_length_table_lookup(payload_len, |len| j + 4 < len);
+
+            let lo_shifted = self.mul_const_add(base_minus_one, lo, byte);
+            lo = self.mul_add(in_lo, lo_shifted, lo);
+            let hi_shifted = self.mul_const_add(base_minus_one, hi, byte);
+            hi = self.mul_add(in_hi, hi_shifted, hi);
+        }
+        let lo = self.select(is_long, lo, prefix);
+
+        let item_len = self.add_const(payload_len, F::ONE);
+        let next_offset = self.add(offset, item_len);
+        (U64Target([U32Target(lo), U32Target(hi)]), next_offset)
+    }
+
+    fn rlp_lookup(&mut self, table: Vec<Target>, index: Target) -> Target {
+        let claimed_element = self.add_virtual_target();
+        self.random_access(index, claimed_element, table);
+        claimed_element
+    }
+
+    /// Looks up `f(len)` for a payload length `len <= 8`, from a table of constants.
+    fn rlp_length_table_lookup<P: Fn(usize) -> bool>(&mut self, len: Target, f: P) -> Target {
+        let table = (0..16).map(|l| self.constant(F::from_bool(f(l)))).collect();
+        self.rlp_lookup(table, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::iop::target::Target;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_rlp_decode_u64() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let values = [0x00, 0x7f, 0x80, 0x1234, 0x12345678, 0x123456789a, u64::MAX];
+        let mut encoded = vec![];
+        for value in values {
+            let bytes = value.to_be_bytes();
+            let payload = &bytes[value.leading_zeros() as usize / 8..];
+            match payload {
+                [b] if *b < 0x80 => {}
+                _ => encoded.push(0x80 + payload.len() as u8),
+            }
+            encoded.extend_from_slice(payload);
+        }
+        let encoded = encoded
+            .into_iter()
+            .map(|b| builder.constant(F::from_canonical_u16(b as u16)))
+            .collect::<Vec<Target>>();
+
+        let mut offset = builder.zero();
+        for value in values {
+            let (decoded, next_offset) = builder.rlp_decode_u64(&encoded, offset);
+            let expected = builder.constant_u64(value);
+            builder.connect_u64(decoded, expected);
+            offset = next_offset;
+        }
+        let expected_len = builder.constant(F::from_canonical_usize(encoded.len()));
+        builder.connect(offset, expected_len);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,696 @@
+//! A template for rollup state-transition circuits, which prove that a batch of signed transfers
+//! takes the state of a rollup from one root to another.
+//!
+//! Accounts live in a sparse binary Merkle tree of fixed depth, indexed by account ID. An account
+//! holds a secp256k1 public key, a balance and a nonce, and its leaf is the hash of those fields;
+//! unused leaves are zero digests. A transfer is signed with ECDSA over the hash of its fields,
+//! which include the sender's nonce so that it can't be replayed. The circuit applies the transfers
+//! of a batch in order, updating the sender's leaf and then the recipient's leaf of each, and
+//! exposes the roots before and after the batch as public inputs.
+//!
+//! Accounts are created by `RollupState::set_account`, e.g. at genesis; deposits, withdrawals and
+//! fees are left to applications. Signature checks dominate the cost of the circuit, so
+//! applications with a cheaper signature scheme should start by replacing
+//! `CircuitBuilder::verify_transfer_signature`.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use anyhow::{anyhow, ensure, Result};
+use num::{BigUint, Zero};
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::{Field, PrimeField};
+use plonky2_field::secp256k1_scalar::Secp256K1Scalar;
+
+use crate::curve::ecdsa::{
+    sign_message, verify_message, ECDSAPublicKey, ECDSASecretKey, ECDSASignature,
+};
+use crate::curve::secp256k1::Secp256K1;
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::biguint::BigUintTarget;
+use crate::gadgets::ecdsa::{ECDSAPublicKeyTarget, ECDSASignatureTarget};
+use crate::gadgets::nonnative::NonNativeTarget;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+use crate::iop::target::Target;
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain};
+
+/// The number of bits in a balance or a transfer amount. Adding an amount to a balance therefore
+/// cannot wrap around the Goldilocks field.
+pub const BALANCE_BITS: usize = 48;
+
+/// The number of 32-bit limbs of a secp256k1 coordinate or scalar.
+const NUM_LIMBS: usize = 8;
+
+/// The little-endian 32-bit limbs of `x`, padded to `NUM_LIMBS`.
+fn limbs<F: Field, FF: PrimeField>(x: FF) -> Vec<F> {
+    let digits = x.to_canonical_biguint().to_u32_digits();
+    (0..NUM_LIMBS)
+        .map(|i| F::from_canonical_u32(digits.get(i).copied().unwrap_or(0)))
+        .collect()
+}
+
+/// An account of the rollup.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Account {
+    pub public_key: ECDSAPublicKey<Secp256K1>,
+    pub balance: u64,
+    /// The number of transfers sent from this account so far.
+    pub nonce: u64,
+}
+
+impl Account {
+    fn to_elements<F: RichField>(self) -> Vec<F> {
+        let mut elements = vec![
+            F::from_canonical_u64(self.balance),
+            F::from_canonical_u64(self.nonce),
+        ];
+        elements.extend(limbs::<F, _>(self.public_key.0.x));
+        elements.extend(limbs::<F, _>(self.public_key.0.y));
+        elements
+    }
+
+    /// The leaf of this account in the account tree.
+    pub fn hash<F: RichField, H: AlgebraicHasher<F>>(&self) -> HashOut<F> {
+        debug_assert!(self.balance < 1 << BALANCE_BITS);
+        H::hash_no_pad(&self.to_elements())
+    }
+}
+
+/// A transfer of `amount` from account `from` to account `to`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Transfer {
+    pub from: u64,
+    pub to: u64,
+    pub amount: u64,
+    /// The sender's nonce before the transfer.
+    pub nonce: u64,
+}
+
+impl Transfer {
+    fn to_elements<F: RichField>(self) -> Vec<F> {
+        [self.from, self.to, self.amount, self.nonce]
+            .iter()
+            .map(|&x| F::from_canonical_u64(x))
+            .collect()
+    }
+
+    /// The message signed by the sender, i.e. the hash of the transfer as a scalar.
+    pub fn message<F: RichField, H: AlgebraicHasher<F>>(&self) -> Secp256K1Scalar {
+        let hash = H::hash_no_pad(&self.to_elements());
+        let mut value = BigUint::zero();
+        for x in hash.elements.iter().rev() {
+            value = (value << 64) + x.to_canonical_u64();
+        }
+        Secp256K1Scalar::from_biguint(value % Secp256K1Scalar::order())
+    }
+
+    pub fn sign<F: RichField, H: AlgebraicHasher<F>>(
+        &self,
+        secret_key: ECDSASecretKey<Secp256K1>,
+    ) -> SignedTransfer {
+        SignedTransfer {
+            transfer: *self,
+            signature: sign_message(self.message::<F, H>(), secret_key),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SignedTransfer {
+    pub transfer: Transfer,
+    pub signature: ECDSASignature<Secp256K1>,
+}
+
+/// What a circuit needs to apply a transfer: the transfer itself, and the two accounts it touches
+/// with their Merkle proofs, each as of just before that account is updated.
+#[derive(Clone, Debug)]
+pub struct TransferWitness<F: RichField, H: AlgebraicHasher<F>> {
+    pub signed_transfer: SignedTransfer,
+    pub sender: Account,
+    pub sender_proof: MerkleProof<F, H>,
+    pub recipient: Account,
+    pub recipient_proof: MerkleProof<F, H>,
+}
+
+/// The state of a rollup: its accounts, and the sparse Merkle tree committing to them. Only the
+/// nonempty nodes of the tree are stored, so deep trees are cheap.
+#[derive(Clone, Debug)]
+pub struct RollupState<F: RichField, H: AlgebraicHasher<F>> {
+    depth: usize,
+    accounts: HashMap<u64, Account>,
+    /// The digests of the nonempty nodes of each layer, starting from the leaves, by index within
+    /// the layer.
+    nodes: Vec<HashMap<u64, HashOut<F>>>,
+    /// `empty_digests[i]` is the root of an empty subtree of height `i`.
+    empty_digests: Vec<HashOut<F>>,
+    _phantom: PhantomData<H>,
+}
+
+impl<F: RichField, H: AlgebraicHasher<F>> RollupState<F, H> {
+    /// A state with no accounts, whose tree has room for `2^depth` of them.
+    pub fn new(depth: usize) -> Self {
+        assert!(depth < 64, "Account IDs must fit in a u64");
+        let mut empty_digests = vec![HashOut::ZERO];
+        for i in 0..depth {
+            let domain = CompressionDomain::for_merkle_node(i + 1, depth);
+            let empty_digest =
+                H::two_to_one_with_domain(empty_digests[i], empty_digests[i], domain);
+            empty_digests.push(empty_digest);
+        }
+        Self {
+            depth,
+            accounts: HashMap::new(),
+            nodes: vec![HashMap::new(); depth + 1],
+            empty_digests,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn root(&self) -> HashOut<F> {
+        self.node(self.depth, 0)
+    }
+
+    pub fn account(&self, id: u64) -> Option<&Account> {
+        self.accounts.get(&id)
+    }
+
+    fn node(&self, layer: usize, index: u64) -> HashOut<F> {
+        self.nodes[layer]
+            .get(&index)
+            .copied()
+            .unwrap_or(self.empty_digests[layer])
+    }
+
+    /// Creates or overwrites the account with the given ID, updating the digests along its path.
+    pub fn set_account(&mut self, id: u64, account: Account) {
+        assert!(id >> self.depth == 0, "Account ID {} is out of range", id);
+        assert!(
+            account.balance < 1 << BALANCE_BITS,
+            "Balance doesn't fit in {} bits",
+            BALANCE_BITS
+        );
+        self.accounts.insert(id, account);
+
+        let mut index = id;
+        let mut digest = account.hash::<F, H>();
+        for layer in 0..self.depth {
+            self.nodes[layer].insert(index, digest);
+            let left = self.node(layer, index & !1);
+            let right = self.node(layer, index | 1);
+            let domain = CompressionDomain::for_merkle_node(layer + 1, self.depth);
+            digest = H::two_to_one_with_domain(left, right, domain);
+            index >>= 1;
+        }
+        self.nodes[self.depth].insert(index, digest);
+    }
+
+    /// Proves the leaf of the account with the given ID, against `root()`.
+    pub fn prove(&self, id: u64) -> MerkleProof<F, H> {
+        assert!(id >> self.depth == 0, "Account ID {} is out of range", id);
+        let siblings = (0..self.depth)
+            .map(|layer| self.node(layer, (id >> layer) ^ 1))
+            .collect();
+        MerkleProof { siblings }
+    }
+
+    /// Checks and applies a transfer, returning what a circuit needs to apply it in turn.
+    pub fn apply_transfer(
+        &mut self,
+        signed_transfer: &SignedTransfer,
+    ) -> Result<TransferWitness<F, H>> {
+        let transfer = signed_transfer.transfer;
+        let sender = *self
+            .account(transfer.from)
+            .ok_or_else(|| anyhow!("Unknown sender {}.", transfer.from))?;
+        let recipient = *self
+            .account(transfer.to)
+            .ok_or_else(|| anyhow!("Unknown recipient {}.", transfer.to))?;
+        ensure!(transfer.nonce == sender.nonce, "Wrong nonce.");
+        ensure!(transfer.amount <= sender.balance, "Insufficient balance.");
+        ensure!(
+            transfer.from == transfer.to || recipient.balance + transfer.amount < 1 << BALANCE_BITS,
+            "Recipient balance overflows."
+        );
+        ensure!(
+            verify_message(
+                transfer.message::<F, H>(),
+                signed_transfer.signature,
+                sender.public_key
+            ),
+            "Invalid signature."
+        );
+
+        let sender_proof = self.prove(transfer.from);
+        let new_sender = Account {
+            balance: sender.balance - transfer.amount,
+            nonce: sender.nonce + 1,
+            ..sender
+        };
+        self.set_account(transfer.from, new_sender);
+
+        // Read the recipient again, as it's the sender if the transfer is to oneself.
+        let recipient = self.accounts[&transfer.to];
+        let recipient_proof = self.prove(transfer.to);
+        let new_recipient = Account {
+            balance: recipient.balance + transfer.amount,
+            ..recipient
+        };
+        self.set_account(transfer.to, new_recipient);
+
+        Ok(TransferWitness {
+            signed_transfer: *signed_transfer,
+            sender,
+            sender_proof,
+            recipient,
+            recipient_proof,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountTarget {
+    pub public_key: ECDSAPublicKeyTarget<Secp256K1>,
+    pub balance: Target,
+    pub nonce: Target,
+}
+
+impl AccountTarget {
+    fn to_targets(&self) -> Vec<Target> {
+        let point = &self.public_key.0;
+        let mut targets = vec![self.balance, self.nonce];
+        targets.extend(point.x.value.limbs.iter().map(|l| l.0));
+        targets.extend(point.y.value.limbs.iter().map(|l| l.0));
+        targets
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TransferTarget {
+    pub from: Target,
+    pub to: Target,
+    pub amount: Target,
+    pub nonce: Target,
+}
+
+impl TransferTarget {
+    fn to_targets(self) -> Vec<Target> {
+        vec![self.from, self.to, self.amount, self.nonce]
+    }
+}
+
+/// See `TransferWitness`.
+#[derive(Clone, Debug)]
+pub struct TransferWitnessTarget {
+    pub transfer: TransferTarget,
+    pub signature: ECDSASignatureTarget<Secp256K1>,
+    pub sender: AccountTarget,
+    pub sender_proof: MerkleProofTarget,
+    pub recipient: AccountTarget,
+    pub recipient_proof: MerkleProofTarget,
+}
+
+/// A batch of transfers, applied in order to the state with root `old_root`.
+#[derive(Clone, Debug)]
+pub struct RollupBatchTarget {
+    pub old_root: HashOutTarget,
+    pub new_root: HashOutTarget,
+    pub transfers: Vec<TransferWitnessTarget>,
+}
+
+fn set_nonnative_target<F: RichField, FF: PrimeField, W: Witness<F>>(
+    witness: &mut W,
+    target: &NonNativeTarget<FF>,
+    value: FF,
+) {
+    for (&t, x) in target.value.limbs.iter().zip(limbs::<F, _>(value)) {
+        witness.set_target(t.0, x);
+    }
+}
+
+/// Sets the witness for an account target.
+pub fn set_account_target<F: RichField, W: Witness<F>>(
+    witness: &mut W,
+    target: &AccountTarget,
+    account: &Account,
+) {
+    set_nonnative_target(witness, &target.public_key.0.x, account.public_key.0.x);
+    set_nonnative_target(witness, &target.public_key.0.y, account.public_key.0.y);
+    witness.set_target(target.balance, F::from_canonical_u64(account.balance));
+    witness.set_target(target.nonce, F::from_canonical_u64(account.nonce));
+}
+
+/// Sets the witness for a transfer witness target.
+pub fn set_transfer_witness_target<F: RichField, H: AlgebraicHasher<F>, W: Witness<F>>(
+    witness: &mut W,
+    target: &TransferWitnessTarget,
+    transfer_witness: &TransferWitness<F, H>,
+) {
+    let SignedTransfer {
+        transfer,
+        signature,
+    } = transfer_witness.signed_transfer;
+    for (&t, x) in target
+        .transfer
+        .to_targets()
+        .iter()
+        .zip(transfer.to_elements::<F>())
+    {
+        witness.set_target(t, x);
+    }
+    set_nonnative_target(witness, &target.signature.r, signature.r);
+    set_nonnative_target(witness, &target.signature.s, signature.s);
+
+    set_account_target(witness, &target.sender, &transfer_witness.sender);
+    set_account_target(witness, &target.recipient, &transfer_witness.recipient);
+    for (proof_target, proof) in [
+        (&target.sender_proof, &transfer_witness.sender_proof),
+        (&target.recipient_proof, &transfer_witness.recipient_proof),
+    ] {
+        for (&t, &sibling) in proof_target.siblings.iter().zip(&proof.siblings) {
+            witness.set_hash_target(t, sibling);
+        }
+    }
+}
+
+/// Sets the witness for a batch applied to the state with root `old_root`.
+pub fn set_rollup_batch_target<F: RichField, H: AlgebraicHasher<F>, W: Witness<F>>(
+    witness: &mut W,
+    target: &RollupBatchTarget,
+    old_root: HashOut<F>,
+    transfer_witnesses: &[TransferWitness<F, H>],
+) {
+    assert_eq!(
+        target.transfers.len(),
+        transfer_witnesses.len(),
+        "Wrong number of transfers"
+    );
+    witness.set_hash_target(target.old_root, old_root);
+    for (t, w) in target.transfers.iter().zip(transfer_witnesses) {
+        set_transfer_witness_target(witness, t, w);
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn add_virtual_account_target(&mut self) -> AccountTarget {
+        AccountTarget {
+            public_key: ECDSAPublicKeyTarget(self.add_virtual_affine_point_target()),
+            balance: self.add_virtual_target(),
+            nonce: self.add_virtual_target(),
+        }
+    }
+
+    pub fn add_virtual_transfer_witness_target(&mut self, depth: usize) -> TransferWitnessTarget {
+        let targets = self.add_virtual_targets(4);
+        TransferWitnessTarget {
+            transfer: TransferTarget {
+                from: targets[0],
+                to: targets[1],
+                amount: targets[2],
+                nonce: targets[3],
+            },
+            signature: ECDSASignatureTarget {
+                r: self.add_virtual_nonnative_target(),
+                s: self.add_virtual_nonnative_target(),
+            },
+            sender: self.add_virtual_account_target(),
+            sender_proof: self.add_virtual_merkle_proof(depth),
+            recipient: self.add_virtual_account_target(),
+            recipient_proof: self.add_virtual_merkle_proof(depth),
+        }
+    }
+
+    /// A circuit applying `num_transfers` transfers to an account tree of the given depth. The old
+    /// and new roots are registered as public inputs, in that order.
+    pub fn rollup_batch<H: AlgebraicHasher<F>>(
+        &mut self,
+        depth: usize,
+        num_transfers: usize,
+    ) -> RollupBatchTarget {
+        let old_root = self.add_virtual_hash();
+        let mut root = old_root;
+        let transfers = (0..num_transfers)
+            .map(|_| {
+                let transfer = self.add_virtual_transfer_witness_target(depth);
+                root = self.apply_transfer::<H>(root, &transfer);
+                transfer
+            })
+            .collect();
+        self.register_public_inputs(&old_root.elements);
+        self.register_public_inputs(&root.elements);
+        RollupBatchTarget {
+            old_root,
+            new_root: root,
+            transfers,
+        }
+    }
+
+    /// Applies a transfer to the state with root `root`, returning the new root.
+    pub fn apply_transfer<H: AlgebraicHasher<F>>(
+        &mut self,
+        root: HashOutTarget,
+        transfer_witness: &TransferWitnessTarget,
+    ) -> HashOutTarget {
+        let TransferWitnessTarget {
+            transfer,
+            signature,
+            sender,
+            sender_proof,
+            recipient,
+            recipient_proof,
+        } = transfer_witness;
+        let one = self.one();
+
+        self.connect(transfer.nonce, sender.nonce);
+        self.verify_transfer_signature::<H>(transfer, signature, sender);
+
+        // The amount is range-checked, as is the sender's new balance, so the sender can't spend
+        // more than they have.
+        self.range_check(transfer.amount, BALANCE_BITS);
+        let sender_balance = self.sub(sender.balance, transfer.amount);
+        self.range_check(sender_balance, BALANCE_BITS);
+        let new_sender = AccountTarget {
+            balance: sender_balance,
+            nonce: self.add(sender.nonce, one),
+            ..sender.clone()
+        };
+        let root = self.update_account::<H>(root, transfer.from, sender, &new_sender, sender_proof);
+
+        let recipient_balance = self.add(recipient.balance, transfer.amount);
+        self.range_check(recipient_balance, BALANCE_BITS);
+        let new_recipient = AccountTarget {
+            balance: recipient_balance,
+            ..recipient.clone()
+        };
+        self.update_account::<H>(
+            root,
+            transfer.to,
+            recipient,
+            &new_recipient,
+            recipient_proof,
+        )
+    }
+
+    /// Checks the sender's ECDSA signature of a transfer.
+    pub fn verify_transfer_signature<H: AlgebraicHasher<F>>(
+        &mut self,
+        transfer: &TransferTarget,
+        signature: &ECDSASignatureTarget<Secp256K1>,
+        sender: &AccountTarget,
+    ) {
+        let message = self.transfer_message::<H>(transfer);
+        self.verify_message(message, signature.clone(), sender.public_key.clone());
+    }
+
+    /// The message signed by the sender of a transfer, matching `Transfer::message`.
+    pub fn transfer_message<H: AlgebraicHasher<F>>(
+        &mut self,
+        transfer: &TransferTarget,
+    ) -> NonNativeTarget<Secp256K1Scalar> {
+        let hash = self.hash_n_to_hash_no_pad::<H>(transfer.to_targets());
+        let mut limbs = Vec::with_capacity(NUM_LIMBS);
+        for &x in &hash.elements {
+            let (low, high) = self.split_low_high(x, 32, 64);
+            limbs.extend([U32Target(low), U32Target(high)]);
+        }
+        let value = self.biguint_to_nonnative(&BigUintTarget { limbs });
+        self.reduce_nonnative(&value)
+    }
+
+    pub fn account_hash<H: AlgebraicHasher<F>>(
+        &mut self,
+        account: &AccountTarget,
+    ) -> HashOutTarget {
+        self.hash_n_to_hash_no_pad::<H>(account.to_targets())
+    }
+
+    /// Verifies that account `id` is `old_account` in the tree with root `old_root`, and returns the
+    /// root of the tree after replacing it with `new_account`. This range-checks `id`.
+    pub fn update_account<H: AlgebraicHasher<F>>(
+        &mut self,
+        old_root: HashOutTarget,
+        id: Target,
+        old_account: &AccountTarget,
+        new_account: &AccountTarget,
+        proof: &MerkleProofTarget,
+    ) -> HashOutTarget {
+        let id_bits = self.split_le(id, proof.siblings.len());
+        let mut old_digest = self.account_hash::<H>(old_account);
+        let mut new_digest = self.account_hash::<H>(new_account);
+        let num_layers = proof.siblings.len();
+        for (i, (&bit, &sibling)) in id_bits.iter().zip(&proof.siblings).enumerate() {
+            let domain = CompressionDomain::for_merkle_node(i + 1, num_layers);
+            old_digest = H::two_to_one_swapped_with_domain(old_digest, sibling, bit, domain, self);
+            new_digest = H::two_to_one_swapped_with_domain(new_digest, sibling, bit, domain, self);
+        }
+        self.connect_hashes(old_digest, old_root);
+        new_digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use super::*;
+    use crate::curve::curve_types::{Curve, CurveScalar};
+    use crate::hash::merkle_proofs::verify_merkle_proof;
+    use crate::hash::merkle_tree::MerkleCap;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    /// Enough for the account IDs used below, while keeping the Merkle proofs of the batch circuit
+    /// short.
+    const DEPTH: usize = 10;
+
+    fn new_key() -> (ECDSASecretKey<Secp256K1>, ECDSAPublicKey<Secp256K1>) {
+        let sk = ECDSASecretKey(Secp256K1Scalar::rand());
+        let pk = ECDSAPublicKey((CurveScalar(sk.0) * Secp256K1::GENERATOR_PROJECTIVE).to_affine());
+        (sk, pk)
+    }
+
+    /// A state with accounts 3 and 1000, holding 100 and 5 respectively, and their secret keys.
+    fn genesis() -> (RollupState<F, H>, Vec<ECDSASecretKey<Secp256K1>>) {
+        let mut state = RollupState::new(DEPTH);
+        let mut secret_keys = Vec::new();
+        for (id, balance) in [(3, 100), (1000, 5)] {
+            let (sk, public_key) = new_key();
+            state.set_account(
+                id,
+                Account {
+                    public_key,
+                    balance,
+                    nonce: 0,
+                },
+            );
+            secret_keys.push(sk);
+        }
+        (state, secret_keys)
+    }
+
+    #[test]
+    fn test_rollup_state() -> Result<()> {
+        let (mut state, secret_keys) = genesis();
+        let empty_root = RollupState::<F, H>::new(DEPTH).root();
+        assert_ne!(state.root(), empty_root);
+
+        let transfer = Transfer {
+            from: 3,
+            to: 1000,
+            amount: 60,
+            nonce: 0,
+        };
+        let signed = transfer.sign::<F, H>(secret_keys[0]);
+
+        // Transfers with the wrong key, nonce or amount are rejected, and don't change the state.
+        let root = state.root();
+        assert!(state
+            .apply_transfer(&transfer.sign::<F, H>(secret_keys[1]))
+            .is_err());
+        let replayed = Transfer {
+            nonce: 1,
+            ..transfer
+        };
+        assert!(state
+            .apply_transfer(&replayed.sign::<F, H>(secret_keys[0]))
+            .is_err());
+        let overdrawn = Transfer {
+            amount: 101,
+            ..transfer
+        };
+        assert!(state
+            .apply_transfer(&overdrawn.sign::<F, H>(secret_keys[0]))
+            .is_err());
+        assert_eq!(state.root(), root);
+
+        let witness = state.apply_transfer(&signed)?;
+        assert_eq!(state.account(3).unwrap().balance, 40);
+        assert_eq!(state.account(3).unwrap().nonce, 1);
+        assert_eq!(state.account(1000).unwrap().balance, 65);
+        verify_merkle_proof(
+            witness.sender.to_elements(),
+            3,
+            &MerkleCap(vec![root]),
+            &witness.sender_proof,
+        )?;
+
+        // The same signed transfer can't be applied twice.
+        assert!(state.apply_transfer(&signed).is_err());
+
+        for id in [3, 1000] {
+            let account = state.account(id).unwrap();
+            verify_merkle_proof(
+                account.to_elements(),
+                id as usize,
+                &MerkleCap(vec![state.root()]),
+                &state.prove(id),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// A batch of a single transfer, since each transfer costs an in-circuit ECDSA verification.
+    /// Ignored for the same reason as `test_ecdsa_circuit`.
+    #[test]
+    #[ignore]
+    fn test_rollup_batch() -> Result<()> {
+        let (mut state, secret_keys) = genesis();
+        let old_root = state.root();
+        let transfers = [Transfer {
+            from: 3,
+            to: 1000,
+            amount: 60,
+            nonce: 0,
+        }
+        .sign::<F, H>(secret_keys[0])];
+        let witnesses = transfers
+            .iter()
+            .map(|t| state.apply_transfer(t))
+            .collect::<Result<Vec<_>>>()?;
+
+        let config = CircuitConfig::standard_ecc_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let batch = builder.rollup_batch::<H>(DEPTH, transfers.len());
+        set_rollup_batch_target(&mut pw, &batch, old_root, &witnesses);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs[..4], old_root.elements);
+        assert_eq!(proof.public_inputs[4..], state.root().elements);
+        data.verify(proof)
+    }
+}
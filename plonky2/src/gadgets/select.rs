@@ -1,8 +1,13 @@
+use std::marker::PhantomData;
+
 use plonky2_field::extension_field::Extendable;
 
+use crate::gates::conditional_arithmetic::ConditionalArithmeticGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
 use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness};
 use crate::plonk::circuit_builder::CircuitBuilder;
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
@@ -36,6 +41,84 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let y_ext = self.convert_to_ext(y);
         self.select_ext(b, x_ext, y_ext).to_target_array()[0]
     }
+
+    /// Returns `if s { a * b + c } else { d }`, using a single operation of a
+    /// `ConditionalArithmeticGate` rather than a multiply-add followed by a select.
+    pub fn select_mul_add(
+        &mut self,
+        s: BoolTarget,
+        a: Target,
+        b: Target,
+        c: Target,
+        d: Target,
+    ) -> Target {
+        if let Some(s) = self.target_as_constant(s.target) {
+            return if s.is_zero() {
+                d
+            } else {
+                self.mul_add(a, b, c)
+            };
+        }
+
+        let (gate_index, i) = self.find_conditional_arithmetic_gate();
+        let inputs = [
+            (ConditionalArithmeticGate::wire_ith_selector(i), s.target),
+            (ConditionalArithmeticGate::wire_ith_multiplicand_0(i), a),
+            (ConditionalArithmeticGate::wire_ith_multiplicand_1(i), b),
+            (ConditionalArithmeticGate::wire_ith_addend(i), c),
+            (ConditionalArithmeticGate::wire_ith_alternative(i), d),
+        ];
+        for (wire, target) in inputs {
+            self.connect(Target::wire(gate_index, wire), target);
+        }
+
+        Target::wire(gate_index, ConditionalArithmeticGate::wire_ith_output(i))
+    }
+
+    /// Returns the one-hot encoding of `index` as a vector of `n` bits, i.e. the bits `i == index`
+    /// for `i` in `0..n`. Fails to prove unless `index < n`.
+    pub fn one_hot(&mut self, index: Target, n: usize) -> Vec<BoolTarget> {
+        let bits = (0..n)
+            .map(|_| self.add_virtual_bool_target_safe())
+            .collect::<Vec<_>>();
+        self.add_simple_generator(OneHotGenerator {
+            index,
+            bits: bits.clone(),
+            _phantom: PhantomData,
+        });
+
+        // Exactly one bit is set, and its position is `index`.
+        let sum = self.add_many(&bits.iter().map(|b| b.target).collect::<Vec<_>>());
+        self.assert_one(sum);
+        let mut weighted_sum = self.zero();
+        for (i, b) in bits.iter().enumerate() {
+            let i = self.constant(F::from_canonical_usize(i));
+            weighted_sum = self.mul_add(i, b.target, weighted_sum);
+        }
+        self.connect(weighted_sum, index);
+
+        bits
+    }
+}
+
+#[derive(Debug)]
+struct OneHotGenerator<F: RichField> {
+    index: Target,
+    bits: Vec<BoolTarget>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField> SimpleGenerator<F> for OneHotGenerator<F> {
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.index]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let index = witness.get_target(self.index).to_canonical_u64() as usize;
+        for (i, &b) in self.bits.iter().enumerate() {
+            out_buffer.set_bool_target(b, i == index);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -79,4 +162,57 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_select_mul_add() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let values = [F::rand(), F::rand(), F::rand(), F::rand()];
+        let [a, b, c, d] = [(); 4].map(|_| builder.add_virtual_target());
+        for (t, v) in [a, b, c, d].into_iter().zip(values) {
+            pw.set_target(t, v);
+        }
+
+        for s in [true, false] {
+            let st = builder.add_virtual_bool_target_safe();
+            pw.set_bool_target(st, s);
+            let result = builder.select_mul_add(st, a, b, c, d);
+            let [a, b, c, d] = values;
+            let expected = builder.constant(if s { a * b + c } else { d });
+            builder.connect(result, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_one_hot() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let index = builder.add_virtual_target();
+        pw.set_target(index, F::from_canonical_usize(5));
+        let bits = builder.one_hot(index, 8);
+        for (i, b) in bits.into_iter().enumerate() {
+            let expected = builder.constant_bool(i == 5);
+            builder.connect(b.target, expected.target);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
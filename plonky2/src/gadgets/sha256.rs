@@ -0,0 +1,201 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::sha2_util::{rotr, shr, WordBits};
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The SHA-256 round constants.
+#[rustfmt::skip]
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The SHA-256 initial hash value.
+const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The number of bytes in a SHA-256 message block.
+const SHA256_BLOCK_BYTES: usize = 64;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the SHA-256 digest of a message of bytes, whose length is fixed at circuit build
+    /// time. Message bytes are range-checked.
+    pub fn sha256(&mut self, message: &[Target]) -> [Target; 32] {
+        let mut padded = message.to_vec();
+        padded.push(self.constant(F::from_canonical_u32(0x80)));
+        while padded.len() % SHA256_BLOCK_BYTES != SHA256_BLOCK_BYTES - 8 {
+            padded.push(self.zero());
+        }
+        let bit_len = (message.len() as u64) * 8;
+        for b in bit_len.to_be_bytes() {
+            padded.push(self.constant(F::from_canonical_u32(b as u32)));
+        }
+
+        let mut state = SHA256_IV
+            .iter()
+            .map(|&h| self.constant_le_bits(h as u64, 32))
+            .collect::<Vec<_>>();
+        for block in padded.chunks(SHA256_BLOCK_BYTES) {
+            let block = block
+                .chunks(4)
+                .map(|word| self.be_bytes_to_le_bits(word))
+                .collect::<Vec<_>>();
+            state = self.sha256_compress_bits(&state, &block);
+        }
+
+        let digest = state
+            .iter()
+            .flat_map(|word| self.le_bits_to_be_bytes(word))
+            .collect::<Vec<_>>();
+        digest.try_into().unwrap()
+    }
+
+    /// Applies the SHA-256 compression function to the given chaining state and message block.
+    pub fn sha256_compress(
+        &mut self,
+        state: &[U32Target; 8],
+        block: &[U32Target; 16],
+    ) -> [U32Target; 8] {
+        let state = state
+            .iter()
+            .map(|&x| self.split_u32_le(x))
+            .collect::<Vec<_>>();
+        let block = block
+            .iter()
+            .map(|&x| self.split_u32_le(x))
+            .collect::<Vec<_>>();
+        let new_state = self.sha256_compress_bits(&state, &block);
+
+        let mut res = [self.zero_u32(); 8];
+        for (r, word) in res.iter_mut().zip(new_state) {
+            *r = self.le_bits_to_u32(&word);
+        }
+        res
+    }
+
+    fn sha256_compress_bits(&mut self, state: &[WordBits], block: &[WordBits]) -> Vec<WordBits> {
+        debug_assert_eq!(state.len(), 8);
+        debug_assert_eq!(block.len(), 16);
+
+        let zero = self._false();
+
+        // Message schedule.
+        let mut w = block.to_vec();
+        for t in 16..64 {
+            let s0 = self.sha2_xor3(
+                &rotr(&w[t - 15], 7),
+                &rotr(&w[t - 15], 18),
+                &shr(&w[t - 15], 3, zero),
+            );
+            let s1 = self.sha2_xor3(
+                &rotr(&w[t - 2], 17),
+                &rotr(&w[t - 2], 19),
+                &shr(&w[t - 2], 10, zero),
+            );
+            let wt = self.sha256_add(&[&s1, &w[t - 7], &s0, &w[t - 16]]);
+            w.push(wt);
+        }
+
+        let mut vars = state.to_vec();
+        for t in 0..64 {
+            let [a, b, c, d, e, f, g, h]: [WordBits; 8] = vars.try_into().unwrap();
+
+            let big_s1 = self.sha2_xor3(&rotr(&e, 6), &rotr(&e, 11), &rotr(&e, 25));
+            let ch = self.sha2_ch(&e, &f, &g);
+            let k = self.constant_le_bits(SHA256_K[t] as u64, 32);
+            let t1 = self.sha256_add(&[&h, &big_s1, &ch, &k, &w[t]]);
+
+            let big_s0 = self.sha2_xor3(&rotr(&a, 2), &rotr(&a, 13), &rotr(&a, 22));
+            let maj = self.sha2_maj(&a, &b, &c);
+            let t2 = self.sha256_add(&[&big_s0, &maj]);
+
+            let new_e = self.sha256_add(&[&d, &t1]);
+            let new_a = self.sha256_add(&[&t1, &t2]);
+            vars = vec![new_a, a, b, c, new_e, e, f, g];
+        }
+
+        state
+            .iter()
+            .zip(&vars)
+            .map(|(x, y)| self.sha256_add(&[x, y]))
+            .collect()
+    }
+
+    /// Computes the sum of the given words modulo `2^32`.
+    fn sha256_add(&mut self, words: &[&WordBits]) -> WordBits {
+        let words = words
+            .iter()
+            .map(|w| self.le_bits_to_u32(w))
+            .collect::<Vec<_>>();
+        let (sum, _carry) = self.add_many_u32(&words);
+        self.split_u32_le(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    fn test_sha256(message: &[u8], expected: &str) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let message_targets = builder.add_virtual_targets(message.len());
+        let digest = builder.sha256(&message_targets);
+        for (&t, &b) in message_targets.iter().zip(message) {
+            pw.set_target(t, F::from_canonical_u16(b as u16));
+        }
+
+        let expected = (0..32)
+            .map(|i| u8::from_str_radix(&expected[2 * i..2 * i + 2], 16).unwrap())
+            .map(|b| builder.constant(F::from_canonical_u16(b as u16)))
+            .collect::<Vec<Target>>();
+        for (d, e) in digest.into_iter().zip(expected) {
+            builder.connect(d, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_sha256_abc() -> Result<()> {
+        test_sha256(
+            b"abc",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        )
+    }
+
+    #[test]
+    fn test_sha256_two_blocks() -> Result<()> {
+        let message = (0..100).map(|i| i as u8).collect::<Vec<_>>();
+        test_sha256(
+            &message,
+            "bce0aff19cf5aa6a7469a30d61d04e4376e4bbf6381052ee9e7f33925c954d52",
+        )
+    }
+}
@@ -0,0 +1,71 @@
+//! Bitwise helpers shared by the SHA-256 and SHA-512 gadgets. Words are represented by their
+//! little-endian bits, so these work for either word size.
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::RichField;
+use crate::iop::target::BoolTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A word, represented by its little-endian bits.
+pub(crate) type WordBits = Vec<BoolTarget>;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub(crate) fn sha2_xor3(
+        &mut self,
+        x: &[BoolTarget],
+        y: &[BoolTarget],
+        z: &[BoolTarget],
+    ) -> WordBits {
+        let xy = self.xor_bits(x, y);
+        self.xor_bits(&xy, z)
+    }
+
+    /// Computes `Ch(e, f, g) = (e AND f) XOR (NOT e AND g)`, i.e. `if e { f } else { g }`.
+    pub(crate) fn sha2_ch(
+        &mut self,
+        e: &[BoolTarget],
+        f: &[BoolTarget],
+        g: &[BoolTarget],
+    ) -> WordBits {
+        (0..e.len())
+            .map(|i| self.select_bit(e[i], f[i], g[i]))
+            .collect()
+    }
+
+    /// Computes `Maj(a, b, c)`. If `a` and `b` agree then their common value is the majority,
+    /// otherwise `c` is.
+    pub(crate) fn sha2_maj(
+        &mut self,
+        a: &[BoolTarget],
+        b: &[BoolTarget],
+        c: &[BoolTarget],
+    ) -> WordBits {
+        (0..a.len())
+            .map(|i| {
+                let differ = self.xor(a[i], b[i]);
+                self.select_bit(differ, c[i], a[i])
+            })
+            .collect()
+    }
+
+    /// Returns `if b { x } else { y }`, as `b (x - y) + y`, using only base field arithmetic.
+    fn select_bit(&mut self, b: BoolTarget, x: BoolTarget, y: BoolTarget) -> BoolTarget {
+        let diff = self.sub(x.target, y.target);
+        BoolTarget::new_unsafe(self.mul_add(b.target, diff, y.target))
+    }
+}
+
+/// Rotates a little-endian word right by `n` bits.
+pub(crate) fn rotr(x: &[BoolTarget], n: usize) -> WordBits {
+    let mut res = x.to_vec();
+    res.rotate_left(n);
+    res
+}
+
+/// Shifts a little-endian word right by `n` bits.
+pub(crate) fn shr(x: &[BoolTarget], n: usize, zero: BoolTarget) -> WordBits {
+    let mut res = x[n..].to_vec();
+    res.resize(x.len(), zero);
+    res
+}
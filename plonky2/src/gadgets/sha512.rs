@@ -0,0 +1,222 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u64::U64Target;
+use crate::gadgets::sha2_util::{rotr, shr, WordBits};
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The SHA-512 round constants.
+#[rustfmt::skip]
+const SHA512_K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// The SHA-512 initial hash value.
+const SHA512_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The number of bytes in a SHA-512 message block.
+const SHA512_BLOCK_BYTES: usize = 128;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the SHA-512 digest of a message of bytes, whose length is fixed at circuit build
+    /// time. Message bytes are range-checked.
+    pub fn sha512(&mut self, message: &[Target]) -> [Target; 64] {
+        let mut padded = message.to_vec();
+        padded.push(self.constant(F::from_canonical_u32(0x80)));
+        while padded.len() % SHA512_BLOCK_BYTES != SHA512_BLOCK_BYTES - 16 {
+            padded.push(self.zero());
+        }
+        let bit_len = (message.len() as u128) * 8;
+        for b in bit_len.to_be_bytes() {
+            padded.push(self.constant(F::from_canonical_u32(b as u32)));
+        }
+
+        let mut state = SHA512_IV
+            .iter()
+            .map(|&h| self.constant_le_bits(h, 64))
+            .collect::<Vec<_>>();
+        for block in padded.chunks(SHA512_BLOCK_BYTES) {
+            let block = block
+                .chunks(8)
+                .map(|word| self.be_bytes_to_le_bits(word))
+                .collect::<Vec<_>>();
+            state = self.sha512_compress_bits(&state, &block);
+        }
+
+        let digest = state
+            .iter()
+            .flat_map(|word| self.le_bits_to_be_bytes(word))
+            .collect::<Vec<_>>();
+        digest.try_into().unwrap()
+    }
+
+    /// Applies the SHA-512 compression function to the given chaining state and message block.
+    pub fn sha512_compress(
+        &mut self,
+        state: &[U64Target; 8],
+        block: &[U64Target; 16],
+    ) -> [U64Target; 8] {
+        let state = state
+            .iter()
+            .map(|&x| self.split_u64_le(x))
+            .collect::<Vec<_>>();
+        let block = block
+            .iter()
+            .map(|&x| self.split_u64_le(x))
+            .collect::<Vec<_>>();
+        let new_state = self.sha512_compress_bits(&state, &block);
+
+        let mut res = [self.zero_u64(); 8];
+        for (r, word) in res.iter_mut().zip(new_state) {
+            *r = self.le_bits_to_u64(&word);
+        }
+        res
+    }
+
+    fn sha512_compress_bits(&mut self, state: &[WordBits], block: &[WordBits]) -> Vec<WordBits> {
+        debug_assert_eq!(state.len(), 8);
+        debug_assert_eq!(block.len(), 16);
+
+        let zero = self._false();
+
+        // Message schedule.
+        let mut w = block.to_vec();
+        for t in 16..80 {
+            let s0 = self.sha2_xor3(
+                &rotr(&w[t - 15], 1),
+                &rotr(&w[t - 15], 8),
+                &shr(&w[t - 15], 7, zero),
+            );
+            let s1 = self.sha2_xor3(
+                &rotr(&w[t - 2], 19),
+                &rotr(&w[t - 2], 61),
+                &shr(&w[t - 2], 6, zero),
+            );
+            let wt = self.sha512_add(&[&s1, &w[t - 7], &s0, &w[t - 16]]);
+            w.push(wt);
+        }
+
+        let mut vars = state.to_vec();
+        for t in 0..80 {
+            let [a, b, c, d, e, f, g, h]: [WordBits; 8] = vars.try_into().unwrap();
+
+            let big_s1 = self.sha2_xor3(&rotr(&e, 14), &rotr(&e, 18), &rotr(&e, 41));
+            let ch = self.sha2_ch(&e, &f, &g);
+            let k = self.constant_le_bits(SHA512_K[t], 64);
+            let t1 = self.sha512_add(&[&h, &big_s1, &ch, &k, &w[t]]);
+
+            let big_s0 = self.sha2_xor3(&rotr(&a, 28), &rotr(&a, 34), &rotr(&a, 39));
+            let maj = self.sha2_maj(&a, &b, &c);
+            let t2 = self.sha512_add(&[&big_s0, &maj]);
+
+            let new_e = self.sha512_add(&[&d, &t1]);
+            let new_a = self.sha512_add(&[&t1, &t2]);
+            vars = vec![new_a, a, b, c, new_e, e, f, g];
+        }
+
+        state
+            .iter()
+            .zip(&vars)
+            .map(|(x, y)| self.sha512_add(&[x, y]))
+            .collect()
+    }
+
+    /// Computes the sum of the given words modulo `2^64`.
+    fn sha512_add(&mut self, words: &[&WordBits]) -> WordBits {
+        let words = words
+            .iter()
+            .map(|w| self.le_bits_to_u64(w))
+            .collect::<Vec<_>>();
+        let (sum, _carry) = self.add_many_u64(&words);
+        self.split_u64_le(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    fn test_sha512(message: &[u8], expected: &str) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let message_targets = builder.add_virtual_targets(message.len());
+        let digest = builder.sha512(&message_targets);
+        for (&t, &b) in message_targets.iter().zip(message) {
+            pw.set_target(t, F::from_canonical_u16(b as u16));
+        }
+
+        let expected = (0..64)
+            .map(|i| u8::from_str_radix(&expected[2 * i..2 * i + 2], 16).unwrap())
+            .map(|b| builder.constant(F::from_canonical_u16(b as u16)))
+            .collect::<Vec<Target>>();
+        for (d, e) in digest.into_iter().zip(expected) {
+            builder.connect(d, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_sha512_abc() -> Result<()> {
+        test_sha512(
+            b"abc",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        )
+    }
+
+    #[test]
+    fn test_sha512_two_blocks() -> Result<()> {
+        let message = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        test_sha512(
+            &message,
+            "986058e9895e2c2ab8f9e8cbdf801db12a44842a56a91d5a4e87b1fc98b29372\
+             2c4664142e42c3c551ff898646268cd92b84ed230b8c94bed7798d4f27cd7465",
+        )
+    }
+}
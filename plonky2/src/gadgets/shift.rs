@@ -0,0 +1,213 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::arithmetic_u64::U64Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// Shifts and rotations of 32 and 64-bit words by an amount only known at proving time, as needed
+/// for the shift instructions of a VM. Fixed shifts should instead permute bits directly.
+///
+/// A 32-bit limb is shifted by `t` by multiplying it with `2^t`, which is looked up from a table
+/// of constants, so that the product's low and high halves are the bits which stay in the limb and
+/// the bits which spill over into the next limb. Right shifts multiply by `2^(32 - t)` instead,
+/// which puts `x >> t` in the high half and the shifted-out bits at the top of the low half.
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes `x << shift`, where `shift` must be less than 32.
+    pub fn shl_u32(&mut self, x: U32Target, shift: Target) -> U32Target {
+        let shift_bits = self.split_le(shift, 5);
+        let (low, _) = self.shift_limb(x, &shift_bits, false);
+        low
+    }
+
+    /// Computes `x >> shift`, where `shift` must be less than 32.
+    pub fn shr_u32(&mut self, x: U32Target, shift: Target) -> U32Target {
+        let shift_bits = self.split_le(shift, 5);
+        let (_, high) = self.shift_limb(x, &shift_bits, true);
+        high
+    }
+
+    /// Rotates `x` left by `shift`, which must be less than 32.
+    pub fn rotl_u32(&mut self, x: U32Target, shift: Target) -> U32Target {
+        let shift_bits = self.split_le(shift, 5);
+        let (low, high) = self.shift_limb(x, &shift_bits, false);
+        U32Target(self.add(low.0, high.0))
+    }
+
+    /// Rotates `x` right by `shift`, which must be less than 32.
+    pub fn rotr_u32(&mut self, x: U32Target, shift: Target) -> U32Target {
+        let shift_bits = self.split_le(shift, 5);
+        let (low, high) = self.shift_limb(x, &shift_bits, true);
+        U32Target(self.add(low.0, high.0))
+    }
+
+    /// Computes `x << shift`, where `shift` must be less than 64.
+    pub fn shl_u64(&mut self, x: U64Target, shift: Target) -> U64Target {
+        let shift_bits = self.split_le(shift, 6);
+        let (lo_low, lo_high) = self.shift_limb(x.lo(), &shift_bits[..5], false);
+        let (hi_low, _) = self.shift_limb(x.hi(), &shift_bits[..5], false);
+        let lo = lo_low.0;
+        let hi = self.add(hi_low.0, lo_high.0);
+
+        // Shift by another 32 bits if the top bit of `shift` is set.
+        let zero = self.zero();
+        let limb_shift = shift_bits[5];
+        U64Target([
+            U32Target(self.select(limb_shift, zero, lo)),
+            U32Target(self.select(limb_shift, lo, hi)),
+        ])
+    }
+
+    /// Computes `x >> shift`, where `shift` must be less than 64.
+    pub fn shr_u64(&mut self, x: U64Target, shift: Target) -> U64Target {
+        let shift_bits = self.split_le(shift, 6);
+        let (_, lo_high) = self.shift_limb(x.lo(), &shift_bits[..5], true);
+        let (hi_low, hi_high) = self.shift_limb(x.hi(), &shift_bits[..5], true);
+        let lo = self.add(lo_high.0, hi_low.0);
+        let hi = hi_high.0;
+
+        // Shift by another 32 bits if the top bit of `shift` is set.
+        let zero = self.zero();
+        let limb_shift = shift_bits[5];
+        U64Target([
+            U32Target(self.select(limb_shift, hi, lo)),
+            U32Target(self.select(limb_shift, zero, hi)),
+        ])
+    }
+
+    /// Rotates `x` left by `shift`, which must be less than 64.
+    pub fn rotl_u64(&mut self, x: U64Target, shift: Target) -> U64Target {
+        let shift_bits = self.split_le(shift, 6);
+        let (lo_low, lo_high) = self.shift_limb(x.lo(), &shift_bits[..5], false);
+        let (hi_low, hi_high) = self.shift_limb(x.hi(), &shift_bits[..5], false);
+        let lo = self.add(lo_low.0, hi_high.0);
+        let hi = self.add(hi_low.0, lo_high.0);
+        self.swap_limbs_if(shift_bits[5], lo, hi)
+    }
+
+    /// Rotates `x` right by `shift`, which must be less than 64.
+    pub fn rotr_u64(&mut self, x: U64Target, shift: Target) -> U64Target {
+        let shift_bits = self.split_le(shift, 6);
+        let (lo_low, lo_high) = self.shift_limb(x.lo(), &shift_bits[..5], true);
+        let (hi_low, hi_high) = self.shift_limb(x.hi(), &shift_bits[..5], true);
+        let lo = self.add(lo_high.0, hi_low.0);
+        let hi = self.add(hi_high.0, lo_low.0);
+        self.swap_limbs_if(shift_bits[5], lo, hi)
+    }
+
+    /// Multiplies a limb by `2^t`, or by `2^(32 - t)` for a right shift, where `t` is given by its
+    /// 5 little-endian bits, and returns the low and high halves of the product.
+    fn shift_limb(
+        &mut self,
+        x: U32Target,
+        shift_bits: &[BoolTarget],
+        right: bool,
+    ) -> (U32Target, U32Target) {
+        debug_assert_eq!(shift_bits.len(), 5);
+        let table = (0..32)
+            .map(|t| {
+                let log = if right { 32 - t } else { t };
+                self.constant(F::from_canonical_u64(1 << log))
+            })
+            .collect();
+        let shift = self.le_bits_to_target(shift_bits);
+        let multiplier = self.add_virtual_target();
+        self.random_access(shift, multiplier, table);
+        // The multiplier is `2^32` for a right shift by zero, which is not a valid `U32Target`, but
+        // the product still fits in 64 bits, which is all that `U32ArithmeticGate` relies on.
+        self.mul_u32(x, U32Target(multiplier))
+    }
+
+    fn swap_limbs_if(&mut self, b: BoolTarget, lo: Target, hi: Target) -> U64Target {
+        U64Target([
+            U32Target(self.select(b, hi, lo)),
+            U32Target(self.select(b, lo, hi)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use rand::{thread_rng, Rng};
+
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_shift_u32() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        for shift in [0, 1, 13, 31] {
+            let x = rng.gen::<u32>();
+            let x_target = builder.constant_u32(x);
+            let shift_target = builder.constant(F::from_canonical_u32(shift));
+
+            let results = [
+                builder.shl_u32(x_target, shift_target),
+                builder.shr_u32(x_target, shift_target),
+                builder.rotl_u32(x_target, shift_target),
+                builder.rotr_u32(x_target, shift_target),
+            ];
+            let expected = [
+                x << shift,
+                x >> shift,
+                x.rotate_left(shift),
+                x.rotate_right(shift),
+            ];
+            for (r, e) in results.into_iter().zip(expected) {
+                let e = builder.constant_u32(e);
+                builder.connect_u32(r, e);
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_shift_u64() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        for shift in [0, 5, 31, 32, 47, 63] {
+            let x = rng.gen::<u64>();
+            let x_target = builder.constant_u64(x);
+            let shift_target = builder.constant(F::from_canonical_u32(shift));
+
+            let results = [
+                builder.shl_u64(x_target, shift_target),
+                builder.shr_u64(x_target, shift_target),
+                builder.rotl_u64(x_target, shift_target),
+                builder.rotr_u64(x_target, shift_target),
+            ];
+            let expected = [
+                x << shift,
+                x >> shift,
+                x.rotate_left(shift),
+                x.rotate_right(shift),
+            ];
+            for (r, e) in results.into_iter().zip(expected) {
+                let e = builder.constant_u64(e);
+                builder.connect_u64(r, e);
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,132 @@
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::RichField;
+use crate::hash::tip5::{TIP5_LOOKUP_TABLE, TIP5_MONTGOMERY_R};
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Applies the Tip5 lookup table to the bytes of the Montgomery representation of `x`, as in
+    /// `tip5_split_and_lookup`.
+    ///
+    /// Rather than looking up each byte in a table, this uses the algebraic description of the
+    /// table: `y = T(x)` iff `(x + 1)^3 = (y + 1) + 257 k` for a byte `y` and some `k < 2^16`. The
+    /// bytes of the input are checked to be those of its canonical representation.
+    pub fn tip5_split_and_lookup(&mut self, x: Target) -> Target {
+        let r = F::from_canonical_u64(TIP5_MONTGOMERY_R);
+        let montgomery = self.mul_const(r, x);
+        let input_bits = self.split_le(montgomery, 64);
+
+        // The bits are those of the canonical representation unless the high half is `2^32 - 1`
+        // and the low half is nonzero.
+        let mut high_all_ones = input_bits[32];
+        for &bit in &input_bits[33..] {
+            high_all_ones = self.and(high_all_ones, bit);
+        }
+        let low = self.le_bits_to_target(&input_bits[..32]);
+        let non_canonical = self.mul(high_all_ones.target, low);
+        self.assert_zero(non_canonical);
+
+        let output = self.add_virtual_target();
+        let quotients = [self.add_virtual_target(), self.add_virtual_target()];
+        self.add_simple_generator(Tip5LookupGenerator {
+            montgomery,
+            output,
+            quotients,
+        });
+        let output_bits = self.split_le(output, 64);
+        let quotient_bits = quotients
+            .into_iter()
+            .flat_map(|q| self.split_le(q, 64))
+            .collect::<Vec<_>>();
+
+        let modulus = F::from_canonical_u32(257);
+        for i in 0..8 {
+            let x_byte = self.le_bits_to_target(&input_bits[8 * i..8 * (i + 1)]);
+            let y_byte = self.le_bits_to_target(&output_bits[8 * i..8 * (i + 1)]);
+            let k = self.le_bits_to_target(&quotient_bits[16 * i..16 * (i + 1)]);
+            let x_plus_one = self.add_const(x_byte, F::ONE);
+            let lhs = self.cube(x_plus_one);
+            let rhs = self.mul_const_add(modulus, k, y_byte);
+            let rhs = self.add_const(rhs, F::ONE);
+            self.connect(lhs, rhs);
+        }
+
+        self.mul_const(r.inverse(), output)
+    }
+}
+
+/// Computes the Montgomery representation of the output of the lookup, along with the quotients
+/// `k` for each byte, packed into 16-bit limbs, four to each of `quotients`.
+#[derive(Debug)]
+struct Tip5LookupGenerator {
+    montgomery: Target,
+    output: Target,
+    quotients: [Target; 2],
+}
+
+impl<F: RichField> SimpleGenerator<F> for Tip5LookupGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.montgomery]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let input_bytes = witness
+            .get_target(self.montgomery)
+            .to_canonical_u64()
+            .to_le_bytes();
+
+        let mut output = 0;
+        let mut quotients = [0; 2];
+        for (i, &x) in input_bytes.iter().enumerate() {
+            let y = TIP5_LOOKUP_TABLE[x as usize];
+            let x_plus_one = x as u64 + 1;
+            let k = (x_plus_one.pow(3) - (y as u64 + 1)) / 257;
+            output |= (y as u64) << (8 * i);
+            quotients[i / 4] |= k << (16 * (i % 4));
+        }
+
+        out_buffer.set_target(self.output, F::from_canonical_u64(output));
+        for (&target, &q) in self.quotients.iter().zip(&quotients) {
+            out_buffer.set_target(target, F::from_canonical_u64(q));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::hash::tip5::tip5_split_and_lookup;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_tip5_split_and_lookup() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        for x in [F::ZERO, F::ONE, F::NEG_ONE, F::rand()] {
+            let x_target = builder.add_virtual_target();
+            pw.set_target(x_target, x);
+            let y = builder.tip5_split_and_lookup(x_target);
+            let expected = builder.constant(tip5_split_and_lookup(x));
+            builder.connect(y, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,227 @@
+//! Building blocks for circuits verifying the execution of small virtual machines: a program ROM,
+//! program counter transitions, and instruction decoding.
+//!
+//! A program is a list of instruction words, each of which packs bit fields such as an opcode and
+//! its operands, as described by an `InstructionFormat`. Each step of an execution reads the word
+//! at the program counter from the ROM, decodes it, and computes the next program counter.
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// The layout of an instruction word, as the widths of its bit fields, starting from the least
+/// significant bits.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InstructionFormat {
+    pub field_bits: Vec<usize>,
+}
+
+impl InstructionFormat {
+    pub fn new(field_bits: Vec<usize>) -> Self {
+        let format = Self { field_bits };
+        assert!(
+            format.num_bits() < 64,
+            "Instruction words must fit in a field element"
+        );
+        format
+    }
+
+    /// The total number of bits of an instruction word.
+    pub fn num_bits(&self) -> usize {
+        self.field_bits.iter().sum()
+    }
+
+    /// Packs the given field values into an instruction word.
+    pub fn encode(&self, fields: &[u64]) -> u64 {
+        assert_eq!(
+            fields.len(),
+            self.field_bits.len(),
+            "Wrong number of fields"
+        );
+        let mut word = 0;
+        let mut shift = 0;
+        for (&field, &bits) in fields.iter().zip(&self.field_bits) {
+            assert!(
+                field < 1 << bits,
+                "Field value {} doesn't fit in {} bits",
+                field,
+                bits
+            );
+            word |= field << shift;
+            shift += bits;
+        }
+        word
+    }
+}
+
+/// A program ROM, holding one instruction word per address.
+#[derive(Clone, Debug)]
+pub struct RomTarget {
+    pub words: Vec<Target>,
+}
+
+impl RomTarget {
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+/// The commitment to a program, as computed by `CircuitBuilder::rom_commitment`.
+pub fn rom_commitment<F: RichField, H: AlgebraicHasher<F>>(program: &[u64]) -> HashOut<F> {
+    let words = program
+        .iter()
+        .map(|&w| F::from_canonical_u64(w))
+        .collect::<Vec<_>>();
+    H::hash_no_pad(&words)
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// A ROM holding a program which is fixed in the circuit.
+    pub fn constant_rom(&mut self, program: &[u64]) -> RomTarget {
+        RomTarget {
+            words: program
+                .iter()
+                .map(|&w| self.constant(F::from_canonical_u64(w)))
+                .collect(),
+        }
+    }
+
+    /// A ROM of `len` words holding a program given in the witness, which can be bound to a
+    /// program by `rom_commitment`.
+    pub fn add_virtual_rom(&mut self, len: usize) -> RomTarget {
+        RomTarget {
+            words: self.add_virtual_targets(len),
+        }
+    }
+
+    /// The hash of the words of `rom`, matching `rom_commitment` out of circuit.
+    pub fn rom_commitment<H: AlgebraicHasher<F>>(&mut self, rom: &RomTarget) -> HashOutTarget {
+        self.hash_n_to_hash_no_pad::<H>(rom.words.clone())
+    }
+
+    /// Reads the instruction word at address `pc`.
+    /// Note: `pc` is not range-checked against the length of the ROM; see `random_access`.
+    pub fn rom_read(&mut self, rom: &RomTarget, pc: Target) -> Target {
+        let word = self.add_virtual_target();
+        self.random_access(pc, word, rom.words.clone());
+        word
+    }
+
+    /// Splits an instruction word into the values of its fields. This range-checks every field, so
+    /// it also checks that `word` has no bits beyond those of the format.
+    pub fn decode_instruction(&mut self, word: Target, format: &InstructionFormat) -> Vec<Target> {
+        let bits = self.split_le(word, format.num_bits());
+        let mut fields = Vec::with_capacity(format.field_bits.len());
+        let mut start = 0;
+        for &num_bits in &format.field_bits {
+            fields.push(self.le_sum(bits[start..start + num_bits].iter()));
+            start += num_bits;
+        }
+        fields
+    }
+
+    /// Returns selectors for `opcode`, i.e. a bit for each of the `num_opcodes` opcodes which is set
+    /// iff it is the given one. Fails to prove unless `opcode < num_opcodes`.
+    pub fn opcode_selectors(&mut self, opcode: Target, num_opcodes: usize) -> Vec<BoolTarget> {
+        self.one_hot(opcode, num_opcodes)
+    }
+
+    /// The program counter after an instruction at `pc`, which jumps to `jump_target` if `jump` is
+    /// set and otherwise moves on to the next instruction.
+    pub fn next_pc(&mut self, pc: Target, jump: BoolTarget, jump_target: Target) -> Target {
+        let one = self.one();
+        let zero = self.zero();
+        let incremented = self.add(pc, one);
+        self.select_mul_add(jump, jump_target, one, zero, incremented)
+    }
+}
+
+/// Sets the witness for a ROM target.
+pub fn set_rom_target<F: RichField, W: Witness<F>>(
+    witness: &mut W,
+    rom: &RomTarget,
+    program: &[u64],
+) {
+    assert_eq!(rom.len(), program.len(), "Wrong program length");
+    for (&t, &w) in rom.words.iter().zip(program) {
+        witness.set_target(t, F::from_canonical_u64(w));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::gadgets::vm::{rom_commitment, set_rom_target, InstructionFormat};
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    // A machine with one register and three instructions.
+    const ADD: u64 = 0;
+    const JUMP: u64 = 1;
+    const HALT: u64 = 2;
+
+    /// Runs a program which skips over an instruction, and checks that it halts at the expected
+    /// address with the expected register value.
+    #[test]
+    fn test_vm_execution() -> Result<()> {
+        let format = InstructionFormat::new(vec![2, 8]);
+        let program = [
+            format.encode(&[ADD, 5]),
+            format.encode(&[JUMP, 3]),
+            format.encode(&[ADD, 100]),
+            format.encode(&[ADD, 2]),
+            format.encode(&[HALT, 0]),
+        ];
+        let num_steps = 6;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let rom = builder.add_virtual_rom(program.len());
+        set_rom_target(&mut pw, &rom, &program);
+        let commitment = builder.rom_commitment::<PoseidonHash>(&rom);
+        let expected_commitment =
+            builder.constant_hash(rom_commitment::<F, PoseidonHash>(&program));
+        builder.connect_hashes(commitment, expected_commitment);
+
+        let mut pc = builder.zero();
+        let mut register = builder.zero();
+        for _ in 0..num_steps {
+            let word = builder.rom_read(&rom, pc);
+            let fields = builder.decode_instruction(word, &format);
+            let (opcode, operand) = (fields[0], fields[1]);
+            let selectors = builder.opcode_selectors(opcode, 3);
+
+            register = builder.mul_add(selectors[ADD as usize].target, operand, register);
+            let next = builder.next_pc(pc, selectors[JUMP as usize], operand);
+            pc = builder.select(selectors[HALT as usize], pc, next);
+        }
+
+        let expected_pc = builder.constant(F::from_canonical_u64(4));
+        builder.connect(pc, expected_pc);
+        let expected_register = builder.constant(F::from_canonical_u64(7));
+        builder.connect(register, expected_register);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
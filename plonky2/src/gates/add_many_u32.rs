@@ -18,7 +18,7 @@ use crate::plonk::circuit_data::CircuitConfig;
 use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
 
 const LOG2_MAX_NUM_ADDENDS: usize = 4;
-const MAX_NUM_ADDENDS: usize = 16;
+pub(crate) const MAX_NUM_ADDENDS: usize = 16;
 
 /// A gate to perform addition on `num_addends` different 32-bit values, plus a small carry
 #[derive(Copy, Clone, Debug)]
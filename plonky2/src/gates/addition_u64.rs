@@ -0,0 +1,496 @@
+use std::marker::PhantomData;
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+use plonky2_field::packed_field::PackedField;
+
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CircuitConfig;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+
+/// A gate to perform an addition on 64-bit values, each given as two 32-bit limbs: given `x`, `y`
+/// and a `carry` bit, it returns `x + y + carry` modulo `2^64`, along with the carry bit out of the
+/// high limb. The result limbs are range-checked and the carries constrained to be bits, but the
+/// inputs are not range-checked.
+#[derive(Copy, Clone, Debug)]
+pub struct U64AdditionGate<F: RichField + Extendable<D>, const D: usize> {
+    pub num_ops: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> U64AdditionGate<F, D> {
+    pub fn new_from_config(config: &CircuitConfig) -> Self {
+        Self {
+            num_ops: Self::num_ops(config),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn num_ops(config: &CircuitConfig) -> usize {
+        let wires_per_op = 8 + 1 + 2 * Self::num_limbs();
+        let routed_wires_per_op = 8;
+        (config.num_wires / wires_per_op).min(config.num_routed_wires / routed_wires_per_op)
+    }
+
+    pub fn wire_ith_input_x_low(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * i
+    }
+    pub fn wire_ith_input_x_high(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * i + 1
+    }
+    pub fn wire_ith_input_y_low(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * i + 2
+    }
+    pub fn wire_ith_input_y_high(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * i + 3
+    }
+    pub fn wire_ith_input_carry(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * i + 4
+    }
+
+    pub fn wire_ith_output_low(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * i + 5
+    }
+    pub fn wire_ith_output_high(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * i + 6
+    }
+    pub fn wire_ith_output_carry(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * i + 7
+    }
+
+    pub fn limb_bits() -> usize {
+        2
+    }
+    // We have limbs for the 32 bits of each half of the result.
+    pub fn num_limbs() -> usize {
+        32 / Self::limb_bits()
+    }
+
+    /// The carry from the low limb into the high limb.
+    pub fn wire_ith_middle_carry(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        8 * self.num_ops + (1 + 2 * Self::num_limbs()) * i
+    }
+
+    /// The `j`th limb of the result, where the first `num_limbs` limbs are those of the low half.
+    pub fn wire_ith_output_jth_limb(&self, i: usize, j: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        debug_assert!(j < 2 * Self::num_limbs());
+        8 * self.num_ops + (1 + 2 * Self::num_limbs()) * i + 1 + j
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for U64AdditionGate<F, D> {
+    fn id(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+        for i in 0..self.num_ops {
+            let x_low = vars.local_wires[self.wire_ith_input_x_low(i)];
+            let x_high = vars.local_wires[self.wire_ith_input_x_high(i)];
+            let y_low = vars.local_wires[self.wire_ith_input_y_low(i)];
+            let y_high = vars.local_wires[self.wire_ith_input_y_high(i)];
+            let input_carry = vars.local_wires[self.wire_ith_input_carry(i)];
+
+            let output_low = vars.local_wires[self.wire_ith_output_low(i)];
+            let output_high = vars.local_wires[self.wire_ith_output_high(i)];
+            let output_carry = vars.local_wires[self.wire_ith_output_carry(i)];
+            let middle_carry = vars.local_wires[self.wire_ith_middle_carry(i)];
+
+            let base = F::Extension::from_canonical_u64(1 << 32u64);
+            constraints.push(x_low + y_low + input_carry - (output_low + base * middle_carry));
+            constraints.push(x_high + y_high + middle_carry - (output_high + base * output_carry));
+
+            // Range-check the carries to be one bit.
+            constraints.push(middle_carry * (F::Extension::ONE - middle_carry));
+            constraints.push(output_carry * (F::Extension::ONE - output_carry));
+
+            // Range-check both halves of the result to be at most 32 bits.
+            let mut combined_low_limbs = F::Extension::ZERO;
+            let mut combined_high_limbs = F::Extension::ZERO;
+            let limb_base = F::Extension::from_canonical_u64(1u64 << Self::limb_bits());
+            for j in (0..2 * Self::num_limbs()).rev() {
+                let this_limb = vars.local_wires[self.wire_ith_output_jth_limb(i, j)];
+                let max_limb = 1 << Self::limb_bits();
+                let product = (0..max_limb)
+                    .map(|x| this_limb - F::Extension::from_canonical_usize(x))
+                    .product();
+                constraints.push(product);
+
+                if j < Self::num_limbs() {
+                    combined_low_limbs = limb_base * combined_low_limbs + this_limb;
+                } else {
+                    combined_high_limbs = limb_base * combined_high_limbs + this_limb;
+                }
+            }
+            constraints.push(combined_low_limbs - output_low);
+            constraints.push(combined_high_limbs - output_high);
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_recursively(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        for i in 0..self.num_ops {
+            let x_low = vars.local_wires[self.wire_ith_input_x_low(i)];
+            let x_high = vars.local_wires[self.wire_ith_input_x_high(i)];
+            let y_low = vars.local_wires[self.wire_ith_input_y_low(i)];
+            let y_high = vars.local_wires[self.wire_ith_input_y_high(i)];
+            let input_carry = vars.local_wires[self.wire_ith_input_carry(i)];
+
+            let output_low = vars.local_wires[self.wire_ith_output_low(i)];
+            let output_high = vars.local_wires[self.wire_ith_output_high(i)];
+            let output_carry = vars.local_wires[self.wire_ith_output_carry(i)];
+            let middle_carry = vars.local_wires[self.wire_ith_middle_carry(i)];
+
+            let base: F::Extension = F::from_canonical_u64(1 << 32u64).into();
+            let base_target = builder.constant_extension(base);
+
+            let computed_low = builder.add_many_extension(&[x_low, y_low, input_carry]);
+            let combined_low = builder.mul_add_extension(middle_carry, base_target, output_low);
+            constraints.push(builder.sub_extension(computed_low, combined_low));
+
+            let computed_high = builder.add_many_extension(&[x_high, y_high, middle_carry]);
+            let combined_high = builder.mul_add_extension(output_carry, base_target, output_high);
+            constraints.push(builder.sub_extension(computed_high, combined_high));
+
+            // Range-check the carries to be one bit.
+            let one = builder.one_extension();
+            let not_middle_carry = builder.sub_extension(one, middle_carry);
+            constraints.push(builder.mul_extension(middle_carry, not_middle_carry));
+            let not_output_carry = builder.sub_extension(one, output_carry);
+            constraints.push(builder.mul_extension(output_carry, not_output_carry));
+
+            // Range-check both halves of the result to be at most 32 bits.
+            let mut combined_low_limbs = builder.zero_extension();
+            let mut combined_high_limbs = builder.zero_extension();
+            let limb_base = builder
+                .constant_extension(F::Extension::from_canonical_u64(1u64 << Self::limb_bits()));
+            for j in (0..2 * Self::num_limbs()).rev() {
+                let this_limb = vars.local_wires[self.wire_ith_output_jth_limb(i, j)];
+                let max_limb = 1 << Self::limb_bits();
+
+                let mut product = builder.one_extension();
+                for x in 0..max_limb {
+                    let x_target =
+                        builder.constant_extension(F::Extension::from_canonical_usize(x));
+                    let diff = builder.sub_extension(this_limb, x_target);
+                    product = builder.mul_extension(product, diff);
+                }
+                constraints.push(product);
+
+                if j < Self::num_limbs() {
+                    combined_low_limbs =
+                        builder.mul_add_extension(limb_base, combined_low_limbs, this_limb);
+                } else {
+                    combined_high_limbs =
+                        builder.mul_add_extension(limb_base, combined_high_limbs, this_limb);
+                }
+            }
+            constraints.push(builder.sub_extension(combined_low_limbs, output_low));
+            constraints.push(builder.sub_extension(combined_high_limbs, output_high));
+        }
+
+        constraints
+    }
+
+    fn generators(
+        &self,
+        gate_index: usize,
+        _local_constants: &[F],
+    ) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        (0..self.num_ops)
+            .map(|i| {
+                let g: Box<dyn WitnessGenerator<F>> = Box::new(
+                    U64AdditionGenerator {
+                        gate: *self,
+                        gate_index,
+                        i,
+                        _phantom: PhantomData,
+                    }
+                    .adapter(),
+                );
+                g
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * (8 + 1 + 2 * Self::num_limbs())
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        1 << Self::limb_bits()
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops * (6 + 2 * Self::num_limbs())
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for U64AdditionGate<F, D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_ops {
+            let x_low = vars.local_wires[self.wire_ith_input_x_low(i)];
+            let x_high = vars.local_wires[self.wire_ith_input_x_high(i)];
+            let y_low = vars.local_wires[self.wire_ith_input_y_low(i)];
+            let y_high = vars.local_wires[self.wire_ith_input_y_high(i)];
+            let input_carry = vars.local_wires[self.wire_ith_input_carry(i)];
+
+            let output_low = vars.local_wires[self.wire_ith_output_low(i)];
+            let output_high = vars.local_wires[self.wire_ith_output_high(i)];
+            let output_carry = vars.local_wires[self.wire_ith_output_carry(i)];
+            let middle_carry = vars.local_wires[self.wire_ith_middle_carry(i)];
+
+            let base = F::from_canonical_u64(1 << 32u64);
+            yield_constr.one(x_low + y_low + input_carry - (output_low + middle_carry * base));
+            yield_constr.one(x_high + y_high + middle_carry - (output_high + output_carry * base));
+
+            // Range-check the carries to be one bit.
+            yield_constr.one(middle_carry * (P::ONES - middle_carry));
+            yield_constr.one(output_carry * (P::ONES - output_carry));
+
+            // Range-check both halves of the result to be at most 32 bits.
+            let mut combined_low_limbs = P::ZEROS;
+            let mut combined_high_limbs = P::ZEROS;
+            let limb_base = F::from_canonical_u64(1u64 << Self::limb_bits());
+            for j in (0..2 * Self::num_limbs()).rev() {
+                let this_limb = vars.local_wires[self.wire_ith_output_jth_limb(i, j)];
+                let max_limb = 1 << Self::limb_bits();
+                let product = (0..max_limb)
+                    .map(|x| this_limb - F::from_canonical_usize(x))
+                    .product();
+                yield_constr.one(product);
+
+                if j < Self::num_limbs() {
+                    combined_low_limbs = combined_low_limbs * limb_base + this_limb;
+                } else {
+                    combined_high_limbs = combined_high_limbs * limb_base + this_limb;
+                }
+            }
+            yield_constr.one(combined_low_limbs - output_low);
+            yield_constr.one(combined_high_limbs - output_high);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct U64AdditionGenerator<F: RichField + Extendable<D>, const D: usize> {
+    gate: U64AdditionGate<F, D>,
+    gate_index: usize,
+    i: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
+    for U64AdditionGenerator<F, D>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        let local_target = |input| Target::wire(self.gate_index, input);
+
+        vec![
+            local_target(self.gate.wire_ith_input_x_low(self.i)),
+            local_target(self.gate.wire_ith_input_x_high(self.i)),
+            local_target(self.gate.wire_ith_input_y_low(self.i)),
+            local_target(self.gate.wire_ith_input_y_high(self.i)),
+            local_target(self.gate.wire_ith_input_carry(self.i)),
+        ]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_wire = |input| Wire {
+            gate: self.gate_index,
+            input,
+        };
+
+        let get_local_u64 = |input| witness.get_wire(local_wire(input)).to_canonical_u64();
+
+        let x_low = get_local_u64(self.gate.wire_ith_input_x_low(self.i));
+        let x_high = get_local_u64(self.gate.wire_ith_input_x_high(self.i));
+        let y_low = get_local_u64(self.gate.wire_ith_input_y_low(self.i));
+        let y_high = get_local_u64(self.gate.wire_ith_input_y_high(self.i));
+        let input_carry = get_local_u64(self.gate.wire_ith_input_carry(self.i));
+
+        let sum_low = x_low + y_low + input_carry;
+        let (output_low, middle_carry) = (sum_low & 0xFFFFFFFF, sum_low >> 32);
+        let sum_high = x_high + y_high + middle_carry;
+        let (output_high, output_carry) = (sum_high & 0xFFFFFFFF, sum_high >> 32);
+
+        let outputs = [
+            (self.gate.wire_ith_output_low(self.i), output_low),
+            (self.gate.wire_ith_output_high(self.i), output_high),
+            (self.gate.wire_ith_output_carry(self.i), output_carry),
+            (self.gate.wire_ith_middle_carry(self.i), middle_carry),
+        ];
+        for (wire, value) in outputs {
+            out_buffer.set_wire(local_wire(wire), F::from_canonical_u64(value));
+        }
+
+        let num_limbs = U64AdditionGate::<F, D>::num_limbs();
+        let limb_base = 1 << U64AdditionGate::<F, D>::limb_bits();
+        let output_limbs = [output_low, output_high].into_iter().flat_map(|half| {
+            (0..num_limbs).scan(half, |acc, _| {
+                let tmp = *acc % limb_base;
+                *acc /= limb_base;
+                Some(F::from_canonical_u64(tmp))
+            })
+        });
+
+        for (j, limb) in output_limbs.enumerate() {
+            let wire = local_wire(self.gate.wire_ith_output_jth_limb(self.i, j));
+            out_buffer.set_wire(wire, limb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+    use rand::Rng;
+
+    use crate::gates::addition_u64::U64AdditionGate;
+    use crate::gates::gate::Gate;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::hash::hash_types::HashOut;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::vars::EvaluationVars;
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(U64AdditionGate::<GoldilocksField, 4> {
+            num_ops: 3,
+            _phantom: PhantomData,
+        })
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(U64AdditionGate::<GoldilocksField, D> {
+            num_ops: 3,
+            _phantom: PhantomData,
+        })
+    }
+
+    #[test]
+    fn test_gate_constraint() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+        const NUM_U64_ADDITION_OPS: usize = 3;
+
+        fn get_wires(inputs: &[(u64, u64, bool)]) -> Vec<FF> {
+            let mut v0 = Vec::new();
+            let mut v1 = Vec::new();
+
+            let num_limbs = U64AdditionGate::<F, D>::num_limbs();
+            let limb_base = 1 << U64AdditionGate::<F, D>::limb_bits();
+            for &(x, y, carry) in inputs {
+                let (sum, overflow_0) = x.overflowing_add(y);
+                let (sum, overflow_1) = sum.overflowing_add(carry as u64);
+                let middle_carry = ((x as u32 as u64) + (y as u32 as u64) + carry as u64) >> 32;
+
+                for value in [x as u32, (x >> 32) as u32, y as u32, (y >> 32) as u32] {
+                    v0.push(value as u64);
+                }
+                v0.extend([
+                    carry as u64,
+                    sum & 0xFFFFFFFF,
+                    sum >> 32,
+                    (overflow_0 || overflow_1) as u64,
+                ]);
+
+                v1.push(middle_carry);
+                let mut rest = sum;
+                for _ in 0..2 * num_limbs {
+                    v1.push(rest % limb_base);
+                    rest /= limb_base;
+                }
+            }
+
+            v0.iter()
+                .chain(v1.iter())
+                .map(|&x| F::from_canonical_u64(x).into())
+                .collect()
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut inputs: Vec<_> = (0..NUM_U64_ADDITION_OPS)
+            .map(|_| (rng.gen::<u64>(), rng.gen::<u64>(), rng.gen::<bool>()))
+            .collect();
+        inputs[0] = (u64::MAX, 0, true);
+
+        let gate = U64AdditionGate::<F, D> {
+            num_ops: NUM_U64_ADDITION_OPS,
+            _phantom: PhantomData,
+        };
+
+        let vars = EvaluationVars {
+            local_constants: &[],
+            local_wires: &get_wires(&inputs),
+            public_inputs_hash: &HashOut::rand(),
+        };
+
+        assert!(
+            gate.eval_unfiltered(vars).iter().all(|x| x.is_zero()),
+            "Gate constraints are not satisfied."
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::marker::PhantomData;
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::packed_field::PackedField;
+
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CircuitConfig;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+
+/// A gate which performs a conditional multiply-add, i.e. `result = if s { a b + c } else { d }`,
+/// computed as `s (a b + c - d) + d`. The selector `s` is assumed to be boolean; the gate doesn't
+/// check it. If the config supports enough routed wires, it can support several such operations in
+/// one gate.
+#[derive(Debug)]
+pub struct ConditionalArithmeticGate {
+    /// Number of conditional operations performed by a conditional arithmetic gate.
+    pub num_ops: usize,
+}
+
+impl ConditionalArithmeticGate {
+    pub fn new_from_config(config: &CircuitConfig) -> Self {
+        Self {
+            num_ops: Self::num_ops(config),
+        }
+    }
+
+    /// Determine the maximum number of operations that can fit in one gate for the given config.
+    pub(crate) fn num_ops(config: &CircuitConfig) -> usize {
+        let wires_per_op = 6;
+        config.num_routed_wires / wires_per_op
+    }
+
+    pub fn wire_ith_selector(i: usize) -> usize {
+        6 * i
+    }
+    pub fn wire_ith_multiplicand_0(i: usize) -> usize {
+        6 * i + 1
+    }
+    pub fn wire_ith_multiplicand_1(i: usize) -> usize {
+        6 * i + 2
+    }
+    pub fn wire_ith_addend(i: usize) -> usize {
+        6 * i + 3
+    }
+    pub fn wire_ith_alternative(i: usize) -> usize {
+        6 * i + 4
+    }
+    pub fn wire_ith_output(i: usize) -> usize {
+        6 * i + 5
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for ConditionalArithmeticGate {
+    fn id(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::new();
+        for i in 0..self.num_ops {
+            let selector = vars.local_wires[Self::wire_ith_selector(i)];
+            let multiplicand_0 = vars.local_wires[Self::wire_ith_multiplicand_0(i)];
+            let multiplicand_1 = vars.local_wires[Self::wire_ith_multiplicand_1(i)];
+            let addend = vars.local_wires[Self::wire_ith_addend(i)];
+            let alternative = vars.local_wires[Self::wire_ith_alternative(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+            let computed_output =
+                selector * (multiplicand_0 * multiplicand_1 + addend - alternative) + alternative;
+
+            constraints.push(output - computed_output);
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_recursively(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::new();
+        for i in 0..self.num_ops {
+            let selector = vars.local_wires[Self::wire_ith_selector(i)];
+            let multiplicand_0 = vars.local_wires[Self::wire_ith_multiplicand_0(i)];
+            let multiplicand_1 = vars.local_wires[Self::wire_ith_multiplicand_1(i)];
+            let addend = vars.local_wires[Self::wire_ith_addend(i)];
+            let alternative = vars.local_wires[Self::wire_ith_alternative(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+            let computed_output = {
+                let product = builder.mul_add_extension(multiplicand_0, multiplicand_1, addend);
+                let diff = builder.sub_extension(product, alternative);
+                builder.mul_add_extension(selector, diff, alternative)
+            };
+
+            let diff = builder.sub_extension(output, computed_output);
+            constraints.push(diff);
+        }
+
+        constraints
+    }
+
+    fn generators(
+        &self,
+        gate_index: usize,
+        _local_constants: &[F],
+    ) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        (0..self.num_ops)
+            .map(|i| {
+                let g: Box<dyn WitnessGenerator<F>> = Box::new(
+                    ConditionalArithmeticGenerator::<F, D> {
+                        gate_index,
+                        i,
+                        _phantom: PhantomData,
+                    }
+                    .adapter(),
+                );
+                g
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * 6
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        3
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for ConditionalArithmeticGate
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_ops {
+            let selector = vars.local_wires[Self::wire_ith_selector(i)];
+            let multiplicand_0 = vars.local_wires[Self::wire_ith_multiplicand_0(i)];
+            let multiplicand_1 = vars.local_wires[Self::wire_ith_multiplicand_1(i)];
+            let addend = vars.local_wires[Self::wire_ith_addend(i)];
+            let alternative = vars.local_wires[Self::wire_ith_alternative(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+            let computed_output =
+                selector * (multiplicand_0 * multiplicand_1 + addend - alternative) + alternative;
+
+            yield_constr.one(output - computed_output);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ConditionalArithmeticGenerator<F: RichField + Extendable<D>, const D: usize> {
+    gate_index: usize,
+    i: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
+    for ConditionalArithmeticGenerator<F, D>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        [
+            ConditionalArithmeticGate::wire_ith_selector(self.i),
+            ConditionalArithmeticGate::wire_ith_multiplicand_0(self.i),
+            ConditionalArithmeticGate::wire_ith_multiplicand_1(self.i),
+            ConditionalArithmeticGate::wire_ith_addend(self.i),
+            ConditionalArithmeticGate::wire_ith_alternative(self.i),
+        ]
+        .iter()
+        .map(|&i| Target::wire(self.gate_index, i))
+        .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let get_wire =
+            |wire: usize| -> F { witness.get_target(Target::wire(self.gate_index, wire)) };
+
+        let selector = get_wire(ConditionalArithmeticGate::wire_ith_selector(self.i));
+        let multiplicand_0 = get_wire(ConditionalArithmeticGate::wire_ith_multiplicand_0(self.i));
+        let multiplicand_1 = get_wire(ConditionalArithmeticGate::wire_ith_multiplicand_1(self.i));
+        let addend = get_wire(ConditionalArithmeticGate::wire_ith_addend(self.i));
+        let alternative = get_wire(ConditionalArithmeticGate::wire_ith_alternative(self.i));
+
+        let output_target = Target::wire(
+            self.gate_index,
+            ConditionalArithmeticGate::wire_ith_output(self.i),
+        );
+
+        let computed_output =
+            selector * (multiplicand_0 * multiplicand_1 + addend - alternative) + alternative;
+
+        out_buffer.set_target(output_target, computed_output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::gates::conditional_arithmetic::ConditionalArithmeticGate;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn low_degree() {
+        let gate =
+            ConditionalArithmeticGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_low_degree::<GoldilocksField, _, 4>(gate);
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate =
+            ConditionalArithmeticGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+}
@@ -0,0 +1,542 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::packed_field::PackedField;
+
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+
+/// The arithmetic a `GateDefinition` is written in. Each implementation evaluates the same
+/// expressions in a different setting: over the extension field, over packed base field elements,
+/// in a recursive circuit, or symbolically to find their degree.
+pub trait GateAlgebra<F: RichField + Extendable<D>, const D: usize> {
+    type Value: Copy;
+
+    /// The `index`th wire of the gate's row.
+    fn wire(&mut self, index: usize) -> Self::Value;
+
+    /// The `index`th constant of the gate's row.
+    fn local_constant(&mut self, index: usize) -> Self::Value;
+
+    /// A fixed field element.
+    fn constant(&mut self, c: F) -> Self::Value;
+
+    fn add(&mut self, x: Self::Value, y: Self::Value) -> Self::Value;
+
+    fn sub(&mut self, x: Self::Value, y: Self::Value) -> Self::Value;
+
+    fn mul(&mut self, x: Self::Value, y: Self::Value) -> Self::Value;
+
+    /// Computes `x * y + z`.
+    fn mul_add(&mut self, x: Self::Value, y: Self::Value, z: Self::Value) -> Self::Value {
+        let product = self.mul(x, y);
+        self.add(product, z)
+    }
+
+    fn square(&mut self, x: Self::Value) -> Self::Value {
+        self.mul(x, x)
+    }
+}
+
+/// A gate specified once, by its wire layout, its constraints and how to generate its witness,
+/// from which `CustomGate` derives all the evaluation forms required by `Gate`, its witness
+/// generator, its number of constraints and its degree.
+pub trait GateDefinition<F: RichField + Extendable<D>, const D: usize>:
+    'static + Clone + Debug + Send + Sync
+{
+    fn num_wires(&self) -> usize;
+
+    fn num_constants(&self) -> usize;
+
+    /// The constraints of the gate, which must all vanish on a valid witness.
+    fn constraints<A: GateAlgebra<F, D>>(&self, a: &mut A) -> Vec<A::Value>;
+
+    /// The wires this gate sets, along with their values. These must be computed from the wires
+    /// which the gate doesn't set, which the generator waits for. Other wires must be set by copy
+    /// constraints. By default, the gate sets no wires.
+    fn witness<A: GateAlgebra<F, D>>(&self, _a: &mut A) -> Vec<(usize, A::Value)> {
+        Vec::new()
+    }
+}
+
+/// The gate defined by a `GateDefinition`.
+#[derive(Clone, Debug)]
+pub struct CustomGate<G> {
+    pub definition: G,
+}
+
+impl<G> CustomGate<G> {
+    pub fn new(definition: G) -> Self {
+        Self { definition }
+    }
+}
+
+impl<F, G, const D: usize> Gate<F, D> for CustomGate<G>
+where
+    F: RichField + Extendable<D>,
+    G: GateDefinition<F, D>,
+{
+    fn id(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        self.definition
+            .constraints(&mut ExtensionEvaluator { vars })
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_recursively(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        self.definition
+            .constraints(&mut CircuitEvaluator { builder, vars })
+    }
+
+    fn generators(
+        &self,
+        gate_index: usize,
+        local_constants: &[F],
+    ) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        let mut recorder = DependencyRecorder::default();
+        if self.definition.witness(&mut recorder).is_empty() {
+            return Vec::new();
+        }
+
+        let mut dependencies = recorder.wires;
+        dependencies.sort_unstable();
+        dependencies.dedup();
+        let gen = CustomGateGenerator::<F, G, D> {
+            definition: self.definition.clone(),
+            gate_index,
+            local_constants: local_constants.to_vec(),
+            dependencies,
+            _phantom: PhantomData,
+        };
+        vec![Box::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        self.definition.num_wires()
+    }
+
+    fn num_constants(&self) -> usize {
+        self.definition.num_constants()
+    }
+
+    fn degree(&self) -> usize {
+        self.definition
+            .constraints(&mut DegreeEvaluator)
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.definition
+            .constraints(&mut DependencyRecorder::default())
+            .len()
+    }
+}
+
+impl<F, G, const D: usize> PackedEvaluableBase<F, D> for CustomGate<G>
+where
+    F: RichField + Extendable<D>,
+    G: GateDefinition<F, D>,
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let constraints = self.definition.constraints(&mut PackedEvaluator { vars });
+        yield_constr.many(constraints);
+    }
+}
+
+struct ExtensionEvaluator<'a, F: RichField + Extendable<D>, const D: usize> {
+    vars: EvaluationVars<'a, F, D>,
+}
+
+impl<'a, F: RichField + Extendable<D>, const D: usize> GateAlgebra<F, D>
+    for ExtensionEvaluator<'a, F, D>
+{
+    type Value = F::Extension;
+
+    fn wire(&mut self, index: usize) -> F::Extension {
+        self.vars.local_wires[index]
+    }
+
+    fn local_constant(&mut self, index: usize) -> F::Extension {
+        self.vars.local_constants[index]
+    }
+
+    fn constant(&mut self, c: F) -> F::Extension {
+        c.into()
+    }
+
+    fn add(&mut self, x: F::Extension, y: F::Extension) -> F::Extension {
+        x + y
+    }
+
+    fn sub(&mut self, x: F::Extension, y: F::Extension) -> F::Extension {
+        x - y
+    }
+
+    fn mul(&mut self, x: F::Extension, y: F::Extension) -> F::Extension {
+        x * y
+    }
+}
+
+struct PackedEvaluator<'a, P: PackedField> {
+    vars: EvaluationVarsBasePacked<'a, P>,
+}
+
+impl<'a, F, P, const D: usize> GateAlgebra<F, D> for PackedEvaluator<'a, P>
+where
+    F: RichField + Extendable<D>,
+    P: PackedField<Scalar = F>,
+{
+    type Value = P;
+
+    fn wire(&mut self, index: usize) -> P {
+        self.vars.local_wires[index]
+    }
+
+    fn local_constant(&mut self, index: usize) -> P {
+        self.vars.local_constants[index]
+    }
+
+    fn constant(&mut self, c: F) -> P {
+        P::from(c)
+    }
+
+    fn add(&mut self, x: P, y: P) -> P {
+        x + y
+    }
+
+    fn sub(&mut self, x: P, y: P) -> P {
+        x - y
+    }
+
+    fn mul(&mut self, x: P, y: P) -> P {
+        x * y
+    }
+}
+
+struct CircuitEvaluator<'a, 'b, F: RichField + Extendable<D>, const D: usize> {
+    builder: &'b mut CircuitBuilder<F, D>,
+    vars: EvaluationTargets<'a, D>,
+}
+
+impl<'a, 'b, F: RichField + Extendable<D>, const D: usize> GateAlgebra<F, D>
+    for CircuitEvaluator<'a, 'b, F, D>
+{
+    type Value = ExtensionTarget<D>;
+
+    fn wire(&mut self, index: usize) -> ExtensionTarget<D> {
+        self.vars.local_wires[index]
+    }
+
+    fn local_constant(&mut self, index: usize) -> ExtensionTarget<D> {
+        self.vars.local_constants[index]
+    }
+
+    fn constant(&mut self, c: F) -> ExtensionTarget<D> {
+        self.builder.constant_extension(c.into())
+    }
+
+    fn add(&mut self, x: ExtensionTarget<D>, y: ExtensionTarget<D>) -> ExtensionTarget<D> {
+        self.builder.add_extension(x, y)
+    }
+
+    fn sub(&mut self, x: ExtensionTarget<D>, y: ExtensionTarget<D>) -> ExtensionTarget<D> {
+        self.builder.sub_extension(x, y)
+    }
+
+    fn mul(&mut self, x: ExtensionTarget<D>, y: ExtensionTarget<D>) -> ExtensionTarget<D> {
+        self.builder.mul_extension(x, y)
+    }
+
+    fn mul_add(
+        &mut self,
+        x: ExtensionTarget<D>,
+        y: ExtensionTarget<D>,
+        z: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        self.builder.mul_add_extension(x, y, z)
+    }
+}
+
+/// Evaluates expressions to their degree as polynomials in the wires and constants.
+struct DegreeEvaluator;
+
+impl<F: RichField + Extendable<D>, const D: usize> GateAlgebra<F, D> for DegreeEvaluator {
+    type Value = usize;
+
+    fn wire(&mut self, _index: usize) -> usize {
+        1
+    }
+
+    fn local_constant(&mut self, _index: usize) -> usize {
+        1
+    }
+
+    fn constant(&mut self, _c: F) -> usize {
+        0
+    }
+
+    fn add(&mut self, x: usize, y: usize) -> usize {
+        x.max(y)
+    }
+
+    fn sub(&mut self, x: usize, y: usize) -> usize {
+        x.max(y)
+    }
+
+    fn mul(&mut self, x: usize, y: usize) -> usize {
+        x + y
+    }
+}
+
+/// Records which wires expressions read, without computing anything.
+#[derive(Default)]
+struct DependencyRecorder {
+    wires: Vec<usize>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> GateAlgebra<F, D> for DependencyRecorder {
+    type Value = ();
+
+    fn wire(&mut self, index: usize) {
+        self.wires.push(index);
+    }
+
+    fn local_constant(&mut self, _index: usize) {}
+
+    fn constant(&mut self, _c: F) {}
+
+    fn add(&mut self, _x: (), _y: ()) {}
+
+    fn sub(&mut self, _x: (), _y: ()) {}
+
+    fn mul(&mut self, _x: (), _y: ()) {}
+}
+
+/// Evaluates expressions on the witness of a gate.
+struct WitnessEvaluator<'a, 'b, F: RichField> {
+    witness: &'a PartitionWitness<'b, F>,
+    gate_index: usize,
+    local_constants: &'a [F],
+}
+
+impl<'a, 'b, F: RichField + Extendable<D>, const D: usize> GateAlgebra<F, D>
+    for WitnessEvaluator<'a, 'b, F>
+{
+    type Value = F;
+
+    fn wire(&mut self, index: usize) -> F {
+        self.witness.get_wire(Wire {
+            gate: self.gate_index,
+            input: index,
+        })
+    }
+
+    fn local_constant(&mut self, index: usize) -> F {
+        self.local_constants[index]
+    }
+
+    fn constant(&mut self, c: F) -> F {
+        c
+    }
+
+    fn add(&mut self, x: F, y: F) -> F {
+        x + y
+    }
+
+    fn sub(&mut self, x: F, y: F) -> F {
+        x - y
+    }
+
+    fn mul(&mut self, x: F, y: F) -> F {
+        x * y
+    }
+}
+
+#[derive(Debug)]
+struct CustomGateGenerator<F: RichField + Extendable<D>, G: GateDefinition<F, D>, const D: usize> {
+    definition: G,
+    gate_index: usize,
+    local_constants: Vec<F>,
+    dependencies: Vec<usize>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F, G, const D: usize> SimpleGenerator<F> for CustomGateGenerator<F, G, D>
+where
+    F: RichField + Extendable<D>,
+    G: GateDefinition<F, D>,
+{
+    fn dependencies(&self) -> Vec<Target> {
+        self.dependencies
+            .iter()
+            .map(|&input| Target::wire(self.gate_index, input))
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let mut evaluator = WitnessEvaluator {
+            witness,
+            gate_index: self.gate_index,
+            local_constants: &self.local_constants,
+        };
+        for (input, value) in self.definition.witness(&mut evaluator) {
+            out_buffer.set_wire(
+                Wire {
+                    gate: self.gate_index,
+                    input,
+                },
+                value,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::extension_field::Extendable;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::gates::custom::{CustomGate, GateAlgebra, GateDefinition};
+    use crate::gates::gate::Gate;
+    use crate::gates::gate_testing::{test_eval_fns, test_generators, test_low_degree};
+    use crate::hash::hash_types::RichField;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    /// Computes `output = c * x^3 + y`, where `c` is a constant of the gate, storing `x^2` in an
+    /// advice wire to keep the degree down.
+    #[derive(Clone, Debug)]
+    struct CubicGate;
+
+    const WIRE_X: usize = 0;
+    const WIRE_Y: usize = 1;
+    const WIRE_OUTPUT: usize = 2;
+    const WIRE_X_SQUARED: usize = 3;
+
+    impl<F: RichField + Extendable<D>, const D: usize> GateDefinition<F, D> for CubicGate {
+        fn num_wires(&self) -> usize {
+            4
+        }
+
+        fn num_constants(&self) -> usize {
+            1
+        }
+
+        fn constraints<A: GateAlgebra<F, D>>(&self, a: &mut A) -> Vec<A::Value> {
+            let (x, y, output) = (a.wire(WIRE_X), a.wire(WIRE_Y), a.wire(WIRE_OUTPUT));
+            let x_squared = a.wire(WIRE_X_SQUARED);
+            let c = a.local_constant(0);
+
+            let computed_x_squared = a.square(x);
+            let cx = a.mul(c, x);
+            let computed_output = a.mul_add(cx, x_squared, y);
+            vec![
+                a.sub(computed_x_squared, x_squared),
+                a.sub(computed_output, output),
+            ]
+        }
+
+        fn witness<A: GateAlgebra<F, D>>(&self, a: &mut A) -> Vec<(usize, A::Value)> {
+            let (x, y) = (a.wire(WIRE_X), a.wire(WIRE_Y));
+            let c = a.local_constant(0);
+
+            let x_squared = a.square(x);
+            let cx = a.mul(c, x);
+            let output = a.mul_add(cx, x_squared, y);
+            vec![(WIRE_X_SQUARED, x_squared), (WIRE_OUTPUT, output)]
+        }
+    }
+
+    #[test]
+    fn derived_properties() {
+        type F = GoldilocksField;
+        let gate = CustomGate::new(CubicGate);
+        assert_eq!(<CustomGate<_> as Gate<F, 4>>::degree(&gate), 3);
+        assert_eq!(<CustomGate<_> as Gate<F, 4>>::num_constraints(&gate), 2);
+    }
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(CustomGate::new(CubicGate))
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(CustomGate::new(CubicGate))
+    }
+
+    #[test]
+    fn generators() -> Result<()> {
+        type F = GoldilocksField;
+        let inputs = [(WIRE_X, F::rand()), (WIRE_Y, F::rand())];
+        test_generators::<F, _, 2>(CustomGate::new(CubicGate), &[F::rand()], &inputs)
+    }
+
+    #[test]
+    fn test_custom_gate_in_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let (x, y, c) = (F::rand(), F::rand(), F::rand());
+        let gate = builder.add_gate(CustomGate::new(CubicGate), vec![c]);
+        pw.set_target(Target::wire(gate, WIRE_X), x);
+        pw.set_target(Target::wire(gate, WIRE_Y), y);
+
+        let expected = builder.constant(c * x.cube() + y);
+        builder.connect(Target::wire(gate, WIRE_OUTPUT), expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
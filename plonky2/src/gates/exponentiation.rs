@@ -267,11 +267,24 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
 
         let mut current_intermediate_value = F::ONE;
         for i in 0..num_power_bits {
-            if power_bits[num_power_bits - i - 1] == F::ONE {
-                current_intermediate_value *= base;
+            let bit = power_bits[num_power_bits - i - 1];
+            if cfg!(feature = "constant-time") {
+                // Multiply whether or not the bit is set, so as not to leak the exponent.
+                current_intermediate_value = F::ct_select(
+                    !bit.ct_is_zero(),
+                    current_intermediate_value.ct_mul(base),
+                    current_intermediate_value,
+                );
+                intermediate_values.push(current_intermediate_value);
+                current_intermediate_value =
+                    current_intermediate_value.ct_mul(current_intermediate_value);
+            } else {
+                if bit == F::ONE {
+                    current_intermediate_value *= base;
+                }
+                intermediate_values.push(current_intermediate_value);
+                current_intermediate_value *= current_intermediate_value;
             }
-            intermediate_values.push(current_intermediate_value);
-            current_intermediate_value *= current_intermediate_value;
         }
 
         for i in 0..num_power_bits {
@@ -1,4 +1,7 @@
-use anyhow::{ensure, Result};
+//! Checks for implementations of `Gate`, used by the built-in gates' tests and available to crates
+//! defining their own gates.
+
+use anyhow::{anyhow, ensure, Result};
 use plonky2_field::extension_field::{Extendable, FieldExtension};
 use plonky2_field::field_types::Field;
 use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
@@ -7,7 +10,9 @@ use plonky2_util::log2_ceil;
 use crate::gates::gate::Gate;
 use crate::hash::hash_types::HashOut;
 use crate::hash::hash_types::RichField;
-use crate::iop::witness::{PartialWitness, Witness};
+use crate::iop::generator::GeneratedValues;
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, PartitionWitness, Witness};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CircuitConfig;
 use crate::plonk::config::{GenericConfig, Hasher};
@@ -85,6 +90,8 @@ fn random_low_degree_values<F: Field>(rate_bits: usize) -> Vec<F> {
         .values
 }
 
+/// Tests that `eval_unfiltered`, `eval_unfiltered_base_batch` and `eval_unfiltered_recursively`
+/// agree on random inputs.
 pub fn test_eval_fns<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -95,6 +102,7 @@ pub fn test_eval_fns<
 ) -> Result<()>
 where
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     // Test that `eval_unfiltered` and `eval_unfiltered_base` are coherent.
     let wires_base = F::rand_vec(gate.num_wires());
@@ -163,3 +171,71 @@ where
     let proof = data.prove(pw)?;
     verify(proof, &data.verifier_only, &data.common)
 }
+
+/// Tests that the generators of `gate` complete its row into a witness satisfying its constraints,
+/// given the row's `constants` and the values of the wires in `inputs`, which the gate expects to be
+/// set by copy constraints. It is an error for a wire to be left unset.
+pub fn test_generators<F: RichField + Extendable<D>, G: Gate<F, D>, const D: usize>(
+    gate: G,
+    constants: &[F],
+    inputs: &[(usize, F)],
+) -> Result<()> {
+    let num_wires = gate.num_wires();
+    let representative_map = (0..num_wires).collect::<Vec<_>>();
+    let mut witness = PartitionWitness::new(num_wires, 1, 0, &representative_map);
+    for &(input, value) in inputs {
+        witness.set_target(Target::wire(0, input), value);
+    }
+
+    // Run the generators until they have all finished, or none of them makes progress.
+    let mut pending = gate.generators(0, constants);
+    loop {
+        let num_pending = pending.len();
+        let mut out_buffer = GeneratedValues::empty();
+        pending.retain(|generator| !generator.run(&witness, &mut out_buffer));
+        let made_progress = !out_buffer.target_values.is_empty() || pending.len() < num_pending;
+        for (target, value) in out_buffer.target_values {
+            witness.set_target(target, value);
+        }
+        if pending.is_empty() || !made_progress {
+            break;
+        }
+    }
+    ensure!(
+        pending.is_empty(),
+        "{} generators never finished",
+        pending.len()
+    );
+
+    let wires = (0..num_wires)
+        .map(|input| {
+            witness
+                .try_get_target(Target::wire(0, input))
+                .map(F::Extension::from_basefield)
+                .ok_or_else(|| anyhow!("Wire {} was not set", input))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let constants = constants
+        .iter()
+        .map(|&x| F::Extension::from_basefield(x))
+        .collect::<Vec<_>>();
+    let vars = EvaluationVars {
+        local_constants: &constants,
+        local_wires: &wires,
+        public_inputs_hash: &HashOut::rand(),
+    };
+
+    let unsatisfied = gate
+        .eval_unfiltered(vars)
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.is_zero())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    ensure!(
+        unsatisfied.is_empty(),
+        "Constraints {:?} are not satisfied",
+        unsatisfied
+    );
+    Ok(())
+}
@@ -62,7 +62,14 @@ impl<F: RichField + Extendable<D>, const D: usize> Tree<GateRef<F, D>> {
     /// and the number of constant wires needed when using this tree.
     pub fn from_gates(mut gates: Vec<GateRef<F, D>>) -> (Self, usize, usize) {
         let timer = std::time::Instant::now();
-        gates.sort_unstable_by_key(|g| (-(g.0.degree() as isize), -(g.0.num_constants() as isize)));
+        // Break ties by ID, so that the tree doesn't depend on the order the gates are given in.
+        gates.sort_unstable_by_key(|g| {
+            (
+                -(g.0.degree() as isize),
+                -(g.0.num_constants() as isize),
+                g.0.id(),
+            )
+        });
 
         for max_degree_bits in 1..10 {
             // The quotient polynomials are padded to the next power of 2 in `compute_quotient_polys`.
@@ -0,0 +1,214 @@
+use std::ops::Range;
+
+use plonky2_field::extension_field::algebra::ExtensionAlgebra;
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::extension_field::FieldExtension;
+
+use crate::gates::gate::Gate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::{ExtensionAlgebraTarget, ExtensionTarget};
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CircuitConfig;
+use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+
+/// Computes `old_acc + c sum a_i b_i` for `num_pairs` pairs `(a_i, b_i)` of elements of the
+/// extension field, where `c` is a constant of the gate.
+#[derive(Debug, Clone)]
+pub struct InnerProductExtensionGate<const D: usize> {
+    pub num_pairs: usize,
+}
+
+impl<const D: usize> InnerProductExtensionGate<D> {
+    pub fn new(num_pairs: usize) -> Self {
+        Self { num_pairs }
+    }
+
+    pub fn new_from_config(config: &CircuitConfig) -> Self {
+        Self::new(Self::max_pairs(config.num_routed_wires))
+    }
+
+    pub fn max_pairs(num_routed_wires: usize) -> usize {
+        // `2*D` routed wires are used for the output and old accumulator, and `2*D` for each pair.
+        (num_routed_wires - 2 * D) / (2 * D)
+    }
+
+    pub fn wires_output() -> Range<usize> {
+        0..D
+    }
+    pub fn wires_old_acc() -> Range<usize> {
+        D..2 * D
+    }
+    const START_PAIRS: usize = 2 * D;
+    pub fn wires_ith_first(i: usize) -> Range<usize> {
+        Self::START_PAIRS + 2 * D * i..Self::START_PAIRS + 2 * D * i + D
+    }
+    pub fn wires_ith_second(i: usize) -> Range<usize> {
+        Self::START_PAIRS + 2 * D * i + D..Self::START_PAIRS + 2 * D * (i + 1)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for InnerProductExtensionGate<D> {
+    fn id(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let constant = vars.local_constants[0];
+        let old_acc = vars.get_local_ext_algebra(Self::wires_old_acc());
+        let output = vars.get_local_ext_algebra(Self::wires_output());
+
+        let sum: ExtensionAlgebra<F::Extension, D> = (0..self.num_pairs)
+            .map(|i| {
+                vars.get_local_ext_algebra(Self::wires_ith_first(i))
+                    * vars.get_local_ext_algebra(Self::wires_ith_second(i))
+            })
+            .sum();
+        let computed_output = old_acc + sum.scalar_mul(constant);
+
+        (output - computed_output).to_basefield_array().to_vec()
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        let constant = vars.local_constants[0];
+        let old_acc = vars.get_local_ext(Self::wires_old_acc());
+        let output = vars.get_local_ext(Self::wires_output());
+
+        let sum: F::Extension = (0..self.num_pairs)
+            .map(|i| {
+                vars.get_local_ext(Self::wires_ith_first(i))
+                    * vars.get_local_ext(Self::wires_ith_second(i))
+            })
+            .sum();
+        let computed_output = old_acc + sum.scalar_mul(constant);
+
+        yield_constr.many((output - computed_output).to_basefield_array());
+    }
+
+    fn eval_unfiltered_recursively(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let constant = vars.local_constants[0];
+        let old_acc = vars.get_local_ext_algebra(Self::wires_old_acc());
+        let output = vars.get_local_ext_algebra(Self::wires_output());
+
+        let zero = builder.zero_extension();
+        let mut sum = ExtensionAlgebraTarget([zero; D]);
+        for i in 0..self.num_pairs {
+            let first = vars.get_local_ext_algebra(Self::wires_ith_first(i));
+            let second = vars.get_local_ext_algebra(Self::wires_ith_second(i));
+            sum = builder.mul_add_ext_algebra(first, second, sum);
+        }
+        let computed_output = builder.scalar_mul_add_ext_algebra(constant, sum, old_acc);
+
+        builder
+            .sub_ext_algebra(output, computed_output)
+            .to_ext_target_array()
+            .to_vec()
+    }
+
+    fn generators(
+        &self,
+        gate_index: usize,
+        local_constants: &[F],
+    ) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        vec![Box::new(
+            InnerProductExtensionGenerator {
+                gate_index,
+                gate: self.clone(),
+                constant: local_constants[0],
+            }
+            .adapter(),
+        )]
+    }
+
+    fn num_wires(&self) -> usize {
+        2 * D + 2 * D * self.num_pairs
+    }
+
+    fn num_constants(&self) -> usize {
+        1
+    }
+
+    fn degree(&self) -> usize {
+        3
+    }
+
+    fn num_constraints(&self) -> usize {
+        D
+    }
+}
+
+#[derive(Debug)]
+struct InnerProductExtensionGenerator<F: RichField + Extendable<D>, const D: usize> {
+    gate_index: usize,
+    gate: InnerProductExtensionGate<D>,
+    constant: F,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
+    for InnerProductExtensionGenerator<F, D>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        InnerProductExtensionGate::<D>::wires_old_acc()
+            .chain((0..self.gate.num_pairs).flat_map(|i| {
+                InnerProductExtensionGate::<D>::wires_ith_first(i)
+                    .chain(InnerProductExtensionGate::<D>::wires_ith_second(i))
+            }))
+            .map(|i| Target::wire(self.gate_index, i))
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_extension = |range: Range<usize>| -> F::Extension {
+            let t = ExtensionTarget::from_range(self.gate_index, range);
+            witness.get_extension_target(t)
+        };
+
+        let old_acc = local_extension(InnerProductExtensionGate::<D>::wires_old_acc());
+        let sum = (0..self.gate.num_pairs)
+            .map(|i| {
+                local_extension(InnerProductExtensionGate::<D>::wires_ith_first(i))
+                    * local_extension(InnerProductExtensionGate::<D>::wires_ith_second(i))
+            })
+            .sum::<F::Extension>();
+
+        let output_target = ExtensionTarget::from_range(
+            self.gate_index,
+            InnerProductExtensionGate::<D>::wires_output(),
+        );
+        out_buffer.set_extension_target(output_target, old_acc + sum.scalar_mul(self.constant));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::inner_product_extension::InnerProductExtensionGate;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(InnerProductExtensionGate::new(9));
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(InnerProductExtensionGate::new(9))
+    }
+}
@@ -3,7 +3,7 @@ use std::ops::Range;
 
 use plonky2_field::extension_field::algebra::PolynomialCoeffsAlgebra;
 use plonky2_field::extension_field::{Extendable, FieldExtension};
-use plonky2_field::interpolation::interpolant;
+use plonky2_field::interpolation::BarycentricDomain;
 use plonky2_field::polynomial::PolynomialCoeffs;
 
 use crate::gadgets::interpolation::InterpolationGate;
@@ -250,13 +250,13 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
         };
 
         // Compute the interpolant.
-        let points = self.gate.coset(get_local_wire(self.gate.wire_shift()));
-        let points = points
-            .into_iter()
-            .enumerate()
-            .map(|(i, point)| (point.into(), get_local_ext(self.gate.wires_value(i))))
+        let shift = get_local_wire(self.gate.wire_shift());
+        let values = (0..self.gate.num_points())
+            .map(|i| get_local_ext(self.gate.wires_value(i)))
             .collect::<Vec<_>>();
-        let interpolant = interpolant(&points);
+        let interpolant =
+            BarycentricDomain::<F::Extension>::coset(self.gate.subgroup_bits, shift.into())
+                .interpolant(&values);
 
         for (i, &coeff) in interpolant.coeffs.iter().enumerate() {
             let wires = self.gate.wires_coeff(i).map(local_wire);
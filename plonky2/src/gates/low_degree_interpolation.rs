@@ -4,7 +4,7 @@ use std::ops::Range;
 use plonky2_field::extension_field::algebra::PolynomialCoeffsAlgebra;
 use plonky2_field::extension_field::{Extendable, FieldExtension};
 use plonky2_field::field_types::Field;
-use plonky2_field::interpolation::interpolant;
+use plonky2_field::interpolation::BarycentricDomain;
 use plonky2_field::polynomial::PolynomialCoeffs;
 
 use crate::gadgets::interpolation::InterpolationGate;
@@ -67,14 +67,6 @@ impl<F: RichField + Extendable<D>, const D: usize> LowDegreeInterpolationGate<F,
     fn end(&self) -> usize {
         self.powers_evaluation_point(self.num_points() - 1).end
     }
-
-    /// The domain of the points we're interpolating.
-    fn coset(&self, shift: F) -> impl Iterator<Item = F> {
-        let g = F::primitive_root_of_unity(self.subgroup_bits);
-        let size = 1 << self.subgroup_bits;
-        // Speed matters here, so we avoid `cyclic_subgroup_coset_known_order` which allocates.
-        g.powers().take(size).map(move |x| x * shift)
-    }
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LowDegreeInterpolationGate<F, D> {
@@ -352,13 +344,12 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
         }
 
         // Compute the interpolant.
-        let points = self.gate.coset(wire_shift);
-        let points = points
-            .into_iter()
-            .enumerate()
-            .map(|(i, point)| (point.into(), get_local_ext(self.gate.wires_value(i))))
+        let values = (0..self.gate.num_points())
+            .map(|i| get_local_ext(self.gate.wires_value(i)))
             .collect::<Vec<_>>();
-        let interpolant = interpolant(&points);
+        let interpolant =
+            BarycentricDomain::<F::Extension>::coset(self.gate.subgroup_bits, wire_shift.into())
+                .interpolant(&values);
 
         for (i, &coeff) in interpolant.coeffs.iter().enumerate() {
             let wires = self.gate.wires_coeff(i).map(local_wire);
@@ -389,6 +380,7 @@ mod tests {
     use plonky2_field::extension_field::quadratic::QuadraticExtension;
     use plonky2_field::field_types::Field;
     use plonky2_field::goldilocks_field::GoldilocksField;
+    use plonky2_field::interpolation::BarycentricDomain;
     use plonky2_field::polynomial::PolynomialCoeffs;
 
     use crate::gadgets::interpolation::InterpolationGate;
@@ -425,9 +417,9 @@ mod tests {
             coeffs: PolynomialCoeffs<FF>,
             eval_point: FF,
         ) -> Vec<FF> {
-            let points = gate.coset(shift);
+            let domain = BarycentricDomain::coset(gate.subgroup_bits, shift);
             let mut v = vec![shift];
-            for x in points {
+            for &x in domain.points() {
                 v.extend(coeffs.eval(x.into()).0);
             }
             v.extend(eval_point.0);
@@ -2,19 +2,24 @@
 #![allow(clippy::new_ret_no_self)]
 
 pub mod add_many_u32;
+pub mod addition_u64;
 pub mod arithmetic_base;
 pub mod arithmetic_extension;
 pub mod arithmetic_u32;
 pub mod assert_le;
 pub mod base_sum;
 pub mod comparison;
+pub mod conditional_arithmetic;
 pub mod constant;
+pub mod custom;
 pub mod exponentiation;
 pub mod gate;
 pub mod gate_tree;
+pub mod inner_product_extension;
 pub mod interpolation;
 pub mod low_degree_interpolation;
 pub mod multiplication_extension;
+pub mod multiplication_u64;
 pub mod noop;
 mod packed_util;
 pub mod poseidon;
@@ -24,8 +29,10 @@ pub mod random_access;
 pub mod range_check_u32;
 pub mod reducing;
 pub mod reducing_extension;
+pub mod rescue_prime;
 pub mod subtraction_u32;
 pub mod switch;
+pub mod tip5;
 pub mod util;
 
 // Can't use #[cfg(test)] here because it needs to be visible to other crates.
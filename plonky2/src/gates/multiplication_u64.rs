@@ -0,0 +1,596 @@
+use std::marker::PhantomData;
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+use plonky2_field::packed_field::PackedField;
+
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CircuitConfig;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+
+/// The number of intermediate values of a multiplication: the carries `c0`, `c1a`, `c1b`, `c2a`
+/// and `c2b`, and the partial sums `t` and `u` of the middle columns.
+const NUM_INTERMEDIATES: usize = 7;
+
+/// The number of 32-bit values which are range-checked: the low three limbs of the product, and
+/// every intermediate except the one-bit carry `c2b`.
+const NUM_RANGE_CHECKED: usize = 9;
+
+/// A gate to perform a multiplication on 64-bit values, each given as two 32-bit limbs: given `x`
+/// and `y`, it returns the full 128-bit product `x * y` as four 32-bit limbs, along with an
+/// `overflow` flag which is set iff the product doesn't fit in 64 bits.
+///
+/// The product is computed by schoolbook multiplication on the limbs, with every partial sum
+/// split into 32-bit halves before it could wrap around the field:
+///
+/// ```text
+/// x0 * y0           = r0 + c0  * 2^32
+/// x0 * y1 + c0      = t  + c1a * 2^32
+/// x1 * y0 + t       = r1 + c1b * 2^32
+/// x1 * y1 + c1b     = u  + c2a * 2^32
+/// u + c1a           = r2 + c2b * 2^32
+/// c2a + c2b         = r3
+/// ```
+///
+/// The result limbs and intermediates are range-checked and `c2b` is constrained to be a bit, but
+/// the inputs are not range-checked. An operation takes 161 wires, so the gate can only be used
+/// with configs which have at least that many; see `CircuitBuilder::mul_u64`.
+#[derive(Copy, Clone, Debug)]
+pub struct U64MultiplicationGate<F: RichField + Extendable<D>, const D: usize> {
+    pub num_ops: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> U64MultiplicationGate<F, D> {
+    pub fn new_from_config(config: &CircuitConfig) -> Self {
+        Self {
+            num_ops: Self::num_ops(config),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn num_ops(config: &CircuitConfig) -> usize {
+        let wires_per_op = 9 + Self::num_internal_wires();
+        let routed_wires_per_op = 9;
+        (config.num_wires / wires_per_op).min(config.num_routed_wires / routed_wires_per_op)
+    }
+
+    pub fn wire_ith_input_x_low(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        9 * i
+    }
+    pub fn wire_ith_input_x_high(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        9 * i + 1
+    }
+    pub fn wire_ith_input_y_low(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        9 * i + 2
+    }
+    pub fn wire_ith_input_y_high(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        9 * i + 3
+    }
+
+    /// The `j`th 32-bit limb of the 128-bit product, in little-endian order.
+    pub fn wire_ith_output_jth_limb(&self, i: usize, j: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        debug_assert!(j < 4);
+        9 * i + 4 + j
+    }
+    pub fn wire_ith_output_overflow(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        9 * i + 8
+    }
+
+    pub fn limb_bits() -> usize {
+        2
+    }
+    // We have limbs for each of the 32-bit values which are range-checked.
+    pub fn num_limbs() -> usize {
+        32 / Self::limb_bits()
+    }
+
+    fn num_internal_wires() -> usize {
+        NUM_INTERMEDIATES + 1 + NUM_RANGE_CHECKED * Self::num_limbs()
+    }
+
+    fn internal_wires_start(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        9 * self.num_ops + Self::num_internal_wires() * i
+    }
+
+    /// The `j`th intermediate value, in the order `c0, t, c1a, c1b, u, c2a, c2b`.
+    pub fn wire_ith_intermediate(&self, i: usize, j: usize) -> usize {
+        debug_assert!(j < NUM_INTERMEDIATES);
+        self.internal_wires_start(i) + j
+    }
+
+    /// The inverse of `r2 + r3`, or zero if it is zero, which is used to compute the overflow flag.
+    pub fn wire_ith_overflow_inverse(&self, i: usize) -> usize {
+        self.internal_wires_start(i) + NUM_INTERMEDIATES
+    }
+
+    /// The `j`th limb of the range-checked values `r0, r1, r2, c0, t, c1a, c1b, u, c2a`, with
+    /// `num_limbs` limbs for each.
+    pub fn wire_ith_range_check_jth_limb(&self, i: usize, j: usize) -> usize {
+        debug_assert!(j < NUM_RANGE_CHECKED * Self::num_limbs());
+        self.internal_wires_start(i) + NUM_INTERMEDIATES + 1 + j
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for U64MultiplicationGate<F, D> {
+    fn id(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+        for i in 0..self.num_ops {
+            let x0 = vars.local_wires[self.wire_ith_input_x_low(i)];
+            let x1 = vars.local_wires[self.wire_ith_input_x_high(i)];
+            let y0 = vars.local_wires[self.wire_ith_input_y_low(i)];
+            let y1 = vars.local_wires[self.wire_ith_input_y_high(i)];
+            let [r0, r1, r2, r3] =
+                [0, 1, 2, 3].map(|j| vars.local_wires[self.wire_ith_output_jth_limb(i, j)]);
+            let overflow = vars.local_wires[self.wire_ith_output_overflow(i)];
+            let [c0, t, c1a, c1b, u, c2a, c2b] =
+                [0, 1, 2, 3, 4, 5, 6].map(|j| vars.local_wires[self.wire_ith_intermediate(i, j)]);
+            let inverse = vars.local_wires[self.wire_ith_overflow_inverse(i)];
+
+            let base = F::Extension::from_canonical_u64(1 << 32u64);
+            constraints.push(x0 * y0 - (r0 + base * c0));
+            constraints.push(x0 * y1 + c0 - (t + base * c1a));
+            constraints.push(x1 * y0 + t - (r1 + base * c1b));
+            constraints.push(x1 * y1 + c1b - (u + base * c2a));
+            constraints.push(u + c1a - (r2 + base * c2b));
+            constraints.push(c2a + c2b - r3);
+
+            // Range-check the last carry to be one bit.
+            constraints.push(c2b * (F::Extension::ONE - c2b));
+
+            // `overflow = high * inverse` and `high * (1 - overflow) = 0` force `overflow` to be 1
+            // iff `high != 0`. Neither limb can be large enough to wrap, so `high` is zero iff both are.
+            let high = r2 + r3;
+            constraints.push(overflow - high * inverse);
+            constraints.push(high * (F::Extension::ONE - overflow));
+
+            // Range-check the result limbs and intermediates to be at most 32 bits.
+            let limb_base = F::Extension::from_canonical_u64(1u64 << Self::limb_bits());
+            for (k, value) in [r0, r1, r2, c0, t, c1a, c1b, u, c2a]
+                .into_iter()
+                .enumerate()
+            {
+                let mut combined_limbs = F::Extension::ZERO;
+                for j in (0..Self::num_limbs()).rev() {
+                    let this_limb = vars.local_wires
+                        [self.wire_ith_range_check_jth_limb(i, k * Self::num_limbs() + j)];
+                    let max_limb = 1 << Self::limb_bits();
+                    let product = (0..max_limb)
+                        .map(|x| this_limb - F::Extension::from_canonical_usize(x))
+                        .product();
+                    constraints.push(product);
+
+                    combined_limbs = limb_base * combined_limbs + this_limb;
+                }
+                constraints.push(combined_limbs - value);
+            }
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_recursively(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        for i in 0..self.num_ops {
+            let x0 = vars.local_wires[self.wire_ith_input_x_low(i)];
+            let x1 = vars.local_wires[self.wire_ith_input_x_high(i)];
+            let y0 = vars.local_wires[self.wire_ith_input_y_low(i)];
+            let y1 = vars.local_wires[self.wire_ith_input_y_high(i)];
+            let [r0, r1, r2, r3] =
+                [0, 1, 2, 3].map(|j| vars.local_wires[self.wire_ith_output_jth_limb(i, j)]);
+            let overflow = vars.local_wires[self.wire_ith_output_overflow(i)];
+            let [c0, t, c1a, c1b, u, c2a, c2b] =
+                [0, 1, 2, 3, 4, 5, 6].map(|j| vars.local_wires[self.wire_ith_intermediate(i, j)]);
+            let inverse = vars.local_wires[self.wire_ith_overflow_inverse(i)];
+
+            let base: F::Extension = F::from_canonical_u64(1 << 32u64).into();
+            let base_target = builder.constant_extension(base);
+            let zero = builder.zero_extension();
+
+            // Each row is `(a, b, addend, low, carry)`, constraining `a * b + addend = low + carry * 2^32`.
+            let splits = [
+                (x0, y0, zero, r0, c0),
+                (x0, y1, c0, t, c1a),
+                (x1, y0, t, r1, c1b),
+                (x1, y1, c1b, u, c2a),
+            ];
+            for (a, b, addend, low, carry) in splits {
+                let computed = builder.mul_add_extension(a, b, addend);
+                let combined = builder.mul_add_extension(carry, base_target, low);
+                constraints.push(builder.sub_extension(computed, combined));
+            }
+            let computed = builder.add_extension(u, c1a);
+            let combined = builder.mul_add_extension(c2b, base_target, r2);
+            constraints.push(builder.sub_extension(computed, combined));
+            let computed = builder.add_extension(c2a, c2b);
+            constraints.push(builder.sub_extension(computed, r3));
+
+            // Range-check the last carry to be one bit.
+            let one = builder.one_extension();
+            let not_c2b = builder.sub_extension(one, c2b);
+            constraints.push(builder.mul_extension(c2b, not_c2b));
+
+            // `overflow = high * inverse` and `high * (1 - overflow) = 0` force `overflow` to be 1
+            // iff `high != 0`. Neither limb can be large enough to wrap, so `high` is zero iff both are.
+            let high = builder.add_extension(r2, r3);
+            let computed_overflow = builder.mul_extension(high, inverse);
+            constraints.push(builder.sub_extension(overflow, computed_overflow));
+            let not_overflow = builder.sub_extension(one, overflow);
+            constraints.push(builder.mul_extension(high, not_overflow));
+
+            // Range-check the result limbs and intermediates to be at most 32 bits.
+            let limb_base = builder
+                .constant_extension(F::Extension::from_canonical_u64(1u64 << Self::limb_bits()));
+            for (k, value) in [r0, r1, r2, c0, t, c1a, c1b, u, c2a]
+                .into_iter()
+                .enumerate()
+            {
+                let mut combined_limbs = builder.zero_extension();
+                for j in (0..Self::num_limbs()).rev() {
+                    let this_limb = vars.local_wires
+                        [self.wire_ith_range_check_jth_limb(i, k * Self::num_limbs() + j)];
+                    let max_limb = 1 << Self::limb_bits();
+
+                    let mut product = builder.one_extension();
+                    for x in 0..max_limb {
+                        let x_target =
+                            builder.constant_extension(F::Extension::from_canonical_usize(x));
+                        let diff = builder.sub_extension(this_limb, x_target);
+                        product = builder.mul_extension(product, diff);
+                    }
+                    constraints.push(product);
+
+                    combined_limbs =
+                        builder.mul_add_extension(limb_base, combined_limbs, this_limb);
+                }
+                constraints.push(builder.sub_extension(combined_limbs, value));
+            }
+        }
+
+        constraints
+    }
+
+    fn generators(
+        &self,
+        gate_index: usize,
+        _local_constants: &[F],
+    ) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        (0..self.num_ops)
+            .map(|i| {
+                let g: Box<dyn WitnessGenerator<F>> = Box::new(
+                    U64MultiplicationGenerator {
+                        gate: *self,
+                        gate_index,
+                        i,
+                        _phantom: PhantomData,
+                    }
+                    .adapter(),
+                );
+                g
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * (9 + Self::num_internal_wires())
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        1 << Self::limb_bits()
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops * (9 + NUM_RANGE_CHECKED * (Self::num_limbs() + 1))
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for U64MultiplicationGate<F, D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_ops {
+            let x0 = vars.local_wires[self.wire_ith_input_x_low(i)];
+            let x1 = vars.local_wires[self.wire_ith_input_x_high(i)];
+            let y0 = vars.local_wires[self.wire_ith_input_y_low(i)];
+            let y1 = vars.local_wires[self.wire_ith_input_y_high(i)];
+            let [r0, r1, r2, r3] =
+                [0, 1, 2, 3].map(|j| vars.local_wires[self.wire_ith_output_jth_limb(i, j)]);
+            let overflow = vars.local_wires[self.wire_ith_output_overflow(i)];
+            let [c0, t, c1a, c1b, u, c2a, c2b] =
+                [0, 1, 2, 3, 4, 5, 6].map(|j| vars.local_wires[self.wire_ith_intermediate(i, j)]);
+            let inverse = vars.local_wires[self.wire_ith_overflow_inverse(i)];
+
+            let base = F::from_canonical_u64(1 << 32u64);
+            yield_constr.one(x0 * y0 - (r0 + c0 * base));
+            yield_constr.one(x0 * y1 + c0 - (t + c1a * base));
+            yield_constr.one(x1 * y0 + t - (r1 + c1b * base));
+            yield_constr.one(x1 * y1 + c1b - (u + c2a * base));
+            yield_constr.one(u + c1a - (r2 + c2b * base));
+            yield_constr.one(c2a + c2b - r3);
+
+            // Range-check the last carry to be one bit.
+            yield_constr.one(c2b * (P::ONES - c2b));
+
+            // `overflow = high * inverse` and `high * (1 - overflow) = 0` force `overflow` to be 1
+            // iff `high != 0`. Neither limb can be large enough to wrap, so `high` is zero iff both are.
+            let high = r2 + r3;
+            yield_constr.one(overflow - high * inverse);
+            yield_constr.one(high * (P::ONES - overflow));
+
+            // Range-check the result limbs and intermediates to be at most 32 bits.
+            let limb_base = F::from_canonical_u64(1u64 << Self::limb_bits());
+            for (k, value) in [r0, r1, r2, c0, t, c1a, c1b, u, c2a]
+                .into_iter()
+                .enumerate()
+            {
+                let mut combined_limbs = P::ZEROS;
+                for j in (0..Self::num_limbs()).rev() {
+                    let this_limb = vars.local_wires
+                        [self.wire_ith_range_check_jth_limb(i, k * Self::num_limbs() + j)];
+                    let max_limb = 1 << Self::limb_bits();
+                    let product = (0..max_limb)
+                        .map(|x| this_limb - F::from_canonical_usize(x))
+                        .product();
+                    yield_constr.one(product);
+
+                    combined_limbs = combined_limbs * limb_base + this_limb;
+                }
+                yield_constr.one(combined_limbs - value);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct U64MultiplicationGenerator<F: RichField + Extendable<D>, const D: usize> {
+    gate: U64MultiplicationGate<F, D>,
+    gate_index: usize,
+    i: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
+    for U64MultiplicationGenerator<F, D>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        let local_target = |input| Target::wire(self.gate_index, input);
+
+        vec![
+            local_target(self.gate.wire_ith_input_x_low(self.i)),
+            local_target(self.gate.wire_ith_input_x_high(self.i)),
+            local_target(self.gate.wire_ith_input_y_low(self.i)),
+            local_target(self.gate.wire_ith_input_y_high(self.i)),
+        ]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_wire = |input| Wire {
+            gate: self.gate_index,
+            input,
+        };
+
+        let get_local_u64 = |input| witness.get_wire(local_wire(input)).to_canonical_u64();
+
+        let x0 = get_local_u64(self.gate.wire_ith_input_x_low(self.i));
+        let x1 = get_local_u64(self.gate.wire_ith_input_x_high(self.i));
+        let y0 = get_local_u64(self.gate.wire_ith_input_y_low(self.i));
+        let y1 = get_local_u64(self.gate.wire_ith_input_y_high(self.i));
+
+        let split = |value: u64| (value & 0xFFFFFFFF, value >> 32);
+        let (r0, c0) = split(x0 * y0);
+        let (t, c1a) = split(x0 * y1 + c0);
+        let (r1, c1b) = split(x1 * y0 + t);
+        let (u, c2a) = split(x1 * y1 + c1b);
+        let (r2, c2b) = split(u + c1a);
+        let r3 = c2a + c2b;
+
+        let high = F::from_canonical_u64(r2 + r3);
+        let inverse = high.try_inverse().unwrap_or(F::ZERO);
+        let overflow = !high.is_zero();
+
+        let outputs = [r0, r1, r2, r3]
+            .into_iter()
+            .enumerate()
+            .map(|(j, limb)| (self.gate.wire_ith_output_jth_limb(self.i, j), limb))
+            .chain(
+                [c0, t, c1a, c1b, u, c2a, c2b]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(j, value)| (self.gate.wire_ith_intermediate(self.i, j), value)),
+            )
+            .chain([(self.gate.wire_ith_output_overflow(self.i), overflow as u64)]);
+        for (wire, value) in outputs {
+            out_buffer.set_wire(local_wire(wire), F::from_canonical_u64(value));
+        }
+        out_buffer.set_wire(
+            local_wire(self.gate.wire_ith_overflow_inverse(self.i)),
+            inverse,
+        );
+
+        let num_limbs = U64MultiplicationGate::<F, D>::num_limbs();
+        let limb_base = 1 << U64MultiplicationGate::<F, D>::limb_bits();
+        let range_checked_limbs =
+            [r0, r1, r2, c0, t, c1a, c1b, u, c2a]
+                .into_iter()
+                .flat_map(|value| {
+                    (0..num_limbs).scan(value, |acc, _| {
+                        let tmp = *acc % limb_base;
+                        *acc /= limb_base;
+                        Some(F::from_canonical_u64(tmp))
+                    })
+                });
+
+        for (j, limb) in range_checked_limbs.enumerate() {
+            let wire = local_wire(self.gate.wire_ith_range_check_jth_limb(self.i, j));
+            out_buffer.set_wire(wire, limb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+    use rand::Rng;
+
+    use crate::gates::gate::Gate;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::multiplication_u64::U64MultiplicationGate;
+    use crate::hash::hash_types::HashOut;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::vars::EvaluationVars;
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(U64MultiplicationGate::<GoldilocksField, 4> {
+            num_ops: 2,
+            _phantom: PhantomData,
+        })
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(U64MultiplicationGate::<GoldilocksField, D> {
+            num_ops: 2,
+            _phantom: PhantomData,
+        })
+    }
+
+    #[test]
+    fn test_gate_constraint() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+        const NUM_U64_MULTIPLICATION_OPS: usize = 3;
+
+        fn get_wires(inputs: &[(u64, u64)]) -> Vec<FF> {
+            let mut v0 = Vec::new();
+            let mut v1 = Vec::new();
+
+            let num_limbs = U64MultiplicationGate::<F, D>::num_limbs();
+            let limb_base = 1 << U64MultiplicationGate::<F, D>::limb_bits();
+            for &(x, y) in inputs {
+                let product = x as u128 * y as u128;
+                let (x0, x1) = (x & 0xFFFFFFFF, x >> 32);
+                let (y0, y1) = (y & 0xFFFFFFFF, y >> 32);
+
+                let c0 = (x0 * y0) >> 32;
+                let t = (x0 * y1 + c0) & 0xFFFFFFFF;
+                let c1a = (x0 * y1 + c0) >> 32;
+                let c1b = (x1 * y0 + t) >> 32;
+                let u = (x1 * y1 + c1b) & 0xFFFFFFFF;
+                let c2a = (x1 * y1 + c1b) >> 32;
+                let c2b = (u + c1a) >> 32;
+
+                let limbs = [0, 1, 2, 3].map(|j| (product >> (32 * j)) as u32 as u64);
+                let high = product >> 64;
+
+                v0.extend([x0, x1, y0, y1]);
+                v0.extend(limbs);
+                v0.push((high != 0) as u64);
+
+                let mut intermediates = [c0, t, c1a, c1b, u, c2a, c2b]
+                    .into_iter()
+                    .map(F::from_canonical_u64)
+                    .collect::<Vec<_>>();
+                intermediates.push(
+                    F::from_canonical_u64(limbs[2] + limbs[3])
+                        .try_inverse()
+                        .unwrap_or(F::ZERO),
+                );
+                v1.extend(intermediates);
+                for value in [limbs[0], limbs[1], limbs[2], c0, t, c1a, c1b, u, c2a] {
+                    let mut rest = value;
+                    for _ in 0..num_limbs {
+                        v1.push(F::from_canonical_u64(rest % limb_base));
+                        rest /= limb_base;
+                    }
+                }
+            }
+
+            v0.into_iter()
+                .map(F::from_canonical_u64)
+                .chain(v1)
+                .map(|x| x.into())
+                .collect()
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut inputs: Vec<_> = (0..NUM_U64_MULTIPLICATION_OPS)
+            .map(|_| (rng.gen::<u64>(), rng.gen::<u64>()))
+            .collect();
+        inputs[0] = (u64::MAX, u64::MAX);
+        inputs[1] = (u32::MAX as u64, 1 << 32);
+
+        let gate = U64MultiplicationGate::<F, D> {
+            num_ops: NUM_U64_MULTIPLICATION_OPS,
+            _phantom: PhantomData,
+        };
+
+        let vars = EvaluationVars {
+            local_constants: &[],
+            local_wires: &get_wires(&inputs),
+            public_inputs_hash: &HashOut::rand(),
+        };
+
+        assert!(
+            gate.eval_unfiltered(vars).iter().all(|x| x.is_zero()),
+            "Gate constraints are not satisfied."
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use plonky2_field::extension_field::Extendable;
-use plonky2_field::packable::Packable;
+use plonky2_field::field_types::Field;
+use plonky2_field::packable::{Packable, PackedKernel};
 use plonky2_field::packed_field::PackedField;
 
 use crate::gates::gate::Gate;
@@ -16,22 +17,44 @@ pub trait PackedEvaluableBase<F: RichField + Extendable<D>, const D: usize>: Gat
 
     /// Evaluates entire batch of points. Returns a matrix of constraints. Constraint `j` for point
     /// `i` is at `index j * batch_size + i`.
+    ///
+    /// The points are packed with the best packing of `F` supported by the running CPU, rather than
+    /// the one selected at compile time.
     fn eval_unfiltered_base_batch_packed(&self, vars_batch: EvaluationVarsBaseBatch<F>) -> Vec<F> {
-        let mut res = vec![F::ZERO; vars_batch.len() * self.num_constraints()];
-        let (vars_packed_iter, vars_leftovers_iter) = vars_batch.pack::<<F as Packable>::Packing>();
+        F::with_best_packing(PackedGateKernel::<F, Self, D> {
+            gate: self,
+            vars_batch,
+        })
+    }
+}
+
+/// The evaluation of a gate's constraints on a batch of points, generic over the packing it uses.
+struct PackedGateKernel<'a, F: Field, G: ?Sized, const D: usize> {
+    gate: &'a G,
+    vars_batch: EvaluationVarsBaseBatch<'a, F>,
+}
+
+impl<'a, F, G, const D: usize> PackedKernel<F> for PackedGateKernel<'a, F, G, D>
+where
+    F: RichField + Extendable<D>,
+    G: PackedEvaluableBase<F, D> + ?Sized,
+{
+    type Output = Vec<F>;
+
+    #[inline(always)]
+    fn run<P: PackedField<Scalar = F>>(self) -> Vec<F> {
+        let Self { gate, vars_batch } = self;
+        let mut res = vec![F::ZERO; vars_batch.len() * gate.num_constraints()];
+        let (vars_packed_iter, vars_leftovers_iter) = vars_batch.pack::<P>();
         let leftovers_start = vars_batch.len() - vars_leftovers_iter.len();
         for (i, vars_packed) in vars_packed_iter.enumerate() {
-            self.eval_unfiltered_base_packed(
+            gate.eval_unfiltered_base_packed(
                 vars_packed,
-                StridedConstraintConsumer::new(
-                    &mut res[..],
-                    vars_batch.len(),
-                    <F as Packable>::Packing::WIDTH * i,
-                ),
+                StridedConstraintConsumer::new(&mut res[..], vars_batch.len(), P::WIDTH * i),
             );
         }
         for (i, vars_leftovers) in vars_leftovers_iter.enumerate() {
-            self.eval_unfiltered_base_packed(
+            gate.eval_unfiltered_base_packed(
                 vars_leftovers,
                 StridedConstraintConsumer::new(&mut res[..], vars_batch.len(), leftovers_start + i),
             );
@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
 
 use itertools::Itertools;
+use plonky2_field::constant_time::CtMask;
 use plonky2_field::extension_field::Extendable;
 use plonky2_field::field_types::Field;
 use plonky2_field::packed_field::PackedField;
@@ -314,10 +315,16 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
             vec_size
         );
 
-        set_local_wire(
-            self.gate.wire_claimed_element(copy),
-            get_local_wire(self.gate.wire_list_item(access_index, copy)),
-        );
+        let claimed_element = if cfg!(feature = "constant-time") {
+            // Read every item, so that the memory access pattern doesn't leak the index.
+            (0..vec_size).fold(F::ZERO, |acc, i| {
+                let item = get_local_wire(self.gate.wire_list_item(i, copy));
+                F::ct_select(CtMask::eq_u64(i as u64, access_index as u64), item, acc)
+            })
+        } else {
+            get_local_wire(self.gate.wire_list_item(access_index, copy))
+        };
+        set_local_wire(self.gate.wire_claimed_element(copy), claimed_element);
 
         for i in 0..self.gate.bits {
             let bit = F::from_bool(((access_index >> i) & 1) != 0);
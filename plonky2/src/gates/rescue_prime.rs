@@ -0,0 +1,439 @@
+use std::marker::PhantomData;
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+
+use crate::gates::gate::Gate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::hash::hashing::SPONGE_WIDTH;
+use crate::hash::rescue_prime::{
+    self, rescue_prime_constant_layer, rescue_prime_mds_layer, RESCUE_PRIME_ALPHA,
+    RESCUE_PRIME_N_ROUNDS,
+};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+
+/// Evaluates a full Rescue-Prime permutation with 12 state elements.
+///
+/// The outputs of each inverse S-box are stored as wires, and checked by raising them to the power
+/// `alpha`. Like `PoseidonGate`, this has a flag which can be used to swap the first four inputs
+/// with the next four, for ordering sibling digests.
+#[derive(Debug)]
+pub struct RescuePrimeGate<F: RichField + Extendable<D>, const D: usize> {
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> RescuePrimeGate<F, D> {
+    pub fn new() -> Self {
+        RescuePrimeGate {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The wire index for the `i`th input to the permutation.
+    pub fn wire_input(i: usize) -> usize {
+        i
+    }
+
+    /// The wire index for the `i`th output to the permutation.
+    pub fn wire_output(i: usize) -> usize {
+        SPONGE_WIDTH + i
+    }
+
+    /// If this is set to 1, the first four inputs will be swapped with the next four inputs. This
+    /// is useful for ordering hashes in Merkle proofs. Otherwise, this should be set to 0.
+    pub const WIRE_SWAP: usize = 2 * SPONGE_WIDTH;
+
+    const START_DELTA: usize = 2 * SPONGE_WIDTH + 1;
+
+    /// A wire which stores `swap * (input[i + 4] - input[i])`; used to compute the swapped inputs.
+    fn wire_delta(i: usize) -> usize {
+        assert!(i < 4);
+        Self::START_DELTA + i
+    }
+
+    const START_INVERSE_SBOX: usize = Self::START_DELTA + 4;
+
+    /// A wire which stores the output of the `i`-th inverse S-box of the `round`-th round.
+    fn wire_inverse_sbox_output(round: usize, i: usize) -> usize {
+        debug_assert!(round < RESCUE_PRIME_N_ROUNDS);
+        debug_assert!(i < SPONGE_WIDTH);
+        Self::START_INVERSE_SBOX + SPONGE_WIDTH * round + i
+    }
+
+    /// End of wire indices, exclusive.
+    fn end() -> usize {
+        Self::START_INVERSE_SBOX + SPONGE_WIDTH * RESCUE_PRIME_N_ROUNDS
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for RescuePrimeGate<F, D> {
+    fn id(&self) -> String {
+        format!("{:?}<WIDTH={}>", self, SPONGE_WIDTH)
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        // Assert that `swap` is binary.
+        let swap = vars.local_wires[Self::WIRE_SWAP];
+        constraints.push(swap * (swap - F::Extension::ONE));
+
+        // Assert that each delta wire is set properly: `delta_i = swap * (rhs - lhs)`.
+        for i in 0..4 {
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            constraints.push(swap * (input_rhs - input_lhs) - delta_i);
+        }
+
+        // Compute the possibly-swapped input layer.
+        let mut state = [F::Extension::ZERO; SPONGE_WIDTH];
+        for i in 0..4 {
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let input_lhs = Self::wire_input(i);
+            let input_rhs = Self::wire_input(i + 4);
+            state[i] = vars.local_wires[input_lhs] + delta_i;
+            state[i + 4] = vars.local_wires[input_rhs] - delta_i;
+        }
+        for i in 8..SPONGE_WIDTH {
+            state[i] = vars.local_wires[Self::wire_input(i)];
+        }
+
+        for round in 0..RESCUE_PRIME_N_ROUNDS {
+            for s in state.iter_mut() {
+                *s = s.exp_u64(RESCUE_PRIME_ALPHA);
+            }
+            state = rescue_prime_mds_layer(&state);
+            rescue_prime_constant_layer(&mut state, 2 * round);
+
+            for i in 0..SPONGE_WIDTH {
+                let sbox_out = vars.local_wires[Self::wire_inverse_sbox_output(round, i)];
+                constraints.push(sbox_out.exp_u64(RESCUE_PRIME_ALPHA) - state[i]);
+                state[i] = sbox_out;
+            }
+            state = rescue_prime_mds_layer(&state);
+            rescue_prime_constant_layer(&mut state, 2 * round + 1);
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            constraints.push(state[i] - vars.local_wires[Self::wire_output(i)]);
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        // Assert that `swap` is binary.
+        let swap = vars.local_wires[Self::WIRE_SWAP];
+        yield_constr.one(swap * swap.sub_one());
+
+        // Assert that each delta wire is set properly: `delta_i = swap * (rhs - lhs)`.
+        for i in 0..4 {
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            yield_constr.one(swap * (input_rhs - input_lhs) - delta_i);
+        }
+
+        // Compute the possibly-swapped input layer.
+        let mut state = [F::ZERO; SPONGE_WIDTH];
+        for i in 0..4 {
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let input_lhs = Self::wire_input(i);
+            let input_rhs = Self::wire_input(i + 4);
+            state[i] = vars.local_wires[input_lhs] + delta_i;
+            state[i + 4] = vars.local_wires[input_rhs] - delta_i;
+        }
+        for i in 8..SPONGE_WIDTH {
+            state[i] = vars.local_wires[Self::wire_input(i)];
+        }
+
+        for round in 0..RESCUE_PRIME_N_ROUNDS {
+            for s in state.iter_mut() {
+                *s = s.exp_u64(RESCUE_PRIME_ALPHA);
+            }
+            state = rescue_prime_mds_layer(&state);
+            rescue_prime_constant_layer(&mut state, 2 * round);
+
+            for i in 0..SPONGE_WIDTH {
+                let sbox_out = vars.local_wires[Self::wire_inverse_sbox_output(round, i)];
+                yield_constr.one(sbox_out.exp_u64(RESCUE_PRIME_ALPHA) - state[i]);
+                state[i] = sbox_out;
+            }
+            state = rescue_prime_mds_layer(&state);
+            rescue_prime_constant_layer(&mut state, 2 * round + 1);
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            yield_constr.one(state[i] - vars.local_wires[Self::wire_output(i)]);
+        }
+    }
+
+    fn eval_unfiltered_recursively(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        // Assert that `swap` is binary.
+        let swap = vars.local_wires[Self::WIRE_SWAP];
+        constraints.push(builder.mul_sub_extension(swap, swap, swap));
+
+        // Assert that each delta wire is set properly: `delta_i = swap * (rhs - lhs)`.
+        for i in 0..4 {
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let diff = builder.sub_extension(input_rhs, input_lhs);
+            constraints.push(builder.mul_sub_extension(swap, diff, delta_i));
+        }
+
+        // Compute the possibly-swapped input layer.
+        let mut state = [builder.zero_extension(); SPONGE_WIDTH];
+        for i in 0..4 {
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            state[i] = builder.add_extension(input_lhs, delta_i);
+            state[i + 4] = builder.sub_extension(input_rhs, delta_i);
+        }
+        for i in 8..SPONGE_WIDTH {
+            state[i] = vars.local_wires[Self::wire_input(i)];
+        }
+
+        for round in 0..RESCUE_PRIME_N_ROUNDS {
+            for s in state.iter_mut() {
+                *s = builder.exp_u64_extension(*s, RESCUE_PRIME_ALPHA);
+            }
+            state = rescue_prime::rescue_prime_mds_layer_recursive(builder, &state);
+            rescue_prime::rescue_prime_constant_layer_recursive(builder, &mut state, 2 * round);
+
+            for i in 0..SPONGE_WIDTH {
+                let sbox_out = vars.local_wires[Self::wire_inverse_sbox_output(round, i)];
+                let sbox_in = builder.exp_u64_extension(sbox_out, RESCUE_PRIME_ALPHA);
+                constraints.push(builder.sub_extension(sbox_in, state[i]));
+                state[i] = sbox_out;
+            }
+            state = rescue_prime::rescue_prime_mds_layer_recursive(builder, &state);
+            rescue_prime::rescue_prime_constant_layer_recursive(builder, &mut state, 2 * round + 1);
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            constraints
+                .push(builder.sub_extension(state[i], vars.local_wires[Self::wire_output(i)]));
+        }
+
+        constraints
+    }
+
+    fn generators(
+        &self,
+        gate_index: usize,
+        _local_constants: &[F],
+    ) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        let gen = RescuePrimeGenerator::<F, D> {
+            gate_index,
+            _phantom: PhantomData,
+        };
+        vec![Box::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        Self::end()
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        RESCUE_PRIME_ALPHA as usize
+    }
+
+    fn num_constraints(&self) -> usize {
+        SPONGE_WIDTH * RESCUE_PRIME_N_ROUNDS + SPONGE_WIDTH + 1 + 4
+    }
+}
+
+#[derive(Debug)]
+struct RescuePrimeGenerator<F: RichField + Extendable<D>, const D: usize> {
+    gate_index: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
+    for RescuePrimeGenerator<F, D>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        (0..SPONGE_WIDTH)
+            .map(|i| RescuePrimeGate::<F, D>::wire_input(i))
+            .chain(Some(RescuePrimeGate::<F, D>::WIRE_SWAP))
+            .map(|input| Target::wire(self.gate_index, input))
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_wire = |input| Wire {
+            gate: self.gate_index,
+            input,
+        };
+
+        let mut state = (0..SPONGE_WIDTH)
+            .map(|i| witness.get_wire(local_wire(RescuePrimeGate::<F, D>::wire_input(i))))
+            .collect::<Vec<_>>();
+
+        let swap_value = witness.get_wire(local_wire(RescuePrimeGate::<F, D>::WIRE_SWAP));
+        debug_assert!(swap_value == F::ZERO || swap_value == F::ONE);
+
+        for i in 0..4 {
+            let delta_i = swap_value * (state[i + 4] - state[i]);
+            out_buffer.set_wire(local_wire(RescuePrimeGate::<F, D>::wire_delta(i)), delta_i);
+        }
+
+        if swap_value == F::ONE {
+            for i in 0..4 {
+                state.swap(i, 4 + i);
+            }
+        }
+
+        let mut state: [F; SPONGE_WIDTH] = state.try_into().unwrap();
+        for round in 0..RESCUE_PRIME_N_ROUNDS {
+            for s in state.iter_mut() {
+                *s = s.exp_u64(RESCUE_PRIME_ALPHA);
+            }
+            state = rescue_prime_mds_layer(&state);
+            rescue_prime_constant_layer(&mut state, 2 * round);
+
+            for i in 0..SPONGE_WIDTH {
+                state[i] = rescue_prime::rescue_prime_inverse_sbox(state[i]);
+                out_buffer.set_wire(
+                    local_wire(RescuePrimeGate::<F, D>::wire_inverse_sbox_output(round, i)),
+                    state[i],
+                );
+            }
+            state = rescue_prime_mds_layer(&state);
+            rescue_prime_constant_layer(&mut state, 2 * round + 1);
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            out_buffer.set_wire(
+                local_wire(RescuePrimeGate::<F, D>::wire_output(i)),
+                state[i],
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::rescue_prime::RescuePrimeGate;
+    use crate::hash::hashing::SPONGE_WIDTH;
+    use crate::hash::rescue_prime::rescue_prime_permutation;
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::wire::Wire;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn wire_indices() {
+        type F = GoldilocksField;
+        type Gate = RescuePrimeGate<F, 4>;
+
+        assert_eq!(Gate::wire_input(0), 0);
+        assert_eq!(Gate::wire_input(11), 11);
+        assert_eq!(Gate::wire_output(0), 12);
+        assert_eq!(Gate::wire_output(11), 23);
+        assert_eq!(Gate::WIRE_SWAP, 24);
+        assert_eq!(Gate::wire_delta(0), 25);
+        assert_eq!(Gate::wire_delta(3), 28);
+        assert_eq!(Gate::wire_inverse_sbox_output(0, 0), 29);
+        assert_eq!(Gate::wire_inverse_sbox_output(7, 11), 124);
+        assert_eq!(Gate::end(), 125);
+    }
+
+    #[test]
+    fn generated_output() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::new(config);
+        type Gate = RescuePrimeGate<F, D>;
+        let gate = Gate::new();
+        let gate_index = builder.add_gate(gate, vec![]);
+        let circuit = builder.build_prover::<C>();
+
+        let permutation_inputs = (0..SPONGE_WIDTH)
+            .map(F::from_canonical_usize)
+            .collect::<Vec<_>>();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_wire(
+            Wire {
+                gate: gate_index,
+                input: Gate::WIRE_SWAP,
+            },
+            F::ONE,
+        );
+        for i in 0..SPONGE_WIDTH {
+            inputs.set_wire(
+                Wire {
+                    gate: gate_index,
+                    input: Gate::wire_input(i),
+                },
+                permutation_inputs[i],
+            );
+        }
+
+        let witness = generate_partial_witness(inputs, &circuit.prover_only, &circuit.common);
+
+        let mut swapped_inputs = permutation_inputs;
+        for i in 0..4 {
+            swapped_inputs.swap(i, 4 + i);
+        }
+        let expected_outputs = rescue_prime_permutation::<F>(swapped_inputs.try_into().unwrap());
+        for i in 0..SPONGE_WIDTH {
+            let out = witness.get_wire(Wire {
+                gate: 0,
+                input: Gate::wire_output(i),
+            });
+            assert_eq!(out, expected_outputs[i]);
+        }
+    }
+
+    #[test]
+    fn low_degree() {
+        type F = GoldilocksField;
+        let gate = RescuePrimeGate::<F, 4>::new();
+        test_low_degree(gate)
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = RescuePrimeGate::<F, 2>::new();
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+}
@@ -1,5 +1,6 @@
 use std::marker::PhantomData;
 
+use plonky2_field::constant_time::CtMask;
 use plonky2_field::extension_field::Extendable;
 use plonky2_field::field_types::Field;
 use plonky2_field::packed_field::PackedField;
@@ -298,7 +299,10 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
 
         let result_initial = input_x - input_y - input_borrow;
         let result_initial_u64 = result_initial.to_canonical_u64();
-        let output_borrow = if result_initial_u64 > 1 << 32u64 {
+        let output_borrow = if cfg!(feature = "constant-time") {
+            let borrowed = CtMask::lt_u64(1 << 32u64, result_initial_u64);
+            F::ct_select(borrowed, F::ONE, F::ZERO)
+        } else if result_initial_u64 > 1 << 32u64 {
             F::ONE
         } else {
             F::ZERO
@@ -0,0 +1,476 @@
+use std::marker::PhantomData;
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+
+use crate::gates::gate::Gate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::hash::hashing::SPONGE_WIDTH;
+use crate::hash::tip5::{
+    self, tip5_constant_layer, tip5_mds_layer, tip5_power_map, tip5_split_and_lookup,
+    TIP5_N_ROUNDS, TIP5_N_SPLIT_AND_LOOKUP, TIP5_WIDTH,
+};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+
+/// Evaluates the Tip5 permutation on 12 state elements, padded with zeros to the 16 elements of the
+/// Tip5 state, and outputs the first 12 elements of the result.
+///
+/// The split-and-lookup S-box can't be expressed by low-degree constraints, so its inputs and
+/// outputs are routed wires, and it is up to the caller to check the lookups, e.g. with
+/// `CircuitBuilder::tip5_split_and_lookup`. Like `PoseidonGate`, this has a flag which can be used
+/// to swap the first four inputs with the next four, for ordering sibling digests.
+#[derive(Debug)]
+pub struct Tip5Gate<F: RichField + Extendable<D>, const D: usize> {
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Tip5Gate<F, D> {
+    pub fn new() -> Self {
+        Tip5Gate {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The wire index for the `i`th input to the permutation.
+    pub fn wire_input(i: usize) -> usize {
+        debug_assert!(i < SPONGE_WIDTH);
+        i
+    }
+
+    /// The wire index for the `i`th output to the permutation.
+    pub fn wire_output(i: usize) -> usize {
+        debug_assert!(i < SPONGE_WIDTH);
+        SPONGE_WIDTH + i
+    }
+
+    /// If this is set to 1, the first four inputs will be swapped with the next four inputs. This
+    /// is useful for ordering hashes in Merkle proofs. Otherwise, this should be set to 0.
+    pub const WIRE_SWAP: usize = 2 * SPONGE_WIDTH;
+
+    const START_DELTA: usize = 2 * SPONGE_WIDTH + 1;
+
+    /// A wire which stores `swap * (input[i + 4] - input[i])`; used to compute the swapped inputs.
+    fn wire_delta(i: usize) -> usize {
+        assert!(i < 4);
+        Self::START_DELTA + i
+    }
+
+    const START_LOOKUP: usize = Self::START_DELTA + 4;
+
+    /// A wire which stores the input of the `i`-th split-and-lookup S-box of the `round`-th round.
+    pub fn wire_lookup_input(round: usize, i: usize) -> usize {
+        debug_assert!(round < TIP5_N_ROUNDS);
+        debug_assert!(i < TIP5_N_SPLIT_AND_LOOKUP);
+        Self::START_LOOKUP + 2 * TIP5_N_SPLIT_AND_LOOKUP * round + i
+    }
+
+    /// A wire which stores the output of the `i`-th split-and-lookup S-box of the `round`-th round.
+    pub fn wire_lookup_output(round: usize, i: usize) -> usize {
+        debug_assert!(round < TIP5_N_ROUNDS);
+        debug_assert!(i < TIP5_N_SPLIT_AND_LOOKUP);
+        Self::START_LOOKUP + 2 * TIP5_N_SPLIT_AND_LOOKUP * round + TIP5_N_SPLIT_AND_LOOKUP + i
+    }
+
+    const START_POWER: usize = Self::START_LOOKUP + 2 * TIP5_N_SPLIT_AND_LOOKUP * TIP5_N_ROUNDS;
+
+    /// A wire which stores the input of the power map applied to the `i`-th state element in the
+    /// `round`-th round.
+    fn wire_power_input(round: usize, i: usize) -> usize {
+        debug_assert!(
+            round != 0,
+            "First round power map inputs are not stored as wires"
+        );
+        debug_assert!(round < TIP5_N_ROUNDS);
+        debug_assert!((TIP5_N_SPLIT_AND_LOOKUP..TIP5_WIDTH).contains(&i));
+        Self::START_POWER
+            + (TIP5_WIDTH - TIP5_N_SPLIT_AND_LOOKUP) * (round - 1)
+            + (i - TIP5_N_SPLIT_AND_LOOKUP)
+    }
+
+    /// End of wire indices, exclusive.
+    fn end() -> usize {
+        Self::START_POWER + (TIP5_WIDTH - TIP5_N_SPLIT_AND_LOOKUP) * (TIP5_N_ROUNDS - 1)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for Tip5Gate<F, D> {
+    fn id(&self) -> String {
+        format!("{:?}<WIDTH={}>", self, SPONGE_WIDTH)
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        // Assert that `swap` is binary.
+        let swap = vars.local_wires[Self::WIRE_SWAP];
+        constraints.push(swap * (swap - F::Extension::ONE));
+
+        // Assert that each delta wire is set properly: `delta_i = swap * (rhs - lhs)`.
+        for i in 0..4 {
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            constraints.push(swap * (input_rhs - input_lhs) - delta_i);
+        }
+
+        // Compute the possibly-swapped input layer, padded with zeros.
+        let mut state = [F::Extension::ZERO; TIP5_WIDTH];
+        for i in 0..4 {
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let input_lhs = Self::wire_input(i);
+            let input_rhs = Self::wire_input(i + 4);
+            state[i] = vars.local_wires[input_lhs] + delta_i;
+            state[i + 4] = vars.local_wires[input_rhs] - delta_i;
+        }
+        for i in 8..SPONGE_WIDTH {
+            state[i] = vars.local_wires[Self::wire_input(i)];
+        }
+
+        for round in 0..TIP5_N_ROUNDS {
+            for i in 0..TIP5_N_SPLIT_AND_LOOKUP {
+                let lookup_in = vars.local_wires[Self::wire_lookup_input(round, i)];
+                constraints.push(state[i] - lookup_in);
+                state[i] = vars.local_wires[Self::wire_lookup_output(round, i)];
+            }
+            for i in TIP5_N_SPLIT_AND_LOOKUP..TIP5_WIDTH {
+                if round != 0 {
+                    let power_in = vars.local_wires[Self::wire_power_input(round, i)];
+                    constraints.push(state[i] - power_in);
+                    state[i] = power_in;
+                }
+                state[i] = tip5_power_map(state[i]);
+            }
+            state = tip5_mds_layer(&state);
+            tip5_constant_layer(&mut state, round);
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            constraints.push(state[i] - vars.local_wires[Self::wire_output(i)]);
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        // Assert that `swap` is binary.
+        let swap = vars.local_wires[Self::WIRE_SWAP];
+        yield_constr.one(swap * swap.sub_one());
+
+        // Assert that each delta wire is set properly: `delta_i = swap * (rhs - lhs)`.
+        for i in 0..4 {
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            yield_constr.one(swap * (input_rhs - input_lhs) - delta_i);
+        }
+
+        // Compute the possibly-swapped input layer, padded with zeros.
+        let mut state = [F::ZERO; TIP5_WIDTH];
+        for i in 0..4 {
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let input_lhs = Self::wire_input(i);
+            let input_rhs = Self::wire_input(i + 4);
+            state[i] = vars.local_wires[input_lhs] + delta_i;
+            state[i + 4] = vars.local_wires[input_rhs] - delta_i;
+        }
+        for i in 8..SPONGE_WIDTH {
+            state[i] = vars.local_wires[Self::wire_input(i)];
+        }
+
+        for round in 0..TIP5_N_ROUNDS {
+            for i in 0..TIP5_N_SPLIT_AND_LOOKUP {
+                let lookup_in = vars.local_wires[Self::wire_lookup_input(round, i)];
+                yield_constr.one(state[i] - lookup_in);
+                state[i] = vars.local_wires[Self::wire_lookup_output(round, i)];
+            }
+            for i in TIP5_N_SPLIT_AND_LOOKUP..TIP5_WIDTH {
+                if round != 0 {
+                    let power_in = vars.local_wires[Self::wire_power_input(round, i)];
+                    yield_constr.one(state[i] - power_in);
+                    state[i] = power_in;
+                }
+                state[i] = tip5_power_map(state[i]);
+            }
+            state = tip5_mds_layer(&state);
+            tip5_constant_layer(&mut state, round);
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            yield_constr.one(state[i] - vars.local_wires[Self::wire_output(i)]);
+        }
+    }
+
+    fn eval_unfiltered_recursively(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        // Assert that `swap` is binary.
+        let swap = vars.local_wires[Self::WIRE_SWAP];
+        constraints.push(builder.mul_sub_extension(swap, swap, swap));
+
+        // Assert that each delta wire is set properly: `delta_i = swap * (rhs - lhs)`.
+        for i in 0..4 {
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let diff = builder.sub_extension(input_rhs, input_lhs);
+            constraints.push(builder.mul_sub_extension(swap, diff, delta_i));
+        }
+
+        // Compute the possibly-swapped input layer, padded with zeros.
+        let mut state = [builder.zero_extension(); TIP5_WIDTH];
+        for i in 0..4 {
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            state[i] = builder.add_extension(input_lhs, delta_i);
+            state[i + 4] = builder.sub_extension(input_rhs, delta_i);
+        }
+        for i in 8..SPONGE_WIDTH {
+            state[i] = vars.local_wires[Self::wire_input(i)];
+        }
+
+        for round in 0..TIP5_N_ROUNDS {
+            for i in 0..TIP5_N_SPLIT_AND_LOOKUP {
+                let lookup_in = vars.local_wires[Self::wire_lookup_input(round, i)];
+                constraints.push(builder.sub_extension(state[i], lookup_in));
+                state[i] = vars.local_wires[Self::wire_lookup_output(round, i)];
+            }
+            for i in TIP5_N_SPLIT_AND_LOOKUP..TIP5_WIDTH {
+                if round != 0 {
+                    let power_in = vars.local_wires[Self::wire_power_input(round, i)];
+                    constraints.push(builder.sub_extension(state[i], power_in));
+                    state[i] = power_in;
+                }
+                state[i] = builder.exp_u64_extension(state[i], 7);
+            }
+            state = tip5::tip5_mds_layer_recursive(builder, &state);
+            tip5::tip5_constant_layer_recursive(builder, &mut state, round);
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            constraints
+                .push(builder.sub_extension(state[i], vars.local_wires[Self::wire_output(i)]));
+        }
+
+        constraints
+    }
+
+    fn generators(
+        &self,
+        gate_index: usize,
+        _local_constants: &[F],
+    ) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        let gen = Tip5Generator::<F, D> {
+            gate_index,
+            _phantom: PhantomData,
+        };
+        vec![Box::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        Self::end()
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        7
+    }
+
+    fn num_constraints(&self) -> usize {
+        1 + 4
+            + TIP5_N_SPLIT_AND_LOOKUP * TIP5_N_ROUNDS
+            + (TIP5_WIDTH - TIP5_N_SPLIT_AND_LOOKUP) * (TIP5_N_ROUNDS - 1)
+            + SPONGE_WIDTH
+    }
+}
+
+#[derive(Debug)]
+struct Tip5Generator<F: RichField + Extendable<D>, const D: usize> {
+    gate_index: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F> for Tip5Generator<F, D> {
+    fn dependencies(&self) -> Vec<Target> {
+        (0..SPONGE_WIDTH)
+            .map(|i| Tip5Gate::<F, D>::wire_input(i))
+            .chain(Some(Tip5Gate::<F, D>::WIRE_SWAP))
+            .map(|input| Target::wire(self.gate_index, input))
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_wire = |input| Wire {
+            gate: self.gate_index,
+            input,
+        };
+
+        let mut state = [F::ZERO; TIP5_WIDTH];
+        for i in 0..SPONGE_WIDTH {
+            state[i] = witness.get_wire(local_wire(Tip5Gate::<F, D>::wire_input(i)));
+        }
+
+        let swap_value = witness.get_wire(local_wire(Tip5Gate::<F, D>::WIRE_SWAP));
+        debug_assert!(swap_value == F::ZERO || swap_value == F::ONE);
+
+        for i in 0..4 {
+            let delta_i = swap_value * (state[i + 4] - state[i]);
+            out_buffer.set_wire(local_wire(Tip5Gate::<F, D>::wire_delta(i)), delta_i);
+        }
+
+        if swap_value == F::ONE {
+            for i in 0..4 {
+                state.swap(i, 4 + i);
+            }
+        }
+
+        for round in 0..TIP5_N_ROUNDS {
+            for i in 0..TIP5_N_SPLIT_AND_LOOKUP {
+                out_buffer.set_wire(
+                    local_wire(Tip5Gate::<F, D>::wire_lookup_input(round, i)),
+                    state[i],
+                );
+                state[i] = tip5_split_and_lookup(state[i]);
+                out_buffer.set_wire(
+                    local_wire(Tip5Gate::<F, D>::wire_lookup_output(round, i)),
+                    state[i],
+                );
+            }
+            for i in TIP5_N_SPLIT_AND_LOOKUP..TIP5_WIDTH {
+                if round != 0 {
+                    out_buffer.set_wire(
+                        local_wire(Tip5Gate::<F, D>::wire_power_input(round, i)),
+                        state[i],
+                    );
+                }
+                state[i] = tip5_power_map(state[i]);
+            }
+            state = tip5_mds_layer(&state);
+            tip5_constant_layer(&mut state, round);
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            out_buffer.set_wire(local_wire(Tip5Gate::<F, D>::wire_output(i)), state[i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::tip5::Tip5Gate;
+    use crate::hash::hashing::{PlonkyPermutation, SPONGE_WIDTH};
+    use crate::hash::tip5::Tip5Permutation;
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::wire::Wire;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn wire_indices() {
+        type F = GoldilocksField;
+        type Gate = Tip5Gate<F, 4>;
+
+        assert_eq!(Gate::wire_input(0), 0);
+        assert_eq!(Gate::wire_input(11), 11);
+        assert_eq!(Gate::wire_output(0), 12);
+        assert_eq!(Gate::wire_output(11), 23);
+        assert_eq!(Gate::WIRE_SWAP, 24);
+        assert_eq!(Gate::wire_delta(0), 25);
+        assert_eq!(Gate::wire_delta(3), 28);
+        assert_eq!(Gate::wire_lookup_input(0, 0), 29);
+        assert_eq!(Gate::wire_lookup_output(0, 3), 36);
+        assert_eq!(Gate::wire_lookup_output(4, 3), 68);
+        assert_eq!(Gate::wire_power_input(1, 4), 69);
+        assert_eq!(Gate::wire_power_input(4, 15), 116);
+        assert_eq!(Gate::end(), 117);
+    }
+
+    #[test]
+    fn generated_output() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::new(config);
+        type Gate = Tip5Gate<F, D>;
+        let gate = Gate::new();
+        let gate_index = builder.add_gate(gate, vec![]);
+        let circuit = builder.build_prover::<C>();
+
+        let permutation_inputs = (0..SPONGE_WIDTH)
+            .map(F::from_canonical_usize)
+            .collect::<Vec<_>>();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_wire(
+            Wire {
+                gate: gate_index,
+                input: Gate::WIRE_SWAP,
+            },
+            F::ZERO,
+        );
+        for i in 0..SPONGE_WIDTH {
+            inputs.set_wire(
+                Wire {
+                    gate: gate_index,
+                    input: Gate::wire_input(i),
+                },
+                permutation_inputs[i],
+            );
+        }
+
+        let witness = generate_partial_witness(inputs, &circuit.prover_only, &circuit.common);
+
+        let expected_outputs: [F; SPONGE_WIDTH] =
+            Tip5Permutation::permute(permutation_inputs.try_into().unwrap());
+        for i in 0..SPONGE_WIDTH {
+            let out = witness.get_wire(Wire {
+                gate: 0,
+                input: Gate::wire_output(i),
+            });
+            assert_eq!(out, expected_outputs[i]);
+        }
+    }
+
+    #[test]
+    fn low_degree() {
+        type F = GoldilocksField;
+        let gate = Tip5Gate::<F, 4>::new();
+        test_low_degree(gate)
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = Tip5Gate::<F, 2>::new();
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+}
@@ -1,2 +1,9 @@
-#[cfg(target_feature = "neon")]
+// The module is compiled for every AArch64 target, with NEON enabled on each of its functions, so
+// callers must check `has_neon` first.
 pub(crate) mod poseidon_goldilocks_neon;
+
+/// Whether the running CPU supports NEON, as required by `poseidon_goldilocks_neon`.
+#[inline]
+pub(crate) fn has_neon() -> bool {
+    std::arch::is_aarch64_feature_detected!("neon")
+}
@@ -112,7 +112,8 @@ const_assert!(check_round_const_bounds_init());
 const EPSILON: u64 = 0xffffffff;
 
 /// Addition modulo ORDER accounting for wraparound. Correct only when a + b < 2**64 + ORDER.
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn add_with_wraparound(a: u64, b: u64) -> u64 {
     let res: u64;
     let adj: u64;
@@ -131,7 +132,8 @@ unsafe fn add_with_wraparound(a: u64, b: u64) -> u64 {
 }
 
 /// Subtraction of a and (b >> 32) modulo ORDER accounting for wraparound.
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn sub_with_wraparound_lsr32(a: u64, b: u64) -> u64 {
     let b_hi = b >> 32;
     // This could be done with a.overflowing_add(b_hi), but `checked_sub` signals to the compiler
@@ -149,7 +151,8 @@ unsafe fn sub_with_wraparound_lsr32(a: u64, b: u64) -> u64 {
 }
 
 /// Multiplication of the low word (i.e., x as u32) by EPSILON.
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn mul_epsilon(x: u64) -> u64 {
     let res;
     asm!(
@@ -163,7 +166,8 @@ unsafe fn mul_epsilon(x: u64) -> u64 {
     res
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn multiply(x: u64, y: u64) -> u64 {
     let xy = (x as u128) * (y as u128);
     let xy_lo = xy as u64;
@@ -181,7 +185,8 @@ unsafe fn multiply(x: u64, y: u64) -> u64 {
 
 /// Standalone const layer. Run only once, at the start of round 1. Remaining const layers are fused with the preceeding
 /// MDS matrix multiplication.
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 #[unroll_for_loops]
 unsafe fn const_layer_full(
     mut state: [u64; WIDTH],
@@ -199,7 +204,8 @@ unsafe fn const_layer_full(
 // ========================================== FULL ROUNDS ==========================================
 
 /// Full S-box.
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 #[unroll_for_loops]
 unsafe fn sbox_layer_full(state: [u64; WIDTH]) -> [u64; WIDTH] {
     // This is done in scalar. S-boxes in vector are only slightly slower throughput-wise but have an insane latency
@@ -239,7 +245,8 @@ const MDSI9: i32 = 2; // MDS[9] == 3
 const MDSI10: i32 = 3; // MDS[10] == 16
 const MDSI11: i32 = 1; // MDS[11] == 10
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn mds_reduce(
     [[cumul0_a, cumul0_b], [cumul1_a, cumul1_b]]: [[uint64x2_t; 2]; 2],
 ) -> uint64x2_t {
@@ -270,7 +277,8 @@ unsafe fn mds_reduce(
     vsraq_n_u64::<32>(res_unadj, res_adj)
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn mds_const_layers_full(
     state: [u64; WIDTH],
     round_constants: &[u64; WIDTH],
@@ -540,7 +548,8 @@ macro_rules! mds_reduce_asm {
     };
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn partial_round(
     (state_scalar, state_vector): ([u64; WIDTH], [uint64x2_t; 5]),
     round_constants: &[u64; WIDTH],
@@ -964,13 +973,15 @@ unsafe fn partial_round(
 
 // ========================================== GLUE CODE ===========================================
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn full_round(state: [u64; 12], round_constants: &[u64; WIDTH]) -> [u64; 12] {
     let state = sbox_layer_full(state);
     mds_const_layers_full(state, round_constants)
 }
 
 #[inline]
+#[target_feature(enable = "neon")]
 unsafe fn full_rounds(
     mut state: [u64; 12],
     round_constants: &[u64; WIDTH * HALF_N_FULL_ROUNDS],
@@ -981,7 +992,8 @@ unsafe fn full_rounds(
     state
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 unsafe fn partial_rounds(
     state: [u64; 12],
     round_constants: &[u64; WIDTH * N_PARTIAL_ROUNDS],
@@ -1038,7 +1050,8 @@ fn wrap_state(state: [u64; 12]) -> [GoldilocksField; 12] {
     ]
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 pub unsafe fn poseidon(state: [GoldilocksField; 12]) -> [GoldilocksField; 12] {
     let state = unwrap_state(state);
     let state = const_layer_full(state, ALL_ROUND_CONSTANTS[0..WIDTH].try_into().unwrap());
@@ -1059,12 +1072,14 @@ pub unsafe fn poseidon(state: [GoldilocksField; 12]) -> [GoldilocksField; 12] {
     wrap_state(state)
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 pub unsafe fn sbox_layer(state: &mut [GoldilocksField; WIDTH]) {
     *state = wrap_state(sbox_layer_full(unwrap_state(*state)));
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "neon")]
 pub unsafe fn mds_layer(state: &[GoldilocksField; WIDTH]) -> [GoldilocksField; WIDTH] {
     let state = unwrap_state(*state);
     // We want to do an MDS layer without the constant layer.
@@ -1,5 +1,13 @@
 // Requires:
 // - AVX2
 // - BMI2 (for MULX and SHRX)
-#[cfg(all(target_feature = "avx2", target_feature = "bmi2"))]
+// The module is compiled for every x86-64 target, with these enabled on each of its functions, so
+// callers must check `has_avx2_bmi2` first. AVX is enabled alongside them, since the inline assembly
+// uses `ymm` registers, and every CPU with AVX2 has it.
 pub(crate) mod poseidon_goldilocks_avx2_bmi2;
+
+/// Whether the running CPU supports the extensions required by `poseidon_goldilocks_avx2_bmi2`.
+#[inline]
+pub(crate) fn has_avx2_bmi2() -> bool {
+    is_x86_feature_detected!("avx2") && is_x86_feature_detected!("bmi2")
+}
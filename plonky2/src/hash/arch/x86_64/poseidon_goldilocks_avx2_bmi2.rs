@@ -160,7 +160,8 @@ macro_rules! map3 {
     };
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn const_layer(
     state: (__m256i, __m256i, __m256i),
     round_const_arr: &[u64; 12],
@@ -195,7 +196,8 @@ unsafe fn const_layer(
     res
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn square3(
     x: (__m256i, __m256i, __m256i),
 ) -> ((__m256i, __m256i, __m256i), (__m256i, __m256i, __m256i)) {
@@ -227,7 +229,8 @@ unsafe fn square3(
     (res_lo, res_hi)
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn mul3(
     x: (__m256i, __m256i, __m256i),
     y: (__m256i, __m256i, __m256i),
@@ -280,7 +283,8 @@ unsafe fn mul3(
 }
 
 /// Addition, where the second operand is `0 <= y < 0xffffffff00000001`.
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn add_small(
     x_s: (__m256i, __m256i, __m256i),
     y: (__m256i, __m256i, __m256i),
@@ -292,7 +296,8 @@ unsafe fn add_small(
     res_s
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn maybe_adj_sub(res_wrapped_s: __m256i, mask: __m256i) -> __m256i {
     // The subtraction is very unlikely to overflow so we're best off branching.
     // The even u32s in `mask` are meaningless, so we want to ignore them. `_mm256_testz_pd`
@@ -312,7 +317,8 @@ unsafe fn maybe_adj_sub(res_wrapped_s: __m256i, mask: __m256i) -> __m256i {
 }
 
 /// Addition, where the second operand is much smaller than `0xffffffff00000001`.
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn sub_tiny(
     x_s: (__m256i, __m256i, __m256i),
     y: (__m256i, __m256i, __m256i),
@@ -323,7 +329,8 @@ unsafe fn sub_tiny(
     res_s
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn reduce3(
     (lo0, hi0): ((__m256i, __m256i, __m256i), (__m256i, __m256i, __m256i)),
 ) -> (__m256i, __m256i, __m256i) {
@@ -338,7 +345,8 @@ unsafe fn reduce3(
     lo2
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn sbox_layer_full(state: (__m256i, __m256i, __m256i)) -> (__m256i, __m256i, __m256i) {
     let state2_unreduced = square3(state);
     let state2 = reduce3(state2_unreduced);
@@ -351,7 +359,8 @@ unsafe fn sbox_layer_full(state: (__m256i, __m256i, __m256i)) -> (__m256i, __m25
     state7
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn mds_layer_reduce(
     lo_s: (__m256i, __m256i, __m256i),
     hi: (__m256i, __m256i, __m256i),
@@ -433,7 +442,8 @@ unsafe fn mds_layer_reduce(
     (res0, res1, res2)
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn mds_multiply_and_add_round_const_s(
     state: (__m256i, __m256i, __m256i),
     (base, index): (*const u64, usize),
@@ -713,7 +723,8 @@ unsafe fn mds_multiply_and_add_round_const_s(
     )
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn mds_const_layers_full(
     state: (__m256i, __m256i, __m256i),
     round_constants: (*const u64, usize),
@@ -723,7 +734,8 @@ unsafe fn mds_const_layers_full(
 }
 
 /// Compute x ** 7
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn sbox_partial(mut x: u64) -> u64 {
     // This is done in assembly to fix LLVM's poor treatment of wraparound addition/subtraction
     // and to ensure that multiplication by EPSILON is done with bitshifts, leaving port 1 for
@@ -802,7 +814,8 @@ unsafe fn sbox_partial(mut x: u64) -> u64 {
     x
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn partial_round(
     (state0, state1, state2): (__m256i, __m256i, __m256i),
     round_constants: (*const u64, usize),
@@ -878,7 +891,8 @@ unsafe fn partial_round(
     )
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn full_round(
     state: (__m256i, __m256i, __m256i),
     round_constants: (*const u64, usize),
@@ -889,6 +903,7 @@ unsafe fn full_round(
 }
 
 #[inline] // Called twice; permit inlining but don't _require_ it
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn half_full_rounds(
     mut state: (__m256i, __m256i, __m256i),
     start_round: usize,
@@ -903,7 +918,8 @@ unsafe fn half_full_rounds(
     state
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn all_partial_rounds(
     mut state: (__m256i, __m256i, __m256i),
     start_round: usize,
@@ -918,7 +934,8 @@ unsafe fn all_partial_rounds(
     state
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn load_state(state: &[GoldilocksField; 12]) -> (__m256i, __m256i, __m256i) {
     (
         _mm256_loadu_si256((&state[0..4]).as_ptr().cast::<__m256i>()),
@@ -927,7 +944,8 @@ unsafe fn load_state(state: &[GoldilocksField; 12]) -> (__m256i, __m256i, __m256
     )
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 unsafe fn store_state(buf: &mut [GoldilocksField; 12], state: (__m256i, __m256i, __m256i)) {
     _mm256_storeu_si256((&mut buf[0..4]).as_mut_ptr().cast::<__m256i>(), state.0);
     _mm256_storeu_si256((&mut buf[4..8]).as_mut_ptr().cast::<__m256i>(), state.1);
@@ -935,6 +953,7 @@ unsafe fn store_state(buf: &mut [GoldilocksField; 12], state: (__m256i, __m256i,
 }
 
 #[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 pub unsafe fn poseidon(state: &[GoldilocksField; 12]) -> [GoldilocksField; 12] {
     let state = load_state(state);
 
@@ -951,7 +970,8 @@ pub unsafe fn poseidon(state: &[GoldilocksField; 12]) -> [GoldilocksField; 12] {
     res
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 pub unsafe fn constant_layer(state_arr: &mut [GoldilocksField; WIDTH], round_ctr: usize) {
     let state = load_state(state_arr);
     let round_consts = &ALL_ROUND_CONSTANTS[WIDTH * round_ctr..][..WIDTH]
@@ -961,14 +981,16 @@ pub unsafe fn constant_layer(state_arr: &mut [GoldilocksField; WIDTH], round_ctr
     store_state(state_arr, state);
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 pub unsafe fn sbox_layer(state_arr: &mut [GoldilocksField; WIDTH]) {
     let state = load_state(state_arr);
     let state = sbox_layer_full(state);
     store_state(state_arr, state);
 }
 
-#[inline(always)]
+#[inline]
+#[target_feature(enable = "avx,avx2,bmi2")]
 pub unsafe fn mds_layer(state: &[GoldilocksField; WIDTH]) -> [GoldilocksField; WIDTH] {
     let state = load_state(state);
     // We want to do an MDS layer without the constant layer.
@@ -0,0 +1,174 @@
+//! Hashing of byte strings to field elements, for binding external data into circuits.
+//!
+//! Bytes are packed into field elements 7 at a time, which is injective since `2^56 < p`, and the
+//! packed elements are absorbed into a sponge after the lengths of the domain separation tag and of
+//! the message. The outputs are squeezed from the sponge, so they are uniform field elements rather
+//! than truncated byte strings, and have no bias beyond that of the permutation itself.
+
+use plonky2_field::extension_field::{Extendable, FieldExtension};
+
+use crate::hash::hash_types::RichField;
+use crate::hash::hashing::hash_n_to_m_no_pad;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// The number of bytes packed into each field element.
+const BYTES_PER_ELEMENT: usize = 7;
+
+fn pack_bytes<F: RichField>(bytes: &[u8]) -> impl Iterator<Item = F> + '_ {
+    bytes.chunks(BYTES_PER_ELEMENT).map(|chunk| {
+        let mut limb = [0; 8];
+        limb[..chunk.len()].copy_from_slice(chunk);
+        F::from_canonical_u64(u64::from_le_bytes(limb))
+    })
+}
+
+/// Hashes `message` to `num_elements` field elements. The domain separation tag `dst` should be
+/// unique to each application, so that outputs for different purposes are independent.
+pub fn hash_to_field<F: RichField, H: AlgebraicHasher<F>>(
+    dst: &[u8],
+    message: &[u8],
+    num_elements: usize,
+) -> Vec<F> {
+    let inputs = [
+        F::from_canonical_usize(dst.len()),
+        F::from_canonical_usize(message.len()),
+    ]
+    .into_iter()
+    .chain(pack_bytes(dst))
+    .chain(pack_bytes(message))
+    .collect::<Vec<_>>();
+    hash_n_to_m_no_pad::<F, H::Permutation>(&inputs, num_elements)
+}
+
+/// Hashes `message` to `num_elements` extension field elements, whose coordinates are consecutive
+/// outputs of `hash_to_field`.
+pub fn hash_to_extension_field<
+    F: RichField + Extendable<D>,
+    H: AlgebraicHasher<F>,
+    const D: usize,
+>(
+    dst: &[u8],
+    message: &[u8],
+    num_elements: usize,
+) -> Vec<F::Extension> {
+    hash_to_field::<F, H>(dst, message, D * num_elements)
+        .chunks(D)
+        .map(|chunk| F::Extension::from_basefield_array(chunk.try_into().unwrap()))
+        .collect()
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// In-circuit version of `hash_to_field`, where the message is a sequence of bytes whose length
+    /// is fixed at circuit build time. Message bytes are range-checked.
+    pub fn hash_to_field<H: AlgebraicHasher<F>>(
+        &mut self,
+        dst: &[u8],
+        message: &[Target],
+        num_elements: usize,
+    ) -> Vec<Target> {
+        for &b in message {
+            self.range_check(b, 8);
+        }
+
+        let mut inputs = vec![
+            self.constant(F::from_canonical_usize(dst.len())),
+            self.constant(F::from_canonical_usize(message.len())),
+        ];
+        inputs.extend(
+            pack_bytes(dst)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|x| self.constant(x)),
+        );
+        let base = F::from_canonical_u32(1 << 8);
+        for chunk in message.chunks(BYTES_PER_ELEMENT) {
+            let mut limb = self.zero();
+            for &b in chunk.iter().rev() {
+                limb = self.mul_const_add(base, limb, b);
+            }
+            inputs.push(limb);
+        }
+
+        self.hash_n_to_m_no_pad::<H>(inputs, num_elements)
+    }
+
+    /// In-circuit version of `hash_to_extension_field`.
+    pub fn hash_to_extension_field<H: AlgebraicHasher<F>>(
+        &mut self,
+        dst: &[u8],
+        message: &[Target],
+        num_elements: usize,
+    ) -> Vec<ExtensionTarget<D>> {
+        self.hash_to_field::<H>(dst, message, D * num_elements)
+            .chunks(D)
+            .map(|chunk| ExtensionTarget(chunk.try_into().unwrap()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use rand::{thread_rng, Rng};
+
+    use crate::hash::hash_to_field::{hash_to_extension_field, hash_to_field};
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const DST: &[u8] = b"plonky2-hash-to-field-test";
+
+    #[test]
+    fn test_domain_separation() {
+        let message = b"message";
+        let x = hash_to_field::<F, PoseidonHash>(DST, message, 2);
+        assert_eq!(x.len(), 2);
+        assert_ne!(x, hash_to_field::<F, PoseidonHash>(b"other", message, 2));
+        // Messages which only differ by trailing zero bytes pack to the same elements, so the
+        // message length must separate them.
+        assert_ne!(x, hash_to_field::<F, PoseidonHash>(DST, b"message\0", 2));
+    }
+
+    #[test]
+    fn test_hash_to_field_circuit() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = thread_rng();
+        let message = (0..40).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+        let message_targets = builder.add_virtual_targets(message.len());
+        for (&t, &b) in message_targets.iter().zip(&message) {
+            pw.set_target(t, F::from_canonical_u16(b as u16));
+        }
+
+        let outputs = builder.hash_to_field::<PoseidonHash>(DST, &message_targets, 10);
+        let expected = hash_to_field::<F, PoseidonHash>(DST, &message, 10);
+        for (o, e) in outputs.into_iter().zip(expected) {
+            let e = builder.constant(e);
+            builder.connect(o, e);
+        }
+
+        let outputs = builder.hash_to_extension_field::<PoseidonHash>(DST, &message_targets, 3);
+        let expected = hash_to_extension_field::<F, PoseidonHash, D>(DST, &message, 3);
+        for (o, e) in outputs.into_iter().zip(expected) {
+            let e = builder.constant_extension(e);
+            builder.connect_extension(o, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -1,3 +1,4 @@
+use plonky2_field::constant_time::ConstantTimeField;
 use plonky2_field::field_types::{Field, PrimeField64};
 use plonky2_field::goldilocks_field::GoldilocksField;
 use rand::Rng;
@@ -8,7 +9,7 @@ use crate::iop::target::Target;
 use crate::plonk::config::GenericHashOut;
 
 /// A prime order field with the features we need to use it as a base field in our argument system.
-pub trait RichField: PrimeField64 + Poseidon {}
+pub trait RichField: PrimeField64 + Poseidon + ConstantTimeField {}
 
 impl RichField for GoldilocksField {}
 
@@ -75,6 +76,13 @@ impl<F: RichField> GenericHashOut<F> for HashOut<F> {
         }
     }
 
+    fn is_canonical(bytes: &[u8]) -> bool {
+        bytes
+            .chunks(8)
+            .take(4)
+            .all(|x| u64::from_le_bytes(x.try_into().unwrap()) < F::ORDER)
+    }
+
     fn to_vec(&self) -> Vec<F> {
         self.elements.to_vec()
     }
@@ -135,6 +143,10 @@ impl<F: RichField, const N: usize> GenericHashOut<F> for BytesHash<N> {
         Self(bytes.try_into().unwrap())
     }
 
+    fn is_canonical(_bytes: &[u8]) -> bool {
+        true
+    }
+
     fn to_vec(&self) -> Vec<F> {
         self.0
             // Chunks of 7 bytes since 8 bytes would allow collisions.
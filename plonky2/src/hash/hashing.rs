@@ -1,12 +1,15 @@
 //! Concrete instantiation of a hash function.
 
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+
 use plonky2_field::extension_field::Extendable;
 
 use crate::hash::hash_types::RichField;
 use crate::hash::hash_types::{HashOut, HashOutTarget};
-use crate::iop::target::Target;
+use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::AlgebraicHasher;
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain, Hasher};
 
 pub(crate) const SPONGE_RATE: usize = 8;
 pub(crate) const SPONGE_CAPACITY: usize = 4;
@@ -71,21 +74,135 @@ pub fn compress<F: RichField, P: PlonkyPermutation<F>>(x: HashOut<F>, y: HashOut
     }
 }
 
+/// Like `compress`, but with the tag of the given domain in the first capacity element.
+pub fn compress_with_domain<F: RichField, P: PlonkyPermutation<F>>(
+    x: HashOut<F>,
+    y: HashOut<F>,
+    domain: CompressionDomain,
+) -> HashOut<F> {
+    let mut perm_inputs = [F::ZERO; SPONGE_WIDTH];
+    perm_inputs[..4].copy_from_slice(&x.elements);
+    perm_inputs[4..8].copy_from_slice(&y.elements);
+    perm_inputs[8] = F::from_canonical_u16(domain.tag().into());
+    HashOut {
+        elements: P::permute(perm_inputs)[..4].try_into().unwrap(),
+    }
+}
+
 /// Permutation that can be used in the sponge construction for an algebraic hash.
 pub trait PlonkyPermutation<F: RichField> {
     fn permute(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH];
 }
 
+/// A permutation which can also be evaluated in a circuit. This is all that is needed to obtain an
+/// algebraic hash function, namely `PermutationHasher<Self>`, which can then be used in a
+/// `GenericConfig`.
+pub trait AlgebraicPermutation<F: RichField>: PlonkyPermutation<F> {
+    /// Circuit version of `permute`.
+    fn permute_circuit<const D: usize>(
+        inputs: [Target; SPONGE_WIDTH],
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> [Target; SPONGE_WIDTH]
+    where
+        F: Extendable<D>;
+}
+
+/// The sponge-based hash function built from the permutation `P`, with the same sponge and
+/// compression function as the built-in algebraic hashers. In circuits, the conditional swap of
+/// Merkle proof verification is done with arithmetic before the permutation, since there is no
+/// dedicated gate to do it in.
+pub struct PermutationHasher<P>(PhantomData<fn() -> P>);
+
+impl<P> Clone for PermutationHasher<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P> Copy for PermutationHasher<P> {}
+
+impl<P> Debug for PermutationHasher<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("PermutationHasher")
+    }
+}
+
+impl<P> PartialEq for PermutationHasher<P> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<P> Eq for PermutationHasher<P> {}
+
+impl<F: RichField, P: AlgebraicPermutation<F>> Hasher<F> for PermutationHasher<P> {
+    const HASH_SIZE: usize = 4 * 8;
+    type Hash = HashOut<F>;
+    type Permutation = P;
+
+    fn hash_no_pad(input: &[F]) -> Self::Hash {
+        hash_n_to_hash_no_pad::<F, P>(input)
+    }
+
+    fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        compress::<F, P>(left, right)
+    }
+
+    fn two_to_one_with_domain(
+        left: Self::Hash,
+        right: Self::Hash,
+        domain: CompressionDomain,
+    ) -> Self::Hash {
+        compress_with_domain::<F, P>(left, right, domain)
+    }
+}
+
+impl<F: RichField, P: AlgebraicPermutation<F>> AlgebraicHasher<F> for PermutationHasher<P> {
+    fn permute_swapped<const D: usize>(
+        inputs: [Target; SPONGE_WIDTH],
+        swap: BoolTarget,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> [Target; SPONGE_WIDTH]
+    where
+        F: RichField + Extendable<D>,
+    {
+        let mut inputs = inputs;
+        for i in 0..4 {
+            let (left, right) = (inputs[i], inputs[4 + i]);
+            inputs[i] = builder.select(swap, right, left);
+            inputs[4 + i] = builder.select(swap, left, right);
+        }
+        P::permute_circuit(inputs, builder)
+    }
+}
+
 /// Hash a message without any padding step. Note that this can enable length-extension attacks.
 /// However, it is still collision-resistant in cases where the input has a fixed length.
 pub fn hash_n_to_m_no_pad<F: RichField, P: PlonkyPermutation<F>>(
     inputs: &[F],
     num_outputs: usize,
 ) -> Vec<F> {
+    hash_n_to_m_no_pad_with_rate::<F, P>(inputs, num_outputs, SPONGE_RATE)
+}
+
+/// Like `hash_n_to_m_no_pad`, but absorbing and squeezing `rate` elements per permutation rather
+/// than `SPONGE_RATE`. The rate can be at most `SPONGE_RATE`, so that at least
+/// `SPONGE_WIDTH - SPONGE_RATE` elements of the state are capacity; a smaller rate only costs more
+/// permutations.
+pub fn hash_n_to_m_no_pad_with_rate<F: RichField, P: PlonkyPermutation<F>>(
+    inputs: &[F],
+    num_outputs: usize,
+    rate: usize,
+) -> Vec<F> {
+    assert!(
+        0 < rate && rate <= SPONGE_RATE,
+        "The rate must be between 1 and {}",
+        SPONGE_RATE
+    );
     let mut state = [F::ZERO; SPONGE_WIDTH];
 
     // Absorb all input chunks.
-    for input_chunk in inputs.chunks(SPONGE_RATE) {
+    for input_chunk in inputs.chunks(rate) {
         state[..input_chunk.len()].copy_from_slice(input_chunk);
         state = P::permute(state);
     }
@@ -93,7 +210,7 @@ pub fn hash_n_to_m_no_pad<F: RichField, P: PlonkyPermutation<F>>(
     // Squeeze until we have the desired number of outputs.
     let mut outputs = Vec::new();
     loop {
-        for &item in state.iter().take(SPONGE_RATE) {
+        for &item in state.iter().take(rate) {
             outputs.push(item);
             if outputs.len() == num_outputs {
                 return outputs;
@@ -106,3 +223,170 @@ pub fn hash_n_to_m_no_pad<F: RichField, P: PlonkyPermutation<F>>(
 pub fn hash_n_to_hash_no_pad<F: RichField, P: PlonkyPermutation<F>>(inputs: &[F]) -> HashOut<F> {
     HashOut::from_vec(hash_n_to_m_no_pad::<F, P>(inputs, 4))
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::extension_field::quadratic::QuadraticExtension;
+    use plonky2_field::extension_field::Extendable;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::hash::hash_types::{HashOut, RichField};
+    use crate::hash::hashing::{
+        AlgebraicPermutation, PermutationHasher, PlonkyPermutation, SPONGE_WIDTH,
+    };
+    use crate::hash::merkle_proofs::MerkleProofTarget;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::iop::challenger::{Challenger, RecursiveChallenger};
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher};
+    use crate::plonk::verifier::verify;
+
+    /// A toy permutation, as a user crate might define, alternating `x^7` S-boxes with the
+    /// invertible linear layer `x_i -> x_i + sum_j x_j`. It is not meant to be secure.
+    struct ToyPermutation;
+
+    const TOY_ROUNDS: usize = 8;
+
+    fn toy_round_constant<F: Field>(round: usize, i: usize) -> F {
+        F::from_canonical_usize(round * SPONGE_WIDTH + i + 1)
+    }
+
+    impl<F: RichField> PlonkyPermutation<F> for ToyPermutation {
+        fn permute(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+            let mut state = input;
+            for round in 0..TOY_ROUNDS {
+                for (i, x) in state.iter_mut().enumerate() {
+                    *x = (*x + toy_round_constant(round, i)).exp_u64(7);
+                }
+                let sum = state.iter().copied().sum::<F>();
+                for x in state.iter_mut() {
+                    *x += sum;
+                }
+            }
+            state
+        }
+    }
+
+    impl<F: RichField> AlgebraicPermutation<F> for ToyPermutation {
+        fn permute_circuit<const D: usize>(
+            inputs: [Target; SPONGE_WIDTH],
+            builder: &mut CircuitBuilder<F, D>,
+        ) -> [Target; SPONGE_WIDTH]
+        where
+            F: Extendable<D>,
+        {
+            let mut state = inputs;
+            for round in 0..TOY_ROUNDS {
+                for (i, x) in state.iter_mut().enumerate() {
+                    let y = builder.add_const(*x, toy_round_constant(round, i));
+                    let y2 = builder.square(y);
+                    let y3 = builder.mul(y2, y);
+                    let y4 = builder.square(y2);
+                    *x = builder.mul(y4, y3);
+                }
+                let sum = builder.add_many(&state);
+                for x in state.iter_mut() {
+                    *x = builder.add(*x, sum);
+                }
+            }
+            state
+        }
+    }
+
+    type H = PermutationHasher<ToyPermutation>;
+
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    struct ToyGoldilocksConfig;
+    impl GenericConfig<2> for ToyGoldilocksConfig {
+        type F = GoldilocksField;
+        type FE = QuadraticExtension<Self::F>;
+        type Hasher = H;
+        type CommitPhaseHasher = H;
+        type InnerHasher = H;
+        type PublicInputsHasher = H;
+    }
+
+    const D: usize = 2;
+    type C = ToyGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_permutation_hasher_proof() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // In-circuit hashing agrees with native hashing.
+        let inputs = F::rand_vec(20);
+        let input_targets = builder.add_virtual_targets(inputs.len());
+        for (&t, &x) in input_targets.iter().zip(&inputs) {
+            pw.set_target(t, x);
+        }
+        let hash = builder.hash_n_to_hash_no_pad::<H>(input_targets);
+        let expected = builder.constant_hash(H::hash_no_pad(&inputs));
+        builder.connect_hashes(hash, expected);
+
+        // In-circuit Merkle proof verification, which uses the swapped compression.
+        let log_n = 4;
+        let leaves = (0..1 << log_n).map(|_| F::rand_vec(7)).collect::<Vec<_>>();
+        let tree = MerkleTree::<F, H>::new(leaves, 0);
+        let index = 5;
+        let proof = tree.prove(index);
+        let proof_t = MerkleProofTarget {
+            siblings: builder.add_virtual_hashes(proof.siblings.len()),
+        };
+        for (&t, &h) in proof_t.siblings.iter().zip(&proof.siblings) {
+            pw.set_hash_target(t, h);
+        }
+        let cap_t = builder.add_virtual_cap(0);
+        pw.set_cap_target(&cap_t, &tree.cap);
+        let index_t = builder.constant(F::from_canonical_usize(index));
+        let index_bits = builder.split_le(index_t, log_n);
+        let leaf = tree.leaves[index]
+            .iter()
+            .map(|&x| builder.constant(x))
+            .collect();
+        builder.verify_merkle_proof::<H>(leaf, &index_bits, &cap_t, &proof_t);
+
+        // The Merkle trees, challenger and public input hashing of the proof all use `H`.
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_permutation_hasher_challenger() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let inputs = F::rand_vec(10);
+        let hash = HashOut::rand();
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_elements(&inputs);
+        challenger.observe_hash::<H>(hash);
+        let expected = challenger.get_n_challenges(12);
+
+        let mut recursive_challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        let input_targets = inputs
+            .iter()
+            .map(|&x| builder.constant(x))
+            .collect::<Vec<_>>();
+        recursive_challenger.observe_elements(&input_targets);
+        let hash_target = builder.constant_hash(hash);
+        recursive_challenger.observe_hash(&hash_target);
+        let challenges = recursive_challenger.get_n_challenges(&mut builder, 12);
+        for (c, e) in challenges.into_iter().zip(expected) {
+            let e = builder.constant(e);
+            builder.connect(c, e);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -6,7 +6,8 @@ use keccak_hash::keccak;
 
 use crate::hash::hash_types::{BytesHash, RichField};
 use crate::hash::hashing::{PlonkyPermutation, SPONGE_WIDTH};
-use crate::plonk::config::Hasher;
+use crate::hash::keccak_batch::{keccak256_batch, KECCAK_LANES};
+use crate::plonk::config::{CompressionDomain, Hasher};
 use crate::util::serialization::Buffer;
 
 /// Keccak-256 pseudo-permutation (not necessarily one-to-one) used in the challenger.
@@ -65,6 +66,38 @@ impl<F: RichField, const N: usize> Hasher<F> for KeccakHash<N> {
         BytesHash(arr)
     }
 
+    fn hash_or_noop_batch(inputs: &[Vec<F>]) -> Vec<Self::Hash>
+    where
+        [(); <Self as Hasher<F>>::HASH_SIZE]:,
+    {
+        let mut hashes = Vec::with_capacity(inputs.len());
+        let mut chunks = inputs.chunks_exact(KECCAK_LANES);
+        for chunk in &mut chunks {
+            let len = chunk[0].len();
+            if len * 8 > N && chunk.iter().all(|input| input.len() == len) {
+                let outputs = keccak256_batch::<F, KECCAK_LANES>(chunk);
+                hashes.extend(outputs.iter().map(|output| {
+                    let mut arr = [0; N];
+                    arr.copy_from_slice(&output[..N]);
+                    BytesHash(arr)
+                }));
+            } else {
+                hashes.extend(
+                    chunk
+                        .iter()
+                        .map(|input| <Self as Hasher<F>>::hash_or_noop(input)),
+                );
+            }
+        }
+        hashes.extend(
+            chunks
+                .remainder()
+                .iter()
+                .map(|input| <Self as Hasher<F>>::hash_or_noop(input)),
+        );
+        hashes
+    }
+
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
         let mut v = vec![0; N * 2];
         v[0..N].copy_from_slice(&left.0);
@@ -73,4 +106,43 @@ impl<F: RichField, const N: usize> Hasher<F> for KeccakHash<N> {
         arr.copy_from_slice(&keccak(v).0[..N]);
         BytesHash(arr)
     }
+
+    fn two_to_one_with_domain(
+        left: Self::Hash,
+        right: Self::Hash,
+        domain: CompressionDomain,
+    ) -> Self::Hash {
+        let mut v = vec![0; N * 2 + 1];
+        v[0..N].copy_from_slice(&left.0);
+        v[N..2 * N].copy_from_slice(&right.0);
+        v[2 * N] = domain.tag();
+        let mut arr = [0; N];
+        arr.copy_from_slice(&keccak(v).0[..N]);
+        BytesHash(arr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::hash::keccak::KeccakHash;
+    use crate::plonk::config::Hasher;
+
+    type F = GoldilocksField;
+    type H = KeccakHash<25>;
+
+    #[test]
+    fn test_hash_or_noop_batch() {
+        // Leaves of equal length are hashed together, while short or ragged ones are not.
+        for lens in [vec![10; 19], vec![3; 8], (0..19).collect()] {
+            let inputs = lens.into_iter().map(F::rand_vec).collect::<Vec<_>>();
+            let expected = inputs
+                .iter()
+                .map(|input| H::hash_or_noop(input))
+                .collect::<Vec<_>>();
+            assert_eq!(H::hash_or_noop_batch(&inputs), expected);
+        }
+    }
 }
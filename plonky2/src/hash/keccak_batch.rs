@@ -0,0 +1,166 @@
+//! A Keccak-256 implementation which hashes several messages of equal length at once. The state of
+//! each message occupies one lane of every word, and all operations are applied lane-wise, so that
+//! the compiler can map each word to a SIMD vector.
+
+use crate::hash::hash_types::RichField;
+
+/// The number of messages hashed together by `keccak256_batch` in `KeccakHash`. This matches the
+/// number of 64-bit lanes in the widest vector registers of the target.
+#[cfg(target_feature = "avx512f")]
+pub const KECCAK_LANES: usize = 8;
+#[cfg(not(target_feature = "avx512f"))]
+pub const KECCAK_LANES: usize = 4;
+
+/// The Keccak-256 rate, in 64-bit words.
+const KECCAK256_RATE: usize = 17;
+
+const KECCAK_ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// The rotation offsets of the ρ step, indexed by `x + 5 y`.
+const KECCAK_RHO_OFFSETS: [u32; 25] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// Applies Keccak-f[1600] to `LANES` states at once. Word `x + 5 y` of the state is at
+/// `state[x + 5 y]`, with one lane per state.
+pub fn keccak_f1600_batch<const LANES: usize>(state: &mut [[u64; LANES]; 25]) {
+    for rc in KECCAK_ROUND_CONSTANTS {
+        // θ
+        let mut c = [[0; LANES]; 5];
+        for x in 0..5 {
+            for l in 0..LANES {
+                c[x][l] = state[x][l]
+                    ^ state[x + 5][l]
+                    ^ state[x + 10][l]
+                    ^ state[x + 15][l]
+                    ^ state[x + 20][l];
+            }
+        }
+        for x in 0..5 {
+            for l in 0..LANES {
+                let d = c[(x + 4) % 5][l] ^ c[(x + 1) % 5][l].rotate_left(1);
+                for y in 0..5 {
+                    state[x + 5 * y][l] ^= d;
+                }
+            }
+        }
+
+        // ρ and π
+        let mut b = [[0; LANES]; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                let rho = KECCAK_RHO_OFFSETS[x + 5 * y];
+                for l in 0..LANES {
+                    b[y + 5 * ((2 * x + 3 * y) % 5)][l] = state[x + 5 * y][l].rotate_left(rho);
+                }
+            }
+        }
+
+        // χ
+        for y in 0..5 {
+            for x in 0..5 {
+                for l in 0..LANES {
+                    state[x + 5 * y][l] =
+                        b[x + 5 * y][l] ^ (!b[(x + 1) % 5 + 5 * y][l] & b[(x + 2) % 5 + 5 * y][l]);
+                }
+            }
+        }
+
+        // ι
+        for l in 0..LANES {
+            state[0][l] ^= rc;
+        }
+    }
+}
+
+/// Computes the Keccak-256 hashes of `LANES` sequences of field elements of equal length, where
+/// each element is encoded as 8 little-endian bytes, as in `Buffer::write_field_vec`.
+pub fn keccak256_batch<F: RichField, const LANES: usize>(inputs: &[Vec<F>]) -> [[u8; 32]; LANES] {
+    assert_eq!(inputs.len(), LANES);
+    let len = inputs[0].len();
+    assert!(inputs.iter().all(|input| input.len() == len));
+
+    let mut state = [[0; LANES]; 25];
+    let mut absorbed = 0;
+    // Each field element fills one word, so only the final block needs padding, and the padding
+    // always starts at a word boundary.
+    loop {
+        let block_len = (len - absorbed).min(KECCAK256_RATE);
+        for i in 0..block_len {
+            for (l, input) in inputs.iter().enumerate() {
+                state[i][l] ^= input[absorbed + i].to_canonical_u64();
+            }
+        }
+        absorbed += block_len;
+        if block_len < KECCAK256_RATE {
+            for l in 0..LANES {
+                state[block_len][l] ^= 0x01;
+                state[KECCAK256_RATE - 1][l] ^= 0x80 << 56;
+            }
+            keccak_f1600_batch(&mut state);
+            break;
+        }
+        keccak_f1600_batch(&mut state);
+    }
+
+    let mut outputs = [[0; 32]; LANES];
+    for (l, output) in outputs.iter_mut().enumerate() {
+        for i in 0..4 {
+            output[8 * i..8 * (i + 1)].copy_from_slice(&state[i][l].to_le_bytes());
+        }
+    }
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use keccak_hash::keccak;
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::hash::keccak_batch::{keccak256_batch, KECCAK_LANES};
+    use crate::util::serialization::Buffer;
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_keccak256_batch() {
+        // Include lengths on either side of a block boundary.
+        for len in [0, 1, 5, 16, 17, 18, 33, 34, 35, 135] {
+            let inputs = (0..KECCAK_LANES)
+                .map(|_| F::rand_vec(len))
+                .collect::<Vec<_>>();
+            let outputs = keccak256_batch::<F, KECCAK_LANES>(&inputs);
+            for (input, output) in inputs.iter().zip(outputs) {
+                let mut buffer = Buffer::new(Vec::new());
+                buffer.write_field_vec(input).unwrap();
+                assert_eq!(output, keccak(buffer.bytes()).0, "length {}", len);
+            }
+        }
+    }
+}
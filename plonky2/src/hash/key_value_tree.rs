@@ -0,0 +1,400 @@
+//! A key-value tree of wide arity, e.g. 16 or 256, for stateful applications which want shallower
+//! paths than those of a binary sparse Merkle tree.
+//!
+//! The value at key `k` is stored in leaf `k` of a complete `2^arity_bits`-ary tree, whose leaves
+//! are the hashes of their values, and whose internal nodes are the hashes of all their children.
+//! Absent keys hold the value zero, so a proof that a key holds zero is a proof of non-membership.
+//!
+//! Unlike a Verkle tree, nodes aren't vector commitments to their children, so a proof contains
+//! every child of each node on the path. Proofs are therefore shallower, but not smaller, than
+//! binary Merkle proofs for the same number of keys.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use anyhow::{ensure, Result};
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::iop::target::Target;
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// A proof of the value held at a key of a `KeyValueTree`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyValueTreeProof<F: Field> {
+    /// For each layer, starting from the leaves, the children of the parent of the node on the
+    /// path, including that node.
+    pub children: Vec<Vec<HashOut<F>>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyValueTreeProofTarget {
+    /// For each layer, starting from the leaves, the children of the parent of the node on the
+    /// path, including that node.
+    pub children: Vec<Vec<HashOutTarget>>,
+}
+
+/// A key-value tree with `2^(arity_bits * depth)` keys, storing only its nonempty nodes.
+#[derive(Clone, Debug)]
+pub struct KeyValueTree<F: RichField, H: AlgebraicHasher<F>> {
+    arity_bits: usize,
+    depth: usize,
+    values: HashMap<u64, F>,
+    /// The digests of the nonempty nodes of each layer, starting from the leaves, by index within
+    /// the layer.
+    nodes: Vec<HashMap<u64, HashOut<F>>>,
+    /// The digest of an empty subtree rooted at each layer, starting from the leaves.
+    empty_digests: Vec<HashOut<F>>,
+    _phantom: PhantomData<H>,
+}
+
+fn leaf_digest<F: RichField, H: AlgebraicHasher<F>>(value: F) -> HashOut<F> {
+    H::hash_no_pad(&[value])
+}
+
+fn node_digest<F: RichField, H: AlgebraicHasher<F>>(children: &[HashOut<F>]) -> HashOut<F> {
+    let elements = children.iter().flat_map(|c| c.elements).collect::<Vec<_>>();
+    H::hash_no_pad(&elements)
+}
+
+impl<F: RichField, H: AlgebraicHasher<F>> KeyValueTree<F, H> {
+    /// An empty tree of `2^arity_bits`-ary nodes with the given depth.
+    pub fn new(arity_bits: usize, depth: usize) -> Self {
+        assert!(arity_bits > 0, "The arity must be at least 2");
+        assert!(arity_bits * depth < 64, "Keys must fit in a u64");
+
+        let mut empty_digests = vec![leaf_digest::<F, H>(F::ZERO)];
+        for i in 0..depth {
+            let children = vec![empty_digests[i]; 1 << arity_bits];
+            empty_digests.push(node_digest::<F, H>(&children));
+        }
+
+        Self {
+            arity_bits,
+            depth,
+            values: HashMap::new(),
+            nodes: vec![HashMap::new(); depth + 1],
+            empty_digests,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        1 << self.arity_bits
+    }
+
+    pub fn num_keys(&self) -> u64 {
+        1 << (self.arity_bits * self.depth)
+    }
+
+    pub fn root(&self) -> HashOut<F> {
+        self.node(self.depth, 0)
+    }
+
+    /// The value held at `key`, which is zero if none was inserted.
+    pub fn get(&self, key: u64) -> F {
+        self.values.get(&key).copied().unwrap_or(F::ZERO)
+    }
+
+    fn node(&self, layer: usize, index: u64) -> HashOut<F> {
+        self.nodes[layer]
+            .get(&index)
+            .copied()
+            .unwrap_or(self.empty_digests[layer])
+    }
+
+    /// The indices of the children of the parent of node `index` of a layer.
+    fn siblings(&self, index: u64) -> impl Iterator<Item = u64> {
+        let first = index >> self.arity_bits << self.arity_bits;
+        first..first + self.arity() as u64
+    }
+
+    /// Sets the value at `key`, updating the digests along its path. Setting it to zero removes it.
+    pub fn insert(&mut self, key: u64, value: F) {
+        assert!(key < self.num_keys(), "Key {} is out of range", key);
+        if value == F::ZERO {
+            self.values.remove(&key);
+        } else {
+            self.values.insert(key, value);
+        }
+
+        let mut index = key;
+        let mut digest = leaf_digest::<F, H>(value);
+        for layer in 0..=self.depth {
+            if digest == self.empty_digests[layer] {
+                self.nodes[layer].remove(&index);
+            } else {
+                self.nodes[layer].insert(index, digest);
+            }
+            if layer == self.depth {
+                break;
+            }
+            let children = self
+                .siblings(index)
+                .map(|i| self.node(layer, i))
+                .collect::<Vec<_>>();
+            digest = node_digest::<F, H>(&children);
+            index >>= self.arity_bits;
+        }
+    }
+
+    /// Proves the value held at `key`.
+    pub fn prove(&self, key: u64) -> KeyValueTreeProof<F> {
+        assert!(key < self.num_keys(), "Key {} is out of range", key);
+        let children = (0..self.depth)
+            .map(|layer| {
+                let index = key >> (layer * self.arity_bits);
+                self.siblings(index).map(|i| self.node(layer, i)).collect()
+            })
+            .collect();
+        KeyValueTreeProof { children }
+    }
+}
+
+/// Verifies that `key` holds `value` in the `2^arity_bits`-ary key-value tree with the given root.
+pub fn verify_key_value_tree_proof<F: RichField, H: AlgebraicHasher<F>>(
+    arity_bits: usize,
+    key: u64,
+    value: F,
+    root: HashOut<F>,
+    proof: &KeyValueTreeProof<F>,
+) -> Result<()> {
+    ensure!(
+        key >> (arity_bits * proof.children.len()) == 0,
+        "Key is out of range."
+    );
+    let mut digest = leaf_digest::<F, H>(value);
+    for (layer, children) in proof.children.iter().enumerate() {
+        ensure!(
+            children.len() == 1 << arity_bits,
+            "Wrong number of children."
+        );
+        let position = (key >> (layer * arity_bits)) as usize & ((1 << arity_bits) - 1);
+        ensure!(
+            children[position] == digest,
+            "Invalid key-value tree proof."
+        );
+        digest = node_digest::<F, H>(children);
+    }
+    ensure!(digest == root, "Invalid key-value tree proof.");
+    Ok(())
+}
+
+/// Sets the witness for a key-value tree proof target.
+pub fn set_key_value_tree_proof_target<F: RichField, W: Witness<F>>(
+    witness: &mut W,
+    target: &KeyValueTreeProofTarget,
+    proof: &KeyValueTreeProof<F>,
+) {
+    for (layer_targets, layer) in target.children.iter().zip(&proof.children) {
+        for (&t, &child) in layer_targets.iter().zip(layer) {
+            witness.set_hash_target(t, child);
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn add_virtual_key_value_tree_proof(
+        &mut self,
+        arity_bits: usize,
+        depth: usize,
+    ) -> KeyValueTreeProofTarget {
+        KeyValueTreeProofTarget {
+            children: (0..depth)
+                .map(|_| {
+                    (0..1 << arity_bits)
+                        .map(|_| self.add_virtual_hash())
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Verifies that `key` holds `value` in the `2^arity_bits`-ary key-value tree with the given
+    /// root.
+    pub fn verify_key_value_tree_proof<H: AlgebraicHasher<F>>(
+        &mut self,
+        arity_bits: usize,
+        key: Target,
+        value: Target,
+        root: HashOutTarget,
+        proof: &KeyValueTreeProofTarget,
+    ) {
+        let (computed_root, _) =
+            self.key_value_tree_update_roots::<H>(arity_bits, key, value, value, proof);
+        self.connect_hashes(computed_root, root);
+    }
+
+    /// Verifies that `key` holds `old_value` in the `2^arity_bits`-ary key-value tree with root
+    /// `old_root`, and returns the root of the tree after setting it to `new_value`.
+    pub fn update_key_value_tree<H: AlgebraicHasher<F>>(
+        &mut self,
+        arity_bits: usize,
+        key: Target,
+        old_value: Target,
+        new_value: Target,
+        old_root: HashOutTarget,
+        proof: &KeyValueTreeProofTarget,
+    ) -> HashOutTarget {
+        let (computed_old_root, new_root) =
+            self.key_value_tree_update_roots::<H>(arity_bits, key, old_value, new_value, proof);
+        self.connect_hashes(computed_old_root, old_root);
+        new_root
+    }
+
+    /// Computes the roots of the tree authenticated by `proof` with `old_value` and `new_value` at
+    /// `key`. This range-checks `key`.
+    fn key_value_tree_update_roots<H: AlgebraicHasher<F>>(
+        &mut self,
+        arity_bits: usize,
+        key: Target,
+        old_value: Target,
+        new_value: Target,
+        proof: &KeyValueTreeProofTarget,
+    ) -> (HashOutTarget, HashOutTarget) {
+        let arity = 1 << arity_bits;
+        let key_bits = self.split_le(key, arity_bits * proof.children.len());
+
+        let mut old_digest = self.hash_n_to_hash_no_pad::<H>(vec![old_value]);
+        let mut new_digest = self.hash_n_to_hash_no_pad::<H>(vec![new_value]);
+        for (layer, children) in proof.children.iter().enumerate() {
+            assert_eq!(children.len(), arity, "Wrong number of children");
+            let position_bits = &key_bits[layer * arity_bits..(layer + 1) * arity_bits];
+            let position = self.le_sum(position_bits.iter());
+            let selectors = self.one_hot(position, arity);
+
+            // The selected child must be the old digest, and is replaced by the new digest.
+            let mut selected = [self.zero(); 4];
+            let mut new_children = Vec::with_capacity(arity);
+            for (&child, &selector) in children.iter().zip(&selectors) {
+                let mut new_child = [self.zero(); 4];
+                for i in 0..4 {
+                    selected[i] = self.mul_add(selector.target, child.elements[i], selected[i]);
+                    new_child[i] = self.select(selector, new_digest.elements[i], child.elements[i]);
+                }
+                new_children.extend(new_child);
+            }
+            self.connect_hashes(HashOutTarget { elements: selected }, old_digest);
+
+            let old_elements = children.iter().flat_map(|c| c.elements).collect();
+            old_digest = self.hash_n_to_hash_no_pad::<H>(old_elements);
+            new_digest = self.hash_n_to_hash_no_pad::<H>(new_children);
+        }
+
+        (old_digest, new_digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::hash::key_value_tree::{
+        set_key_value_tree_proof_target, verify_key_value_tree_proof, KeyValueTree,
+    };
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = PoseidonHash;
+
+    const ARITY_BITS: usize = 4;
+    const DEPTH: usize = 3;
+
+    #[test]
+    fn test_key_value_tree_proofs() -> Result<()> {
+        let mut tree = KeyValueTree::<F, H>::new(ARITY_BITS, DEPTH);
+        let entries = [
+            (0, F::ONE),
+            (17, F::TWO),
+            (4095, F::rand()),
+            (300, F::rand()),
+        ];
+        for (key, value) in entries {
+            tree.insert(key, value);
+        }
+
+        for (key, value) in entries {
+            verify_key_value_tree_proof::<F, H>(
+                ARITY_BITS,
+                key,
+                value,
+                tree.root(),
+                &tree.prove(key),
+            )?;
+        }
+        // An absent key holds zero.
+        verify_key_value_tree_proof::<F, H>(ARITY_BITS, 18, F::ZERO, tree.root(), &tree.prove(18))?;
+        assert!(verify_key_value_tree_proof::<F, H>(
+            ARITY_BITS,
+            17,
+            F::ONE,
+            tree.root(),
+            &tree.prove(17)
+        )
+        .is_err());
+
+        // Removing every entry gives back the empty tree.
+        for (key, _) in entries {
+            tree.insert(key, F::ZERO);
+        }
+        assert_eq!(
+            tree.root(),
+            KeyValueTree::<F, H>::new(ARITY_BITS, DEPTH).root()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_value_tree_update_circuit() -> Result<()> {
+        let mut tree = KeyValueTree::<F, H>::new(ARITY_BITS, DEPTH);
+        tree.insert(5, F::rand());
+        tree.insert(1000, F::rand());
+
+        let key = 1000;
+        let old_value = tree.get(key);
+        let new_value = F::rand();
+        let old_root = tree.root();
+        let proof = tree.prove(key);
+        tree.insert(key, new_value);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let key_t = builder.add_virtual_target();
+        let old_value_t = builder.add_virtual_target();
+        let new_value_t = builder.add_virtual_target();
+        let old_root_t = builder.add_virtual_hash();
+        let proof_t = builder.add_virtual_key_value_tree_proof(ARITY_BITS, DEPTH);
+        pw.set_target(key_t, F::from_canonical_u64(key));
+        pw.set_target(old_value_t, old_value);
+        pw.set_target(new_value_t, new_value);
+        pw.set_hash_target(old_root_t, old_root);
+        set_key_value_tree_proof_target(&mut pw, &proof_t, &proof);
+
+        let new_root_t = builder.update_key_value_tree::<H>(
+            ARITY_BITS,
+            key_t,
+            old_value_t,
+            new_value_t,
+            old_root_t,
+            &proof_t,
+        );
+        let expected_new_root = builder.constant_hash(tree.root());
+        builder.connect_hashes(new_root_t, expected_new_root);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
@@ -0,0 +1,492 @@
+//! An append-only accumulator in the style of a Merkle mountain range, for applications which
+//! accumulate events across many proofs, e.g. bridges, logs or message queues.
+//!
+//! The leaves are grouped into perfect binary Merkle trees of distinct heights, the peaks, as given
+//! by the binary representation of the number of leaves: there is a peak of height `h` iff bit `h`
+//! of the number of leaves is set, and peaks are ordered from the highest to the lowest. Appending
+//! a leaf merges equal-height peaks, like incrementing a binary counter.
+//!
+//! An accumulator of capacity `2^(max_height + 1) - 1` has a fixed-size state: the number of leaves
+//! and one slot per height, holding that height's peak or a zero digest. The state is committed to
+//! by hashing it, and can be carried from one proof to the next through that commitment.
+
+use std::marker::PhantomData;
+
+use anyhow::{ensure, Result};
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain};
+
+/// The domain of a node at `height` above the leaves.
+fn node_domain(height: usize) -> CompressionDomain {
+    if height == 1 {
+        CompressionDomain::Leaf
+    } else {
+        CompressionDomain::Internal
+    }
+}
+
+/// Whether the leaf at `index` of a Merkle mountain range with `num_leaves` leaves is in the peak
+/// of height `h`, i.e. whether that peak exists, and `index` is in the range of leaves it covers.
+fn in_peak(index: u64, num_leaves: u64, h: usize) -> bool {
+    (num_leaves >> h) & 1 == 1 && (index >> h) & 1 == 0 && index >> (h + 1) == num_leaves >> (h + 1)
+}
+
+/// The state of a Merkle mountain range.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MmrState<F: Field> {
+    pub num_leaves: u64,
+    /// The peak of each height, starting from zero, or a zero digest if there is none.
+    pub peaks: Vec<HashOut<F>>,
+}
+
+impl<F: RichField> MmrState<F> {
+    fn to_elements(&self) -> Vec<F> {
+        let mut elements = vec![F::from_canonical_u64(self.num_leaves)];
+        elements.extend(self.peaks.iter().flat_map(|p| p.elements));
+        elements
+    }
+
+    pub fn commitment<H: AlgebraicHasher<F>>(&self) -> HashOut<F> {
+        H::hash_no_pad(&self.to_elements())
+    }
+}
+
+/// A proof that a leaf is in a Merkle mountain range.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MmrProof<F: Field> {
+    /// The height of the peak whose tree holds the leaf.
+    pub peak_height: usize,
+    /// The siblings of the path from the leaf to its peak, starting from the leaf.
+    pub siblings: Vec<HashOut<F>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MmrStateTarget {
+    pub num_leaves: Target,
+    pub peaks: Vec<HashOutTarget>,
+}
+
+impl MmrStateTarget {
+    fn to_targets(&self) -> Vec<Target> {
+        let mut targets = vec![self.num_leaves];
+        targets.extend(self.peaks.iter().flat_map(|p| p.elements));
+        targets
+    }
+
+    pub fn max_height(&self) -> usize {
+        self.peaks.len() - 1
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MmrProofTarget {
+    pub peak_height: Target,
+    /// The siblings of the path from the leaf to its peak, padded to the maximum height.
+    pub siblings: Vec<HashOutTarget>,
+}
+
+/// A Merkle mountain range with peaks of height at most `max_height`.
+#[derive(Clone, Debug)]
+pub struct MerkleMountainRange<F: RichField, H: AlgebraicHasher<F>> {
+    max_height: usize,
+    /// The digests of the nodes of each height, starting from the leaves.
+    layers: Vec<Vec<HashOut<F>>>,
+    _phantom: PhantomData<H>,
+}
+
+impl<F: RichField, H: AlgebraicHasher<F>> MerkleMountainRange<F, H> {
+    pub fn new(max_height: usize) -> Self {
+        assert!(max_height < 63, "The number of leaves must fit in a u64");
+        Self {
+            max_height,
+            layers: vec![Vec::new(); max_height + 1],
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn max_height(&self) -> usize {
+        self.max_height
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends a leaf, returning its index.
+    pub fn append(&mut self, leaf_data: &[F]) -> usize {
+        let index = self.len();
+        assert!(
+            index + 1 < 1 << (self.max_height + 1),
+            "The accumulator is full"
+        );
+
+        let mut digest = H::hash_no_pad(leaf_data);
+        let mut position = index;
+        for height in 0..=self.max_height {
+            self.layers[height].push(digest);
+            if position & 1 == 0 {
+                break;
+            }
+            let left = self.layers[height][position - 1];
+            digest = H::two_to_one_with_domain(left, digest, node_domain(height + 1));
+            position >>= 1;
+        }
+        index
+    }
+
+    pub fn state(&self) -> MmrState<F> {
+        let num_leaves = self.len();
+        let peaks = (0..=self.max_height)
+            .map(|h| {
+                if (num_leaves >> h) & 1 == 1 {
+                    self.layers[h][(num_leaves >> h) - 1]
+                } else {
+                    HashOut::ZERO
+                }
+            })
+            .collect();
+        MmrState {
+            num_leaves: num_leaves as u64,
+            peaks,
+        }
+    }
+
+    pub fn commitment(&self) -> HashOut<F> {
+        self.state().commitment::<H>()
+    }
+
+    /// Proves the leaf at `index` against the current state.
+    pub fn prove(&self, index: usize) -> MmrProof<F> {
+        let num_leaves = self.len();
+        assert!(index < num_leaves, "Leaf index out of range");
+        let peak_height = (0..=self.max_height)
+            .rev()
+            .find(|&h| in_peak(index as u64, num_leaves as u64, h))
+            .unwrap();
+        let siblings = (0..peak_height)
+            .map(|h| self.layers[h][(index >> h) ^ 1])
+            .collect();
+        MmrProof {
+            peak_height,
+            siblings,
+        }
+    }
+}
+
+/// Verifies that the leaf at `index` of the Merkle mountain range with the given state holds
+/// `leaf_data`.
+pub fn verify_mmr_proof<F: RichField, H: AlgebraicHasher<F>>(
+    leaf_data: &[F],
+    index: u64,
+    state: &MmrState<F>,
+    proof: &MmrProof<F>,
+) -> Result<()> {
+    let h = proof.peak_height;
+    ensure!(h < state.peaks.len(), "Invalid peak height.");
+    ensure!(
+        in_peak(index, state.num_leaves, h),
+        "Leaf index out of range."
+    );
+    ensure!(proof.siblings.len() == h, "Wrong number of siblings.");
+
+    let mut digest = H::hash_no_pad(leaf_data);
+    for (i, &sibling) in proof.siblings.iter().enumerate() {
+        digest = if (index >> i) & 1 == 1 {
+            H::two_to_one_with_domain(sibling, digest, node_domain(i + 1))
+        } else {
+            H::two_to_one_with_domain(digest, sibling, node_domain(i + 1))
+        };
+    }
+    ensure!(
+        digest == state.peaks[h],
+        "Invalid Merkle mountain range proof."
+    );
+    Ok(())
+}
+
+/// Sets the witness for a Merkle mountain range state target.
+pub fn set_mmr_state_target<F: RichField, W: Witness<F>>(
+    witness: &mut W,
+    target: &MmrStateTarget,
+    state: &MmrState<F>,
+) {
+    assert_eq!(
+        target.peaks.len(),
+        state.peaks.len(),
+        "Wrong maximum height"
+    );
+    witness.set_target(target.num_leaves, F::from_canonical_u64(state.num_leaves));
+    for (&t, &peak) in target.peaks.iter().zip(&state.peaks) {
+        witness.set_hash_target(t, peak);
+    }
+}
+
+/// Sets the witness for a Merkle mountain range proof target. The unused siblings are set to zero.
+pub fn set_mmr_proof_target<F: RichField, W: Witness<F>>(
+    witness: &mut W,
+    target: &MmrProofTarget,
+    proof: &MmrProof<F>,
+) {
+    witness.set_target(
+        target.peak_height,
+        F::from_canonical_usize(proof.peak_height),
+    );
+    for (i, &t) in target.siblings.iter().enumerate() {
+        witness.set_hash_target(t, proof.siblings.get(i).copied().unwrap_or(HashOut::ZERO));
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn add_virtual_mmr_state(&mut self, max_height: usize) -> MmrStateTarget {
+        MmrStateTarget {
+            num_leaves: self.add_virtual_target(),
+            peaks: self.add_virtual_hashes(max_height + 1),
+        }
+    }
+
+    pub fn add_virtual_mmr_proof(&mut self, max_height: usize) -> MmrProofTarget {
+        MmrProofTarget {
+            peak_height: self.add_virtual_target(),
+            siblings: self.add_virtual_hashes(max_height),
+        }
+    }
+
+    pub fn mmr_commitment<H: AlgebraicHasher<F>>(
+        &mut self,
+        state: &MmrStateTarget,
+    ) -> HashOutTarget {
+        self.hash_n_to_hash_no_pad::<H>(state.to_targets())
+    }
+
+    /// Appends a leaf, returning the new state. Fails to prove if the accumulator is full.
+    pub fn mmr_append<H: AlgebraicHasher<F>>(
+        &mut self,
+        state: &MmrStateTarget,
+        leaf_data: Vec<Target>,
+    ) -> MmrStateTarget {
+        let max_height = state.max_height();
+        let zero = self.zero();
+        let count_bits = self.split_le(state.num_leaves, max_height + 1);
+
+        // Propagate the new leaf like a carry: each peak it meets is merged into it and cleared,
+        // until it reaches an empty slot.
+        let _false = self._false();
+        let mut carry = self.hash_n_to_hash_no_pad::<H>(leaf_data);
+        let mut active = self._true();
+        let mut peaks = Vec::with_capacity(max_height + 1);
+        for (height, (&bit, &peak)) in count_bits.iter().zip(&state.peaks).enumerate() {
+            let clear = self.and(active, bit);
+            let not_bit = self.not(bit);
+            let place = self.and(active, not_bit);
+            let merged = H::two_to_one_swapped_with_domain(
+                peak,
+                carry,
+                _false,
+                node_domain(height + 1),
+                self,
+            );
+
+            let mut new_peak = [zero; 4];
+            let mut new_carry = [zero; 4];
+            for i in 0..4 {
+                let kept = self.select(clear, zero, peak.elements[i]);
+                new_peak[i] = self.select(place, carry.elements[i], kept);
+                new_carry[i] = self.select(clear, merged.elements[i], carry.elements[i]);
+            }
+            peaks.push(HashOutTarget { elements: new_peak });
+            carry = HashOutTarget {
+                elements: new_carry,
+            };
+            active = clear;
+        }
+        // The carry must have found an empty slot.
+        self.assert_zero(active.target);
+
+        let one = self.one();
+        MmrStateTarget {
+            num_leaves: self.add(state.num_leaves, one),
+            peaks,
+        }
+    }
+
+    /// Verifies that the leaf at `index` of the Merkle mountain range with the given state holds
+    /// `leaf_data`. This range-checks `index`.
+    pub fn verify_mmr_proof<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: Vec<Target>,
+        index: Target,
+        state: &MmrStateTarget,
+        proof: &MmrProofTarget,
+    ) {
+        let max_height = state.max_height();
+        assert_eq!(proof.siblings.len(), max_height, "Wrong number of siblings");
+        let index_bits = self.split_le(index, max_height + 1);
+        let count_bits = self.split_le(state.num_leaves, max_height + 1);
+        let selectors = self.one_hot(proof.peak_height, max_height + 1);
+
+        // The digests of the path from the leaf up to the maximum height, of which the one at the
+        // peak's height must be the peak.
+        let mut digests = vec![self.hash_n_to_hash_no_pad::<H>(leaf_data)];
+        for (i, (&bit, &sibling)) in index_bits.iter().zip(&proof.siblings).enumerate() {
+            let digest = H::two_to_one_swapped_with_domain(
+                digests[i],
+                sibling,
+                bit,
+                node_domain(i + 1),
+                self,
+            );
+            digests.push(digest);
+        }
+        let zero = self.zero();
+        let mut selected_digest = [zero; 4];
+        let mut selected_peak = [zero; 4];
+        for ((&selector, digest), peak) in selectors.iter().zip(&digests).zip(&state.peaks) {
+            for i in 0..4 {
+                selected_digest[i] =
+                    self.mul_add(selector.target, digest.elements[i], selected_digest[i]);
+                selected_peak[i] =
+                    self.mul_add(selector.target, peak.elements[i], selected_peak[i]);
+            }
+        }
+        self.connect_hashes(
+            HashOutTarget {
+                elements: selected_digest,
+            },
+            HashOutTarget {
+                elements: selected_peak,
+            },
+        );
+
+        // The peak must exist, and the index must be in its range: its bits above the peak's
+        // height must match those of the number of leaves, and its bit at that height must be zero.
+        let mut above = self._false();
+        for ((&selector, &index_bit), &count_bit) in
+            selectors.iter().zip(&index_bits).zip(&count_bits)
+        {
+            let diff = self.sub(index_bit.target, count_bit.target);
+            let masked_diff = self.mul(above.target, diff);
+            self.assert_zero(masked_diff);
+            let masked_index_bit = self.mul(selector.target, index_bit.target);
+            self.assert_zero(masked_index_bit);
+            let missing_peak = self.arithmetic(
+                F::NEG_ONE,
+                F::ONE,
+                selector.target,
+                count_bit.target,
+                selector.target,
+            );
+            self.assert_zero(missing_peak);
+            above = BoolTarget::new_unsafe(self.add(above.target, selector.target));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::hash::merkle_mountain_range::{
+        set_mmr_proof_target, set_mmr_state_target, verify_mmr_proof, MerkleMountainRange,
+    };
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = PoseidonHash;
+
+    const MAX_HEIGHT: usize = 4;
+
+    fn random_leaves(n: usize) -> Vec<Vec<F>> {
+        (0..n).map(|_| F::rand_vec(3)).collect()
+    }
+
+    #[test]
+    fn test_mmr_proofs() -> Result<()> {
+        let mut mmr = MerkleMountainRange::<F, H>::new(MAX_HEIGHT);
+        let leaves = random_leaves(13);
+        for (i, leaf) in leaves.iter().enumerate() {
+            assert_eq!(mmr.append(leaf), i);
+
+            let state = mmr.state();
+            for (j, leaf) in leaves[..=i].iter().enumerate() {
+                let proof = mmr.prove(j);
+                verify_mmr_proof::<F, H>(leaf, j as u64, &state, &proof)?;
+                // The proof doesn't hold at another index, or for leaves not yet appended.
+                assert!(verify_mmr_proof::<F, H>(leaf, (j ^ 1) as u64, &state, &proof).is_err());
+                assert!(verify_mmr_proof::<F, H>(leaf, (i + 1) as u64, &state, &proof).is_err());
+            }
+        }
+
+        // The peaks are given by the binary representation of the number of leaves.
+        let state = mmr.state();
+        let nonzero_peaks = state
+            .peaks
+            .iter()
+            .map(|p| p.elements != [F::ZERO; 4])
+            .collect::<Vec<_>>();
+        assert_eq!(nonzero_peaks, [true, false, true, true, false]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmr_circuit() -> Result<()> {
+        let leaves = random_leaves(7);
+        let mut mmr = MerkleMountainRange::<F, H>::new(MAX_HEIGHT);
+        for leaf in &leaves[..5] {
+            mmr.append(leaf);
+        }
+        let old_state = mmr.state();
+        for leaf in &leaves[5..] {
+            mmr.append(leaf);
+        }
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut state_t = builder.add_virtual_mmr_state(MAX_HEIGHT);
+        set_mmr_state_target(&mut pw, &state_t, &old_state);
+        for leaf in &leaves[5..] {
+            let leaf_t = builder.add_virtual_targets(leaf.len());
+            for (&t, &x) in leaf_t.iter().zip(leaf) {
+                pw.set_target(t, x);
+            }
+            state_t = builder.mmr_append::<H>(&state_t, leaf_t);
+        }
+        let commitment = builder.mmr_commitment::<H>(&state_t);
+        let expected_commitment = builder.constant_hash(mmr.commitment());
+        builder.connect_hashes(commitment, expected_commitment);
+
+        // Leaves appended both before and during the circuit are members of the new state.
+        for index in [2, 6] {
+            let proof_t = builder.add_virtual_mmr_proof(MAX_HEIGHT);
+            set_mmr_proof_target(&mut pw, &proof_t, &mmr.prove(index));
+            let leaf_t = builder.add_virtual_targets(3);
+            for (&t, &x) in leaf_t.iter().zip(&leaves[index]) {
+                pw.set_target(t, x);
+            }
+            let index_t = builder.constant(F::from_canonical_usize(index));
+            builder.verify_mmr_proof::<H>(leaf_t, index_t, &state_t, &proof_t);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
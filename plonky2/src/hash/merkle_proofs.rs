@@ -1,14 +1,16 @@
+use std::collections::{BTreeMap, HashMap};
+
 use anyhow::{ensure, Result};
 use plonky2_field::extension_field::Extendable;
 use serde::{Deserialize, Serialize};
 
 use crate::hash::hash_types::RichField;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget};
-use crate::hash::hashing::SPONGE_WIDTH;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::{AlgebraicHasher, Hasher};
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain, Hasher};
+use crate::util::maybe_rayon::*;
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(bound = "")]
@@ -36,13 +38,15 @@ where
 {
     let mut index = leaf_index;
     let mut current_digest = H::hash_or_noop(&leaf_data);
-    for &sibling_digest in proof.siblings.iter() {
+    let num_layers = proof.siblings.len();
+    for (i, &sibling_digest) in proof.siblings.iter().enumerate() {
         let bit = index & 1;
         index >>= 1;
+        let domain = CompressionDomain::for_merkle_node(i + 1, num_layers);
         current_digest = if bit == 1 {
-            H::two_to_one(sibling_digest, current_digest)
+            H::two_to_one_with_domain(sibling_digest, current_digest, domain)
         } else {
-            H::two_to_one(current_digest, sibling_digest)
+            H::two_to_one_with_domain(current_digest, sibling_digest, domain)
         }
     }
     ensure!(
@@ -53,6 +57,76 @@ where
     Ok(())
 }
 
+/// Verifies that each `(leaf_data, leaf_index, proof)` opening is present in the Merkle tree with
+/// the given cap. This accepts exactly when every opening would be accepted by
+/// `verify_merkle_proof`, barring hash collisions, but the tree is rebuilt one layer at a time
+/// from the opened leaves so that each node shared by several paths is only hashed once.
+pub fn verify_merkle_proofs_batch<F: RichField, H: Hasher<F>>(
+    openings: &[(Vec<F>, usize, MerkleProof<F, H>)],
+    merkle_cap: &MerkleCap<F, H>,
+) -> Result<()>
+where
+    [(); H::HASH_SIZE]:,
+{
+    if openings.is_empty() {
+        return Ok(());
+    }
+    let num_layers = openings[0].2.siblings.len();
+    ensure!(
+        openings
+            .iter()
+            .all(|(_, _, proof)| proof.siblings.len() == num_layers),
+        "Merkle proofs have different lengths."
+    );
+
+    // The known digests of the current layer, by index within the layer.
+    let mut digests = HashMap::new();
+    let leaf_digests = openings
+        .par_iter()
+        .map(|(leaf_data, _, _)| H::hash_or_noop(leaf_data))
+        .collect::<Vec<_>>();
+    for ((_, leaf_index, _), digest) in openings.iter().zip(leaf_digests) {
+        let known = *digests.entry(*leaf_index).or_insert(digest);
+        ensure!(known == digest, "Conflicting openings of the same leaf.");
+    }
+
+    for i in 0..num_layers {
+        // The children of each parent whose digest is needed. A sibling given by a proof must
+        // agree with the digest computed from any other opening.
+        let mut children = BTreeMap::new();
+        for (_, leaf_index, proof) in openings {
+            let index = leaf_index >> i;
+            let digest = digests[&index];
+            let sibling = proof.siblings[i];
+            if let Some(&known) = digests.get(&(index ^ 1)) {
+                ensure!(known == sibling, "Invalid Merkle proof.");
+            }
+            let pair = if index & 1 == 1 {
+                (sibling, digest)
+            } else {
+                (digest, sibling)
+            };
+            let known = *children.entry(index >> 1).or_insert(pair);
+            ensure!(known == pair, "Invalid Merkle proof.");
+        }
+
+        let domain = CompressionDomain::for_merkle_node(i + 1, num_layers);
+        digests = children
+            .into_par_iter()
+            .map(|(index, (left, right))| (index, H::two_to_one_with_domain(left, right, domain)))
+            .collect();
+    }
+
+    for (index, digest) in digests {
+        ensure!(
+            merkle_cap.0.get(index) == Some(&digest),
+            "Invalid Merkle proof."
+        );
+    }
+
+    Ok(())
+}
+
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Verifies that the given leaf data is present at the given index in the Merkle tree with the
     /// given cap. The index is given by it's little-endian bits.
@@ -64,15 +138,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         merkle_cap: &MerkleCapTarget,
         proof: &MerkleProofTarget,
     ) {
-        let zero = self.zero();
         let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data);
 
-        for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
-            let mut perm_inputs = [zero; SPONGE_WIDTH];
-            perm_inputs[..4].copy_from_slice(&state.elements);
-            perm_inputs[4..8].copy_from_slice(&sibling.elements);
-            let outputs = self.permute_swapped::<H>(perm_inputs, bit);
-            state = HashOutTarget::from_vec(outputs[0..4].to_vec());
+        let num_layers = proof.siblings.len();
+        for (i, (&bit, &sibling)) in leaf_index_bits.iter().zip(&proof.siblings).enumerate() {
+            let domain = CompressionDomain::for_merkle_node(i + 1, num_layers);
+            state = H::two_to_one_swapped_with_domain(state, sibling, bit, domain, self);
         }
 
         let index = self.le_sum(leaf_index_bits[proof.siblings.len()..].iter().copied());
@@ -95,18 +166,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         merkle_cap: &MerkleCapTarget,
         proof: &MerkleProofTarget,
     ) {
-        let zero = self.zero();
         let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data);
 
-        for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
-            let mut perm_inputs = [zero; SPONGE_WIDTH];
-            perm_inputs[..4].copy_from_slice(&state.elements);
-            perm_inputs[4..8].copy_from_slice(&sibling.elements);
-            let perm_outs = self.permute_swapped::<H>(perm_inputs, bit);
-            let hash_outs = perm_outs[0..4].try_into().unwrap();
-            state = HashOutTarget {
-                elements: hash_outs,
-            };
+        let num_layers = proof.siblings.len();
+        for (i, (&bit, &sibling)) in leaf_index_bits.iter().zip(&proof.siblings).enumerate() {
+            let domain = CompressionDomain::for_merkle_node(i + 1, num_layers);
+            state = H::two_to_one_swapped_with_domain(state, sibling, bit, domain, self);
         }
 
         for i in 0..4 {
@@ -129,10 +194,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 mod tests {
     use anyhow::Result;
     use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
     use rand::{thread_rng, Rng};
 
     use super::*;
+    use crate::hash::hash_types::HashOut;
     use crate::hash::merkle_tree::MerkleTree;
+    use crate::hash::poseidon::PoseidonHash;
     use crate::iop::witness::{PartialWitness, Witness};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
@@ -187,4 +255,39 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_verify_merkle_proofs_batch() -> Result<()> {
+        type F = GoldilocksField;
+        type H = PoseidonHash;
+
+        let log_n = 8;
+        let n = 1 << log_n;
+        let cap_height = 2;
+        let leaves = random_data::<F>(n, 7);
+        let tree = MerkleTree::<F, H>::new(leaves, cap_height);
+
+        // Include repeated indices and siblings, whose paths share every node.
+        let mut rng = thread_rng();
+        let mut indices = (0..50).map(|_| rng.gen_range(0..n)).collect::<Vec<_>>();
+        indices.extend([0, 0, 1, n - 1]);
+        let openings = indices
+            .iter()
+            .map(|&i| (tree.leaves[i].clone(), i, tree.prove(i)))
+            .collect::<Vec<_>>();
+        verify_merkle_proofs_batch(&openings, &tree.cap)?;
+
+        // Tampering with any single opening is detected.
+        let mut bad_leaf = openings.clone();
+        bad_leaf[10].0[0] += F::ONE;
+        assert!(verify_merkle_proofs_batch(&bad_leaf, &tree.cap).is_err());
+        let mut bad_index = openings.clone();
+        bad_index[10].1 ^= 1 << 3;
+        assert!(verify_merkle_proofs_batch(&bad_index, &tree.cap).is_err());
+        let mut bad_sibling = openings;
+        bad_sibling[10].2.siblings[log_n - cap_height - 1] = HashOut::rand();
+        assert!(verify_merkle_proofs_batch(&bad_sibling, &tree.cap).is_err());
+
+        Ok(())
+    }
 }
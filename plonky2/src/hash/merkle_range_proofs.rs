@@ -0,0 +1,302 @@
+use anyhow::{ensure, Result};
+use plonky2_field::extension_field::Extendable;
+use serde::{Deserialize, Serialize};
+
+use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::merkle_tree::{MerkleCap, MerkleTree};
+use crate::iop::target::Target;
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain, Hasher};
+
+/// A proof that a range of consecutive leaves of a Merkle tree hold given data.
+///
+/// Since every node between the two boundary paths of the range can be recomputed from the leaves,
+/// only the nodes just outside the range are needed: at most two per layer, rather than one per
+/// layer for each leaf with independent proofs.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(bound = "")]
+pub struct MerkleRangeProof<F: RichField, H: Hasher<F>> {
+    /// For each layer, starting from the bottommost, the digest of the node to the left of the
+    /// range's nodes if the leftmost one is a right child, followed by the digest of the node to
+    /// the right of the range's nodes if the rightmost one is a left child.
+    pub layers: Vec<Vec<H::Hash>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleRangeProofTarget {
+    /// See `MerkleRangeProof::layers`.
+    pub layers: Vec<Vec<HashOutTarget>>,
+}
+
+/// For each layer of a tree with `num_layers` layers below the cap, whether the proof of the range
+/// of leaves starting at `start` of length `len` has a node to the left and to the right.
+fn boundary_nodes(start: usize, len: usize, num_layers: usize) -> Vec<(bool, bool)> {
+    assert!(len > 0, "The range must be nonempty");
+    let (mut first, mut last) = (start, start + len - 1);
+    (0..num_layers)
+        .map(|_| {
+            let boundary = (first & 1 == 1, last & 1 == 0);
+            first >>= 1;
+            last >>= 1;
+            boundary
+        })
+        .collect()
+}
+
+impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
+    /// Creates a proof for the leaves with indices in `start..start + len`.
+    pub fn prove_range(&self, start: usize, len: usize) -> MerkleRangeProof<F, H> {
+        assert!(
+            start + len <= self.leaves.len(),
+            "The range is out of bounds"
+        );
+        let first_proof = self.prove(start);
+        let last_proof = self.prove(start + len - 1);
+        let layers = boundary_nodes(start, len, first_proof.siblings.len())
+            .into_iter()
+            .enumerate()
+            .map(|(i, (left, right))| {
+                let mut layer = Vec::new();
+                if left {
+                    layer.push(first_proof.siblings[i]);
+                }
+                if right {
+                    layer.push(last_proof.siblings[i]);
+                }
+                layer
+            })
+            .collect();
+        MerkleRangeProof { layers }
+    }
+}
+
+/// Verifies that the leaves with indices in `start..start + leaves_data.len()` of the Merkle tree
+/// with the given cap hold `leaves_data`.
+pub fn verify_merkle_range_proof<F: RichField, H: Hasher<F>>(
+    leaves_data: &[Vec<F>],
+    start: usize,
+    merkle_cap: &MerkleCap<F, H>,
+    proof: &MerkleRangeProof<F, H>,
+) -> Result<()>
+where
+    [(); H::HASH_SIZE]:,
+{
+    ensure!(!leaves_data.is_empty(), "The range must be nonempty.");
+    let num_layers = proof.layers.len();
+    ensure!(
+        start + leaves_data.len() <= merkle_cap.len() << num_layers,
+        "The range is out of bounds."
+    );
+
+    let mut first = start;
+    let mut digests = leaves_data
+        .iter()
+        .map(|leaf| H::hash_or_noop(leaf))
+        .collect::<Vec<_>>();
+    let boundaries = boundary_nodes(start, leaves_data.len(), num_layers);
+    for (i, (layer, (left, right))) in proof.layers.iter().zip(boundaries).enumerate() {
+        ensure!(
+            layer.len() == left as usize + right as usize,
+            "Invalid Merkle range proof."
+        );
+        let mut siblings = layer.iter().copied();
+        if left {
+            digests.insert(0, siblings.next().unwrap());
+            first -= 1;
+        }
+        if right {
+            digests.push(siblings.next().unwrap());
+        }
+
+        let domain = CompressionDomain::for_merkle_node(i + 1, num_layers);
+        digests = digests
+            .chunks_exact(2)
+            .map(|pair| H::two_to_one_with_domain(pair[0], pair[1], domain))
+            .collect();
+        first >>= 1;
+    }
+
+    ensure!(
+        merkle_cap.0[first..first + digests.len()] == digests[..],
+        "Invalid Merkle range proof."
+    );
+    Ok(())
+}
+
+/// Sets the witness for a Merkle range proof target.
+pub fn set_merkle_range_proof_target<F: RichField, H: AlgebraicHasher<F>, W: Witness<F>>(
+    witness: &mut W,
+    target: &MerkleRangeProofTarget,
+    proof: &MerkleRangeProof<F, H>,
+) {
+    for (layer_targets, layer) in target.layers.iter().zip(&proof.layers) {
+        for (&t, &sibling) in layer_targets.iter().zip(layer) {
+            witness.set_hash_target(t, sibling);
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a proof target for the range of leaves `start..start + len` of a tree with `num_layers`
+    /// layers below the cap. The shape of the proof depends on the range, so it's fixed in the
+    /// circuit.
+    pub fn add_virtual_merkle_range_proof(
+        &mut self,
+        start: usize,
+        len: usize,
+        num_layers: usize,
+    ) -> MerkleRangeProofTarget {
+        MerkleRangeProofTarget {
+            layers: boundary_nodes(start, len, num_layers)
+                .into_iter()
+                .map(|(left, right)| self.add_virtual_hashes(left as usize + right as usize))
+                .collect(),
+        }
+    }
+
+    /// Verifies that the leaves with indices in `start..start + leaves_data.len()` of the Merkle
+    /// tree with the given cap hold `leaves_data`.
+    pub fn verify_merkle_range_proof<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaves_data: Vec<Vec<Target>>,
+        start: usize,
+        merkle_cap: &MerkleCapTarget,
+        proof: &MerkleRangeProofTarget,
+    ) {
+        let num_layers = proof.layers.len();
+        assert!(
+            start + leaves_data.len() <= merkle_cap.0.len() << num_layers,
+            "The range is out of bounds"
+        );
+
+        let _false = self._false();
+        let mut first = start;
+        let mut digests = leaves_data
+            .into_iter()
+            .map(|leaf| self.hash_or_noop::<H>(leaf))
+            .collect::<Vec<_>>();
+        let boundaries = boundary_nodes(start, digests.len(), num_layers);
+        for (i, (layer, (left, right))) in proof.layers.iter().zip(boundaries).enumerate() {
+            assert_eq!(
+                layer.len(),
+                left as usize + right as usize,
+                "The proof has the wrong shape for this range"
+            );
+            let mut siblings = layer.iter().copied();
+            if left {
+                digests.insert(0, siblings.next().unwrap());
+                first -= 1;
+            }
+            if right {
+                digests.push(siblings.next().unwrap());
+            }
+
+            let domain = CompressionDomain::for_merkle_node(i + 1, num_layers);
+            digests = digests
+                .chunks_exact(2)
+                .map(|pair| {
+                    H::two_to_one_swapped_with_domain(pair[0], pair[1], _false, domain, self)
+                })
+                .collect();
+            first >>= 1;
+        }
+
+        for (i, digest) in digests.into_iter().enumerate() {
+            self.connect_hashes(digest, merkle_cap.0[first + i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::merkle_range_proofs::{
+        set_merkle_range_proof_target, verify_merkle_range_proof,
+    };
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = PoseidonHash;
+
+    fn random_data(n: usize, k: usize) -> Vec<Vec<F>> {
+        (0..n).map(|_| F::rand_vec(k)).collect()
+    }
+
+    #[test]
+    fn test_merkle_range_proofs() -> Result<()> {
+        let log_n = 6;
+        let cap_height = 2;
+        let tree = MerkleTree::<F, H>::new(random_data(1 << log_n, 5), cap_height);
+
+        for (start, len) in [(0, 1), (5, 1), (3, 10), (0, 64), (17, 30), (63, 1)] {
+            let proof = tree.prove_range(start, len);
+            let leaves = &tree.leaves[start..start + len];
+            verify_merkle_range_proof(leaves, start, &tree.cap, &proof)?;
+
+            // The proof doesn't hold for a shifted range, or modified data.
+            if start > 0 {
+                let shifted = &tree.leaves[start - 1..start - 1 + len];
+                assert!(verify_merkle_range_proof(shifted, start - 1, &tree.cap, &proof).is_err());
+            }
+            let mut bad_leaves = leaves.to_vec();
+            bad_leaves[len / 2][0] += F::ONE;
+            assert!(verify_merkle_range_proof(&bad_leaves, start, &tree.cap, &proof).is_err());
+        }
+
+        // A proof of a long range is much smaller than independent proofs of its leaves.
+        let proof = tree.prove_range(3, 40);
+        let num_siblings = proof.layers.iter().map(|l| l.len()).sum::<usize>();
+        assert!(num_siblings <= 2 * (log_n - cap_height));
+
+        let mut bad_proof = proof;
+        let layer = bad_proof.layers.iter_mut().find(|l| !l.is_empty()).unwrap();
+        layer[0] = HashOut::rand();
+        assert!(verify_merkle_range_proof(&tree.leaves[3..43], 3, &tree.cap, &bad_proof).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merkle_range_proof_circuit() -> Result<()> {
+        let log_n = 6;
+        let cap_height = 1;
+        let (start, len) = (11, 20);
+        let tree = MerkleTree::<F, H>::new(random_data(1 << log_n, 3), cap_height);
+        let proof = tree.prove_range(start, len);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let proof_t = builder.add_virtual_merkle_range_proof(start, len, log_n - cap_height);
+        set_merkle_range_proof_target(&mut pw, &proof_t, &proof);
+        let cap_t = builder.add_virtual_cap(cap_height);
+        pw.set_cap_target(&cap_t, &tree.cap);
+        let leaves_t = tree.leaves[start..start + len]
+            .iter()
+            .map(|leaf| {
+                let leaf_t = builder.add_virtual_targets(leaf.len());
+                for (&t, &x) in leaf_t.iter().zip(leaf) {
+                    pw.set_target(t, x);
+                }
+                leaf_t
+            })
+            .collect();
+
+        builder.verify_merkle_range_proof::<H>(leaves_t, start, &cap_t, &proof_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
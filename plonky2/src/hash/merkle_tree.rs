@@ -1,14 +1,16 @@
 use std::mem::MaybeUninit;
 use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use plonky2_util::log2_strict;
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
-use crate::plonk::config::GenericHashOut;
 use crate::plonk::config::Hasher;
+use crate::plonk::config::{CompressionDomain, GenericHashOut};
+use crate::util::maybe_rayon::*;
+use crate::util::progress::{ProvingEvent, ProvingMonitor};
 
 /// The Merkle cap of height `h` of a Merkle tree is the `h`-th layer (from the root) of the tree.
 /// It can be used in place of the root to verify Merkle paths, which are `h` elements shorter.
@@ -57,16 +59,47 @@ fn capacity_up_to_mut<T>(v: &mut Vec<T>, len: usize) -> &mut [MaybeUninit<T>] {
     }
 }
 
-fn fill_subtree<F: RichField, H: Hasher<F>>(
-    digests_buf: &mut [MaybeUninit<H::Hash>],
+/// The number of leaves passed to each call of `Hasher::hash_or_noop_batch`.
+const LEAF_HASH_BATCH_SIZE: usize = 64;
+
+/// Hashes each leaf, in batches so that hashers can vectorize across leaves, reporting progress to
+/// `monitor` each time another percent of the leaves has been hashed.
+fn hash_leaves<F: RichField, H: Hasher<F>>(
     leaves: &[Vec<F>],
-) -> H::Hash
+    monitor: &ProvingMonitor,
+) -> Vec<H::Hash>
 where
     [(); H::HASH_SIZE]:,
 {
-    assert_eq!(leaves.len(), digests_buf.len() / 2 + 1);
+    let total = leaves.len();
+    let hashed = AtomicUsize::new(0);
+    leaves
+        .par_chunks(LEAF_HASH_BATCH_SIZE)
+        .flat_map_iter(|batch| {
+            let digests = H::hash_or_noop_batch(batch);
+            let before = hashed.fetch_add(batch.len(), Ordering::Relaxed);
+            let after = before + batch.len();
+            if before * 100 / total != after * 100 / total {
+                monitor.report(ProvingEvent::LeavesHashed {
+                    hashed: after,
+                    total,
+                });
+            }
+            digests
+        })
+        .collect()
+}
+
+/// Fills the digests of a subtree whose root is at height `log2(leaf_digests.len())` in a tree
+/// with `num_layers` layers below the cap, and returns the digest of its root.
+fn fill_subtree<F: RichField, H: Hasher<F>>(
+    digests_buf: &mut [MaybeUninit<H::Hash>],
+    leaf_digests: &[H::Hash],
+    num_layers: usize,
+) -> H::Hash {
+    assert_eq!(leaf_digests.len(), digests_buf.len() / 2 + 1);
     if digests_buf.is_empty() {
-        H::hash_or_noop(&leaves[0])
+        leaf_digests[0]
     } else {
         // Layout is: left recursive output || left child digest
         //             || right child digest || right recursive output.
@@ -75,58 +108,75 @@ where
         let (left_digests_buf, right_digests_buf) = digests_buf.split_at_mut(digests_buf.len() / 2);
         let (left_digest_mem, left_digests_buf) = left_digests_buf.split_last_mut().unwrap();
         let (right_digest_mem, right_digests_buf) = right_digests_buf.split_first_mut().unwrap();
-        // Split `leaves` between both children.
-        let (left_leaves, right_leaves) = leaves.split_at(leaves.len() / 2);
-        let (left_digest, right_digest) = rayon::join(
-            || fill_subtree::<F, H>(left_digests_buf, left_leaves),
-            || fill_subtree::<F, H>(right_digests_buf, right_leaves),
+        // Split `leaf_digests` between both children.
+        let (left_leaves, right_leaves) = leaf_digests.split_at(leaf_digests.len() / 2);
+        let (left_digest, right_digest) = join(
+            || fill_subtree::<F, H>(left_digests_buf, left_leaves, num_layers),
+            || fill_subtree::<F, H>(right_digests_buf, right_leaves, num_layers),
         );
         left_digest_mem.write(left_digest);
         right_digest_mem.write(right_digest);
-        H::two_to_one(left_digest, right_digest)
+        let domain =
+            CompressionDomain::for_merkle_node(log2_strict(leaf_digests.len()), num_layers);
+        H::two_to_one_with_domain(left_digest, right_digest, domain)
     }
 }
 
 fn fill_digests_buf<F: RichField, H: Hasher<F>>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
     cap_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    leaf_digests: &[H::Hash],
     cap_height: usize,
-) where
-    [(); H::HASH_SIZE]:,
-{
+) {
     // Special case of a tree that's all cap. The usual case will panic because we'll try to split
     // an empty slice into chunks of `0`. (We would not need this if there was a way to split into
     // `blah` chunks as opposed to chunks _of_ `blah`.)
     if digests_buf.is_empty() {
-        debug_assert_eq!(cap_buf.len(), leaves.len());
+        debug_assert_eq!(cap_buf.len(), leaf_digests.len());
         cap_buf
-            .par_iter_mut()
-            .zip(leaves)
-            .for_each(|(cap_buf, leaf)| {
-                cap_buf.write(H::hash_or_noop(leaf));
+            .iter_mut()
+            .zip(leaf_digests)
+            .for_each(|(cap_buf, &leaf_digest)| {
+                cap_buf.write(leaf_digest);
             });
         return;
     }
 
     let subtree_digests_len = digests_buf.len() >> cap_height;
-    let subtree_leaves_len = leaves.len() >> cap_height;
+    let subtree_leaves_len = leaf_digests.len() >> cap_height;
+    let num_layers = log2_strict(subtree_leaves_len);
     let digests_chunks = digests_buf.par_chunks_exact_mut(subtree_digests_len);
-    let leaves_chunks = leaves.par_chunks_exact(subtree_leaves_len);
+    let leaves_chunks = leaf_digests.par_chunks_exact(subtree_leaves_len);
     assert_eq!(digests_chunks.len(), cap_buf.len());
     assert_eq!(digests_chunks.len(), leaves_chunks.len());
     digests_chunks.zip(cap_buf).zip(leaves_chunks).for_each(
         |((subtree_digests, subtree_cap), subtree_leaves)| {
             // We have `1 << cap_height` sub-trees, one for each entry in `cap`. They are totally
-            // independent, so we schedule one task for each. `digests_buf` and `leaves` are split
-            // into `1 << cap_height` slices, one for each sub-tree.
-            subtree_cap.write(fill_subtree::<F, H>(subtree_digests, subtree_leaves));
+            // independent, so we schedule one task for each. `digests_buf` and `leaf_digests` are
+            // split into `1 << cap_height` slices, one for each sub-tree.
+            subtree_cap.write(fill_subtree::<F, H>(
+                subtree_digests,
+                subtree_leaves,
+                num_layers,
+            ));
         },
     );
 }
 
 impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
     pub fn new(leaves: Vec<Vec<F>>, cap_height: usize) -> Self
+    where
+        [(); H::HASH_SIZE]:,
+    {
+        Self::new_with_monitor(leaves, cap_height, &ProvingMonitor::default())
+    }
+
+    /// Builds a tree, reporting the progress of hashing its leaves to `monitor`.
+    pub fn new_with_monitor(
+        leaves: Vec<Vec<F>>,
+        cap_height: usize,
+        monitor: &ProvingMonitor,
+    ) -> Self
     where
         [(); H::HASH_SIZE]:,
     {
@@ -144,7 +194,8 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
 
         let digests_buf = capacity_up_to_mut(&mut digests, num_digests);
         let cap_buf = capacity_up_to_mut(&mut cap, len_cap);
-        fill_digests_buf::<F, H>(digests_buf, cap_buf, &leaves[..], cap_height);
+        let leaf_digests = hash_leaves::<F, H>(&leaves, monitor);
+        fill_digests_buf::<F, H>(digests_buf, cap_buf, &leaf_digests, cap_height);
 
         unsafe {
             // SAFETY: `fill_digests_buf` and `cap` initialized the spare capacity up to
@@ -274,4 +325,36 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_internal_node_is_not_a_leaf() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        // Leaves of 4 elements are their own digests, so without domain separation, an internal
+        // node would be a valid leaf of a tree with one less layer.
+        let leaves = random_data::<F>(4, 4);
+        let tree = MerkleTree::<F, H>::new(leaves.clone(), 0);
+        let proof = tree.prove(0);
+        let internal_sibling = proof.siblings[1];
+        let internal_node = H::two_to_one_with_domain(
+            H::hash_or_noop(&leaves[0]),
+            proof.siblings[0],
+            CompressionDomain::Leaf,
+        );
+
+        let fake_proof = MerkleProof {
+            siblings: vec![internal_sibling],
+        };
+        assert!(
+            verify_merkle_proof(internal_node.elements.to_vec(), 0, &tree.cap, &fake_proof)
+                .is_err()
+        );
+        assert_eq!(
+            H::two_to_one_with_domain(internal_node, internal_sibling, CompressionDomain::Cap),
+            tree.cap.0[0]
+        );
+    }
 }
@@ -1,9 +1,18 @@
 mod arch;
+pub mod hash_to_field;
 pub mod hash_types;
 pub mod hashing;
 pub mod keccak;
+pub mod keccak_batch;
+pub mod key_value_tree;
+pub mod merkle_mountain_range;
 pub mod merkle_proofs;
+pub mod merkle_range_proofs;
 pub mod merkle_tree;
 pub mod path_compression;
 pub mod poseidon;
+pub mod poseidon_constants;
 pub mod poseidon_goldilocks;
+pub mod public_inputs;
+pub mod rescue_prime;
+pub mod tip5;
@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 
+use anyhow::{anyhow, ensure, Result};
 use num::Integer;
 
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
-use crate::plonk::config::Hasher;
+use crate::hash::merkle_tree::MerkleCap;
+use crate::plonk::config::{CompressionDomain, Hasher};
 
 /// Compress multiple Merkle proofs on the same tree by removing redundancy in the Merkle paths.
 pub(crate) fn compress_merkle_proofs<F: RichField, H: Hasher<F>>(
@@ -78,7 +80,9 @@ where
         .map(|p| p.siblings.iter())
         .collect::<Vec<_>>();
     // Fill the `seen` map from the bottom of the tree to the cap.
-    for layer_height in 0..height - cap_height {
+    let num_layers = height - cap_height;
+    for layer_height in 0..num_layers {
+        let domain = CompressionDomain::for_merkle_node(layer_height + 1, num_layers);
         for (&i, p) in leaves_indices.iter().zip(siblings.iter_mut()) {
             let index = (i + num_leaves) >> layer_height;
             let current_hash = seen[&index];
@@ -87,9 +91,9 @@ where
                 .entry(sibling_index)
                 .or_insert_with(|| *p.next().unwrap());
             let parent_hash = if index.is_even() {
-                H::two_to_one(current_hash, sibling_hash)
+                H::two_to_one_with_domain(current_hash, sibling_hash, domain)
             } else {
-                H::two_to_one(sibling_hash, current_hash)
+                H::two_to_one_with_domain(sibling_hash, current_hash, domain)
             };
             seen.insert(index >> 1, parent_hash);
         }
@@ -113,6 +117,78 @@ where
     decompressed_proofs
 }
 
+/// Verifies Merkle proofs compressed by `compress_merkle_proofs` against a cap, without
+/// decompressing them. Each opening holds the data, index and compressed proof of a distinct leaf,
+/// in the order in which `compress_merkle_proofs` first saw that leaf.
+pub(crate) fn verify_compressed_merkle_proofs<F: RichField, H: Hasher<F>>(
+    openings: &[(&[F], usize, &MerkleProof<F, H>)],
+    height: usize,
+    merkle_cap: &MerkleCap<F, H>,
+) -> Result<()>
+where
+    [(); H::HASH_SIZE]:,
+{
+    let num_leaves = 1 << height;
+    let cap_length = merkle_cap.len();
+    ensure!(
+        cap_length.is_power_of_two() && cap_length <= num_leaves,
+        "Merkle cap is larger than the tree."
+    );
+    // Holds the already seen nodes in the tree along with their value.
+    let mut seen = HashMap::new();
+    for &(leaf_data, i, _) in openings {
+        ensure!(i < num_leaves, "Leaf index out of range.");
+        seen.insert(i + num_leaves, H::hash_or_noop(leaf_data));
+    }
+
+    // Iterators over the siblings.
+    let mut siblings = openings
+        .iter()
+        .map(|(_, _, p)| p.siblings.iter())
+        .collect::<Vec<_>>();
+    // Fill the `seen` map from the bottom of the tree to the cap, taking each sibling which isn't
+    // on the path of any opening from the compressed proofs.
+    let num_layers = height - cap_length.trailing_zeros() as usize;
+    for layer_height in 0..num_layers {
+        let domain = CompressionDomain::for_merkle_node(layer_height + 1, num_layers);
+        for (&(_, i, _), p) in openings.iter().zip(siblings.iter_mut()) {
+            let index = (i + num_leaves) >> layer_height;
+            let current_hash = seen[&index];
+            let sibling_index = index ^ 1;
+            let sibling_hash = match seen.get(&sibling_index) {
+                Some(&h) => h,
+                None => {
+                    let h = *p
+                        .next()
+                        .ok_or_else(|| anyhow!("Merkle proof is too short."))?;
+                    seen.insert(sibling_index, h);
+                    h
+                }
+            };
+            let parent_hash = if index.is_even() {
+                H::two_to_one_with_domain(current_hash, sibling_hash, domain)
+            } else {
+                H::two_to_one_with_domain(sibling_hash, current_hash, domain)
+            };
+            seen.insert(index >> 1, parent_hash);
+        }
+    }
+    ensure!(
+        siblings.iter_mut().all(|p| p.next().is_none()),
+        "Merkle proof is too long."
+    );
+
+    for &(_, i, _) in openings {
+        let index = (i + num_leaves) >> num_layers;
+        ensure!(
+            seen[&index] == merkle_cap.0[index - cap_length],
+            "Invalid Merkle proof."
+        );
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use plonky2_field::field_types::Field;
@@ -148,6 +224,18 @@ mod tests {
 
         assert_eq!(proofs, decompressed_proofs);
 
+        // Each distinct leaf, with the compressed proof of its first occurrence.
+        let mut openings = Vec::new();
+        for (&i, p) in indices.iter().zip(&compressed_proofs) {
+            if openings.iter().all(|&(_, j, _)| j != i) {
+                openings.push((vs[i].as_slice(), i, p));
+            }
+        }
+        assert!(verify_compressed_merkle_proofs(&openings, h, &mt.cap).is_ok());
+        let wrong_leaf = vec![F::rand()];
+        openings[0].0 = wrong_leaf.as_slice();
+        assert!(verify_compressed_merkle_proofs(&openings, h, &mt.cap).is_err());
+
         let compressed_proof_bytes = serde_cbor::to_vec(&compressed_proofs).unwrap();
         println!(
             "Compressed proof length: {} bytes",
@@ -9,11 +9,14 @@ use crate::gates::gate::Gate;
 use crate::gates::poseidon::PoseidonGate;
 use crate::gates::poseidon_mds::PoseidonMdsGate;
 use crate::hash::hash_types::{HashOut, RichField};
-use crate::hash::hashing::{compress, hash_n_to_hash_no_pad, PlonkyPermutation, SPONGE_WIDTH};
+use crate::hash::hashing::{
+    compress, compress_with_domain, hash_n_to_hash_no_pad, AlgebraicPermutation, PlonkyPermutation,
+    SPONGE_WIDTH,
+};
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::{AlgebraicHasher, Hasher};
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain, Hasher};
 
 // The number of full rounds and partial rounds is given by the
 // calc_round_numbers.py script. They happen to be the same for both
@@ -222,9 +225,16 @@ pub trait Poseidon: PrimeField64 {
         res
     }
 
+    #[inline(always)]
+    fn mds_layer(state: &[Self; WIDTH]) -> [Self; WIDTH] {
+        Self::mds_layer_scalar(state)
+    }
+
+    /// The portable implementation of `mds_layer`, which vectorized overrides fall back to on CPUs
+    /// lacking the extensions they use.
     #[inline(always)]
     #[unroll_for_loops]
-    fn mds_layer(state_: &[Self; WIDTH]) -> [Self; WIDTH] {
+    fn mds_layer_scalar(state_: &[Self; WIDTH]) -> [Self; WIDTH] {
         let mut result = [Self::ZERO; WIDTH];
 
         let mut state = [0u64; WIDTH];
@@ -465,8 +475,14 @@ pub trait Poseidon: PrimeField64 {
     }
 
     #[inline(always)]
-    #[unroll_for_loops]
     fn constant_layer(state: &mut [Self; WIDTH], round_ctr: usize) {
+        Self::constant_layer_scalar(state, round_ctr);
+    }
+
+    /// The portable implementation of `constant_layer`.
+    #[inline(always)]
+    #[unroll_for_loops]
+    fn constant_layer_scalar(state: &mut [Self; WIDTH], round_ctr: usize) {
         for i in 0..12 {
             if i < WIDTH {
                 let round_constant = ALL_ROUND_CONSTANTS[i + WIDTH * round_ctr];
@@ -525,8 +541,14 @@ pub trait Poseidon: PrimeField64 {
     }
 
     #[inline(always)]
-    #[unroll_for_loops]
     fn sbox_layer(state: &mut [Self; WIDTH]) {
+        Self::sbox_layer_scalar(state);
+    }
+
+    /// The portable implementation of `sbox_layer`.
+    #[inline(always)]
+    #[unroll_for_loops]
+    fn sbox_layer_scalar(state: &mut [Self; WIDTH]) {
         for i in 0..12 {
             if i < WIDTH {
                 state[i] = Self::sbox_monomial(state[i]);
@@ -582,6 +604,12 @@ pub trait Poseidon: PrimeField64 {
 
     #[inline]
     fn poseidon(input: [Self; WIDTH]) -> [Self; WIDTH] {
+        Self::poseidon_scalar(input)
+    }
+
+    /// The portable implementation of `poseidon`.
+    #[inline]
+    fn poseidon_scalar(input: [Self; WIDTH]) -> [Self; WIDTH] {
         let mut state = input;
         let mut round_ctr = 0;
 
@@ -625,6 +653,19 @@ impl<F: RichField> PlonkyPermutation<F> for PoseidonPermutation {
     }
 }
 
+impl<F: RichField> AlgebraicPermutation<F> for PoseidonPermutation {
+    fn permute_circuit<const D: usize>(
+        inputs: [Target; SPONGE_WIDTH],
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> [Target; SPONGE_WIDTH]
+    where
+        F: Extendable<D>,
+    {
+        let _false = builder._false();
+        PoseidonHash::permute_swapped(inputs, _false, builder)
+    }
+}
+
 /// Poseidon hash function.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct PoseidonHash;
@@ -640,6 +681,14 @@ impl<F: RichField> Hasher<F> for PoseidonHash {
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
         compress::<F, Self::Permutation>(left, right)
     }
+
+    fn two_to_one_with_domain(
+        left: Self::Hash,
+        right: Self::Hash,
+        domain: CompressionDomain,
+    ) -> Self::Hash {
+        compress_with_domain::<F, Self::Permutation>(left, right, domain)
+    }
 }
 
 impl<F: RichField> AlgebraicHasher<F> for PoseidonHash {
@@ -0,0 +1,278 @@
+//! Generation of Poseidon round constants and MDS matrices with the Grain LFSR, following the
+//! reference procedure from the Poseidon paper (`generate_parameters_grain.sage`), for an `x^alpha`
+//! S-box over a prime field of at most 64 bits.
+//!
+//! The generators are `const fn`s, so that the parameters of a new instance can be computed at
+//! compile time, e.g. `const PARAMS: PoseidonParameters<8, 240> = PoseidonParameters::generate(p,
+//! 8, 22);`. The reference procedure additionally checks the MDS matrix against infinitely long
+//! invariant subspace trails, which is not done here; a matrix intended for production use should
+//! be checked with the reference script.
+
+/// Round constants and MDS matrix of a Poseidon instance, as canonical field elements.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoseidonParameters<const WIDTH: usize, const N_ROUND_CONSTANTS: usize> {
+    /// The round constants, with the `WIDTH` constants of each round in order.
+    pub round_constants: [u64; N_ROUND_CONSTANTS],
+    /// The Cauchy MDS matrix, with entries `1 / (x_i + y_j)`.
+    pub mds_matrix: [[u64; WIDTH]; WIDTH],
+}
+
+impl<const WIDTH: usize, const N_ROUND_CONSTANTS: usize>
+    PoseidonParameters<WIDTH, N_ROUND_CONSTANTS>
+{
+    /// Generates the parameters of the instance of width `WIDTH` over the field of order
+    /// `modulus` with the given numbers of rounds. `N_ROUND_CONSTANTS` must be
+    /// `WIDTH * (n_full_rounds + n_partial_rounds)`.
+    pub const fn generate(modulus: u64, n_full_rounds: usize, n_partial_rounds: usize) -> Self {
+        assert!(N_ROUND_CONSTANTS == WIDTH * (n_full_rounds + n_partial_rounds));
+        let field_bits = (64 - modulus.leading_zeros()) as usize;
+        let mut lfsr = GrainLfsr::new(field_bits, WIDTH, n_full_rounds, n_partial_rounds);
+
+        let mut round_constants = [0; N_ROUND_CONSTANTS];
+        let mut i = 0;
+        while i < N_ROUND_CONSTANTS {
+            // Rejection sampling, so that constants are uniform.
+            let (next, c) = lfsr.next_bits(field_bits);
+            lfsr = next;
+            if c < modulus {
+                round_constants[i] = c;
+                i += 1;
+            }
+        }
+
+        // Sample `x_i` and `y_j` until they are all distinct and no `x_i + y_j` is zero, which
+        // makes the Cauchy matrix well defined and MDS.
+        let (xs, ys) = loop {
+            let mut xs = [0; WIDTH];
+            let mut ys = [0; WIDTH];
+            let mut i = 0;
+            while i < WIDTH {
+                let (next, x) = lfsr.next_bits(field_bits);
+                xs[i] = x % modulus;
+                lfsr = next;
+                i += 1;
+            }
+            let mut i = 0;
+            while i < WIDTH {
+                let (next, y) = lfsr.next_bits(field_bits);
+                ys[i] = y % modulus;
+                lfsr = next;
+                i += 1;
+            }
+            if distinct(&xs, &ys) && no_zero_sums(&xs, &ys, modulus) {
+                break (xs, ys);
+            }
+        };
+
+        let mut mds_matrix = [[0; WIDTH]; WIDTH];
+        let mut i = 0;
+        while i < WIDTH {
+            let mut j = 0;
+            while j < WIDTH {
+                mds_matrix[i][j] = inverse_mod(add_mod(xs[i], ys[j], modulus), modulus);
+                j += 1;
+            }
+            i += 1;
+        }
+
+        Self {
+            round_constants,
+            mds_matrix,
+        }
+    }
+}
+
+/// The Grain LFSR, whose 80 bits of state are stored in the low bits of `state`, oldest first.
+#[derive(Copy, Clone, Debug)]
+struct GrainLfsr {
+    state: u128,
+}
+
+impl GrainLfsr {
+    const fn new(
+        field_bits: usize,
+        width: usize,
+        n_full_rounds: usize,
+        n_partial_rounds: usize,
+    ) -> Self {
+        // The initial state encodes, as big-endian bit strings of the given lengths: the field
+        // type (1 for prime fields), the S-box type (0 for `x^alpha`), the field size, the width,
+        // the numbers of full and partial rounds, and finally 30 ones.
+        let fields = [
+            (1, 2),
+            (0, 4),
+            (field_bits, 12),
+            (width, 12),
+            (n_full_rounds, 10),
+            (n_partial_rounds, 10),
+            ((1 << 30) - 1, 30),
+        ];
+        let mut state = 0;
+        let mut pos = 0;
+        let mut i = 0;
+        while i < fields.len() {
+            let (value, len) = fields[i];
+            assert!(value < 1 << len);
+            let mut j = 0;
+            while j < len {
+                let bit = (value >> (len - 1 - j)) & 1;
+                state |= (bit as u128) << pos;
+                pos += 1;
+                j += 1;
+            }
+            i += 1;
+        }
+
+        let mut lfsr = Self { state };
+        let mut i = 0;
+        while i < 160 {
+            lfsr = lfsr.step().0;
+            i += 1;
+        }
+        lfsr
+    }
+
+    const fn step(self) -> (Self, bool) {
+        let s = self.state;
+        let bit = ((s >> 62) ^ (s >> 51) ^ (s >> 38) ^ (s >> 23) ^ (s >> 13) ^ s) & 1;
+        let state = (s >> 1) | (bit << 79);
+        (Self { state }, bit == 1)
+    }
+
+    /// Returns the next output bit. Bits are produced in pairs, and the second bit of a pair is
+    /// output iff the first bit is set.
+    const fn next_bit(self) -> (Self, bool) {
+        let mut lfsr = self;
+        loop {
+            let (next, keep) = lfsr.step();
+            let (next, bit) = next.step();
+            lfsr = next;
+            if keep {
+                return (lfsr, bit);
+            }
+        }
+    }
+
+    /// Returns an integer made of the next `n` output bits, most significant first.
+    const fn next_bits(self, n: usize) -> (Self, u64) {
+        let mut lfsr = self;
+        let mut x = 0;
+        let mut i = 0;
+        while i < n {
+            let (next, bit) = lfsr.next_bit();
+            lfsr = next;
+            x = (x << 1) | bit as u64;
+            i += 1;
+        }
+        (lfsr, x)
+    }
+}
+
+const fn distinct<const WIDTH: usize>(xs: &[u64; WIDTH], ys: &[u64; WIDTH]) -> bool {
+    let mut i = 0;
+    while i < 2 * WIDTH {
+        let mut j = i + 1;
+        while j < 2 * WIDTH {
+            let a = if i < WIDTH { xs[i] } else { ys[i - WIDTH] };
+            let b = if j < WIDTH { xs[j] } else { ys[j - WIDTH] };
+            if a == b {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn no_zero_sums<const WIDTH: usize>(xs: &[u64; WIDTH], ys: &[u64; WIDTH], p: u64) -> bool {
+    let mut i = 0;
+    while i < WIDTH {
+        let mut j = 0;
+        while j < WIDTH {
+            if add_mod(xs[i], ys[j], p) == 0 {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn add_mod(x: u64, y: u64, p: u64) -> u64 {
+    ((x as u128 + y as u128) % p as u128) as u64
+}
+
+const fn mul_mod(x: u64, y: u64, p: u64) -> u64 {
+    (x as u128 * y as u128 % p as u128) as u64
+}
+
+/// Computes `x^-1 mod p` as `x^(p - 2)`, for a prime `p`.
+const fn inverse_mod(x: u64, p: u64) -> u64 {
+    let mut result = 1;
+    let mut base = x;
+    let mut exp = p - 2;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, p);
+        }
+        base = mul_mod(base, base, p);
+        exp >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2_field::field_types::{Field, Field64};
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::hash::poseidon_constants::PoseidonParameters;
+
+    type F = GoldilocksField;
+
+    // Evaluated at compile time.
+    const PARAMS: PoseidonParameters<12, 360> = PoseidonParameters::generate(F::ORDER, 8, 22);
+
+    #[test]
+    fn test_goldilocks_width_12() {
+        // Test vectors computed with the reference procedure.
+        assert_eq!(
+            PARAMS.round_constants[..4],
+            [
+                0x13dcf33aba214f46,
+                0x30b3b654a1da6d83,
+                0x1fc634ada6159b56,
+                0x937459964dc03466,
+            ]
+        );
+        assert_eq!(PARAMS.round_constants[359], 0x23c7426af725a6a0);
+        assert_eq!(
+            PARAMS.mds_matrix[0][..3],
+            [0x5edfe0e0ee54d262, 0x624371bc65182b55, 0x44e1c8237dd3dc06]
+        );
+        assert_eq!(PARAMS.mds_matrix[11][11], 0x7a3747e4492d24e7);
+    }
+
+    #[test]
+    fn test_mds_matrix_is_invertible() {
+        // Gaussian elimination over the field.
+        let mut m = PARAMS
+            .mds_matrix
+            .map(|row| row.map(F::from_canonical_u64).to_vec())
+            .to_vec();
+        for col in 0..12 {
+            let pivot = (col..12).find(|&r| m[r][col].is_nonzero()).unwrap();
+            m.swap(col, pivot);
+            let inv = m[col][col].inverse();
+            for r in col + 1..12 {
+                let factor = m[r][col] * inv;
+                for c in col..12 {
+                    let x = m[col][c];
+                    m[r][c] -= factor * x;
+                }
+            }
+        }
+    }
+}
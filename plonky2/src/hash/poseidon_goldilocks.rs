@@ -211,60 +211,72 @@ impl Poseidon for GoldilocksField {
          0x2c3887c29246a985, 0x863ca0992eae09b0, 0xb8dee12bf8e622dc, ],
     ];
 
-    #[cfg(all(target_arch="x86_64", target_feature="avx2", target_feature="bmi2"))]
+    // The vectorized implementations are chosen at runtime, so that a binary built for a baseline
+    // target still uses the extensions of the CPU it runs on.
+
     #[inline]
     fn poseidon(input: [Self; 12]) -> [Self; 12] {
-        unsafe {
-            crate::hash::arch::x86_64::poseidon_goldilocks_avx2_bmi2::poseidon(&input)
+        #[cfg(target_arch = "x86_64")]
+        if crate::hash::arch::x86_64::has_avx2_bmi2() {
+            return unsafe {
+                crate::hash::arch::x86_64::poseidon_goldilocks_avx2_bmi2::poseidon(&input)
+            };
+        }
+        #[cfg(target_arch = "aarch64")]
+        if crate::hash::arch::aarch64::has_neon() {
+            return unsafe {
+                crate::hash::arch::aarch64::poseidon_goldilocks_neon::poseidon(input)
+            };
         }
+        Self::poseidon_scalar(input)
     }
 
-    #[cfg(all(target_arch="x86_64", target_feature="avx2", target_feature="bmi2"))]
     #[inline(always)]
     fn constant_layer(state: &mut [Self; 12], round_ctr: usize) {
-        unsafe {
-            crate::hash::arch::x86_64::poseidon_goldilocks_avx2_bmi2::constant_layer(state, round_ctr);
+        #[cfg(target_arch = "x86_64")]
+        if crate::hash::arch::x86_64::has_avx2_bmi2() {
+            unsafe {
+                crate::hash::arch::x86_64::poseidon_goldilocks_avx2_bmi2::constant_layer(state, round_ctr);
+            }
+            return;
         }
+        Self::constant_layer_scalar(state, round_ctr);
     }
 
-    #[cfg(all(target_arch="x86_64", target_feature="avx2", target_feature="bmi2"))]
     #[inline(always)]
     fn sbox_layer(state: &mut [Self; 12]) {
-        unsafe {
-            crate::hash::arch::x86_64::poseidon_goldilocks_avx2_bmi2::sbox_layer(state);
+        #[cfg(target_arch = "x86_64")]
+        if crate::hash::arch::x86_64::has_avx2_bmi2() {
+            unsafe {
+                crate::hash::arch::x86_64::poseidon_goldilocks_avx2_bmi2::sbox_layer(state);
+            }
+            return;
         }
-    }
-
-    #[cfg(all(target_arch="x86_64", target_feature="avx2", target_feature="bmi2"))]
-    #[inline(always)]
-    fn mds_layer(state: &[Self; 12]) -> [Self; 12] {
-        unsafe {
-            crate::hash::arch::x86_64::poseidon_goldilocks_avx2_bmi2::mds_layer(state)
-        }
-    }
-
-    #[cfg(all(target_arch="aarch64", target_feature="neon"))]
-    #[inline]
-    fn poseidon(input: [Self; 12]) -> [Self; 12] {
-        unsafe {
-            crate::hash::arch::aarch64::poseidon_goldilocks_neon::poseidon(input)
+        #[cfg(target_arch = "aarch64")]
+        if crate::hash::arch::aarch64::has_neon() {
+            unsafe {
+                crate::hash::arch::aarch64::poseidon_goldilocks_neon::sbox_layer(state);
+            }
+            return;
         }
+        Self::sbox_layer_scalar(state);
     }
 
-    #[cfg(all(target_arch="aarch64", target_feature="neon"))]
-    #[inline(always)]
-    fn sbox_layer(state: &mut [Self; 12]) {
-        unsafe {
-            crate::hash::arch::aarch64::poseidon_goldilocks_neon::sbox_layer(state);
-        }
-    }
-
-    #[cfg(all(target_arch="aarch64", target_feature="neon"))]
     #[inline(always)]
     fn mds_layer(state: &[Self; 12]) -> [Self; 12] {
-        unsafe {
-            crate::hash::arch::aarch64::poseidon_goldilocks_neon::mds_layer(state)
+        #[cfg(target_arch = "x86_64")]
+        if crate::hash::arch::x86_64::has_avx2_bmi2() {
+            return unsafe {
+                crate::hash::arch::x86_64::poseidon_goldilocks_avx2_bmi2::mds_layer(state)
+            };
         }
+        #[cfg(target_arch = "aarch64")]
+        if crate::hash::arch::aarch64::has_neon() {
+            return unsafe {
+                crate::hash::arch::aarch64::poseidon_goldilocks_neon::mds_layer(state)
+            };
+        }
+        Self::mds_layer_scalar(state)
     }
 }
 
@@ -275,6 +287,7 @@ mod tests {
     use plonky2_field::goldilocks_field::GoldilocksField as F;
 
     use crate::hash::poseidon::test_helpers::{check_consistency, check_test_vectors};
+    use crate::hash::poseidon::Poseidon;
 
     #[test]
     fn test_vectors() {
@@ -336,4 +349,22 @@ mod tests {
     fn consistency() {
         check_consistency::<F>();
     }
+
+    /// The implementations chosen at runtime agree with the portable ones, whichever they are on
+    /// the CPU running the test.
+    #[test]
+    fn test_dispatch_matches_scalar() {
+        let input = F::rand_arr::<12>();
+        assert_eq!(F::poseidon(input), F::poseidon_scalar(input));
+        assert_eq!(F::mds_layer(&input), F::mds_layer_scalar(&input));
+
+        let mut state = input;
+        let mut state_scalar = input;
+        F::constant_layer(&mut state, 3);
+        F::constant_layer_scalar(&mut state_scalar, 3);
+        assert_eq!(state, state_scalar);
+        F::sbox_layer(&mut state);
+        F::sbox_layer_scalar(&mut state_scalar);
+        assert_eq!(state, state_scalar);
+    }
 }
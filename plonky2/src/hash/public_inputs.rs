@@ -0,0 +1,411 @@
+//! Hash functions for the public inputs of a proof. The public inputs hash is computed in the
+//! circuit, where the `PublicInputGate` exposes it, and by the verifier. It can be chosen
+//! independently of the config's other hashers, e.g. so that a smart contract can recompute it
+//! with Keccak while recursion uses Poseidon for everything else.
+//!
+//! Public inputs can also be split into named groups, each hashed separately with its own hash
+//! function. The public inputs hash is then the config's hash of the group digests, followed by the
+//! public inputs outside of any group, so that a consumer of one group only needs the digests of
+//! the others.
+
+use std::ops::Range;
+
+use keccak_hash::keccak;
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::hash::hashing::{AlgebraicPermutation, PermutationHasher};
+use crate::hash::keccak::KeccakHash;
+use crate::hash::poseidon::PoseidonHash;
+use crate::hash::rescue_prime::RescuePrimeHash;
+use crate::hash::tip5::Tip5Hash;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, Hasher};
+
+/// A hash function for public inputs, with a native and an in-circuit implementation. Digests are
+/// four field elements, which is the number of hash wires of the `PublicInputGate`.
+pub trait PublicInputsHasher<F: RichField + Extendable<D>, const D: usize> {
+    fn hash_public_inputs(inputs: &[F]) -> HashOut<F>;
+
+    fn hash_public_inputs_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: Vec<Target>,
+    ) -> HashOutTarget;
+}
+
+fn hash_algebraic<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    inputs: Vec<Target>,
+) -> HashOutTarget {
+    builder.hash_n_to_hash_no_pad::<H>(inputs)
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PublicInputsHasher<F, D> for PoseidonHash {
+    fn hash_public_inputs(inputs: &[F]) -> HashOut<F> {
+        Self::hash_no_pad(inputs)
+    }
+
+    fn hash_public_inputs_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: Vec<Target>,
+    ) -> HashOutTarget {
+        hash_algebraic::<F, Self, D>(builder, inputs)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PublicInputsHasher<F, D> for Tip5Hash {
+    fn hash_public_inputs(inputs: &[F]) -> HashOut<F> {
+        Self::hash_no_pad(inputs)
+    }
+
+    fn hash_public_inputs_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: Vec<Target>,
+    ) -> HashOutTarget {
+        hash_algebraic::<F, Self, D>(builder, inputs)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PublicInputsHasher<F, D> for RescuePrimeHash {
+    fn hash_public_inputs(inputs: &[F]) -> HashOut<F> {
+        Self::hash_no_pad(inputs)
+    }
+
+    fn hash_public_inputs_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: Vec<Target>,
+    ) -> HashOutTarget {
+        hash_algebraic::<F, Self, D>(builder, inputs)
+    }
+}
+
+impl<F: RichField + Extendable<D>, P: AlgebraicPermutation<F>, const D: usize>
+    PublicInputsHasher<F, D> for PermutationHasher<P>
+{
+    fn hash_public_inputs(inputs: &[F]) -> HashOut<F> {
+        Self::hash_no_pad(inputs)
+    }
+
+    fn hash_public_inputs_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: Vec<Target>,
+    ) -> HashOutTarget {
+        hash_algebraic::<F, Self, D>(builder, inputs)
+    }
+}
+
+/// Keccak-256 of the public inputs, each encoded as 8 little-endian bytes. Each 8-byte word of the
+/// digest, read as a little-endian integer, is reduced to a field element.
+impl<F: RichField + Extendable<D>, const D: usize> PublicInputsHasher<F, D> for KeccakHash<32> {
+    fn hash_public_inputs(inputs: &[F]) -> HashOut<F> {
+        let bytes = inputs
+            .iter()
+            .flat_map(|x| x.to_canonical_u64().to_le_bytes())
+            .collect::<Vec<_>>();
+        let digest = keccak(bytes).0;
+        HashOut::from_vec(
+            digest
+                .chunks_exact(8)
+                .map(|word| F::from_noncanonical_u64(u64::from_le_bytes(word.try_into().unwrap())))
+                .collect(),
+        )
+    }
+
+    fn hash_public_inputs_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: Vec<Target>,
+    ) -> HashOutTarget {
+        let bytes = inputs
+            .into_iter()
+            .flat_map(|x| {
+                let bits = builder.split_le(x, 64);
+                bits.chunks(8)
+                    .map(|byte| builder.le_sum(byte.iter()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let digest = builder.keccak256(&bytes);
+        let base = F::from_canonical_u16(256);
+        HashOutTarget::from_vec(
+            digest
+                .chunks_exact(8)
+                .map(|word| {
+                    // Horner's rule, from the most significant byte.
+                    word.iter().rev().fold(builder.zero(), |acc, &byte| {
+                        builder.mul_const_add(base, acc, byte)
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A hash function for a group of public inputs, chosen when building the circuit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PublicInputsHashFunction {
+    Poseidon,
+    Tip5,
+    RescuePrime,
+    /// Keccak-256, as implemented by `KeccakHash<32>`.
+    Keccak,
+}
+
+impl PublicInputsHashFunction {
+    pub fn hash<F: RichField + Extendable<D>, const D: usize>(self, inputs: &[F]) -> HashOut<F> {
+        match self {
+            Self::Poseidon => {
+                <PoseidonHash as PublicInputsHasher<F, D>>::hash_public_inputs(inputs)
+            }
+            Self::Tip5 => <Tip5Hash as PublicInputsHasher<F, D>>::hash_public_inputs(inputs),
+            Self::RescuePrime => {
+                <RescuePrimeHash as PublicInputsHasher<F, D>>::hash_public_inputs(inputs)
+            }
+            Self::Keccak => {
+                <KeccakHash<32> as PublicInputsHasher<F, D>>::hash_public_inputs(inputs)
+            }
+        }
+    }
+
+    pub fn hash_circuit<F: RichField + Extendable<D>, const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: Vec<Target>,
+    ) -> HashOutTarget {
+        match self {
+            Self::Poseidon => PoseidonHash::hash_public_inputs_circuit(builder, inputs),
+            Self::Tip5 => Tip5Hash::hash_public_inputs_circuit(builder, inputs),
+            Self::RescuePrime => RescuePrimeHash::hash_public_inputs_circuit(builder, inputs),
+            Self::Keccak => KeccakHash::<32>::hash_public_inputs_circuit(builder, inputs),
+        }
+    }
+}
+
+/// A named group of public inputs, which is hashed separately from the others.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PublicInputGroup {
+    pub name: String,
+    pub hash: PublicInputsHashFunction,
+    /// The positions of the group's inputs among the public inputs.
+    pub range: Range<usize>,
+}
+
+impl PublicInputGroup {
+    /// The group's inputs, out of all the public inputs.
+    pub fn inputs<'a, T>(&self, public_inputs: &'a [T]) -> &'a [T] {
+        &public_inputs[self.range.clone()]
+    }
+
+    /// The digest of the group's inputs, out of all the public inputs.
+    pub fn digest<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        public_inputs: &[F],
+    ) -> HashOut<F> {
+        self.hash.hash::<F, D>(self.inputs(public_inputs))
+    }
+}
+
+/// The elements hashed by `H` into the public inputs hash: the public inputs themselves if there
+/// are no groups, or else the digests of the groups followed by the ungrouped public inputs.
+fn hashed_elements<T: Copy>(
+    public_inputs: &[T],
+    groups: &[PublicInputGroup],
+    mut digest: impl FnMut(&PublicInputGroup) -> [T; 4],
+) -> Vec<T> {
+    let ungrouped = public_inputs
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !groups.iter().any(|g| g.range.contains(i)))
+        .map(|(_, &x)| x);
+    groups
+        .iter()
+        .flat_map(&mut digest)
+        .chain(ungrouped)
+        .collect()
+}
+
+/// Hashes public inputs which may be split into groups, with `H` combining the groups.
+pub fn hash_grouped_public_inputs<
+    F: RichField + Extendable<D>,
+    H: PublicInputsHasher<F, D>,
+    const D: usize,
+>(
+    public_inputs: &[F],
+    groups: &[PublicInputGroup],
+) -> HashOut<F> {
+    if groups.is_empty() {
+        return H::hash_public_inputs(public_inputs);
+    }
+    H::hash_public_inputs(&hashed_elements(public_inputs, groups, |g| {
+        g.digest::<F, D>(public_inputs).elements
+    }))
+}
+
+/// The in-circuit counterpart of `hash_grouped_public_inputs`.
+pub fn hash_grouped_public_inputs_circuit<
+    F: RichField + Extendable<D>,
+    H: PublicInputsHasher<F, D>,
+    const D: usize,
+>(
+    builder: &mut CircuitBuilder<F, D>,
+    public_inputs: Vec<Target>,
+    groups: &[PublicInputGroup],
+) -> HashOutTarget {
+    if groups.is_empty() {
+        return H::hash_public_inputs_circuit(builder, public_inputs);
+    }
+    let elements = hashed_elements(&public_inputs, groups, |g| {
+        g.hash
+            .hash_circuit(builder, g.inputs(&public_inputs).to_vec())
+            .elements
+    });
+    H::hash_public_inputs_circuit(builder, elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::keccak::KeccakHash;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::hash::public_inputs::{PublicInputsHashFunction, PublicInputsHasher};
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, VerifierCircuitData, VerifierCircuitTarget};
+    use crate::plonk::config::{
+        GenericConfig, KeccakPublicInputsGoldilocksConfig, PoseidonGoldilocksConfig,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_keccak_public_inputs_hash_circuit() -> Result<()> {
+        let inputs = F::rand_vec(20);
+        let expected: HashOut<F> =
+            <KeccakHash<32> as PublicInputsHasher<F, D>>::hash_public_inputs(&inputs);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = builder.add_virtual_targets(inputs.len());
+        for (&t, &x) in targets.iter().zip(&inputs) {
+            pw.set_target(t, x);
+        }
+        let hash = <KeccakHash<32> as PublicInputsHasher<F, D>>::hash_public_inputs_circuit(
+            &mut builder,
+            targets,
+        );
+        for (&t, x) in hash.elements.iter().zip(expected.elements) {
+            let x = builder.constant(x);
+            builder.connect(t, x);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_keccak_public_inputs_config() -> Result<()> {
+        type C = KeccakPublicInputsGoldilocksConfig;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = builder.add_virtual_targets(3);
+        builder.register_public_inputs(&targets);
+        for &t in &targets {
+            pw.set_target(t, F::rand());
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        assert_eq!(
+            proof.get_public_inputs_hash(),
+            <KeccakHash<32> as PublicInputsHasher<F, D>>::hash_public_inputs(&proof.public_inputs)
+        );
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_public_input_groups() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let ungrouped = builder.add_virtual_target();
+        builder.register_public_input(ungrouped);
+        let on_chain = builder.add_virtual_targets(3);
+        builder.register_public_input_group(
+            "on-chain",
+            PublicInputsHashFunction::Keccak,
+            &on_chain,
+        );
+        let recursion = builder.add_virtual_targets(5);
+        builder.register_public_input_group(
+            "recursion",
+            PublicInputsHashFunction::Poseidon,
+            &recursion,
+        );
+        for &t in [ungrouped].iter().chain(&on_chain).chain(&recursion) {
+            pw.set_target(t, F::rand());
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        // The public inputs hash only depends on the other groups through their digests.
+        let on_chain_group = data.common.public_input_group("on-chain").unwrap();
+        let recursion_group = data.common.public_input_group("recursion").unwrap();
+        let on_chain_inputs = on_chain_group.inputs(&proof.public_inputs);
+        assert_eq!(on_chain_inputs, &proof.public_inputs[1..4]);
+        let on_chain_digest =
+            <KeccakHash<32> as PublicInputsHasher<F, D>>::hash_public_inputs(on_chain_inputs);
+        let recursion_digest = recursion_group.digest::<F, D>(&proof.public_inputs);
+        let elements = on_chain_digest
+            .elements
+            .into_iter()
+            .chain(recursion_digest.elements)
+            .chain([proof.public_inputs[0]])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            proof.get_public_inputs_hash_with(&data.common),
+            <PoseidonHash as PublicInputsHasher<F, D>>::hash_public_inputs(&elements)
+        );
+        data.verify(proof.clone())?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        let pt = builder.add_virtual_proof_with_pis(&data.common);
+        pw.set_proof_with_pis_target(&pt, &proof);
+        let inner_data = VerifierCircuitTarget {
+            constants_sigmas_cap: builder.add_virtual_cap(data.common.config.fri_config.cap_height),
+        };
+        pw.set_cap_target(
+            &inner_data.constants_sigmas_cap,
+            &data.verifier_only.constants_sigmas_cap,
+        );
+        builder.verify_proof(pt, &inner_data, &data.common);
+        let recursive_data = builder.build::<C>();
+        let recursive_proof = recursive_data.prove(pw)?;
+        recursive_data.verify(recursive_proof)
+    }
+
+    #[test]
+    fn test_public_input_groups_change_digest() {
+        let verifier_data = |name| {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let targets = builder.add_virtual_targets(2);
+            builder.register_public_input_group(name, PublicInputsHashFunction::Poseidon, &targets);
+            let data = builder.build::<C>();
+            VerifierCircuitData {
+                verifier_only: data.verifier_only,
+                common: data.common,
+            }
+        };
+        // The circuits only differ in the name of their group.
+        assert_ne!(verifier_data("a").digest(), verifier_data("b").digest());
+    }
+}
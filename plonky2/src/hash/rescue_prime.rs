@@ -0,0 +1,391 @@
+//! Implementation of the Rescue-Prime permutation, as described in
+//! https://eprint.iacr.org/2020/1143.pdf
+//!
+//! Each round applies the S-box `x^7` and then its inverse `x^(1/7)`, each followed by a linear
+//! layer. The inverse S-box has a high degree, but is checked in circuits by its low-degree
+//! inverse, so a Rescue-Prime gate needs more wires than a Poseidon gate but fewer rounds.
+//!
+//! The parameters are those which the reference implementation of the paper derives for the
+//! Goldilocks field, a width of 12, a capacity of 4 and 128-bit security.
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+
+use crate::gates::rescue_prime::RescuePrimeGate;
+use crate::hash::hash_types::{HashOut, RichField};
+use crate::hash::hashing::{
+    compress, compress_with_domain, hash_n_to_hash_no_pad, PlonkyPermutation, SPONGE_WIDTH,
+};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain, Hasher};
+
+/// The number of rounds for a width of 12 and a capacity of 4 at the 128-bit security level, with
+/// the recommended 50% security margin.
+pub const RESCUE_PRIME_N_ROUNDS: usize = 8;
+
+pub(crate) const RESCUE_PRIME_ALPHA: u64 = 7;
+/// The inverse of `RESCUE_PRIME_ALPHA` modulo `p - 1` in the Goldilocks field.
+const RESCUE_PRIME_ALPHA_INV: u64 = 10540996611094048183;
+
+/// The MDS matrix of the reference implementation: the transpose of the right half of the echelon
+/// form of the `12 x 24` Vandermonde matrix `(g^(i j))`, where `g = 7` generates the multiplicative
+/// group.
+#[rustfmt::skip]
+const RESCUE_PRIME_MDS: [[u64; SPONGE_WIDTH]; SPONGE_WIDTH] = [
+    [
+        0x1d4432c2c62b8560, 0x9bc11561d6440acb, 0x202ca9ebe5cceb64, 0x9bfe2a4f0c017c2a,
+        0x6f1ff66150e7e72b, 0x99c7056e7a4e495b, 0x3671223a0ae084fd, 0xee9d983091e3d5a9,
+        0x021e37506702caaa, 0x63f74568eb8a4c10, 0xf6c4b0a72dba2fb7, 0x00000000898036b0,
+    ],
+    [
+        0x2ec0835c6c55ca7c, 0x4cc36b4624116cae, 0x6b833e9b3184f367, 0xc4925e08b239ff38,
+        0x40946583f303b927, 0x4c6292ccf81b0176, 0x2edc329316f945c7, 0x1769b9de2beb36f5,
+        0x7385d036486bcb5f, 0xf4f63e1de4711088, 0xd80e5636e790da47, 0x409f2b674968e8b6,
+    ],
+    [
+        0x2388d9365f8d086e, 0x95ca47ec855e4eb2, 0x05ab2b5356e05b9e, 0xc0d2cd7eab963979,
+        0xf7cd4dcbe23adfad, 0xc95e3c5ffb05edaa, 0xa8f8edee8da2b931, 0xa6b5af17a0f7e23f,
+        0x0d8e93bb5b5990d6, 0x2c3a8d613a13810f, 0x404442655843e95b, 0xf5475b511f11afc9,
+    ],
+    [
+        0x97d4f25db7d4bae3, 0x7ea6ca3c47cfd890, 0xba8b270db132aca8, 0xbfe968a65d720a56,
+        0x56ad2192d27a1592, 0x2b43ced6084ac90f, 0xf1528542c1c708f1, 0x328f12e8482a2dc5,
+        0x917ef019c09894b4, 0x386fba1f35a6ed31, 0x7aca524ab57dfcc1, 0x84842e9461432199,
+    ],
+    [
+        0xd945a3b972e6545e, 0x044c6c8187d1db7f, 0xdf17bb7a8b70a3d4, 0x4ab87e3a7e93ddd6,
+        0xd28b1641cfb56a6c, 0x5c6e359fb8727a31, 0x5b87beea92e0b2ce, 0xd4bfd68ca6d159a1,
+        0x254b361e05918ecf, 0xdcc27d13db8a5725, 0x2666d2ce353f36e3, 0x70e84eb1230e409d,
+    ],
+    [
+        0xcbd41ae089895ff9, 0x70ba27f427fc468a, 0xebe593c21d3d5084, 0x284d3f173d043bc0,
+        0x9b0451ddedf53a94, 0x4b9d26f247444217, 0x8787f807bcbf7469, 0x35765054162bc210,
+        0xca4c5ceede976ebb, 0xa6768e87e8400447, 0x732ced96bdb4c4aa, 0x27af50126787e270,
+    ],
+    [
+        0x444a0e7d460b2987, 0xb9adab858ff7f4a2, 0x2bfb348d94abae16, 0xda9ed3e85a6cfea2,
+        0x08a2d39045f82546, 0xc305f534f614e394, 0x479b7371a0dfac64, 0xf2073fc4629c5419,
+        0x8a0574193bb44f01, 0xbd64db499b136800, 0x003467f37d001520, 0xae840a2fa7935fae,
+    ],
+    [
+        0x2ecb3a5a4e76cd9d, 0x7b5253aa4e5d296e, 0xd9904d2d6d5d4357, 0xb7c84148102fc9a1,
+        0xa89d7544c75dd629, 0x13d0c8233d513e1c, 0x37faacb3482248e5, 0xccda3c18931e54cb,
+        0x9f1cbddcf5524b2f, 0xa818c4e3203b2c20, 0xf0b20bd7905d52c1, 0xcb5f2eb35fc48000,
+    ],
+    [
+        0x4b3e156b5cc2b9b5, 0xc514abe21838143c, 0x496c10024f7f89f7, 0x0e28687dfb263e48,
+        0xc69c1c8c68f3cab6, 0x6ca309ef3ee85638, 0x82f61a93d57a9534, 0x4f538d204147839c,
+        0xd520ff01048b2e24, 0x3955de4f89b618c4, 0xe8f1478786466178, 0x9b27d3246d3987b9,
+    ],
+    [
+        0x910bc2a89fd955b1, 0xa8525755c08ebda7, 0x938876200811379b, 0x5f8bcf49f0602799,
+        0xe3c8a72fa5132910, 0xbd43552e28503732, 0x238048495bd93cb6, 0x3c0fdb9eefab3cd4,
+        0x3ac9701d5b6038e0, 0x1ce14d168b57b6ef, 0x1c6a38085ce81245, 0x5edc8b104a9eb19a,
+    ],
+    [
+        0x4ec29ed04b4c4964, 0x70b304c1a0fc291f, 0x88c905f3ded7137f, 0x1b35910e8342a387,
+        0xd40a1da0ff916ef1, 0xf9ca73079f019da1, 0x01033e3e72e6ce39, 0x7b81d19ba52bcb25,
+        0xeba6ca0474260fca, 0x58fe79ae4c0f2cf5, 0xc125d8de133dd49f, 0x4c67085227851f30,
+    ],
+    [
+        0x8f32049c8a4e0020, 0xf05daf4764cf2933, 0xa029343fe68b9154, 0x64e63504883c12d4,
+        0x41fe4fe19aa4f6aa, 0x1e713f98a7184ddf, 0xa21e9b8b691b563f, 0x6f069368b627d139,
+        0x5da04e94bc4258ba, 0xa7decd2f51d2a109, 0xb74cbb64c0b7ce74, 0x4d004d3a724dfe54,
+    ],
+];
+
+/// The round constants, generated as in the reference implementation, by reading 9-byte little-endian
+/// integers modulo `p` from SHAKE256 of `Rescue-XLIX(p,12,4,128)`.
+#[rustfmt::skip]
+const RESCUE_PRIME_ROUND_CONSTANTS: [u64; 2 * RESCUE_PRIME_N_ROUNDS * SPONGE_WIDTH] = [
+    // Round 0, first half.
+    0xdf4a7c2aeaa76b43, 0x36f6146f159448a3, 0x20806950af2cb240, 0xe52bc17cde4a9396,
+    0x22955641abac882e, 0x1f24251cc7584861, 0x0ee166359dc2f227, 0x84e589d15fe9c8b3,
+    0xbfbeebeea04d9cfd, 0x0e12626bbef49c65, 0x59c73926c0c09258, 0x090a8b7ab5cba96b,
+    // Round 0, second half.
+    0x93edc3c90d41a7bc, 0x5c6891eddf5cfe94, 0xbaf99b281bb03ff9, 0x9c2eb2dd6b7eb3f9,
+    0x1e889fc821a4be09, 0x82002d129c81d374, 0x50297b2f9666b8d9, 0xdc622d7b18fca35e,
+    0xd110214cb87641e9, 0xee74064efeb7b334, 0x0311f5353a86a3f4, 0x975448f9f7d59930,
+    // Round 1, first half.
+    0x5df41d8874c695f8, 0x82da97ffe65920ac, 0x580e84993f50682a, 0x12b5ff159b281de6,
+    0x9c39cc1fbe3afa05, 0x8daf7368680a0f5c, 0xc1679bcd580dd7b0, 0x0674d434e3dff25d,
+    0xdfbfd639969c6454, 0xd1ebe222c05bf99b, 0xfc8444539e4fa4b2, 0x3e34f988211f5129,
+    // Round 1, second half.
+    0x820e016a12d1fa35, 0xd952ff35ebd208c5, 0x2f1f7275b141ae15, 0x09294e1238c74824,
+    0x466aea4707d2d1b9, 0xf2380216df52247a, 0x9bb9643d459c4b23, 0x5a25f0df37bdf030,
+    0xecc71239a7014b23, 0xaba57ca39ba8e2bb, 0x7ba0e06ee05cb674, 0x1cd6ef3e8e1a8e4d,
+    // Round 2, first half.
+    0x3cf604a202e65055, 0x0005eb1f7c758f3f, 0x3a8f84225d1a83ea, 0x11095ba466230bd3,
+    0x71ab78d709010bef, 0x72ef94c69b99e5b4, 0xdbc62d71ff4a119d, 0x4dd056313ea417a4,
+    0x79ec27cc236fc314, 0xd8e312ad83af2a7c, 0xd8fd14a237f8187b, 0x723a6b7de8e7fc85,
+    // Round 2, second half.
+    0xb6c00937ffa0ff87, 0xfd1ebf86249d4eef, 0x6a0af5be41ebe1fc, 0x6c88ada5a967a389,
+    0x0f6e094f796a154e, 0x01f0cbe704014831, 0x623364077f0ec4fc, 0x45776b9eb34215ec,
+    0x5a07ec086c93391e, 0x4f0b0e5dc84eab49, 0xfbe67d647097a609, 0xb17d4f1db757ef73,
+    // Round 3, first half.
+    0x2cff5dd2e15b6b09, 0x984cf4b5d2f28e9c, 0xfdadf07472065cb8, 0xc2eb929d0d9bd828,
+    0xadd3584e85d1e760, 0x1a70d2f530089515, 0x81b6095c2961ec14, 0x18145491fbc7c37c,
+    0x2e0a379d5a303b49, 0x36c3b409a559d993, 0x062cedee3b5f422d, 0x2b0efb333c1b4ec4,
+    // Round 3, second half.
+    0xee3d90f29221fb94, 0x512a4ad495a917b5, 0xc3e0ee4e5be42aa2, 0xd1c1f30697b41ce8,
+    0x4924c0bafe03eab3, 0xa853be4100776cf8, 0xfdb6327314910d0c, 0x084a66bdc4d45872,
+    0x53d9e5507b940647, 0x0190c823c7dfb248, 0x27fdf46b9d152106, 0x2fc9d067c4cc03a2,
+    // Round 4, first half.
+    0x9fee6eaaa885c8a0, 0xe6514d5e6bd053f4, 0xa72e17d101192d78, 0x8f6e371c66d76c94,
+    0x34cd7ba573a2c096, 0x439a7c0d8bf89cf7, 0x4c69c6cdcccb5022, 0x0fe3097b897256f0,
+    0xdadd08cdb07c6e20, 0x005120ea8ab7c721, 0xd8d56aed1d3b232c, 0x751bec1376b750d2,
+    // Round 4, second half.
+    0xb8887a180fedceca, 0x660bc126c2c2d6fb, 0xc8e1c3abb2cbd531, 0x1b9dc069a6dd8cb7,
+    0x264c77da403d20f4, 0x51b72162affc1a40, 0x2eb73a2c66e2a4f7, 0x96de27eedfd8809e,
+    0x673550c2931904ad, 0x8bef03b956084508, 0x17f9c4fbd53e721c, 0xdc54cadee6558c34,
+    // Round 5, first half.
+    0x1cd502044efb620a, 0x0067e87c53a94787, 0x6846ea55e04c937e, 0xc921fc38d2b5458f,
+    0xb6535259e247a66b, 0xec8ba314290144a0, 0x7400b44ed05f4b04, 0x5c075e01fb0be205,
+    0xe000a30c1c2de0a0, 0xa7e44cd6ee91c152, 0xe62208d413a283d8, 0x9b37682c988a7f6d,
+    // Round 5, second half.
+    0xc91b07ecd5e520e4, 0xe886e508cfbde663, 0x3a57d6241dfb2b7d, 0xf1235561577a94de,
+    0xe52b52113f35d62b, 0xafd91d2b649b561d, 0x66403afee8e8c4cb, 0x4303746fb5531e6b,
+    0x086626a246ee0da4, 0x959912ae6b28ee60, 0xd855ce73157a6a39, 0xe8085cc563759366,
+    // Round 6, first half.
+    0xa03af941f674d5db, 0x685ec828e76cec2b, 0x4b6776291ebd3931, 0xf418123ad4a424d6,
+    0x734ec470ef28edc6, 0xc264d009d8d2597c, 0xdca434bd40769c7c, 0x0b481cd44944a9c9,
+    0x5ea04d088a1d0701, 0xae57661b5af56e24, 0x34fba8c61f95b7fa, 0xeb497ca1f81cd385,
+    // Round 6, second half.
+    0x6eb26e6a31ad928e, 0x937327d499dfd51f, 0xa4939cbf0b385a8d, 0x608159f3a343a189,
+    0xd6f05f349cd243d5, 0x0b80feee3073e180, 0xdce9a9ef117a7daa, 0x8ce09765f42f07de,
+    0x9295e7ca46013110, 0xff52bc22c262edb7, 0x0c3137d856a41485, 0xe616a21fd06d027d,
+    // Round 7, first half.
+    0x8937d3fdca04dfc2, 0x16c986e2fb382eba, 0x01ee5045c70ef0c8, 0x08ebafa770a6c938,
+    0xbe3e5a7894d4da8a, 0x6b870bd34e65bb36, 0x841ab26977e20b53, 0xd7d76ff20450f97a,
+    0xd4ccad9ab88d9755, 0x3f65f37468333171, 0xbaeedd8884d239ea, 0x8dcb991b9ff8a30d,
+    // Round 7, second half.
+    0x50ebb1bd1f97059e, 0xd24e10305cc29cf2, 0xe55f63fae5f76e0d, 0xb3ce2b562db82712,
+    0xd4b3423421fed2a7, 0x6c7128887ac5fd6b, 0x3c825c9493166ff7, 0x716956326df30e1b,
+    0x58487434d42f9c1e, 0x3ac884bddcf5d47b, 0xdc6cac43bad89d05, 0x9b815d1f02e9496d,
+];
+
+/// Multiplies the state by the MDS matrix.
+pub(crate) fn rescue_prime_mds_layer<F: Field>(state: &[F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+    let mut result = [F::ZERO; SPONGE_WIDTH];
+    for (r, row) in result.iter_mut().zip(RESCUE_PRIME_MDS) {
+        for (&c, &s) in row.iter().zip(state) {
+            *r += F::from_canonical_u64(c) * s;
+        }
+    }
+    result
+}
+
+/// Adds the constants of the given half-round, where half-round `2r` follows the S-box of round `r`
+/// and half-round `2r + 1` follows its inverse S-box.
+pub(crate) fn rescue_prime_constant_layer<F: Field>(
+    state: &mut [F; SPONGE_WIDTH],
+    half_round: usize,
+) {
+    for (i, s) in state.iter_mut().enumerate() {
+        let c = RESCUE_PRIME_ROUND_CONSTANTS[half_round * SPONGE_WIDTH + i];
+        *s += F::from_canonical_u64(c);
+    }
+}
+
+/// Recursive version of `rescue_prime_mds_layer`.
+pub(crate) fn rescue_prime_mds_layer_recursive<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    state: &[ExtensionTarget<D>; SPONGE_WIDTH],
+) -> [ExtensionTarget<D>; SPONGE_WIDTH] {
+    let mut result = [builder.zero_extension(); SPONGE_WIDTH];
+    for (r, row) in result.iter_mut().zip(RESCUE_PRIME_MDS) {
+        for (&c, &s) in row.iter().zip(state) {
+            *r = builder.mul_const_add_extension(F::from_canonical_u64(c), s, *r);
+        }
+    }
+    result
+}
+
+/// Recursive version of `rescue_prime_constant_layer`.
+pub(crate) fn rescue_prime_constant_layer_recursive<
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
+    builder: &mut CircuitBuilder<F, D>,
+    state: &mut [ExtensionTarget<D>; SPONGE_WIDTH],
+    half_round: usize,
+) {
+    for (i, s) in state.iter_mut().enumerate() {
+        let c = RESCUE_PRIME_ROUND_CONSTANTS[half_round * SPONGE_WIDTH + i];
+        *s = builder.add_const_extension(*s, F::from_canonical_u64(c));
+    }
+}
+
+/// The inverse S-box `x^(1/alpha)`.
+pub(crate) fn rescue_prime_inverse_sbox<F: Field>(x: F) -> F {
+    x.exp_u64(RESCUE_PRIME_ALPHA_INV)
+}
+
+/// The Rescue-Prime permutation.
+pub fn rescue_prime_permutation<F: RichField>(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+    let mut state = input;
+    for round in 0..RESCUE_PRIME_N_ROUNDS {
+        for s in state.iter_mut() {
+            *s = s.exp_u64(RESCUE_PRIME_ALPHA);
+        }
+        state = rescue_prime_mds_layer(&state);
+        rescue_prime_constant_layer(&mut state, 2 * round);
+
+        for s in state.iter_mut() {
+            *s = rescue_prime_inverse_sbox(*s);
+        }
+        state = rescue_prime_mds_layer(&state);
+        rescue_prime_constant_layer(&mut state, 2 * round + 1);
+    }
+    state
+}
+
+pub struct RescuePrimePermutation;
+impl<F: RichField> PlonkyPermutation<F> for RescuePrimePermutation {
+    fn permute(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+        rescue_prime_permutation(input)
+    }
+}
+
+/// Rescue-Prime hash function.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RescuePrimeHash;
+impl<F: RichField> Hasher<F> for RescuePrimeHash {
+    const HASH_SIZE: usize = 4 * 8;
+    type Hash = HashOut<F>;
+    type Permutation = RescuePrimePermutation;
+
+    fn hash_no_pad(input: &[F]) -> Self::Hash {
+        hash_n_to_hash_no_pad::<F, Self::Permutation>(input)
+    }
+
+    fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        compress::<F, Self::Permutation>(left, right)
+    }
+
+    fn two_to_one_with_domain(
+        left: Self::Hash,
+        right: Self::Hash,
+        domain: CompressionDomain,
+    ) -> Self::Hash {
+        compress_with_domain::<F, Self::Permutation>(left, right, domain)
+    }
+}
+
+impl<F: RichField> AlgebraicHasher<F> for RescuePrimeHash {
+    fn permute_swapped<const D: usize>(
+        inputs: [Target; SPONGE_WIDTH],
+        swap: BoolTarget,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> [Target; SPONGE_WIDTH]
+    where
+        F: RichField + Extendable<D>,
+    {
+        let gate_type = RescuePrimeGate::<F, D>::new();
+        let gate = builder.add_gate(gate_type, vec![]);
+
+        let swap_wire = RescuePrimeGate::<F, D>::WIRE_SWAP;
+        let swap_wire = Target::wire(gate, swap_wire);
+        builder.connect(swap.target, swap_wire);
+
+        // Route input wires.
+        for i in 0..SPONGE_WIDTH {
+            let in_wire = RescuePrimeGate::<F, D>::wire_input(i);
+            let in_wire = Target::wire(gate, in_wire);
+            builder.connect(inputs[i], in_wire);
+        }
+
+        // Collect output wires.
+        (0..SPONGE_WIDTH)
+            .map(|i| Target::wire(gate, RescuePrimeGate::<F, D>::wire_output(i)))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::{Field, PrimeField64};
+
+    use crate::hash::hashing::hash_n_to_hash_no_pad;
+    use crate::hash::rescue_prime::{
+        rescue_prime_permutation, RescuePrimeHash, RescuePrimePermutation, RESCUE_PRIME_ALPHA,
+        RESCUE_PRIME_ALPHA_INV,
+    };
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, RescuePrimeGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = RescuePrimeGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn inverse_sbox() {
+        let x = F::rand();
+        assert_eq!(
+            x.exp_u64(RESCUE_PRIME_ALPHA_INV)
+                .exp_u64(RESCUE_PRIME_ALPHA),
+            x
+        );
+        assert_eq!(
+            x.exp_u64(RESCUE_PRIME_ALPHA)
+                .exp_u64(RESCUE_PRIME_ALPHA_INV),
+            x
+        );
+    }
+
+    // These vectors were computed with an independent implementation of the reference algorithms,
+    // which derive the MDS matrix, the round constants and the number of rounds.
+    #[test]
+    fn test_rescue_prime_vectors() {
+        let neg_one = F::NEG_ONE.to_canonical_u64();
+        #[rustfmt::skip]
+        let test_vectors: Vec<([u64; 12], [u64; 12])> = vec![
+            ([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, ],
+             [0x7c4ea1a395327454, 0x300e094edcccc84b, 0x7e770b8dd0d986ab, 0x9d952a65403cd583,
+              0xfee6165b6d8b06b6, 0x4bcfe4e505f69f0e, 0x6c8b2e1bd8b26684, 0x819af7d76427f00a,
+              0x42e2d4e9847668b8, 0x0eae7f3e436856b3, 0xa9bca2bafbceff92, 0x3eb742c18c88d3d2, ]),
+            ([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, ],
+             [0xccd94518a9af0782, 0xf7ae608ea3308620, 0xf56dd53fae1f5876, 0x11e7b12aedd8ca86,
+              0x869f9c3f93cd5630, 0x6ffe37312e58ac20, 0xac42b1f88aa27570, 0x312f6b96f7611c8a,
+              0xf8b19bd51a741b7e, 0x9d1c158cfa1b7a12, 0x62ae69ae877e1e51, 0xce62641553ffe1bc, ]),
+            ([neg_one; 12],
+             [0xa24a89012433b20a, 0xf524a99b206af48e, 0xc0c346134c67def4, 0x3fc6bff1a3c1f0d1,
+              0x65fb4b7b6acf48c3, 0x31b6b7d505482465, 0xf68117be8c929946, 0x8044f4fb0f1a35a2,
+              0xe7b39e1dcd1321e6, 0x51d1895d502290f6, 0x5a9ce8f392956843, 0x33c05795d03e7886, ]),
+        ];
+
+        for (input, expected) in test_vectors {
+            let output = rescue_prime_permutation(input.map(F::from_canonical_u64));
+            assert_eq!(output.map(|x| x.to_canonical_u64()), expected);
+        }
+    }
+
+    #[test]
+    fn test_rescue_prime_hash_circuit() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let inputs = F::rand_vec(10);
+        let input_targets = builder.add_virtual_targets(inputs.len());
+        for (&t, &x) in input_targets.iter().zip(&inputs) {
+            pw.set_target(t, x);
+        }
+        let hash = builder.hash_n_to_hash_no_pad::<RescuePrimeHash>(input_targets);
+        let expected =
+            builder.constant_hash(hash_n_to_hash_no_pad::<F, RescuePrimePermutation>(&inputs));
+        builder.connect_hashes(hash, expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -0,0 +1,400 @@
+//! Implementation of the Tip5 permutation, as described in https://eprint.iacr.org/2023/107.pdf
+//!
+//! Tip5 is defined over the Goldilocks field. Its S-box layer applies a byte-wise lookup table to
+//! the first few state elements and the power map `x^7` to the others, which gives a better
+//! security margin per round than a purely algebraic permutation, at the cost of range checks when
+//! the lookups are arithmetized.
+//!
+//! `tip5_hash_10` and `tip5_hash_varlen` are the sponge of the specification, with a rate of 10
+//! elements and digests of 5 elements, for interoperating with other Tip5 implementations.
+//! `Tip5Hash` instead adapts the permutation to the sponge used throughout the library.
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+use plonky2_util::ceil_div_usize;
+
+use crate::gates::tip5::Tip5Gate;
+use crate::hash::hash_types::{HashOut, RichField};
+use crate::hash::hashing::{
+    compress, compress_with_domain, hash_n_to_hash_no_pad, PlonkyPermutation, SPONGE_WIDTH,
+};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, CompressionDomain, Hasher};
+
+/// The width of the Tip5 state.
+pub const TIP5_WIDTH: usize = 16;
+pub const TIP5_N_ROUNDS: usize = 5;
+/// The number of state elements which go through the split-and-lookup S-box. The remaining ones go
+/// through the power map.
+pub const TIP5_N_SPLIT_AND_LOOKUP: usize = 4;
+/// The rate of the sponge of the specification.
+pub const TIP5_RATE: usize = 10;
+/// The number of elements of the digests of the sponge of the specification.
+pub const TIP5_DIGEST_LEN: usize = 5;
+
+/// `2^64 mod p`, the Montgomery factor of the Goldilocks field. The lookup table is applied to the
+/// bytes of the Montgomery representation `x * 2^64` of an element, for compatibility with
+/// implementations which store elements in that form.
+pub(crate) const TIP5_MONTGOMERY_R: u64 = 0xffffffff;
+
+/// The byte-wise lookup table, `T(x) = (x + 1)^3 mod 257 - 1`. It maps `0` to `0` and `255` to
+/// `255`, so canonical Montgomery representations are mapped to canonical ones.
+pub(crate) const TIP5_LOOKUP_TABLE: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let x = i as u32 + 1;
+        table[i] = ((x * x * x) % 257 - 1) as u8;
+        i += 1;
+    }
+    table
+};
+
+/// The first column of the circulant MDS matrix.
+const TIP5_MDS_FIRST_COLUMN: [u64; TIP5_WIDTH] = [
+    61402, 1108, 28750, 33823, 7454, 43244, 53865, 12034, 56951, 27521, 41351, 40901, 12021, 59689,
+    26798, 17845,
+];
+
+/// The round constants, where constant `i` is the BLAKE3 hash of the ASCII string `Tip5-{i}`,
+/// truncated to 16 bytes, read as a little-endian integer and reduced modulo `p`.
+#[rustfmt::skip]
+const TIP5_ROUND_CONSTANTS: [u64; TIP5_N_ROUNDS * TIP5_WIDTH] = [
+    // Round 0.
+    0xb7f93ed9de92c855, 0x61180c08e5f9f052, 0x7de47f5269ecb050, 0x6023165c4c7209f6,
+    0x5f1d4b3eb504c2ce, 0xcb902084430360e8, 0x71b3a238dd159f4e, 0x23dd43f6a145ad1d,
+    0x1155d799d91365c1, 0x207bc3984c0f8493, 0x48dc326b908115cf, 0x48e968de42991685,
+    0x6d1810223f6ee3fc, 0x98de82a14f22b720, 0x379b2461e904c705, 0xc141aa0879b297e2,
+    // Round 1.
+    0xd479b28899168b2f, 0x219a5bd2b13c5810, 0x48f7f178ea1094bd, 0x32d93014aee9432d,
+    0xef7ae3d4a1475436, 0x723d65204baae78c, 0xc1b0bfafdee7bc38, 0x0795446ba69180b2,
+    0x8e448e9b2d9711b7, 0xd8d61963c90bfc9e, 0x99764427d391e7f3, 0xda9d09162586f52f,
+    0xf18edaa08b36bf8d, 0xbd2fe87ca544b048, 0xde46cb5f7148a1f0, 0xfae286fe44bb4e1f,
+    // Round 2.
+    0xc8cea8156fbdddb6, 0x36deee74b882128b, 0xe8d481a4fb0131eb, 0xa5d8bd6b705eabbe,
+    0x2257b67cbf280f20, 0x541a82ff750b4cc7, 0xdabcd3ebb56bee15, 0x2bef6372b261883b,
+    0x2334807ed948dfe1, 0x11f00082ea453cc1, 0x29e4f2163127d52b, 0x532687b7b38a01e7,
+    0xae704e1e028c0a42, 0xfc3b6076e7619602, 0x4a16e639ea4a45f8, 0xe8df8593fbfd6664,
+    // Round 3.
+    0x6051790fbf7734b3, 0x9ae8e0bfba246e73, 0xcd8e924d185bc73a, 0x8db1dd936e5d2e29,
+    0x1e4b9e5daf80b092, 0xede055721cff8712, 0xb32a45d962127aa2, 0xb8c6c188d9e21c18,
+    0x83bf41742fa3ed7b, 0xc20345895c6b19b3, 0x8e614a82a4a8abc4, 0xacf2298c9f8b2c78,
+    0x06e68a662960ca5e, 0xc248f0aa4133b86c, 0xab7736b99d476334, 0xda3d560a8120d5b0,
+    // Round 4.
+    0x0c74add19a5ca361, 0x2b6611e3a3b7f6db, 0x281542b98eb9c26d, 0x3f21f740197d5c9a,
+    0xd5a4e8844b670d96, 0x0d3cac2ff9f2b338, 0x12ecd77a1979be38, 0x896c1112b6cdbd57,
+    0x9d2258b0b94e8234, 0x8d335341c592e402, 0x8a95007530ba4f45, 0xf92387445e92b73e,
+    0x6ae72b5e7fbae4db, 0xd032648bbf67b657, 0x75fd3ae6c5ce99b5, 0x8e85809ac6fba687,
+];
+
+/// Applies the Tip5 lookup table to the bytes of the Montgomery representation of `x`.
+pub fn tip5_split_and_lookup<F: RichField>(x: F) -> F {
+    let r = F::from_canonical_u64(TIP5_MONTGOMERY_R);
+    let bytes = (x * r)
+        .to_canonical_u64()
+        .to_le_bytes()
+        .map(|b| TIP5_LOOKUP_TABLE[b as usize]);
+    F::from_canonical_u64(u64::from_le_bytes(bytes)) * r.inverse()
+}
+
+/// The power map applied to the state elements which don't go through the lookup table.
+pub(crate) fn tip5_power_map<F: Field>(x: F) -> F {
+    let x3 = x.cube();
+    x3 * x3 * x
+}
+
+/// Multiplies the state by the circulant MDS matrix.
+pub(crate) fn tip5_mds_layer<F: Field>(state: &[F; TIP5_WIDTH]) -> [F; TIP5_WIDTH] {
+    let mut result = [F::ZERO; TIP5_WIDTH];
+    for (i, r) in result.iter_mut().enumerate() {
+        for (j, &s) in state.iter().enumerate() {
+            let c = TIP5_MDS_FIRST_COLUMN[(TIP5_WIDTH + i - j) % TIP5_WIDTH];
+            *r += F::from_canonical_u64(c) * s;
+        }
+    }
+    result
+}
+
+pub(crate) fn tip5_constant_layer<F: Field>(state: &mut [F; TIP5_WIDTH], round: usize) {
+    for (i, s) in state.iter_mut().enumerate() {
+        *s += F::from_canonical_u64(TIP5_ROUND_CONSTANTS[round * TIP5_WIDTH + i]);
+    }
+}
+
+/// Recursive version of `tip5_mds_layer`.
+pub(crate) fn tip5_mds_layer_recursive<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    state: &[ExtensionTarget<D>; TIP5_WIDTH],
+) -> [ExtensionTarget<D>; TIP5_WIDTH] {
+    let mut result = [builder.zero_extension(); TIP5_WIDTH];
+    for (i, r) in result.iter_mut().enumerate() {
+        for (j, &s) in state.iter().enumerate() {
+            let c = TIP5_MDS_FIRST_COLUMN[(TIP5_WIDTH + i - j) % TIP5_WIDTH];
+            *r = builder.mul_const_add_extension(F::from_canonical_u64(c), s, *r);
+        }
+    }
+    result
+}
+
+/// Recursive version of `tip5_constant_layer`.
+pub(crate) fn tip5_constant_layer_recursive<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    state: &mut [ExtensionTarget<D>; TIP5_WIDTH],
+    round: usize,
+) {
+    for (i, s) in state.iter_mut().enumerate() {
+        let c = F::from_canonical_u64(TIP5_ROUND_CONSTANTS[round * TIP5_WIDTH + i]);
+        *s = builder.add_const_extension(*s, c);
+    }
+}
+
+/// The Tip5 permutation of a 16-element state.
+pub fn tip5_permutation<F: RichField>(input: [F; TIP5_WIDTH]) -> [F; TIP5_WIDTH] {
+    let mut state = input;
+    for round in 0..TIP5_N_ROUNDS {
+        for (i, s) in state.iter_mut().enumerate() {
+            *s = if i < TIP5_N_SPLIT_AND_LOOKUP {
+                tip5_split_and_lookup(*s)
+            } else {
+                tip5_power_map(*s)
+            };
+        }
+        state = tip5_mds_layer(&state);
+        tip5_constant_layer(&mut state, round);
+    }
+    state
+}
+
+/// Hashes exactly `TIP5_RATE` elements with the sponge of the specification, whose capacity is then
+/// set to ones to separate this domain from that of `tip5_hash_varlen`.
+pub fn tip5_hash_10<F: RichField>(input: &[F; TIP5_RATE]) -> [F; TIP5_DIGEST_LEN] {
+    let mut state = [F::ONE; TIP5_WIDTH];
+    state[..TIP5_RATE].copy_from_slice(input);
+    tip5_permutation(state)[..TIP5_DIGEST_LEN]
+        .try_into()
+        .unwrap()
+}
+
+/// Hashes any number of elements with the sponge of the specification. The input is padded with a
+/// one and then zeros to a multiple of `TIP5_RATE`, and each chunk overwrites the rate.
+pub fn tip5_hash_varlen<F: RichField>(input: &[F]) -> [F; TIP5_DIGEST_LEN] {
+    let mut padded = input.to_vec();
+    padded.push(F::ONE);
+    padded.resize(ceil_div_usize(padded.len(), TIP5_RATE) * TIP5_RATE, F::ZERO);
+
+    let mut state = [F::ZERO; TIP5_WIDTH];
+    for chunk in padded.chunks(TIP5_RATE) {
+        state[..TIP5_RATE].copy_from_slice(chunk);
+        state = tip5_permutation(state);
+    }
+    state[..TIP5_DIGEST_LEN].try_into().unwrap()
+}
+
+/// Tip5 adapted to the 12-element sponge used throughout the library: the state is padded with
+/// zeros to 16 elements before the permutation, and truncated to 12 elements after it. As with
+/// `KeccakPermutation`, the result is a pseudo-permutation, which behaves like a sponge with a
+/// larger capacity whose extra capacity elements are reset after each call.
+pub struct Tip5Permutation;
+impl<F: RichField> PlonkyPermutation<F> for Tip5Permutation {
+    fn permute(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+        let mut state = [F::ZERO; TIP5_WIDTH];
+        state[..SPONGE_WIDTH].copy_from_slice(&input);
+        tip5_permutation(state)[..SPONGE_WIDTH].try_into().unwrap()
+    }
+}
+
+/// Tip5 hash function.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Tip5Hash;
+impl<F: RichField> Hasher<F> for Tip5Hash {
+    const HASH_SIZE: usize = 4 * 8;
+    type Hash = HashOut<F>;
+    type Permutation = Tip5Permutation;
+
+    fn hash_no_pad(input: &[F]) -> Self::Hash {
+        hash_n_to_hash_no_pad::<F, Self::Permutation>(input)
+    }
+
+    fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        compress::<F, Self::Permutation>(left, right)
+    }
+
+    fn two_to_one_with_domain(
+        left: Self::Hash,
+        right: Self::Hash,
+        domain: CompressionDomain,
+    ) -> Self::Hash {
+        compress_with_domain::<F, Self::Permutation>(left, right, domain)
+    }
+}
+
+impl<F: RichField> AlgebraicHasher<F> for Tip5Hash {
+    fn permute_swapped<const D: usize>(
+        inputs: [Target; SPONGE_WIDTH],
+        swap: BoolTarget,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> [Target; SPONGE_WIDTH]
+    where
+        F: RichField + Extendable<D>,
+    {
+        let gate_type = Tip5Gate::<F, D>::new();
+        let gate = builder.add_gate(gate_type, vec![]);
+
+        let swap_wire = Tip5Gate::<F, D>::WIRE_SWAP;
+        let swap_wire = Target::wire(gate, swap_wire);
+        builder.connect(swap.target, swap_wire);
+
+        // Route input wires.
+        for i in 0..SPONGE_WIDTH {
+            let in_wire = Tip5Gate::<F, D>::wire_input(i);
+            let in_wire = Target::wire(gate, in_wire);
+            builder.connect(inputs[i], in_wire);
+        }
+
+        // The gate only checks the algebraic part of the permutation, so the lookups are checked
+        // separately.
+        for round in 0..TIP5_N_ROUNDS {
+            for i in 0..TIP5_N_SPLIT_AND_LOOKUP {
+                let lookup_in = Tip5Gate::<F, D>::wire_lookup_input(round, i);
+                let lookup_out = Tip5Gate::<F, D>::wire_lookup_output(round, i);
+                let output = builder.tip5_split_and_lookup(Target::wire(gate, lookup_in));
+                builder.connect(output, Target::wire(gate, lookup_out));
+            }
+        }
+
+        // Collect output wires.
+        (0..SPONGE_WIDTH)
+            .map(|i| Target::wire(gate, Tip5Gate::<F, D>::wire_output(i)))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::{Field, PrimeField64};
+
+    use crate::hash::hashing::hash_n_to_hash_no_pad;
+    use crate::hash::tip5::{
+        tip5_hash_10, tip5_hash_varlen, tip5_permutation, tip5_split_and_lookup, Tip5Hash,
+        Tip5Permutation, TIP5_LOOKUP_TABLE, TIP5_MONTGOMERY_R,
+    };
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Tip5GoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = Tip5GoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn lookup_table_is_permutation() {
+        let mut seen = [false; 256];
+        for b in TIP5_LOOKUP_TABLE {
+            assert!(!seen[b as usize]);
+            seen[b as usize] = true;
+        }
+        assert_eq!(TIP5_LOOKUP_TABLE[..8], [0, 7, 26, 63, 124, 215, 85, 254]);
+        assert_eq!(TIP5_LOOKUP_TABLE[255], 255);
+    }
+
+    #[test]
+    fn split_and_lookup() {
+        assert_eq!(tip5_split_and_lookup(F::ZERO), F::ZERO);
+        // `p - 1` has the Montgomery representation `p - 2^32 + 1`, which is `0xfffffffe00000002`.
+        let r_inv = F::from_canonical_u64(TIP5_MONTGOMERY_R).inverse();
+        let montgomery_bytes = [2u8, 0, 0, 0, 0xfe, 0xff, 0xff, 0xff];
+        let expected = montgomery_bytes.map(|b| TIP5_LOOKUP_TABLE[b as usize]);
+        assert_eq!(
+            tip5_split_and_lookup(F::NEG_ONE),
+            F::from_canonical_u64(u64::from_le_bytes(expected)) * r_inv
+        );
+    }
+
+    // These vectors were computed with an independent implementation of the specification, whose
+    // round constants were checked against their BLAKE3 derivation.
+    #[test]
+    fn test_tip5_permutation_vectors() {
+        let neg_one = F::NEG_ONE.to_canonical_u64();
+        #[rustfmt::skip]
+        let test_vectors: Vec<([u64; 16], [u64; 16])> = vec![
+            ([0, 0, 0, 0, 0, 0, 0, 0,
+              0, 0, 0, 0, 0, 0, 0, 0, ],
+             [0x904e118b98c5091a, 0xb66473ab4d5e7146, 0x867effe4342eb413, 0x915c7258076e23b2,
+              0x476b911d5b242b40, 0xf3592451d4661816, 0x3089bcf3c55196e4, 0x20353d09bd95ddf4,
+              0xef3611f928083490, 0x8271f91e393bf946, 0x3228b0d64402dc2b, 0x287770d702e0c228,
+              0x38c860aaf760fa27, 0xffc7fd37089507a6, 0x83ad1cdf2cd52336, 0x4299ff12b25b3699, ]),
+            ([0, 1, 2, 3, 4, 5, 6, 7,
+              8, 9, 10, 11, 12, 13, 14, 15, ],
+             [0x924ba0c0cf3e769a, 0xafc870f40ed2f17e, 0xe2545e342ded3dde, 0x3c7639cdb78b89bd,
+              0xf6728bad3537d97b, 0x0a56b826deaf632e, 0x9648fedb03a4b5f7, 0x2c0acf54f7ce9f47,
+              0x583e321ccbf36e0b, 0x1c0479836aecdf11, 0x3e0b992f4ffdae9f, 0x81129a14f5f8c912,
+              0xced933fd32e9ae95, 0x9fda8af76fa2a618, 0x35f2a80143565725, 0x4386a790c7e439b8, ]),
+            ([neg_one; 16],
+             [0x78a2de46fa535979, 0x40f20f3031ab6ff7, 0x97e82aeb50da31d6, 0x6e599cabd6499269,
+              0xf4bc7bd30f213239, 0x06f7ee6cae1799dc, 0xfaf0ac948fe11ed5, 0x6751a6fc7400d5aa,
+              0x79b23f56808ae465, 0xdc67274beb810712, 0x12fc32f123cd787a, 0x46c05b1480f6d033,
+              0x21e8859e12527738, 0x821a597a02a58da7, 0x303613eb274acd57, 0x4bbe095c0c10766f, ]),
+        ];
+
+        for (input, expected) in test_vectors {
+            let output = tip5_permutation(input.map(F::from_canonical_u64));
+            assert_eq!(output.map(|x| x.to_canonical_u64()), expected);
+        }
+    }
+
+    #[test]
+    fn test_tip5_sponge_vectors() {
+        let inputs = (0..15).map(F::from_canonical_u64).collect::<Vec<_>>();
+
+        #[rustfmt::skip]
+        let expected = [
+            0x3cd0661645ab639e, 0xb52852f769f5557b, 0x235ba82502fa2b9e, 0x10e06165f66e3a2f,
+            0xfc5c43ae26e33986,
+        ];
+        let output = tip5_hash_10(&inputs[..10].try_into().unwrap());
+        assert_eq!(output.map(|x| x.to_canonical_u64()), expected);
+
+        #[rustfmt::skip]
+        let expected = [
+            0xafcd5c07267b932b, 0x29cc9265121f5276, 0x5d343bb95f06e590, 0x43f9b10c88a31399,
+            0x332c1c51e38c7d0a,
+        ];
+        let output = tip5_hash_varlen::<F>(&[]);
+        assert_eq!(output.map(|x| x.to_canonical_u64()), expected);
+
+        #[rustfmt::skip]
+        let expected = [
+            0xd4c3aa55ae4af374, 0xebe7fc5a60d6ca85, 0x0a99529b2dd4a730, 0x3e8289d5304cd657,
+            0x6c9b2af534795251,
+        ];
+        let output = tip5_hash_varlen(&inputs);
+        assert_eq!(output.map(|x| x.to_canonical_u64()), expected);
+    }
+
+    #[test]
+    fn test_tip5_hash_circuit() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let inputs = F::rand_vec(10);
+        let input_targets = builder.add_virtual_targets(inputs.len());
+        for (&t, &x) in input_targets.iter().zip(&inputs) {
+            pw.set_target(t, x);
+        }
+        let hash = builder.hash_n_to_hash_no_pad::<Tip5Hash>(input_targets);
+        let expected = builder.constant_hash(hash_n_to_hash_no_pad::<F, Tip5Permutation>(&inputs));
+        builder.connect_hashes(hash, expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
@@ -9,6 +9,7 @@ use crate::hash::hashing::{PlonkyPermutation, SPONGE_RATE, SPONGE_WIDTH};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
+use crate::iop::transcript::{SharedTranscriptObserver, TranscriptEntry};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, GenericHashOut, Hasher};
 
@@ -18,6 +19,9 @@ pub struct Challenger<F: RichField, H: Hasher<F>> {
     sponge_state: [F; SPONGE_WIDTH],
     input_buffer: Vec<F>,
     output_buffer: Vec<F>,
+    /// The label of the current phase of the protocol, reported to `observer`.
+    label: &'static str,
+    observer: Option<SharedTranscriptObserver<F>>,
     _phantom: PhantomData<H>,
 }
 
@@ -35,15 +39,34 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             sponge_state: [F::ZERO; SPONGE_WIDTH],
             input_buffer: Vec::new(),
             output_buffer: Vec::new(),
+            label: "",
+            observer: None,
             _phantom: Default::default(),
         }
     }
 
+    /// Reports every element this challenger absorbs or squeezes to `observer`.
+    pub fn set_observer(&mut self, observer: SharedTranscriptObserver<F>) {
+        self.observer = Some(observer);
+    }
+
+    /// Labels subsequent transcript entries with `label`, e.g. the name of a protocol phase.
+    pub fn set_label(&mut self, label: &'static str) {
+        self.label = label;
+    }
+
+    fn record(&self, entry: TranscriptEntry<F>) {
+        if let Some(observer) = &self.observer {
+            observer.lock().unwrap().record(self.label, entry);
+        }
+    }
+
     pub fn observe_element(&mut self, element: F) {
         // Any buffered outputs are now invalid, since they wouldn't reflect this input.
         self.output_buffer.clear();
 
         self.input_buffer.push(element);
+        self.record(TranscriptEntry::Observed(element));
     }
 
     pub fn observe_extension_element<const D: usize>(&mut self, element: &F::Extension)
@@ -87,9 +110,12 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             self.output_buffer = self.sponge_state[0..SPONGE_RATE].to_vec();
         }
 
-        self.output_buffer
+        let challenge = self
+            .output_buffer
             .pop()
-            .expect("Output buffer should be non-empty")
+            .expect("Output buffer should be non-empty");
+        self.record(TranscriptEntry::Challenge(challenge));
+        challenge
     }
 
     pub fn get_n_challenges(&mut self, n: usize) -> Vec<F> {
@@ -161,6 +187,9 @@ pub struct RecursiveChallenger<F: RichField + Extendable<D>, H: AlgebraicHasher<
     sponge_state: [Target; SPONGE_WIDTH],
     input_buffer: Vec<Target>,
     output_buffer: Vec<Target>,
+    label: &'static str,
+    /// Transcript entries not yet passed to the builder, if it has a transcript observer.
+    transcript: Option<Vec<(&'static str, TranscriptEntry<Target>)>>,
 }
 
 impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
@@ -172,17 +201,29 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
             sponge_state: [zero; SPONGE_WIDTH],
             input_buffer: Vec::new(),
             output_buffer: Vec::new(),
+            label: "",
+            transcript: builder.transcript_observer.as_ref().map(|_| Vec::new()),
         }
     }
 
-    pub(crate) fn observe_element(&mut self, target: Target) {
+    /// Labels subsequent transcript entries with `label`, as in `Challenger::set_label`. The
+    /// entries are reported to the builder's transcript observer, if any, once their values are
+    /// known. Elements observed after the last challenge is drawn aren't reported.
+    pub fn set_label(&mut self, label: &'static str) {
+        self.label = label;
+    }
+
+    pub fn observe_element(&mut self, target: Target) {
         // Any buffered outputs are now invalid, since they wouldn't reflect this input.
         self.output_buffer.clear();
 
         self.input_buffer.push(target);
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push((self.label, TranscriptEntry::Observed(target)));
+        }
     }
 
-    pub(crate) fn observe_elements(&mut self, targets: &[Target]) {
+    pub fn observe_elements(&mut self, targets: &[Target]) {
         for &target in targets {
             self.observe_element(target);
         }
@@ -217,9 +258,15 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
             self.output_buffer = self.sponge_state[0..SPONGE_RATE].to_vec();
         }
 
-        self.output_buffer
+        let challenge = self
+            .output_buffer
             .pop()
-            .expect("Output buffer should be non-empty")
+            .expect("Output buffer should be non-empty");
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push((self.label, TranscriptEntry::Challenge(challenge)));
+            builder.record_transcript(transcript.drain(..));
+        }
+        challenge
     }
 
     pub fn get_n_challenges(
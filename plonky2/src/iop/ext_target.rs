@@ -1,7 +1,7 @@
 use std::ops::Range;
 
 use plonky2_field::extension_field::algebra::ExtensionAlgebra;
-use plonky2_field::extension_field::{Extendable, FieldExtension, OEF};
+use plonky2_field::extension_field::{Extendable, FieldExtension};
 use plonky2_field::field_types::Field;
 
 use crate::hash::hash_types::RichField;
@@ -35,17 +35,9 @@ impl<const D: usize> ExtensionTarget<D> {
             return self.repeated_frobenius(count % D, builder);
         }
         let arr = self.to_target_array();
-        let k = (F::order() - 1u32) / (D as u64);
-        let z0 = F::Extension::W.exp_biguint(&(k * count as u64));
-        #[allow(clippy::needless_collect)]
-        let zs = z0
-            .powers()
-            .take(D)
-            .map(|z| builder.constant(z))
-            .collect::<Vec<_>>();
-
         let mut res = Vec::with_capacity(D);
-        for (z, a) in zs.into_iter().zip(arr) {
+        for (i, a) in arr.into_iter().enumerate() {
+            let z = builder.constant(F::DTH_ROOT_POWERS[count * i % D]);
             res.push(builder.mul(z, a));
         }
 
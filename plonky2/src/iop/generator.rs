@@ -1,11 +1,13 @@
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
+use itertools::Itertools;
 use num::BigUint;
 use plonky2_field::extension_field::{Extendable, FieldExtension};
 use plonky2_field::field_types::{Field, PrimeField};
 
 use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::arithmetic_u64::U64Target;
 use crate::gadgets::biguint::BigUintTarget;
 use crate::gadgets::nonnative::NonNativeTarget;
 use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
@@ -15,6 +17,10 @@ use crate::iop::wire::Wire;
 use crate::iop::witness::{PartialWitness, PartitionWitness, Witness};
 use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::GenericConfig;
+use crate::util::zeroize::ZeroizeOnDrop;
+
+/// The number of unpopulated targets listed when witness generation stalls.
+const MAX_REPORTED_TARGETS: usize = 10;
 
 /// Given a `PartitionWitness` that has only inputs set, populates the rest of the witness using the
 /// given set of generators.
@@ -28,35 +34,41 @@ pub(crate) fn generate_partial_witness<
     prover_data: &'a ProverOnlyCircuitData<F, C, D>,
     common_data: &'a CommonCircuitData<F, C, D>,
 ) -> PartitionWitness<'a, F> {
+    let inputs = ZeroizeOnDrop::new(inputs);
     let config = &common_data.config;
     let generators = &prover_data.generators;
     let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
 
-    let mut witness = PartitionWitness::new(
+    let mut witness = ZeroizeOnDrop::new(PartitionWitness::new(
         config.num_wires,
         common_data.degree(),
         common_data.num_virtual_targets,
         &prover_data.representative_map,
-    );
+    ));
+    witness.source_locations = Some(&prover_data.source_locations);
 
-    for (t, v) in inputs.target_values.into_iter() {
+    for (&t, &v) in &inputs.target_values {
         witness.set_target(t, v);
     }
+    drop(inputs);
 
     // Build a list of "pending" generators which are queued to be run. Initially, all generators
     // are queued.
     let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
+    // The queue for the next pass. It's swapped with the current one after each pass, so both are
+    // allocated once, with room for every generator.
+    let mut next_pending_generator_indices = Vec::with_capacity(generators.len());
 
     // We also track a list of "expired" generators which have already returned false.
     let mut generator_is_expired = vec![false; generators.len()];
     let mut remaining_generators = generators.len();
 
-    let mut buffer = GeneratedValues::empty();
+    // Its allocation still holds the values drained from it, so it's zeroized too. Most generators
+    // fill in at most a row of wires, so it's sized for one.
+    let mut buffer = ZeroizeOnDrop::new(GeneratedValues::with_capacity(config.num_wires));
 
     // Keep running generators until we fail to make progress.
     while !pending_generator_indices.is_empty() {
-        let mut next_pending_generator_indices = Vec::new();
-
         for &generator_idx in &pending_generator_indices {
             if generator_is_expired[generator_idx] {
                 continue;
@@ -88,16 +100,34 @@ pub(crate) fn generate_partial_witness<
             }
         }
 
-        pending_generator_indices = next_pending_generator_indices;
+        std::mem::swap(
+            &mut pending_generator_indices,
+            &mut next_pending_generator_indices,
+        );
+        next_pending_generator_indices.clear();
     }
 
-    assert_eq!(
-        remaining_generators, 0,
-        "{} generators weren't run",
-        remaining_generators,
-    );
+    if remaining_generators > 0 {
+        // Point at some of the targets which were never populated, since those are what stalled
+        // the remaining generators.
+        let unpopulated = generators
+            .iter()
+            .zip(&generator_is_expired)
+            .filter(|(_, expired)| !**expired)
+            .flat_map(|(generator, _)| generator.watch_list())
+            .filter(|&t| witness.try_get_target(t).is_none())
+            .unique()
+            .take(MAX_REPORTED_TARGETS)
+            .map(|t| witness.describe_target(t))
+            .collect::<Vec<_>>();
+        panic!(
+            "{} generators weren't run, waiting on targets including:\n  {}",
+            remaining_generators,
+            unpopulated.join("\n  ")
+        );
+    }
 
-    witness
+    witness.into_inner()
 }
 
 /// A generator participates in the generation of the witness.
@@ -169,6 +199,11 @@ impl<F: Field> GeneratedValues<F> {
         self.set_target(target.0, F::from_canonical_u32(value))
     }
 
+    pub fn set_u64_target(&mut self, target: U64Target, value: u64) {
+        self.set_u32_target(target.lo(), value as u32);
+        self.set_u32_target(target.hi(), (value >> 32) as u32);
+    }
+
     pub fn set_biguint_target(&mut self, target: BigUintTarget, value: BigUint) {
         let mut limbs = value.to_u32_digits();
 
@@ -283,24 +318,6 @@ impl<F: Field> SimpleGenerator<F> for CopyGenerator {
     }
 }
 
-/// A generator for including a random value
-#[derive(Debug)]
-pub(crate) struct RandomValueGenerator {
-    pub(crate) target: Target,
-}
-
-impl<F: Field> SimpleGenerator<F> for RandomValueGenerator {
-    fn dependencies(&self) -> Vec<Target> {
-        Vec::new()
-    }
-
-    fn run_once(&self, _witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
-        let random_value = F::rand();
-
-        out_buffer.set_target(self.target, random_value);
-    }
-}
-
 /// A generator for testing if a value equals zero
 #[derive(Debug)]
 pub(crate) struct NonzeroTestGenerator {
@@ -308,7 +325,7 @@ pub(crate) struct NonzeroTestGenerator {
     pub(crate) dummy: Target,
 }
 
-impl<F: Field> SimpleGenerator<F> for NonzeroTestGenerator {
+impl<F: RichField> SimpleGenerator<F> for NonzeroTestGenerator {
     fn dependencies(&self) -> Vec<Target> {
         vec![self.to_test]
     }
@@ -316,7 +333,13 @@ impl<F: Field> SimpleGenerator<F> for NonzeroTestGenerator {
     fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
         let to_test_value = witness.get_target(self.to_test);
 
-        let dummy_value = if to_test_value == F::ZERO {
+        let dummy_value = if cfg!(feature = "constant-time") {
+            F::ct_select(
+                to_test_value.ct_is_zero(),
+                F::ONE,
+                to_test_value.ct_inverse_or_zero(),
+            )
+        } else if to_test_value == F::ZERO {
             F::ONE
         } else {
             to_test_value.inverse()
@@ -2,6 +2,8 @@
 pub mod challenger;
 pub mod ext_target;
 pub mod generator;
+pub(crate) mod source_locations;
 pub mod target;
+pub mod transcript;
 pub mod wire;
 pub mod witness;
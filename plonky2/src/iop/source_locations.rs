@@ -0,0 +1,85 @@
+use std::panic::Location;
+
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::plonk::region::{innermost_region, region_path, CircuitRegion};
+
+/// The source locations at which each virtual target, gate and extra opening of a circuit was
+/// created, and the regions they belong to, used to point witness generation, copy constraint and
+/// parameter failures back at the code responsible. Locations are only recorded in debug builds.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SourceLocations {
+    virtual_targets: Vec<&'static Location<'static>>,
+    gates: Vec<&'static Location<'static>>,
+    extra_openings: Vec<&'static Location<'static>>,
+    regions: Vec<CircuitRegion>,
+}
+
+impl SourceLocations {
+    pub fn add_virtual_target(&mut self, index: usize, location: &'static Location<'static>) {
+        debug_assert_eq!(index, self.virtual_targets.len());
+        self.virtual_targets.push(location);
+    }
+
+    pub fn add_gate(&mut self, index: usize, location: &'static Location<'static>) {
+        debug_assert_eq!(index, self.gates.len());
+        self.gates.push(location);
+    }
+
+    pub fn add_extra_opening(&mut self, index: usize, location: &'static Location<'static>) {
+        debug_assert_eq!(index, self.extra_openings.len());
+        self.extra_openings.push(location);
+    }
+
+    pub fn set_regions(&mut self, regions: Vec<CircuitRegion>) {
+        self.regions = regions;
+    }
+
+    /// The location at which `target`, or the gate it belongs to, was created, if it was recorded.
+    pub fn get(&self, target: Target) -> Option<&'static Location<'static>> {
+        match target {
+            Target::Wire(Wire { gate, .. }) => self.gates.get(gate).copied(),
+            Target::VirtualTarget { index } => self.virtual_targets.get(index).copied(),
+        }
+    }
+
+    /// The location at which the gate with the given index was added, if it was recorded.
+    pub fn gate(&self, index: usize) -> Option<&'static Location<'static>> {
+        self.gates.get(index).copied()
+    }
+
+    /// The location at which the extra opening with the given index was requested, if it was
+    /// recorded.
+    pub fn extra_opening(&self, index: usize) -> Option<&'static Location<'static>> {
+        self.extra_openings.get(index).copied()
+    }
+
+    /// Describes `target` for an error message, including its location if it was recorded and the
+    /// region it belongs to.
+    pub fn describe(&self, target: Target) -> String {
+        let mut details = Vec::new();
+        if let Some(location) = self.get(target) {
+            details.push(format!("created at {}", location));
+        }
+        if let Some(region) = innermost_region(&self.regions, target) {
+            details.push(format!("in region {}", region_path(&self.regions, region)));
+        }
+        if details.is_empty() {
+            format!("{:?}", target)
+        } else {
+            format!("{:?} ({})", target, details.join(", "))
+        }
+    }
+}
+
+/// Appends ` (<what> at <location>)` to `message` if the location was recorded.
+pub(crate) fn with_location(
+    message: String,
+    what: &str,
+    location: Option<&'static Location<'static>>,
+) -> String {
+    match location {
+        Some(location) => format!("{} ({} at {})", message, what, location),
+        None => message,
+    }
+}
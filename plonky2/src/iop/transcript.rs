@@ -0,0 +1,176 @@
+//! Hooks for auditing Fiat-Shamir transcripts. A `TranscriptObserver` attached to a challenger is
+//! told of every element it absorbs and every challenge it emits, labelled with the phase of the
+//! protocol, so that the transcripts of the native prover, the native verifier and the recursive
+//! verifier can be dumped and diffed to catch elements which one of them fails to bind.
+//!
+//! Each challenge is reported as the individual field elements squeezed from the sponge, before
+//! they are combined into extension elements or query indices.
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+use plonky2_field::field_types::Field;
+
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness};
+
+/// An entry of a Fiat-Shamir transcript.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TranscriptEntry<T> {
+    /// An element absorbed by the challenger.
+    Observed(T),
+    /// An element squeezed from the challenger as (part of) a challenge.
+    Challenge(T),
+}
+
+impl<T> TranscriptEntry<T> {
+    pub fn value(&self) -> &T {
+        match self {
+            TranscriptEntry::Observed(x) | TranscriptEntry::Challenge(x) => x,
+        }
+    }
+
+    pub fn map<U, M: FnOnce(T) -> U>(self, f: M) -> TranscriptEntry<U> {
+        match self {
+            TranscriptEntry::Observed(x) => TranscriptEntry::Observed(f(x)),
+            TranscriptEntry::Challenge(x) => TranscriptEntry::Challenge(f(x)),
+        }
+    }
+}
+
+/// Receives the entries of a Fiat-Shamir transcript as a challenger produces them.
+pub trait TranscriptObserver<F>: 'static + Send + Debug {
+    fn record(&mut self, label: &'static str, entry: TranscriptEntry<F>);
+}
+
+/// An observer shared with the challengers reporting to it.
+pub type SharedTranscriptObserver<F> = Arc<Mutex<dyn TranscriptObserver<F>>>;
+
+/// An observer which records the whole transcript.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Transcript<F> {
+    pub entries: Vec<(&'static str, TranscriptEntry<F>)>,
+}
+
+impl<F: Copy + Eq> Transcript<F> {
+    /// The index of the first entry at which the two transcripts differ, if any, either in label
+    /// or in value, or because one ends early.
+    pub fn first_difference(&self, other: &Self) -> Option<usize> {
+        let common_len = self.entries.len().min(other.entries.len());
+        (0..common_len)
+            .find(|&i| self.entries[i] != other.entries[i])
+            .or_else(|| (self.entries.len() != other.entries.len()).then_some(common_len))
+    }
+}
+
+impl<F: 'static + Send + Debug> TranscriptObserver<F> for Transcript<F> {
+    fn record(&mut self, label: &'static str, entry: TranscriptEntry<F>) {
+        self.entries.push((label, entry));
+    }
+}
+
+impl<F: Display> Display for Transcript<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (label, entry)) in self.entries.iter().enumerate() {
+            match entry {
+                TranscriptEntry::Observed(x) => {
+                    writeln!(f, "{:>6} {:<24} observe {}", i, label, x)?
+                }
+                TranscriptEntry::Challenge(x) => {
+                    writeln!(f, "{:>6} {:<24} squeeze {}", i, label, x)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports the values of a recursive challenger's transcript once they are all known.
+#[derive(Debug)]
+pub(crate) struct TranscriptGenerator<F: Field> {
+    pub(crate) entries: Vec<(&'static str, TranscriptEntry<Target>)>,
+    pub(crate) observer: SharedTranscriptObserver<F>,
+}
+
+impl<F: Field> SimpleGenerator<F> for TranscriptGenerator<F> {
+    fn dependencies(&self) -> Vec<Target> {
+        self.entries
+            .iter()
+            .map(|(_, entry)| *entry.value())
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, _out_buffer: &mut GeneratedValues<F>) {
+        let mut observer = self.observer.lock().unwrap();
+        for &(label, entry) in &self.entries {
+            observer.record(label, entry.map(|t| witness.get_target(t)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+
+    use crate::gates::noop::NoopGate;
+    use crate::iop::transcript::Transcript;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, VerifierCircuitTarget};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_transcripts_match() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let mut data = builder.build::<C>();
+        let prover_transcript = Arc::new(Mutex::new(Transcript::default()));
+        data.set_transcript_observer(prover_transcript.clone());
+        let proof = data.prove(PartialWitness::new())?;
+
+        let verifier_transcript = Arc::new(Mutex::new(Transcript::default()));
+        proof.observe_transcript(&data.common, verifier_transcript.clone())?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let recursive_transcript = Arc::new(Mutex::new(Transcript::default()));
+        builder.set_transcript_observer(recursive_transcript.clone());
+        let mut pw = PartialWitness::new();
+        let pt = builder.add_virtual_proof_with_pis(&data.common);
+        pw.set_proof_with_pis_target(&pt, &proof);
+        let inner_data = VerifierCircuitTarget {
+            constants_sigmas_cap: builder.add_virtual_cap(data.common.config.fri_config.cap_height),
+        };
+        pw.set_cap_target(
+            &inner_data.constants_sigmas_cap,
+            &data.verifier_only.constants_sigmas_cap,
+        );
+        builder.verify_proof(pt, &inner_data, &data.common);
+        let recursive_data = builder.build::<C>();
+        recursive_data.prove(pw)?;
+
+        let prover_transcript = prover_transcript.lock().unwrap();
+        let verifier_transcript = verifier_transcript.lock().unwrap();
+        let recursive_transcript = recursive_transcript.lock().unwrap();
+        assert!(!prover_transcript.entries.is_empty());
+        assert_eq!(
+            prover_transcript.first_difference(&verifier_transcript),
+            None
+        );
+        assert_eq!(
+            verifier_transcript.first_difference(&recursive_transcript),
+            None
+        );
+        Ok(())
+    }
+}
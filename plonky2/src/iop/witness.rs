@@ -3,11 +3,12 @@ use std::collections::HashMap;
 use itertools::Itertools;
 use num::{BigUint, FromPrimitive, Zero};
 use plonky2_field::extension_field::{Extendable, FieldExtension};
-use plonky2_field::field_types::{Field, PrimeField};
+use plonky2_field::field_types::{Field, PrimeField, PrimeField64};
 
 use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
 use crate::fri::witness_util::set_fri_proof_target;
 use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::arithmetic_u64::U64Target;
 use crate::gadgets::biguint::BigUintTarget;
 use crate::gadgets::nonnative::NonNativeTarget;
 use crate::hash::hash_types::HashOutTarget;
@@ -15,6 +16,7 @@ use crate::hash::hash_types::RichField;
 use crate::hash::hash_types::{HashOut, MerkleCapTarget};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::source_locations::SourceLocations;
 use crate::iop::target::{BoolTarget, Target};
 use crate::iop::wire::Wire;
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
@@ -63,6 +65,15 @@ pub trait Witness<F: Field> {
         panic!("not a bool")
     }
 
+    fn get_u64_target(&self, target: U64Target) -> u64
+    where
+        F: PrimeField64,
+    {
+        let lo = self.get_target(target.lo().0).to_canonical_u64();
+        let hi = self.get_target(target.hi().0).to_canonical_u64();
+        (hi << 32) + lo
+    }
+
     fn get_biguint_target(&self, target: BigUintTarget) -> BigUint
     where
         F: PrimeField,
@@ -159,6 +170,11 @@ pub trait Witness<F: Field> {
         self.set_target(target.0, F::from_canonical_u32(value))
     }
 
+    fn set_u64_target(&mut self, target: U64Target, value: u64) {
+        self.set_u32_target(target.lo(), value as u32);
+        self.set_u32_target(target.hi(), (value >> 32) as u32);
+    }
+
     fn set_biguint_target(&mut self, target: &BigUintTarget, value: &BigUint) {
         for (&lt, &l) in target.limbs.iter().zip(&value.to_u32_digits()) {
             self.set_u32_target(lt, l);
@@ -174,6 +190,7 @@ pub trait Witness<F: Field> {
     ) where
         F: RichField + Extendable<D>,
         C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
     {
         let ProofWithPublicInputs {
             proof,
@@ -200,6 +217,7 @@ pub trait Witness<F: Field> {
     ) where
         F: RichField + Extendable<D>,
         C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
     {
         self.set_cap_target(&proof_target.wires_cap, &proof.wires_cap);
         self.set_cap_target(
@@ -310,6 +328,8 @@ pub struct PartitionWitness<'a, F: Field> {
     pub representative_map: &'a [usize],
     pub num_wires: usize,
     pub degree: usize,
+    /// Where the circuit's targets were created, used to describe targets in error messages.
+    pub(crate) source_locations: Option<&'a SourceLocations>,
 }
 
 impl<'a, F: Field> PartitionWitness<'a, F> {
@@ -324,6 +344,7 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
             representative_map,
             num_wires,
             degree,
+            source_locations: None,
         }
     }
 
@@ -334,9 +355,10 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
         let rep_value = &mut self.values[rep_index];
         if let Some(old_value) = *rep_value {
             assert_eq!(
-                value, old_value,
-                "Partition containing {:?} was set twice with different values",
-                target
+                value,
+                old_value,
+                "Partition containing {} was set twice with different values",
+                self.describe_target(target)
             );
             None
         } else {
@@ -345,11 +367,19 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
         }
     }
 
+    /// Describes `target` for an error message, including where it was created if that's known.
+    pub(crate) fn describe_target(&self, target: Target) -> String {
+        match self.source_locations {
+            Some(locations) => locations.describe(target),
+            None => format!("{:?}", target),
+        }
+    }
+
     pub(crate) fn target_index(&self, target: Target) -> usize {
         target.index(self.num_wires, self.degree)
     }
 
-    pub fn full_witness(self) -> MatrixWitness<F> {
+    pub fn full_witness(&self) -> MatrixWitness<F> {
         let mut wire_values = vec![vec![F::ZERO; self.degree]; self.num_wires];
         for i in 0..self.degree {
             for j in 0..self.num_wires {
@@ -19,12 +19,13 @@ pub mod gates;
 pub mod hash;
 pub mod iop;
 pub mod plonk;
+pub mod stdlib;
 pub mod util;
 
 // Set up Jemalloc
-#[cfg(not(target_env = "msvc"))]
+#[cfg(all(feature = "jemalloc", not(target_env = "msvc")))]
 use jemallocator::Jemalloc;
 
-#[cfg(not(target_env = "msvc"))]
+#[cfg(all(feature = "jemalloc", not(target_env = "msvc")))]
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
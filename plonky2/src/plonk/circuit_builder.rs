@@ -1,54 +1,74 @@
 use std::cmp::max;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::io::Write;
+use std::panic::Location;
+use std::sync::Arc;
 use std::time::Instant;
 
-use log::{debug, info, Level};
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn, Level};
 use plonky2_field::cosets::get_unique_coset_shifts;
 use plonky2_field::extension_field::{Extendable, FieldExtension};
 use plonky2_field::fft::fft_root_table;
 use plonky2_field::field_types::Field;
 use plonky2_field::polynomial::PolynomialValues;
 use plonky2_util::{log2_ceil, log2_strict};
-
-use crate::fri::oracle::PolynomialBatch;
-use crate::fri::{FriConfig, FriParams};
+use rand::thread_rng;
+use tracing::{field, info_span};
+
+use crate::fri::fft_backend::CpuFftBackend;
+use crate::fri::grinding::CpuGrinder;
+use crate::fri::pcs::FriProverParams;
+use crate::fri::structure::FriPolynomialInfo;
+use crate::fri::{FriParams, SaltMode};
 use crate::gadgets::arithmetic::BaseArithmeticOperation;
 use crate::gadgets::arithmetic_extension::ExtensionArithmeticOperation;
 use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::byte_tables::ByteTableCache;
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::gates::add_many_u32::U32AddManyGate;
+use crate::gates::addition_u64::U64AdditionGate;
 use crate::gates::arithmetic_base::ArithmeticGate;
 use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
 use crate::gates::arithmetic_u32::U32ArithmeticGate;
+use crate::gates::conditional_arithmetic::ConditionalArithmeticGate;
 use crate::gates::constant::ConstantGate;
 use crate::gates::gate::{Gate, GateInstance, GateRef, PrefixedGate};
 use crate::gates::gate_tree::Tree;
 use crate::gates::multiplication_extension::MulExtensionGate;
+use crate::gates::multiplication_u64::U64MultiplicationGate;
 use crate::gates::noop::NoopGate;
 use crate::gates::public_input::PublicInputGate;
 use crate::gates::random_access::RandomAccessGate;
 use crate::gates::subtraction_u32::U32SubtractionGate;
 use crate::gates::switch::SwitchGate;
-use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_proofs::MerkleProofTarget;
-use crate::iop::ext_target::ExtensionTarget;
-use crate::iop::generator::{
-    CopyGenerator, RandomValueGenerator, SimpleGenerator, WitnessGenerator,
+use crate::hash::public_inputs::{
+    hash_grouped_public_inputs_circuit, PublicInputGroup, PublicInputsHashFunction,
 };
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{CopyGenerator, SimpleGenerator, WitnessGenerator};
+use crate::iop::source_locations::{with_location, SourceLocations};
 use crate::iop::target::{BoolTarget, Target};
+use crate::iop::transcript::{SharedTranscriptObserver, TranscriptEntry, TranscriptGenerator};
 use crate::iop::wire::Wire;
 use crate::plonk::circuit_data::{
-    CircuitConfig, CircuitData, CommonCircuitData, ProverCircuitData, ProverOnlyCircuitData,
-    VerifierCircuitData, VerifierOnlyCircuitData,
+    CircuitConfig, CircuitData, CommonCircuitData, ExtraOpening, ProverCircuitData,
+    ProverOnlyCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
 };
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::copy_constraint::CopyConstraint;
+use crate::plonk::pcs::{PcsProverContext, PlonkPcs, PolynomialCommitmentScheme};
 use crate::plonk::permutation_argument::Forest;
 use crate::plonk::plonk_common::PlonkOracle;
+use crate::plonk::region::CircuitRegion;
 use crate::timed;
 use crate::util::context_tree::ContextTree;
 use crate::util::marking::{Markable, MarkedTargets};
 use crate::util::partial_products::num_partial_products;
+use crate::util::progress::ProvingMonitor;
 use crate::util::timing::TimingTree;
 use crate::util::{transpose, transpose_poly_values};
 
@@ -62,12 +82,22 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     pub(crate) gate_instances: Vec<GateInstance<F, D>>,
 
     /// Targets to be made public.
-    public_inputs: Vec<Target>,
+    pub(crate) public_inputs: Vec<Target>,
+
+    /// Groups of public inputs which are hashed separately.
+    public_input_groups: Vec<PublicInputGroup>,
+
+    /// The points other than `zeta` and `g * zeta` at which polynomials are opened.
+    extra_openings: Vec<ExtraOpening<F>>,
 
     /// The next available index for a `VirtualTarget`.
-    virtual_target_index: usize,
+    pub(crate) virtual_target_index: usize,
 
-    copy_constraints: Vec<CopyConstraint>,
+    pub(crate) copy_constraints: Vec<CopyConstraint>,
+
+    /// The name of copy constraints added in the current context, which they all share. It is
+    /// computed when first needed, and reset whenever the context changes.
+    copy_constraint_name: Option<Arc<str>>,
 
     /// A tree of named scopes, used for debugging.
     context_log: ContextTree,
@@ -76,7 +106,25 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     marked_targets: Vec<MarkedTargets<D>>,
 
     /// Generators used to generate the witness.
-    generators: Vec<Box<dyn WitnessGenerator<F>>>,
+    pub(crate) generators: Vec<Box<dyn WitnessGenerator<F>>>,
+
+    /// Targets which the prover sets to random values, to blind the witness polynomials.
+    blinding_targets: Vec<Target>,
+
+    /// The observer to which recursive challengers report their transcripts, if any.
+    pub(crate) transcript_observer: Option<SharedTranscriptObserver<F>>,
+
+    /// The transcript entries reported by recursive challengers so far.
+    transcript: Vec<(&'static str, TranscriptEntry<Target>)>,
+
+    /// Where each virtual target and gate was created. Only recorded in debug builds.
+    source_locations: SourceLocations,
+
+    /// The regions entered so far, in the order they were entered.
+    regions: Vec<CircuitRegion>,
+
+    /// The index of the innermost region currently entered, if any.
+    current_region: Option<usize>,
 
     constants_to_targets: HashMap<F, Target>,
     targets_to_constants: HashMap<Target, F>,
@@ -87,7 +135,21 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Memoized results of `arithmetic_extension` calls.
     pub(crate) arithmetic_results: HashMap<ExtensionArithmeticOperation<F, D>, ExtensionTarget<D>>,
 
+    /// Results of `arithmetic` calls which scale a single target by a constant, mapped to the
+    /// constant and that target, so that consecutive scalings can be combined.
+    pub(crate) base_scalings: HashMap<Target, (F, Target)>,
+
+    /// Like `base_scalings`, for `arithmetic_extension` calls scaling by base field constants.
+    pub(crate) extension_scalings: HashMap<ExtensionTarget<D>, (F, ExtensionTarget<D>)>,
+
+    /// Byte decompositions and lookups shared by all gadgets using byte tables.
+    pub(crate) byte_tables: ByteTableCache,
+
     batched_gates: BatchedGates<F, D>,
+
+    /// Violated parameter relations found while adding gadgets, which `try_build` reports along
+    /// with those found by `check_params`.
+    pub(crate) param_errors: Vec<String>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
@@ -97,44 +159,79 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             gates: HashSet::new(),
             gate_instances: Vec::new(),
             public_inputs: Vec::new(),
+            public_input_groups: Vec::new(),
+            extra_openings: Vec::new(),
             virtual_target_index: 0,
             copy_constraints: Vec::new(),
+            copy_constraint_name: None,
             context_log: ContextTree::new(),
             marked_targets: Vec::new(),
             generators: Vec::new(),
+            blinding_targets: Vec::new(),
+            transcript_observer: None,
+            transcript: Vec::new(),
+            source_locations: SourceLocations::default(),
+            regions: Vec::new(),
+            current_region: None,
             constants_to_targets: HashMap::new(),
             base_arithmetic_results: HashMap::new(),
             arithmetic_results: HashMap::new(),
+            base_scalings: HashMap::new(),
+            extension_scalings: HashMap::new(),
+            byte_tables: ByteTableCache::default(),
             targets_to_constants: HashMap::new(),
             batched_gates: BatchedGates::new(),
+            param_errors: Vec::new(),
         };
         builder.check_config();
         builder
     }
 
     fn check_config(&self) {
-        let &CircuitConfig {
+        let CircuitConfig {
             security_bits,
-            fri_config:
-                FriConfig {
-                    rate_bits,
-                    proof_of_work_bits,
-                    num_query_rounds,
-                    ..
-                },
+            fri_config,
             ..
         } = &self.config;
 
         // Conjectured FRI security; see the ethSTARK paper.
         let fri_field_bits = F::Extension::order().bits() as usize;
-        let fri_query_security_bits = num_query_rounds * rate_bits + proof_of_work_bits as usize;
+        let fri_query_security_bits = fri_config.conjectured_security_bits();
         let fri_security_bits = fri_field_bits.min(fri_query_security_bits);
         assert!(
-            fri_security_bits >= security_bits,
+            fri_security_bits >= *security_bits,
             "FRI params fall short of target security"
         );
     }
 
+    /// Requests that the polynomials with the given indices in the given oracles are also opened
+    /// at `multiplier * zeta`, for an argument layered on top of the Plonk commitments. Their values
+    /// are given by the `extra` openings of proofs, in the order of the requests, and are checked
+    /// by FRI along with the other openings. Returns the index of the opening.
+    #[track_caller]
+    pub fn add_extra_opening(
+        &mut self,
+        multiplier: F,
+        polynomials: &[(PlonkOracle, usize)],
+    ) -> usize {
+        assert!(multiplier.is_nonzero(), "Opening point is zero");
+        if cfg!(debug_assertions) {
+            self.source_locations
+                .add_extra_opening(self.extra_openings.len(), Location::caller());
+        }
+        self.extra_openings.push(ExtraOpening {
+            multiplier,
+            polynomials: polynomials
+                .iter()
+                .map(|&(oracle, polynomial_index)| FriPolynomialInfo {
+                    oracle_index: oracle.index,
+                    polynomial_index,
+                })
+                .collect(),
+        });
+        self.extra_openings.len() - 1
+    }
+
     pub fn num_gates(&self) -> usize {
         self.gate_instances.len()
     }
@@ -149,30 +246,74 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         targets.iter().for_each(|&t| self.register_public_input(t));
     }
 
+    /// Registers the given targets as public inputs forming a group named `name`, which is hashed
+    /// with `hash` separately from the other public inputs. The group's digest then stands in for
+    /// its inputs in the public inputs hash.
+    pub fn register_public_input_group(
+        &mut self,
+        name: &str,
+        hash: PublicInputsHashFunction,
+        targets: &[Target],
+    ) {
+        assert!(
+            self.public_input_groups.iter().all(|g| g.name != name),
+            "Public input group {} registered twice",
+            name
+        );
+        let start = self.public_inputs.len();
+        self.register_public_inputs(targets);
+        self.public_input_groups.push(PublicInputGroup {
+            name: name.to_string(),
+            hash,
+            range: start..self.public_inputs.len(),
+        });
+    }
+
     /// Adds a new "virtual" target. This is not an actual wire in the witness, but just a target
     /// that help facilitate witness generation. In particular, a generator can assign a values to a
     /// virtual target, which can then be copied to other (virtual or concrete) targets. When we
     /// generate the final witness (a grid of wire values), these virtual targets will go away.
+    ///
+    /// In debug builds, the caller's location is recorded and reported if generating the witness
+    /// fails because of this target.
+    #[track_caller]
     pub fn add_virtual_target(&mut self) -> Target {
         let index = self.virtual_target_index;
         self.virtual_target_index += 1;
+        if cfg!(debug_assertions) {
+            self.source_locations
+                .add_virtual_target(index, Location::caller());
+        }
         Target::VirtualTarget { index }
     }
 
+    #[track_caller]
     pub fn add_virtual_targets(&mut self, n: usize) -> Vec<Target> {
-        (0..n).map(|_i| self.add_virtual_target()).collect()
+        // Not a closure, since closures don't forward the caller's location.
+        let mut targets = Vec::with_capacity(n);
+        for _ in 0..n {
+            targets.push(self.add_virtual_target());
+        }
+        targets
     }
 
+    #[track_caller]
     pub fn add_virtual_hash(&mut self) -> HashOutTarget {
         HashOutTarget::from_vec(self.add_virtual_targets(4))
     }
 
+    #[track_caller]
     pub fn add_virtual_cap(&mut self, cap_height: usize) -> MerkleCapTarget {
         MerkleCapTarget(self.add_virtual_hashes(1 << cap_height))
     }
 
+    #[track_caller]
     pub fn add_virtual_hashes(&mut self, n: usize) -> Vec<HashOutTarget> {
-        (0..n).map(|_i| self.add_virtual_hash()).collect()
+        let mut hashes = Vec::with_capacity(n);
+        for _ in 0..n {
+            hashes.push(self.add_virtual_hash());
+        }
+        hashes
     }
 
     pub(crate) fn add_virtual_merkle_proof(&mut self, len: usize) -> MerkleProofTarget {
@@ -181,14 +322,18 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    #[track_caller]
     pub fn add_virtual_extension_target(&mut self) -> ExtensionTarget<D> {
         ExtensionTarget(self.add_virtual_targets(D).try_into().unwrap())
     }
 
+    #[track_caller]
     pub fn add_virtual_extension_targets(&mut self, n: usize) -> Vec<ExtensionTarget<D>> {
-        (0..n)
-            .map(|_i| self.add_virtual_extension_target())
-            .collect()
+        let mut targets = Vec::with_capacity(n);
+        for _ in 0..n {
+            targets.push(self.add_virtual_extension_target());
+        }
+        targets
     }
 
     pub(crate) fn add_virtual_poly_coeff_ext(
@@ -200,17 +345,30 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     // TODO: Unsafe
+    #[track_caller]
     pub fn add_virtual_bool_target(&mut self) -> BoolTarget {
         BoolTarget::new_unsafe(self.add_virtual_target())
     }
 
+    #[track_caller]
     pub fn add_virtual_bool_target_safe(&mut self) -> BoolTarget {
         let b = BoolTarget::new_unsafe(self.add_virtual_target());
         self.assert_bool(b);
         b
     }
 
-    /// Adds a gate to the circuit, and returns its index.
+    /// Reserves capacity for at least `n` more gates, along with a generator and a copy constraint
+    /// for each, so that building a circuit of known approximate size doesn't repeatedly regrow
+    /// the builder's vectors.
+    pub fn reserve_gates(&mut self, n: usize) {
+        self.gate_instances.reserve(n);
+        self.generators.reserve(n);
+        self.copy_constraints.reserve(n);
+    }
+
+    /// Adds a gate to the circuit, and returns its index. In debug builds, the caller's location is
+    /// recorded and reported if generating the witness fails because of one of the gate's wires.
+    #[track_caller]
     pub fn add_gate<G: Gate<F, D>>(&mut self, gate_type: G, constants: Vec<F>) -> usize {
         self.check_gate_compatibility(&gate_type);
         assert_eq!(
@@ -219,20 +377,35 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             "Number of constants doesn't match."
         );
 
+        self.add_gate_ref(GateRef::new(gate_type), constants)
+    }
+
+    /// Like `add_gate`, for a gate which has already been checked against this circuit's config.
+    #[track_caller]
+    pub(crate) fn add_gate_ref(&mut self, gate_ref: GateRef<F, D>, constants: Vec<F>) -> usize {
         let index = self.gate_instances.len();
 
         // Note that we can't immediately add this gate's generators, because the list of constants
         // could be modified later, i.e. in the case of `ConstantGate`. We will add them later in
         // `build` instead.
 
-        // Register this gate type if we haven't seen it before.
-        let gate_ref = GateRef::new(gate_type);
-        self.gates.insert(gate_ref.clone());
+        // Register this gate type if we haven't seen it before. Otherwise, the instance shares the
+        // registered gate, so that large circuits hold one copy of each gate type.
+        let gate_ref = match self.gates.get(&gate_ref) {
+            Some(registered) => registered.clone(),
+            None => {
+                self.gates.insert(gate_ref.clone());
+                gate_ref
+            }
+        };
 
         self.gate_instances.push(GateInstance {
             gate_ref,
             constants,
         });
+        if cfg!(debug_assertions) {
+            self.source_locations.add_gate(index, Location::caller());
+        }
 
         index
     }
@@ -247,12 +420,30 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    #[track_caller]
     pub fn connect_extension(&mut self, src: ExtensionTarget<D>, dst: ExtensionTarget<D>) {
         for i in 0..D {
             self.connect(src.0[i], dst.0[i]);
         }
     }
 
+    /// Connects each extension target of `xs` to the corresponding one of `ys`.
+    #[track_caller]
+    pub fn connect_extension_slices(
+        &mut self,
+        xs: &[ExtensionTarget<D>],
+        ys: &[ExtensionTarget<D>],
+    ) {
+        assert_eq!(
+            xs.len(),
+            ys.len(),
+            "Slices to connect have different lengths"
+        );
+        let xs = xs.iter().flat_map(|x| x.0).collect::<Vec<_>>();
+        let ys = ys.iter().flat_map(|y| y.0).collect::<Vec<_>>();
+        self.connect_slices(&xs, &ys);
+    }
+
     /// Adds a generator which will copy `src` to `dst`.
     pub fn generate_copy(&mut self, src: Target, dst: Target) {
         self.add_simple_generator(CopyGenerator { src, dst });
@@ -260,24 +451,61 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
     /// Uses Plonk's permutation argument to require that two elements be equal.
     /// Both elements must be routable, otherwise this method will panic.
+    #[track_caller]
     pub fn connect(&mut self, x: Target, y: Target) {
-        assert!(
-            x.is_routable(&self.config),
-            "Tried to route a wire that isn't routable"
-        );
-        assert!(
-            y.is_routable(&self.config),
-            "Tried to route a wire that isn't routable"
+        let name = self.copy_constraint_name();
+        self.add_copy_constraint(x, y, name);
+    }
+
+    /// Connects each target of `xs` to the corresponding target of `ys`. This is equivalent to
+    /// calling `connect` for each pair, but allocates for the copy constraints at once.
+    #[track_caller]
+    pub fn connect_slices(&mut self, xs: &[Target], ys: &[Target]) {
+        assert_eq!(
+            xs.len(),
+            ys.len(),
+            "Slices to connect have different lengths"
         );
+        let name = self.copy_constraint_name();
+        self.copy_constraints.reserve(xs.len());
+        for (&x, &y) in xs.iter().zip(ys) {
+            self.add_copy_constraint(x, y, name.clone());
+        }
+    }
+
+    fn copy_constraint_name(&mut self) -> Arc<str> {
+        let context_log = &self.context_log;
+        self.copy_constraint_name
+            .get_or_insert_with(|| context_log.open_stack().into())
+            .clone()
+    }
+
+    #[track_caller]
+    fn add_copy_constraint(&mut self, x: Target, y: Target, name: Arc<str>) {
+        for t in [x, y] {
+            assert!(
+                t.is_routable(&self.config),
+                "Tried to route a wire that isn't routable: {}",
+                self.source_locations.describe(t)
+            );
+        }
         self.copy_constraints
-            .push(CopyConstraint::new((x, y), self.context_log.open_stack()));
+            .push(CopyConstraint::new((x, y), name));
     }
 
+    #[track_caller]
     pub fn assert_zero(&mut self, x: Target) {
         let zero = self.zero();
         self.connect(x, zero);
     }
 
+    /// Asserts that each of `xs` is zero, by connecting them all to a single zero constant, so
+    /// that no gates are added.
+    pub fn assert_zero_many(&mut self, xs: &[Target]) {
+        let zero = self.zero();
+        self.connect_slices(xs, &vec![zero; xs.len()]);
+    }
+
     pub fn assert_one(&mut self, x: Target) {
         let one = self.one();
         self.connect(x, one);
@@ -291,6 +519,20 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.generators.push(Box::new(generator.adapter()));
     }
 
+    /// Reports the transcripts of recursive challengers created from now on to `observer`, each
+    /// time a witness is generated. Entries are reported in the order the challengers produced
+    /// them, for comparison with the transcripts of native challengers.
+    pub fn set_transcript_observer(&mut self, observer: SharedTranscriptObserver<F>) {
+        self.transcript_observer = Some(observer);
+    }
+
+    pub(crate) fn record_transcript(
+        &mut self,
+        entries: impl IntoIterator<Item = (&'static str, TranscriptEntry<Target>)>,
+    ) {
+        self.transcript.extend(entries);
+    }
+
     /// Returns a routable target with a value of 0.
     pub fn zero(&mut self) -> Target {
         self.constant(F::ZERO)
@@ -353,6 +595,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         U32Target(self.constant(F::from_canonical_u32(c)))
     }
 
+    pub fn constant_hash(&mut self, h: HashOut<F>) -> HashOutTarget {
+        HashOutTarget {
+            elements: h.elements.map(|c| self.constant(c)),
+        }
+    }
+
     /// If the given target is a constant (i.e. it was created by the `constant(F)` method), returns
     /// its constant value. Otherwise, returns `None`.
     pub fn target_as_constant(&self, target: Target) -> Option<F> {
@@ -379,10 +627,43 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
     pub fn push_context(&mut self, level: log::Level, ctx: &str) {
         self.context_log.push(ctx, level, self.num_gates());
+        self.copy_constraint_name = None;
     }
 
     pub fn pop_context(&mut self) {
         self.context_log.pop(self.num_gates());
+        self.copy_constraint_name = None;
+    }
+
+    /// Runs `f` inside a new region named `name`. The gates, virtual targets and public inputs
+    /// added by `f` are attributed to the region, which is also entered as a context for profiling
+    /// and named in witness generation errors involving its targets.
+    pub fn region<T>(&mut self, name: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        let index = self.regions.len();
+        self.regions.push(CircuitRegion::new(
+            name,
+            self.current_region,
+            self.num_gates(),
+            self.virtual_target_index,
+            self.public_inputs.len(),
+        ));
+        let parent = self.current_region.replace(index);
+        self.push_context(Level::Debug, name);
+
+        let res = f(self);
+
+        self.pop_context();
+        self.current_region = parent;
+        let region = &mut self.regions[index];
+        region.gates.end = self.gate_instances.len();
+        region.virtual_targets.end = self.virtual_target_index;
+        region.public_inputs.end = self.public_inputs.len();
+        res
+    }
+
+    /// The regions entered so far, in the order they were entered.
+    pub fn regions(&self) -> &[CircuitRegion] {
+        &self.regions
     }
 
     pub fn add_marked(&mut self, targets: Markable<D>, name: &str) {
@@ -393,9 +674,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     fn fri_params(&self, degree_bits: usize) -> FriParams {
-        self.config
-            .fri_config
-            .fri_params(degree_bits, self.config.zero_knowledge)
+        self.config.fri_config.fri_params(
+            degree_bits,
+            self.config.fri_salt_size::<D>(),
+            self.config.salt_mode,
+        )
     }
 
     /// The number of (base field) `arithmetic` operations that can be performed in a single gate.
@@ -429,10 +712,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let final_poly_coeffs: usize = degree_estimate / arities.iter().product::<usize>();
         let fri_openings = fri_queries * (1 + D * total_fri_folding_points + D * final_poly_coeffs);
 
+        // Any polynomial may also be opened at the extra points.
+        let extra_openings = D * self.extra_openings.len();
         // We add D for openings at zeta.
-        let regular_poly_openings = D + fri_openings;
+        let regular_poly_openings = D + extra_openings + fri_openings;
         // We add 2 * D for openings at zeta and g * zeta.
-        let z_openings = 2 * D + fri_openings;
+        let z_openings = 2 * D + extra_openings + fri_openings;
 
         (regular_poly_openings, z_openings)
     }
@@ -461,11 +746,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// The number of gates once `blind_and_pad` has run.
+    fn padded_degree(&self) -> usize {
+        let mut num_gates = self.gate_instances.len();
+        if self.config.zero_knowledge {
+            let (regular_poly_openings, z_openings) = self.blinding_counts();
+            num_gates += regular_poly_openings + 2 * z_openings;
+        }
+        num_gates.next_power_of_two()
+    }
+
     fn blind_and_pad(&mut self) {
         if self.config.zero_knowledge {
             self.blind();
         }
 
+        let num_gates = self.gate_instances.len();
+        self.gate_instances
+            .reserve(num_gates.next_power_of_two() - num_gates);
         while !self.gate_instances.len().is_power_of_two() {
             self.add_gate(NoopGate, vec![]);
         }
@@ -486,9 +784,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         for _ in 0..regular_poly_openings {
             let gate = self.add_gate(NoopGate, vec![]);
             for w in 0..num_wires {
-                self.add_simple_generator(RandomValueGenerator {
-                    target: Target::Wire(Wire { gate, input: w }),
-                });
+                self.blinding_targets
+                    .push(Target::Wire(Wire { gate, input: w }));
             }
         }
 
@@ -500,12 +797,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             let gate_2 = self.add_gate(NoopGate, vec![]);
 
             for w in 0..num_routed_wires {
-                self.add_simple_generator(RandomValueGenerator {
-                    target: Target::Wire(Wire {
-                        gate: gate_1,
-                        input: w,
-                    }),
-                });
+                self.blinding_targets.push(Target::Wire(Wire {
+                    gate: gate_1,
+                    input: w,
+                }));
                 self.generate_copy(
                     Target::Wire(Wire {
                         gate: gate_1,
@@ -600,22 +895,282 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Recommends a cheaper config for the circuit built so far, based on what it actually uses:
+    /// - `num_wires` is reduced to the most wires used by any of its gates,
+    /// - `num_routed_wires` to the most wires touched by its copy constraints, and
+    /// - `max_quotient_degree_factor` to the factor `build` would pick given its constraint degrees
+    ///   and the recommended routed wires.
+    ///
+    /// Gates used to verify FRI proofs are registered like any other, so a recursive circuit's
+    /// recommendation still meets the requirements of `check_recursion_config`. However, some gates,
+    /// such as `ArithmeticGate`, size themselves to the config, so the recommendation is a starting
+    /// point to rebuild the circuit with and compare, rather than a guarantee of a smaller circuit.
+    /// Gates which `build` adds to hash the public inputs aren't accounted for either.
+    pub fn recommend_config(&self) -> CircuitConfig {
+        // `build` always adds these gates.
+        let mut gates = self.gates.clone();
+        gates.insert(GateRef::new(PublicInputGate));
+        gates.insert(GateRef::new(NoopGate));
+
+        let max_gate_wires = gates.iter().map(|g| g.0.num_wires()).max().unwrap();
+        let max_copied_wire = self
+            .copy_constraints
+            .iter()
+            .flat_map(|c| [c.pair.0, c.pair.1])
+            .filter_map(|t| match t {
+                Target::Wire(Wire { input, .. }) => Some(input + 1),
+                Target::VirtualTarget { .. } => None,
+            })
+            .chain([PublicInputGate::wires_public_inputs_hash().end])
+            .max()
+            .unwrap();
+
+        let (_, max_filtered_constraint_degree, _) = Tree::from_gates(gates.into_iter().collect());
+        let quotient_degree_factor =
+            choose_quotient_degree_factor(&self.config, max_filtered_constraint_degree);
+
+        // The prover requires more routed wires than the quotient degree factor.
+        let num_routed_wires = max_copied_wire
+            .max(quotient_degree_factor + 1)
+            .min(self.config.num_routed_wires);
+        let num_wires = max_gate_wires.max(num_routed_wires);
+        let mut config = CircuitConfig {
+            num_wires,
+            num_routed_wires,
+            ..self.config.clone()
+        };
+        config.max_quotient_degree_factor =
+            choose_quotient_degree_factor(&config, max_filtered_constraint_degree);
+        config
+    }
+
+    /// Writes the gate count of each context in the collapsed stack format, which `inferno` or
+    /// speedscope can render as a flamegraph of where the circuit's gates come from. Gates which
+    /// are only added when building, such as those filling batched operations or padding, aren't
+    /// included.
+    pub fn write_gate_counts_collapsed<W: Write>(&self, mut out: W) -> io::Result<()> {
+        self.context_log
+            .write_collapsed_stacks(self.num_gates(), &mut out)
+    }
+
+    /// Checks the relations between the config, the FRI parameters, the constraint degrees and the
+    /// extra openings which proving relies on, returning an error which lists every violated
+    /// relation.
+    fn check_params(
+        &self,
+        degree_bits: usize,
+        fri_params: &FriParams,
+        max_filtered_constraint_degree: usize,
+        num_constants: usize,
+    ) -> Result<()> {
+        let config = &self.config;
+        let fri_config = &config.fri_config;
+        let mut errors = self.param_errors.clone();
+
+        match (
+            config.num_challenges,
+            config.min_num_challenges::<F>(degree_bits),
+        ) {
+            (_, None) => errors.push(format!(
+                "the field is too small for Plonk challenges to give any soundness at degree 2^{}",
+                degree_bits
+            )),
+            (Some(num_challenges), Some(min_num_challenges))
+                if num_challenges < min_num_challenges =>
+            {
+                warn!(
+                    "{} challenges give {} of the {} targeted bits of soundness; {} are needed",
+                    num_challenges,
+                    num_challenges * config.challenge_soundness_bits::<F>(degree_bits),
+                    config.security_bits,
+                    min_num_challenges
+                )
+            }
+            _ => {}
+        }
+
+        if config.num_routed_wires > config.num_wires {
+            errors.push(format!(
+                "num_routed_wires ({}) exceeds num_wires ({})",
+                config.num_routed_wires, config.num_wires
+            ));
+        }
+
+        // Degree errors point at the first instance of the highest-degree gate.
+        let degree_location = || {
+            let (index, instance) = self
+                .gate_instances
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, instance)| instance.gate_ref.0.degree())?;
+            Some((index, instance.gate_ref.0.id()))
+        };
+        let with_degree_location = |message: String| match degree_location() {
+            Some((index, id)) => with_location(
+                message,
+                &format!("{} added", id),
+                self.source_locations.gate(index),
+            ),
+            None => message,
+        };
+
+        let min_quotient_degree_factor = (max_filtered_constraint_degree - 1).max(2);
+        if config.max_quotient_degree_factor < min_quotient_degree_factor {
+            errors.push(with_degree_location(format!(
+                "max_quotient_degree_factor ({}) is below the factor of {} needed by constraints of degree {}",
+                config.max_quotient_degree_factor,
+                min_quotient_degree_factor,
+                max_filtered_constraint_degree
+            )));
+        }
+        let max_quotient_degree_bits = config.max_quotient_degree_bits();
+        if log2_ceil(min_quotient_degree_factor) > max_quotient_degree_bits {
+            errors.push(with_degree_location(format!(
+                "rate_bits ({}) only supports quotient degree factors up to {}, but constraints of degree {} need {}",
+                fri_config.rate_bits,
+                1 << max_quotient_degree_bits,
+                max_filtered_constraint_degree,
+                min_quotient_degree_factor
+            )));
+        }
+
+        let lde_bits = fri_params.lde_bits();
+        if fri_config.cap_height > lde_bits {
+            errors.push(format!(
+                "cap_height ({}) exceeds the height of the LDE trees ({}) for degree 2^{}",
+                fri_config.cap_height, lde_bits, degree_bits
+            ));
+        }
+
+        if fri_params.total_arities() > degree_bits {
+            errors.push(format!(
+                "FRI total reduction arity ({}) exceeds degree_bits ({})",
+                fri_params.total_arities(),
+                degree_bits
+            ));
+        } else {
+            let mut layer_bits = lde_bits;
+            for (i, &arity_bits) in fri_params.reduction_arity_bits.iter().enumerate() {
+                if layer_bits < arity_bits + fri_config.commit_phase_cap_height {
+                    errors.push(format!(
+                        "commit_phase_cap_height ({}) exceeds the height of FRI layer {} ({})",
+                        fri_config.commit_phase_cap_height,
+                        i,
+                        layer_bits - arity_bits
+                    ));
+                    break;
+                }
+                layer_bits -= arity_bits;
+            }
+        }
+
+        if let Err(e) = fri_params.check_salt_size(D) {
+            errors.push(e.to_string());
+        }
+        if config.zero_knowledge
+            && config.salt_mode == SaltMode::Masked
+            && 1 << degree_bits <= fri_config.num_query_rounds
+        {
+            errors.push(format!(
+                "masked salting needs a degree (2^{}) above the number of FRI queries ({})",
+                degree_bits, fri_config.num_query_rounds
+            ));
+        }
+
+        // The sizes of the oracles can only be derived from consistent parameters.
+        let num_challenges = config
+            .num_challenges
+            .or_else(|| config.min_num_challenges::<F>(degree_bits));
+        if let (true, Some(num_challenges)) = (errors.is_empty(), num_challenges) {
+            let quotient_degree_factor =
+                choose_quotient_degree_factor(config, max_filtered_constraint_degree);
+            let num_partial_products =
+                num_partial_products(config.num_routed_wires, quotient_degree_factor);
+            let num_oracle_polys = [
+                num_constants + config.num_routed_wires,
+                config.num_wires,
+                num_challenges * (1 + num_partial_products),
+                num_challenges * quotient_degree_factor,
+            ];
+            for (i, opening) in self.extra_openings.iter().enumerate() {
+                for p in &opening.polynomials {
+                    if p.polynomial_index >= num_oracle_polys[p.oracle_index] {
+                        errors.push(with_location(
+                            format!(
+                                "extra opening of polynomial {} of oracle {}, which has only {}",
+                                p.polynomial_index,
+                                p.oracle_index,
+                                num_oracle_polys[p.oracle_index]
+                            ),
+                            "requested",
+                            self.source_locations.extra_opening(i),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Invalid circuit parameters:\n- {}",
+                errors.join("\n- ")
+            ))
+        }
+    }
+
     /// Builds a "full circuit", with both prover and verifier data.
-    pub fn build<C: GenericConfig<D, F = F>>(mut self) -> CircuitData<F, C, D>
+    ///
+    /// # Panics
+    /// If the config is inconsistent with itself or with the circuit; see `try_build`.
+    pub fn build<C: GenericConfig<D, F = F>>(self) -> CircuitData<F, C, D>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds a "full circuit", with both prover and verifier data, or fails with an error listing
+    /// every relation between the config, the FRI parameters and the circuit's constraint degrees
+    /// which is violated. These are checked first, once the gates hashing the public inputs are
+    /// added, before the circuit is blinded and padded or any polynomial is computed.
+    pub fn try_build<C: GenericConfig<D, F = F>>(mut self) -> Result<CircuitData<F, C, D>>
     where
         [(); C::Hasher::HASH_SIZE]:,
     {
+        let span = info_span!(
+            "build",
+            num_gates = field::Empty,
+            degree_bits = field::Empty,
+            num_gate_types = self.gates.len(),
+            num_constants = field::Empty,
+            num_public_inputs = self.public_inputs.len(),
+            num_challenges = field::Empty,
+            quotient_degree_factor = field::Empty,
+            num_generators = field::Empty,
+        )
+        .entered();
         let mut timing = TimingTree::new("preprocess", Level::Trace);
         let start = Instant::now();
-        let rate_bits = self.config.fri_config.rate_bits;
-
-        self.fill_batched_gates();
+        if let Some(observer) = self.transcript_observer.take() {
+            let entries = std::mem::take(&mut self.transcript);
+            if !entries.is_empty() {
+                self.add_simple_generator(TranscriptGenerator { entries, observer });
+            }
+        }
 
         // Hash the public inputs, and route them to a `PublicInputGate` which will enforce that
         // those hash wires match the claimed public inputs.
         let num_public_inputs = self.public_inputs.len();
-        let public_inputs_hash =
-            self.hash_n_to_hash_no_pad::<C::InnerHasher>(self.public_inputs.clone());
+        let public_inputs = self.public_inputs.clone();
+        let public_input_groups = self.public_input_groups.clone();
+        let public_inputs_hash = hash_grouped_public_inputs_circuit::<F, C::PublicInputsHasher, D>(
+            &mut self,
+            public_inputs,
+            &public_input_groups,
+        );
         let pi_gate = self.add_gate(PublicInputGate, vec![]);
         for (&hash_part, wire) in public_inputs_hash
             .elements
@@ -624,33 +1179,51 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         {
             self.connect(hash_part, Target::wire(pi_gate, wire))
         }
+        // Hashers such as Keccak use batched operations, so the batched gates are filled last.
+        self.fill_batched_gates();
 
         info!(
             "Degree before blinding & padding: {}",
             self.gate_instances.len()
         );
-        self.blind_and_pad();
-        let degree = self.gate_instances.len();
-        info!("Degree after blinding & padding: {}", degree);
+        span.record("num_gates", self.gate_instances.len());
+        let degree = self.padded_degree();
         let degree_bits = log2_strict(degree);
         let fri_params = self.fri_params(degree_bits);
-        assert!(
-            fri_params.total_arities() <= degree_bits,
-            "FRI total reduction arity is too large.",
-        );
+        // Padding adds `NoopGate`s, if it adds any gates.
+        let mut gates = self.gates.clone();
+        if degree > self.gate_instances.len() {
+            gates.insert(GateRef::new(NoopGate));
+        }
+        let (gate_tree, max_filtered_constraint_degree, num_constants) =
+            Tree::from_gates(gates.into_iter().collect());
+        self.check_params(
+            degree_bits,
+            &fri_params,
+            max_filtered_constraint_degree,
+            num_constants,
+        )?;
+
+        self.blind_and_pad();
+        debug_assert_eq!(self.gate_instances.len(), degree);
+        info!("Degree after blinding & padding: {}", degree);
+        if self.config.num_challenges.is_none() {
+            self.config.num_challenges = self.config.min_num_challenges::<F>(degree_bits);
+            debug!(
+                "Number of challenges set to: {:?}.",
+                self.config.num_challenges
+            );
+        }
+        span.record("degree_bits", degree_bits);
+        span.record("num_challenges", self.config.num_challenges);
 
-        let gates = self.gates.iter().cloned().collect();
-        let (gate_tree, max_filtered_constraint_degree, num_constants) = Tree::from_gates(gates);
         let prefixed_gates = PrefixedGate::from_tree(gate_tree);
 
-        // `quotient_degree_factor` has to be between `max_filtered_constraint_degree-1` and `1<<rate_bits`.
-        // We find the value that minimizes `num_partial_product + quotient_degree_factor`.
-        let min_quotient_degree_factor = (max_filtered_constraint_degree - 1).max(2);
-        let max_quotient_degree_factor = self.config.max_quotient_degree_factor.min(1 << rate_bits);
-        let quotient_degree_factor = (min_quotient_degree_factor..=max_quotient_degree_factor)
-            .min_by_key(|&q| num_partial_products(self.config.num_routed_wires, q) + q)
-            .unwrap();
+        let quotient_degree_factor =
+            choose_quotient_degree_factor(&self.config, max_filtered_constraint_degree);
         debug!("Quotient degree factor set to: {}.", quotient_degree_factor);
+        span.record("num_constants", num_constants);
+        span.record("quotient_degree_factor", quotient_degree_factor);
 
         let subgroup = F::two_adic_subgroup(degree_bits);
 
@@ -668,31 +1241,54 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
 
         // Precompute FFT roots.
-        let max_fft_points = 1 << (degree_bits + max(rate_bits, log2_ceil(quotient_degree_factor)));
-        let fft_root_table = fft_root_table(max_fft_points);
+        let max_rate_bits = [
+            PlonkOracle::CONSTANTS_SIGMAS,
+            PlonkOracle::WIRES,
+            PlonkOracle::ZS_PARTIAL_PRODUCTS,
+            PlonkOracle::QUOTIENT,
+        ]
+        .into_iter()
+        .map(|oracle| self.config.oracle_rate_bits(oracle))
+        .max()
+        .unwrap();
+        let max_fft_points =
+            1 << (degree_bits + max(max_rate_bits, log2_ceil(quotient_degree_factor)));
+        let fft_backend = CpuFftBackend::with_root_table(fft_root_table(max_fft_points));
 
         let constants_sigmas_vecs = [constant_vecs, sigma_vecs.clone()].concat();
-        let constants_sigmas_commitment = PolynomialBatch::from_values(
-            constants_sigmas_vecs,
-            rate_bits,
-            PlonkOracle::CONSTANTS_SIGMAS.blinding,
-            self.config.fri_config.cap_height,
-            &mut timing,
-            Some(&fft_root_table),
-        );
+        let pcs_prover_params = FriProverParams {
+            fft_backend: Box::new(fft_backend),
+            pow_grinder: Box::new(CpuGrinder),
+        };
+        let constants_sigmas_commitment =
+            <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::commit_values(
+                constants_sigmas_vecs,
+                self.config.fri_oracles()[PlonkOracle::CONSTANTS_SIGMAS.index],
+                &fri_params,
+                &pcs_prover_params,
+                None,
+                &mut PcsProverContext {
+                    timing: &mut timing,
+                    monitor: &ProvingMonitor::default(),
+                    rng: &mut thread_rng(),
+                    stream: None,
+                },
+            );
 
-        let constants_sigmas_cap = constants_sigmas_commitment.merkle_tree.cap.clone();
+        let constants_sigmas_cap = <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::commitment(
+            &constants_sigmas_commitment,
+        );
         let verifier_only = VerifierOnlyCircuitData {
             constants_sigmas_cap: constants_sigmas_cap.clone(),
         };
 
-        // Add gate generators.
-        self.add_generators(
+        // Add gate generators. Most gates have at least one, so reserve that many up front.
+        self.generators.reserve(self.gate_instances.len());
+        self.generators.extend(
             self.gate_instances
                 .iter()
                 .enumerate()
-                .flat_map(|(index, gate)| gate.gate_ref.0.generators(index, &gate.constants))
-                .collect(),
+                .flat_map(|(index, gate)| gate.gate_ref.0.generators(index, &gate.constants)),
         );
 
         // Index generator indices by their watched targets.
@@ -712,6 +1308,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             indices.shrink_to_fit();
         }
 
+        span.record("num_generators", self.generators.len());
+        let mut source_locations = self.source_locations;
+        source_locations.set_regions(self.regions);
         let prover_only = ProverOnlyCircuitData {
             generators: self.generators,
             generator_indices_by_watches,
@@ -720,8 +1319,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             subgroup,
             public_inputs: self.public_inputs,
             marked_targets: self.marked_targets,
+            blinding_targets: self.blinding_targets,
             representative_map: forest.parents,
-            fft_root_table: Some(fft_root_table),
+            source_locations,
+            pcs_prover_params,
+            deterministic_seed: None,
+            transcript_observer: None,
         };
 
         // The HashSet of gates will have a non-deterministic order. When converting to a Vec, we
@@ -755,18 +1358,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             num_constants,
             num_virtual_targets: self.virtual_target_index,
             num_public_inputs,
+            public_input_groups,
             k_is,
             num_partial_products,
+            extra_openings: self.extra_openings,
             circuit_digest,
         };
+        debug_assert!(common.extra_openings.iter().all(|opening| opening
+            .polynomials
+            .iter()
+            .all(|p| p.polynomial_index < common.num_oracle_polys(p.oracle_index))));
 
         timing.print();
         debug!("Building circuit took {}s", start.elapsed().as_secs_f32());
-        CircuitData {
+        Ok(CircuitData {
             prover_only,
             verifier_only,
             common,
-        }
+        })
     }
 
     /// Builds a "prover circuit", with data needed to generate proofs but not verify them.
@@ -792,18 +1401,37 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         [(); C::Hasher::HASH_SIZE]:,
     {
         // TODO: Can skip parts of this.
-        let CircuitData {
-            verifier_only,
-            common,
-            ..
-        } = self.build();
-        VerifierCircuitData {
-            verifier_only,
-            common,
-        }
+        self.build().verifier_data()
     }
 }
 
+/// The free slots of arithmetic gates, ordered by their constants rather than by the map's
+/// iteration order, so that filling them is reproducible.
+fn sorted_by_constants<F: RichField>(
+    free: &HashMap<(F, F), (usize, usize)>,
+) -> Vec<((F, F), (usize, usize))> {
+    let mut free = free.clone().into_iter().collect::<Vec<_>>();
+    free.sort_unstable_by_key(|((c0, c1), _)| (c0.to_canonical_u64(), c1.to_canonical_u64()));
+    free
+}
+
+/// Picks the quotient degree factor for a circuit. It has to be between
+/// `max_filtered_constraint_degree - 1` and `1 << rate_bits`, for the smallest rate of the oracles
+/// which the quotient is computed from; we find the value that minimizes
+/// `num_partial_products + quotient_degree_factor`.
+fn choose_quotient_degree_factor(
+    config: &CircuitConfig,
+    max_filtered_constraint_degree: usize,
+) -> usize {
+    let min_quotient_degree_factor = (max_filtered_constraint_degree - 1).max(2);
+    let max_quotient_degree_factor = config
+        .max_quotient_degree_factor
+        .min(1 << config.max_quotient_degree_bits());
+    (min_quotient_degree_factor..=max_quotient_degree_factor)
+        .min_by_key(|&q| num_partial_products(config.num_routed_wires, q) + q)
+        .unwrap()
+}
+
 /// Various gate types can contain multiple copies in a single Gate. This helper struct lets a
 /// CircuitBuilder track such gates that are currently being "filled up."
 pub struct BatchedGates<F: RichField + Extendable<D>, const D: usize> {
@@ -831,6 +1459,12 @@ pub struct BatchedGates<F: RichField + Extendable<D>, const D: usize> {
     pub(crate) current_u32_arithmetic_gate: Option<(usize, usize)>,
     /// The `U32SubtractionGate` currently being filled (so new u32 subtraction operations will be added to this gate before creating a new one)
     pub(crate) current_u32_subtraction_gate: Option<(usize, usize)>,
+    /// The `U64AdditionGate` currently being filled (so new u64 addition operations will be added to this gate before creating a new one)
+    pub(crate) current_u64_addition_gate: Option<(usize, usize)>,
+    /// The `U64MultiplicationGate` currently being filled (so new u64 multiplication operations will be added to this gate before creating a new one)
+    pub(crate) current_u64_multiplication_gate: Option<(usize, usize)>,
+    /// The `ConditionalArithmeticGate` currently being filled (so new conditional operations will be added to this gate before creating a new one)
+    pub(crate) current_conditional_arithmetic_gate: Option<(usize, usize)>,
 
     /// An available `ConstantGate` instance, if any.
     pub(crate) free_constant: Option<(usize, usize)>,
@@ -847,6 +1481,9 @@ impl<F: RichField + Extendable<D>, const D: usize> BatchedGates<F, D> {
             free_u32_add_many: HashMap::new(),
             current_u32_arithmetic_gate: None,
             current_u32_subtraction_gate: None,
+            current_u64_addition_gate: None,
+            current_u64_multiplication_gate: None,
+            current_conditional_arithmetic_gate: None,
             free_constant: None,
         }
     }
@@ -1072,6 +1709,63 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         (gate_index, copy)
     }
 
+    pub(crate) fn find_u64_addition_gate(&mut self) -> (usize, usize) {
+        let (gate_index, copy) = match self.batched_gates.current_u64_addition_gate {
+            None => {
+                let gate = U64AdditionGate::new_from_config(&self.config);
+                let gate_index = self.add_gate(gate, vec![]);
+                (gate_index, 0)
+            }
+            Some((gate_index, copy)) => (gate_index, copy),
+        };
+
+        if copy == U64AdditionGate::<F, D>::num_ops(&self.config) - 1 {
+            self.batched_gates.current_u64_addition_gate = None;
+        } else {
+            self.batched_gates.current_u64_addition_gate = Some((gate_index, copy + 1));
+        }
+
+        (gate_index, copy)
+    }
+
+    pub(crate) fn find_u64_multiplication_gate(&mut self) -> (usize, usize) {
+        let (gate_index, copy) = match self.batched_gates.current_u64_multiplication_gate {
+            None => {
+                let gate = U64MultiplicationGate::new_from_config(&self.config);
+                let gate_index = self.add_gate(gate, vec![]);
+                (gate_index, 0)
+            }
+            Some((gate_index, copy)) => (gate_index, copy),
+        };
+
+        if copy == U64MultiplicationGate::<F, D>::num_ops(&self.config) - 1 {
+            self.batched_gates.current_u64_multiplication_gate = None;
+        } else {
+            self.batched_gates.current_u64_multiplication_gate = Some((gate_index, copy + 1));
+        }
+
+        (gate_index, copy)
+    }
+
+    pub(crate) fn find_conditional_arithmetic_gate(&mut self) -> (usize, usize) {
+        let (gate_index, copy) = match self.batched_gates.current_conditional_arithmetic_gate {
+            None => {
+                let gate = ConditionalArithmeticGate::new_from_config(&self.config);
+                let gate_index = self.add_gate(gate, vec![]);
+                (gate_index, 0)
+            }
+            Some((gate_index, copy)) => (gate_index, copy),
+        };
+
+        if copy == ConditionalArithmeticGate::num_ops(&self.config) - 1 {
+            self.batched_gates.current_conditional_arithmetic_gate = None;
+        } else {
+            self.batched_gates.current_conditional_arithmetic_gate = Some((gate_index, copy + 1));
+        }
+
+        (gate_index, copy)
+    }
+
     /// Returns the gate index and copy index of a free `ConstantGate` slot, potentially adding a
     /// new `ConstantGate` if needed.
     fn constant_gate_instance(&mut self) -> (usize, usize) {
@@ -1096,7 +1790,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// `ArithmeticGate` are run.
     fn fill_base_arithmetic_gates(&mut self) {
         let zero = self.zero();
-        for ((c0, c1), (_gate, i)) in self.batched_gates.free_base_arithmetic.clone() {
+        for ((c0, c1), (_gate, i)) in sorted_by_constants(&self.batched_gates.free_base_arithmetic)
+        {
             for _ in i..ArithmeticGate::num_ops(&self.config) {
                 // If we directly wire in zero, an optimization will skip doing anything and return
                 // zero. So we pass in a virtual target and connect it to zero afterward.
@@ -1112,7 +1807,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// `ArithmeticExtensionGenerator`s are run.
     fn fill_arithmetic_gates(&mut self) {
         let zero = self.zero_extension();
-        for ((c0, c1), (_gate, i)) in self.batched_gates.free_arithmetic.clone() {
+        for ((c0, c1), (_gate, i)) in sorted_by_constants(&self.batched_gates.free_arithmetic) {
             for _ in i..ArithmeticExtensionGate::<D>::num_ops(&self.config) {
                 // If we directly wire in zero, an optimization will skip doing anything and return
                 // zero. So we pass in a virtual target and connect it to zero afterward.
@@ -1128,7 +1823,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// `ArithmeticExtensionGenerator`s are run.
     fn fill_mul_gates(&mut self) {
         let zero = self.zero_extension();
-        for (c0, (_gate, i)) in self.batched_gates.free_mul.clone() {
+        let mut free_mul = self
+            .batched_gates
+            .free_mul
+            .clone()
+            .into_iter()
+            .collect::<Vec<_>>();
+        free_mul.sort_unstable_by_key(|(c0, _)| c0.to_canonical_u64());
+        for (c0, (_gate, i)) in free_mul {
             for _ in i..MulExtensionGate::<D>::num_ops(&self.config) {
                 // If we directly wire in zero, an optimization will skip doing anything and return
                 // zero. So we pass in a virtual target and connect it to zero afterward.
@@ -1144,7 +1846,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// `RandomAccessGenerator`s are run.
     fn fill_random_access_gates(&mut self) {
         let zero = self.zero();
-        for (bits, (_, i)) in self.batched_gates.free_random_access.clone() {
+        let mut free_random_access = self
+            .batched_gates
+            .free_random_access
+            .clone()
+            .into_iter()
+            .collect::<Vec<_>>();
+        free_random_access.sort_unstable();
+        for (bits, (_, i)) in free_random_access {
             let max_copies =
                 RandomAccessGate::<F, D>::new_from_config(&self.config, bits).num_copies;
             for _ in i..max_copies {
@@ -1184,7 +1893,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// `U32AddManyGenerator`s are run.
     fn fill_u32_add_many_gates(&mut self) {
         let zero = self.zero_u32();
-        for (num_addends, (_, i)) in self.batched_gates.free_u32_add_many.clone() {
+        let mut free_u32_add_many = self
+            .batched_gates
+            .free_u32_add_many
+            .clone()
+            .into_iter()
+            .collect::<Vec<_>>();
+        free_u32_add_many.sort_unstable();
+        for (num_addends, (_, i)) in free_u32_add_many {
             let max_copies =
                 U32AddManyGate::<F, D>::new_from_config(&self.config, num_addends).num_ops;
             for _ in i..max_copies {
@@ -1228,7 +1944,50 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
-    fn fill_batched_gates(&mut self) {
+    /// Fill the remaining unused U64 addition operations with zeros, so that all
+    /// `U64AdditionGenerator`s are run.
+    fn fill_u64_addition_gates(&mut self) {
+        let zero = self.zero_u64();
+        let _false = self._false();
+        if let Some((_gate_index, copy)) = self.batched_gates.current_u64_addition_gate {
+            for _i in copy..U64AdditionGate::<F, D>::num_ops(&self.config) {
+                let dummy = self.add_virtual_u64_target();
+                self.add_u64_with_carry(dummy, dummy, _false);
+                self.connect_u64(dummy, zero);
+            }
+        }
+    }
+
+    /// Fill the remaining unused U64 multiplication operations with zeros, so that all
+    /// `U64MultiplicationGenerator`s are run.
+    fn fill_u64_multiplication_gates(&mut self) {
+        let zero = self.zero_u64();
+        if let Some((_gate_index, copy)) = self.batched_gates.current_u64_multiplication_gate {
+            for _i in copy..U64MultiplicationGate::<F, D>::num_ops(&self.config) {
+                let dummy = self.add_virtual_u64_target();
+                self.mul_u64(dummy, dummy);
+                self.connect_u64(dummy, zero);
+            }
+        }
+    }
+
+    /// Fill the remaining unused conditional arithmetic operations with zeros, so that all
+    /// `ConditionalArithmeticGenerator`s are run.
+    fn fill_conditional_arithmetic_gates(&mut self) {
+        let zero = self.zero();
+        if let Some((_gate_index, copy)) = self.batched_gates.current_conditional_arithmetic_gate {
+            for _ in copy..ConditionalArithmeticGate::num_ops(&self.config) {
+                // If we directly wire in constants, an optimization will skip the gate. So we pass
+                // in a virtual target and connect it to zero afterward.
+                let dummy = self.add_virtual_target();
+                let selector = BoolTarget::new_unsafe(dummy);
+                self.select_mul_add(selector, dummy, dummy, dummy, dummy);
+                self.connect(dummy, zero);
+            }
+        }
+    }
+
+    pub(crate) fn fill_batched_gates(&mut self) {
         self.fill_arithmetic_gates();
         self.fill_base_arithmetic_gates();
         self.fill_mul_gates();
@@ -1237,5 +1996,377 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.fill_u32_add_many_gates();
         self.fill_u32_arithmetic_gates();
         self.fill_u32_subtraction_gates();
+        self.fill_u64_addition_gates();
+        self.fill_u64_multiplication_gates();
+        self.fill_conditional_arithmetic_gates();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::extension_field::FieldExtension;
+    use plonky2_field::field_types::Field;
+
+    use crate::fri::SaltMode;
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::plonk_common::PlonkOracle;
+    use crate::plonk::region::CircuitRegion;
+    use crate::util::serialization::Buffer;
+    use crate::with_context;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type FF = <C as GenericConfig<D>>::FE;
+
+    #[test]
+    fn test_write_gate_counts_collapsed() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        builder.add_gate(NoopGate, vec![]);
+        with_context!(builder, "outer", {
+            builder.add_gate(NoopGate, vec![]);
+            with_context!(builder, "inner; nested", {
+                builder.add_gate(NoopGate, vec![]);
+                builder.add_gate(NoopGate, vec![]);
+            });
+        });
+        with_context!(builder, "empty", {});
+
+        let mut out = Vec::new();
+        builder.write_gate_counts_collapsed(&mut out)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "root 1\nroot;outer 1\nroot;outer;inner, nested 2\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_source_locations() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let (target, target_line) = (builder.add_virtual_target(), line!());
+        let (gate, gate_line) = (builder.add_gate(NoopGate, vec![]), line!());
+        let location = |line| format!("(created at {}:{}:", file!(), line);
+        assert!(builder
+            .source_locations
+            .describe(target)
+            .contains(&location(target_line)));
+        assert!(builder
+            .source_locations
+            .describe(Target::wire(gate, 0))
+            .contains(&location(gate_line)));
+    }
+
+    #[test]
+    #[should_panic(expected = "was set twice with different values")]
+    fn test_conflicting_copy() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        builder.connect(x, y);
+        pw.set_target(x, F::ONE);
+        pw.set_target(y, F::TWO);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "isn't routable: Wire(Wire { gate: 0, input: 134 }) (created at")]
+    fn test_unroutable_copy() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let gate = builder.add_gate(NoopGate, vec![]);
+        let x = builder.add_virtual_target();
+        builder.connect(x, Target::wire(gate, 134));
+    }
+
+    #[test]
+    fn test_regions() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let before = builder.add_virtual_target();
+        let (outer_target, inner_target) = builder.region("outer", |builder| {
+            let outer_target = builder.add_virtual_target();
+            builder.register_public_input(outer_target);
+            let inner_target = builder.region("inner", |builder| {
+                builder.add_gate(NoopGate, vec![]);
+                builder.add_virtual_target()
+            });
+            (outer_target, inner_target)
+        });
+
+        let regions = builder.regions();
+        assert_eq!(regions.len(), 2);
+        let (outer, inner) = (&regions[0], &regions[1]);
+        assert_eq!((outer.parent, inner.parent), (None, Some(0)));
+        assert_eq!((outer.gates.clone(), inner.gates.clone()), (0..1, 0..1));
+        assert_eq!(outer.public_inputs, 0..1);
+        assert!(inner.public_inputs.is_empty());
+        assert!(!outer.contains(before));
+        assert!(outer.contains(outer_target) && !inner.contains(outer_target));
+        assert!(outer.contains(inner_target) && inner.contains(inner_target));
+        assert!(inner.contains(Target::wire(0, 0)));
+    }
+
+    #[test]
+    fn test_region_serialization() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        let y = builder.region("outputs", |builder| {
+            let y = builder.add_virtual_target();
+            builder.register_public_input(y);
+            y
+        });
+        let region = builder.regions()[0].clone();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::ONE);
+        pw.set_target(y, F::TWO);
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        // Only the region's layout and its public inputs are stored.
+        let region_bytes = serde_cbor::to_vec(&region)?;
+        let mut buffer = Buffer::new(Vec::new());
+        buffer.write_region_public_inputs(&region, &proof.public_inputs)?;
+
+        let region: CircuitRegion = serde_cbor::from_slice(&region_bytes)?;
+        assert_eq!(region.public_inputs, 1..2);
+        let mut buffer = Buffer::new(buffer.bytes());
+        assert_eq!(buffer.read_field_vec_with_len::<F>()?, vec![F::TWO]);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "in region outer/inner")]
+    fn test_region_in_witness_error() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let (x, y) = builder.region("outer", |builder| {
+            builder.region("inner", |builder| {
+                (builder.add_virtual_target(), builder.add_virtual_target())
+            })
+        });
+        builder.connect(x, y);
+        pw.set_target(x, F::ONE);
+        pw.set_target(y, F::TWO);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    fn test_recommend_config() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let build = |config: CircuitConfig| {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let x = builder.add_virtual_target();
+            let mut acc = x;
+            for _ in 0..100 {
+                acc = builder.mul_add(acc, x, x);
+            }
+            builder
+        };
+
+        let recommended = build(config.clone()).recommend_config();
+        assert!(recommended.num_wires < config.num_wires);
+        assert!(recommended.num_routed_wires <= config.num_routed_wires);
+        assert!(recommended.max_quotient_degree_factor <= config.max_quotient_degree_factor);
+
+        let mut pw = PartialWitness::new();
+        let builder = build(recommended);
+        pw.set_target(Target::VirtualTarget { index: 0 }, F::TWO);
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_deterministic_circuit_digest() {
+        let build = || {
+            let config = CircuitConfig::standard_recursion_config();
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            // Base and extension arithmetic gates have the same degree and number of constants,
+            // so their relative order in the gate tree must not depend on hashing.
+            let x = builder.add_virtual_target();
+            let y = builder.mul_add(x, x, x);
+            let z = builder.add_virtual_extension_target();
+            let w = builder.mul_add_extension(z, z, z);
+            let v = builder.mul_extension(w, z);
+            builder.register_public_input(y);
+            builder.register_public_inputs(&v.0);
+            builder.build::<C>()
+        };
+
+        let data = build();
+        for _ in 0..5 {
+            let other = build();
+            assert_eq!(data.common.circuit_digest, other.common.circuit_digest);
+            assert_eq!(
+                data.verifier_only.constants_sigmas_cap,
+                other.verifier_only.constants_sigmas_cap
+            );
+        }
+    }
+
+    #[test]
+    fn test_bulk_connect() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let values = F::rand_vec(100);
+        let xs = builder.add_virtual_targets(values.len());
+        let ys = builder.add_virtual_targets(values.len());
+        for (&x, &v) in xs.iter().zip(&values) {
+            pw.set_target(x, v);
+        }
+        builder.connect_slices(&xs, &ys);
+        builder.register_public_inputs(&ys);
+        let diffs = xs
+            .iter()
+            .zip(&ys)
+            .map(|(&x, &y)| builder.sub(x, y))
+            .collect::<Vec<_>>();
+
+        // The zero assertions only add copy constraints, one per target.
+        builder.zero();
+        let num_gates = builder.num_gates();
+        let num_copy_constraints = builder.copy_constraints.len();
+        builder.assert_zero_many(&diffs);
+        assert_eq!(builder.num_gates(), num_gates);
+        assert_eq!(
+            builder.copy_constraints.len(),
+            num_copy_constraints + diffs.len()
+        );
+
+        let ext_values = FF::rand_vec(10);
+        let xs_ext = builder.add_virtual_extension_targets(ext_values.len());
+        let ys_ext = builder.add_virtual_extension_targets(ext_values.len());
+        for (&x, &v) in xs_ext.iter().zip(&ext_values) {
+            pw.set_extension_target(x, v);
+        }
+        builder.connect_extension_slices(&xs_ext, &ys_ext);
+        for y in &ys_ext {
+            builder.register_public_inputs(&y.0);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        // The connected targets hold the values of the targets they were connected to.
+        let expected_public_inputs = values
+            .iter()
+            .copied()
+            .chain(
+                ext_values
+                    .iter()
+                    .flat_map(FieldExtension::<D>::to_basefield_array),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(proof.public_inputs, expected_public_inputs);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_try_build_lists_violations() {
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.max_quotient_degree_factor = 1;
+        config.fri_config.cap_height = 20;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        builder.add_gate(NoopGate, vec![]);
+
+        let line = line!() + 1;
+        builder.add_gate(
+            ArithmeticGate::new_from_config(&builder.config),
+            vec![F::ONE, F::ONE],
+        );
+
+        let err = builder.try_build::<C>().err().unwrap().to_string();
+        assert!(err.contains("max_quotient_degree_factor (1)"));
+        if cfg!(debug_assertions) {
+            assert!(err.contains(&format!("added at {}:{}:", file!(), line)));
+        }
+        assert!(err.contains("cap_height (20)"));
+    }
+
+    #[test]
+    fn test_try_build_rejects_extra_opening_index() {
+        let config = CircuitConfig::standard_recursion_config();
+        let num_wires = config.num_wires;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        builder.add_gate(NoopGate, vec![]);
+        let line = line!() + 1;
+        builder.add_extra_opening(F::NEG_ONE, &[(PlonkOracle::WIRES, num_wires)]);
+
+        let err = builder.try_build::<C>().err().unwrap().to_string();
+        assert!(err.contains(&format!(
+            "extra opening of polynomial {} of oracle {}",
+            num_wires,
+            PlonkOracle::WIRES.index
+        )));
+        if cfg!(debug_assertions) {
+            assert!(err.contains(&format!("(requested at {}:{}:", file!(), line)));
+        }
+    }
+
+    #[test]
+    fn test_check_params_rejects_masked_salt_size() {
+        let builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let degree_bits = 10;
+        let fri_config = &builder.config.fri_config;
+        assert!(builder
+            .check_params(
+                degree_bits,
+                &fri_config.fri_params(degree_bits, D, SaltMode::Masked),
+                3,
+                0
+            )
+            .is_ok());
+
+        let err = builder
+            .check_params(
+                degree_bits,
+                &fri_config.fri_params(degree_bits, D + 1, SaltMode::Masked),
+                3,
+                0,
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("masked salting needs a salt size of 2"));
+    }
+
+    #[test]
+    fn test_check_recursion_config() {
+        let builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        assert!(builder.check_recursion_config::<C>(4).is_ok());
+
+        let config = CircuitConfig {
+            num_routed_wires: 20,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let builder = CircuitBuilder::<F, D>::new(config);
+        let err = builder.check_recursion_config::<C>(4).unwrap_err();
+        assert!(err.to_string().contains("routed wires are needed"));
     }
 }
@@ -1,31 +1,45 @@
 use std::collections::BTreeMap;
+use std::io::Write;
 use std::ops::{Range, RangeFrom};
 
-use anyhow::Result;
-use plonky2_field::extension_field::Extendable;
-use plonky2_field::fft::FftRootTable;
+use anyhow::{ensure, Result};
+use keccak_hash::keccak;
+use plonky2_field::extension_field::{Extendable, FieldExtension};
+use plonky2_util::{ceil_div_usize, log2_ceil};
+use rand::{thread_rng, CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
 use crate::field::field_types::Field;
-use crate::fri::oracle::PolynomialBatch;
-use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::fri::fft_backend::FftBackend;
+use crate::fri::grinding::{DeterministicCpuGrinder, PowGrinder};
+use crate::fri::lde_cache::LdeCache;
+use crate::fri::oracle::SALT_SIZE;
+use crate::fri::presets::{FriPreset, Preference};
 use crate::fri::structure::{
-    FriBatchInfo, FriBatchInfoTarget, FriInstanceInfo, FriInstanceInfoTarget, FriPolynomialInfo,
+    FriBatchInfo, FriBatchInfoTarget, FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo,
+    FriPolynomialInfo,
 };
-use crate::fri::{FriConfig, FriParams};
+use crate::fri::{FriConfig, FriParams, SaltMode};
 use crate::gates::gate::PrefixedGate;
-use crate::hash::hash_types::{MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::public_inputs::{hash_grouped_public_inputs, PublicInputGroup};
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::generator::WitnessGenerator;
+use crate::iop::source_locations::SourceLocations;
 use crate::iop::target::Target;
+use crate::iop::transcript::SharedTranscriptObserver;
 use crate::iop::witness::PartialWitness;
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::plonk_common::{PlonkOracle, FRI_ORACLES};
-use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::pcs::{PlonkPcsProverParams, PlonkProverData};
+use crate::plonk::plonk_common::{OracleBlinding, OracleRates, PlonkOracle};
+use crate::plonk::proof::{CompressedProofWithPublicInputs, Proof, ProofWithPublicInputs};
 use crate::plonk::prover::prove;
-use crate::plonk::verifier::verify;
+use crate::plonk::verifier::{verify, verify_with_public_inputs_hash};
 use crate::util::marking::MarkedTargets;
+use crate::util::progress::ProvingMonitor;
+use crate::util::serialization::{Encoding, ProofStream};
 use crate::util::timing::TimingTree;
 
 #[derive(Clone, Debug)]
@@ -38,9 +52,20 @@ pub struct CircuitConfig {
     pub use_base_arithmetic_gate: bool,
     pub security_bits: usize,
     /// The number of challenge points to generate, for IOPs that have soundness errors of (roughly)
-    /// `degree / |F|`.
-    pub num_challenges: usize,
+    /// `degree / |F|`. `None` lets `build` choose the fewest which reach `security_bits`, once the
+    /// degree is known; see `min_num_challenges`. The config of a built circuit always has it set.
+    pub num_challenges: Option<usize>,
     pub zero_knowledge: bool,
+    /// The oracles which are blinded when `zero_knowledge` is enabled.
+    pub blinding: OracleBlinding,
+    /// The number of random salt elements added to each Merkle leaf of a blinded oracle, when
+    /// `salt_mode` is `SaltMode::PerLeaf`.
+    pub salt_size: usize,
+    /// How blinded oracles are salted. `SaltMode::Masked` commits to a single masking polynomial
+    /// per blinded oracle instead of `salt_size` random columns, which gives smaller proofs.
+    pub salt_mode: SaltMode,
+    /// How many bits higher than FRI's rate each oracle is committed at.
+    pub oracle_rates: OracleRates,
     /// A cap on the quotient polynomial's degree factor. The actual degree factor is derived
     /// systematically, but will never exceed this value.
     pub max_quotient_degree_factor: usize,
@@ -54,10 +79,92 @@ impl Default for CircuitConfig {
 }
 
 impl CircuitConfig {
+    /// The number of challenge sets, which must have been chosen, as in the config of a built
+    /// circuit.
+    pub fn num_challenges(&self) -> usize {
+        self.num_challenges
+            .expect("The number of challenges is chosen when the circuit is built")
+    }
+
     pub fn num_advice_wires(&self) -> usize {
         self.num_wires - self.num_routed_wires
     }
 
+    /// The number of salt elements in the Merkle leaves of any blinded oracle, or zero if the
+    /// circuit is not zero-knowledge.
+    pub(crate) fn fri_salt_size<const D: usize>(&self) -> usize {
+        if !self.zero_knowledge {
+            return 0;
+        }
+        match self.salt_mode {
+            SaltMode::PerLeaf => self.salt_size,
+            SaltMode::Masked => D,
+        }
+    }
+
+    /// The number of salt elements in the Merkle leaves of the given oracle, or an error if it
+    /// isn't one of the prover's oracles.
+    pub(crate) fn oracle_salt_size<const D: usize>(&self, oracle: PlonkOracle) -> Result<usize> {
+        Ok(if self.blinding.is_blinded(oracle)? {
+            self.fri_salt_size::<D>()
+        } else {
+            0
+        })
+    }
+
+    /// The rate bits of the LDE which the given oracle is committed to.
+    pub(crate) fn oracle_rate_bits(&self, oracle: PlonkOracle) -> usize {
+        self.fri_config.rate_bits + self.oracle_rates.extra_rate_bits(oracle)
+    }
+
+    /// The largest rate bits which the quotient can be computed with, i.e. the log of the largest
+    /// quotient degree factor which the oracles' LDEs support.
+    pub(crate) fn max_quotient_degree_bits(&self) -> usize {
+        self.fri_config.rate_bits + self.oracle_rates.min_quotient_input_extra_rate_bits()
+    }
+
+    /// The bits of soundness given by each of the `num_challenges` independent sets of Plonk
+    /// challenges, in a circuit of degree `2^degree_bits` over `F`.
+    ///
+    /// The permutation challenges `beta` and `gamma` and the constraint combination challenge
+    /// `alpha` are drawn from the base field. By Schwartz-Zippel, a false permutation passes a set
+    /// of challenges with probability at most `n k / |F|`, for `n` gates and `k` routed wires, which
+    /// is the degree of the grand product in them; this dominates the `num_gate_constraints / |F|`
+    /// error of combining constraints with `alpha`. Independent sets multiply these errors, so each
+    /// gives `log2 |F| - log2(n k)` bits.
+    ///
+    /// The opening point `zeta` and FRI's challenges are drawn from the extension field instead,
+    /// and are only drawn once, so they don't depend on `num_challenges`.
+    pub fn challenge_soundness_bits<F: Field>(&self, degree_bits: usize) -> usize {
+        let field_bits = F::order().bits() as usize - 1;
+        field_bits.saturating_sub(degree_bits + log2_ceil(self.num_routed_wires))
+    }
+
+    /// The fewest challenge sets which give `security_bits` of soundness in a circuit of degree
+    /// `2^degree_bits` over `F`, or `None` if no number does because the field is too small.
+    pub fn min_num_challenges<F: Field>(&self, degree_bits: usize) -> Option<usize> {
+        match self.challenge_soundness_bits::<F>(degree_bits) {
+            0 => None,
+            bits => Some(ceil_div_usize(self.security_bits, bits).max(1)),
+        }
+    }
+
+    pub(crate) fn fri_oracles(&self) -> Vec<FriOracleInfo> {
+        let blinded = self.blinding.by_oracle();
+        [
+            PlonkOracle::CONSTANTS_SIGMAS,
+            PlonkOracle::WIRES,
+            PlonkOracle::ZS_PARTIAL_PRODUCTS,
+            PlonkOracle::QUOTIENT,
+        ]
+        .into_iter()
+        .map(|oracle| FriOracleInfo {
+            blinding: blinded[oracle.index],
+            extra_rate_bits: self.oracle_rates.extra_rate_bits(oracle),
+        })
+        .collect()
+    }
+
     /// A typical recursion config, without zero-knowledge, targeting ~100 bit security.
     pub fn standard_recursion_config() -> Self {
         Self {
@@ -66,16 +173,14 @@ impl CircuitConfig {
             constant_gate_size: 5,
             use_base_arithmetic_gate: true,
             security_bits: 100,
-            num_challenges: 2,
+            num_challenges: Some(2),
             zero_knowledge: false,
+            blinding: OracleBlinding::ALL,
+            salt_size: SALT_SIZE,
+            salt_mode: SaltMode::PerLeaf,
+            oracle_rates: OracleRates::UNIFORM,
             max_quotient_degree_factor: 8,
-            fri_config: FriConfig {
-                rate_bits: 3,
-                cap_height: 4,
-                proof_of_work_bits: 16,
-                reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
-                num_query_rounds: 28,
-            },
+            fri_config: FriPreset::Balanced.fri_config(),
         }
     }
 
@@ -92,6 +197,66 @@ impl CircuitConfig {
             ..Self::standard_recursion_config()
         }
     }
+
+    /// The standard recursion config, with the FRI configuration of the given preset. If the
+    /// preset's rate is too low for the quotient degree factor, as with `FriPreset::FastProver`,
+    /// the oracles which the quotient is computed from are committed at a higher rate.
+    pub fn fri_preset_config(preset: FriPreset) -> Self {
+        Self {
+            fri_config: preset.fri_config(),
+            ..Self::standard_recursion_config()
+        }
+        .with_quotient_input_rates()
+    }
+
+    /// Commits to the oracles which the quotient is computed from at a rate high enough for
+    /// `max_quotient_degree_factor`, where FRI's rate is lower.
+    fn with_quotient_input_rates(self) -> Self {
+        let extra_rate_bits =
+            log2_ceil(self.max_quotient_degree_factor).saturating_sub(self.fri_config.rate_bits);
+        Self {
+            oracle_rates: OracleRates::quotient_inputs(extra_rate_bits),
+            ..self
+        }
+    }
+
+    /// A config with at least `security_bits` bits of conjectured security over a 64-bit base field
+    /// and its extension of degree `D`, favouring `preference`. The rate, arities and caps come
+    /// from the preference's FRI preset; the number of queries and the proof-of-work bits are
+    /// derived from the target, and the number of challenges is left for `build` to derive. As in
+    /// `fri_preset_config`, oracles may be committed at a higher rate than FRI's.
+    ///
+    /// FRI's folding challenges from the extension field are counted as giving `64 D - 14` bits,
+    /// leaving room for the degrees of the polynomials they are checked against, which bounds the
+    /// target.
+    pub fn with_security_bits<const D: usize>(
+        security_bits: usize,
+        preference: Preference,
+    ) -> Self {
+        const FIELD_BITS: usize = 64;
+        const DEGREE_SLACK_BITS: usize = 14;
+        assert!(
+            security_bits <= FIELD_BITS * D - DEGREE_SLACK_BITS,
+            "The extension field of degree {} is too small for {} bits of security",
+            D,
+            security_bits
+        );
+
+        let mut fri_config = preference.fri_preset().fri_config();
+        fri_config.proof_of_work_bits = fri_config.proof_of_work_bits.min(security_bits as u32);
+        fri_config.num_query_rounds = ceil_div_usize(
+            security_bits - fri_config.proof_of_work_bits as usize,
+            fri_config.rate_bits,
+        )
+        .max(1);
+        Self {
+            security_bits,
+            num_challenges: None,
+            fri_config,
+            ..Self::standard_recursion_config()
+        }
+        .with_quotient_input_rates()
+    }
 }
 
 /// Circuit data required by the prover or the verifier.
@@ -104,31 +269,186 @@ pub struct CircuitData<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     CircuitData<F, C, D>
 {
+    /// Drops the prover's data, keeping what is needed to verify proofs.
+    pub fn verifier_data(self) -> VerifierCircuitData<F, C, D> {
+        VerifierCircuitData {
+            verifier_only: self.verifier_only,
+            common: self.common,
+        }
+    }
+
     pub fn prove(&self, inputs: PartialWitness<F>) -> Result<ProofWithPublicInputs<F, C, D>>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        self.prove_with_monitor(inputs, &ProvingMonitor::default())
+    }
+
+    /// Proves while reporting progress to `monitor`, failing if it's cancelled.
+    pub fn prove_with_monitor(
+        &self,
+        inputs: PartialWitness<F>,
+        monitor: &ProvingMonitor,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         prove(
             &self.prover_only,
             &self.common,
             inputs,
             &mut TimingTree::default(),
+            monitor,
+            None,
+            self.prover_only.rng().as_mut(),
+            None,
         )
     }
 
+    /// Proves while sampling all of the prover's randomness, i.e. the blinding wire values and the
+    /// salts, from `rng` rather than the thread-local generator. This must be a CSPRNG, as any
+    /// bias or predictability in its output may leak the witness.
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        inputs: PartialWitness<F>,
+        rng: &mut R,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        prove(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            &ProvingMonitor::default(),
+            None,
+            rng,
+            None,
+        )
+    }
+
+    /// Proves deterministically, sampling all of the prover's randomness from ChaCha20 keyed with
+    /// `seed`. The seed must be secret and never reused for another witness, e.g. derived with a
+    /// KDF from a secret key and the statement being proven, much like a deterministic signature
+    /// nonce.
+    pub fn prove_with_seed(
+        &self,
+        inputs: PartialWitness<F>,
+        seed: [u8; 32],
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        self.prove_with_rng(inputs, &mut ChaCha20Rng::from_seed(seed))
+    }
+
+    /// Proves while reusing the wire LDEs cached in `cache` for the wires whose values are
+    /// unchanged since the last proof of this circuit using it, and caches the new ones. This
+    /// speeds up proving a witness which differs from the previous one in a few columns.
+    pub fn prove_incremental(
+        &self,
+        inputs: PartialWitness<F>,
+        cache: &mut LdeCache<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        prove(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            &ProvingMonitor::default(),
+            Some(cache),
+            self.prover_only.rng().as_mut(),
+            None,
+        )
+    }
+
+    /// Proves while writing the proof to `writer`, in the format of `ProofWithPublicInputs::to_bytes`,
+    /// as the prover produces it. Each FRI query round, which make up most of a proof, is written as
+    /// soon as it's computed rather than held until the end, so sending the proof can overlap with
+    /// the rest of proving. If proving fails, what was written is only a prefix of a proof.
+    pub fn prove_to_writer<W: Write>(&self, inputs: PartialWitness<F>, writer: &mut W) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        let mut stream = ProofStream::new(writer, Encoding::Compact);
+        prove(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            &ProvingMonitor::default(),
+            None,
+            self.prover_only.rng().as_mut(),
+            Some(&mut stream),
+        )?;
+        Ok(stream.finish()?)
+    }
+
+    /// Sets the backend searching for FRI proof-of-work witnesses, e.g. to offload it to a GPU.
+    pub fn set_pow_grinder(&mut self, pow_grinder: Box<dyn PowGrinder<F, C::InnerHasher>>) {
+        self.prover_only.pcs_prover_params.pow_grinder = pow_grinder;
+    }
+
+    /// Sets the backend performing the prover's FFTs, e.g. to offload them to a GPU.
+    pub fn set_fft_backend(&mut self, fft_backend: Box<dyn FftBackend<F>>) {
+        self.prover_only.pcs_prover_params.fft_backend = fft_backend;
+    }
+
+    /// Makes proving deterministic, for golden-proof regression tests and differential testing:
+    /// all the prover's randomness is drawn from ChaCha20 keyed with `seed` afresh for each proof,
+    /// and proof-of-work witnesses are searched for with `DeterministicCpuGrinder`, so that the same
+    /// witness always gives byte-identical proofs. Randomness is then reused across proofs, which
+    /// breaks zero knowledge for different witnesses, so this is only meant for testing.
+    pub fn set_deterministic_seed(&mut self, seed: [u8; 32]) {
+        self.prover_only.deterministic_seed = Some(seed);
+        self.prover_only.pcs_prover_params.pow_grinder = Box::new(DeterministicCpuGrinder);
+    }
+
+    /// Reports the prover's Fiat-Shamir transcript to `observer` for each proof, e.g. to compare it
+    /// with the verifier's, given by `ProofWithPublicInputs::observe_transcript`.
+    pub fn set_transcript_observer(&mut self, observer: SharedTranscriptObserver<F>) {
+        self.prover_only.transcript_observer = Some(observer);
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         verify(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Verifies a proof given only the hash of its public inputs, e.g. when the caller holds a
+    /// digest of large public inputs rather than the inputs themselves.
+    pub fn verify_with_public_inputs_hash(
+        &self,
+        proof: Proof<F, C, D>,
+        public_inputs_hash: HashOut<F>,
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        verify_with_public_inputs_hash(proof, public_inputs_hash, &self.verifier_only, &self.common)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
     ) -> Result<()>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         compressed_proof_with_pis.verify(&self.verifier_only, &self.common)
     }
@@ -156,14 +476,145 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     pub fn prove(&self, inputs: PartialWitness<F>) -> Result<ProofWithPublicInputs<F, C, D>>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        self.prove_with_monitor(inputs, &ProvingMonitor::default())
+    }
+
+    /// Proves while reporting progress to `monitor`, failing if it's cancelled.
+    pub fn prove_with_monitor(
+        &self,
+        inputs: PartialWitness<F>,
+        monitor: &ProvingMonitor,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        prove(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            monitor,
+            None,
+            self.prover_only.rng().as_mut(),
+            None,
+        )
+    }
+
+    /// Proves while sampling all of the prover's randomness, i.e. the blinding wire values and the
+    /// salts, from `rng` rather than the thread-local generator. This must be a CSPRNG, as any
+    /// bias or predictability in its output may leak the witness.
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        inputs: PartialWitness<F>,
+        rng: &mut R,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        prove(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            &ProvingMonitor::default(),
+            None,
+            rng,
+            None,
+        )
+    }
+
+    /// Proves deterministically, sampling all of the prover's randomness from ChaCha20 keyed with
+    /// `seed`. The seed must be secret and never reused for another witness, e.g. derived with a
+    /// KDF from a secret key and the statement being proven, much like a deterministic signature
+    /// nonce.
+    pub fn prove_with_seed(
+        &self,
+        inputs: PartialWitness<F>,
+        seed: [u8; 32],
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        self.prove_with_rng(inputs, &mut ChaCha20Rng::from_seed(seed))
+    }
+
+    /// Proves while reusing the wire LDEs cached in `cache` for the wires whose values are
+    /// unchanged since the last proof of this circuit using it, and caches the new ones. This
+    /// speeds up proving a witness which differs from the previous one in a few columns.
+    pub fn prove_incremental(
+        &self,
+        inputs: PartialWitness<F>,
+        cache: &mut LdeCache<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         prove(
             &self.prover_only,
             &self.common,
             inputs,
             &mut TimingTree::default(),
+            &ProvingMonitor::default(),
+            Some(cache),
+            self.prover_only.rng().as_mut(),
+            None,
         )
     }
+
+    /// Proves while writing the proof to `writer`, in the format of `ProofWithPublicInputs::to_bytes`,
+    /// as the prover produces it. Each FRI query round, which make up most of a proof, is written as
+    /// soon as it's computed rather than held until the end, so sending the proof can overlap with
+    /// the rest of proving. If proving fails, what was written is only a prefix of a proof.
+    pub fn prove_to_writer<W: Write>(&self, inputs: PartialWitness<F>, writer: &mut W) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        let mut stream = ProofStream::new(writer, Encoding::Compact);
+        prove(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            &ProvingMonitor::default(),
+            None,
+            self.prover_only.rng().as_mut(),
+            Some(&mut stream),
+        )?;
+        Ok(stream.finish()?)
+    }
+
+    /// Sets the backend searching for FRI proof-of-work witnesses, e.g. to offload it to a GPU.
+    pub fn set_pow_grinder(&mut self, pow_grinder: Box<dyn PowGrinder<F, C::InnerHasher>>) {
+        self.prover_only.pcs_prover_params.pow_grinder = pow_grinder;
+    }
+
+    /// Sets the backend performing the prover's FFTs, e.g. to offload them to a GPU.
+    pub fn set_fft_backend(&mut self, fft_backend: Box<dyn FftBackend<F>>) {
+        self.prover_only.pcs_prover_params.fft_backend = fft_backend;
+    }
+
+    /// Makes proving deterministic, for golden-proof regression tests and differential testing:
+    /// all the prover's randomness is drawn from ChaCha20 keyed with `seed` afresh for each proof,
+    /// and proof-of-work witnesses are searched for with `DeterministicCpuGrinder`, so that the same
+    /// witness always gives byte-identical proofs. Randomness is then reused across proofs, which
+    /// breaks zero knowledge for different witnesses, so this is only meant for testing.
+    pub fn set_deterministic_seed(&mut self, seed: [u8; 32]) {
+        self.prover_only.deterministic_seed = Some(seed);
+        self.prover_only.pcs_prover_params.pow_grinder = Box::new(DeterministicCpuGrinder);
+    }
+
+    /// Reports the prover's Fiat-Shamir transcript to `observer` for each proof, e.g. to compare it
+    /// with the verifier's, given by `ProofWithPublicInputs::observe_transcript`.
+    pub fn set_transcript_observer(&mut self, observer: SharedTranscriptObserver<F>) {
+        self.prover_only.transcript_observer = Some(observer);
+    }
 }
 
 /// Circuit data required by the prover.
@@ -183,19 +634,141 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         verify(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Verifies a proof given only the hash of its public inputs, e.g. when the caller holds a
+    /// digest of large public inputs rather than the inputs themselves.
+    pub fn verify_with_public_inputs_hash(
+        &self,
+        proof: Proof<F, C, D>,
+        public_inputs_hash: HashOut<F>,
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        verify_with_public_inputs_hash(proof, public_inputs_hash, &self.verifier_only, &self.common)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
     ) -> Result<()>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         compressed_proof_with_pis.verify(&self.verifier_only, &self.common)
     }
+
+    /// A Keccak digest of everything the verifier depends on: the commitment to the constants and
+    /// sigmas, the circuit's gates and its shape parameters. Two `VerifierCircuitData`s with the
+    /// same digest accept the same proofs.
+    pub fn digest(&self) -> [u8; 32] {
+        let common = &self.common;
+        let config = &common.config;
+        let fri_params = &common.fri_params;
+        let mut bytes = Vec::new();
+        for h in &self.verifier_only.constants_sigmas_cap.0 {
+            bytes.extend(h.to_bytes());
+        }
+        let params = [
+            common.degree_bits,
+            common.quotient_degree_factor,
+            common.num_gate_constraints,
+            common.num_constants,
+            common.num_public_inputs,
+            common.num_partial_products,
+            config.num_wires,
+            config.num_routed_wires,
+            config.num_challenges(),
+            config.zero_knowledge as usize,
+            config.blinding.wires as usize,
+            config.blinding.zs_partial_products as usize,
+            config.blinding.quotient as usize,
+            config.oracle_rates.constants_sigmas,
+            config.oracle_rates.wires,
+            config.oracle_rates.zs_partial_products,
+            config.oracle_rates.quotient,
+            fri_params.salt_size,
+            (fri_params.salt_mode == SaltMode::Masked) as usize,
+            fri_params.config.rate_bits,
+            fri_params.config.cap_height,
+            fri_params.config.commit_phase_cap_height,
+            fri_params.config.proof_of_work_bits as usize,
+            fri_params.config.num_query_rounds,
+            fri_params.reduction_arity_bits.len(),
+        ];
+        for x in params
+            .into_iter()
+            .chain(fri_params.reduction_arity_bits.iter().copied())
+        {
+            bytes.extend((x as u64).to_le_bytes());
+        }
+        for k_i in &common.k_is {
+            bytes.extend(k_i.to_canonical_u64().to_le_bytes());
+        }
+        for opening in &common.extra_openings {
+            bytes.extend(opening.multiplier.to_canonical_u64().to_le_bytes());
+            bytes.extend((opening.polynomials.len() as u64).to_le_bytes());
+            for p in &opening.polynomials {
+                bytes.extend((p.oracle_index as u64).to_le_bytes());
+                bytes.extend((p.polynomial_index as u64).to_le_bytes());
+            }
+        }
+        // The groups change the public inputs hash, so two circuits differing only in them must
+        // have different digests.
+        bytes.extend((common.public_input_groups.len() as u64).to_le_bytes());
+        for group in &common.public_input_groups {
+            bytes.extend((group.name.len() as u64).to_le_bytes());
+            bytes.extend(group.name.as_bytes());
+            bytes.extend((group.hash as u64).to_le_bytes());
+            bytes.extend((group.range.start as u64).to_le_bytes());
+            bytes.extend((group.range.end as u64).to_le_bytes());
+        }
+        // Gate IDs include the gates' parameters, and prefixes determine the selectors.
+        for gate in &common.gates {
+            let id = gate.gate.0.id();
+            bytes.extend((id.len() as u64).to_le_bytes());
+            bytes.extend(id.as_bytes());
+            bytes.extend((gate.prefix.len() as u64).to_le_bytes());
+            bytes.extend(gate.prefix.iter().map(|&b| b as u8));
+        }
+        keccak(bytes).0
+    }
+
+    /// Deserializes a proof with public inputs from `proof_bytes` and verifies it, after checking
+    /// that this verifier data has the pinned digest `vk_digest` and that the proof's public inputs
+    /// are `public_inputs`. This lets light clients reject a proof for the wrong circuit even if
+    /// they are handed the wrong verifier data.
+    pub fn verify_raw(
+        &self,
+        proof_bytes: &[u8],
+        vk_digest: [u8; 32],
+        public_inputs: &[F],
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        ensure!(
+            self.digest() == vk_digest,
+            "Verifier data doesn't match the pinned digest."
+        );
+        let proof_with_pis = ProofWithPublicInputs::from_bytes(proof_bytes.to_vec(), &self.common)?;
+        ensure!(
+            proof_with_pis.public_inputs == public_inputs,
+            "Proof has the wrong public inputs."
+        );
+        self.verify(proof_with_pis)
+    }
+
+    pub fn common_data(&self) -> &CommonCircuitData<F, C, D> {
+        &self.common
+    }
 }
 
 /// Circuit data required by the prover, but not the verifier.
@@ -209,7 +782,7 @@ pub(crate) struct ProverOnlyCircuitData<
     /// they watch.
     pub generator_indices_by_watches: BTreeMap<usize, Vec<usize>>,
     /// Commitments to the constants polynomials and sigma polynomials.
-    pub constants_sigmas_commitment: PolynomialBatch<F, C, D>,
+    pub constants_sigmas_commitment: PlonkProverData<F, C, D>,
     /// The transpose of the list of sigma polynomials.
     pub sigmas: Vec<Vec<F>>,
     /// Subgroup of order `degree`.
@@ -218,11 +791,33 @@ pub(crate) struct ProverOnlyCircuitData<
     pub public_inputs: Vec<Target>,
     /// A vector of marked targets. The values assigned to these targets will be displayed by the prover.
     pub marked_targets: Vec<MarkedTargets<D>>,
+    /// Targets which the prover sets to random values, to blind the witness polynomials.
+    pub blinding_targets: Vec<Target>,
     /// A map from each `Target`'s index to the index of its representative in the disjoint-set
     /// forest.
     pub representative_map: Vec<usize>,
-    /// Pre-computed roots for faster FFT.
-    pub fft_root_table: Option<FftRootTable<F>>,
+    /// Where each virtual target and gate was created, in debug builds.
+    pub source_locations: SourceLocations,
+    /// The prover-side parameters of the polynomial commitment scheme, i.e. the backends performing
+    /// the prover's FFTs, by default on the CPU with pre-computed roots, and searching for FRI
+    /// proof-of-work witnesses.
+    pub pcs_prover_params: PlonkPcsProverParams<F, C, D>,
+    /// The seed from which the prover's randomness is derived, if proving is deterministic.
+    pub deterministic_seed: Option<[u8; 32]>,
+    /// The observer to which the prover reports its Fiat-Shamir transcript, if any.
+    pub transcript_observer: Option<SharedTranscriptObserver<F>>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    ProverOnlyCircuitData<F, C, D>
+{
+    /// The source of the prover's randomness when none is given explicitly.
+    fn rng(&self) -> Box<dyn RngCore> {
+        match self.deterministic_seed {
+            Some(seed) => Box::new(ChaCha20Rng::from_seed(seed)),
+            None => Box::new(thread_rng()),
+        }
+    }
 }
 
 /// Circuit data required by the verifier, but not the prover.
@@ -232,6 +827,24 @@ pub(crate) struct VerifierOnlyCircuitData<C: GenericConfig<D>, const D: usize> {
     pub(crate) constants_sigmas_cap: MerkleCap<C::F, C::Hasher>,
 }
 
+/// A point at which some of the committed polynomials are opened in addition to `zeta` and
+/// `g * zeta`, for arguments layered on top of the Plonk commitments. The point is
+/// `multiplier * zeta`; e.g. a power of `g` opens the polynomials at a rotation of `zeta`.
+#[derive(Clone, Debug)]
+pub struct ExtraOpening<F: Field> {
+    pub multiplier: F,
+    pub polynomials: Vec<FriPolynomialInfo>,
+}
+
+impl<F: Field> ExtraOpening<F> {
+    pub fn point<const D: usize>(&self, zeta: F::Extension) -> F::Extension
+    where
+        F: Extendable<D>,
+    {
+        zeta.scalar_mul(self.multiplier)
+    }
+}
+
 /// Circuit data required by both the prover and the verifier.
 #[derive(Debug)]
 pub struct CommonCircuitData<
@@ -261,12 +874,18 @@ pub struct CommonCircuitData<
 
     pub(crate) num_public_inputs: usize,
 
+    /// Groups of public inputs which are hashed separately.
+    pub(crate) public_input_groups: Vec<PublicInputGroup>,
+
     /// The `{k_i}` valued used in `S_ID_i` in Plonk's permutation argument.
     pub(crate) k_is: Vec<F>,
 
     /// The number of partial products needed to compute the `Z` polynomials.
     pub(crate) num_partial_products: usize,
 
+    /// The points other than `zeta` and `g * zeta` at which polynomials are opened.
+    pub(crate) extra_openings: Vec<ExtraOpening<F>>,
+
     /// A digest of the "circuit" (i.e. the instance, minus public inputs), which can be used to
     /// seed Fiat-Shamir.
     pub(crate) circuit_digest: <<C as GenericConfig<D>>::Hasher as Hasher<F>>::Hash,
@@ -275,6 +894,19 @@ pub struct CommonCircuitData<
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     CommonCircuitData<F, C, D>
 {
+    /// The group of public inputs named `name`, if any.
+    pub fn public_input_group(&self, name: &str) -> Option<&PublicInputGroup> {
+        self.public_input_groups.iter().find(|g| g.name == name)
+    }
+
+    /// The hash of the given public inputs, as computed by this circuit.
+    pub fn hash_public_inputs(&self, public_inputs: &[F]) -> HashOut<F> {
+        hash_grouped_public_inputs::<F, C::PublicInputsHasher, D>(
+            public_inputs,
+            &self.public_input_groups,
+        )
+    }
+
     pub fn degree(&self) -> usize {
         1 << self.degree_bits
     }
@@ -299,6 +931,10 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         self.quotient_degree_factor * self.degree()
     }
 
+    pub fn extra_openings(&self) -> &[ExtraOpening<F>] {
+        &self.extra_openings
+    }
+
     /// Range of the constants polynomials in the `constants_sigmas_commitment`.
     pub fn constants_range(&self) -> Range<usize> {
         0..self.num_constants
@@ -311,12 +947,12 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
 
     /// Range of the `z`s polynomials in the `zs_partial_products_commitment`.
     pub fn zs_range(&self) -> Range<usize> {
-        0..self.config.num_challenges
+        0..self.config.num_challenges()
     }
 
     /// Range of the partial products polynomials in the `zs_partial_products_commitment`.
     pub fn partial_products_range(&self) -> RangeFrom<usize> {
-        self.config.num_challenges..
+        self.config.num_challenges()..
     }
 
     pub(crate) fn get_fri_instance(&self, zeta: F::Extension) -> FriInstanceInfo<F, D> {
@@ -334,9 +970,13 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             polynomials: self.fri_zs_polys(),
         };
 
-        let openings = vec![zeta_batch, zeta_right_batch];
+        let mut openings = vec![zeta_batch, zeta_right_batch];
+        openings.extend(self.extra_openings.iter().map(|opening| FriBatchInfo {
+            point: opening.point::<D>(zeta),
+            polynomials: opening.polynomials.clone(),
+        }));
         FriInstanceInfo {
-            oracles: FRI_ORACLES.to_vec(),
+            oracles: self.config.fri_oracles(),
             batches: openings,
         }
     }
@@ -360,9 +1000,15 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             polynomials: self.fri_zs_polys(),
         };
 
-        let openings = vec![zeta_batch, zeta_right_batch];
+        let mut openings = vec![zeta_batch, zeta_right_batch];
+        for opening in &self.extra_openings {
+            openings.push(FriBatchInfoTarget {
+                point: builder.mul_const_extension(opening.multiplier, zeta),
+                polynomials: opening.polynomials.clone(),
+            });
+        }
         FriInstanceInfoTarget {
-            oracles: FRI_ORACLES.to_vec(),
+            oracles: self.config.fri_oracles(),
             batches: openings,
         }
     }
@@ -391,19 +1037,29 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     }
 
     pub(crate) fn num_zs_partial_products_polys(&self) -> usize {
-        self.config.num_challenges * (1 + self.num_partial_products)
+        self.config.num_challenges() * (1 + self.num_partial_products)
     }
 
     fn fri_zs_polys(&self) -> Vec<FriPolynomialInfo> {
         FriPolynomialInfo::from_range(PlonkOracle::ZS_PARTIAL_PRODUCTS.index, self.zs_range())
     }
 
+    /// The number of polynomials committed to by the oracle with the given index.
+    pub(crate) fn num_oracle_polys(&self, oracle_index: usize) -> usize {
+        [
+            self.num_preprocessed_polys(),
+            self.config.num_wires,
+            self.num_zs_partial_products_polys(),
+            self.num_quotient_polys(),
+        ][oracle_index]
+    }
+
     fn fri_quotient_polys(&self) -> Vec<FriPolynomialInfo> {
         FriPolynomialInfo::from_range(PlonkOracle::QUOTIENT.index, 0..self.num_quotient_polys())
     }
 
     pub(crate) fn num_quotient_polys(&self) -> usize {
-        self.config.num_challenges * self.quotient_degree_factor
+        self.config.num_challenges() * self.quotient_degree_factor
     }
 
     fn fri_all_polys(&self) -> Vec<FriPolynomialInfo> {
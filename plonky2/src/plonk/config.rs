@@ -5,11 +5,14 @@ use plonky2_field::extension_field::{Extendable, FieldExtension};
 use plonky2_field::goldilocks_field::GoldilocksField;
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::hash::hash_types::HashOut;
 use crate::hash::hash_types::RichField;
+use crate::hash::hash_types::{HashOut, HashOutTarget};
 use crate::hash::hashing::{PlonkyPermutation, SPONGE_WIDTH};
 use crate::hash::keccak::KeccakHash;
 use crate::hash::poseidon::PoseidonHash;
+use crate::hash::public_inputs::PublicInputsHasher;
+use crate::hash::rescue_prime::RescuePrimeHash;
+use crate::hash::tip5::Tip5Hash;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 
@@ -18,10 +21,52 @@ pub trait GenericHashOut<F: RichField>:
 {
     fn to_bytes(&self) -> Vec<u8>;
     fn from_bytes(bytes: &[u8]) -> Self;
+    /// Whether `bytes` is the canonical encoding of a hash, e.g. with every field element of the
+    /// hash less than the field order. Only such bytes may be passed to `from_bytes`.
+    fn is_canonical(bytes: &[u8]) -> bool;
 
     fn to_vec(&self) -> Vec<F>;
 }
 
+/// The context in which two digests are compressed. It is bound into the compression, so that a
+/// digest computed in one context can't be passed off as a digest from another, such as an internal
+/// node of a Merkle tree posing as a leaf of a shallower tree.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CompressionDomain {
+    /// Compression of two leaf digests.
+    Leaf,
+    /// Compression of two internal node digests.
+    Internal,
+    /// Compression into an element of a Merkle cap.
+    Cap,
+}
+
+impl CompressionDomain {
+    /// The domain of the node at `height` above the leaves of a Merkle (sub)tree with `num_layers`
+    /// layers below its root, which is an element of the cap. The leaves take precedence over the
+    /// cap when the tree has a single layer, so that an internal node can never be opened as a
+    /// leaf of a shallower tree.
+    pub fn for_merkle_node(height: usize, num_layers: usize) -> Self {
+        debug_assert!(1 <= height && height <= num_layers);
+        if height == 1 {
+            CompressionDomain::Leaf
+        } else if height == num_layers {
+            CompressionDomain::Cap
+        } else {
+            CompressionDomain::Internal
+        }
+    }
+
+    /// A nonzero tag identifying the domain.
+    pub fn tag(&self) -> u8 {
+        match self {
+            CompressionDomain::Leaf => 1,
+            CompressionDomain::Internal => 2,
+            CompressionDomain::Cap => 3,
+        }
+    }
+}
+
 /// Trait for hash functions.
 pub trait Hasher<F: RichField>: Sized + Clone + Debug + Eq + PartialEq {
     /// Size of `Hash` in bytes.
@@ -52,7 +97,7 @@ pub trait Hasher<F: RichField>: Sized + Clone + Debug + Eq + PartialEq {
     where
         [(); Self::HASH_SIZE]:,
     {
-        if inputs.len() <= 4 {
+        if inputs.len() * 8 <= Self::HASH_SIZE {
             let mut inputs_bytes = [0u8; Self::HASH_SIZE];
             for i in 0..inputs.len() {
                 inputs_bytes[i * 8..(i + 1) * 8]
@@ -64,7 +109,26 @@ pub trait Hasher<F: RichField>: Sized + Clone + Debug + Eq + PartialEq {
         }
     }
 
+    /// Applies `hash_or_noop` to each of the given inputs. Hashers with a vectorized implementation
+    /// can override this to hash several inputs at once.
+    fn hash_or_noop_batch(inputs: &[Vec<F>]) -> Vec<Self::Hash>
+    where
+        [(); Self::HASH_SIZE]:,
+    {
+        inputs
+            .iter()
+            .map(|input| Self::hash_or_noop(input))
+            .collect()
+    }
+
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash;
+
+    /// Like `two_to_one`, but also binds the given domain into the compression.
+    fn two_to_one_with_domain(
+        left: Self::Hash,
+        right: Self::Hash,
+        domain: CompressionDomain,
+    ) -> Self::Hash;
 }
 
 /// Trait for algebraic hash functions, built from a permutation using the sponge construction.
@@ -81,6 +145,27 @@ pub trait AlgebraicHasher<F: RichField>: Hasher<F, Hash = HashOut<F>> {
     ) -> [Target; SPONGE_WIDTH]
     where
         F: RichField + Extendable<D>;
+
+    /// Circuit version of `Hasher::two_to_one_with_domain`, where `left` and `right` are swapped
+    /// first if `swap` is set.
+    fn two_to_one_swapped_with_domain<const D: usize>(
+        left: HashOutTarget,
+        right: HashOutTarget,
+        swap: BoolTarget,
+        domain: CompressionDomain,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> HashOutTarget
+    where
+        F: RichField + Extendable<D>,
+    {
+        let zero = builder.zero();
+        let mut perm_inputs = [zero; SPONGE_WIDTH];
+        perm_inputs[..4].copy_from_slice(&left.elements);
+        perm_inputs[4..8].copy_from_slice(&right.elements);
+        perm_inputs[8] = builder.constant(F::from_canonical_u16(domain.tag().into()));
+        let outputs = Self::permute_swapped(perm_inputs, swap, builder);
+        HashOutTarget::from_vec(outputs[..4].to_vec())
+    }
 }
 
 /// Generic configuration trait.
@@ -91,10 +176,16 @@ pub trait GenericConfig<const D: usize>:
     type F: RichField + Extendable<D, Extension = Self::FE>;
     /// Field extension of degree D of the main field.
     type FE: FieldExtension<D, BaseField = Self::F>;
-    /// Hash function used for building Merkle trees.
+    /// Hash function used for building Merkle trees, other than those of the FRI commit phase.
     type Hasher: Hasher<Self::F>;
-    /// Algebraic hash function used for the challenger and hashing public inputs.
+    /// Hash function used for building the Merkle trees of the FRI commit phase. This can differ
+    /// from `Hasher`, e.g. to commit to the large initial trees with a fast hash, while the commit
+    /// phase trees use an algebraic hash which is cheaper to verify in a recursive circuit.
+    type CommitPhaseHasher: Hasher<Self::F>;
+    /// Algebraic hash function used for the challenger.
     type InnerHasher: AlgebraicHasher<Self::F>;
+    /// Hash function used for hashing public inputs, both by the verifier and in the circuit.
+    type PublicInputsHasher: PublicInputsHasher<Self::F, D>;
 }
 
 /// Configuration using Poseidon over the Goldilocks field.
@@ -104,7 +195,33 @@ impl GenericConfig<2> for PoseidonGoldilocksConfig {
     type F = GoldilocksField;
     type FE = QuadraticExtension<Self::F>;
     type Hasher = PoseidonHash;
+    type CommitPhaseHasher = PoseidonHash;
     type InnerHasher = PoseidonHash;
+    type PublicInputsHasher = PoseidonHash;
+}
+
+/// Configuration using Tip5 over the Goldilocks field.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Tip5GoldilocksConfig;
+impl GenericConfig<2> for Tip5GoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuadraticExtension<Self::F>;
+    type Hasher = Tip5Hash;
+    type CommitPhaseHasher = Tip5Hash;
+    type InnerHasher = Tip5Hash;
+    type PublicInputsHasher = Tip5Hash;
+}
+
+/// Configuration using Rescue-Prime over the Goldilocks field.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RescuePrimeGoldilocksConfig;
+impl GenericConfig<2> for RescuePrimeGoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuadraticExtension<Self::F>;
+    type Hasher = RescuePrimeHash;
+    type CommitPhaseHasher = RescuePrimeHash;
+    type InnerHasher = RescuePrimeHash;
+    type PublicInputsHasher = RescuePrimeHash;
 }
 
 /// Configuration using truncated Keccak over the Goldilocks field.
@@ -114,5 +231,33 @@ impl GenericConfig<2> for KeccakGoldilocksConfig {
     type F = GoldilocksField;
     type FE = QuadraticExtension<Self::F>;
     type Hasher = KeccakHash<25>;
+    type CommitPhaseHasher = KeccakHash<25>;
+    type InnerHasher = PoseidonHash;
+    type PublicInputsHasher = PoseidonHash;
+}
+
+/// Configuration using Poseidon over the Goldilocks field, except for the public inputs hash, which
+/// uses Keccak-256 so that it is cheap to recompute on Ethereum.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct KeccakPublicInputsGoldilocksConfig;
+impl GenericConfig<2> for KeccakPublicInputsGoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuadraticExtension<Self::F>;
+    type Hasher = PoseidonHash;
+    type CommitPhaseHasher = PoseidonHash;
+    type InnerHasher = PoseidonHash;
+    type PublicInputsHasher = KeccakHash<32>;
+}
+
+/// Configuration using truncated Keccak for the initial Merkle trees, which are the bulk of the
+/// prover's hashing, and Poseidon for the FRI commit phase trees, over the Goldilocks field.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct KeccakPoseidonGoldilocksConfig;
+impl GenericConfig<2> for KeccakPoseidonGoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuadraticExtension<Self::F>;
+    type Hasher = KeccakHash<25>;
+    type CommitPhaseHasher = PoseidonHash;
     type InnerHasher = PoseidonHash;
+    type PublicInputsHasher = PoseidonHash;
 }
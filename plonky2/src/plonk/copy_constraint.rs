@@ -1,22 +1,24 @@
+use std::sync::Arc;
+
 use crate::iop::target::Target;
 
-/// A named copy constraint.
+/// A named copy constraint. Names are shared by all the copy constraints of a context.
 pub struct CopyConstraint {
     pub pair: (Target, Target),
-    pub name: String,
+    pub name: Arc<str>,
 }
 
 impl From<(Target, Target)> for CopyConstraint {
     fn from(pair: (Target, Target)) -> Self {
         Self {
             pair,
-            name: String::new(),
+            name: "".into(),
         }
     }
 }
 
 impl CopyConstraint {
-    pub fn new(pair: (Target, Target), name: String) -> Self {
+    pub fn new(pair: (Target, Target), name: Arc<str>) -> Self {
         Self { pair, name }
     }
 }
@@ -0,0 +1,160 @@
+//! Estimates of how much of the trace a gadget consumes under a given `CircuitConfig`.
+//!
+//! Costs are measured by running the gadget on a scratch `CircuitBuilder` and packing its batched
+//! operations, so they always agree with what the builder would do. They include the `ConstantGate`
+//! rows holding the gadget's constants, which a circuit shares between all its gadgets, but not the
+//! `PublicInputGate`, blinding gates or padding, which `build` adds once per circuit.
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::curve::curve_types::Curve;
+use crate::gadgets::ecdsa::{ECDSAPublicKeyTarget, ECDSASignatureTarget};
+use crate::gates::constant::ConstantGate;
+use crate::gates::gate::Gate;
+use crate::hash::hash_types::RichField;
+use crate::hash::poseidon::PoseidonHash;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CircuitConfig;
+
+/// The trace usage of some piece of circuit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CircuitCost {
+    /// The number of gates, i.e. rows of the trace, including `constant_rows`.
+    pub rows: usize,
+    /// The number of those rows which are `ConstantGate`s holding constants.
+    pub constant_rows: usize,
+    /// The number of wires in those rows, used or not.
+    pub wires: usize,
+}
+
+impl CircuitCost {
+    /// Measures the rows used by whatever `f` adds to an empty circuit.
+    pub fn measure<F, const D: usize>(
+        config: &CircuitConfig,
+        f: impl FnOnce(&mut CircuitBuilder<F, D>),
+    ) -> Self
+    where
+        F: RichField + Extendable<D>,
+    {
+        let mut builder = CircuitBuilder::new(config.clone());
+        f(&mut builder);
+        builder.fill_batched_gates();
+        let rows = builder.num_gates();
+        let constant_gate_id = Gate::<F, D>::id(&ConstantGate {
+            num_consts: config.constant_gate_size,
+        });
+        let constant_rows = builder
+            .gate_instances
+            .iter()
+            .filter(|instance| instance.gate_ref.0.id() == constant_gate_id)
+            .count();
+        Self {
+            rows,
+            constant_rows,
+            wires: rows * config.num_wires,
+        }
+    }
+
+    /// The cost of `num_hashes` independent Poseidon hashes of `num_inputs` elements each.
+    pub fn poseidon_hashes<F, const D: usize>(
+        config: &CircuitConfig,
+        num_hashes: usize,
+        num_inputs: usize,
+    ) -> Self
+    where
+        F: RichField + Extendable<D>,
+    {
+        Self::measure::<F, D>(config, |builder| {
+            for _ in 0..num_hashes {
+                let inputs = builder.add_virtual_targets(num_inputs);
+                builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs);
+            }
+        })
+    }
+
+    /// The cost of `num_ops` independent base field operations of the form
+    /// `c0 * x * y + c1 * z`.
+    pub fn arithmetic_ops<F, const D: usize>(config: &CircuitConfig, num_ops: usize) -> Self
+    where
+        F: RichField + Extendable<D>,
+    {
+        Self::measure::<F, D>(config, |builder| {
+            for _ in 0..num_ops {
+                let [x, y, z] = [(); 3].map(|_| builder.add_virtual_target());
+                builder.arithmetic(F::TWO, F::NEG_ONE, x, y, z);
+            }
+        })
+    }
+
+    /// The cost of verifying one ECDSA signature over the curve `C`.
+    pub fn ecdsa_verify<F, C: Curve, const D: usize>(config: &CircuitConfig) -> Self
+    where
+        F: RichField + Extendable<D>,
+    {
+        Self::measure::<F, D>(config, |builder| {
+            let msg = builder.add_virtual_nonnative_target();
+            let sig = ECDSASignatureTarget {
+                r: builder.add_virtual_nonnative_target(),
+                s: builder.add_virtual_nonnative_target(),
+            };
+            let pk = ECDSAPublicKeyTarget(builder.add_virtual_affine_point_target::<C>());
+            builder.verify_message(msg, sig, pk);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2_field::field_types::Field;
+
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::cost_model::CircuitCost;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_arithmetic_ops_cost() {
+        let config = CircuitConfig::standard_recursion_config();
+        let ops_per_gate = ArithmeticGate::new_from_config(&config).num_ops;
+
+        // Three arithmetic gates, and the constant gate holding the zero which pads the last.
+        let cost = CircuitCost::arithmetic_ops::<F, D>(&config, 2 * ops_per_gate + 1);
+        assert_eq!((cost.rows, cost.constant_rows), (4, 1));
+        assert_eq!(cost.wires, 4 * config.num_wires);
+    }
+
+    #[test]
+    fn test_constant_rows() {
+        let config = CircuitConfig::standard_recursion_config();
+        let constants = |n: usize| {
+            CircuitCost::measure::<F, D>(&config, |builder| {
+                for i in 1..=n {
+                    builder.constant(F::from_canonical_usize(i));
+                }
+            })
+        };
+
+        // Filling batched gates uses zero, so even an empty gadget has a constant row.
+        let empty = constants(0);
+        assert_eq!((empty.rows, empty.constant_rows), (1, 1));
+        // Zero takes one of the first constant gate's slots.
+        let full = constants(config.constant_gate_size - 1);
+        assert_eq!((full.rows, full.constant_rows), (1, 1));
+        let overflowing = constants(config.constant_gate_size);
+        assert_eq!((overflowing.rows, overflowing.constant_rows), (2, 2));
+    }
+
+    #[test]
+    fn test_poseidon_hashes_cost() {
+        let config = CircuitConfig::standard_recursion_config();
+
+        // Each hash is a single permutation; the constants they use are shared.
+        let one = CircuitCost::poseidon_hashes::<F, D>(&config, 1, 8);
+        let ten = CircuitCost::poseidon_hashes::<F, D>(&config, 10, 8);
+        assert_eq!(ten.rows - one.rows, 9);
+    }
+}
@@ -4,52 +4,64 @@ use plonky2_field::extension_field::Extendable;
 use plonky2_field::polynomial::PolynomialCoeffs;
 
 use crate::fri::proof::{CompressedFriProof, FriChallenges, FriProof, FriProofTarget};
-use crate::fri::verifier::{compute_evaluation, fri_combine_initial, PrecomputedReducedOpenings};
+use crate::fri::verifier::{
+    compute_evaluation, fri_combine_denominator_invs, fri_combine_initial, fri_query_subgroup_x,
+    PrecomputedReducedOpenings,
+};
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
-use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::challenger::{Challenger, RecursiveChallenger};
 use crate::iop::target::Target;
+use crate::iop::transcript::SharedTranscriptObserver;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
-use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
 use crate::plonk::proof::{
     CompressedProof, CompressedProofWithPublicInputs, FriInferredElements, OpeningSet,
     OpeningSetTarget, Proof, ProofChallenges, ProofChallengesTarget, ProofTarget,
-    ProofWithPublicInputs, ProofWithPublicInputsTarget,
+    ProofWithPublicInputs,
 };
-use crate::util::reverse_bits;
 
 fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
-    public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+    public_inputs_hash: HashOut<F>,
     wires_cap: &MerkleCap<F, C::Hasher>,
     plonk_zs_partial_products_cap: &MerkleCap<F, C::Hasher>,
     quotient_polys_cap: &MerkleCap<F, C::Hasher>,
     openings: &OpeningSet<F, D>,
-    commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    commit_phase_merkle_caps: &[MerkleCap<F, C::CommitPhaseHasher>],
     final_poly: &PolynomialCoeffs<F::Extension>,
     pow_witness: F,
     common_data: &CommonCircuitData<F, C, D>,
+    observer: Option<SharedTranscriptObserver<F>>,
 ) -> anyhow::Result<ProofChallenges<F, D>> {
     let config = &common_data.config;
-    let num_challenges = config.num_challenges;
+    let num_challenges = config.num_challenges();
 
     let mut challenger = Challenger::<F, C::Hasher>::new();
+    if let Some(observer) = observer {
+        challenger.set_observer(observer);
+    }
 
     // Observe the instance.
+    challenger.set_label("instance");
     challenger.observe_hash::<C::Hasher>(common_data.circuit_digest);
     challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
 
+    challenger.set_label("wires");
     challenger.observe_cap(wires_cap);
     let plonk_betas = challenger.get_n_challenges(num_challenges);
     let plonk_gammas = challenger.get_n_challenges(num_challenges);
 
+    challenger.set_label("zs_partial_products");
     challenger.observe_cap(plonk_zs_partial_products_cap);
     let plonk_alphas = challenger.get_n_challenges(num_challenges);
 
+    challenger.set_label("quotient");
     challenger.observe_cap(quotient_polys_cap);
     let plonk_zeta = challenger.get_extension_challenge::<D>();
 
+    challenger.set_label("openings");
     challenger.observe_openings(&openings.to_fri_openings());
 
     Ok(ProofChallenges {
@@ -70,21 +82,47 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     ProofWithPublicInputs<F, C, D>
 {
-    pub(crate) fn fri_query_indices(
+    /// Computes all Fiat-Shamir challenges used in the Plonk proof.
+    pub(crate) fn get_challenges(
         &self,
+        public_inputs_hash: HashOut<F>,
         common_data: &CommonCircuitData<F, C, D>,
-    ) -> anyhow::Result<Vec<usize>> {
-        Ok(self
-            .get_challenges(self.get_public_inputs_hash(), common_data)?
-            .fri_challenges
-            .fri_query_indices)
+    ) -> anyhow::Result<ProofChallenges<F, D>> {
+        self.proof.get_challenges(public_inputs_hash, common_data)
     }
 
+    /// Reports the verifier's Fiat-Shamir transcript for this proof to `observer`, for comparison
+    /// with the prover's or a recursive verifier's.
+    pub fn observe_transcript(
+        &self,
+        common_data: &CommonCircuitData<F, C, D>,
+        observer: SharedTranscriptObserver<F>,
+    ) -> anyhow::Result<()> {
+        self.proof
+            .get_challenges_with_observer(
+                self.get_public_inputs_hash_with(common_data),
+                common_data,
+                Some(observer),
+            )
+            .map(|_| ())
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Proof<F, C, D> {
     /// Computes all Fiat-Shamir challenges used in the Plonk proof.
     pub(crate) fn get_challenges(
         &self,
-        public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+        public_inputs_hash: HashOut<F>,
+        common_data: &CommonCircuitData<F, C, D>,
+    ) -> anyhow::Result<ProofChallenges<F, D>> {
+        self.get_challenges_with_observer(public_inputs_hash, common_data, None)
+    }
+
+    fn get_challenges_with_observer(
+        &self,
+        public_inputs_hash: HashOut<F>,
         common_data: &CommonCircuitData<F, C, D>,
+        observer: Option<SharedTranscriptObserver<F>>,
     ) -> anyhow::Result<ProofChallenges<F, D>> {
         let Proof {
             wires_cap,
@@ -98,7 +136,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
                     pow_witness,
                     ..
                 },
-        } = &self.proof;
+        } = self;
 
         get_challenges(
             public_inputs_hash,
@@ -110,6 +148,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             final_poly,
             *pow_witness,
             common_data,
+            observer,
         )
     }
 }
@@ -120,7 +159,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     /// Computes all Fiat-Shamir challenges used in the Plonk proof.
     pub(crate) fn get_challenges(
         &self,
-        public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+        public_inputs_hash: HashOut<F>,
         common_data: &CommonCircuitData<F, C, D>,
     ) -> anyhow::Result<ProofChallenges<F, D>> {
         let CompressedProof {
@@ -147,6 +186,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             final_poly,
             *pow_witness,
             common_data,
+            None,
         )
     }
 
@@ -176,13 +216,21 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             *fri_alpha,
         );
         let log_n = common_data.degree_bits + common_data.config.fri_config.rate_bits;
+        let fri_instance = common_data.get_fri_instance(*plonk_zeta);
+        let subgroup_xs = fri_query_indices
+            .iter()
+            .map(|&x_index| fri_query_subgroup_x(x_index, log_n))
+            .collect::<Vec<_>>();
+        let denominator_invs = fri_combine_denominator_invs(&fri_instance, &subgroup_xs);
         // Simulate the proof verification and collect the inferred elements.
         // The content of the loop is basically the same as the `fri_verifier_query_round` function.
-        for &(mut x_index) in fri_query_indices {
-            let mut subgroup_x = F::MULTIPLICATIVE_GROUP_GENERATOR
-                * F::primitive_root_of_unity(log_n).exp_u64(reverse_bits(x_index, log_n) as u64);
+        for ((&(mut x_index), mut subgroup_x), denominator_invs) in fri_query_indices
+            .iter()
+            .zip(subgroup_xs)
+            .zip(&denominator_invs)
+        {
             let mut old_eval = fri_combine_initial::<F, C, D>(
-                &common_data.get_fri_instance(*plonk_zeta),
+                &fri_instance,
                 &self
                     .proof
                     .opening_proof
@@ -191,6 +239,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
                 *fri_alpha,
                 subgroup_x,
                 &precomputed_reduced_evals,
+                denominator_invs,
                 &common_data.fri_params,
             );
             for (i, &arity_bits) in common_data
@@ -243,26 +292,31 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         C::Hasher: AlgebraicHasher<F>,
     {
         let config = &inner_common_data.config;
-        let num_challenges = config.num_challenges;
+        let num_challenges = config.num_challenges();
 
         let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(self);
 
         // Observe the instance.
+        challenger.set_label("instance");
         let digest =
             HashOutTarget::from_vec(self.constants(&inner_common_data.circuit_digest.elements));
         challenger.observe_hash(&digest);
         challenger.observe_hash(&public_inputs_hash);
 
+        challenger.set_label("wires");
         challenger.observe_cap(wires_cap);
         let plonk_betas = challenger.get_n_challenges(self, num_challenges);
         let plonk_gammas = challenger.get_n_challenges(self, num_challenges);
 
+        challenger.set_label("zs_partial_products");
         challenger.observe_cap(plonk_zs_partial_products_cap);
         let plonk_alphas = challenger.get_n_challenges(self, num_challenges);
 
+        challenger.set_label("quotient");
         challenger.observe_cap(quotient_polys_cap);
         let plonk_zeta = challenger.get_extension_challenge(self);
 
+        challenger.set_label("openings");
         challenger.observe_openings(&openings.to_fri_openings());
 
         ProofChallengesTarget {
@@ -281,7 +335,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 }
 
-impl<const D: usize> ProofWithPublicInputsTarget<D> {
+impl<const D: usize> ProofTarget<D> {
     pub(crate) fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>>(
         &self,
         builder: &mut CircuitBuilder<F, D>,
@@ -303,7 +357,7 @@ impl<const D: usize> ProofWithPublicInputsTarget<D> {
                     pow_witness,
                     ..
                 },
-        } = &self.proof;
+        } = self;
 
         builder.get_challenges(
             public_inputs_hash,
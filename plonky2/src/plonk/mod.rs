@@ -2,12 +2,19 @@ pub mod circuit_builder;
 pub mod circuit_data;
 pub mod config;
 pub(crate) mod copy_constraint;
+pub mod cost_model;
 mod get_challenges;
+pub mod pcs;
 pub(crate) mod permutation_argument;
 pub mod plonk_common;
 pub mod proof;
+pub mod proof_chain;
 pub mod prover;
 pub mod recursive_verifier;
+pub mod region;
+pub mod structure;
+pub mod subcircuit;
 pub(crate) mod vanishing_poly;
 pub mod vars;
 pub mod verifier;
+pub mod wrapper;
@@ -0,0 +1,180 @@
+//! The polynomial commitment scheme as seen by the Plonk layer. The prover commits to batches of
+//! polynomials, then proves their values at the points of an instance, which lists the
+//! polynomials opened at each point; the verifier and the recursive verifier check those values
+//! against the commitments.
+//!
+//! Despite their names, `FriInstanceInfo`, `FriOracleInfo` and `FriOpenings` only describe which
+//! polynomials are opened where and to what values, so they serve any scheme. FRI is the only
+//! instantiation for now, and Plonk uses it through the `PlonkPcs` alias: the commitments and
+//! opening proofs of `Proof` are those of `PlonkPcs`, and every commitment and opening of the
+//! prover goes through this trait. The prover still computes the quotient polynomial from the
+//! LDEs of `PolynomialBatch`, so another scheme must also provide those.
+
+use std::fmt::Debug;
+
+use anyhow::Result;
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use rand::RngCore;
+
+use crate::fri::pcs::FriPcs;
+use crate::fri::structure::{
+    FriInstanceInfo, FriInstanceInfoTarget, FriOpenings, FriOpeningsTarget, FriOracleInfo,
+};
+use crate::hash::hash_types::RichField;
+use crate::iop::challenger::Challenger;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::util::progress::ProvingMonitor;
+use crate::util::serialization::ProofStream;
+use crate::util::timing::TimingTree;
+
+/// The scheme used by the Plonk prover and verifiers.
+pub type PlonkPcs = FriPcs;
+
+/// A commitment of `PlonkPcs`, as found in Plonk proofs.
+pub type PlonkCommitment<F, C, const D: usize> =
+    <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::Commitment;
+/// An opening proof of `PlonkPcs`, as found in Plonk proofs.
+pub type PlonkOpeningProof<F, C, const D: usize> =
+    <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::OpeningProof;
+/// A compressed opening proof of `PlonkPcs`, as found in compressed Plonk proofs.
+pub type PlonkCompressedOpeningProof<F, C, const D: usize> =
+    <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::CompressedOpeningProof;
+/// What the Plonk prover keeps of a batch of committed polynomials.
+pub type PlonkProverData<F, C, const D: usize> =
+    <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::ProverData;
+/// The prover-side parameters of `PlonkPcs`, as held by `ProverOnlyCircuitData`.
+pub type PlonkPcsProverParams<F, C, const D: usize> =
+    <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::ProverParams;
+
+/// What a proof lends to the scheme while it is being generated. `'s` is the lifetime of the
+/// stream's writer, which outlives any one borrow of the stream.
+pub struct PcsProverContext<'a, 's> {
+    pub timing: &'a mut TimingTree,
+    pub monitor: &'a ProvingMonitor,
+    /// The source of any randomness, e.g. for hiding commitments.
+    pub rng: &'a mut dyn RngCore,
+    /// Where the proof is written as it is generated, if anywhere. Schemes which can't stream
+    /// their proofs ignore it.
+    pub stream: Option<&'a mut ProofStream<'s>>,
+}
+
+/// A polynomial commitment scheme which can open batches of committed polynomials at several
+/// points at once, with a verifier which can be run natively or in a circuit.
+pub trait PolynomialCommitmentScheme<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>
+{
+    /// The parameters of the scheme, shared by the prover and the verifier.
+    type Params: Debug;
+    /// Settings of the prover which don't affect the validity of its proofs, such as the backends
+    /// its computations run on.
+    type ProverParams;
+    /// What the prover keeps of a batch of committed polynomials.
+    type ProverData;
+    /// What the prover may keep between proofs, to avoid recomputing the parts of a commitment
+    /// whose polynomials are unchanged.
+    type Cache;
+    /// A commitment to a batch of polynomials, as sent to the verifier.
+    type Commitment: Clone + Debug + Eq;
+    type CommitmentTarget: Clone + Debug;
+    type OpeningProof: Clone + Debug + Eq;
+    type OpeningProofTarget: Clone + Debug;
+    /// An opening proof with its redundant parts removed, which the verifier can check directly.
+    type CompressedOpeningProof: Clone + Debug + Eq;
+    /// The verifier's challenges for an opening proof, which are derived from the transcript.
+    type Challenges;
+    type ChallengesTarget;
+
+    /// Commits to a batch of polynomials, given by their coefficients. The oracle describes how
+    /// the batch is opened, e.g. whether the commitment must not reveal anything about the
+    /// polynomials beyond their openings.
+    fn commit(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        oracle: FriOracleInfo,
+        params: &Self::Params,
+        prover_params: &Self::ProverParams,
+        ctx: &mut PcsProverContext,
+    ) -> Self::ProverData
+    where
+        [(); C::Hasher::HASH_SIZE]:;
+
+    /// Like `commit`, but with the polynomials given by their values on the subgroup of their
+    /// degree. If `cache` is given, it may be used to skip recomputing unchanged polynomials.
+    fn commit_values(
+        values: Vec<PolynomialValues<F>>,
+        oracle: FriOracleInfo,
+        params: &Self::Params,
+        prover_params: &Self::ProverParams,
+        cache: Option<&mut Self::Cache>,
+        ctx: &mut PcsProverContext,
+    ) -> Self::ProverData
+    where
+        [(); C::Hasher::HASH_SIZE]:;
+
+    fn commitment(prover_data: &Self::ProverData) -> Self::Commitment;
+
+    /// Proves the openings of `instance`, whose oracles are `prover_data`. The challenger must
+    /// already have observed the commitments and the opened values. Fails if the proof is
+    /// cancelled through the context's monitor.
+    fn open(
+        instance: &FriInstanceInfo<F, D>,
+        prover_data: &[&Self::ProverData],
+        challenger: &mut Challenger<F, C::Hasher>,
+        params: &Self::Params,
+        prover_params: &Self::ProverParams,
+        ctx: &mut PcsProverContext,
+    ) -> Result<Self::OpeningProof>
+    where
+        [(); C::CommitPhaseHasher::HASH_SIZE]:;
+
+    /// Checks that the polynomials committed to in `commitments` take the values `openings` at
+    /// the points of `instance`.
+    fn verify(
+        instance: &FriInstanceInfo<F, D>,
+        openings: &FriOpenings<F, D>,
+        challenges: &Self::Challenges,
+        commitments: &[Self::Commitment],
+        proof: &Self::OpeningProof,
+        params: &Self::Params,
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:;
+
+    /// Removes the parts of `proof` which the verifier can recompute given its challenges.
+    fn compress(
+        proof: Self::OpeningProof,
+        challenges: &Self::Challenges,
+        params: &Self::Params,
+    ) -> Self::CompressedOpeningProof;
+
+    /// Like `verify`, but for a compressed opening proof.
+    fn verify_compressed(
+        instance: &FriInstanceInfo<F, D>,
+        openings: &FriOpenings<F, D>,
+        challenges: &Self::Challenges,
+        commitments: &[Self::Commitment],
+        proof: &Self::CompressedOpeningProof,
+        params: &Self::Params,
+    ) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:;
+
+    /// The in-circuit counterpart of `verify`.
+    fn verify_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        instance: &FriInstanceInfoTarget<D>,
+        openings: &FriOpeningsTarget<D>,
+        challenges: &Self::ChallengesTarget,
+        commitments: &[Self::CommitmentTarget],
+        proof: &Self::OpeningProofTarget,
+        params: &Self::Params,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>;
+}
@@ -2,10 +2,10 @@ use std::collections::HashMap;
 
 use plonky2_field::field_types::Field;
 use plonky2_field::polynomial::PolynomialValues;
-use rayon::prelude::*;
 
 use crate::iop::target::Target;
 use crate::iop::wire::Wire;
+use crate::util::maybe_rayon::*;
 
 /// Disjoint Set Forest data-structure following https://en.wikipedia.org/wiki/Disjoint-set_data_structure.
 pub struct Forest {
@@ -1,59 +1,116 @@
+use anyhow::{anyhow, Result};
 use plonky2_field::extension_field::Extendable;
 use plonky2_field::field_types::Field;
 use plonky2_field::packed_field::PackedField;
 
-use crate::fri::oracle::SALT_SIZE;
-use crate::fri::structure::FriOracleInfo;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::util::reducing::ReducingFactorTarget;
 
-pub(crate) const FRI_ORACLES: [FriOracleInfo; 4] = [
-    PlonkOracle::CONSTANTS_SIGMAS.as_fri_oracle(),
-    PlonkOracle::WIRES.as_fri_oracle(),
-    PlonkOracle::ZS_PARTIAL_PRODUCTS.as_fri_oracle(),
-    PlonkOracle::QUOTIENT.as_fri_oracle(),
-];
-
-/// Holds the Merkle tree index and blinding flag of a set of polynomials used in FRI.
+/// Holds the Merkle tree index of a set of polynomials used in FRI.
 #[derive(Debug, Copy, Clone)]
 pub struct PlonkOracle {
     pub(crate) index: usize,
-    pub(crate) blinding: bool,
 }
 
 impl PlonkOracle {
-    pub const CONSTANTS_SIGMAS: PlonkOracle = PlonkOracle {
-        index: 0,
-        blinding: false,
-    };
-    pub const WIRES: PlonkOracle = PlonkOracle {
-        index: 1,
-        blinding: true,
+    pub const CONSTANTS_SIGMAS: PlonkOracle = PlonkOracle { index: 0 };
+    pub const WIRES: PlonkOracle = PlonkOracle { index: 1 };
+    pub const ZS_PARTIAL_PRODUCTS: PlonkOracle = PlonkOracle { index: 2 };
+    pub const QUOTIENT: PlonkOracle = PlonkOracle { index: 3 };
+}
+
+/// Which of the prover's oracles are blinded, i.e. have random salts added to their Merkle leaves,
+/// when zero-knowledge is enabled. The preprocessed constants and sigmas are never blinded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct OracleBlinding {
+    pub wires: bool,
+    pub zs_partial_products: bool,
+    pub quotient: bool,
+}
+
+impl OracleBlinding {
+    /// Blinds every oracle which depends on the witness.
+    pub const ALL: OracleBlinding = OracleBlinding {
+        wires: true,
+        zs_partial_products: true,
+        quotient: true,
     };
-    pub const ZS_PARTIAL_PRODUCTS: PlonkOracle = PlonkOracle {
-        index: 2,
-        blinding: true,
+
+    /// Blinds only the wires oracle.
+    pub const WIRES_ONLY: OracleBlinding = OracleBlinding {
+        wires: true,
+        zs_partial_products: false,
+        quotient: false,
     };
-    pub const QUOTIENT: PlonkOracle = PlonkOracle {
-        index: 3,
-        blinding: true,
+
+    /// Whether each of the prover's oracles is blinded, in the order of their indices.
+    pub(crate) fn by_oracle(&self) -> [bool; 4] {
+        [false, self.wires, self.zs_partial_products, self.quotient]
+    }
+
+    /// Whether `oracle` is blinded, or an error if it isn't one of the prover's oracles.
+    pub(crate) fn is_blinded(&self, oracle: PlonkOracle) -> Result<bool> {
+        self.by_oracle()
+            .get(oracle.index)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown oracle {}", oracle.index))
+    }
+}
+
+/// The number of bits by which the rate of each oracle exceeds FRI's `rate_bits`. FRI only queries
+/// each oracle on its own LDE domain, so any oracle may be committed at a higher rate, at the cost of
+/// a larger LDE and longer Merkle proofs.
+///
+/// The quotient is computed from the LDEs of the other oracles, so their rates must be at least
+/// the quotient's degree factor, whereas the quotient polynomials themselves need no more than
+/// FRI's rate. Running FRI at a low rate, with more queries, while only the oracles which the
+/// quotient is computed from are committed at a high rate, saves the largest LDEs.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct OracleRates {
+    pub constants_sigmas: usize,
+    pub wires: usize,
+    pub zs_partial_products: usize,
+    pub quotient: usize,
+}
+
+impl OracleRates {
+    /// Commits to every oracle at FRI's rate.
+    pub const UNIFORM: OracleRates = OracleRates {
+        constants_sigmas: 0,
+        wires: 0,
+        zs_partial_products: 0,
+        quotient: 0,
     };
 
-    pub(crate) const fn as_fri_oracle(&self) -> FriOracleInfo {
-        FriOracleInfo {
-            blinding: self.blinding,
+    /// Commits to every oracle which the quotient is computed from with `extra_rate_bits` more
+    /// than FRI's rate, and to the quotient at FRI's rate.
+    pub const fn quotient_inputs(extra_rate_bits: usize) -> OracleRates {
+        OracleRates {
+            constants_sigmas: extra_rate_bits,
+            wires: extra_rate_bits,
+            zs_partial_products: extra_rate_bits,
+            quotient: 0,
+        }
+    }
+
+    pub(crate) fn extra_rate_bits(&self, oracle: PlonkOracle) -> usize {
+        match oracle.index {
+            0 => self.constants_sigmas,
+            1 => self.wires,
+            2 => self.zs_partial_products,
+            3 => self.quotient,
+            _ => panic!("Unknown oracle"),
         }
     }
-}
 
-pub fn salt_size(salted: bool) -> usize {
-    if salted {
-        SALT_SIZE
-    } else {
-        0
+    /// The smallest extra rate of the oracles which the quotient is computed from.
+    pub(crate) fn min_quotient_input_extra_rate_bits(&self) -> usize {
+        self.constants_sigmas
+            .min(self.wires)
+            .min(self.zs_partial_products)
     }
 }
 
@@ -147,3 +204,17 @@ pub fn reduce_with_powers_ext_recursive<F: RichField + Extendable<D>, const D: u
     let mut alpha = ReducingFactorTarget::new(alpha);
     alpha.reduce(terms, builder)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::plonk::plonk_common::{OracleBlinding, PlonkOracle};
+
+    #[test]
+    fn test_is_blinded() {
+        let blinding = OracleBlinding::WIRES_ONLY;
+        assert!(!blinding.is_blinded(PlonkOracle::CONSTANTS_SIGMAS).unwrap());
+        assert!(blinding.is_blinded(PlonkOracle::WIRES).unwrap());
+        assert!(!blinding.is_blinded(PlonkOracle::QUOTIENT).unwrap());
+        assert!(blinding.is_blinded(PlonkOracle { index: 4 }).is_err());
+    }
+}
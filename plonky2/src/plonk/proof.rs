@@ -1,41 +1,45 @@
+use std::io::BufRead;
+
 use anyhow::ensure;
 use plonky2_field::extension_field::Extendable;
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::fri::oracle::PolynomialBatch;
-use crate::fri::proof::{
-    CompressedFriProof, FriChallenges, FriChallengesTarget, FriProof, FriProofTarget,
-};
+use crate::fri::proof::{FriChallenges, FriChallengesTarget, FriProofTarget};
 use crate::fri::structure::{
     FriOpeningBatch, FriOpeningBatchTarget, FriOpenings, FriOpeningsTarget,
 };
 use crate::fri::FriParams;
-use crate::hash::hash_types::{MerkleCapTarget, RichField};
-use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::hash_types::{HashOut, MerkleCapTarget, RichField};
+use crate::hash::public_inputs::PublicInputsHasher;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::verifier::verify_with_challenges;
-use crate::util::serialization::Buffer;
+use crate::plonk::pcs::{
+    PlonkCommitment, PlonkCompressedOpeningProof, PlonkOpeningProof, PlonkPcs,
+    PolynomialCommitmentScheme,
+};
+use crate::plonk::verifier::verify_openings;
+use crate::util::maybe_rayon::*;
+use crate::util::serialization::{proof_size_report, Buffer, Encoding, ProofSizeReport};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
 pub struct Proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
-    /// Merkle cap of LDEs of wire values.
-    pub wires_cap: MerkleCap<F, C::Hasher>,
-    /// Merkle cap of LDEs of Z, in the context of Plonk's permutation argument.
-    pub plonk_zs_partial_products_cap: MerkleCap<F, C::Hasher>,
-    /// Merkle cap of LDEs of the quotient polynomial components.
-    pub quotient_polys_cap: MerkleCap<F, C::Hasher>,
+    /// Commitment to the wire values.
+    pub wires_cap: PlonkCommitment<F, C, D>,
+    /// Commitment to Z, in the context of Plonk's permutation argument, and the partial products.
+    pub plonk_zs_partial_products_cap: PlonkCommitment<F, C, D>,
+    /// Commitment to the quotient polynomial components.
+    pub quotient_polys_cap: PlonkCommitment<F, C, D>,
     /// Purported values of each polynomial at the challenge point.
     pub openings: OpeningSet<F, D>,
-    /// A batch FRI argument for all openings.
-    pub opening_proof: FriProof<F, C::Hasher, D>,
+    /// A batch opening proof for all openings.
+    pub opening_proof: PlonkOpeningProof<F, C, D>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ProofTarget<const D: usize> {
     pub wires_cap: MerkleCapTarget,
     pub plonk_zs_partial_products_cap: MerkleCapTarget,
@@ -45,8 +49,12 @@ pub struct ProofTarget<const D: usize> {
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Proof<F, C, D> {
-    /// Compress the proof.
-    pub fn compress(self, indices: &[usize], params: &FriParams) -> CompressedProof<F, C, D> {
+    /// Compress the proof, given the challenges of its opening proof.
+    pub(crate) fn compress(
+        self,
+        challenges: &ProofChallenges<F, D>,
+        params: &FriParams,
+    ) -> CompressedProof<F, C, D> {
         let Proof {
             wires_cap,
             plonk_zs_partial_products_cap,
@@ -60,7 +68,11 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> P
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
-            opening_proof: opening_proof.compress::<C>(indices, params),
+            opening_proof: <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::compress(
+                opening_proof,
+                &challenges.fri_challenges,
+                params,
+            ),
         }
     }
 }
@@ -83,18 +95,27 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         self,
         common_data: &CommonCircuitData<F, C, D>,
     ) -> anyhow::Result<CompressedProofWithPublicInputs<F, C, D>> {
-        let indices = self.fri_query_indices(common_data)?;
-        let compressed_proof = self.proof.compress(&indices, &common_data.fri_params);
+        let challenges =
+            self.get_challenges(self.get_public_inputs_hash_with(common_data), common_data)?;
+        let compressed_proof = self.proof.compress(&challenges, &common_data.fri_params);
         Ok(CompressedProofWithPublicInputs {
             public_inputs: self.public_inputs,
             proof: compressed_proof,
         })
     }
 
-    pub(crate) fn get_public_inputs_hash(
+    /// The public inputs hash, assuming that the circuit has no public input groups. Use
+    /// `get_public_inputs_hash_with` for circuits which may have some.
+    pub fn get_public_inputs_hash(&self) -> HashOut<F> {
+        C::PublicInputsHasher::hash_public_inputs(&self.public_inputs)
+    }
+
+    /// The public inputs hash, as computed by the circuit described by `common_data`.
+    pub fn get_public_inputs_hash_with(
         &self,
-    ) -> <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash {
-        C::InnerHasher::hash_no_pad(&self.public_inputs)
+        common_data: &CommonCircuitData<F, C, D>,
+    ) -> HashOut<F> {
+        common_data.hash_public_inputs(&self.public_inputs)
     }
 
     pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
@@ -111,22 +132,44 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         let proof = buffer.read_proof_with_public_inputs(common_data)?;
         Ok(proof)
     }
+
+    /// Reads a proof serialized by `to_bytes` from `reader`, without buffering all of its bytes.
+    /// The proof is read until the end of the stream.
+    pub fn from_reader<R: BufRead>(
+        reader: R,
+        common_data: &CommonCircuitData<F, C, D>,
+    ) -> anyhow::Result<Self> {
+        let mut buffer = Buffer::with_stream(reader, Encoding::Compact);
+        let proof = buffer.read_proof_with_public_inputs(common_data)?;
+        Ok(proof)
+    }
+
+    /// The number of bytes taken by each component of this proof, as serialized by `to_bytes`.
+    pub fn size_report(&self) -> anyhow::Result<ProofSizeReport> {
+        self.size_report_with_encoding(Encoding::Compact)
+    }
+
+    /// The number of bytes taken by each component of this proof, when serialized with the given
+    /// encoding.
+    pub fn size_report_with_encoding(&self, encoding: Encoding) -> anyhow::Result<ProofSizeReport> {
+        Ok(proof_size_report(self, encoding)?)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
 pub struct CompressedProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
 {
-    /// Merkle cap of LDEs of wire values.
-    pub wires_cap: MerkleCap<F, C::Hasher>,
-    /// Merkle cap of LDEs of Z, in the context of Plonk's permutation argument.
-    pub plonk_zs_partial_products_cap: MerkleCap<F, C::Hasher>,
-    /// Merkle cap of LDEs of the quotient polynomial components.
-    pub quotient_polys_cap: MerkleCap<F, C::Hasher>,
+    /// Commitment to the wire values.
+    pub wires_cap: PlonkCommitment<F, C, D>,
+    /// Commitment to Z, in the context of Plonk's permutation argument, and the partial products.
+    pub plonk_zs_partial_products_cap: PlonkCommitment<F, C, D>,
+    /// Commitment to the quotient polynomial components.
+    pub quotient_polys_cap: PlonkCommitment<F, C, D>,
     /// Purported values of each polynomial at the challenge point.
     pub openings: OpeningSet<F, D>,
-    /// A compressed batch FRI argument for all openings.
-    pub opening_proof: CompressedFriProof<F, C::Hasher, D>,
+    /// A compressed batch opening proof for all openings.
+    pub opening_proof: PlonkCompressedOpeningProof<F, C, D>,
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
@@ -137,10 +180,11 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         self,
         challenges: &ProofChallenges<F, D>,
         fri_inferred_elements: FriInferredElements<F, D>,
-        params: &FriParams,
+        common_data: &CommonCircuitData<F, C, D>,
     ) -> Proof<F, C, D>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         let CompressedProof {
             wires_cap,
@@ -155,7 +199,12 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
-            opening_proof: opening_proof.decompress::<C>(challenges, fri_inferred_elements, params),
+            opening_proof: opening_proof.decompress::<C>(
+                challenges,
+                fri_inferred_elements,
+                &common_data.config.fri_oracles(),
+                &common_data.fri_params,
+            ),
         }
     }
 }
@@ -180,12 +229,14 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
-        let challenges = self.get_challenges(self.get_public_inputs_hash(), common_data)?;
+        let challenges =
+            self.get_challenges(self.get_public_inputs_hash_with(common_data), common_data)?;
         let fri_inferred_elements = self.get_inferred_elements(&challenges, common_data);
         let decompressed_proof =
             self.proof
-                .decompress(&challenges, fri_inferred_elements, &common_data.fri_params);
+                .decompress(&challenges, fri_inferred_elements, common_data);
         Ok(ProofWithPublicInputs {
             public_inputs: self.public_inputs,
             proof: decompressed_proof,
@@ -199,30 +250,44 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     ) -> anyhow::Result<()>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         ensure!(
             self.public_inputs.len() == common_data.num_public_inputs,
             "Number of public inputs doesn't match circuit data."
         );
-        let public_inputs_hash = self.get_public_inputs_hash();
+        let public_inputs_hash = self.get_public_inputs_hash_with(common_data);
         let challenges = self.get_challenges(public_inputs_hash, common_data)?;
-        let fri_inferred_elements = self.get_inferred_elements(&challenges, common_data);
-        let decompressed_proof =
-            self.proof
-                .decompress(&challenges, fri_inferred_elements, &common_data.fri_params);
-        verify_with_challenges(
-            decompressed_proof,
-            public_inputs_hash,
-            challenges,
-            verifier_data,
+        let proof = &self.proof;
+        verify_openings(
+            &proof.openings,
+            &public_inputs_hash,
+            &challenges,
             common_data,
+        )?;
+
+        // The opening proof is checked in its compressed form, rather than decompressed first.
+        let commitments = &[
+            verifier_data.constants_sigmas_cap.clone(),
+            proof.wires_cap.clone(),
+            proof.plonk_zs_partial_products_cap.clone(),
+            proof.quotient_polys_cap.clone(),
+        ];
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::verify_compressed(
+            &common_data.get_fri_instance(challenges.plonk_zeta),
+            &proof.openings.to_fri_openings(),
+            &challenges.fri_challenges,
+            commitments,
+            &proof.opening_proof,
+            &common_data.fri_params,
         )
     }
 
-    pub(crate) fn get_public_inputs_hash(
+    pub(crate) fn get_public_inputs_hash_with(
         &self,
-    ) -> <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash {
-        C::InnerHasher::hash_no_pad(&self.public_inputs)
+        common_data: &CommonCircuitData<F, C, D>,
+    ) -> HashOut<F> {
+        common_data.hash_public_inputs(&self.public_inputs)
     }
 
     pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
@@ -239,6 +304,17 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         let proof = buffer.read_compressed_proof_with_public_inputs(common_data)?;
         Ok(proof)
     }
+
+    /// Reads a proof serialized by `to_bytes` from `reader`, without buffering all of its bytes.
+    /// The proof is read until the end of the stream.
+    pub fn from_reader<R: BufRead>(
+        reader: R,
+        common_data: &CommonCircuitData<F, C, D>,
+    ) -> anyhow::Result<Self> {
+        let mut buffer = Buffer::with_stream(reader, Encoding::Compact);
+        let proof = buffer.read_compressed_proof_with_public_inputs(common_data)?;
+        Ok(proof)
+    }
 }
 
 pub(crate) struct ProofChallenges<F: RichField + Extendable<D>, const D: usize> {
@@ -270,7 +346,7 @@ pub(crate) struct FriInferredElements<F: RichField + Extendable<D>, const D: usi
     pub Vec<F::Extension>,
 );
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ProofWithPublicInputsTarget<const D: usize> {
     pub proof: ProofTarget<D>,
     pub public_inputs: Vec<Target>,
@@ -286,6 +362,8 @@ pub struct OpeningSet<F: RichField + Extendable<D>, const D: usize> {
     pub plonk_zs_right: Vec<F::Extension>,
     pub partial_products: Vec<F::Extension>,
     pub quotient_polys: Vec<F::Extension>,
+    /// The values of the polynomials of each of the circuit's `ExtraOpening`s.
+    pub extra: Vec<Vec<F::Extension>>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
@@ -304,6 +382,12 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
                 .map(|p| p.to_extension().eval(z))
                 .collect::<Vec<_>>()
         };
+        let commitments = [
+            constants_sigmas_commitment,
+            wires_commitment,
+            zs_partial_products_commitment,
+            quotient_polys_commitment,
+        ];
         let constants_sigmas_eval = eval_commitment(zeta, constants_sigmas_commitment);
         let zs_partial_products_eval = eval_commitment(zeta, zs_partial_products_commitment);
         Self {
@@ -317,6 +401,22 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
             partial_products: zs_partial_products_eval[common_data.partial_products_range()]
                 .to_vec(),
             quotient_polys: eval_commitment(zeta, quotient_polys_commitment),
+            extra: common_data
+                .extra_openings
+                .iter()
+                .map(|opening| {
+                    let point = opening.point::<D>(zeta);
+                    opening
+                        .polynomials
+                        .par_iter()
+                        .map(|p| {
+                            commitments[p.oracle_index].polynomials[p.polynomial_index]
+                                .to_extension()
+                                .eval(point)
+                        })
+                        .collect()
+                })
+                .collect(),
         }
     }
 
@@ -335,8 +435,14 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
         let zeta_right_batch = FriOpeningBatch {
             values: self.plonk_zs_right.clone(),
         };
+        let extra_batches = self.extra.iter().map(|values| FriOpeningBatch {
+            values: values.clone(),
+        });
         FriOpenings {
-            batches: vec![zeta_batch, zeta_right_batch],
+            batches: [zeta_batch, zeta_right_batch]
+                .into_iter()
+                .chain(extra_batches)
+                .collect(),
         }
     }
 }
@@ -351,6 +457,7 @@ pub struct OpeningSetTarget<const D: usize> {
     pub plonk_zs_right: Vec<ExtensionTarget<D>>,
     pub partial_products: Vec<ExtensionTarget<D>>,
     pub quotient_polys: Vec<ExtensionTarget<D>>,
+    pub extra: Vec<Vec<ExtensionTarget<D>>>,
 }
 
 impl<const D: usize> OpeningSetTarget<D> {
@@ -369,24 +476,36 @@ impl<const D: usize> OpeningSetTarget<D> {
         let zeta_right_batch = FriOpeningBatchTarget {
             values: self.plonk_zs_right.clone(),
         };
+        let extra_batches = self.extra.iter().map(|values| FriOpeningBatchTarget {
+            values: values.clone(),
+        });
         FriOpeningsTarget {
-            batches: vec![zeta_batch, zeta_right_batch],
+            batches: [zeta_batch, zeta_right_batch]
+                .into_iter()
+                .chain(extra_batches)
+                .collect(),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::{BufReader, Write};
+
     use anyhow::Result;
-    use plonky2_field::field_types::Field;
+    use plonky2_field::field_types::{Field, Field64};
 
     use crate::fri::reduction_strategies::FriReductionStrategy;
     use crate::gates::noop::NoopGate;
-    use crate::iop::witness::PartialWitness;
+    use crate::iop::witness::{PartialWitness, Witness};
     use crate::plonk::circuit_builder::CircuitBuilder;
-    use crate::plonk::circuit_data::CircuitConfig;
-    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::circuit_data::{CircuitConfig, VerifierCircuitData};
+    use crate::plonk::config::{
+        GenericConfig, KeccakPoseidonGoldilocksConfig, PoseidonGoldilocksConfig,
+    };
+    use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
     use crate::plonk::verifier::verify;
+    use crate::util::serialization::{Buffer, Encoding};
 
     #[test]
     fn test_proof_compression() -> Result<()> {
@@ -425,4 +544,354 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)?;
         data.verify_compressed(compressed_proof)
     }
+
+    #[test]
+    fn test_prove_with_seed() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_zk_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let x_value = F::rand();
+        let prove = |seed| {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, x_value);
+            data.prove_with_seed(pw, seed)
+        };
+        let proof = prove([1; 32])?;
+        let same_seed_proof = prove([1; 32])?;
+        let other_seed_proof = prove([2; 32])?;
+
+        // The commitments are determined by the seed. Later parts of the proof needn't be, as the
+        // proof-of-work witness is whichever one the grinder finds first.
+        assert_eq!(proof.proof.wires_cap, same_seed_proof.proof.wires_cap);
+        assert_eq!(
+            proof.proof.quotient_polys_cap,
+            same_seed_proof.proof.quotient_polys_cap
+        );
+        assert_ne!(proof.proof.wires_cap, other_seed_proof.proof.wires_cap);
+
+        data.verify(proof)?;
+        data.verify(other_seed_proof)
+    }
+
+    #[test]
+    fn test_deterministic_proofs() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_zk_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let mut data = builder.build::<C>();
+        data.set_deterministic_seed([7; 32]);
+
+        let x_value = F::rand();
+        let prove = || {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, x_value);
+            data.prove(pw)
+        };
+        let proof = prove()?;
+        assert_eq!(proof.to_bytes()?, prove()?.to_bytes()?);
+        data.verify(proof)
+    }
+
+    #[derive(Default)]
+    struct CountingWriter {
+        bytes: Vec<u8>,
+        num_writes: usize,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.num_writes += 1;
+            self.bytes.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_prove_to_writer() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_zk_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let mut data = builder.build::<C>();
+        data.set_deterministic_seed([7; 32]);
+
+        let x_value = F::rand();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, x_value);
+        let proof = data.prove(pw.clone())?;
+        let mut writer = CountingWriter::default();
+        data.prove_to_writer(pw, &mut writer)?;
+        let bytes = writer.bytes;
+        assert_eq!(bytes, proof.to_bytes()?);
+        // Writes are buffered rather than made for each field element.
+        assert!(writer.num_writes < bytes.len() / 1024);
+
+        let streamed_proof = ProofWithPublicInputs::from_bytes(bytes, &data.common)?;
+        data.verify(streamed_proof)
+    }
+
+    #[test]
+    fn test_lazy_compressed_verification() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        // Many queries on a small tree, so that paths are shared.
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 2]);
+        config.fri_config.num_query_rounds = 60;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let compressed_proof = proof.compress(&data.common)?;
+        data.verify_compressed(compressed_proof.clone())?;
+
+        let mut wrong_step = compressed_proof.clone();
+        let rounds = &mut wrong_step.proof.opening_proof.query_round_proofs;
+        rounds.steps[1].values_mut().next().unwrap().evals[0] += FF::ONE;
+        assert!(data.verify_compressed(wrong_step).is_err());
+
+        let mut wrong_leaf = compressed_proof.clone();
+        let rounds = &mut wrong_leaf.proof.opening_proof.query_round_proofs;
+        let initial_trees_proof = rounds.initial_trees_proofs.values_mut().next().unwrap();
+        initial_trees_proof.evals_proofs[1].0[0] += F::ONE;
+        assert!(data.verify_compressed(wrong_leaf).is_err());
+
+        let mut wrong_sibling = compressed_proof;
+        let rounds = &mut wrong_sibling.proof.opening_proof.query_round_proofs;
+        let step = rounds.steps[0]
+            .values_mut()
+            .find(|step| !step.merkle_proof.siblings.is_empty())
+            .unwrap();
+        step.merkle_proof.siblings.pop();
+        assert!(data.verify_compressed(wrong_sibling).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_commit_phase_hasher() -> Result<()> {
+        const D: usize = 2;
+        type C = KeccakPoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 1]);
+        config.fri_config.num_query_rounds = 50;
+
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.constant(F::rand());
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        let bytes = proof.to_bytes()?;
+        let deserialized = ProofWithPublicInputs::from_bytes(bytes, &data.common)?;
+        assert_eq!(proof, deserialized);
+
+        let compressed_proof = proof.clone().compress(&data.common)?;
+        assert_eq!(proof, compressed_proof.clone().decompress(&data.common)?);
+
+        verify(proof, &data.verifier_only, &data.common)?;
+        data.verify_compressed(compressed_proof)
+    }
+
+    #[test]
+    fn test_size_report() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        pw.set_target(x, F::rand());
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        let report = proof.size_report()?;
+        assert_eq!(report.total(), proof.to_bytes()?.len());
+        assert_eq!(
+            report.query_rounds.len(),
+            config.fri_config.num_query_rounds
+        );
+        assert_eq!(report.public_inputs, 8);
+        assert_eq!(report.pow_witness, 8);
+
+        let report = proof.size_report_with_encoding(Encoding::Evm)?;
+        let mut buffer = Buffer::with_encoding(Vec::new(), Encoding::Evm);
+        buffer.write_proof_with_public_inputs(&proof)?;
+        assert_eq!(report.total(), buffer.len());
+        assert_eq!(report.public_inputs, 32);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_reader() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        pw.set_target(x, F::rand());
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        // A small buffer, so that field elements straddle the reader's reads.
+        let bytes = proof.to_bytes()?;
+        let reader = BufReader::with_capacity(7, bytes.as_slice());
+        assert_eq!(
+            proof,
+            ProofWithPublicInputs::from_reader(reader, &data.common)?
+        );
+        let truncated = &bytes[..bytes.len() - 3];
+        assert!(ProofWithPublicInputs::<F, C, D>::from_reader(truncated, &data.common).is_err());
+        // Only the circuit's public inputs are read, so an extra one is rejected.
+        let mut extended = bytes.clone();
+        extended.extend(1u64.to_le_bytes());
+        assert!(
+            ProofWithPublicInputs::<F, C, D>::from_reader(extended.as_slice(), &data.common)
+                .is_err()
+        );
+
+        let compressed_proof = proof.compress(&data.common)?;
+        let bytes = compressed_proof.to_bytes()?;
+        let reader = BufReader::with_capacity(7, bytes.as_slice());
+        assert_eq!(
+            compressed_proof,
+            CompressedProofWithPublicInputs::from_reader(reader, &data.common)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_raw() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let verifier_data = |num_public_inputs| {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let targets = builder.add_virtual_targets(num_public_inputs);
+            builder.register_public_inputs(&targets);
+            let mut pw = PartialWitness::new();
+            for &t in &targets {
+                pw.set_target(t, F::rand());
+            }
+            for _ in 0..100 {
+                builder.add_gate(NoopGate, vec![]);
+            }
+            let data = builder.build::<C>();
+            let proof = data.prove(pw);
+            let verifier_data = VerifierCircuitData {
+                verifier_only: data.verifier_only,
+                common: data.common,
+            };
+            (verifier_data, proof)
+        };
+        let (data, proof) = verifier_data(1);
+        let proof = proof?;
+        let bytes = proof.to_bytes()?;
+        let digest = data.digest();
+        data.verify_raw(&bytes, digest, &proof.public_inputs)?;
+
+        // Wrong public inputs are rejected.
+        assert!(data.verify_raw(&bytes, digest, &[F::ONE]).is_err());
+        // Verifier data for another circuit doesn't have the pinned digest.
+        let (other_data, _) = verifier_data(2);
+        assert_ne!(other_data.digest(), digest);
+        assert!(other_data
+            .verify_raw(&bytes, digest, &proof.public_inputs)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_non_canonical_encodings() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        pw.set_target(x, F::ONE);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        let bytes = proof.to_bytes()?;
+        assert_eq!(
+            proof,
+            ProofWithPublicInputs::from_bytes(bytes.clone(), &data.common)?
+        );
+
+        // The public input 1, encoded as 1 + p.
+        let mut non_canonical_input = bytes.clone();
+        let n = bytes.len();
+        non_canonical_input[n - 8..].copy_from_slice(&(1 + F::ORDER).to_le_bytes());
+        assert!(
+            ProofWithPublicInputs::<F, C, D>::from_bytes(non_canonical_input, &data.common)
+                .is_err()
+        );
+
+        // The first element of the wires cap's first hash, replaced by a value exceeding p.
+        let mut non_canonical_hash = bytes;
+        non_canonical_hash[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(
+            ProofWithPublicInputs::<F, C, D>::from_bytes(non_canonical_hash, &data.common).is_err()
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,186 @@
+//! Chaining of sequential proofs, so that each proof is bound to the ones before it.
+//!
+//! The first four public inputs of a chained circuit are a seed, which is the hash of the previous
+//! proof's public inputs, or a genesis seed for the first proof. The challenger observes the hash of
+//! the public inputs before anything else, so the transcript of proof `N + 1` is seeded by a
+//! commitment to proof `N`, and through its seed, to every earlier proof. A `ProofChain` tracks the
+//! expected seed on both sides: the prover uses it to fill in each proof's seed, and the verifier
+//! uses it to reject proofs which are out of order, repeated, or from another chain.
+
+use anyhow::{ensure, Result};
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::iop::witness::{PartialWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitData, CommonCircuitData, VerifierCircuitData};
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::proof::ProofWithPublicInputs;
+
+/// The state of a chain of proofs: how many proofs it holds, and the seed the next one must carry.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ProofChain<F: RichField> {
+    seed: HashOut<F>,
+    len: usize,
+}
+
+impl<F: RichField> ProofChain<F> {
+    /// An empty chain whose first proof will carry `genesis_seed`, which should be unique to the
+    /// chain, e.g. a hash of its identifier.
+    pub fn new(genesis_seed: HashOut<F>) -> Self {
+        Self {
+            seed: genesis_seed,
+            len: 0,
+        }
+    }
+
+    /// The seed of the next proof.
+    pub fn seed(&self) -> HashOut<F> {
+        self.seed
+    }
+
+    /// The number of proofs in the chain so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Proves the next proof of the chain, setting its seed in `inputs`. The circuit must have been
+    /// built with `CircuitBuilder::proof_chain_seed`.
+    pub fn prove<C: GenericConfig<D, F = F>, const D: usize>(
+        &mut self,
+        data: &CircuitData<F, C, D>,
+        mut inputs: PartialWitness<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        F: Extendable<D>,
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        let public_inputs = &data.prover_only.public_inputs;
+        ensure!(
+            public_inputs.len() >= 4,
+            "The circuit has no proof chain seed."
+        );
+        inputs.set_hash_target(
+            HashOutTarget::from_vec(public_inputs[..4].to_vec()),
+            self.seed,
+        );
+        let proof = data.prove(inputs)?;
+        *self = self.next(&data.common, &proof)?;
+        Ok(proof)
+    }
+
+    /// Verifies the next proof of the chain, checking that it carries the expected seed.
+    pub fn verify<C: GenericConfig<D, F = F>, const D: usize>(
+        &mut self,
+        data: &VerifierCircuitData<F, C, D>,
+        proof: ProofWithPublicInputs<F, C, D>,
+    ) -> Result<()>
+    where
+        F: Extendable<D>,
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        let next = self.next(&data.common, &proof)?;
+        data.verify(proof)?;
+        *self = next;
+        Ok(())
+    }
+
+    /// The state of the chain after appending `proof`.
+    fn next<C: GenericConfig<D, F = F>, const D: usize>(
+        &self,
+        common_data: &CommonCircuitData<F, C, D>,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<Self>
+    where
+        F: Extendable<D>,
+    {
+        ensure!(
+            proof.public_inputs.len() >= 4 && proof.public_inputs[..4] == self.seed.elements,
+            "Proof doesn't carry the expected proof chain seed."
+        );
+        Ok(Self {
+            seed: proof.get_public_inputs_hash_with(common_data),
+            len: self.len + 1,
+        })
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Registers the proof chain seed as the first four public inputs. It's set by
+    /// `ProofChain::prove`, and the circuit may use it, e.g. to bind other data to the chain.
+    pub fn proof_chain_seed(&mut self) -> HashOutTarget {
+        assert!(
+            self.public_inputs.is_empty(),
+            "The proof chain seed must be the first public input"
+        );
+        let seed = self.add_virtual_hash();
+        self.register_public_inputs(&seed.elements);
+        seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::hash::hash_types::HashOut;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, VerifierCircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof_chain::ProofChain;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_proof_chain() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        builder.proof_chain_seed();
+        let x = builder.add_virtual_target();
+        let x_squared = builder.square(x);
+        builder.register_public_input(x_squared);
+        let data = builder.build::<C>();
+
+        let genesis_seed = HashOut::rand();
+        let mut prover_chain = ProofChain::new(genesis_seed);
+        let proofs = (0..3)
+            .map(|i| {
+                let mut pw = PartialWitness::new();
+                pw.set_target(x, F::from_canonical_u64(i));
+                prover_chain.prove(&data, pw)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(prover_chain.len(), 3);
+
+        let data = VerifierCircuitData {
+            verifier_only: data.verifier_only,
+            common: data.common,
+        };
+
+        // Proofs are rejected out of order, or in another chain.
+        let mut chain = ProofChain::new(genesis_seed);
+        assert!(chain.verify(&data, proofs[1].clone()).is_err());
+        assert!(ProofChain::new(HashOut::rand())
+            .verify(&data, proofs[0].clone())
+            .is_err());
+
+        for proof in &proofs {
+            chain.verify(&data, proof.clone())?;
+        }
+        assert_eq!(chain, prover_chain);
+
+        // A proof can't be repeated.
+        assert!(chain.verify(&data, proofs[2].clone()).is_err());
+        Ok(())
+    }
+}
@@ -6,48 +6,78 @@ use plonky2_field::extension_field::Extendable;
 use plonky2_field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use plonky2_field::zero_poly_coset::ZeroPolyOnCoset;
 use plonky2_util::{ceil_div_usize, log2_ceil};
-use rayon::prelude::*;
+use rand::RngCore;
+use tracing::{field, info_span};
 
 use crate::field::field_types::Field;
+use crate::fri::lde_cache::LdeCache;
 use crate::fri::oracle::PolynomialBatch;
-use crate::hash::hash_types::RichField;
+use crate::hash::hash_types::{HashOut, RichField};
 use crate::iop::challenger::Challenger;
 use crate::iop::generator::generate_partial_witness;
 use crate::iop::witness::{MatrixWitness, PartialWitness, Witness};
 use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::pcs::{PcsProverContext, PlonkPcs, PolynomialCommitmentScheme};
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::OpeningSet;
 use crate::plonk::proof::{Proof, ProofWithPublicInputs};
 use crate::plonk::vanishing_poly::eval_vanishing_poly_base_batch;
 use crate::plonk::vars::EvaluationVarsBaseBatch;
 use crate::timed;
+use crate::util::maybe_rayon::*;
 use crate::util::partial_products::{partial_products_and_z_gx, quotient_chunk_products};
+use crate::util::progress::{ProvingMonitor, ProvingPhase};
+use crate::util::serialization::ProofStream;
 use crate::util::timing::TimingTree;
 use crate::util::transpose;
+use crate::util::zeroize::ZeroizeOnDrop;
 
 pub(crate) fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, C, D>,
     inputs: PartialWitness<F>,
     timing: &mut TimingTree,
+    monitor: &ProvingMonitor,
+    wires_cache: Option<&mut LdeCache<F>>,
+    mut rng: &mut dyn RngCore,
+    mut stream: Option<&mut ProofStream>,
 ) -> Result<ProofWithPublicInputs<F, C, D>>
 where
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     let config = &common_data.config;
-    let num_challenges = config.num_challenges;
+    let num_challenges = config.num_challenges();
     let quotient_degree = common_data.quotient_degree();
     let degree = common_data.degree();
-
-    let partition_witness = timed!(
+    let span = info_span!(
+        "prove",
+        degree_bits = common_data.degree_bits,
+        num_wires = config.num_wires,
+        num_challenges,
+        quotient_degree_factor = common_data.quotient_degree_factor,
+        lde_bits = common_data.fri_params.lde_bits(),
+        num_generators = prover_data.generators.len(),
+        num_public_inputs = field::Empty,
+    )
+    .entered();
+
+    // The witness, and the buffers derived from it, are zeroized however this returns.
+    let mut inputs = ZeroizeOnDrop::new(inputs);
+    monitor.start_phase(ProvingPhase::GenerateWitness)?;
+    for &target in &prover_data.blinding_targets {
+        inputs.set_target(target, F::rand_from_rng(&mut rng));
+    }
+    let partition_witness = ZeroizeOnDrop::new(timed!(
         timing,
         &format!("run {} generators", prover_data.generators.len()),
-        generate_partial_witness(inputs, prover_data, common_data)
-    );
+        generate_partial_witness(inputs.into_inner(), prover_data, common_data)
+    ));
 
     let public_inputs = partition_witness.get_targets(&prover_data.public_inputs);
-    let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
+    span.record("num_public_inputs", public_inputs.len());
+    let public_inputs_hash = common_data.hash_public_inputs(&public_inputs);
 
     if cfg!(debug_assertions) {
         // Display the marked targets for debugging purposes.
@@ -56,11 +86,11 @@ where
         }
     }
 
-    let witness = timed!(
+    let witness = ZeroizeOnDrop::new(timed!(
         timing,
         "compute full witness",
         partition_witness.full_witness()
-    );
+    ));
 
     let wires_values: Vec<PolynomialValues<F>> = timed!(
         timing,
@@ -72,26 +102,45 @@ where
             .collect()
     );
 
-    let wires_commitment = timed!(
+    monitor.start_phase(ProvingPhase::CommitWires)?;
+    let fri_oracles = config.fri_oracles();
+    let pcs_params = &common_data.fri_params;
+    let pcs_prover_params = &prover_data.pcs_prover_params;
+    let wires_commitment = ZeroizeOnDrop::new(timed!(
         timing,
         "compute wires commitment",
-        PolynomialBatch::from_values(
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::commit_values(
             wires_values,
-            config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::WIRES.blinding,
-            config.fri_config.cap_height,
-            timing,
-            prover_data.fft_root_table.as_ref(),
+            fri_oracles[PlonkOracle::WIRES.index],
+            pcs_params,
+            pcs_prover_params,
+            wires_cache,
+            &mut PcsProverContext {
+                timing,
+                monitor,
+                rng,
+                stream: None,
+            },
         )
-    );
+    ));
 
     let mut challenger = Challenger::<F, C::Hasher>::new();
+    if let Some(observer) = &prover_data.transcript_observer {
+        challenger.set_observer(observer.clone());
+    }
 
     // Observe the instance.
+    challenger.set_label("instance");
     challenger.observe_hash::<C::Hasher>(common_data.circuit_digest);
     challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
 
-    challenger.observe_cap(&wires_commitment.merkle_tree.cap);
+    challenger.set_label("wires");
+    let wires_cap =
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::commitment(&wires_commitment);
+    challenger.observe_cap(&wires_cap);
+    if let Some(stream) = stream.as_deref_mut() {
+        stream.write_merkle_cap(&wires_cap);
+    }
     let betas = challenger.get_n_challenges(num_challenges);
     let gammas = challenger.get_n_challenges(num_challenges);
 
@@ -112,23 +161,38 @@ where
         .collect();
     let zs_partial_products = [plonk_z_vecs, partial_products_and_zs.concat()].concat();
 
-    let partial_products_and_zs_commitment = timed!(
+    monitor.start_phase(ProvingPhase::CommitPartialProducts)?;
+    let partial_products_and_zs_commitment = ZeroizeOnDrop::new(timed!(
         timing,
         "commit to partial products and Z's",
-        PolynomialBatch::from_values(
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::commit_values(
             zs_partial_products,
-            config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
-            config.fri_config.cap_height,
-            timing,
-            prover_data.fft_root_table.as_ref(),
+            fri_oracles[PlonkOracle::ZS_PARTIAL_PRODUCTS.index],
+            pcs_params,
+            pcs_prover_params,
+            None,
+            &mut PcsProverContext {
+                timing,
+                monitor,
+                rng,
+                stream: None,
+            },
         )
-    );
+    ));
 
-    challenger.observe_cap(&partial_products_and_zs_commitment.merkle_tree.cap);
+    challenger.set_label("zs_partial_products");
+    let plonk_zs_partial_products_cap =
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::commitment(
+            &partial_products_and_zs_commitment,
+        );
+    challenger.observe_cap(&plonk_zs_partial_products_cap);
+    if let Some(stream) = stream.as_deref_mut() {
+        stream.write_merkle_cap(&plonk_zs_partial_products_cap);
+    }
 
     let alphas = challenger.get_n_challenges(num_challenges);
 
+    monitor.start_phase(ProvingPhase::ComputeQuotient)?;
     let quotient_polys = timed!(
         timing,
         "compute quotient polys",
@@ -160,20 +224,31 @@ where
             .collect()
     );
 
-    let quotient_polys_commitment = timed!(
+    monitor.start_phase(ProvingPhase::CommitQuotient)?;
+    let quotient_polys_commitment = ZeroizeOnDrop::new(timed!(
         timing,
         "commit to quotient polys",
-        PolynomialBatch::from_coeffs(
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::commit(
             all_quotient_poly_chunks,
-            config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::QUOTIENT.blinding,
-            config.fri_config.cap_height,
-            timing,
-            prover_data.fft_root_table.as_ref(),
+            fri_oracles[PlonkOracle::QUOTIENT.index],
+            pcs_params,
+            pcs_prover_params,
+            &mut PcsProverContext {
+                timing,
+                monitor,
+                rng,
+                stream: None,
+            },
         )
-    );
-
-    challenger.observe_cap(&quotient_polys_commitment.merkle_tree.cap);
+    ));
+
+    challenger.set_label("quotient");
+    let quotient_polys_cap =
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::commitment(&quotient_polys_commitment);
+    challenger.observe_cap(&quotient_polys_cap);
+    if let Some(stream) = stream.as_deref_mut() {
+        stream.write_merkle_cap(&quotient_polys_cap);
+    }
 
     let zeta = challenger.get_extension_challenge::<D>();
     // To avoid leaking witness data, we want to ensure that our opening locations, `zeta` and
@@ -184,7 +259,17 @@ where
         zeta.exp_power_of_2(common_data.degree_bits) != F::Extension::ONE,
         "Opening point is in the subgroup."
     );
+    for opening in &common_data.extra_openings {
+        ensure!(
+            opening
+                .point::<D>(zeta)
+                .exp_power_of_2(common_data.degree_bits)
+                != F::Extension::ONE,
+            "Extra opening point is in the subgroup."
+        );
+    }
 
+    monitor.start_phase(ProvingPhase::ComputeOpenings)?;
     let openings = timed!(
         timing,
         "construct the opening set",
@@ -198,12 +283,16 @@ where
             common_data,
         )
     );
+    challenger.set_label("openings");
     challenger.observe_openings(&openings.to_fri_openings());
+    if let Some(stream) = stream.as_deref_mut() {
+        stream.write_opening_set(&openings);
+    }
 
     let opening_proof = timed!(
         timing,
         "compute opening proofs",
-        PolynomialBatch::prove_openings(
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::open(
             &common_data.get_fri_instance(zeta),
             &[
                 &prover_data.constants_sigmas_commitment,
@@ -212,15 +301,24 @@ where
                 &quotient_polys_commitment,
             ],
             &mut challenger,
-            &common_data.fri_params,
-            timing,
+            pcs_params,
+            pcs_prover_params,
+            &mut PcsProverContext {
+                timing,
+                monitor,
+                rng,
+                stream: stream.as_deref_mut(),
+            },
         )
-    );
+    )?;
+    if let Some(stream) = stream {
+        stream.write_public_inputs(&public_inputs);
+    }
 
     let proof = Proof {
-        wires_cap: wires_commitment.merkle_tree.cap,
-        plonk_zs_partial_products_cap: partial_products_and_zs_commitment.merkle_tree.cap,
-        quotient_polys_cap: quotient_polys_commitment.merkle_tree.cap,
+        wires_cap,
+        plonk_zs_partial_products_cap,
+        quotient_polys_cap,
         openings,
         opening_proof,
     };
@@ -242,7 +340,7 @@ fn all_wires_permutation_partial_products<
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, C, D>,
 ) -> Vec<Vec<PolynomialValues<F>>> {
-    (0..common_data.config.num_challenges)
+    (0..common_data.config.num_challenges())
         .map(|i| {
             wires_permutation_partial_products_and_zs(
                 witness,
@@ -327,24 +425,24 @@ fn compute_quotient_polys<
 >(
     common_data: &CommonCircuitData<F, C, D>,
     prover_data: &'a ProverOnlyCircuitData<F, C, D>,
-    public_inputs_hash: &<<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+    public_inputs_hash: &HashOut<F>,
     wires_commitment: &'a PolynomialBatch<F, C, D>,
     zs_partial_products_commitment: &'a PolynomialBatch<F, C, D>,
     betas: &[F],
     gammas: &[F],
     alphas: &[F],
 ) -> Vec<PolynomialCoeffs<F>> {
-    let num_challenges = common_data.config.num_challenges;
+    let num_challenges = common_data.config.num_challenges();
     let quotient_degree_bits = log2_ceil(common_data.quotient_degree_factor);
     assert!(
-        quotient_degree_bits <= common_data.config.fri_config.rate_bits,
+        quotient_degree_bits <= common_data.config.max_quotient_degree_bits(),
         "Having constraints of degree higher than the rate is not supported yet. \
         If we need this in the future, we can precompute the larger LDE before computing the `PolynomialBatch`s."
     );
 
     // We reuse the LDE computed in `PolynomialBatch` and extract every `step` points to get
-    // an LDE matching `max_filtered_constraint_degree`.
-    let step = 1 << (common_data.config.fri_config.rate_bits - quotient_degree_bits);
+    // an LDE matching `max_filtered_constraint_degree`. Since oracles may be committed at
+    // different rates, the step depends on the oracle.
     // When opening the `Z`s polys at the "next" point in Plonk, need to look at the point `next_step`
     // steps away since we work on an LDE of degree `max_filtered_constraint_degree`.
     let next_step = 1 << quotient_degree_bits;
@@ -353,8 +451,9 @@ fn compute_quotient_polys<
     let lde_size = points.len();
 
     // Retrieve the LDE values at index `i`.
-    let get_at_index =
-        |comm: &'a PolynomialBatch<F, C, D>, i: usize| -> &'a [F] { comm.get_lde_values(i * step) };
+    let get_at_index = |comm: &'a PolynomialBatch<F, C, D>, i: usize| -> &'a [F] {
+        comm.get_lde_values(i << (comm.rate_bits - quotient_degree_bits))
+    };
 
     let z_h_on_coset = ZeroPolyOnCoset::new(common_data.degree_bits, quotient_degree_bits);
 
@@ -460,9 +559,12 @@ fn compute_quotient_polys<
         .flatten()
         .collect();
 
-    transpose(&quotient_values)
-        .into_par_iter()
+    let quotient_values = transpose(&quotient_values)
+        .into_iter()
         .map(PolynomialValues::new)
-        .map(|values| values.coset_ifft(F::coset_shift()))
-        .collect()
+        .collect();
+    prover_data
+        .pcs_prover_params
+        .fft_backend
+        .batch_coset_ifft(quotient_values, F::coset_shift())
 }
@@ -1,9 +1,12 @@
 use plonky2_field::extension_field::Extendable;
 
 use crate::hash::hash_types::{HashOutTarget, RichField};
+use crate::hash::public_inputs::hash_grouped_public_inputs_circuit;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierCircuitTarget};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::pcs::{PlonkPcs, PolynomialCommitmentScheme};
+use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{
     OpeningSetTarget, ProofChallengesTarget, ProofTarget, ProofWithPublicInputsTarget,
 };
@@ -21,18 +24,43 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         inner_common_data: &CommonCircuitData<F, C, D>,
     ) where
         C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
     {
         assert_eq!(
             proof_with_pis.public_inputs.len(),
             inner_common_data.num_public_inputs
         );
-        let public_inputs_hash =
-            self.hash_n_to_hash_no_pad::<C::InnerHasher>(proof_with_pis.public_inputs.clone());
-        let challenges = proof_with_pis.get_challenges(self, public_inputs_hash, inner_common_data);
+        let public_inputs_hash = hash_grouped_public_inputs_circuit::<F, C::PublicInputsHasher, D>(
+            self,
+            proof_with_pis.public_inputs.clone(),
+            &inner_common_data.public_input_groups,
+        );
 
-        self.verify_proof_with_challenges(
+        self.verify_proof_with_public_inputs_hash(
             proof_with_pis.proof,
             public_inputs_hash,
+            inner_verifier_data,
+            inner_common_data,
+        );
+    }
+
+    /// Recursively verifies an inner proof given only the hash of its public inputs, e.g. when
+    /// the public inputs are large and only their digest is needed by the outer circuit.
+    pub fn verify_proof_with_public_inputs_hash<C: GenericConfig<D, F = F>>(
+        &mut self,
+        proof: ProofTarget<D>,
+        public_inputs_hash: HashOutTarget,
+        inner_verifier_data: &VerifierCircuitTarget,
+        inner_common_data: &CommonCircuitData<F, C, D>,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
+    {
+        let challenges = proof.get_challenges(self, public_inputs_hash, inner_common_data);
+
+        self.verify_proof_with_challenges(
+            proof,
+            public_inputs_hash,
             challenges,
             inner_verifier_data,
             inner_common_data,
@@ -49,6 +77,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         inner_common_data: &CommonCircuitData<F, C, D>,
     ) where
         C::Hasher: AlgebraicHasher<F>,
+        C::CommitPhaseHasher: AlgebraicHasher<F>,
     {
         let one = self.one_extension();
 
@@ -110,7 +139,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         with_context!(
             self,
             "verify FRI proof",
-            self.verify_fri_proof::<C>(
+            <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::verify_circuit(
+                self,
                 &fri_instance,
                 &proof.openings.to_fri_openings(),
                 &challenges.fri_challenges,
@@ -133,7 +163,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
-    fn add_virtual_proof<InnerC: GenericConfig<D, F = F>>(
+    pub fn add_virtual_proof<InnerC: GenericConfig<D, F = F>>(
         &mut self,
         common_data: &CommonCircuitData<F, InnerC, D>,
     ) -> ProofTarget<D> {
@@ -141,11 +171,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let fri_params = &common_data.fri_params;
         let cap_height = fri_params.config.cap_height;
 
+        let fri_oracles = config.fri_oracles();
+        let salt_size =
+            |oracle: PlonkOracle| fri_params.oracle_salt_size(fri_oracles[oracle.index]);
         let num_leaves_per_oracle = &[
-            common_data.num_preprocessed_polys(),
-            config.num_wires,
-            common_data.num_zs_partial_products_polys(),
-            common_data.num_quotient_polys(),
+            common_data.num_preprocessed_polys() + salt_size(PlonkOracle::CONSTANTS_SIGMAS),
+            config.num_wires + salt_size(PlonkOracle::WIRES),
+            common_data.num_zs_partial_products_polys()
+                + salt_size(PlonkOracle::ZS_PARTIAL_PRODUCTS),
+            common_data.num_quotient_polys() + salt_size(PlonkOracle::QUOTIENT),
         ];
 
         ProofTarget {
@@ -153,7 +187,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             plonk_zs_partial_products_cap: self.add_virtual_cap(cap_height),
             quotient_polys_cap: self.add_virtual_cap(cap_height),
             openings: self.add_opening_set(common_data),
-            opening_proof: self.add_virtual_fri_proof(num_leaves_per_oracle, fri_params),
+            opening_proof: self.add_virtual_fri_proof(
+                num_leaves_per_oracle,
+                &fri_oracles,
+                fri_params,
+            ),
         }
     }
 
@@ -162,7 +200,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         common_data: &CommonCircuitData<F, InnerC, D>,
     ) -> OpeningSetTarget<D> {
         let config = &common_data.config;
-        let num_challenges = config.num_challenges;
+        let num_challenges = config.num_challenges();
         let total_partial_products = num_challenges * common_data.num_partial_products;
         OpeningSetTarget {
             constants: self.add_virtual_extension_targets(common_data.num_constants),
@@ -172,6 +210,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             plonk_zs_right: self.add_virtual_extension_targets(num_challenges),
             partial_products: self.add_virtual_extension_targets(total_partial_products),
             quotient_polys: self.add_virtual_extension_targets(common_data.num_quotient_polys()),
+            extra: common_data
+                .extra_openings
+                .iter()
+                .map(|opening| self.add_virtual_extension_targets(opening.polynomials.len()))
+                .collect(),
         }
     }
 }
@@ -180,18 +223,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 mod tests {
     use anyhow::Result;
     use log::{info, Level};
+    use plonky2_field::field_types::Field;
+    use plonky2_field::ops::Square;
+    use rand::thread_rng;
 
     use super::*;
     use crate::fri::reduction_strategies::FriReductionStrategy;
-    use crate::fri::FriConfig;
+    use crate::fri::{FriConfig, SaltMode};
     use crate::gates::noop::NoopGate;
     use crate::iop::witness::{PartialWitness, Witness};
     use crate::plonk::circuit_data::{CircuitConfig, VerifierOnlyCircuitData};
     use crate::plonk::config::{
         GenericConfig, Hasher, KeccakGoldilocksConfig, PoseidonGoldilocksConfig,
     };
+    use crate::plonk::plonk_common::{OracleBlinding, OracleRates};
     use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
     use crate::plonk::prover::prove;
+    use crate::util::progress::ProvingMonitor;
     use crate::util::timing::TimingTree;
 
     #[test]
@@ -279,6 +327,7 @@ mod tests {
             fri_config: FriConfig {
                 rate_bits: 8,
                 cap_height: 0,
+                commit_phase_cap_height: 0,
                 proof_of_work_bits: 20,
                 reduction_strategy: FriReductionStrategy::MinSize(None),
                 num_query_rounds: 10,
@@ -316,6 +365,230 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_recursive_verifier_wires_only_blinding() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let inner_config = CircuitConfig {
+            blinding: OracleBlinding::WIRES_ONLY,
+            salt_size: 2,
+            ..CircuitConfig::standard_recursion_zk_config()
+        };
+        let (proof, vd, cd) = dummy_proof::<F, C, D>(&inner_config, 4_000)?;
+        let wires_leaf_len = proof.proof.opening_proof.query_round_proofs[0]
+            .initial_trees_proof
+            .evals_proofs[PlonkOracle::WIRES.index]
+            .0
+            .len();
+        assert_eq!(wires_leaf_len, cd.config.num_wires + 2);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let (proof, _vd, cd) =
+            recursive_proof::<F, C, C, D>(proof, vd, cd, &config, None, false, false)?;
+        test_serialization(&proof, &cd)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_masked_salts() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let inner_config = CircuitConfig {
+            salt_mode: SaltMode::Masked,
+            ..CircuitConfig::standard_recursion_zk_config()
+        };
+        let (proof, vd, cd) = dummy_proof::<F, C, D>(&inner_config, 4_000)?;
+        let initial_trees_proof =
+            &proof.proof.opening_proof.query_round_proofs[0].initial_trees_proof;
+        let wires_leaf_len = initial_trees_proof.evals_proofs[PlonkOracle::WIRES.index]
+            .0
+            .len();
+        assert_eq!(wires_leaf_len, cd.config.num_wires + D);
+        test_serialization(&proof, &cd)?;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let (proof, _vd, cd) =
+            recursive_proof::<F, C, C, D>(proof, vd, cd, &config, None, false, false)?;
+        test_serialization(&proof, &cd)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_commit_phase_cap_height() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut inner_config = CircuitConfig::standard_recursion_config();
+        inner_config.fri_config.commit_phase_cap_height = 1;
+        let (proof, vd, cd) = dummy_proof::<F, C, D>(&inner_config, 4_000)?;
+        let fri_proof = &proof.proof.opening_proof;
+        assert!(fri_proof
+            .commit_phase_merkle_caps
+            .iter()
+            .all(|cap| cap.0.len() == 2));
+        test_serialization(&proof, &cd)?;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let (proof, _vd, cd) =
+            recursive_proof::<F, C, C, D>(proof, vd, cd, &config, None, false, false)?;
+        test_serialization(&proof, &cd)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_extra_openings() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        for _ in 0..4_000 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let g = F::primitive_root_of_unity(12);
+        builder.add_extra_opening(
+            g.square(),
+            &[(PlonkOracle::WIRES, 0), (PlonkOracle::WIRES, 3)],
+        );
+        builder.add_extra_opening(F::NEG_ONE, &[(PlonkOracle::QUOTIENT, 1)]);
+        let data = builder.build::<C>();
+        assert_eq!(data.common.degree_bits, 12);
+        let proof = data.prove(PartialWitness::new())?;
+        assert_eq!(proof.proof.openings.extra.len(), 2);
+        assert_eq!(proof.proof.openings.extra[0].len(), 2);
+        data.verify(proof.clone())?;
+
+        let mut tampered = proof.clone();
+        tampered.proof.openings.extra[1][0] += <C as GenericConfig<D>>::FE::ONE;
+        assert!(data.verify(tampered).is_err());
+        let mut truncated = proof.clone();
+        truncated.proof.openings.extra.pop();
+        assert!(data.verify(truncated).is_err());
+        test_serialization(&proof, &data.common)?;
+
+        let (proof, _vd, cd) = recursive_proof::<F, C, C, D>(
+            proof,
+            data.verifier_only,
+            data.common,
+            &config,
+            None,
+            false,
+            false,
+        )?;
+        test_serialization(&proof, &cd)?;
+
+        Ok(())
+    }
+
+    /// Runs FRI at rate 1/2, with the oracles which the quotient is computed from committed at rate
+    /// 1/8.
+    #[test]
+    fn test_recursive_verifier_mixed_oracle_rates() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let standard_config = CircuitConfig::standard_recursion_config();
+        let inner_config = CircuitConfig {
+            oracle_rates: OracleRates::quotient_inputs(2),
+            fri_config: FriConfig {
+                rate_bits: 1,
+                num_query_rounds: 84,
+                ..standard_config.fri_config.clone()
+            },
+            ..standard_config.clone()
+        };
+        let (proof, vd, cd) = dummy_proof::<F, C, D>(&inner_config, 4_000)?;
+        let initial_trees_proof =
+            &proof.proof.opening_proof.query_round_proofs[0].initial_trees_proof;
+        let proof_len = |oracle: PlonkOracle| {
+            initial_trees_proof.evals_proofs[oracle.index]
+                .1
+                .siblings
+                .len()
+        };
+        assert_eq!(
+            proof_len(PlonkOracle::WIRES),
+            proof_len(PlonkOracle::QUOTIENT) + 2
+        );
+        test_serialization(&proof, &cd)?;
+
+        let (proof, _vd, cd) =
+            recursive_proof::<F, C, C, D>(proof, vd, cd, &standard_config, None, false, false)?;
+        test_serialization(&proof, &cd)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_with_public_inputs_hash() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let inputs = builder.add_virtual_targets(100);
+        builder.register_public_inputs(&inputs);
+        let mut pw = PartialWitness::new();
+        for &t in &inputs {
+            pw.set_target(t, F::rand());
+        }
+        let inner_data = builder.build::<C>();
+        let inner_proof = inner_data.prove(pw)?;
+        let public_inputs_hash = inner_proof.get_public_inputs_hash_with(&inner_data.common);
+
+        inner_data.verify_with_public_inputs_hash(inner_proof.proof.clone(), public_inputs_hash)?;
+        let mut wrong_hash = public_inputs_hash;
+        wrong_hash.elements[0] += F::ONE;
+        assert!(inner_data
+            .verify_with_public_inputs_hash(inner_proof.proof.clone(), wrong_hash)
+            .is_err());
+
+        // The outer circuit only exposes the digest of the inner public inputs.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        let pt = builder.add_virtual_proof(&inner_data.common);
+        pw.set_proof_target(&pt, &inner_proof.proof);
+        let hash_target = builder.add_virtual_hash();
+        pw.set_hash_target(hash_target, public_inputs_hash);
+        builder.register_public_inputs(&hash_target.elements);
+        let inner_vd = VerifierCircuitTarget {
+            constants_sigmas_cap: builder
+                .add_virtual_cap(inner_data.common.config.fri_config.cap_height),
+        };
+        pw.set_cap_target(
+            &inner_vd.constants_sigmas_cap,
+            &inner_data.verifier_only.constants_sigmas_cap,
+        );
+        builder.verify_proof_with_public_inputs_hash(
+            pt,
+            hash_target,
+            &inner_vd,
+            &inner_data.common,
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, public_inputs_hash.elements);
+        data.verify(proof)
+    }
+
     /// Creates a dummy proof which should have roughly `num_dummy_gates` gates.
     fn dummy_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
         config: &CircuitConfig,
@@ -327,6 +600,7 @@ mod tests {
     )>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         let mut builder = CircuitBuilder::<F, D>::new(config.clone());
         for _ in 0..num_dummy_gates {
@@ -361,7 +635,9 @@ mod tests {
     )>
     where
         InnerC::Hasher: AlgebraicHasher<F>,
+        InnerC::CommitPhaseHasher: AlgebraicHasher<F>,
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         let mut builder = CircuitBuilder::<F, D>::new(config.clone());
         let mut pw = PartialWitness::new();
@@ -395,7 +671,16 @@ mod tests {
         let data = builder.build::<C>();
 
         let mut timing = TimingTree::new("prove", Level::Debug);
-        let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
+        let proof = prove(
+            &data.prover_only,
+            &data.common,
+            pw,
+            &mut timing,
+            &ProvingMonitor::default(),
+            None,
+            &mut thread_rng(),
+            None,
+        )?;
         if print_timing {
             timing.print();
         }
@@ -416,6 +701,7 @@ mod tests {
     ) -> Result<()>
     where
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         let proof_bytes = proof.to_bytes()?;
         info!("Proof length: {} bytes", proof_bytes.len());
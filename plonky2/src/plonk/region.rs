@@ -0,0 +1,79 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+
+/// A named part of a circuit, created with `CircuitBuilder::region`, along with the gates, virtual
+/// targets and public inputs which were added inside it.
+///
+/// Batched operations, such as arithmetic, are attributed to the region which added the gate
+/// holding them, which may not be the region which requested them.
+///
+/// Regions can be serialized, along with the values of their public inputs (see
+/// `Buffer::write_region_public_inputs`), to store or send a subcircuit's outputs without the rest
+/// of the circuit's.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CircuitRegion {
+    pub name: String,
+    /// The index of the enclosing region, if any. Regions are listed in the order they were
+    /// entered, so a parent always precedes its children.
+    pub parent: Option<usize>,
+    /// The indices of the gates added inside this region.
+    pub gates: Range<usize>,
+    /// The indices of the virtual targets added inside this region.
+    pub virtual_targets: Range<usize>,
+    /// The indices, within the circuit's public inputs, of those registered inside this region.
+    pub public_inputs: Range<usize>,
+}
+
+impl CircuitRegion {
+    pub(crate) fn new(
+        name: &str,
+        parent: Option<usize>,
+        num_gates: usize,
+        num_virtual_targets: usize,
+        num_public_inputs: usize,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            parent,
+            gates: num_gates..num_gates,
+            virtual_targets: num_virtual_targets..num_virtual_targets,
+            public_inputs: num_public_inputs..num_public_inputs,
+        }
+    }
+
+    /// The values of the public inputs registered inside this region, out of those of all the
+    /// circuit's public inputs.
+    pub fn public_input_values<'a, T>(&self, public_inputs: &'a [T]) -> &'a [T] {
+        &public_inputs[self.public_inputs.clone()]
+    }
+
+    /// Whether `target` is a virtual target, or the wire of a gate, added inside this region.
+    pub fn contains(&self, target: Target) -> bool {
+        match target {
+            Target::Wire(Wire { gate, .. }) => self.gates.contains(&gate),
+            Target::VirtualTarget { index } => self.virtual_targets.contains(&index),
+        }
+    }
+}
+
+/// The index of the innermost region of `regions` containing `target`, if any.
+pub(crate) fn innermost_region(regions: &[CircuitRegion], target: Target) -> Option<usize> {
+    // Children follow their parents and siblings don't overlap, so the last match is innermost.
+    regions.iter().rposition(|r| r.contains(target))
+}
+
+/// The names of the region at `index` and its ancestors, outermost first, separated by `/`.
+pub(crate) fn region_path(regions: &[CircuitRegion], index: usize) -> String {
+    let mut names = vec![regions[index].name.as_str()];
+    let mut parent = regions[index].parent;
+    while let Some(i) = parent {
+        names.push(&regions[i].name);
+        parent = regions[i].parent;
+    }
+    names.reverse();
+    names.join("/")
+}
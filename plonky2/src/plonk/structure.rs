@@ -0,0 +1,271 @@
+//! Introspection of the shape of proofs and circuits, to diagnose proofs which fail to verify
+//! because of a parameter mismatch rather than a bad witness.
+//!
+//! A `Structure` is a list of named parameters. Proofs and `CommonCircuitData`s describe themselves
+//! with the same names wherever a parameter of the circuit determines the shape of its proofs, so
+//! that comparing a proof's structure with that of the circuit it's checked against points at the
+//! mismatched parameter.
+
+use std::fmt::{self, Display, Formatter};
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_util::log2_strict;
+
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::GenericConfig;
+use crate::plonk::proof::ProofWithPublicInputs;
+
+/// The named parameters describing the shape of a proof or a circuit.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Structure {
+    pub entries: Vec<(&'static str, String)>,
+}
+
+/// A parameter whose values differ between two structures.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StructureDifference {
+    pub name: &'static str,
+    pub left: String,
+    pub right: String,
+}
+
+impl Structure {
+    fn push<T: Display>(&mut self, name: &'static str, value: T) {
+        self.entries.push((name, value.to_string()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The parameters which both structures describe but with different values, in the order of
+    /// `self`. Parameters described by only one of them are ignored, so a proof's structure can be
+    /// compared with that of a circuit.
+    pub fn differences(&self, other: &Self) -> Vec<StructureDifference> {
+        self.entries
+            .iter()
+            .filter_map(|(name, left)| {
+                let right = other.get(name)?;
+                (left != right).then(|| StructureDifference {
+                    name,
+                    left: left.clone(),
+                    right: right.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Display for Structure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.entries {
+            writeln!(f, "{:<32} {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for StructureDifference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<32} {} != {}", self.name, self.left, self.right)
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    ProofWithPublicInputs<F, C, D>
+{
+    /// Describes the shape of this proof.
+    pub fn structure(&self) -> Structure {
+        let proof = &self.proof;
+        let openings = &proof.openings;
+        let fri_proof = &proof.opening_proof;
+        let mut s = Structure::default();
+        s.push("public inputs", self.public_inputs.len());
+        s.push("cap height", log2_strict(proof.wires_cap.len()));
+        s.push("wires cap", proof.wires_cap.len());
+        s.push(
+            "Zs and partial products cap",
+            proof.plonk_zs_partial_products_cap.len(),
+        );
+        s.push("quotient polys cap", proof.quotient_polys_cap.len());
+        s.push("openings: constants", openings.constants.len());
+        s.push("openings: plonk sigmas", openings.plonk_sigmas.len());
+        s.push("openings: wires", openings.wires.len());
+        s.push("openings: plonk Zs", openings.plonk_zs.len());
+        s.push("openings: plonk Zs right", openings.plonk_zs_right.len());
+        s.push(
+            "openings: partial products",
+            openings.partial_products.len(),
+        );
+        s.push("openings: quotient polys", openings.quotient_polys.len());
+        s.push(
+            "openings: extra",
+            format!(
+                "{:?}",
+                openings.extra.iter().map(Vec::len).collect::<Vec<_>>()
+            ),
+        );
+        s.push("FRI query rounds", fri_proof.query_round_proofs.len());
+        // The arities can be read from the coset evaluations opened at each step of any query.
+        let arity_bits = fri_proof.query_round_proofs.first().map(|round| {
+            round
+                .steps
+                .iter()
+                .map(|step| log2_strict(step.evals.len()))
+                .collect::<Vec<_>>()
+        });
+        if let Some(arity_bits) = arity_bits {
+            s.push("FRI reduction arity bits", format!("{:?}", arity_bits));
+        }
+        s.push(
+            "FRI commit phase caps",
+            format!(
+                "{:?}",
+                fri_proof
+                    .commit_phase_merkle_caps
+                    .iter()
+                    .map(|cap| cap.len())
+                    .collect::<Vec<_>>()
+            ),
+        );
+        s.push("FRI final poly length", fri_proof.final_poly.len());
+        s
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    CommonCircuitData<F, C, D>
+{
+    /// Describes the parameters of this circuit, including the shape of its proofs.
+    pub fn structure(&self) -> Structure {
+        let config = &self.config;
+        let fri_config = &config.fri_config;
+        let num_challenges = config.num_challenges();
+        let mut s = Structure::default();
+        s.push("circuit digest", format!("{:?}", self.circuit_digest));
+        s.push("degree bits", self.degree_bits);
+        s.push(
+            "gates",
+            format!(
+                "{:?}",
+                self.gates.iter().map(|g| g.gate.0.id()).collect::<Vec<_>>()
+            ),
+        );
+        s.push("num wires", config.num_wires);
+        s.push("num routed wires", config.num_routed_wires);
+        s.push("num challenges", num_challenges);
+        s.push("zero knowledge", config.zero_knowledge);
+        s.push("quotient degree factor", self.quotient_degree_factor);
+        s.push("public inputs", self.num_public_inputs);
+        s.push("cap height", fri_config.cap_height);
+        s.push("wires cap", 1 << fri_config.cap_height);
+        s.push("Zs and partial products cap", 1 << fri_config.cap_height);
+        s.push("quotient polys cap", 1 << fri_config.cap_height);
+        s.push("openings: constants", self.num_constants);
+        s.push("openings: plonk sigmas", config.num_routed_wires);
+        s.push("openings: wires", config.num_wires);
+        s.push("openings: plonk Zs", num_challenges);
+        s.push("openings: plonk Zs right", num_challenges);
+        s.push(
+            "openings: partial products",
+            num_challenges * self.num_partial_products,
+        );
+        s.push(
+            "openings: quotient polys",
+            num_challenges * self.quotient_degree_factor,
+        );
+        s.push(
+            "openings: extra",
+            format!(
+                "{:?}",
+                self.extra_openings
+                    .iter()
+                    .map(|opening| opening.polynomials.len())
+                    .collect::<Vec<_>>()
+            ),
+        );
+        s.push("FRI rate bits", fri_config.rate_bits);
+        s.push("FRI query rounds", fri_config.num_query_rounds);
+        s.push(
+            "FRI reduction arity bits",
+            format!("{:?}", self.fri_params.reduction_arity_bits),
+        );
+        s.push(
+            "FRI commit phase caps",
+            format!(
+                "{:?}",
+                vec![
+                    1usize << fri_config.commit_phase_cap_height;
+                    self.fri_params.reduction_arity_bits.len()
+                ]
+            ),
+        );
+        s.push("FRI final poly length", self.fri_params.final_poly_len());
+        s.push("FRI proof of work bits", fri_config.proof_of_work_bits);
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn noop_circuit(config: CircuitConfig) -> CircuitData<F, C, D> {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        builder.build::<C>()
+    }
+
+    #[test]
+    fn test_structure_differences() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let data = noop_circuit(config.clone());
+        let proof = data.prove(PartialWitness::new())?;
+        assert!(proof
+            .structure()
+            .differences(&data.common.structure())
+            .is_empty());
+
+        let mut other_config = config;
+        other_config.fri_config.num_query_rounds += 1;
+        let other_data = noop_circuit(other_config);
+        let other_proof = other_data.prove(PartialWitness::new())?;
+        let differences = proof
+            .structure()
+            .differences(&other_data.common.structure());
+        assert_eq!(differences.len(), 1);
+        assert_eq!(differences[0].name, "FRI query rounds");
+        assert_eq!(
+            proof
+                .structure()
+                .differences(&other_proof.structure())
+                .iter()
+                .map(|d| d.name)
+                .collect::<Vec<_>>(),
+            ["FRI query rounds"]
+        );
+        assert!(data
+            .common
+            .structure()
+            .differences(&other_data.common.structure())
+            .iter()
+            .any(|d| d.name == "FRI query rounds"));
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+//! Subcircuits let a gadget be built once and then instantiated many times, by copying its rows
+//! and copy constraints into the parent circuit rather than running the gadget code again.
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::gates::gate::GateInstance;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CircuitConfig;
+
+/// A gadget built once into standalone rows, which `CircuitBuilder::add_subcircuit` can
+/// instantiate in any circuit with the same wire layout.
+///
+/// The witness of a subcircuit must be generated entirely by its gates, so gadgets which add their
+/// own witness generators, such as those computing inverses or bit decompositions outside of a gate,
+/// can't be made into subcircuits.
+pub struct Subcircuit<F: RichField + Extendable<D>, const D: usize> {
+    num_wires: usize,
+    num_routed_wires: usize,
+    gate_instances: Vec<GateInstance<F, D>>,
+    copy_constraints: Vec<(Target, Target)>,
+    num_virtual_targets: usize,
+    inputs: Vec<Target>,
+    outputs: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Subcircuit<F, D> {
+    /// Builds the gadget `f` into a subcircuit with `num_inputs` inputs. `f` is given the input
+    /// targets, and returns the targets to expose as outputs.
+    pub fn new(
+        config: &CircuitConfig,
+        num_inputs: usize,
+        f: impl FnOnce(&mut CircuitBuilder<F, D>, &[Target]) -> Vec<Target>,
+    ) -> Self {
+        let mut builder = CircuitBuilder::new(config.clone());
+        let inputs = builder.add_virtual_targets(num_inputs);
+        let outputs = f(&mut builder, &inputs);
+        // Pad partially used gates now, since the parent can't share their free slots.
+        builder.fill_batched_gates();
+
+        assert!(
+            builder.generators.is_empty(),
+            "A subcircuit's witness must be generated by its gates, but {} generators were added",
+            builder.generators.len()
+        );
+        assert!(
+            builder.public_inputs.is_empty(),
+            "A subcircuit can't register public inputs"
+        );
+
+        Self {
+            num_wires: config.num_wires,
+            num_routed_wires: config.num_routed_wires,
+            gate_instances: builder.gate_instances,
+            copy_constraints: builder
+                .copy_constraints
+                .into_iter()
+                .map(|c| c.pair)
+                .collect(),
+            num_virtual_targets: builder.virtual_target_index,
+            inputs,
+            outputs,
+        }
+    }
+
+    pub fn num_gates(&self) -> usize {
+        self.gate_instances.len()
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds an instance of `subcircuit`, with its inputs connected to `inputs`, and returns its
+    /// outputs.
+    pub fn add_subcircuit(
+        &mut self,
+        subcircuit: &Subcircuit<F, D>,
+        inputs: &[Target],
+    ) -> Vec<Target> {
+        assert_eq!(
+            (subcircuit.num_wires, subcircuit.num_routed_wires),
+            (self.config.num_wires, self.config.num_routed_wires),
+            "The subcircuit was built with a different wire layout"
+        );
+        assert_eq!(
+            inputs.len(),
+            subcircuit.num_inputs(),
+            "Wrong number of subcircuit inputs"
+        );
+
+        let gate_offset = self.num_gates();
+        for instance in &subcircuit.gate_instances {
+            self.add_gate_ref(instance.gate_ref.clone(), instance.constants.clone());
+        }
+        let virtual_targets = self.add_virtual_targets(subcircuit.num_virtual_targets);
+        let relocate = |t: Target| match t {
+            Target::Wire(Wire { gate, input }) => Target::wire(gate_offset + gate, input),
+            Target::VirtualTarget { index } => virtual_targets[index],
+        };
+
+        let (xs, ys): (Vec<_>, Vec<_>) = subcircuit
+            .copy_constraints
+            .iter()
+            .map(|&(x, y)| (relocate(x), relocate(y)))
+            .unzip();
+        self.connect_slices(&xs, &ys);
+
+        let subcircuit_inputs = subcircuit
+            .inputs
+            .iter()
+            .map(|&t| relocate(t))
+            .collect::<Vec<_>>();
+        self.connect_slices(inputs, &subcircuit_inputs);
+
+        subcircuit.outputs.iter().map(|&t| relocate(t)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+    use crate::plonk::subcircuit::Subcircuit;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_subcircuit() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        // Computes `hash(x * y + 3, x)`.
+        let subcircuit = Subcircuit::<F, D>::new(&config, 2, |builder, inputs| {
+            let (x, y) = (inputs[0], inputs[1]);
+            let three = builder.constant(F::from_canonical_u64(3));
+            let z = builder.mul_add(x, y, three);
+            builder
+                .hash_n_to_hash_no_pad::<PoseidonHash>(vec![z, x])
+                .elements
+                .to_vec()
+        });
+
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..4 {
+            let (x, y) = (F::rand(), F::rand());
+            let inputs = builder.add_virtual_targets(2);
+            pw.set_target(inputs[0], x);
+            pw.set_target(inputs[1], y);
+
+            let outputs = builder.add_subcircuit(&subcircuit, &inputs);
+            let expected = PoseidonHash::hash_no_pad(&[x * y + F::from_canonical_u64(3), x]);
+            for (&output, &e) in outputs.iter().zip(&expected.elements) {
+                let e = builder.constant(e);
+                builder.connect(output, e);
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "must be generated by its gates")]
+    fn test_subcircuit_rejects_generators() {
+        let config = CircuitConfig::standard_recursion_config();
+        Subcircuit::<F, D>::new(&config, 1, |builder, inputs| {
+            vec![builder.inverse(inputs[0])]
+        });
+    }
+}
@@ -50,7 +50,7 @@ pub(crate) fn eval_vanishing_poly<
 
     let l1_x = plonk_common::eval_l_1(common_data.degree(), x);
 
-    for i in 0..common_data.config.num_challenges {
+    for i in 0..common_data.config.num_challenges() {
         let z_x = local_zs[i];
         let z_gx = next_zs[i];
         vanishing_z_1_terms.push(l1_x * (z_x - F::Extension::ONE));
@@ -132,7 +132,7 @@ pub(crate) fn eval_vanishing_poly_base_batch<
         evaluate_gate_constraints_base_batch(&common_data.gates, num_gate_constraints, vars_batch);
     debug_assert!(constraint_terms_batch.len() == n * num_gate_constraints);
 
-    let num_challenges = common_data.config.num_challenges;
+    let num_challenges = common_data.config.num_challenges();
     let num_routed_wires = common_data.config.num_routed_wires;
 
     let mut numerator_values = Vec::with_capacity(num_routed_wires);
@@ -330,7 +330,7 @@ pub(crate) fn eval_vanishing_poly_recursively<
         s_ids.push(builder.scalar_mul_ext(k, x));
     }
 
-    for i in 0..common_data.config.num_challenges {
+    for i in 0..common_data.config.num_challenges() {
         let z_x = local_zs[i];
         let z_gx = next_zs[i];
 
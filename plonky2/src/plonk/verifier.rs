@@ -1,13 +1,14 @@
 use anyhow::{ensure, Result};
 use plonky2_field::extension_field::Extendable;
 use plonky2_field::field_types::Field;
+use tracing::{debug_span, info_span};
 
-use crate::fri::verifier::verify_fri_proof;
-use crate::hash::hash_types::RichField;
+use crate::hash::hash_types::{HashOut, RichField};
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::pcs::{PlonkPcs, PolynomialCommitmentScheme};
 use crate::plonk::plonk_common::reduce_with_powers;
-use crate::plonk::proof::{Proof, ProofChallenges, ProofWithPublicInputs};
+use crate::plonk::proof::{OpeningSet, Proof, ProofChallenges, ProofWithPublicInputs};
 use crate::plonk::vanishing_poly::eval_vanishing_poly;
 use crate::plonk::vars::EvaluationVars;
 
@@ -18,17 +19,51 @@ pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
 ) -> Result<()>
 where
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     ensure!(
         proof_with_pis.public_inputs.len() == common_data.num_public_inputs,
         "Number of public inputs doesn't match circuit data."
     );
-    let public_inputs_hash = proof_with_pis.get_public_inputs_hash();
-    let challenges = proof_with_pis.get_challenges(public_inputs_hash, common_data)?;
+    let public_inputs_hash = proof_with_pis.get_public_inputs_hash_with(common_data);
 
-    verify_with_challenges(
+    verify_with_public_inputs_hash(
         proof_with_pis.proof,
         public_inputs_hash,
+        verifier_data,
+        common_data,
+    )
+}
+
+/// Verifies a proof given only the hash of its public inputs.
+pub(crate) fn verify_with_public_inputs_hash<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof: Proof<F, C, D>,
+    public_inputs_hash: HashOut<F>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, C, D>,
+) -> Result<()>
+where
+    [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
+{
+    let fri_config = &common_data.config.fri_config;
+    let _span = info_span!(
+        "verify",
+        degree_bits = common_data.degree_bits,
+        num_challenges = common_data.config.num_challenges(),
+        num_query_rounds = fri_config.num_query_rounds,
+        proof_of_work_bits = fri_config.proof_of_work_bits,
+    )
+    .entered();
+    let challenges = debug_span!("get challenges")
+        .in_scope(|| proof.get_challenges(public_inputs_hash, common_data))?;
+    verify_with_challenges(
+        proof,
+        public_inputs_hash,
         challenges,
         verifier_data,
         common_data,
@@ -41,25 +76,80 @@ pub(crate) fn verify_with_challenges<
     const D: usize,
 >(
     proof: Proof<F, C, D>,
-    public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+    public_inputs_hash: HashOut<F>,
     challenges: ProofChallenges<F, D>,
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     common_data: &CommonCircuitData<F, C, D>,
 ) -> Result<()>
 where
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
-    let local_constants = &proof.openings.constants;
-    let local_wires = &proof.openings.wires;
+    // FRI only checks the openings of the batches in the circuit's instance, so a proof must not
+    // omit any extra opening.
+    ensure!(
+        proof.openings.extra.len() == common_data.extra_openings.len()
+            && proof
+                .openings
+                .extra
+                .iter()
+                .zip(&common_data.extra_openings)
+                .all(|(values, opening)| values.len() == opening.polynomials.len()),
+        "Extra openings don't match circuit data."
+    );
+    debug_span!("verify openings").in_scope(|| {
+        verify_openings(
+            &proof.openings,
+            &public_inputs_hash,
+            &challenges,
+            common_data,
+        )
+    })?;
+
+    let merkle_caps = &[
+        verifier_data.constants_sigmas_cap.clone(),
+        proof.wires_cap,
+        proof.plonk_zs_partial_products_cap,
+        proof.quotient_polys_cap,
+    ];
+
+    debug_span!("verify FRI proof").in_scope(|| {
+        <PlonkPcs as PolynomialCommitmentScheme<F, C, D>>::verify(
+            &common_data.get_fri_instance(challenges.plonk_zeta),
+            &proof.openings.to_fri_openings(),
+            &challenges.fri_challenges,
+            merkle_caps,
+            &proof.opening_proof,
+            &common_data.fri_params,
+        )
+    })?;
+
+    Ok(())
+}
+
+/// Checks that the opened values satisfy the vanishing polynomial identities at zeta. This leaves
+/// checking that they are the openings of the committed polynomials to the FRI verifier.
+pub(crate) fn verify_openings<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    openings: &OpeningSet<F, D>,
+    public_inputs_hash: &HashOut<F>,
+    challenges: &ProofChallenges<F, D>,
+    common_data: &CommonCircuitData<F, C, D>,
+) -> Result<()> {
+    let local_constants = &openings.constants;
+    let local_wires = &openings.wires;
     let vars = EvaluationVars {
         local_constants,
         local_wires,
-        public_inputs_hash: &public_inputs_hash,
+        public_inputs_hash,
     };
-    let local_zs = &proof.openings.plonk_zs;
-    let next_zs = &proof.openings.plonk_zs_right;
-    let s_sigmas = &proof.openings.plonk_sigmas;
-    let partial_products = &proof.openings.partial_products;
+    let local_zs = &openings.plonk_zs;
+    let next_zs = &openings.plonk_zs_right;
+    let s_sigmas = &openings.plonk_sigmas;
+    let partial_products = &openings.partial_products;
 
     // Evaluate the vanishing polynomial at our challenge point, zeta.
     let vanishing_polys_zeta = eval_vanishing_poly(
@@ -76,7 +166,7 @@ where
     );
 
     // Check each polynomial identity, of the form `vanishing(x) = Z_H(x) quotient(x)`, at zeta.
-    let quotient_polys_zeta = &proof.openings.quotient_polys;
+    let quotient_polys_zeta = &openings.quotient_polys;
     let zeta_pow_deg = challenges
         .plonk_zeta
         .exp_power_of_2(common_data.degree_bits);
@@ -93,21 +183,5 @@ where
         ensure!(vanishing_polys_zeta[i] == z_h_zeta * reduce_with_powers(chunk, zeta_pow_deg));
     }
 
-    let merkle_caps = &[
-        verifier_data.constants_sigmas_cap.clone(),
-        proof.wires_cap,
-        proof.plonk_zs_partial_products_cap,
-        proof.quotient_polys_cap,
-    ];
-
-    verify_fri_proof::<F, C, D>(
-        &common_data.get_fri_instance(challenges.plonk_zeta),
-        &proof.openings.to_fri_openings(),
-        &challenges.fri_challenges,
-        merkle_caps,
-        &proof.opening_proof,
-        &common_data.fri_params,
-    )?;
-
     Ok(())
 }
@@ -0,0 +1,248 @@
+use anyhow::{ensure, Result};
+use plonky2_field::extension_field::Extendable;
+
+use crate::fri::presets::FriPreset;
+use crate::hash::hash_types::RichField;
+use crate::iop::witness::{PartialWitness, Witness};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CircuitConfig, CircuitData, VerifierCircuitData, VerifierCircuitTarget,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+
+impl CircuitConfig {
+    /// A config for a final wrapper proof, which is optimized for proof size rather than prover
+    /// time. It uses a high rate and a high proof-of-work setting, so that only a handful of FRI
+    /// queries are needed, and a cap height of zero, since the proof is not recursed upon further.
+    pub fn size_optimized_wrapper_config() -> Self {
+        Self {
+            fri_config: FriPreset::OnChain.fri_config(),
+            ..Self::standard_recursion_config()
+        }
+    }
+}
+
+/// A circuit which recursively verifies proofs of a fixed inner circuit, and re-exposes the inner
+/// public inputs as its own. It is built with `CircuitConfig::size_optimized_wrapper_config`, unless
+/// another config is given to `new_with_config`.
+pub struct WrapperCircuit<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    pub data: CircuitData<F, C, D>,
+    proof_with_pis_target: ProofWithPublicInputsTarget<D>,
+    inner_data_target: VerifierCircuitTarget,
+    /// The digest of the inner circuit this wrapper was built for, used to validate cache hits.
+    inner_circuit_digest: Vec<F>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    WrapperCircuit<F, C, D>
+{
+    /// Builds a wrapper circuit for proofs of the given inner circuit.
+    pub fn new<InnerC: GenericConfig<D, F = F>>(
+        inner_data: &VerifierCircuitData<F, InnerC, D>,
+    ) -> Self
+    where
+        InnerC::Hasher: AlgebraicHasher<F>,
+        InnerC::CommitPhaseHasher: AlgebraicHasher<F>,
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        Self::new_with_config(inner_data, CircuitConfig::size_optimized_wrapper_config())
+    }
+
+    /// Like `new`, but with the given config rather than the size-optimized one.
+    pub fn new_with_config<InnerC: GenericConfig<D, F = F>>(
+        inner_data: &VerifierCircuitData<F, InnerC, D>,
+        config: CircuitConfig,
+    ) -> Self
+    where
+        InnerC::Hasher: AlgebraicHasher<F>,
+        InnerC::CommitPhaseHasher: AlgebraicHasher<F>,
+        [(); C::Hasher::HASH_SIZE]:,
+    {
+        let inner_cd = &inner_data.common;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_with_pis_target = builder.add_virtual_proof_with_pis(inner_cd);
+        let inner_data_target = VerifierCircuitTarget {
+            constants_sigmas_cap: builder.add_virtual_cap(inner_cd.config.fri_config.cap_height),
+        };
+        builder.register_public_inputs(&proof_with_pis_target.public_inputs);
+        builder.verify_proof(proof_with_pis_target.clone(), &inner_data_target, inner_cd);
+
+        Self {
+            data: builder.build::<C>(),
+            proof_with_pis_target,
+            inner_data_target,
+            inner_circuit_digest: inner_cd.circuit_digest.to_vec(),
+        }
+    }
+
+    /// Whether this wrapper was built for the given inner circuit.
+    pub fn wraps<InnerC: GenericConfig<D, F = F>>(
+        &self,
+        inner_data: &VerifierCircuitData<F, InnerC, D>,
+    ) -> bool {
+        self.inner_circuit_digest == inner_data.common.circuit_digest.to_vec()
+    }
+
+    /// Proves that the given inner proof verifies.
+    pub fn wrap<InnerC: GenericConfig<D, F = F>>(
+        &self,
+        proof: &ProofWithPublicInputs<F, InnerC, D>,
+        inner_data: &VerifierCircuitData<F, InnerC, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        InnerC::Hasher: AlgebraicHasher<F>,
+        InnerC::CommitPhaseHasher: AlgebraicHasher<F>,
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        ensure!(
+            self.wraps(inner_data),
+            "Wrapper circuit was built for a different inner circuit"
+        );
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&self.proof_with_pis_target, proof);
+        pw.set_cap_target(
+            &self.inner_data_target.constants_sigmas_cap,
+            &inner_data.verifier_only.constants_sigmas_cap,
+        );
+        self.data.prove(pw)
+    }
+}
+
+/// Wraps a proof in a final, size-optimized recursive proof, suitable for posting on-chain.
+///
+/// If `cache` holds a wrapper circuit built for the same inner circuit, it is reused; otherwise a
+/// new wrapper circuit is built and stored in `cache`. Since the wrapper uses a high rate, it works
+/// best when the inner proof is itself a recursive proof with a small degree.
+pub fn wrap_final<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    InnerC: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof: &ProofWithPublicInputs<F, InnerC, D>,
+    inner_data: &VerifierCircuitData<F, InnerC, D>,
+    cache: &mut Option<WrapperCircuit<F, C, D>>,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    InnerC::Hasher: AlgebraicHasher<F>,
+    InnerC::CommitPhaseHasher: AlgebraicHasher<F>,
+    [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
+{
+    if !matches!(cache, Some(wrapper) if wrapper.wraps(inner_data)) {
+        *cache = Some(WrapperCircuit::new(inner_data));
+    }
+    cache.as_ref().unwrap().wrap(proof, inner_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use super::*;
+    use crate::gates::noop::NoopGate;
+    use crate::plonk::config::{KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_wrap_final() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type KC = KeccakGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u32(42));
+        let proof = data.prove(pw)?;
+        let inner_data = VerifierCircuitData {
+            verifier_only: data.verifier_only,
+            common: data.common,
+        };
+
+        // The size-optimized config's high rate makes for a slow prover, so the cache is seeded with
+        // a wrapper built with the standard recursion config, which `wrap_final` then reuses.
+        let mut cache = Some(WrapperCircuit::<F, KC, D>::new_with_config(
+            &inner_data,
+            CircuitConfig::standard_recursion_config(),
+        ));
+        let wrapped = wrap_final::<F, KC, C, D>(&proof, &inner_data, &mut cache)?;
+        let rate_bits = CircuitConfig::standard_recursion_config()
+            .fri_config
+            .rate_bits;
+        assert_eq!(
+            cache
+                .as_ref()
+                .unwrap()
+                .data
+                .common
+                .config
+                .fri_config
+                .rate_bits,
+            rate_bits
+        );
+        assert_eq!(wrapped.public_inputs, proof.public_inputs);
+        cache.as_ref().unwrap().data.verify(wrapped)?;
+
+        // The cached wrapper circuit should be reused for a second proof.
+        let wrapped = wrap_final::<F, KC, C, D>(&proof, &inner_data, &mut cache)?;
+        cache.as_ref().unwrap().data.verify(wrapped)
+    }
+
+    #[test]
+    fn test_size_optimized_wrapper() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type KC = KeccakGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        builder.add_gate(NoopGate, vec![]);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u32(42));
+        let proof = data.prove(pw)?;
+        let inner_data = VerifierCircuitData {
+            verifier_only: data.verifier_only,
+            common: data.common,
+        };
+
+        let standard = WrapperCircuit::<F, KC, D>::new_with_config(
+            &inner_data,
+            CircuitConfig::standard_recursion_config(),
+        );
+        let standard_proof = standard.wrap(&proof, &inner_data)?;
+        let standard_len = standard_proof.to_bytes()?.len();
+        standard.data.verify(standard_proof)?;
+
+        let size_optimized = WrapperCircuit::<F, KC, D>::new(&inner_data);
+        assert_eq!(
+            size_optimized.data.common.config.fri_config,
+            FriPreset::OnChain.fri_config()
+        );
+        let size_optimized_proof = size_optimized.wrap(&proof, &inner_data)?;
+        assert_eq!(size_optimized_proof.public_inputs, proof.public_inputs);
+        let size_optimized_len = size_optimized_proof.to_bytes()?.len();
+        size_optimized.data.verify(size_optimized_proof)?;
+
+        assert!(
+            size_optimized_len < standard_len,
+            "size-optimized wrapper proof is {} bytes, standard is {} bytes",
+            size_optimized_len,
+            standard_len
+        );
+        Ok(())
+    }
+}
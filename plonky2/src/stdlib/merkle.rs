@@ -0,0 +1,122 @@
+//! Merkle membership proofs, natively and in circuits.
+
+use anyhow::Result;
+use plonky2_field::extension_field::Extendable;
+use plonky2_util::log2_strict;
+
+pub use crate::hash::hash_types::MerkleCapTarget;
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_proofs;
+pub use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+pub use crate::hash::merkle_tree::{MerkleCap, MerkleTree};
+use crate::iop::target::Target;
+use crate::iop::witness::Witness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, Hasher};
+
+/// Verifies that `leaf_data` is the leaf at `leaf_index` of the Merkle tree with the given cap.
+pub fn verify_membership<F: RichField, H: Hasher<F>>(
+    leaf_data: Vec<F>,
+    leaf_index: usize,
+    merkle_cap: &MerkleCap<F, H>,
+    proof: &MerkleProof<F, H>,
+) -> Result<()>
+where
+    [(); H::HASH_SIZE]:,
+{
+    merkle_proofs::verify_merkle_proof(leaf_data, leaf_index, merkle_cap, proof)
+}
+
+/// Adds a membership proof target for a tree with `2^height` leaves and a cap of height
+/// `cap_height`.
+pub fn add_virtual_membership_proof<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    height: usize,
+    cap_height: usize,
+) -> MerkleProofTarget {
+    assert!(cap_height <= height, "The cap is higher than the tree");
+    builder.add_virtual_merkle_proof(height - cap_height)
+}
+
+/// Sets the witness for a membership proof target.
+pub fn set_membership_proof_target<F: RichField, H: AlgebraicHasher<F>, W: Witness<F>>(
+    witness: &mut W,
+    target: &MerkleProofTarget,
+    proof: &MerkleProof<F, H>,
+) {
+    for (&t, &sibling) in target.siblings.iter().zip(&proof.siblings) {
+        witness.set_hash_target(t, sibling);
+    }
+}
+
+/// Constrains `leaf_data` to be the leaf at `leaf_index` of the Merkle tree with the given cap. The
+/// index is range-checked against the number of leaves of the tree.
+pub fn assert_membership<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    leaf_data: Vec<Target>,
+    leaf_index: Target,
+    merkle_cap: &MerkleCapTarget,
+    proof: &MerkleProofTarget,
+) {
+    let num_layers = proof.siblings.len();
+    let height = num_layers + log2_strict(merkle_cap.0.len());
+    let leaf_index_bits = builder.split_le(leaf_index, height);
+    let cap_index = builder.le_sum(leaf_index_bits[num_layers..].iter().copied());
+    builder.verify_merkle_proof_with_cap_index::<H>(
+        leaf_data,
+        &leaf_index_bits,
+        cap_index,
+        merkle_cap,
+        proof,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::stdlib::merkle::{
+        add_virtual_membership_proof, assert_membership, set_membership_proof_target,
+        verify_membership, MerkleTree,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::InnerHasher;
+
+    #[test]
+    fn test_membership() -> Result<()> {
+        let height = 5;
+        let cap_height = 2;
+        let leaves = (0..1 << height).map(|_| F::rand_vec(6)).collect();
+        let tree = MerkleTree::<F, H>::new(leaves, cap_height);
+        let index = 21;
+        let proof = tree.prove(index);
+        verify_membership(tree.leaves[index].clone(), index, &tree.cap, &proof)?;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        let leaf_t = builder.add_virtual_targets(6);
+        for (&t, &x) in leaf_t.iter().zip(&tree.leaves[index]) {
+            pw.set_target(t, x);
+        }
+        let index_t = builder.add_virtual_target();
+        pw.set_target(index_t, F::from_canonical_usize(index));
+        let cap_t = builder.add_virtual_cap(cap_height);
+        pw.set_cap_target(&cap_t, &tree.cap);
+        let proof_t = add_virtual_membership_proof(&mut builder, height, cap_height);
+        set_membership_proof_target(&mut pw, &proof_t, &proof);
+        assert_membership::<F, H, D>(&mut builder, leaf_t, index_t, &cap_t, &proof_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
@@ -0,0 +1,16 @@
+//! A curated library of the gadgets most applications need: Merkle membership, range checks, u32
+//! arithmetic, and selection and permutation helpers.
+//!
+//! Unlike the `CircuitBuilder` methods they are built on, which may change as the rest of the crate
+//! evolves, the items of this module form a stable API, versioned by [`VERSION`]. Its major version
+//! is bumped on any breaking change to a signature or to the constraints a gadget enforces, so
+//! application crates can depend on it rather than on the builder's internals.
+
+pub mod merkle;
+pub mod permutation;
+pub mod range;
+pub mod select;
+pub mod uint32;
+
+/// The version of the `stdlib` API, as `(major, minor, patch)`.
+pub const VERSION: (u16, u16, u16) = (1, 0, 0);
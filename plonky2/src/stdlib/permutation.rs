@@ -0,0 +1,105 @@
+//! Permutation arguments.
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// Constrains `b` to be a permutation of `a`.
+///
+/// This is a grand product argument: the polynomials `prod (X - a_i)` and `prod (X - b_i)` must
+/// agree at a challenge point `gamma` in the extension field, derived by hashing both lists so that
+/// it can't be chosen after them. If `b` isn't a permutation of `a`, the polynomials differ, and
+/// they agree at `gamma` with probability at most `a.len() / |F^D|`.
+pub fn assert_permutation<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    a: &[Target],
+    b: &[Target],
+) {
+    assert_eq!(a.len(), b.len(), "Permutation lists have different lengths");
+    if a.is_empty() {
+        return;
+    }
+
+    let inputs = a.iter().chain(b).copied().collect();
+    let gamma = ExtensionTarget(
+        builder
+            .hash_n_to_m_no_pad::<H>(inputs, D)
+            .try_into()
+            .unwrap(),
+    );
+    let a_product = grand_product(builder, gamma, a);
+    let b_product = grand_product(builder, gamma, b);
+    builder.connect_extension(a_product, b_product);
+}
+
+/// Computes `prod (gamma - x_i)`.
+fn grand_product<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    gamma: ExtensionTarget<D>,
+    xs: &[Target],
+) -> ExtensionTarget<D> {
+    let terms = xs
+        .iter()
+        .map(|&x| {
+            let x = builder.convert_to_ext(x);
+            builder.sub_extension(gamma, x)
+        })
+        .collect::<Vec<_>>();
+    builder.mul_many_extension(&terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::stdlib::permutation::assert_permutation;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::InnerHasher;
+
+    fn prove_permutation(a: &[F], b: &[F]) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        let a_t = builder.add_virtual_targets(a.len());
+        let b_t = builder.add_virtual_targets(b.len());
+        for (&t, &x) in a_t.iter().zip(a).chain(b_t.iter().zip(b)) {
+            pw.set_target(t, x);
+        }
+        assert_permutation::<F, H, D>(&mut builder, &a_t, &b_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_permutation() -> Result<()> {
+        let a = F::rand_vec(8);
+        let mut b = a.clone();
+        b.reverse();
+        b.swap(0, 5);
+        prove_permutation(&a, &b)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_not_permutation() {
+        let a = F::rand_vec(8);
+        let mut b = a.clone();
+        b.reverse();
+        b[3] = F::rand();
+        prove_permutation(&a, &b).unwrap();
+    }
+}
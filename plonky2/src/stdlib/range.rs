@@ -0,0 +1,62 @@
+//! Range checks and comparisons of field elements viewed as small integers.
+
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// Constrains `x < 2^num_bits`.
+pub fn range_check<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: Target,
+    num_bits: usize,
+) {
+    builder.range_check(x, num_bits);
+}
+
+/// Constrains `x < 2^num_bits` and returns its `num_bits` little-endian bits.
+pub fn to_bits<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: Target,
+    num_bits: usize,
+) -> Vec<BoolTarget> {
+    builder.split_le(x, num_bits)
+}
+
+/// Returns the integer with the given little-endian bits.
+pub fn from_bits<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bits: &[BoolTarget],
+) -> Target {
+    builder.le_sum(bits.iter())
+}
+
+/// Constrains `x < 2^num_bits` and returns `(low, high)` such that `x = low + 2^split * high` with
+/// `low < 2^split`.
+pub fn split<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: Target,
+    split: usize,
+    num_bits: usize,
+) -> (Target, Target) {
+    builder.split_low_high(x, split, num_bits)
+}
+
+/// Constrains `x <= y`, where both are range-checked to be less than `2^num_bits`.
+pub fn assert_le<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: Target,
+    y: Target,
+    num_bits: usize,
+) {
+    // If `x > y`, then `y - x` wraps around to at least `p - 2^num_bits`, so fails the range check.
+    assert!(
+        (1u128 << (num_bits + 1)) < F::ORDER as u128,
+        "Too many bits to compare"
+    );
+    builder.range_check(x, num_bits);
+    builder.range_check(y, num_bits);
+    let diff = builder.sub(y, x);
+    builder.range_check(diff, num_bits);
+}
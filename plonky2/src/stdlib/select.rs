@@ -0,0 +1,54 @@
+//! Conditional selection and indexed access.
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_util::log2_ceil;
+
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::stdlib::range;
+
+/// Returns `x` if `b` is true, otherwise `y`.
+pub fn select<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    b: BoolTarget,
+    x: Target,
+    y: Target,
+) -> Target {
+    builder.select(b, x, y)
+}
+
+/// Like `select`, but for `ExtensionTarget`s.
+pub fn select_ext<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    b: BoolTarget,
+    x: ExtensionTarget<D>,
+    y: ExtensionTarget<D>,
+) -> ExtensionTarget<D> {
+    builder.select_ext(b, x, y)
+}
+
+/// Returns the one-hot encoding of `index` as `n` bits. Fails to prove unless `index < n`.
+pub fn one_hot<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    index: Target,
+    n: usize,
+) -> Vec<BoolTarget> {
+    builder.one_hot(index, n)
+}
+
+/// Returns `v[index]`. Fails to prove unless `index < v.len()`.
+pub fn index<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    index: Target,
+    v: Vec<Target>,
+) -> Target {
+    assert!(!v.is_empty(), "Cannot index an empty vector");
+    // `random_access` reads zero at indices in its padding, so the bound is checked separately.
+    let last = builder.constant(F::from_canonical_usize(v.len() - 1));
+    range::assert_le(builder, index, last, log2_ceil(v.len()));
+    let element = builder.add_virtual_target();
+    builder.random_access(index, element, v);
+    element
+}
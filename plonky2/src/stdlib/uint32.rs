@@ -0,0 +1,89 @@
+//! Arithmetic on 32-bit unsigned integers.
+
+use plonky2_field::extension_field::Extendable;
+
+pub use crate::gadgets::arithmetic_u32::U32Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::BoolTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// Adds a new `U32Target`, range-checked to be less than `2^32`.
+pub fn add_virtual<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+) -> U32Target {
+    let x = builder.add_virtual_u32_target();
+    builder.range_check_u32(vec![x]);
+    x
+}
+
+/// Returns a constant `U32Target`.
+pub fn constant<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    c: u32,
+) -> U32Target {
+    builder.constant_u32(c)
+}
+
+/// Constrains each of the given targets to be less than `2^32`.
+pub fn range_check<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    xs: Vec<U32Target>,
+) {
+    builder.range_check_u32(xs);
+}
+
+/// Returns `x + y` as a pair `(low, carry)`.
+pub fn add<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: U32Target,
+    y: U32Target,
+) -> (U32Target, U32Target) {
+    builder.add_u32(x, y)
+}
+
+/// Returns the sum of the given targets as a pair `(low, carry)`.
+pub fn add_many<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    xs: &[U32Target],
+) -> (U32Target, U32Target) {
+    builder.add_many_u32(xs)
+}
+
+/// Returns `x - y - borrow` as a pair `(result, borrow)`, where the returned borrow is 1 iff
+/// `y + borrow > x`.
+pub fn sub<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: U32Target,
+    y: U32Target,
+    borrow: U32Target,
+) -> (U32Target, U32Target) {
+    builder.sub_u32(x, y, borrow)
+}
+
+/// Returns `x * y` as a pair `(low, high)`.
+pub fn mul<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: U32Target,
+    y: U32Target,
+) -> (U32Target, U32Target) {
+    builder.mul_u32(x, y)
+}
+
+/// Returns `x * y + z` as a pair `(low, high)`.
+pub fn mul_add<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: U32Target,
+    y: U32Target,
+    z: U32Target,
+) -> (U32Target, U32Target) {
+    builder.mul_add_u32(x, y, z)
+}
+
+/// Returns whether `xs` is at most `ys`, both considered as base-`2^32` limbs of a large value.
+pub fn list_le<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    xs: Vec<U32Target>,
+    ys: Vec<U32Target>,
+) -> BoolTarget {
+    builder.list_le_u32(xs, ys)
+}
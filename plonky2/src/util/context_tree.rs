@@ -1,3 +1,6 @@
+use std::io;
+use std::io::Write;
+
 use log::{log, Level};
 
 /// The hierarchy of contexts, and the gate count contributed by each one. Useful for debugging.
@@ -119,6 +122,45 @@ impl ContextTree {
             child.print_helper(current_gate_count, depth + 1);
         }
     }
+
+    /// Writes the tree in the collapsed stack format read by flamegraph tools such as `inferno` and
+    /// speedscope. Each line lists a scope's ancestors and the scope itself, separated by `;`, then
+    /// the number of gates added directly in that scope rather than in its children.
+    pub fn write_collapsed_stacks<W: Write>(
+        &self,
+        current_gate_count: usize,
+        out: &mut W,
+    ) -> io::Result<()> {
+        self.write_collapsed_stacks_helper(current_gate_count, "", out)
+    }
+
+    fn write_collapsed_stacks_helper<W: Write>(
+        &self,
+        current_gate_count: usize,
+        parent_stack: &str,
+        out: &mut W,
+    ) -> io::Result<()> {
+        // Semicolons would split the frame.
+        let name = self.name.replace(';', ",");
+        let stack = if parent_stack.is_empty() {
+            name
+        } else {
+            format!("{};{}", parent_stack, name)
+        };
+        let children_count = self
+            .children
+            .iter()
+            .map(|c| c.gate_count_delta(current_gate_count))
+            .sum::<usize>();
+        let own_count = self.gate_count_delta(current_gate_count) - children_count;
+        if own_count > 0 {
+            writeln!(out, "{} {}", stack, own_count)?;
+        }
+        for child in &self.children {
+            child.write_collapsed_stacks_helper(current_gate_count, &stack, out)?;
+        }
+        Ok(())
+    }
 }
 
 /// Creates a named scope; useful for debugging.
@@ -0,0 +1,172 @@
+//! Encoding of Merkle caps, public inputs and compressed proofs in the word-aligned layout that a
+//! Solidity verifier consumes from calldata, as described in `Encoding::Evm`. Caps and proofs have
+//! no length prefixes, since their shapes are determined by the circuit, while public inputs are
+//! prefixed by their number.
+
+use anyhow::{ensure, Result};
+use plonky2_field::extension_field::Extendable;
+
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_tree::MerkleCap;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::proof::CompressedProof;
+use crate::util::serialization::{Buffer, Encoding};
+
+pub fn encode_merkle_cap<F: RichField, H: Hasher<F>>(cap: &MerkleCap<F, H>) -> Result<Vec<u8>> {
+    let mut buffer = Buffer::with_encoding(Vec::new(), Encoding::Evm);
+    buffer.write_merkle_cap(cap)?;
+    Ok(buffer.bytes())
+}
+
+pub fn decode_merkle_cap<F: RichField, H: Hasher<F>>(
+    bytes: Vec<u8>,
+    cap_height: usize,
+) -> Result<MerkleCap<F, H>> {
+    let mut buffer = Buffer::with_encoding(bytes, Encoding::Evm);
+    let cap = buffer.read_merkle_cap(cap_height)?;
+    ensure!(buffer.remaining() == 0, "Trailing bytes after Merkle cap");
+    Ok(cap)
+}
+
+pub fn encode_public_inputs<F: RichField>(public_inputs: &[F]) -> Result<Vec<u8>> {
+    let mut buffer = Buffer::with_encoding(Vec::new(), Encoding::Evm);
+    buffer.write_field_vec_with_len(public_inputs)?;
+    Ok(buffer.bytes())
+}
+
+pub fn decode_public_inputs<F: RichField>(bytes: Vec<u8>) -> Result<Vec<F>> {
+    let mut buffer = Buffer::with_encoding(bytes, Encoding::Evm);
+    let public_inputs = buffer.read_field_vec_with_len()?;
+    ensure!(
+        buffer.remaining() == 0,
+        "Trailing bytes after public inputs"
+    );
+    Ok(public_inputs)
+}
+
+pub fn encode_compressed_proof<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof: &CompressedProof<F, C, D>,
+) -> Result<Vec<u8>> {
+    let mut buffer = Buffer::with_encoding(Vec::new(), Encoding::Evm);
+    buffer.write_compressed_proof(proof)?;
+    Ok(buffer.bytes())
+}
+
+pub fn decode_compressed_proof<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    bytes: Vec<u8>,
+    common_data: &CommonCircuitData<F, C, D>,
+) -> Result<CompressedProof<F, C, D>> {
+    let mut buffer = Buffer::with_encoding(bytes, Encoding::Evm);
+    let proof = buffer.read_compressed_proof(common_data)?;
+    ensure!(buffer.remaining() == 0, "Trailing bytes after proof");
+    Ok(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::field_types::{Field, Field64};
+
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::gates::noop::NoopGate;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::CompressedProofWithPublicInputs;
+    use crate::util::evm_calldata::{
+        decode_compressed_proof, decode_merkle_cap, decode_public_inputs, encode_compressed_proof,
+        encode_merkle_cap, encode_public_inputs,
+    };
+
+    const D: usize = 2;
+    type C = KeccakGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_merkle_cap_layout() -> Result<()> {
+        type H = <C as GenericConfig<D>>::Hasher;
+        type PH = <PoseidonGoldilocksConfig as GenericConfig<D>>::Hasher;
+
+        let leaves = (0..16).map(|_| F::rand_vec(8)).collect::<Vec<_>>();
+        let tree = MerkleTree::<F, H>::new(leaves.clone(), 2);
+        let encoded = encode_merkle_cap(&tree.cap)?;
+        // Each 25-byte Keccak digest is left-aligned in its own word.
+        assert_eq!(encoded.len(), 4 * 32);
+        for (word, digest) in encoded.chunks(32).zip(&tree.cap.0) {
+            assert_eq!(&word[..25], &digest.0);
+            assert!(word[25..].iter().all(|&b| b == 0));
+        }
+        assert_eq!(decode_merkle_cap::<F, H>(encoded, 2)?, tree.cap);
+
+        let tree = MerkleTree::<F, PH>::new(leaves, 2);
+        let encoded = encode_merkle_cap(&tree.cap)?;
+        assert_eq!(encoded.len(), 4 * 32);
+        assert_eq!(decode_merkle_cap::<F, PH>(encoded, 2)?, tree.cap);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_inputs_layout() -> Result<()> {
+        let public_inputs = vec![F::ONE, F::NEG_ONE, F::rand()];
+        let encoded = encode_public_inputs(&public_inputs)?;
+        assert_eq!(encoded.len(), 4 * 32);
+        assert_eq!(encoded[31], 3);
+        assert_eq!(encoded[63], 1);
+        assert_eq!(encoded[88..96], (F::ORDER - 1).to_be_bytes());
+        assert_eq!(decode_public_inputs::<F>(encoded.clone())?, public_inputs);
+
+        // Non-canonical elements and trailing bytes are rejected.
+        let mut non_canonical = encoded.clone();
+        non_canonical[88..96].copy_from_slice(&F::ORDER.to_be_bytes());
+        assert!(decode_public_inputs::<F>(non_canonical).is_err());
+        let mut trailing = encoded;
+        trailing.push(0);
+        assert!(decode_public_inputs::<F>(trailing).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_proof_round_trip() -> Result<()> {
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 1]);
+        config.fri_config.num_query_rounds = 50;
+
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        pw.set_target(x, F::rand());
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?.compress(&data.common)?;
+
+        let encoded_proof = encode_compressed_proof(&proof.proof)?;
+        assert_eq!(encoded_proof.len() % 32, 0);
+        let encoded_public_inputs = encode_public_inputs(&proof.public_inputs)?;
+
+        let decoded = CompressedProofWithPublicInputs {
+            proof: decode_compressed_proof(encoded_proof, &data.common)?,
+            public_inputs: decode_public_inputs(encoded_public_inputs)?,
+        };
+        assert_eq!(decoded, proof);
+        data.verify_compressed(decoded)
+    }
+}
@@ -0,0 +1,397 @@
+//! `Arbitrary` implementations of proofs, for fuzzing verifiers and deserializers with malicious
+//! inputs. The generated proofs have consistent shapes, e.g. each query round has a step for each
+//! reduction and each Merkle proof has the length implied by its tree's height, but random contents.
+//! Dimensions are kept small, so that a fuzzer explores many shapes quickly.
+
+use std::collections::HashMap;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use plonky2_field::extension_field::{Extendable, FieldExtension};
+use plonky2_field::field_types::{Field, Field64};
+use plonky2_field::polynomial::PolynomialCoeffs;
+
+use crate::fri::proof::{
+    CompressedFriProof, CompressedFriQueryRounds, FriInitialTreeProof, FriProof, FriQueryRound,
+    FriQueryStep,
+};
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_proofs::MerkleProof;
+use crate::hash::merkle_tree::MerkleCap;
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::proof::{CompressedProof, OpeningSet, Proof};
+
+/// The dimensions of a proof, which would otherwise be given by its circuit's `CommonCircuitData`.
+struct ProofShape {
+    /// The log of the size of the LDE domain.
+    lde_bits: usize,
+    cap_height: usize,
+    commit_phase_cap_height: usize,
+    reduction_arity_bits: Vec<usize>,
+    num_query_rounds: usize,
+    final_poly_len: usize,
+    num_constants: usize,
+    num_routed_wires: usize,
+    num_wires: usize,
+    num_challenges: usize,
+    num_partial_products: usize,
+    quotient_degree_factor: usize,
+}
+
+impl ProofShape {
+    fn arbitrary(u: &mut Unstructured) -> Result<Self> {
+        let lde_bits = u.int_in_range(1..=10)?;
+        let cap_height = u.int_in_range(0..=lde_bits.min(2))?;
+        let mut reduction_arity_bits = Vec::new();
+        let mut height = lde_bits;
+        while height > 1 && reduction_arity_bits.len() < 4 && u.arbitrary()? {
+            let arity_bits = u.int_in_range(1..=(height - 1).min(3))?;
+            reduction_arity_bits.push(arity_bits);
+            height -= arity_bits;
+        }
+        let num_routed_wires = u.int_in_range(1..=8)?;
+        Ok(Self {
+            lde_bits,
+            cap_height,
+            commit_phase_cap_height: u.int_in_range(0..=height.min(2))?,
+            reduction_arity_bits,
+            num_query_rounds: u.int_in_range(1..=8)?,
+            final_poly_len: 1 << u.int_in_range(0..=height.min(3))?,
+            num_constants: u.int_in_range(0..=4)?,
+            num_routed_wires,
+            num_wires: u.int_in_range(num_routed_wires..=16)?,
+            num_challenges: u.int_in_range(1..=2)?,
+            num_partial_products: u.int_in_range(0..=4)?,
+            quotient_degree_factor: u.int_in_range(1..=8)?,
+        })
+    }
+
+    /// The number of values in a leaf of each initial tree.
+    fn initial_leaf_sizes(&self) -> [usize; 4] {
+        [
+            self.num_constants + self.num_routed_wires,
+            self.num_wires,
+            self.num_challenges * (1 + self.num_partial_products),
+            self.num_challenges * self.quotient_degree_factor,
+        ]
+    }
+
+    /// The height of the commit phase tree of each reduction.
+    fn commit_phase_heights(&self) -> Vec<usize> {
+        self.reduction_arity_bits
+            .iter()
+            .scan(self.lde_bits, |height, &arity_bits| {
+                *height -= arity_bits;
+                Some(*height)
+            })
+            .collect()
+    }
+}
+
+fn arbitrary_field<F: Field64>(u: &mut Unstructured) -> Result<F> {
+    Ok(F::from_canonical_u64(u.int_in_range(0..=F::ORDER - 1)?))
+}
+
+fn arbitrary_field_vec<F: Field64>(u: &mut Unstructured, len: usize) -> Result<Vec<F>> {
+    let mut v = Vec::with_capacity(len);
+    for _ in 0..len {
+        v.push(arbitrary_field(u)?);
+    }
+    Ok(v)
+}
+
+fn arbitrary_ext_vec<F: RichField + Extendable<D>, const D: usize>(
+    u: &mut Unstructured,
+    len: usize,
+) -> Result<Vec<F::Extension>> {
+    let mut v = Vec::with_capacity(len);
+    for _ in 0..len {
+        let mut arr = [F::ZERO; D];
+        for a in arr.iter_mut() {
+            *a = arbitrary_field(u)?;
+        }
+        v.push(F::Extension::from_basefield_array(arr));
+    }
+    Ok(v)
+}
+
+fn arbitrary_hashes<F: RichField, H: Hasher<F>>(
+    u: &mut Unstructured,
+    len: usize,
+) -> Result<Vec<H::Hash>> {
+    let mut v = Vec::with_capacity(len);
+    for _ in 0..len {
+        v.push(H::hash_no_pad(&arbitrary_field_vec(u, 4)?));
+    }
+    Ok(v)
+}
+
+fn arbitrary_cap<F: RichField, H: Hasher<F>>(
+    u: &mut Unstructured,
+    cap_height: usize,
+) -> Result<MerkleCap<F, H>> {
+    Ok(MerkleCap(arbitrary_hashes::<F, H>(u, 1 << cap_height)?))
+}
+
+/// A Merkle proof of `len` siblings, or of at most `len` siblings if `compressed`.
+fn arbitrary_merkle_proof<F: RichField, H: Hasher<F>>(
+    u: &mut Unstructured,
+    len: usize,
+    compressed: bool,
+) -> Result<MerkleProof<F, H>> {
+    let len = if compressed {
+        u.int_in_range(0..=len)?
+    } else {
+        len
+    };
+    Ok(MerkleProof {
+        siblings: arbitrary_hashes::<F, H>(u, len)?,
+    })
+}
+
+fn arbitrary_initial_trees_proof<F: RichField, H: Hasher<F>>(
+    u: &mut Unstructured,
+    shape: &ProofShape,
+    compressed: bool,
+) -> Result<FriInitialTreeProof<F, H>> {
+    let mut evals_proofs = Vec::with_capacity(4);
+    for leaf_size in shape.initial_leaf_sizes() {
+        let evals = arbitrary_field_vec(u, leaf_size)?;
+        let merkle_proof =
+            arbitrary_merkle_proof(u, shape.lde_bits - shape.cap_height, compressed)?;
+        evals_proofs.push((evals, merkle_proof));
+    }
+    Ok(FriInitialTreeProof { evals_proofs })
+}
+
+fn arbitrary_query_step<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    u: &mut Unstructured,
+    shape: &ProofShape,
+    arity_bits: usize,
+    height: usize,
+    compressed: bool,
+) -> Result<FriQueryStep<F, H, D>> {
+    // A compressed step omits the evaluation which the verifier infers.
+    let num_evals = (1 << arity_bits) - compressed as usize;
+    Ok(FriQueryStep {
+        evals: arbitrary_ext_vec::<F, D>(u, num_evals)?,
+        merkle_proof: arbitrary_merkle_proof(
+            u,
+            height - shape.commit_phase_cap_height,
+            compressed,
+        )?,
+    })
+}
+
+fn arbitrary_opening_set<F: RichField + Extendable<D>, const D: usize>(
+    u: &mut Unstructured,
+    shape: &ProofShape,
+) -> Result<OpeningSet<F, D>> {
+    let num_challenges = shape.num_challenges;
+    Ok(OpeningSet {
+        constants: arbitrary_ext_vec::<F, D>(u, shape.num_constants)?,
+        plonk_sigmas: arbitrary_ext_vec::<F, D>(u, shape.num_routed_wires)?,
+        wires: arbitrary_ext_vec::<F, D>(u, shape.num_wires)?,
+        plonk_zs: arbitrary_ext_vec::<F, D>(u, num_challenges)?,
+        plonk_zs_right: arbitrary_ext_vec::<F, D>(u, num_challenges)?,
+        partial_products: arbitrary_ext_vec::<F, D>(
+            u,
+            num_challenges * shape.num_partial_products,
+        )?,
+        quotient_polys: arbitrary_ext_vec::<F, D>(
+            u,
+            num_challenges * shape.quotient_degree_factor,
+        )?,
+        extra: Vec::new(),
+    })
+}
+
+fn arbitrary_fri_proof<
+    F: RichField + Extendable<D>,
+    H: Hasher<F>,
+    CH: Hasher<F>,
+    const D: usize,
+>(
+    u: &mut Unstructured,
+    shape: &ProofShape,
+) -> Result<FriProof<F, H, CH, D>> {
+    let heights = shape.commit_phase_heights();
+    let mut commit_phase_merkle_caps = Vec::with_capacity(heights.len());
+    for _ in &heights {
+        commit_phase_merkle_caps.push(arbitrary_cap(u, shape.commit_phase_cap_height)?);
+    }
+    let mut query_round_proofs = Vec::with_capacity(shape.num_query_rounds);
+    for _ in 0..shape.num_query_rounds {
+        let initial_trees_proof = arbitrary_initial_trees_proof(u, shape, false)?;
+        let mut steps = Vec::with_capacity(heights.len());
+        for (&arity_bits, &height) in shape.reduction_arity_bits.iter().zip(&heights) {
+            steps.push(arbitrary_query_step(u, shape, arity_bits, height, false)?);
+        }
+        query_round_proofs.push(FriQueryRound {
+            initial_trees_proof,
+            steps,
+        });
+    }
+    Ok(FriProof {
+        commit_phase_merkle_caps,
+        query_round_proofs,
+        final_poly: PolynomialCoeffs::new(arbitrary_ext_vec::<F, D>(u, shape.final_poly_len)?),
+        pow_witness: arbitrary_field(u)?,
+    })
+}
+
+fn arbitrary_compressed_fri_proof<
+    F: RichField + Extendable<D>,
+    H: Hasher<F>,
+    CH: Hasher<F>,
+    const D: usize,
+>(
+    u: &mut Unstructured,
+    shape: &ProofShape,
+) -> Result<CompressedFriProof<F, H, CH, D>> {
+    let heights = shape.commit_phase_heights();
+    let mut commit_phase_merkle_caps = Vec::with_capacity(heights.len());
+    for _ in &heights {
+        commit_phase_merkle_caps.push(arbitrary_cap(u, shape.commit_phase_cap_height)?);
+    }
+
+    let mut indices = Vec::with_capacity(shape.num_query_rounds);
+    for _ in 0..shape.num_query_rounds {
+        indices.push(u.int_in_range(0..=(1 << shape.lde_bits) - 1)?);
+    }
+    // There is one initial trees proof per distinct index, and one step per distinct coset.
+    let mut initial_trees_proofs = HashMap::new();
+    for &i in &indices {
+        if !initial_trees_proofs.contains_key(&i) {
+            let initial_trees_proof = arbitrary_initial_trees_proof(u, shape, true)?;
+            initial_trees_proofs.insert(i, initial_trees_proof);
+        }
+    }
+    let mut steps = Vec::with_capacity(heights.len());
+    let mut coset_indices = indices.clone();
+    for (&arity_bits, &height) in shape.reduction_arity_bits.iter().zip(&heights) {
+        let mut steps_by_coset = HashMap::new();
+        for c in coset_indices.iter_mut() {
+            *c >>= arity_bits;
+            if !steps_by_coset.contains_key(c) {
+                let step = arbitrary_query_step(u, shape, arity_bits, height, true)?;
+                steps_by_coset.insert(*c, step);
+            }
+        }
+        steps.push(steps_by_coset);
+    }
+
+    Ok(CompressedFriProof {
+        commit_phase_merkle_caps,
+        query_round_proofs: CompressedFriQueryRounds {
+            indices,
+            initial_trees_proofs,
+            steps,
+        },
+        final_poly: PolynomialCoeffs::new(arbitrary_ext_vec::<F, D>(u, shape.final_poly_len)?),
+        pow_witness: arbitrary_field(u)?,
+    })
+}
+
+impl<'a, F: RichField + Extendable<D>, H: Hasher<F>, CH: Hasher<F>, const D: usize> Arbitrary<'a>
+    for FriProof<F, H, CH, D>
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let shape = ProofShape::arbitrary(u)?;
+        arbitrary_fri_proof(u, &shape)
+    }
+}
+
+impl<'a, F: RichField + Extendable<D>, H: Hasher<F>, CH: Hasher<F>, const D: usize> Arbitrary<'a>
+    for CompressedFriProof<F, H, CH, D>
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let shape = ProofShape::arbitrary(u)?;
+        arbitrary_compressed_fri_proof(u, &shape)
+    }
+}
+
+impl<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Arbitrary<'a>
+    for Proof<F, C, D>
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let shape = ProofShape::arbitrary(u)?;
+        Ok(Proof {
+            wires_cap: arbitrary_cap(u, shape.cap_height)?,
+            plonk_zs_partial_products_cap: arbitrary_cap(u, shape.cap_height)?,
+            quotient_polys_cap: arbitrary_cap(u, shape.cap_height)?,
+            openings: arbitrary_opening_set(u, &shape)?,
+            opening_proof: arbitrary_fri_proof(u, &shape)?,
+        })
+    }
+}
+
+impl<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Arbitrary<'a>
+    for CompressedProof<F, C, D>
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let shape = ProofShape::arbitrary(u)?;
+        Ok(CompressedProof {
+            wires_cap: arbitrary_cap(u, shape.cap_height)?,
+            plonk_zs_partial_products_cap: arbitrary_cap(u, shape.cap_height)?,
+            quotient_polys_cap: arbitrary_cap(u, shape.cap_height)?,
+            openings: arbitrary_opening_set(u, &shape)?,
+            opening_proof: arbitrary_compressed_fri_proof(u, &shape)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+    use rand::{RngCore, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::{CompressedProof, Proof};
+    use crate::util::serialization::Buffer;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_arbitrary_proofs_have_consistent_shapes() {
+        // A fixed seed, so that a failure can be reproduced.
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..20 {
+            let mut data = vec![0; 1 << 16];
+            rng.fill_bytes(&mut data);
+            let mut u = Unstructured::new(&data);
+
+            let proof = Proof::<F, C, D>::arbitrary(&mut u).unwrap();
+            let fri_proof = &proof.opening_proof;
+            let num_reductions = fri_proof.commit_phase_merkle_caps.len();
+            for round in &fri_proof.query_round_proofs {
+                assert_eq!(round.steps.len(), num_reductions);
+                assert_eq!(round.initial_trees_proof.evals_proofs.len(), 4);
+                // Steps fold the LDE domain, so their Merkle proofs get shorter.
+                let initial_proof_len = round.initial_trees_proof.evals_proofs[0].1.siblings.len();
+                assert!(round
+                    .steps
+                    .iter()
+                    .all(|step| step.merkle_proof.siblings.len() < initial_proof_len));
+            }
+            let mut buffer = Buffer::new(Vec::new());
+            buffer.write_proof(&proof).unwrap();
+
+            let compressed_proof = CompressedProof::<F, C, D>::arbitrary(&mut u).unwrap();
+            let rounds = &compressed_proof.opening_proof.query_round_proofs;
+            assert_eq!(
+                rounds.steps.len(),
+                compressed_proof
+                    .opening_proof
+                    .commit_phase_merkle_caps
+                    .len()
+            );
+            assert!(rounds
+                .indices
+                .iter()
+                .all(|i| rounds.initial_trees_proofs.contains_key(i)));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Rayon's parallel iterators if the `parallel` feature is enabled, and otherwise sequential
+//! stand-ins with the same method names, so that callers can be written once with `par_iter` and
+//! friends.
+
+#[cfg(feature = "parallel")]
+pub use rayon::join;
+#[cfg(feature = "parallel")]
+pub use rayon::prelude::*;
+
+#[cfg(not(feature = "parallel"))]
+pub use self::sequential::*;
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+    use std::iter::FlatMap;
+    use std::slice::{Chunks, ChunksExact, ChunksExactMut};
+
+    pub trait MaybeParIter<'data> {
+        type Iter: Iterator;
+
+        fn par_iter(&'data self) -> Self::Iter;
+    }
+
+    impl<'data, T: 'data + ?Sized> MaybeParIter<'data> for T
+    where
+        &'data T: IntoIterator,
+    {
+        type Iter = <&'data T as IntoIterator>::IntoIter;
+
+        fn par_iter(&'data self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+
+    pub trait MaybeIntoParIter: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<T: IntoIterator> MaybeIntoParIter for T {}
+
+    pub trait MaybeParChunks<T> {
+        fn par_chunks(&self, chunk_size: usize) -> Chunks<'_, T>;
+        fn par_chunks_exact(&self, chunk_size: usize) -> ChunksExact<'_, T>;
+    }
+
+    impl<T> MaybeParChunks<T> for [T] {
+        fn par_chunks(&self, chunk_size: usize) -> Chunks<'_, T> {
+            self.chunks(chunk_size)
+        }
+
+        fn par_chunks_exact(&self, chunk_size: usize) -> ChunksExact<'_, T> {
+            self.chunks_exact(chunk_size)
+        }
+    }
+
+    pub trait MaybeParChunksMut<T> {
+        fn par_chunks_exact_mut(&mut self, chunk_size: usize) -> ChunksExactMut<'_, T>;
+    }
+
+    impl<T> MaybeParChunksMut<T> for [T] {
+        fn par_chunks_exact_mut(&mut self, chunk_size: usize) -> ChunksExactMut<'_, T> {
+            self.chunks_exact_mut(chunk_size)
+        }
+    }
+
+    /// The methods of rayon's `ParallelIterator` which have no namesake on `Iterator`.
+    pub trait ParallelIteratorMock: Iterator + Sized {
+        fn flat_map_iter<U, G>(self, f: G) -> FlatMap<Self, U, G>
+        where
+            U: IntoIterator,
+            G: FnMut(Self::Item) -> U,
+        {
+            self.flat_map(f)
+        }
+
+        fn find_any<P>(mut self, predicate: P) -> Option<Self::Item>
+        where
+            P: FnMut(&Self::Item) -> bool,
+        {
+            self.find(predicate)
+        }
+
+        fn find_first<P>(mut self, predicate: P) -> Option<Self::Item>
+        where
+            P: FnMut(&Self::Item) -> bool,
+        {
+            self.find(predicate)
+        }
+    }
+
+    impl<I: Iterator> ParallelIteratorMock for I {}
+
+    pub fn join<A, B, RA, RB>(oper_a: A, oper_b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA,
+        B: FnOnce() -> RB,
+    {
+        (oper_a(), oper_b())
+    }
+}
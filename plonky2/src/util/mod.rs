@@ -2,21 +2,27 @@ use plonky2_field::field_types::Field;
 use plonky2_field::polynomial::PolynomialValues;
 
 pub(crate) mod context_tree;
+pub mod evm_calldata;
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
 pub(crate) mod marking;
+pub mod maybe_rayon;
 pub(crate) mod partial_products;
+pub mod progress;
 pub mod reducing;
 pub mod serialization;
 pub mod strided_view;
 pub mod timing;
+pub mod zeroize;
 
 pub(crate) fn transpose_poly_values<F: Field>(polys: Vec<PolynomialValues<F>>) -> Vec<Vec<F>> {
     let poly_values = polys.into_iter().map(|p| p.values).collect::<Vec<_>>();
     transpose(&poly_values)
 }
 
-pub fn transpose<F: Field>(matrix: &[Vec<F>]) -> Vec<Vec<F>> {
+pub fn transpose<F: Field, V: AsRef<[F]>>(matrix: &[V]) -> Vec<Vec<F>> {
     let l = matrix.len();
-    let w = matrix[0].len();
+    let w = matrix[0].as_ref().len();
 
     let mut transposed = vec![vec![]; w];
     for i in 0..w {
@@ -32,13 +38,13 @@ pub fn transpose<F: Field>(matrix: &[Vec<F>]) -> Vec<Vec<F>> {
     if w >= l {
         for i in 0..w {
             for j in 0..l {
-                transposed[i][j] = matrix[j][i];
+                transposed[i][j] = matrix[j].as_ref()[i];
             }
         }
     } else {
         for j in 0..l {
             for i in 0..w {
-                transposed[i][j] = matrix[j][i];
+                transposed[i][j] = matrix[j].as_ref()[i];
             }
         }
     }
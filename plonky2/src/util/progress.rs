@@ -0,0 +1,213 @@
+//! Progress reporting and cancellation for long-running proofs.
+//!
+//! A `ProvingMonitor` is threaded through the prover alongside its `TimingTree`. Its callback is
+//! told when each phase starts, how many leaves of the Merkle tree being built have been hashed,
+//! and which FRI reduction round is running. Its cancellation token is checked between phases, and
+//! cancelling it makes `prove` return an error at the next check, so a service can abort a proof
+//! without killing the process. Callbacks may be called from any of the prover's threads.
+
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{ensure, Result};
+
+/// A phase of proving.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ProvingPhase {
+    GenerateWitness,
+    CommitWires,
+    CommitPartialProducts,
+    ComputeQuotient,
+    CommitQuotient,
+    ComputeOpenings,
+    FriCommit,
+    FriProofOfWork,
+    FriQueries,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ProvingEvent {
+    PhaseStarted(ProvingPhase),
+    /// `hashed` of the `total` leaves of the Merkle tree being built have been hashed. This is
+    /// reported at most once per percent.
+    LeavesHashed {
+        hashed: usize,
+        total: usize,
+    },
+    /// FRI reduction round `round` (counting from zero) of `num_rounds` has started.
+    FriRound {
+        round: usize,
+        num_rounds: usize,
+    },
+}
+
+/// A token which can be cancelled from another thread to abort the proofs monitoring it.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Receives the progress of a proof and may cancel it. The default monitor does neither.
+#[derive(Clone, Default)]
+pub struct ProvingMonitor {
+    callback: Option<Arc<dyn Fn(ProvingEvent) + Send + Sync>>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl ProvingMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_callback(
+        mut self,
+        callback: impl Fn(ProvingEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    pub fn report(&self, event: ProvingEvent) {
+        if let Some(callback) = &self.callback {
+            callback(event);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .map_or(false, CancellationToken::is_cancelled)
+    }
+
+    /// Fails if the proof has been cancelled, and otherwise reports the start of `phase`.
+    pub fn start_phase(&self, phase: ProvingPhase) -> Result<()> {
+        ensure!(!self.is_cancelled(), "Proving was cancelled.");
+        self.report(ProvingEvent::PhaseStarted(phase));
+        Ok(())
+    }
+}
+
+impl Debug for ProvingMonitor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProvingMonitor")
+            .field("has_callback", &self.callback.is_some())
+            .field("cancellation", &self.cancellation)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::util::progress::{CancellationToken, ProvingEvent, ProvingMonitor, ProvingPhase};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_proving_progress() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let monitor = {
+            let events = events.clone();
+            ProvingMonitor::new().with_callback(move |e| events.lock().unwrap().push(e))
+        };
+        let proof = data.prove_with_monitor(PartialWitness::new(), &monitor)?;
+        data.verify(proof)?;
+
+        let events = events.lock().unwrap();
+        let phases = events
+            .iter()
+            .filter_map(|e| match e {
+                ProvingEvent::PhaseStarted(phase) => Some(*phase),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            phases,
+            vec![
+                ProvingPhase::GenerateWitness,
+                ProvingPhase::CommitWires,
+                ProvingPhase::CommitPartialProducts,
+                ProvingPhase::ComputeQuotient,
+                ProvingPhase::CommitQuotient,
+                ProvingPhase::ComputeOpenings,
+                ProvingPhase::FriCommit,
+                ProvingPhase::FriProofOfWork,
+                ProvingPhase::FriQueries,
+            ]
+        );
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, ProvingEvent::LeavesHashed { hashed, total } if hashed == total)));
+        let num_rounds = data.common.fri_params.reduction_arity_bits.len();
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| matches!(e, ProvingEvent::FriRound { .. }))
+                .count(),
+            num_rounds
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_proof() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        builder.add_gate(NoopGate, vec![]);
+        let data = builder.build::<C>();
+
+        // Cancellation is noticed at the start of the next phase, including FRI's.
+        for cancelled_phase in [ProvingPhase::CommitWires, ProvingPhase::FriProofOfWork] {
+            let token = CancellationToken::new();
+            let monitor = {
+                let token = token.clone();
+                ProvingMonitor::new()
+                    .with_cancellation(token.clone())
+                    .with_callback(move |e| {
+                        if e == ProvingEvent::PhaseStarted(cancelled_phase) {
+                            token.cancel();
+                        }
+                    })
+            };
+            assert!(data
+                .prove_with_monitor(PartialWitness::new(), &monitor)
+                .is_err());
+            assert!(token.is_cancelled());
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use std::io::Cursor;
-use std::io::{Read, Result, Write};
+use std::io::{BufRead, BufWriter, Error, ErrorKind, Result, Write};
 
 use plonky2_field::extension_field::{Extendable, FieldExtension};
 use plonky2_field::field_types::{Field64, PrimeField64};
@@ -15,16 +17,42 @@ use crate::hash::merkle_proofs::MerkleProof;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::plonk::circuit_data::CommonCircuitData;
 use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{
     CompressedProof, CompressedProofWithPublicInputs, OpeningSet, Proof, ProofWithPublicInputs,
 };
+use crate::plonk::region::CircuitRegion;
 
+/// The number of bytes in an EVM word.
+const EVM_WORD_BYTES: usize = 32;
+
+/// The most elements preallocated for a vector whose length is read from the data, so that a
+/// corrupt length can't make us allocate more memory than the data could fill.
+const MAX_PREALLOCATED_LEN: usize = 1 << 16;
+
+/// The byte layout used by a `Buffer`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    /// Field elements take 8 little-endian bytes, and integers take as few bytes as they need.
+    Compact,
+    /// Field elements and integers each take a 32-byte big-endian word, like a `uint256` in EVM
+    /// calldata. Hashes are left-aligned in as many words as they need, like a `bytes32`.
+    Evm,
+}
+
+/// Serializes to, or deserializes from, a byte stream. By default this is an in-memory buffer, but
+/// proofs can also be read incrementally from any `BufRead`, such as a `BufReader` over a file or
+/// socket, without first loading the whole artifact into memory.
 #[derive(Debug)]
-pub struct Buffer(Cursor<Vec<u8>>);
+pub struct Buffer<B = Cursor<Vec<u8>>>(B, Encoding);
 
 impl Buffer {
     pub fn new(buffer: Vec<u8>) -> Self {
-        Self(Cursor::new(buffer))
+        Self::with_encoding(buffer, Encoding::Compact)
+    }
+
+    pub fn with_encoding(buffer: Vec<u8>, encoding: Encoding) -> Self {
+        Self(Cursor::new(buffer), encoding)
     }
 
     pub fn len(&self) -> usize {
@@ -35,44 +63,429 @@ impl Buffer {
         self.0.into_inner()
     }
 
-    fn write_u8(&mut self, x: u8) -> Result<()> {
-        self.0.write_all(&[x])
+    /// The number of bytes which have not been read yet.
+    pub(crate) fn remaining(&self) -> usize {
+        self.len() - self.0.position() as usize
+    }
+}
+
+impl<B> Buffer<B> {
+    pub fn with_stream(stream: B, encoding: Encoding) -> Self {
+        Self(stream, encoding)
+    }
+
+    pub fn into_inner(self) -> B {
+        self.0
+    }
+
+    /// The number of bytes taken by a hash of `hash_size` bytes.
+    fn hash_size(&self, hash_size: usize) -> usize {
+        match self.1 {
+            Encoding::Compact => hash_size,
+            Encoding::Evm => (hash_size + EVM_WORD_BYTES - 1) / EVM_WORD_BYTES * EVM_WORD_BYTES,
+        }
+    }
+}
+
+impl<W: Write> Buffer<W> {
+    fn write_word(&mut self, x: u64) -> Result<()> {
+        let mut word = [0; EVM_WORD_BYTES];
+        word[EVM_WORD_BYTES - 8..].copy_from_slice(&x.to_be_bytes());
+        self.0.write_all(&word)
+    }
+
+    fn write_u8(&mut self, x: u8) -> Result<()> {
+        match self.1 {
+            Encoding::Compact => self.0.write_all(&[x]),
+            Encoding::Evm => self.write_word(x.into()),
+        }
+    }
+
+    fn write_u32(&mut self, x: u32) -> Result<()> {
+        match self.1 {
+            Encoding::Compact => self.0.write_all(&x.to_le_bytes()),
+            Encoding::Evm => self.write_word(x.into()),
+        }
+    }
+
+    fn write_field<F: PrimeField64>(&mut self, x: F) -> Result<()> {
+        match self.1 {
+            Encoding::Compact => self.0.write_all(&x.to_canonical_u64().to_le_bytes()),
+            Encoding::Evm => self.write_word(x.to_canonical_u64()),
+        }
+    }
+
+    fn write_field_ext<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        x: F::Extension,
+    ) -> Result<()> {
+        for &a in &x.to_basefield_array() {
+            self.write_field(a)?;
+        }
+        Ok(())
+    }
+
+    fn write_hash<F: RichField, H: Hasher<F>>(&mut self, h: H::Hash) -> Result<()> {
+        let mut bytes = h.to_bytes();
+        bytes.resize(self.hash_size(H::HASH_SIZE), 0);
+        self.0.write_all(&bytes)
+    }
+
+    /// Writes a length-prefixed vector of field elements.
+    pub fn write_field_vec_with_len<F: PrimeField64>(&mut self, v: &[F]) -> Result<()> {
+        self.write_u32(v.len().try_into().expect("Vector length must fit in u32."))?;
+        self.write_field_vec(v)
+    }
+
+    /// Writes the values of only the public inputs registered inside `region`, out of those of all
+    /// the circuit's public inputs, prefixed with their number. `read_field_vec_with_len` reads
+    /// them back.
+    pub fn write_region_public_inputs<F: PrimeField64>(
+        &mut self,
+        region: &CircuitRegion,
+        public_inputs: &[F],
+    ) -> Result<()> {
+        self.write_field_vec_with_len(region.public_input_values(public_inputs))
+    }
+
+    pub fn write_merkle_cap<F: RichField, H: Hasher<F>>(
+        &mut self,
+        cap: &MerkleCap<F, H>,
+    ) -> Result<()> {
+        for &a in &cap.0 {
+            self.write_hash::<F, H>(a)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_field_vec<F: PrimeField64>(&mut self, v: &[F]) -> Result<()> {
+        for &a in v {
+            self.write_field(a)?;
+        }
+        Ok(())
+    }
+
+    fn write_field_ext_vec<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        v: &[F::Extension],
+    ) -> Result<()> {
+        for &a in v {
+            self.write_field_ext::<F, D>(a)?;
+        }
+        Ok(())
+    }
+
+    fn write_opening_set<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        os: &OpeningSet<F, D>,
+    ) -> Result<()> {
+        self.write_field_ext_vec::<F, D>(&os.constants)?;
+        self.write_field_ext_vec::<F, D>(&os.plonk_sigmas)?;
+        self.write_field_ext_vec::<F, D>(&os.wires)?;
+        self.write_field_ext_vec::<F, D>(&os.plonk_zs)?;
+        self.write_field_ext_vec::<F, D>(&os.plonk_zs_right)?;
+        self.write_field_ext_vec::<F, D>(&os.partial_products)?;
+        self.write_field_ext_vec::<F, D>(&os.quotient_polys)?;
+        for values in &os.extra {
+            self.write_field_ext_vec::<F, D>(values)?;
+        }
+        Ok(())
+    }
+
+    fn write_merkle_proof<F: RichField, H: Hasher<F>>(
+        &mut self,
+        p: &MerkleProof<F, H>,
+    ) -> Result<()> {
+        let length = p.siblings.len();
+        self.write_u8(
+            length
+                .try_into()
+                .expect("Merkle proof length must fit in u8."),
+        )?;
+        for &h in &p.siblings {
+            self.write_hash::<F, H>(h)?;
+        }
+        Ok(())
+    }
+
+    fn write_fri_initial_proof<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        fitp: &FriInitialTreeProof<F, C::Hasher>,
+    ) -> Result<()> {
+        for (v, p) in &fitp.evals_proofs {
+            self.write_field_vec(v)?;
+            self.write_merkle_proof(p)?;
+        }
+        Ok(())
+    }
+
+    fn write_fri_query_step<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        fqs: &FriQueryStep<F, C::CommitPhaseHasher, D>,
+    ) -> Result<()> {
+        self.write_field_ext_vec::<F, D>(&fqs.evals)?;
+        self.write_merkle_proof(&fqs.merkle_proof)
+    }
+
+    fn write_fri_query_rounds<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        fqrs: &[FriQueryRound<F, C::Hasher, C::CommitPhaseHasher, D>],
+    ) -> Result<()> {
+        for fqr in fqrs {
+            self.write_fri_initial_proof::<F, C, D>(&fqr.initial_trees_proof)?;
+            for fqs in &fqr.steps {
+                self.write_fri_query_step::<F, C, D>(fqs)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+        &mut self,
+        fp: &FriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
+    ) -> Result<()> {
+        for cap in &fp.commit_phase_merkle_caps {
+            self.write_merkle_cap(cap)?;
+        }
+        self.write_fri_query_rounds::<F, C, D>(&fp.query_round_proofs)?;
+        self.write_field_ext_vec::<F, D>(&fp.final_poly.coeffs)?;
+        self.write_field(fp.pow_witness)
+    }
+
+    pub fn write_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+        &mut self,
+        proof: &Proof<F, C, D>,
+    ) -> Result<()> {
+        self.write_merkle_cap(&proof.wires_cap)?;
+        self.write_merkle_cap(&proof.plonk_zs_partial_products_cap)?;
+        self.write_merkle_cap(&proof.quotient_polys_cap)?;
+        self.write_opening_set(&proof.openings)?;
+        self.write_fri_proof::<F, C, D>(&proof.opening_proof)
+    }
+
+    pub fn write_proof_with_public_inputs<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<()> {
+        let ProofWithPublicInputs {
+            proof,
+            public_inputs,
+        } = proof_with_pis;
+        self.write_proof(proof)?;
+        self.write_field_vec(public_inputs)
+    }
+
+    fn write_compressed_fri_query_rounds<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        cfqrs: &CompressedFriQueryRounds<F, C::Hasher, C::CommitPhaseHasher, D>,
+    ) -> Result<()> {
+        for &i in &cfqrs.indices {
+            self.write_u32(i as u32)?;
+        }
+
+        let mut initial_trees_proofs = cfqrs.initial_trees_proofs.iter().collect::<Vec<_>>();
+        initial_trees_proofs.sort_by_key(|&x| x.0);
+        for (_, itp) in initial_trees_proofs {
+            self.write_fri_initial_proof::<F, C, D>(itp)?;
+        }
+        for h in &cfqrs.steps {
+            let mut fri_query_steps = h.iter().collect::<Vec<_>>();
+            fri_query_steps.sort_by_key(|&x| x.0);
+            for (_, fqs) in fri_query_steps {
+                self.write_fri_query_step::<F, C, D>(fqs)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_compressed_fri_proof<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        fp: &CompressedFriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
+    ) -> Result<()> {
+        for cap in &fp.commit_phase_merkle_caps {
+            self.write_merkle_cap(cap)?;
+        }
+        self.write_compressed_fri_query_rounds::<F, C, D>(&fp.query_round_proofs)?;
+        self.write_field_ext_vec::<F, D>(&fp.final_poly.coeffs)?;
+        self.write_field(fp.pow_witness)
+    }
+
+    pub fn write_compressed_proof<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        proof: &CompressedProof<F, C, D>,
+    ) -> Result<()> {
+        self.write_merkle_cap(&proof.wires_cap)?;
+        self.write_merkle_cap(&proof.plonk_zs_partial_products_cap)?;
+        self.write_merkle_cap(&proof.quotient_polys_cap)?;
+        self.write_opening_set(&proof.openings)?;
+        self.write_compressed_fri_proof::<F, C, D>(&proof.opening_proof)
+    }
+
+    pub fn write_compressed_proof_with_public_inputs<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        proof_with_pis: &CompressedProofWithPublicInputs<F, C, D>,
+    ) -> Result<()> {
+        let CompressedProofWithPublicInputs {
+            proof,
+            public_inputs,
+        } = proof_with_pis;
+        self.write_compressed_proof(proof)?;
+        self.write_field_vec(public_inputs)
+    }
+}
+
+/// Writes a proof while the prover produces it, in the format of
+/// `Buffer::write_proof_with_public_inputs`, so that the FRI query rounds, which make up most of
+/// the proof, are written as soon as each is computed rather than being held until the end.
+///
+/// The stream buffers its writes, since the proof is written a field element at a time. The first
+/// I/O error is kept and returned by `finish`, and nothing is written after it, so that the prover
+/// needn't handle errors between phases.
+///
+/// Only the query rounds are left out of the proof the prover returns. The Merkle caps, openings
+/// and FRI final polynomial are still held until proving ends, but they're small next to the
+/// query rounds.
+pub struct ProofStream<'a> {
+    buffer: Buffer<BufWriter<&'a mut dyn Write>>,
+    error: Option<Error>,
+}
+
+impl<'a> ProofStream<'a> {
+    pub fn new(writer: &'a mut dyn Write, encoding: Encoding) -> Self {
+        Self {
+            buffer: Buffer::with_stream(BufWriter::new(writer), encoding),
+            error: None,
+        }
+    }
+
+    fn write<G: FnOnce(&mut Buffer<BufWriter<&'a mut dyn Write>>) -> Result<()>>(&mut self, g: G) {
+        if self.error.is_none() {
+            self.error = g(&mut self.buffer).err();
+        }
+    }
+
+    pub(crate) fn write_merkle_cap<F: RichField, H: Hasher<F>>(&mut self, cap: &MerkleCap<F, H>) {
+        self.write(|b| b.write_merkle_cap(cap));
+    }
+
+    pub(crate) fn write_opening_set<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        os: &OpeningSet<F, D>,
+    ) {
+        self.write(|b| b.write_opening_set(os));
+    }
+
+    pub(crate) fn write_fri_query_round<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        fqr: &FriQueryRound<F, C::Hasher, C::CommitPhaseHasher, D>,
+    ) {
+        self.write(|b| b.write_fri_query_rounds::<F, C, D>(std::slice::from_ref(fqr)));
+    }
+
+    /// Writes the final polynomial and proof-of-work witness which end a FRI proof.
+    pub(crate) fn write_fri_final_poly_and_pow<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        final_poly: &PolynomialCoeffs<F::Extension>,
+        pow_witness: F,
+    ) {
+        self.write(|b| {
+            b.write_field_ext_vec::<F, D>(&final_poly.coeffs)?;
+            b.write_field(pow_witness)
+        });
+    }
+
+    pub(crate) fn write_public_inputs<F: PrimeField64>(&mut self, public_inputs: &[F]) {
+        self.write(|b| b.write_field_vec(public_inputs));
+    }
+
+    /// Flushes the buffered writes to the writer, and returns the first error encountered, if any.
+    pub fn finish(mut self) -> Result<()> {
+        self.write(|b| b.0.flush());
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+impl<R: BufRead> Buffer<R> {
+    fn read_word(&mut self) -> Result<u64> {
+        let mut word = [0; EVM_WORD_BYTES];
+        self.0.read_exact(&mut word)?;
+        let (high, low) = word.split_at(EVM_WORD_BYTES - 8);
+        if high.iter().any(|&b| b != 0) {
+            return Err(invalid_data("Word does not fit in 64 bits"));
+        }
+        Ok(u64::from_be_bytes(low.try_into().unwrap()))
     }
+
     fn read_u8(&mut self) -> Result<u8> {
+        if self.1 == Encoding::Evm {
+            return u8::try_from(self.read_word()?).map_err(|_| invalid_data("Word exceeds u8"));
+        }
         let mut buf = [0; std::mem::size_of::<u8>()];
         self.0.read_exact(&mut buf)?;
         Ok(buf[0])
     }
 
-    fn write_u32(&mut self, x: u32) -> Result<()> {
-        self.0.write_all(&x.to_le_bytes())
-    }
     fn read_u32(&mut self) -> Result<u32> {
+        if self.1 == Encoding::Evm {
+            return u32::try_from(self.read_word()?).map_err(|_| invalid_data("Word exceeds u32"));
+        }
         let mut buf = [0; std::mem::size_of::<u32>()];
         self.0.read_exact(&mut buf)?;
         Ok(u32::from_le_bytes(buf))
     }
 
-    fn write_field<F: PrimeField64>(&mut self, x: F) -> Result<()> {
-        self.0.write_all(&x.to_canonical_u64().to_le_bytes())
-    }
+    /// Reads a field element, rejecting non-canonical encodings so that each element has a unique
+    /// encoding, and so proofs can't be altered without changing their bytes.
     fn read_field<F: Field64>(&mut self) -> Result<F> {
-        let mut buf = [0; std::mem::size_of::<u64>()];
-        self.0.read_exact(&mut buf)?;
-        Ok(F::from_canonical_u64(u64::from_le_bytes(
-            buf.try_into().unwrap(),
-        )))
-    }
-
-    fn write_field_ext<F: RichField + Extendable<D>, const D: usize>(
-        &mut self,
-        x: F::Extension,
-    ) -> Result<()> {
-        for &a in &x.to_basefield_array() {
-            self.write_field(a)?;
+        let x = if self.1 == Encoding::Evm {
+            self.read_word()?
+        } else {
+            let mut buf = [0; std::mem::size_of::<u64>()];
+            self.0.read_exact(&mut buf)?;
+            u64::from_le_bytes(buf)
+        };
+        if x >= F::ORDER {
+            return Err(invalid_data("Field element is not canonical"));
         }
-        Ok(())
+        Ok(F::from_canonical_u64(x))
     }
+
     fn read_field_ext<F: RichField + Extendable<D>, const D: usize>(
         &mut self,
     ) -> Result<F::Extension> {
@@ -85,26 +498,40 @@ impl Buffer {
         ))
     }
 
-    fn write_hash<F: RichField, H: Hasher<F>>(&mut self, h: H::Hash) -> Result<()> {
-        self.0.write_all(&h.to_bytes())
-    }
-
     fn read_hash<F: RichField, H: Hasher<F>>(&mut self) -> Result<H::Hash> {
-        let mut buf = vec![0; H::HASH_SIZE];
+        let mut buf = vec![0; self.hash_size(H::HASH_SIZE)];
         self.0.read_exact(&mut buf)?;
-        Ok(H::Hash::from_bytes(&buf))
+        if buf[H::HASH_SIZE..].iter().any(|&b| b != 0) {
+            return Err(invalid_data("Hash padding is not zero"));
+        }
+        // Hashes made of field elements must encode each element canonically.
+        if !H::Hash::is_canonical(&buf[..H::HASH_SIZE]) {
+            return Err(invalid_data("Hash is not canonical"));
+        }
+        Ok(H::Hash::from_bytes(&buf[..H::HASH_SIZE]))
     }
 
-    fn write_merkle_cap<F: RichField, H: Hasher<F>>(
-        &mut self,
-        cap: &MerkleCap<F, H>,
-    ) -> Result<()> {
-        for &a in &cap.0 {
-            self.write_hash::<F, H>(a)?;
+    pub fn read_field_vec_with_len<F: Field64>(&mut self) -> Result<Vec<F>> {
+        let length = self.read_u32()? as usize;
+        // The length isn't trusted, so the vector grows as elements are read, and a length
+        // exceeding the data fails once the data runs out.
+        let mut v = Vec::with_capacity(length.min(MAX_PREALLOCATED_LEN));
+        for _ in 0..length {
+            v.push(self.read_field()?);
         }
-        Ok(())
+        Ok(v)
+    }
+
+    /// Reads the public inputs which end a proof, failing if any data follows them.
+    fn read_public_inputs<F: Field64>(&mut self, num_public_inputs: usize) -> Result<Vec<F>> {
+        let public_inputs = self.read_field_vec(num_public_inputs)?;
+        if !self.0.fill_buf()?.is_empty() {
+            return Err(invalid_data("Data follows the public inputs"));
+        }
+        Ok(public_inputs)
     }
-    fn read_merkle_cap<F: RichField, H: Hasher<F>>(
+
+    pub fn read_merkle_cap<F: RichField, H: Hasher<F>>(
         &mut self,
         cap_height: usize,
     ) -> Result<MerkleCap<F, H>> {
@@ -116,27 +543,12 @@ impl Buffer {
         ))
     }
 
-    pub fn write_field_vec<F: PrimeField64>(&mut self, v: &[F]) -> Result<()> {
-        for &a in v {
-            self.write_field(a)?;
-        }
-        Ok(())
-    }
     pub fn read_field_vec<F: Field64>(&mut self, length: usize) -> Result<Vec<F>> {
         (0..length)
             .map(|_| self.read_field())
             .collect::<Result<Vec<_>>>()
     }
 
-    fn write_field_ext_vec<F: RichField + Extendable<D>, const D: usize>(
-        &mut self,
-        v: &[F::Extension],
-    ) -> Result<()> {
-        for &a in v {
-            self.write_field_ext::<F, D>(a)?;
-        }
-        Ok(())
-    }
     fn read_field_ext_vec<F: RichField + Extendable<D>, const D: usize>(
         &mut self,
         length: usize,
@@ -146,18 +558,6 @@ impl Buffer {
             .collect::<Result<Vec<_>>>()
     }
 
-    fn write_opening_set<F: RichField + Extendable<D>, const D: usize>(
-        &mut self,
-        os: &OpeningSet<F, D>,
-    ) -> Result<()> {
-        self.write_field_ext_vec::<F, D>(&os.constants)?;
-        self.write_field_ext_vec::<F, D>(&os.plonk_sigmas)?;
-        self.write_field_ext_vec::<F, D>(&os.wires)?;
-        self.write_field_ext_vec::<F, D>(&os.plonk_zs)?;
-        self.write_field_ext_vec::<F, D>(&os.plonk_zs_right)?;
-        self.write_field_ext_vec::<F, D>(&os.partial_products)?;
-        self.write_field_ext_vec::<F, D>(&os.quotient_polys)
-    }
     fn read_opening_set<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -170,13 +570,19 @@ impl Buffer {
         let constants = self.read_field_ext_vec::<F, D>(common_data.num_constants)?;
         let plonk_sigmas = self.read_field_ext_vec::<F, D>(config.num_routed_wires)?;
         let wires = self.read_field_ext_vec::<F, D>(config.num_wires)?;
-        let plonk_zs = self.read_field_ext_vec::<F, D>(config.num_challenges)?;
-        let plonk_zs_right = self.read_field_ext_vec::<F, D>(config.num_challenges)?;
-        let partial_products = self
-            .read_field_ext_vec::<F, D>(common_data.num_partial_products * config.num_challenges)?;
+        let plonk_zs = self.read_field_ext_vec::<F, D>(config.num_challenges())?;
+        let plonk_zs_right = self.read_field_ext_vec::<F, D>(config.num_challenges())?;
+        let partial_products = self.read_field_ext_vec::<F, D>(
+            common_data.num_partial_products * config.num_challenges(),
+        )?;
         let quotient_polys = self.read_field_ext_vec::<F, D>(
-            common_data.quotient_degree_factor * config.num_challenges,
+            common_data.quotient_degree_factor * config.num_challenges(),
         )?;
+        let extra = common_data
+            .extra_openings
+            .iter()
+            .map(|opening| self.read_field_ext_vec::<F, D>(opening.polynomials.len()))
+            .collect::<Result<Vec<_>>>()?;
         Ok(OpeningSet {
             constants,
             plonk_sigmas,
@@ -185,24 +591,10 @@ impl Buffer {
             plonk_zs_right,
             partial_products,
             quotient_polys,
+            extra,
         })
     }
 
-    fn write_merkle_proof<F: RichField, H: Hasher<F>>(
-        &mut self,
-        p: &MerkleProof<F, H>,
-    ) -> Result<()> {
-        let length = p.siblings.len();
-        self.write_u8(
-            length
-                .try_into()
-                .expect("Merkle proof length must fit in u8."),
-        )?;
-        for &h in &p.siblings {
-            self.write_hash::<F, H>(h)?;
-        }
-        Ok(())
-    }
     fn read_merkle_proof<F: RichField, H: Hasher<F>>(&mut self) -> Result<MerkleProof<F, H>> {
         let length = self.read_u8()?;
         Ok(MerkleProof {
@@ -212,20 +604,6 @@ impl Buffer {
         })
     }
 
-    fn write_fri_initial_proof<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >(
-        &mut self,
-        fitp: &FriInitialTreeProof<F, C::Hasher>,
-    ) -> Result<()> {
-        for (v, p) in &fitp.evals_proofs {
-            self.write_field_vec(v)?;
-            self.write_merkle_proof(p)?;
-        }
-        Ok(())
-    }
     fn read_fri_initial_proof<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -235,41 +613,42 @@ impl Buffer {
         common_data: &CommonCircuitData<F, C, D>,
     ) -> Result<FriInitialTreeProof<F, C::Hasher>> {
         let config = &common_data.config;
+        let salt_size = |oracle| {
+            config
+                .oracle_salt_size::<D>(oracle)
+                .map_err(|e| invalid_data(&e.to_string()))
+        };
         let mut evals_proofs = Vec::with_capacity(4);
 
-        let constants_sigmas_v =
-            self.read_field_vec(common_data.num_constants + config.num_routed_wires)?;
+        let constants_sigmas_v = self.read_field_vec(
+            common_data.num_constants
+                + config.num_routed_wires
+                + salt_size(PlonkOracle::CONSTANTS_SIGMAS)?,
+        )?;
         let constants_sigmas_p = self.read_merkle_proof()?;
         evals_proofs.push((constants_sigmas_v, constants_sigmas_p));
 
-        let wires_v = self.read_field_vec(config.num_wires)?;
+        let wires_v = self.read_field_vec(config.num_wires + salt_size(PlonkOracle::WIRES)?)?;
         let wires_p = self.read_merkle_proof()?;
         evals_proofs.push((wires_v, wires_p));
 
-        let zs_partial_v =
-            self.read_field_vec(config.num_challenges * (1 + common_data.num_partial_products))?;
+        let zs_partial_v = self.read_field_vec(
+            config.num_challenges() * (1 + common_data.num_partial_products)
+                + salt_size(PlonkOracle::ZS_PARTIAL_PRODUCTS)?,
+        )?;
         let zs_partial_p = self.read_merkle_proof()?;
         evals_proofs.push((zs_partial_v, zs_partial_p));
 
-        let quotient_v =
-            self.read_field_vec(config.num_challenges * common_data.quotient_degree_factor)?;
+        let quotient_v = self.read_field_vec(
+            config.num_challenges() * common_data.quotient_degree_factor
+                + salt_size(PlonkOracle::QUOTIENT)?,
+        )?;
         let quotient_p = self.read_merkle_proof()?;
         evals_proofs.push((quotient_v, quotient_p));
 
         Ok(FriInitialTreeProof { evals_proofs })
     }
 
-    fn write_fri_query_step<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >(
-        &mut self,
-        fqs: &FriQueryStep<F, C::Hasher, D>,
-    ) -> Result<()> {
-        self.write_field_ext_vec::<F, D>(&fqs.evals)?;
-        self.write_merkle_proof(&fqs.merkle_proof)
-    }
     fn read_fri_query_step<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -278,7 +657,7 @@ impl Buffer {
         &mut self,
         arity: usize,
         compressed: bool,
-    ) -> Result<FriQueryStep<F, C::Hasher, D>> {
+    ) -> Result<FriQueryStep<F, C::CommitPhaseHasher, D>> {
         let evals = self.read_field_ext_vec::<F, D>(arity - if compressed { 1 } else { 0 })?;
         let merkle_proof = self.read_merkle_proof()?;
         Ok(FriQueryStep {
@@ -287,22 +666,6 @@ impl Buffer {
         })
     }
 
-    fn write_fri_query_rounds<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >(
-        &mut self,
-        fqrs: &[FriQueryRound<F, C::Hasher, D>],
-    ) -> Result<()> {
-        for fqr in fqrs {
-            self.write_fri_initial_proof::<F, C, D>(&fqr.initial_trees_proof)?;
-            for fqs in &fqr.steps {
-                self.write_fri_query_step::<F, C, D>(fqs)?;
-            }
-        }
-        Ok(())
-    }
     fn read_fri_query_rounds<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -310,7 +673,7 @@ impl Buffer {
     >(
         &mut self,
         common_data: &CommonCircuitData<F, C, D>,
-    ) -> Result<Vec<FriQueryRound<F, C::Hasher, D>>> {
+    ) -> Result<Vec<FriQueryRound<F, C::Hasher, C::CommitPhaseHasher, D>>> {
         let config = &common_data.config;
         let mut fqrs = Vec::with_capacity(config.fri_config.num_query_rounds);
         for _ in 0..config.fri_config.num_query_rounds {
@@ -329,24 +692,13 @@ impl Buffer {
         Ok(fqrs)
     }
 
-    fn write_fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
-        &mut self,
-        fp: &FriProof<F, C::Hasher, D>,
-    ) -> Result<()> {
-        for cap in &fp.commit_phase_merkle_caps {
-            self.write_merkle_cap(cap)?;
-        }
-        self.write_fri_query_rounds::<F, C, D>(&fp.query_round_proofs)?;
-        self.write_field_ext_vec::<F, D>(&fp.final_poly.coeffs)?;
-        self.write_field(fp.pow_witness)
-    }
     fn read_fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
         &mut self,
         common_data: &CommonCircuitData<F, C, D>,
-    ) -> Result<FriProof<F, C::Hasher, D>> {
+    ) -> Result<FriProof<F, C::Hasher, C::CommitPhaseHasher, D>> {
         let config = &common_data.config;
         let commit_phase_merkle_caps = (0..common_data.fri_params.reduction_arity_bits.len())
-            .map(|_| self.read_merkle_cap(config.fri_config.cap_height))
+            .map(|_| self.read_merkle_cap(config.fri_config.commit_phase_cap_height))
             .collect::<Result<Vec<_>>>()?;
         let query_round_proofs = self.read_fri_query_rounds(common_data)?;
         let final_poly = PolynomialCoeffs::new(
@@ -361,16 +713,6 @@ impl Buffer {
         })
     }
 
-    pub fn write_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
-        &mut self,
-        proof: &Proof<F, C, D>,
-    ) -> Result<()> {
-        self.write_merkle_cap(&proof.wires_cap)?;
-        self.write_merkle_cap(&proof.plonk_zs_partial_products_cap)?;
-        self.write_merkle_cap(&proof.quotient_polys_cap)?;
-        self.write_opening_set(&proof.openings)?;
-        self.write_fri_proof::<F, C, D>(&proof.opening_proof)
-    }
     pub fn read_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
         &mut self,
         common_data: &CommonCircuitData<F, C, D>,
@@ -391,21 +733,6 @@ impl Buffer {
         })
     }
 
-    pub fn write_proof_with_public_inputs<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >(
-        &mut self,
-        proof_with_pis: &ProofWithPublicInputs<F, C, D>,
-    ) -> Result<()> {
-        let ProofWithPublicInputs {
-            proof,
-            public_inputs,
-        } = proof_with_pis;
-        self.write_proof(proof)?;
-        self.write_field_vec(public_inputs)
-    }
     pub fn read_proof_with_public_inputs<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -415,9 +742,7 @@ impl Buffer {
         common_data: &CommonCircuitData<F, C, D>,
     ) -> Result<ProofWithPublicInputs<F, C, D>> {
         let proof = self.read_proof(common_data)?;
-        let public_inputs = self.read_field_vec(
-            (self.len() - self.0.position() as usize) / std::mem::size_of::<u64>(),
-        )?;
+        let public_inputs = self.read_public_inputs(common_data.num_public_inputs)?;
 
         Ok(ProofWithPublicInputs {
             proof,
@@ -425,32 +750,6 @@ impl Buffer {
         })
     }
 
-    fn write_compressed_fri_query_rounds<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >(
-        &mut self,
-        cfqrs: &CompressedFriQueryRounds<F, C::Hasher, D>,
-    ) -> Result<()> {
-        for &i in &cfqrs.indices {
-            self.write_u32(i as u32)?;
-        }
-
-        let mut initial_trees_proofs = cfqrs.initial_trees_proofs.iter().collect::<Vec<_>>();
-        initial_trees_proofs.sort_by_key(|&x| x.0);
-        for (_, itp) in initial_trees_proofs {
-            self.write_fri_initial_proof::<F, C, D>(itp)?;
-        }
-        for h in &cfqrs.steps {
-            let mut fri_query_steps = h.iter().collect::<Vec<_>>();
-            fri_query_steps.sort_by_key(|&x| x.0);
-            for (_, fqs) in fri_query_steps {
-                self.write_fri_query_step::<F, C, D>(fqs)?;
-            }
-        }
-        Ok(())
-    }
     fn read_compressed_fri_query_rounds<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -458,10 +757,14 @@ impl Buffer {
     >(
         &mut self,
         common_data: &CommonCircuitData<F, C, D>,
-    ) -> Result<CompressedFriQueryRounds<F, C::Hasher, D>> {
+    ) -> Result<CompressedFriQueryRounds<F, C::Hasher, C::CommitPhaseHasher, D>> {
         let config = &common_data.config;
+        let lde_size = common_data.lde_size();
         let original_indices = (0..config.fri_config.num_query_rounds)
-            .map(|_| self.read_u32().map(|i| i as usize))
+            .map(|_| match self.read_u32()? as usize {
+                i if i < lde_size => Ok(i),
+                _ => Err(invalid_data("Query index is out of range")),
+            })
             .collect::<Result<Vec<_>>>()?;
         let mut indices = original_indices.clone();
         indices.sort_unstable();
@@ -497,21 +800,6 @@ impl Buffer {
         })
     }
 
-    fn write_compressed_fri_proof<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >(
-        &mut self,
-        fp: &CompressedFriProof<F, C::Hasher, D>,
-    ) -> Result<()> {
-        for cap in &fp.commit_phase_merkle_caps {
-            self.write_merkle_cap(cap)?;
-        }
-        self.write_compressed_fri_query_rounds::<F, C, D>(&fp.query_round_proofs)?;
-        self.write_field_ext_vec::<F, D>(&fp.final_poly.coeffs)?;
-        self.write_field(fp.pow_witness)
-    }
     fn read_compressed_fri_proof<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -519,10 +807,10 @@ impl Buffer {
     >(
         &mut self,
         common_data: &CommonCircuitData<F, C, D>,
-    ) -> Result<CompressedFriProof<F, C::Hasher, D>> {
+    ) -> Result<CompressedFriProof<F, C::Hasher, C::CommitPhaseHasher, D>> {
         let config = &common_data.config;
         let commit_phase_merkle_caps = (0..common_data.fri_params.reduction_arity_bits.len())
-            .map(|_| self.read_merkle_cap(config.fri_config.cap_height))
+            .map(|_| self.read_merkle_cap(config.fri_config.commit_phase_cap_height))
             .collect::<Result<Vec<_>>>()?;
         let query_round_proofs = self.read_compressed_fri_query_rounds(common_data)?;
         let final_poly = PolynomialCoeffs::new(
@@ -537,20 +825,6 @@ impl Buffer {
         })
     }
 
-    pub fn write_compressed_proof<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >(
-        &mut self,
-        proof: &CompressedProof<F, C, D>,
-    ) -> Result<()> {
-        self.write_merkle_cap(&proof.wires_cap)?;
-        self.write_merkle_cap(&proof.plonk_zs_partial_products_cap)?;
-        self.write_merkle_cap(&proof.quotient_polys_cap)?;
-        self.write_opening_set(&proof.openings)?;
-        self.write_compressed_fri_proof::<F, C, D>(&proof.opening_proof)
-    }
     pub fn read_compressed_proof<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -575,21 +849,6 @@ impl Buffer {
         })
     }
 
-    pub fn write_compressed_proof_with_public_inputs<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >(
-        &mut self,
-        proof_with_pis: &CompressedProofWithPublicInputs<F, C, D>,
-    ) -> Result<()> {
-        let CompressedProofWithPublicInputs {
-            proof,
-            public_inputs,
-        } = proof_with_pis;
-        self.write_compressed_proof(proof)?;
-        self.write_field_vec(public_inputs)
-    }
     pub fn read_compressed_proof_with_public_inputs<
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -599,9 +858,7 @@ impl Buffer {
         common_data: &CommonCircuitData<F, C, D>,
     ) -> Result<CompressedProofWithPublicInputs<F, C, D>> {
         let proof = self.read_compressed_proof(common_data)?;
-        let public_inputs = self.read_field_vec(
-            (self.len() - self.0.position() as usize) / std::mem::size_of::<u64>(),
-        )?;
+        let public_inputs = self.read_public_inputs(common_data.num_public_inputs)?;
 
         Ok(CompressedProofWithPublicInputs {
             proof,
@@ -609,3 +866,177 @@ impl Buffer {
         })
     }
 }
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// The number of bytes taken by each component of a serialized `ProofWithPublicInputs`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProofSizeReport {
+    pub wires_cap: usize,
+    pub plonk_zs_partial_products_cap: usize,
+    pub quotient_polys_cap: usize,
+    pub openings: usize,
+    pub commit_phase_merkle_caps: usize,
+    pub query_rounds: Vec<FriQueryRoundSize>,
+    pub final_poly: usize,
+    pub pow_witness: usize,
+    pub public_inputs: usize,
+}
+
+/// The number of bytes taken by each component of a serialized FRI query round.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FriQueryRoundSize {
+    /// The opened leaves of the initial trees.
+    pub initial_leaves: usize,
+    /// The Merkle paths of the initial trees, including their length prefixes.
+    pub initial_merkle_paths: usize,
+    /// The coset evaluations of all reduction steps.
+    pub step_evals: usize,
+    /// The Merkle paths of all reduction steps, including their length prefixes.
+    pub step_merkle_paths: usize,
+}
+
+impl FriQueryRoundSize {
+    pub fn total(&self) -> usize {
+        self.initial_leaves + self.initial_merkle_paths + self.step_evals + self.step_merkle_paths
+    }
+}
+
+impl ProofSizeReport {
+    /// The size of all query rounds, summed component-wise.
+    pub fn query_rounds_total(&self) -> FriQueryRoundSize {
+        self.query_rounds
+            .iter()
+            .fold(FriQueryRoundSize::default(), |acc, r| FriQueryRoundSize {
+                initial_leaves: acc.initial_leaves + r.initial_leaves,
+                initial_merkle_paths: acc.initial_merkle_paths + r.initial_merkle_paths,
+                step_evals: acc.step_evals + r.step_evals,
+                step_merkle_paths: acc.step_merkle_paths + r.step_merkle_paths,
+            })
+    }
+
+    /// The size of the whole proof, which is that of its serialization.
+    pub fn total(&self) -> usize {
+        self.wires_cap
+            + self.plonk_zs_partial_products_cap
+            + self.quotient_polys_cap
+            + self.openings
+            + self.commit_phase_merkle_caps
+            + self.query_rounds_total().total()
+            + self.final_poly
+            + self.pow_witness
+            + self.public_inputs
+    }
+}
+
+impl Display for ProofSizeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        let rounds = self.query_rounds_total();
+        let rows = [
+            ("wires cap", self.wires_cap),
+            (
+                "Zs and partial products cap",
+                self.plonk_zs_partial_products_cap,
+            ),
+            ("quotient polys cap", self.quotient_polys_cap),
+            ("openings", self.openings),
+            ("FRI commit phase caps", self.commit_phase_merkle_caps),
+            ("FRI initial leaves", rounds.initial_leaves),
+            ("FRI initial Merkle paths", rounds.initial_merkle_paths),
+            ("FRI step evaluations", rounds.step_evals),
+            ("FRI step Merkle paths", rounds.step_merkle_paths),
+            ("FRI final poly", self.final_poly),
+            ("FRI proof of work", self.pow_witness),
+            ("public inputs", self.public_inputs),
+        ];
+        for (name, size) in rows {
+            writeln!(
+                f,
+                "{:<28} {:>8} bytes ({:>5.1}%)",
+                name,
+                size,
+                100.0 * size as f64 / total as f64
+            )?;
+        }
+        write!(f, "{:<28} {:>8} bytes", "total", total)
+    }
+}
+
+/// Measures each component of a proof by serializing it with the given encoding.
+pub(crate) fn proof_size_report<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    encoding: Encoding,
+) -> Result<ProofSizeReport> {
+    let ProofWithPublicInputs {
+        proof,
+        public_inputs,
+    } = proof_with_pis;
+    let fri_proof = &proof.opening_proof;
+
+    let query_rounds = fri_proof
+        .query_round_proofs
+        .iter()
+        .map(|round| {
+            let evals_proofs = &round.initial_trees_proof.evals_proofs;
+            Ok(FriQueryRoundSize {
+                initial_leaves: size(encoding, |b| {
+                    evals_proofs
+                        .iter()
+                        .try_for_each(|(v, _)| b.write_field_vec(v))
+                })?,
+                initial_merkle_paths: size(encoding, |b| {
+                    evals_proofs
+                        .iter()
+                        .try_for_each(|(_, p)| b.write_merkle_proof(p))
+                })?,
+                step_evals: size(encoding, |b| {
+                    round
+                        .steps
+                        .iter()
+                        .try_for_each(|s| b.write_field_ext_vec::<F, D>(&s.evals))
+                })?,
+                step_merkle_paths: size(encoding, |b| {
+                    round
+                        .steps
+                        .iter()
+                        .try_for_each(|s| b.write_merkle_proof(&s.merkle_proof))
+                })?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(ProofSizeReport {
+        wires_cap: size(encoding, |b| b.write_merkle_cap(&proof.wires_cap))?,
+        plonk_zs_partial_products_cap: size(encoding, |b| {
+            b.write_merkle_cap(&proof.plonk_zs_partial_products_cap)
+        })?,
+        quotient_polys_cap: size(encoding, |b| b.write_merkle_cap(&proof.quotient_polys_cap))?,
+        openings: size(encoding, |b| b.write_opening_set(&proof.openings))?,
+        commit_phase_merkle_caps: size(encoding, |b| {
+            fri_proof
+                .commit_phase_merkle_caps
+                .iter()
+                .try_for_each(|cap| b.write_merkle_cap(cap))
+        })?,
+        query_rounds,
+        final_poly: size(encoding, |b| {
+            b.write_field_ext_vec::<F, D>(&fri_proof.final_poly.coeffs)
+        })?,
+        pow_witness: size(encoding, |b| b.write_field(fri_proof.pow_witness))?,
+        public_inputs: size(encoding, |b| b.write_field_vec(public_inputs))?,
+    })
+}
+
+/// The number of bytes written by `write` with the given encoding.
+fn size(encoding: Encoding, write: impl FnOnce(&mut Buffer) -> Result<()>) -> Result<usize> {
+    let mut buffer = Buffer::with_encoding(Vec::new(), encoding);
+    write(&mut buffer)?;
+    Ok(buffer.len())
+}
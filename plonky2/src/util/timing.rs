@@ -1,8 +1,18 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
+#[cfg(feature = "timing")]
+use std::time::Instant;
 
 use log::{log, Level};
+#[cfg(feature = "timing")]
+use tracing::Span;
 
 /// The hierarchy of scopes, and the time consumed by each one. Useful for profiling.
+///
+/// Each scope other than the root is also reported as a `tracing` span named `timed`, with the
+/// scope's name in its `scope` field. Scopes aren't entered, since a scope may be open while work is
+/// spread over several threads; a top-level scope is a child of the span which is current when it
+/// is pushed, and any other scope is a child of its parent scope.
+#[cfg(feature = "timing")]
 pub struct TimingTree {
     /// The name of this scope.
     name: String,
@@ -14,6 +24,8 @@ pub struct TimingTree {
     exit_time: Option<Instant>,
     /// Any child scopes.
     children: Vec<TimingTree>,
+    /// The span reporting this scope, which is closed along with it. Disabled for the root.
+    span: Span,
 }
 
 impl Default for TimingTree {
@@ -22,6 +34,7 @@ impl Default for TimingTree {
     }
 }
 
+#[cfg(feature = "timing")]
 impl TimingTree {
     pub fn new(root_name: &str, level: Level) -> Self {
         Self {
@@ -30,6 +43,7 @@ impl TimingTree {
             enter_time: Instant::now(),
             exit_time: None,
             children: vec![],
+            span: Span::none(),
         }
     }
 
@@ -67,12 +81,15 @@ impl TimingTree {
             }
         }
 
+        let parent = (!self.span.is_none()).then_some(&self.span);
+        let span = scope_span(ctx, level, parent);
         self.children.push(TimingTree {
             name: ctx.to_string(),
             level,
             enter_time: Instant::now(),
             exit_time: None,
             children: vec![],
+            span,
         })
     }
 
@@ -88,6 +105,7 @@ impl TimingTree {
         }
 
         self.exit_time = Some(Instant::now());
+        self.span = Span::none();
     }
 
     fn duration(&self) -> Duration {
@@ -109,6 +127,7 @@ impl TimingTree {
                 .filter(|c| c.duration() >= min_delta)
                 .map(|c| c.filter(min_delta))
                 .collect(),
+            span: Span::none(),
         }
     }
 
@@ -131,6 +150,58 @@ impl TimingTree {
     }
 }
 
+/// Without the `timing` feature, a timing tree only remembers its level, so that `print` can still
+/// say why there is nothing to print.
+#[cfg(not(feature = "timing"))]
+pub struct TimingTree(Level);
+
+#[cfg(not(feature = "timing"))]
+impl TimingTree {
+    pub fn new(_root_name: &str, level: Level) -> Self {
+        Self(level)
+    }
+
+    pub fn open_stack(&self) -> String {
+        String::new()
+    }
+
+    pub fn push(&mut self, _ctx: &str, _level: log::Level) {}
+
+    pub fn pop(&mut self) {}
+
+    pub fn filter(&self, _min_delta: Duration) -> Self {
+        Self(self.0)
+    }
+
+    pub fn print(&self) {
+        log!(
+            self.0,
+            "TimingTree is empty without the `timing` feature of plonky2"
+        );
+    }
+}
+
+/// Creates the span reporting a scope, as a child of `parent`, or of the current span if `None`.
+#[cfg(feature = "timing")]
+fn scope_span(name: &str, level: Level, parent: Option<&Span>) -> Span {
+    // The level of a span is part of its static metadata, so each level needs its own callsite.
+    macro_rules! span {
+        ($level:expr) => {
+            match parent {
+                Some(parent) => tracing::span!(parent: parent, $level, "timed", scope = name),
+                None => tracing::span!($level, "timed", scope = name),
+            }
+        };
+    }
+    match level {
+        Level::Error => span!(tracing::Level::ERROR),
+        Level::Warn => span!(tracing::Level::WARN),
+        Level::Info => span!(tracing::Level::INFO),
+        Level::Debug => span!(tracing::Level::DEBUG),
+        Level::Trace => span!(tracing::Level::TRACE),
+    }
+}
+
 /// Creates a named scope; useful for debugging.
 #[macro_export]
 macro_rules! timed {
@@ -148,3 +219,132 @@ macro_rules! timed {
         res
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use plonky2_field::field_types::Field;
+    use tracing::field::Visit;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    type SpanFields = HashMap<&'static str, String>;
+
+    /// A subscriber which records the name and fields of every span, where the span with ID `i`
+    /// is the `i - 1`th one recorded.
+    #[derive(Default)]
+    struct SpanRecorder {
+        spans: Arc<Mutex<Vec<(&'static str, SpanFields)>>>,
+    }
+
+    struct FieldRecorder<'a>(&'a mut SpanFields);
+
+    impl Visit for FieldRecorder<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn Debug) {
+            self.0.insert(field.name(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = HashMap::new();
+            span.record(&mut FieldRecorder(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((span.metadata().name(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let (_, fields) = &mut spans[span.into_u64() as usize - 1];
+            values.record(&mut FieldRecorder(fields));
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_spans() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let recorder = SpanRecorder::default();
+        let spans = recorder.spans.clone();
+        let degree_bits = tracing::subscriber::with_default(recorder, || -> Result<usize> {
+            let config = CircuitConfig::standard_recursion_config();
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let x = builder.add_virtual_target();
+            let y = builder.square(x);
+            builder.register_public_input(y);
+            let data = builder.build::<C>();
+
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::TWO);
+            let proof = data.prove(pw)?;
+            verify(proof, &data.verifier_only, &data.common)?;
+            Ok(data.common.degree_bits)
+        })?;
+
+        let spans = spans.lock().unwrap();
+        let span = |name: &str| {
+            spans
+                .iter()
+                .find(|(span_name, _)| *span_name == name)
+                .map(|(_, fields)| fields)
+                .unwrap_or_else(|| panic!("No {} span was created", name))
+        };
+
+        let build_span = span("build");
+        assert_eq!(build_span["degree_bits"], degree_bits.to_string());
+        assert_eq!(build_span["num_public_inputs"], "1");
+        for field in [
+            "num_gates",
+            "num_constants",
+            "quotient_degree_factor",
+            "num_generators",
+        ] {
+            assert!(
+                build_span.contains_key(field),
+                "The build span has no {}",
+                field
+            );
+        }
+
+        let prove_span = span("prove");
+        assert_eq!(prove_span["degree_bits"], degree_bits.to_string());
+        assert_eq!(prove_span["num_public_inputs"], "1");
+        assert!(prove_span.contains_key("lde_bits"));
+
+        let verify_span = span("verify");
+        assert_eq!(verify_span["degree_bits"], degree_bits.to_string());
+        assert!(verify_span.contains_key("num_query_rounds"));
+
+        #[cfg(feature = "timing")]
+        assert!(spans
+            .iter()
+            .any(|(name, fields)| *name == "timed" && fields.contains_key("scope")));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+//! Zeroization of memory holding witness data. With the `hardened` feature, the prover overwrites
+//! its witness buffers and the LDEs derived from them once they're no longer needed, so that
+//! secrets don't linger in freed memory, e.g. for provers sharing a machine with untrusted code.
+//!
+//! Writes are volatile and followed by a compiler fence, so they aren't elided as dead stores.
+//! This can't reach copies made outside of these buffers, such as the old allocation of a vector
+//! which has since grown, temporaries inside FFT backends, or pages swapped to disk. The values and
+//! LDEs kept in an `LdeCache` for incremental proving are retained by design, so should be avoided.
+
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use plonky2_field::extension_field::Extendable;
+use plonky2_field::field_types::Field;
+
+use crate::fri::oracle::PolynomialBatch;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::GeneratedValues;
+use crate::iop::witness::{MatrixWitness, PartialWitness, PartitionWitness};
+use crate::plonk::config::GenericConfig;
+
+/// A buffer which may hold witness data, and can be overwritten with zeros.
+pub trait Zeroize {
+    /// Overwrites the buffer with zeros, leaving it empty.
+    fn zeroize(&mut self);
+}
+
+/// Owns a buffer which is zeroized when it's dropped, if the `hardened` feature is enabled, so that
+/// it's also zeroized when the prover returns early with an error or unwinds from a panic.
+pub(crate) struct ZeroizeOnDrop<T: Zeroize>(T);
+
+impl<T: Zeroize> ZeroizeOnDrop<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Releases the buffer without zeroizing it, leaving its owner responsible for it.
+    pub(crate) fn into_inner(self) -> T {
+        let this = ManuallyDrop::new(self);
+        // Safety: `this` is never dropped, so the value is moved out of it exactly once.
+        unsafe { ptr::read(&this.0) }
+    }
+}
+
+impl<T: Zeroize> Deref for ZeroizeOnDrop<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> DerefMut for ZeroizeOnDrop<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Zeroize> Drop for ZeroizeOnDrop<T> {
+    fn drop(&mut self) {
+        if cfg!(feature = "hardened") {
+            self.0.zeroize();
+        }
+    }
+}
+
+/// Overwrites the whole allocation of `vec` with zeros, including any elements past its length,
+/// such as ones which were drained, and then clears it.
+pub(crate) fn zeroize_vec<T: Copy>(vec: &mut Vec<T>) {
+    let ptr = vec.as_mut_ptr() as *mut MaybeUninit<T>;
+    for i in 0..vec.capacity() {
+        // Safety: `i` is within the allocation, and a `MaybeUninit` may hold any bytes.
+        unsafe { ptr::write_volatile(ptr.add(i), MaybeUninit::zeroed()) };
+    }
+    vec.clear();
+    compiler_fence(Ordering::SeqCst);
+}
+
+pub(crate) fn zeroize_vecs<T: Copy>(vecs: &mut Vec<Vec<T>>) {
+    vecs.iter_mut().for_each(zeroize_vec);
+    vecs.clear();
+}
+
+impl<F: Field> Zeroize for PartialWitness<F> {
+    fn zeroize(&mut self) {
+        for value in self.target_values.values_mut() {
+            // Safety: `value` is a valid, aligned reference.
+            unsafe { ptr::write_volatile(value, F::ZERO) };
+        }
+        self.target_values.clear();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl<'a, F: Field> Zeroize for PartitionWitness<'a, F> {
+    fn zeroize(&mut self) {
+        zeroize_vec(&mut self.values);
+    }
+}
+
+impl<F: Field> Zeroize for MatrixWitness<F> {
+    fn zeroize(&mut self) {
+        zeroize_vecs(&mut self.wire_values);
+    }
+}
+
+impl<F: Field> Zeroize for GeneratedValues<F> {
+    fn zeroize(&mut self) {
+        zeroize_vec(&mut self.target_values);
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Zeroize
+    for PolynomialBatch<F, C, D>
+{
+    /// Zeroizes the polynomials, their LDEs and salts, and the masking polynomial, but not the
+    /// Merkle digests, which don't reveal the leaves.
+    fn zeroize(&mut self) {
+        for polynomial in &mut self.polynomials {
+            zeroize_vec(&mut polynomial.coeffs);
+        }
+        self.polynomials.clear();
+        zeroize_vecs(&mut self.merkle_tree.leaves);
+        if let Some(masking_polynomial) = &mut self.masking_polynomial {
+            zeroize_vec(&mut masking_polynomial.coeffs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use anyhow::{bail, Result};
+    use plonky2_field::field_types::Field;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use crate::util::zeroize::{zeroize_vec, Zeroize, ZeroizeOnDrop};
+
+    type F = GoldilocksField;
+
+    struct Flag(Rc<Cell<bool>>);
+
+    impl Zeroize for Flag {
+        fn zeroize(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    #[test]
+    fn test_zeroize_vec() {
+        let mut vec = F::rand_vec(8);
+        vec.truncate(3);
+        let ptr = vec.as_ptr();
+        zeroize_vec(&mut vec);
+        assert!(vec.is_empty());
+        assert_eq!(vec.as_ptr(), ptr);
+        // Safety: the allocation is still live, and was filled with zeros.
+        let allocation = unsafe { std::slice::from_raw_parts(ptr, vec.capacity()) };
+        assert!(allocation.iter().all(|&x| x == F::ZERO));
+    }
+
+    #[test]
+    fn test_zeroize_on_drop() {
+        fn fail(flag: Flag) -> Result<()> {
+            let _flag = ZeroizeOnDrop::new(flag);
+            bail!("early return")
+        }
+
+        let zeroized = Rc::new(Cell::new(false));
+        assert!(fail(Flag(zeroized.clone())).is_err());
+        assert_eq!(zeroized.get(), cfg!(feature = "hardened"));
+
+        // Released buffers are left to their new owner.
+        let zeroized = Rc::new(Cell::new(false));
+        let flag = ZeroizeOnDrop::new(Flag(zeroized.clone())).into_inner();
+        assert!(!zeroized.get());
+        drop(flag);
+        assert!(!zeroized.get());
+    }
+
+    #[cfg(feature = "hardened")]
+    #[test]
+    fn test_prove_hardened() -> Result<()> {
+        use crate::hash::poseidon::PoseidonHash;
+        use crate::iop::witness::{PartialWitness, Witness};
+        use crate::plonk::circuit_builder::CircuitBuilder;
+        use crate::plonk::circuit_data::CircuitConfig;
+        use crate::plonk::config::PoseidonGoldilocksConfig;
+        use crate::util::progress::{
+            CancellationToken, ProvingEvent, ProvingMonitor, ProvingPhase,
+        };
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let preimage = builder.add_virtual_targets(4);
+        let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage.clone());
+        builder.register_public_inputs(&hash.elements);
+        let data = builder.build::<C>();
+        let witness = || {
+            let mut pw = PartialWitness::new();
+            for &t in &preimage {
+                pw.set_target(t, F::rand());
+            }
+            pw
+        };
+
+        let proof = data.prove(witness())?;
+        data.verify(proof)?;
+
+        // A proof abandoned after the wires are committed to returns early, dropping its buffers.
+        let token = CancellationToken::new();
+        let monitor = {
+            let token = token.clone();
+            ProvingMonitor::new()
+                .with_cancellation(token.clone())
+                .with_callback(move |e| {
+                    if e == ProvingEvent::PhaseStarted(ProvingPhase::CommitPartialProducts) {
+                        token.cancel();
+                    }
+                })
+        };
+        assert!(data.prove_with_monitor(witness(), &monitor).is_err());
+
+        Ok(())
+    }
+}
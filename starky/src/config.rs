@@ -1,5 +1,5 @@
 use plonky2::fri::reduction_strategies::FriReductionStrategy;
-use plonky2::fri::{FriConfig, FriParams};
+use plonky2::fri::{FriConfig, FriParams, SaltMode};
 
 pub struct StarkConfig {
     pub security_bits: usize,
@@ -21,6 +21,7 @@ impl StarkConfig {
             fri_config: FriConfig {
                 rate_bits: 1,
                 cap_height: 4,
+                commit_phase_cap_height: 4,
                 proof_of_work_bits: 10,
                 reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
                 num_query_rounds: 90,
@@ -29,6 +30,7 @@ impl StarkConfig {
     }
 
     pub(crate) fn fri_params(&self, degree_bits: usize) -> FriParams {
-        self.fri_config.fri_params(degree_bits, false)
+        self.fri_config
+            .fri_params(degree_bits, 0, SaltMode::PerLeaf)
     }
 }
@@ -110,6 +110,8 @@ mod tests {
     use anyhow::Result;
     use plonky2::field::extension_field::Extendable;
     use plonky2::field::field_types::Field;
+    use plonky2::fri::grinding::PoseidonGrinder;
+    use plonky2::fri::pcs::FriProverParams;
     use plonky2::hash::hash_types::RichField;
     use plonky2::iop::witness::PartialWitness;
     use plonky2::plonk::circuit_builder::CircuitBuilder;
@@ -117,12 +119,13 @@ mod tests {
     use plonky2::plonk::config::{
         AlgebraicHasher, GenericConfig, Hasher, PoseidonGoldilocksConfig,
     };
+    use plonky2::util::progress::{CancellationToken, ProvingEvent, ProvingMonitor, ProvingPhase};
     use plonky2::util::timing::TimingTree;
 
     use crate::config::StarkConfig;
     use crate::fibonacci_stark::FibonacciStark;
     use crate::proof::StarkProofWithPublicInputs;
-    use crate::prover::prove;
+    use crate::prover::{prove, prove_with_params};
     use crate::recursive_verifier::{
         add_virtual_stark_proof_with_pis, recursively_verify_stark_proof,
         set_stark_proof_with_pis_target,
@@ -158,6 +161,101 @@ mod tests {
         verify_stark_proof(stark, proof, &config)
     }
 
+    #[test]
+    fn test_fibonacci_stark_pow_grinder() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = FibonacciStark<F, D>;
+
+        let config = StarkConfig::standard_fast_config();
+        let num_rows = 1 << 5;
+        let public_inputs = [F::ZERO, F::ONE, fibonacci(num_rows - 1, F::ZERO, F::ONE)];
+        let stark = S::new(num_rows);
+        let trace = stark.generate_trace(public_inputs[0], public_inputs[1]);
+        let prover_params = FriProverParams {
+            pow_grinder: Box::new(PoseidonGrinder),
+            ..FriProverParams::default()
+        };
+        let proof = prove_with_params::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            public_inputs,
+            &prover_params,
+            &mut TimingTree::default(),
+            &ProvingMonitor::default(),
+        )?;
+
+        verify_stark_proof(stark, proof, &config)
+    }
+
+    #[test]
+    fn test_fibonacci_stark_cancelled() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = FibonacciStark<F, D>;
+
+        let config = StarkConfig::standard_fast_config();
+        let num_rows = 1 << 5;
+        let public_inputs = [F::ZERO, F::ONE, fibonacci(num_rows - 1, F::ZERO, F::ONE)];
+        let stark = S::new(num_rows);
+        let trace = stark.generate_trace(public_inputs[0], public_inputs[1]);
+
+        let token = CancellationToken::new();
+        let monitor = {
+            let token = token.clone();
+            ProvingMonitor::new()
+                .with_cancellation(token.clone())
+                .with_callback(move |e| {
+                    if e == ProvingEvent::PhaseStarted(ProvingPhase::FriCommit) {
+                        token.cancel();
+                    }
+                })
+        };
+        assert!(prove_with_params::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            public_inputs,
+            &FriProverParams::default(),
+            &mut TimingTree::default(),
+            &monitor,
+        )
+        .is_err());
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_fibonacci_stark_padded_trace() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = FibonacciStark<F, D>;
+
+        let config = StarkConfig::standard_fast_config();
+        let num_rows = (1 << 8) + 3;
+        let public_inputs = [F::ZERO, F::ONE, fibonacci(num_rows - 1, F::ZERO, F::ONE)];
+        let stark = S::new(num_rows);
+        let trace = stark.generate_trace(public_inputs[0], public_inputs[1]);
+        let proof = prove::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            public_inputs,
+            &mut TimingTree::default(),
+        )?;
+        verify_stark_proof(stark, proof.clone(), &config)?;
+
+        // The proof doesn't verify for another trace length with the same padded length.
+        let mut wrong_len_proof = proof.clone();
+        wrong_len_proof.proof.trace_len = num_rows - 1;
+        assert!(verify_stark_proof(stark, wrong_len_proof, &config).is_err());
+
+        recursive_proof::<F, C, S, C, D>(stark, proof, &config, true)
+    }
+
     #[test]
     fn test_fibonacci_stark_degree() -> Result<()> {
         const D: usize = 2;
@@ -209,15 +307,17 @@ mod tests {
     ) -> Result<()>
     where
         InnerC::Hasher: AlgebraicHasher<F>,
+        InnerC::CommitPhaseHasher: AlgebraicHasher<F>,
         [(); S::COLUMNS]:,
         [(); S::PUBLIC_INPUTS]:,
         [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
     {
         let circuit_config = CircuitConfig::standard_recursion_config();
         let mut builder = CircuitBuilder::<F, D>::new(circuit_config);
         let mut pw = PartialWitness::new();
-        let degree_bits = inner_proof.proof.recover_degree_bits(inner_config);
-        let pt = add_virtual_stark_proof_with_pis(&mut builder, stark, inner_config, degree_bits);
+        let trace_len = inner_proof.proof.trace_len;
+        let pt = add_virtual_stark_proof_with_pis(&mut builder, stark, inner_config, trace_len);
         set_stark_proof_with_pis_target(&mut pw, &pt, &inner_proof);
 
         recursively_verify_stark_proof::<F, InnerC, S, D>(&mut builder, stark, pt, inner_config);
@@ -18,10 +18,11 @@ use crate::proof::{
 };
 
 fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    trace_len: usize,
     trace_cap: &MerkleCap<F, C::Hasher>,
     quotient_polys_cap: &MerkleCap<F, C::Hasher>,
     openings: &StarkOpeningSet<F, D>,
-    commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    commit_phase_merkle_caps: &[MerkleCap<F, C::CommitPhaseHasher>],
     final_poly: &PolynomialCoeffs<F::Extension>,
     pow_witness: F,
     config: &StarkConfig,
@@ -31,6 +32,7 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
 
     let mut challenger = Challenger::<F, C::Hasher>::new();
 
+    challenger.observe_element(F::from_canonical_usize(trace_len));
     challenger.observe_cap(trace_cap);
     let stark_alphas = challenger.get_n_challenges(num_challenges);
 
@@ -73,6 +75,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         degree_bits: usize,
     ) -> Result<StarkProofChallenges<F, D>> {
         let StarkProof {
+            trace_len,
             trace_cap,
             quotient_polys_cap,
             openings,
@@ -86,6 +89,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         } = &self.proof;
 
         get_challenges::<F, C, D>(
+            *trace_len,
             trace_cap,
             quotient_polys_cap,
             openings,
@@ -105,6 +109,7 @@ pub(crate) fn get_challenges_target<
     const D: usize,
 >(
     builder: &mut CircuitBuilder<F, D>,
+    trace_len: usize,
     trace_cap: &MerkleCapTarget,
     quotient_polys_cap: &MerkleCapTarget,
     openings: &StarkOpeningSetTarget<D>,
@@ -120,6 +125,8 @@ where
 
     let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(builder);
 
+    let trace_len = builder.constant(F::from_canonical_usize(trace_len));
+    challenger.observe_element(trace_len);
     challenger.observe_cap(trace_cap);
     let stark_alphas = challenger.get_n_challenges(builder, num_challenges);
 
@@ -151,6 +158,7 @@ impl<const D: usize> StarkProofWithPublicInputsTarget<D> {
         C::Hasher: AlgebraicHasher<F>,
     {
         let StarkProofTarget {
+            trace_len,
             trace_cap,
             quotient_polys_cap,
             openings,
@@ -165,6 +173,7 @@ impl<const D: usize> StarkProofWithPublicInputsTarget<D> {
 
         get_challenges_target::<F, C, D>(
             builder,
+            *trace_len,
             trace_cap,
             quotient_polys_cap,
             openings,
@@ -17,6 +17,8 @@ use crate::config::StarkConfig;
 
 #[derive(Debug, Clone)]
 pub struct StarkProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    /// The number of rows of the trace, which is padded to the next power of two.
+    pub trace_len: usize,
     /// Merkle cap of LDEs of trace values.
     pub trace_cap: MerkleCap<F, C::Hasher>,
     /// Merkle cap of LDEs of trace values.
@@ -24,7 +26,7 @@ pub struct StarkProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     /// Purported values of each polynomial at the challenge point.
     pub openings: StarkOpeningSet<F, D>,
     /// A batch FRI argument for all openings.
-    pub opening_proof: FriProof<F, C::Hasher, D>,
+    pub opening_proof: FriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> StarkProof<F, C, D> {
@@ -39,6 +41,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> S
 }
 
 pub struct StarkProofTarget<const D: usize> {
+    pub trace_len: usize,
     pub trace_cap: MerkleCapTarget,
     pub quotient_polys_cap: MerkleCapTarget,
     pub openings: StarkOpeningSetTarget<D>,
@@ -77,12 +80,14 @@ pub struct CompressedStarkProof<
     C: GenericConfig<D, F = F>,
     const D: usize,
 > {
+    /// The number of rows of the trace, which is padded to the next power of two.
+    pub trace_len: usize,
     /// Merkle cap of LDEs of trace values.
     pub trace_cap: MerkleCap<F, C::Hasher>,
     /// Purported values of each polynomial at the challenge point.
     pub openings: StarkOpeningSet<F, D>,
     /// A batch FRI argument for all openings.
-    pub opening_proof: CompressedFriProof<F, C::Hasher, D>,
+    pub opening_proof: CompressedFriProof<F, C::Hasher, C::CommitPhaseHasher, D>,
 }
 
 pub struct CompressedStarkProofWithPublicInputs<
@@ -5,13 +5,16 @@ use plonky2::field::field_types::Field;
 use plonky2::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use plonky2::field::zero_poly_coset::ZeroPolyOnCoset;
 use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::fri::pcs::FriProverParams;
+use plonky2::fri::SaltMode;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::challenger::Challenger;
 use plonky2::plonk::config::{GenericConfig, Hasher};
 use plonky2::timed;
+use plonky2::util::progress::{ProvingMonitor, ProvingPhase};
 use plonky2::util::timing::TimingTree;
 use plonky2::util::transpose;
-use plonky2_util::{log2_ceil, log2_strict};
+use plonky2_util::log2_ceil;
 use rayon::prelude::*;
 
 use crate::config::StarkConfig;
@@ -34,11 +37,55 @@ where
     [(); S::COLUMNS]:,
     [(); S::PUBLIC_INPUTS]:,
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
-    let degree = trace.len();
-    let degree_bits = log2_strict(degree);
+    prove_with_params(
+        stark,
+        config,
+        trace,
+        public_inputs,
+        &FriProverParams::default(),
+        timing,
+        &ProvingMonitor::default(),
+    )
+}
+
+/// Like `prove`, but runs FFTs and the proof-of-work search on the backends of `prover_params`,
+/// reports progress to `monitor` and fails if the proof is cancelled through it. The trace is
+/// committed to in the `CommitWires` phase.
+pub fn prove_with_params<F, C, S, const D: usize>(
+    stark: S,
+    config: &StarkConfig,
+    trace: Vec<[F; S::COLUMNS]>,
+    public_inputs: [F; S::PUBLIC_INPUTS],
+    prover_params: &FriProverParams<F, C, D>,
+    timing: &mut TimingTree,
+    monitor: &ProvingMonitor,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+    [(); S::COLUMNS]:,
+    [(); S::PUBLIC_INPUTS]:,
+    [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
+{
+    let trace_len = trace.len();
+    ensure!(trace_len > 0, "The trace is empty.");
+    let degree_bits = log2_ceil(trace_len);
+    let degree = 1 << degree_bits;
 
-    let trace_vecs = trace.into_iter().map(|row| row.to_vec()).collect_vec();
+    // Pad the trace to a power of two by repeating it from its first row. As the trace is more than
+    // half of the padded trace, every padding row is a copy of a trace row whose next row is also
+    // copied, so the constraints hold on the padding rows too, except for transition constraints
+    // on the last one, whose next row is the first row.
+    let trace_vecs = trace
+        .iter()
+        .cycle()
+        .take(degree)
+        .map(|row| row.to_vec())
+        .collect_vec();
     let trace_col_major: Vec<Vec<F>> = transpose(&trace_vecs);
 
     let trace_poly_values: Vec<PolynomialValues<F>> = timed!(
@@ -50,6 +97,7 @@ where
             .collect()
     );
 
+    monitor.start_phase(ProvingPhase::CommitWires)?;
     let rate_bits = config.fri_config.rate_bits;
     let cap_height = config.fri_config.cap_height;
     let trace_commitment = timed!(
@@ -58,23 +106,28 @@ where
         PolynomialBatch::<F, C, D>::from_values(
             trace_poly_values,
             rate_bits,
-            false,
+            0,
+            SaltMode::PerLeaf,
             cap_height,
             timing,
-            None,
+            monitor,
+            prover_params.fft_backend.as_ref(),
         )
     );
 
     let trace_cap = trace_commitment.merkle_tree.cap.clone();
     let mut challenger = Challenger::new();
+    challenger.observe_element(F::from_canonical_usize(trace_len));
     challenger.observe_cap(&trace_cap);
 
     let alphas = challenger.get_n_challenges(config.num_challenges);
+    monitor.start_phase(ProvingPhase::ComputeQuotient)?;
     let quotient_polys = compute_quotient_polys::<F, C, S, D>(
         &stark,
         &trace_commitment,
         public_inputs,
         alphas,
+        trace_len,
         degree_bits,
         rate_bits,
     );
@@ -82,22 +135,25 @@ where
         .into_par_iter()
         .flat_map(|mut quotient_poly| {
             quotient_poly
-                .trim_to_len(degree * stark.quotient_degree_factor())
+                .trim_to_len(degree * stark.num_quotient_polys(trace_len))
                 .expect("Quotient has failed, the vanishing polynomial is not divisible by Z_H");
             // Split quotient into degree-n chunks.
             quotient_poly.chunks(degree)
         })
         .collect();
+    monitor.start_phase(ProvingPhase::CommitQuotient)?;
     let quotient_commitment = timed!(
         timing,
         "compute quotient commitment",
         PolynomialBatch::from_coeffs(
             all_quotient_chunks,
             rate_bits,
-            false,
+            0,
+            SaltMode::PerLeaf,
             config.fri_config.cap_height,
             timing,
-            None,
+            monitor,
+            prover_params.fft_backend.as_ref(),
         )
     );
     let quotient_polys_cap = quotient_commitment.merkle_tree.cap.clone();
@@ -112,6 +168,7 @@ where
         zeta.exp_power_of_2(degree_bits) != F::Extension::ONE,
         "Opening point is in the subgroup."
     );
+    monitor.start_phase(ProvingPhase::ComputeOpenings)?;
     let openings = StarkOpeningSet::new(zeta, g, &trace_commitment, &quotient_commitment);
     challenger.observe_openings(&openings.to_fri_openings());
 
@@ -123,14 +180,19 @@ where
         timing,
         "compute openings proof",
         PolynomialBatch::prove_openings(
-            &stark.fri_instance(zeta, g, config.num_challenges),
+            &stark.fri_instance(zeta, g, trace_len, config.num_challenges),
             initial_merkle_trees,
             &mut challenger,
             &fri_params,
+            prover_params.fft_backend.as_ref(),
+            prover_params.pow_grinder.as_ref(),
             timing,
+            monitor,
+            None,
         )
-    );
+    )?;
     let proof = StarkProof {
+        trace_len,
         trace_cap,
         quotient_polys_cap,
         openings,
@@ -150,6 +212,7 @@ fn compute_quotient_polys<F, C, S, const D: usize>(
     trace_commitment: &PolynomialBatch<F, C, D>,
     public_inputs: [F; S::PUBLIC_INPUTS],
     alphas: Vec<F>,
+    trace_len: usize,
     degree_bits: usize,
     rate_bits: usize,
 ) -> Vec<PolynomialCoeffs<F>>
@@ -162,7 +225,7 @@ where
 {
     let degree = 1 << degree_bits;
 
-    let quotient_degree_bits = log2_ceil(stark.quotient_degree_factor());
+    let quotient_degree_bits = log2_ceil(stark.num_quotient_polys(trace_len));
     assert!(
         quotient_degree_bits <= rate_bits,
         "Having constraints of degree higher than the rate is not supported yet."
@@ -175,7 +238,7 @@ where
     let lagrange_first = PolynomialValues::selector(degree, 0).lde_onto_coset(quotient_degree_bits);
     // Evaluation of the last Lagrange polynomial on the LDE domain.
     let lagrange_last =
-        PolynomialValues::selector(degree, degree - 1).lde_onto_coset(quotient_degree_bits);
+        PolynomialValues::selector(degree, trace_len - 1).lde_onto_coset(quotient_degree_bits);
 
     let z_h_on_coset = ZeroPolyOnCoset::<F>::new(degree_bits, quotient_degree_bits);
 
@@ -183,8 +246,15 @@ where
     let get_at_index = |comm: &PolynomialBatch<F, C, D>, i: usize| -> [F; S::COLUMNS] {
         comm.get_lde_values(i * step).try_into().unwrap()
     };
-    // Last element of the subgroup.
-    let last = F::primitive_root_of_unity(degree_bits).inverse();
+    // Elements of the subgroup associated with the last trace row and, if the trace is padded, the
+    // last padding row, on which transition constraints aren't enforced.
+    let g = F::primitive_root_of_unity(degree_bits);
+    let last = g.exp_u64(trace_len as u64 - 1);
+    let last_padding = if trace_len < degree {
+        Some(g.inverse())
+    } else {
+        None
+    };
     let size = degree << quotient_degree_bits;
     let coset = F::cyclic_subgroup_coset_known_order(
         F::primitive_root_of_unity(degree_bits + quotient_degree_bits),
@@ -195,10 +265,14 @@ where
     let quotient_values = (0..size)
         .into_par_iter()
         .map(|i| {
+            let mut z_last = coset[i] - last;
+            if let Some(last_padding) = last_padding {
+                z_last *= coset[i] - last_padding;
+            }
             // TODO: Set `P` to a genuine `PackedField` here.
             let mut consumer = ConstraintConsumer::<F>::new(
                 alphas.clone(),
-                coset[i] - last,
+                z_last,
                 lagrange_first.values[i],
                 lagrange_last.values[i],
             );
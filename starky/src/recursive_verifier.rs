@@ -1,6 +1,7 @@
 use itertools::Itertools;
 use plonky2::field::extension_field::Extendable;
 use plonky2::field::field_types::Field;
+use plonky2::fri::structure::FriOracleInfo;
 use plonky2::fri::witness_util::set_fri_proof_target;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
@@ -8,6 +9,7 @@ use plonky2::iop::witness::Witness;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
 use plonky2::util::reducing::ReducingFactorTarget;
+use plonky2_util::log2_ceil;
 
 use crate::config::StarkConfig;
 use crate::constraint_consumer::RecursiveConstraintConsumer;
@@ -30,6 +32,7 @@ pub fn recursively_verify_stark_proof<
     inner_config: &StarkConfig,
 ) where
     C::Hasher: AlgebraicHasher<F>,
+    C::CommitPhaseHasher: AlgebraicHasher<F>,
     [(); S::COLUMNS]:,
     [(); S::PUBLIC_INPUTS]:,
 {
@@ -62,6 +65,7 @@ fn recursively_verify_stark_proof_with_challenges<
     degree_bits: usize,
 ) where
     C::Hasher: AlgebraicHasher<F>,
+    C::CommitPhaseHasher: AlgebraicHasher<F>,
     [(); S::COLUMNS]:,
     [(); S::PUBLIC_INPUTS]:,
 {
@@ -71,6 +75,7 @@ fn recursively_verify_stark_proof_with_challenges<
         proof,
         public_inputs,
     } = proof_with_pis;
+    let trace_len = proof.trace_len;
     let StarkOpeningSetTarget {
         local_values,
         next_values,
@@ -90,11 +95,14 @@ fn recursively_verify_stark_proof_with_challenges<
     };
     let zeta_pow_deg = builder.exp_power_of_2_extension(challenges.stark_zeta, degree_bits);
     let z_h_zeta = builder.sub_extension(zeta_pow_deg, one);
-    let (l_1, l_last) =
-        eval_l_1_and_l_last_recursively(builder, degree_bits, challenges.stark_zeta, z_h_zeta);
-    let last =
-        builder.constant_extension(F::Extension::primitive_root_of_unity(degree_bits).inverse());
-    let z_last = builder.sub_extension(challenges.stark_zeta, last);
+    let (l_1, l_last) = eval_l_1_and_l_last_recursively(
+        builder,
+        degree_bits,
+        trace_len,
+        challenges.stark_zeta,
+        z_h_zeta,
+    );
+    let z_last = eval_z_last_recursively(builder, degree_bits, trace_len, challenges.stark_zeta);
     let mut consumer = RecursiveConstraintConsumer::<F, D>::new(
         builder.zero_extension(),
         challenges.stark_alphas,
@@ -109,7 +117,7 @@ fn recursively_verify_stark_proof_with_challenges<
     let quotient_polys_zeta = &proof.openings.quotient_polys;
     let mut scale = ReducingFactorTarget::new(zeta_pow_deg);
     for (i, chunk) in quotient_polys_zeta
-        .chunks(stark.quotient_degree_factor())
+        .chunks(stark.num_quotient_polys(trace_len))
         .enumerate()
     {
         let recombined_quotient = scale.reduce(chunk, builder);
@@ -124,6 +132,7 @@ fn recursively_verify_stark_proof_with_challenges<
         builder,
         challenges.stark_zeta,
         F::primitive_root_of_unity(degree_bits),
+        trace_len,
         inner_config.num_challenges,
     );
     builder.verify_fri_proof::<C>(
@@ -139,14 +148,19 @@ fn recursively_verify_stark_proof_with_challenges<
 fn eval_l_1_and_l_last_recursively<F: RichField + Extendable<D>, const D: usize>(
     builder: &mut CircuitBuilder<F, D>,
     log_n: usize,
+    trace_len: usize,
     x: ExtensionTarget<D>,
     z_x: ExtensionTarget<D>,
 ) -> (ExtensionTarget<D>, ExtensionTarget<D>) {
     let n = builder.constant_extension(F::Extension::from_canonical_usize(1 << log_n));
-    let g = builder.constant_extension(F::Extension::primitive_root_of_unity(log_n));
+    let last_inv = builder.constant_extension(
+        F::Extension::primitive_root_of_unity(log_n)
+            .exp_u64(trace_len as u64 - 1)
+            .inverse(),
+    );
     let one = builder.one_extension();
     let l_1_deno = builder.mul_sub_extension(n, x, n);
-    let l_last_deno = builder.mul_sub_extension(g, x, one);
+    let l_last_deno = builder.mul_sub_extension(last_inv, x, one);
     let l_last_deno = builder.mul_extension(n, l_last_deno);
 
     (
@@ -155,6 +169,26 @@ fn eval_l_1_and_l_last_recursively<F: RichField + Extendable<D>, const D: usize>
     )
 }
 
+/// Evaluates the polynomial filtering transition constraints, which vanishes on the last row of a
+/// trace of length `t` and, if `t < n`, on the last row of the trace padded to length `n`.
+fn eval_z_last_recursively<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    log_n: usize,
+    trace_len: usize,
+    x: ExtensionTarget<D>,
+) -> ExtensionTarget<D> {
+    let g = F::Extension::primitive_root_of_unity(log_n);
+    let last = builder.constant_extension(g.exp_u64(trace_len as u64 - 1));
+    let z_last = builder.sub_extension(x, last);
+    if trace_len < 1 << log_n {
+        let last_padding = builder.constant_extension(g.inverse());
+        let z_last_padding = builder.sub_extension(x, last_padding);
+        builder.mul_extension(z_last, z_last_padding)
+    } else {
+        z_last
+    }
+}
+
 pub fn add_virtual_stark_proof_with_pis<
     F: RichField + Extendable<D>,
     S: Stark<F, D>,
@@ -163,9 +197,9 @@ pub fn add_virtual_stark_proof_with_pis<
     builder: &mut CircuitBuilder<F, D>,
     stark: S,
     config: &StarkConfig,
-    degree_bits: usize,
+    trace_len: usize,
 ) -> StarkProofWithPublicInputsTarget<D> {
-    let proof = add_virtual_stark_proof::<F, S, D>(builder, stark, config, degree_bits);
+    let proof = add_virtual_stark_proof::<F, S, D>(builder, stark, config, trace_len);
     let public_inputs = builder.add_virtual_targets(S::PUBLIC_INPUTS);
     StarkProofWithPublicInputsTarget {
         proof,
@@ -177,22 +211,30 @@ pub fn add_virtual_stark_proof<F: RichField + Extendable<D>, S: Stark<F, D>, con
     builder: &mut CircuitBuilder<F, D>,
     stark: S,
     config: &StarkConfig,
-    degree_bits: usize,
+    trace_len: usize,
 ) -> StarkProofTarget<D> {
-    let fri_params = config.fri_params(degree_bits);
+    let fri_params = config.fri_params(log2_ceil(trace_len));
     let cap_height = fri_params.config.cap_height;
 
     let num_leaves_per_oracle = &[
         S::COLUMNS,
         // TODO: permutation polys
-        stark.quotient_degree_factor() * config.num_challenges,
+        stark.num_quotient_polys(trace_len) * config.num_challenges,
     ];
 
     StarkProofTarget {
+        trace_len,
         trace_cap: builder.add_virtual_cap(cap_height),
         quotient_polys_cap: builder.add_virtual_cap(cap_height),
-        openings: add_stark_opening_set::<F, S, D>(builder, stark, config),
-        opening_proof: builder.add_virtual_fri_proof(num_leaves_per_oracle, &fri_params),
+        openings: add_stark_opening_set::<F, S, D>(builder, stark, config, trace_len),
+        opening_proof: builder.add_virtual_fri_proof(
+            num_leaves_per_oracle,
+            &[FriOracleInfo {
+                blinding: false,
+                extra_rate_bits: 0,
+            }; 2],
+            &fri_params,
+        ),
     }
 }
 
@@ -200,6 +242,7 @@ fn add_stark_opening_set<F: RichField + Extendable<D>, S: Stark<F, D>, const D:
     builder: &mut CircuitBuilder<F, D>,
     stark: S,
     config: &StarkConfig,
+    trace_len: usize,
 ) -> StarkOpeningSetTarget<D> {
     let num_challenges = config.num_challenges;
     StarkOpeningSetTarget {
@@ -208,7 +251,7 @@ fn add_stark_opening_set<F: RichField + Extendable<D>, S: Stark<F, D>, const D:
         permutation_zs: vec![/*TODO*/],
         permutation_zs_right: vec![/*TODO*/],
         quotient_polys: builder
-            .add_virtual_extension_targets(stark.quotient_degree_factor() * num_challenges),
+            .add_virtual_extension_targets(stark.num_quotient_polys(trace_len) * num_challenges),
     }
 }
 
@@ -219,6 +262,7 @@ pub fn set_stark_proof_with_pis_target<F, C: GenericConfig<D, F = F>, W, const D
 ) where
     F: RichField + Extendable<D>,
     C::Hasher: AlgebraicHasher<F>,
+    C::CommitPhaseHasher: AlgebraicHasher<F>,
     W: Witness<F>,
 {
     let StarkProofWithPublicInputs {
@@ -245,6 +289,7 @@ pub fn set_stark_proof_target<F, C: GenericConfig<D, F = F>, W, const D: usize>(
 ) where
     F: RichField + Extendable<D>,
     C::Hasher: AlgebraicHasher<F>,
+    C::CommitPhaseHasher: AlgebraicHasher<F>,
     W: Witness<F>,
 {
     witness.set_cap_target(&proof_target.trace_cap, &proof.trace_cap);
@@ -74,18 +74,37 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         1.max(self.constraint_degree() - 1)
     }
 
+    /// The number of quotient polynomials per challenge for a trace of `trace_len` rows. A trace
+    /// whose length is not a power of two is padded, and its transition constraints are filtered
+    /// out on the last padding row as well as the last trace row, which raises the degree of the
+    /// quotient past `quotient_degree_factor` chunks for constraints of degree 2.
+    fn num_quotient_polys(&self, trace_len: usize) -> usize {
+        if trace_len.is_power_of_two() {
+            self.quotient_degree_factor()
+        } else {
+            self.quotient_degree_factor()
+                .max(self.constraint_degree().min(2))
+        }
+    }
+
     /// Computes the FRI instance used to prove this Stark.
     // TODO: Permutation polynomials.
     fn fri_instance(
         &self,
         zeta: F::Extension,
         g: F,
+        trace_len: usize,
         num_challenges: usize,
     ) -> FriInstanceInfo<F, D> {
-        let no_blinding_oracle = FriOracleInfo { blinding: false };
+        let no_blinding_oracle = FriOracleInfo {
+            blinding: false,
+            extra_rate_bits: 0,
+        };
         let trace_info = FriPolynomialInfo::from_range(0, 0..Self::COLUMNS);
-        let quotient_info =
-            FriPolynomialInfo::from_range(1, 0..self.quotient_degree_factor() * num_challenges);
+        let quotient_info = FriPolynomialInfo::from_range(
+            1,
+            0..self.num_quotient_polys(trace_len) * num_challenges,
+        );
         let zeta_batch = FriBatchInfo {
             point: zeta,
             polynomials: [trace_info.clone(), quotient_info].concat(),
@@ -107,12 +126,18 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         builder: &mut CircuitBuilder<F, D>,
         zeta: ExtensionTarget<D>,
         g: F,
+        trace_len: usize,
         num_challenges: usize,
     ) -> FriInstanceInfoTarget<D> {
-        let no_blinding_oracle = FriOracleInfo { blinding: false };
+        let no_blinding_oracle = FriOracleInfo {
+            blinding: false,
+            extra_rate_bits: 0,
+        };
         let trace_info = FriPolynomialInfo::from_range(0, 0..Self::COLUMNS);
-        let quotient_info =
-            FriPolynomialInfo::from_range(1, 0..self.quotient_degree_factor() * num_challenges);
+        let quotient_info = FriPolynomialInfo::from_range(
+            1,
+            0..self.num_quotient_polys(trace_len) * num_challenges,
+        );
         let zeta_batch = FriBatchInfoTarget {
             point: zeta,
             polynomials: [trace_info.clone(), quotient_info].concat(),
@@ -26,6 +26,7 @@ where
     [(); S::COLUMNS]:,
     [(); S::PUBLIC_INPUTS]:,
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     ensure!(proof_with_pis.public_inputs.len() == S::PUBLIC_INPUTS);
     let degree_bits = proof_with_pis.proof.recover_degree_bits(config);
@@ -49,11 +50,22 @@ where
     [(); S::COLUMNS]:,
     [(); S::PUBLIC_INPUTS]:,
     [(); C::Hasher::HASH_SIZE]:,
+    [(); C::CommitPhaseHasher::HASH_SIZE]:,
 {
     let StarkProofWithPublicInputs {
         proof,
         public_inputs,
     } = proof_with_pis;
+    let trace_len = proof.trace_len;
+    ensure!(
+        trace_len <= 1 << degree_bits && 2 * trace_len > 1 << degree_bits,
+        "Trace length doesn't match the proof's degree."
+    );
+    ensure!(
+        proof.openings.quotient_polys.len()
+            == stark.num_quotient_polys(trace_len) * config.num_challenges,
+        "Wrong number of quotient polynomials."
+    );
     let StarkOpeningSet {
         local_values,
         next_values,
@@ -72,9 +84,8 @@ where
             .unwrap(),
     };
 
-    let (l_1, l_last) = eval_l_1_and_l_last(degree_bits, challenges.stark_zeta);
-    let last = F::primitive_root_of_unity(degree_bits).inverse();
-    let z_last = challenges.stark_zeta - last.into();
+    let (l_1, l_last) = eval_l_1_and_l_last(degree_bits, trace_len, challenges.stark_zeta);
+    let z_last = eval_z_last(degree_bits, trace_len, challenges.stark_zeta);
     let mut consumer = ConstraintConsumer::<F::Extension>::new(
         challenges
             .stark_alphas
@@ -92,13 +103,13 @@ where
     let quotient_polys_zeta = &proof.openings.quotient_polys;
     let zeta_pow_deg = challenges.stark_zeta.exp_power_of_2(degree_bits);
     let z_h_zeta = zeta_pow_deg - F::Extension::ONE;
-    // `quotient_polys_zeta` holds `num_challenges * quotient_degree_factor` evaluations.
-    // Each chunk of `quotient_degree_factor` holds the evaluations of `t_0(zeta),...,t_{quotient_degree_factor-1}(zeta)`
+    // `quotient_polys_zeta` holds `num_challenges * num_quotient_polys` evaluations.
+    // Each chunk of `num_quotient_polys` holds the evaluations of `t_0(zeta),...,t_{num_quotient_polys-1}(zeta)`
     // where the "real" quotient polynomial is `t(X) = t_0(X) + t_1(X)*X^n + t_2(X)*X^{2n} + ...`.
     // So to reconstruct `t(zeta)` we can compute `reduce_with_powers(chunk, zeta^n)` for each
-    // `quotient_degree_factor`-sized chunk of the original evaluations.
+    // `num_quotient_polys`-sized chunk of the original evaluations.
     for (i, chunk) in quotient_polys_zeta
-        .chunks(stark.quotient_degree_factor())
+        .chunks(stark.num_quotient_polys(trace_len))
         .enumerate()
     {
         ensure!(vanishing_polys_zeta[i] == z_h_zeta * reduce_with_powers(chunk, zeta_pow_deg));
@@ -111,6 +122,7 @@ where
         &stark.fri_instance(
             challenges.stark_zeta,
             F::primitive_root_of_unity(degree_bits),
+            trace_len,
             config.num_challenges,
         ),
         &proof.openings.to_fri_openings(),
@@ -123,18 +135,32 @@ where
     Ok(())
 }
 
-/// Evaluate the Lagrange polynomials `L_1` and `L_n` at a point `x`.
+/// Evaluate the Lagrange polynomials `L_1` and `L_t`, where `t` is the trace length, at a point `x`.
 /// `L_1(x) = (x^n - 1)/(n * (x - 1))`
-/// `L_n(x) = (x^n - 1)/(n * (g * x - 1))`, with `g` the first element of the subgroup.
-fn eval_l_1_and_l_last<F: Field>(log_n: usize, x: F) -> (F, F) {
+/// `L_t(x) = (x^n - 1)/(n * (g^(1 - t) * x - 1))`, with `g` the first element of the subgroup.
+fn eval_l_1_and_l_last<F: Field>(log_n: usize, trace_len: usize, x: F) -> (F, F) {
     let n = F::from_canonical_usize(1 << log_n);
     let g = F::primitive_root_of_unity(log_n);
+    let last_inv = g.exp_u64(trace_len as u64 - 1).inverse();
     let z_x = x.exp_power_of_2(log_n) - F::ONE;
-    let invs = F::batch_multiplicative_inverse(&[n * (x - F::ONE), n * (g * x - F::ONE)]);
+    let invs = F::batch_multiplicative_inverse(&[n * (x - F::ONE), n * (last_inv * x - F::ONE)]);
 
     (z_x * invs[0], z_x * invs[1])
 }
 
+/// Evaluate the polynomial filtering transition constraints, which vanishes on the last row of a
+/// trace of length `t` and, if `t < n`, on the last row of the trace padded to length `n`:
+/// `Z_last(x) = (x - g^(t - 1))` if `t = n`, and `(x - g^(t - 1)) (x - g^(n - 1))` otherwise.
+fn eval_z_last<F: Field>(log_n: usize, trace_len: usize, x: F) -> F {
+    let g = F::primitive_root_of_unity(log_n);
+    let z_last = x - g.exp_u64(trace_len as u64 - 1);
+    if trace_len < 1 << log_n {
+        z_last * (x - g.inverse())
+    } else {
+        z_last
+    }
+}
+
 /// Recover the length of the trace from a STARK proof and a STARK config.
 
 #[cfg(test)]
@@ -143,7 +169,7 @@ mod tests {
     use plonky2::field::goldilocks_field::GoldilocksField;
     use plonky2::field::polynomial::PolynomialValues;
 
-    use crate::verifier::eval_l_1_and_l_last;
+    use crate::verifier::{eval_l_1_and_l_last, eval_z_last};
 
     #[test]
     fn test_eval_l_1_and_l_last() {
@@ -152,11 +178,32 @@ mod tests {
         let n = 1 << log_n;
 
         let x = F::rand(); // challenge point
-        let expected_l_first_x = PolynomialValues::selector(n, 0).ifft().eval(x);
-        let expected_l_last_x = PolynomialValues::selector(n, n - 1).ifft().eval(x);
+        for trace_len in [n, 19] {
+            let expected_l_first_x = PolynomialValues::selector(n, 0).ifft().eval(x);
+            let expected_l_last_x = PolynomialValues::selector(n, trace_len - 1).ifft().eval(x);
+
+            let (l_first_x, l_last_x) = eval_l_1_and_l_last(log_n, trace_len, x);
+            assert_eq!(l_first_x, expected_l_first_x);
+            assert_eq!(l_last_x, expected_l_last_x);
+        }
+    }
 
-        let (l_first_x, l_last_x) = eval_l_1_and_l_last(log_n, x);
-        assert_eq!(l_first_x, expected_l_first_x);
-        assert_eq!(l_last_x, expected_l_last_x);
+    #[test]
+    fn test_eval_z_last() {
+        type F = GoldilocksField;
+        let log_n = 5;
+        let n = 1 << log_n;
+        let g = F::primitive_root_of_unity(log_n);
+
+        // `Z_last` vanishes exactly on the last trace row and the last padding row.
+        for trace_len in [n, 19] {
+            for i in 0..n {
+                let is_last = i == trace_len - 1 || i == n - 1;
+                assert_eq!(
+                    eval_z_last(log_n, trace_len, g.exp_u64(i as u64)) == F::ZERO,
+                    is_last
+                );
+            }
+        }
     }
 }
@@ -11,6 +11,7 @@ use std::mem::size_of;
 use std::ptr::{swap, swap_nonoverlapping};
 
 mod transpose_util;
+pub mod wide_arith;
 
 use crate::transpose_util::transpose_in_place_square;
 
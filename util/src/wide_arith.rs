@@ -0,0 +1,68 @@
+//! Arithmetic on 64-bit limbs with 128-bit intermediate results, for implementing fields and
+//! big integers on top of `u64`s. These are part of the crate's stable API.
+
+/// Splits `x` into its low and high 64-bit halves.
+#[inline(always)]
+pub const fn split_u128(x: u128) -> (u64, u64) {
+    (x as u64, (x >> 64) as u64)
+}
+
+/// Joins the low and high 64-bit halves `lo` and `hi` of a `u128`.
+#[inline(always)]
+pub const fn join_u128(lo: u64, hi: u64) -> u128 {
+    (lo as u128) | ((hi as u128) << 64)
+}
+
+/// The full 128-bit product of `x` and `y`, as its low and high halves.
+#[inline(always)]
+pub const fn widening_mul(x: u64, y: u64) -> (u64, u64) {
+    split_u128((x as u128) * (y as u128))
+}
+
+/// Computes `acc + x * y + carry` as its low and high halves. This cannot overflow, since
+/// `(2^64 - 1) + (2^64 - 1)^2 + (2^64 - 1) = 2^128 - 1`.
+#[inline(always)]
+pub const fn mul_add_carry(acc: u64, x: u64, y: u64, carry: u64) -> (u64, u64) {
+    split_u128((acc as u128) + (x as u128) * (y as u128) + (carry as u128))
+}
+
+/// Computes `x + y + carry`, returning the sum modulo `2^64` and the outgoing carry.
+#[inline(always)]
+pub const fn add_with_carry(x: u64, y: u64, carry: bool) -> (u64, bool) {
+    let (sum, c0) = x.overflowing_add(y);
+    let (sum, c1) = sum.overflowing_add(carry as u64);
+    (sum, c0 | c1)
+}
+
+/// Computes `x - y - borrow`, returning the difference modulo `2^64` and the outgoing borrow.
+#[inline(always)]
+pub const fn sub_with_borrow(x: u64, y: u64, borrow: bool) -> (u64, bool) {
+    let (diff, b0) = x.overflowing_sub(y);
+    let (diff, b1) = diff.overflowing_sub(borrow as u64);
+    (diff, b0 | b1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wide_arith::{
+        add_with_carry, join_u128, mul_add_carry, split_u128, sub_with_borrow, widening_mul,
+    };
+
+    #[test]
+    fn test_wide_arith() {
+        let x = 0xd7a1_5f3b_9c02_e841u64;
+        let y = 0x8e3c_0b71_f4d6_2a95u64;
+        let (lo, hi) = widening_mul(x, y);
+        assert_eq!(join_u128(lo, hi), x as u128 * y as u128);
+        assert_eq!(split_u128(join_u128(lo, hi)), (lo, hi));
+
+        assert_eq!(
+            mul_add_carry(u64::MAX, u64::MAX, u64::MAX, u64::MAX),
+            (u64::MAX, u64::MAX)
+        );
+        assert_eq!(add_with_carry(u64::MAX, 0, true), (0, true));
+        assert_eq!(add_with_carry(x, y, false), (x.wrapping_add(y), true));
+        assert_eq!(sub_with_borrow(0, 0, true), (u64::MAX, true));
+        assert_eq!(sub_with_borrow(x, y, true), (x - y - 1, false));
+    }
+}
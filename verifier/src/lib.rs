@@ -0,0 +1,133 @@
+//! Verification of serialized Plonky2 proofs, for consumers such as light clients or embedded
+//! verifiers which never build circuits or generate proofs.
+//!
+//! `plonky2` is compiled without its default features here, so verifiers don't pull in rayon or
+//! jemalloc, and don't time their work. Its circuit-building code is still compiled, since
+//! `CommonCircuitData` holds the circuit's gates as `Gate` trait objects, and the `Gate` trait also
+//! covers recursive constraint evaluation and witness generation.
+
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+
+use anyhow::{ensure, Result};
+use plonky2::field::extension_field::Extendable;
+pub use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::hash::hash_types::RichField;
+pub use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierCircuitData};
+pub use plonky2::plonk::config::{
+    GenericConfig, Hasher, KeccakGoldilocksConfig, PoseidonGoldilocksConfig,
+};
+use plonky2::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+
+/// A verifier for the proofs of a single circuit. Its verifier data is checked against a pinned
+/// digest once, when the verifier is created, so that a verifier handed the data of another circuit
+/// can't be created at all.
+#[derive(Debug)]
+pub struct Verifier<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    data: VerifierCircuitData<F, C, D>,
+    digest: [u8; 32],
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Verifier<F, C, D> {
+    /// Creates a verifier from `data`, which must have the digest `vk_digest`, as given by
+    /// `VerifierCircuitData::digest`.
+    pub fn new(data: VerifierCircuitData<F, C, D>, vk_digest: [u8; 32]) -> Result<Self> {
+        let digest = data.digest();
+        ensure!(
+            digest == vk_digest,
+            "Verifier data doesn't match the pinned digest."
+        );
+        Ok(Self { data, digest })
+    }
+
+    pub fn digest(&self) -> [u8; 32] {
+        self.digest
+    }
+
+    pub fn common_data(&self) -> &CommonCircuitData<F, C, D> {
+        self.data.common_data()
+    }
+
+    /// Deserializes a proof written by `ProofWithPublicInputs::to_bytes` and verifies it, after
+    /// checking that its public inputs are `public_inputs`.
+    pub fn verify(&self, proof_bytes: &[u8], public_inputs: &[F]) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        let proof_with_pis =
+            ProofWithPublicInputs::from_bytes(proof_bytes.to_vec(), self.common_data())?;
+        ensure!(
+            proof_with_pis.public_inputs == public_inputs,
+            "Proof has the wrong public inputs."
+        );
+        self.data.verify(proof_with_pis)
+    }
+
+    /// Like `verify`, but for a proof written by `CompressedProofWithPublicInputs::to_bytes`.
+    pub fn verify_compressed(&self, proof_bytes: &[u8], public_inputs: &[F]) -> Result<()>
+    where
+        [(); C::Hasher::HASH_SIZE]:,
+        [(); C::CommitPhaseHasher::HASH_SIZE]:,
+    {
+        let proof_with_pis =
+            CompressedProofWithPublicInputs::from_bytes(proof_bytes.to_vec(), self.common_data())?;
+        ensure!(
+            proof_with_pis.public_inputs == public_inputs,
+            "Proof has the wrong public inputs."
+        );
+        self.data.verify_compressed(proof_with_pis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::field_types::Field;
+    use plonky2::gates::noop::NoopGate;
+    use plonky2::iop::witness::{PartialWitness, Witness};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    use crate::Verifier;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn circuit(num_public_inputs: usize) -> (CircuitData<F, C, D>, PartialWitness<F>) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = builder.add_virtual_targets(num_public_inputs);
+        builder.register_public_inputs(&targets);
+        let mut pw = PartialWitness::new();
+        for &t in &targets {
+            pw.set_target(t, F::rand());
+        }
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        (builder.build::<C>(), pw)
+    }
+
+    #[test]
+    fn test_verifier() -> Result<()> {
+        let (data, pw) = circuit(1);
+        let proof = data.prove(pw)?;
+        let data = data.verifier_data();
+        let compressed_proof = proof.clone().compress(data.common_data())?;
+        let digest = data.digest();
+        let verifier = Verifier::new(data, digest)?;
+
+        verifier.verify(&proof.to_bytes()?, &proof.public_inputs)?;
+        verifier.verify_compressed(&compressed_proof.to_bytes()?, &proof.public_inputs)?;
+        // Wrong public inputs are rejected.
+        assert!(verifier.verify(&proof.to_bytes()?, &[F::ONE]).is_err());
+
+        // Verifier data for another circuit doesn't have the pinned digest.
+        let (other_data, _) = circuit(2);
+        assert!(Verifier::new(other_data.verifier_data(), digest).is_err());
+
+        Ok(())
+    }
+}